@@ -1,4 +1,4 @@
-use alsacoin::cli::CliClient;
+use alsacoin::prelude::CliClient;
 
 pub fn main() {
     CliClient::run().unwrap()
@@ -1,4 +1,4 @@
-use alsacoin::cli::CliDaemon;
+use alsacoin::prelude::CliDaemon;
 
 pub fn main() {
     CliDaemon::run().unwrap()
@@ -0,0 +1,315 @@
+//! # Bench
+//!
+//! `bench` contains the micro-benchmarks of the store backends and of the
+//! cryptographic primitives, used to pick the store backend and the Balloon
+//! parameters that suit a machine.
+
+use crate::error::Error;
+use crate::result::Result;
+use crypto::ecc::ed25519::SecretKey;
+use crypto::hash::{BalloonHasher, BalloonParams, Blake512Hasher, Digest};
+use crypto::random::Random;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+use store::memory::MemoryStoreFactory;
+use store::temporary::TemporaryStoreFactory;
+use store::traits::Store;
+
+/// `BenchResult` is the result of a micro-benchmark.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub ops: u64,
+    pub secs: f64,
+    pub ops_per_sec: f64,
+}
+
+impl BenchResult {
+    /// `new` creates a new `BenchResult` from a number of operations and the
+    /// time they took.
+    pub fn new(name: &str, ops: u64, elapsed: Duration) -> BenchResult {
+        let secs = elapsed.as_secs_f64();
+        let ops_per_sec = if secs > 0.0 { ops as f64 / secs } else { 0.0 };
+
+        BenchResult {
+            name: name.into(),
+            ops,
+            secs,
+            ops_per_sec,
+        }
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<32} {:>10} ops {:>10.3} s {:>14.1} ops/s",
+            self.name, self.ops, self.secs, self.ops_per_sec
+        )
+    }
+}
+
+/// `BenchParams` are the parameters of a `BenchReport`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BenchParams {
+    pub count: u32,
+    pub value_size: u32,
+    pub backends: Vec<String>,
+    pub balloon: BalloonParams,
+}
+
+impl BenchParams {
+    /// `VALID_BACKENDS` sets the store backends that can be benchmarked.
+    pub const VALID_BACKENDS: &'static [&'static str] =
+        &["btree", "sharded", "unqlite", "temporary"];
+
+    /// `DEFAULT_COUNT` is the default number of operations of a micro-benchmark.
+    pub const DEFAULT_COUNT: u32 = 10_000;
+
+    /// `DEFAULT_VALUE_SIZE` is the default size of the values inserted in the stores.
+    pub const DEFAULT_VALUE_SIZE: u32 = 256;
+
+    /// `DEFAULT_SHARDS` is the number of shards of the benchmarked sharded store.
+    pub const DEFAULT_SHARDS: u32 = 16;
+
+    /// `QUERY_COUNT` is the number of values returned by a benchmarked store query.
+    pub const QUERY_COUNT: u32 = 16;
+
+    /// `new` creates a new `BenchParams` benchmarking all the store backends.
+    pub fn new(count: u32, value_size: u32, balloon: BalloonParams) -> BenchParams {
+        BenchParams {
+            count,
+            value_size,
+            backends: Self::VALID_BACKENDS
+                .iter()
+                .map(|backend| backend.to_string())
+                .collect(),
+            balloon,
+        }
+    }
+
+    /// `validate` validates the `BenchParams`.
+    pub fn validate(&self) -> Result<()> {
+        if self.count == 0 {
+            let msg = "invalid count: 0".into();
+            let err = Error::Parse { msg };
+            return Err(err);
+        }
+
+        for backend in self.backends.iter() {
+            if !Self::VALID_BACKENDS.contains(&backend.as_str()) {
+                let msg = format!("invalid backend: {}", backend);
+                let err = Error::Parse { msg };
+                return Err(err);
+            }
+        }
+
+        self.balloon.validate().map_err(|e| e.into())
+    }
+}
+
+impl Default for BenchParams {
+    fn default() -> BenchParams {
+        BenchParams::new(
+            Self::DEFAULT_COUNT,
+            Self::DEFAULT_VALUE_SIZE,
+            BalloonParams::default(),
+        )
+    }
+}
+
+/// `BenchReport` is the report of a run of the micro-benchmarks.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub results: Vec<BenchResult>,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for result in self.results.iter() {
+            writeln!(f, "{}", result)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `bench` runs an operation a number of times, returning its `BenchResult`.
+fn bench<F: FnMut(u64) -> Result<()>>(name: &str, ops: u64, mut f: F) -> Result<BenchResult> {
+    let start = Instant::now();
+
+    for i in 0..ops {
+        f(i)?;
+    }
+
+    Ok(BenchResult::new(name, ops, start.elapsed()))
+}
+
+/// `bench_store` benchmarks the inserts, the gets and the range queries of a `Store`.
+pub fn bench_store<S: Store>(
+    backend: &str,
+    store: &mut S,
+    count: u32,
+    value_size: u32,
+) -> Result<Vec<BenchResult>> {
+    let ops = u64::from(count);
+    let value = Random::bytes(value_size as usize)?;
+    let mut results = Vec::new();
+
+    let name = format!("store {} insert", backend);
+    results.push(bench(&name, ops, |i| {
+        store
+            .insert(&i.to_be_bytes(), &value, None)
+            .map_err(|e| e.into())
+    })?);
+
+    let name = format!("store {} get", backend);
+    results.push(bench(&name, ops, |i| {
+        store
+            .get(&i.to_be_bytes())
+            .map(|_| ())
+            .map_err(|e| e.into())
+    })?);
+
+    let name = format!("store {} query", backend);
+    results.push(bench(&name, ops, |i| {
+        store
+            .query(
+                Some(&i.to_be_bytes()),
+                None,
+                Some(BenchParams::QUERY_COUNT),
+                None,
+            )
+            .map(|_| ())
+            .map_err(|e| e.into())
+    })?);
+
+    Ok(results)
+}
+
+/// `bench_backend` benchmarks a new store of a backend.
+pub fn bench_backend(backend: &str, count: u32, value_size: u32) -> Result<Vec<BenchResult>> {
+    let max_value_size = value_size.max(1);
+    let max_size = u32::max_value();
+
+    match backend {
+        "btree" => {
+            let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size)?;
+            bench_store(backend, &mut store, count, value_size)
+        }
+        "sharded" => {
+            let mut store = MemoryStoreFactory::new_sharded(
+                BenchParams::DEFAULT_SHARDS,
+                max_value_size,
+                max_size,
+            )?;
+            bench_store(backend, &mut store, count, value_size)
+        }
+        "unqlite" => {
+            let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size)?;
+            bench_store(backend, &mut store, count, value_size)
+        }
+        "temporary" => {
+            let mut store = TemporaryStoreFactory::new_unqlite(max_value_size, max_size)?;
+            bench_store(backend, &mut store, count, value_size)
+        }
+        _ => {
+            let msg = format!("invalid backend: {}", backend);
+            let err = Error::Parse { msg };
+            Err(err)
+        }
+    }
+}
+
+/// `bench_hashing` benchmarks the Blake512 hashing and the Balloon hashing with
+/// a set of `BalloonParams`.
+pub fn bench_hashing(count: u32, params: BalloonParams) -> Result<Vec<BenchResult>> {
+    let ops = u64::from(count);
+    let msg = Random::bytes(64)?;
+    let mut results = Vec::new();
+
+    results.push(bench("hash blake512", ops, |_| {
+        Blake512Hasher::hash(&msg);
+        Ok(())
+    })?);
+
+    let hasher = BalloonHasher::new(Digest::random()?, params)?;
+    let name = format!(
+        "hash balloon s={} t={} d={}",
+        params.s_cost, params.t_cost, params.delta
+    );
+
+    results.push(bench(&name, ops, |_| {
+        hasher.hash(&msg).map(|_| ()).map_err(|e| e.into())
+    })?);
+
+    Ok(results)
+}
+
+/// `bench_signing` benchmarks the Ed25519 signing and verification.
+pub fn bench_signing(count: u32) -> Result<Vec<BenchResult>> {
+    let ops = u64::from(count);
+    let secret_key = SecretKey::random()?;
+    let public_key = secret_key.to_public();
+    let msg = Random::bytes(64)?;
+    let mut results = Vec::new();
+
+    results.push(bench("ed25519 sign", ops, |_| {
+        secret_key.sign(&msg);
+        Ok(())
+    })?);
+
+    let signature = secret_key.sign(&msg);
+
+    results.push(bench("ed25519 verify", ops, |_| {
+        public_key.verify(&signature, &msg).map_err(|e| e.into())
+    })?);
+
+    Ok(results)
+}
+
+/// `run` runs the micro-benchmarks of a `BenchParams`, returning their `BenchReport`.
+pub fn run(params: &BenchParams) -> Result<BenchReport> {
+    params.validate()?;
+
+    let mut report = BenchReport::default();
+
+    for backend in params.backends.iter() {
+        let results = bench_backend(backend, params.count, params.value_size)?;
+        report.results.extend(results);
+    }
+
+    report
+        .results
+        .extend(bench_hashing(params.count, params.balloon)?);
+    report.results.extend(bench_signing(params.count)?);
+
+    Ok(report)
+}
+
+#[test]
+fn test_bench() {
+    let mut params = BenchParams::new(8, 32, BalloonParams::default());
+    params.backends = vec!["btree".into(), "sharded".into()];
+
+    let res = run(&params);
+    assert!(res.is_ok());
+
+    let report = res.unwrap();
+    assert_eq!(report.results.len(), 2 * 3 + 2 + 2);
+
+    for result in report.results.iter() {
+        assert_eq!(result.ops, 8);
+    }
+
+    params.backends = vec!["rocksdb".into()];
+    let res = run(&params);
+    assert!(res.is_err());
+
+    params = BenchParams::default();
+    params.count = 0;
+    let res = run(&params);
+    assert!(res.is_err());
+}
@@ -2,9 +2,47 @@
 //!
 //! `client` contains the CLI client type and functions.
 
+use crate::bench::{self, BenchParams, BenchReport};
 use crate::common;
+use crate::console::CliConsole;
+use crate::error::Error;
 use crate::result::Result;
+use crate::status;
+use base16;
 use clap::{App, Arg, ArgMatches, SubCommand};
+use crypto::ecc::ed25519::{PublicKey, SecretKey};
+use crypto::hash::{BalloonParams, Digest};
+use models::account::Account;
+use models::address::{self, StagedAddress};
+use models::checkpoint::Checkpoint;
+use models::coin_selection::CoinSelection;
+use models::consensus_message::ConsensusMessage;
+use models::contact::Contact;
+use models::input::SignaturesStatus;
+use models::result::Result as ModelsResult;
+use models::signers::Signers;
+use models::signing_request::SigningRequest;
+use models::stage::Stage;
+use models::stake::Stake;
+use models::testvectors::TestVectors;
+use models::timestamp::Timestamp;
+use models::traits::Storable;
+use models::transaction::Transaction;
+use models::transaction_builder::TransactionBuilder;
+use models::tx_telemetry::TxTelemetry;
+use models::wallet::Wallet;
+use models::watch::{Watch, WatchAction};
+use protocol::client::BroadcastStatus;
+use protocol::events::{EventFilter, EventKind};
+use protocol::ledger::{self, AddressHistory};
+use protocol::simulator::{Simulation, SimulationParams, SimulationReport};
+use rpc::proto::{event, Event, NodeBan, WalletStatus};
+use rpc::RpcClient;
+use serde_json;
+use std::path::Path;
+use store::snapshot::Snapshot;
+use store::stats::StoreStats;
+use store::traits::{PersistentStore, Store};
 
 /// `add_lookup` adds a lookup command to the `App`.
 fn add_lookup(app: App<'static, 'static>) -> App<'static, 'static> {
@@ -330,11 +368,173 @@ fn add_wallet_create(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `wallet_arg` returns the argument of the address of the wallet of a wallet subcommand.
+fn wallet_arg() -> Arg<'static, 'static> {
+    Arg::with_name("wallet")
+        .help("Address of the wallet account")
+        .long("wallet")
+        .takes_value(true)
+        .value_name("WALLET")
+        .required(true)
+}
+
+/// `rpc_address_arg` returns the argument of the address of the RPC server.
+fn rpc_address_arg() -> Arg<'static, 'static> {
+    Arg::with_name("address")
+        .help("Address of the RPC server")
+        .long("address")
+        .takes_value(true)
+        .value_name("ADDRESS")
+}
+
+/// `add_wallet_encrypt` adds an encrypt command to the wallet subcommand.
+fn add_wallet_encrypt(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("encrypt")
+        .about("Encrypt the secret key of a wallet with a passphrase")
+        .arg(wallet_arg());
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_unlock` adds an unlock command to the wallet subcommand.
+fn add_wallet_unlock(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("unlock")
+        .about("Unlock an encrypted wallet on the daemon")
+        .arg(wallet_arg())
+        .arg(rpc_address_arg())
+        .arg(
+            Arg::with_name("timeout")
+                .help("Seconds the wallet stays unlocked")
+                .long("timeout")
+                .takes_value(true)
+                .value_name("TIMEOUT"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_lock` adds a lock command to the wallet subcommand.
+fn add_wallet_lock(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("lock")
+        .about("Lock an unlocked wallet on the daemon")
+        .arg(wallet_arg())
+        .arg(rpc_address_arg());
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_sign_offline` adds a sign-offline command to the wallet subcommand.
+fn add_wallet_sign_offline(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("sign-offline")
+        .about("Sign the inputs of a signing request with a wallet of an offline machine")
+        .arg(wallet_arg())
+        .arg(
+            Arg::with_name("request")
+                .help("JSON file of the signing request")
+                .short("r")
+                .long("request")
+                .takes_value(true)
+                .value_name("REQUEST")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("File of the exported signatures")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .value_name("OUTPUT")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_contacts_add` adds an add command to the wallet contacts subcommand.
+fn add_wallet_contacts_add(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("add")
+        .about("Add a contact to the address book")
+        .arg(
+            Arg::with_name("label")
+                .help("Label of the contact")
+                .short("l")
+                .long("label")
+                .takes_value(true)
+                .value_name("LABEL")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("address")
+                .help("Address of the contact")
+                .short("a")
+                .long("address")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_contacts_list` adds a list command to the wallet contacts subcommand.
+fn add_wallet_contacts_list(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("list").about("List the contacts of the address book");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_contacts_remove` adds a remove command to the wallet contacts subcommand.
+fn add_wallet_contacts_remove(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("remove")
+        .about("Remove a contact from the address book")
+        .arg(
+            Arg::with_name("label")
+                .help("Label of the contact")
+                .short("l")
+                .long("label")
+                .takes_value(true)
+                .value_name("LABEL")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_wallet_contacts` adds a contacts command to the wallet subcommand.
+fn add_wallet_contacts(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("contacts").about("Address book operations");
+
+    cmd = add_wallet_contacts_add(cmd);
+    cmd = add_wallet_contacts_list(cmd);
+    cmd = add_wallet_contacts_remove(cmd);
+
+    app.subcommand(cmd)
+}
+
 /// `add_wallet` adds a wallet command to the `App`.
 fn add_wallet(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("wallet").about("Wallet operations");
 
     cmd = add_wallet_create(cmd);
+    cmd = add_wallet_encrypt(cmd);
+    cmd = add_wallet_unlock(cmd);
+    cmd = add_wallet_lock(cmd);
+    cmd = add_wallet_sign_offline(cmd);
+    cmd = add_wallet_contacts(cmd);
     cmd = add_storable(cmd);
 
     app.subcommand(cmd)
@@ -617,6 +817,108 @@ fn add_set_coinbase(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `add_export_unsigned` adds a command to export a transaction signing request to the `App`.
+fn add_export_unsigned(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("export-unsigned")
+        .about("Exports a signing request of a transaction to sign it on an offline machine")
+        .arg(
+            Arg::with_name("file")
+                .help("JSON file of the transaction")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("JSON file of the signing request")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .value_name("OUTPUT")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_export_signatures` adds a command to export the transaction signatures to the `App`.
+fn add_export_signatures(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("export-signatures")
+        .about("Exports the input signatures of a transaction to share them with the other signers")
+        .arg(
+            Arg::with_name("file")
+                .help("JSON file of the transaction")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("File of the exported signatures")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .value_name("OUTPUT")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_add_signatures` adds a command to add exported signatures to a transaction to the `App`.
+fn add_add_signatures(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("add-signatures")
+        .about("Adds the input signatures exported by another signer to a transaction")
+        .arg(
+            Arg::with_name("file")
+                .help("JSON file of the transaction")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("signatures")
+                .help("File of the exported signatures")
+                .long("signatures")
+                .takes_value(true)
+                .value_name("SIGNATURES")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_signatures_status` adds a command to check the transaction signatures thresholds to the `App`.
+fn add_signatures_status(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("signatures-status")
+        .about("Returns the signatures weight and threshold of the inputs of a transaction")
+        .arg(
+            Arg::with_name("file")
+                .help("JSON file of the transaction")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
 /// `add_transaction` adds a transaction command to the `App`.
 fn add_transaction(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("transaction").about("Transaction operations");
@@ -626,6 +928,10 @@ fn add_transaction(app: App<'static, 'static>) -> App<'static, 'static> {
     cmd = add_add_input(cmd);
     cmd = add_add_output(cmd);
     cmd = add_set_coinbase(cmd);
+    cmd = add_export_unsigned(cmd);
+    cmd = add_export_signatures(cmd);
+    cmd = add_add_signatures(cmd);
+    cmd = add_signatures_status(cmd);
     cmd = add_fetch(cmd);
     cmd = add_push(cmd);
     cmd = add_storable(cmd);
@@ -752,6 +1058,50 @@ fn add_size(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `add_compact` adds a compact command to the `App`.
+fn add_compact(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd =
+        SubCommand::with_name("compact").about("Compacts the store, reclaiming unused space");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_backup` adds a backup command to the `App`.
+fn add_backup(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("backup")
+        .about("Backs up a point-in-time snapshot of the stage items of the store to a file")
+        .arg(
+            Arg::with_name("file")
+                .help("File where to back up the store")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_restore` adds a restore command to the `App`.
+fn add_restore(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("restore")
+        .about("Restores the stage items of the store from a backup file, replacing them")
+        .arg(
+            Arg::with_name("file")
+                .help("Backup file to restore")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
 /// `add_store` adds a store command to the `App`.
 fn add_store(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("store").about("Store operations");
@@ -759,11 +1109,62 @@ fn add_store(app: App<'static, 'static>) -> App<'static, 'static> {
     cmd = add_import(cmd);
     cmd = add_export(cmd);
     cmd = add_size(cmd);
+    cmd = add_compact(cmd);
+    cmd = add_backup(cmd);
+    cmd = add_restore(cmd);
     cmd = add_clean(cmd);
 
     app.subcommand(cmd)
 }
 
+/// `add_status_store` adds a store command to the status subcommand.
+fn add_status_store(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("store")
+        .about("Returns the store disk usage and the key count per prefix");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_status_daemon` adds a daemon command to the status subcommand.
+fn add_status_daemon(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("daemon")
+        .about("Returns the status of the daemon, read from its control socket");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_status_tx` adds a tx command to the status subcommand.
+fn add_status_tx(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("tx")
+        .about("Returns the consensus timeline of a transaction")
+        .arg(
+            Arg::with_name("id")
+                .help("Transaction id")
+                .takes_value(true)
+                .value_name("ID")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_status` adds a status command to the `App`.
+fn add_status(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("status").about("Status reports");
+
+    cmd = add_status_store(cmd);
+    cmd = add_status_daemon(cmd);
+    cmd = add_status_tx(cmd);
+
+    app.subcommand(cmd)
+}
+
 /// `add_hash` adds a hash command to the `App`.
 fn add_hash(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("hash")
@@ -844,6 +1245,23 @@ fn add_sign(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `add_decode` adds a decode command to the `App`.
+fn add_decode(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("decode")
+        .about("Decodes and validates a transaction or a consensus message")
+        .arg(
+            Arg::with_name("input")
+                .help("Hex of the CBOR binary, or file with the JSON, the hex or the CBOR binary")
+                .takes_value(true)
+                .value_name("INPUT")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
 /// `add_balance` adds a balance command to the `App`.
 fn add_balance(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("balance")
@@ -861,21 +1279,54 @@ fn add_balance(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `add_history` adds a history command to the `App`.
+fn add_history(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("history")
+        .about("Returns the transactions affecting an account, in time order")
+        .arg(
+            Arg::with_name("address")
+                .help("Account address")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("count")
+                .help("Maximum number of items returned")
+                .long("count")
+                .takes_value(true)
+                .value_name("COUNT"),
+        )
+        .arg(
+            Arg::with_name("skip")
+                .help("Number of items skipped at start")
+                .long("skip")
+                .takes_value(true)
+                .value_name("SKIP"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
 /// `add_send` adds a send command to the `App`.
 fn add_send(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("send")
-        .about("Send an amount from an address to an other")
+        .about("Send an amount from one or more addresses to an other")
         .arg(
             Arg::with_name("from")
-                .help("Address of the sending account")
+                .help("Addresses of the sending accounts")
                 .long("from")
                 .takes_value(true)
                 .value_name("FROM")
+                .multiple(true)
+                .number_of_values(1)
                 .required(true),
         )
         .arg(
             Arg::with_name("to")
-                .help("Address of the receiving account")
+                .help("Address or contact label of the receiving account")
                 .long("to")
                 .takes_value(true)
                 .value_name("TO")
@@ -888,22 +1339,59 @@ fn add_send(app: App<'static, 'static>) -> App<'static, 'static> {
                 .takes_value(true)
                 .value_name("AMOUNT")
                 .required(true),
-        );
-
-    cmd = common::add_common(cmd);
-
-    app.subcommand(cmd)
-}
-
-/// `add_mine` adds a mine command to the `App`.
-fn add_mine(app: App<'static, 'static>) -> App<'static, 'static> {
-    let mut cmd = SubCommand::with_name("mine")
-        .about("Mines a transaction")
+        )
         .arg(
-            Arg::with_name("key")
-                .help("Transaction key")
-                .short("k")
-                .long("key")
+            Arg::with_name("strategy")
+                .help("Strategy selecting the sending accounts")
+                .long("strategy")
+                .takes_value(true)
+                .value_name("STRATEGY")
+                .possible_values(CoinSelection::VALID_STRATEGIES)
+                .default_value("largest-first"),
+        )
+        .arg(
+            Arg::with_name("change")
+                .help("Address receiving the change, defaulting to the first sending account")
+                .long("change")
+                .takes_value(true)
+                .value_name("CHANGE"),
+        )
+        .arg(
+            Arg::with_name("dust")
+                .help("Change below which the amount is left in the sending accounts")
+                .long("dust")
+                .takes_value(true)
+                .value_name("DUST"),
+        )
+        .arg(
+            Arg::with_name("address")
+                .help("Address of the RPC server")
+                .long("address")
+                .takes_value(true)
+                .value_name("ADDRESS"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .help("Seconds to wait for the transaction acceptance")
+                .long("timeout")
+                .takes_value(true)
+                .value_name("TIMEOUT"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_mine` adds a mine command to the `App`.
+fn add_mine(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("mine")
+        .about("Mines a transaction")
+        .arg(
+            Arg::with_name("key")
+                .help("Transaction key")
+                .short("k")
+                .long("key")
                 .takes_value(true)
                 .value_name("KEY")
                 .conflicts_with("value"),
@@ -934,6 +1422,451 @@ fn add_mine(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `add_console` adds a console command to the `App`.
+fn add_console(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("console")
+        .about("Opens an interactive console on a running daemon")
+        .arg(
+            Arg::with_name("address")
+                .help("Address of the daemon gRPC server")
+                .short("a")
+                .long("address")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .default_value(RpcClient::DEFAULT_ADDRESS),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_events` adds an events command to the `App`.
+fn add_events(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("events")
+        .about("Streams the events of a running daemon")
+        .arg(rpc_address_arg())
+        .arg(
+            Arg::with_name("kind")
+                .help("Kind of the events to stream")
+                .long("kind")
+                .takes_value(true)
+                .value_name("KIND")
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(&[
+                    "new_transaction",
+                    "accepted",
+                    "rejected",
+                    "conflict",
+                    "watch",
+                    "error",
+                    "stalled",
+                ]),
+        )
+        .arg(
+            Arg::with_name("account")
+                .help("Address of an account involved in the events to stream")
+                .long("account")
+                .takes_value(true)
+                .value_name("ACCOUNT")
+                .multiple(true)
+                .number_of_values(1),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_watch_add` adds an add command to the watch subcommand.
+fn add_watch_add(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("add")
+        .about("Add an address to the watch-list")
+        .arg(
+            Arg::with_name("address")
+                .help("Watched address")
+                .short("a")
+                .long("address")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("action")
+                .help("Action triggered by the accepted transactions: log, event or a webhook URL")
+                .long("action")
+                .takes_value(true)
+                .value_name("ACTION")
+                .default_value("log"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_watch_list` adds a list command to the watch subcommand.
+fn add_watch_list(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("list").about("List the addresses of the watch-list");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_watch_remove` adds a remove command to the watch subcommand.
+fn add_watch_remove(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("remove")
+        .about("Remove an address from the watch-list")
+        .arg(
+            Arg::with_name("address")
+                .help("Watched address")
+                .short("a")
+                .long("address")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_watch` adds a watch command to the `App`.
+fn add_watch(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("watch").about("Watch-list operations");
+
+    cmd = add_watch_add(cmd);
+    cmd = add_watch_list(cmd);
+    cmd = add_watch_remove(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_ban` adds a ban command to the `App`.
+fn add_ban(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("ban")
+        .about("Bans a node on a running daemon")
+        .arg(
+            Arg::with_name("node")
+                .help("Address of the node to ban")
+                .takes_value(true)
+                .value_name("NODE")
+                .required(true),
+        )
+        .arg(rpc_address_arg())
+        .arg(
+            Arg::with_name("duration")
+                .help("Duration of the ban in seconds")
+                .long("duration")
+                .takes_value(true)
+                .value_name("DURATION"),
+        )
+        .arg(
+            Arg::with_name("reason")
+                .help("Reason of the ban")
+                .long("reason")
+                .takes_value(true)
+                .value_name("REASON"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_unban` adds an unban command to the `App`.
+fn add_unban(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("unban")
+        .about("Removes the ban of a node on a running daemon")
+        .arg(
+            Arg::with_name("node")
+                .help("Address of the node to unban")
+                .takes_value(true)
+                .value_name("NODE")
+                .required(true),
+        )
+        .arg(rpc_address_arg());
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_lock_account` adds a lock-account command to the `App`.
+fn add_lock_account(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("lock-account")
+        .about("Locks the balance of an account of the wallet on-chain")
+        .arg(
+            Arg::with_name("account")
+                .help("Address of the account to lock")
+                .takes_value(true)
+                .value_name("ACCOUNT")
+                .required(true),
+        )
+        .arg(rpc_address_arg())
+        .arg(
+            Arg::with_name("duration")
+                .help("Duration of the lock in seconds")
+                .long("duration")
+                .takes_value(true)
+                .value_name("DURATION")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_stake_account` adds a stake-account command to the `App`.
+fn add_stake_account(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("stake-account")
+        .about("Stakes an amount of the balance of an account of the wallet on-chain")
+        .arg(
+            Arg::with_name("account")
+                .help("Address of the account to stake")
+                .takes_value(true)
+                .value_name("ACCOUNT")
+                .required(true),
+        )
+        .arg(rpc_address_arg())
+        .arg(
+            Arg::with_name("amount")
+                .help("Amount of the balance to lock")
+                .long("amount")
+                .takes_value(true)
+                .value_name("AMOUNT")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .help("Duration of the stake in seconds")
+                .long("duration")
+                .takes_value(true)
+                .value_name("DURATION")
+                .required(true),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_simulate` adds a simulate command to the `App`.
+fn add_simulate(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("simulate")
+        .about("Simulates the consensus on an in-process network")
+        .arg(
+            Arg::with_name("nodes")
+                .help("Number of nodes of the network")
+                .long("nodes")
+                .takes_value(true)
+                .value_name("NODES")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("txs")
+                .help("Number of transactions to submit")
+                .long("txs")
+                .takes_value(true)
+                .value_name("TXS")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("loss")
+                .help("Probability of losing a message, between 0 and 1")
+                .long("loss")
+                .takes_value(true)
+                .value_name("LOSS"),
+        )
+        .arg(
+            Arg::with_name("steps")
+                .help("Maximum number of steps of the simulation")
+                .long("steps")
+                .takes_value(true)
+                .value_name("STEPS"),
+        )
+        .arg(
+            Arg::with_name("k")
+                .help("Consensus parameter k")
+                .long("k")
+                .takes_value(true)
+                .value_name("K"),
+        )
+        .arg(
+            Arg::with_name("alpha")
+                .help("Consensus parameter alpha")
+                .long("alpha")
+                .takes_value(true)
+                .value_name("ALPHA"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .help("Seed of the simulation random draws")
+                .long("seed")
+                .takes_value(true)
+                .value_name("SEED"),
+        )
+        .arg(
+            Arg::with_name("dag")
+                .help("File where to dump the resulting DAG")
+                .long("dag")
+                .takes_value(true)
+                .value_name("FILE"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_bench` adds a bench command to the `App`.
+fn add_bench(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("bench")
+        .about("Benchmarks the store backends and the cryptographic primitives")
+        .arg(
+            Arg::with_name("count")
+                .help("Number of operations of each benchmark")
+                .long("count")
+                .takes_value(true)
+                .value_name("COUNT"),
+        )
+        .arg(
+            Arg::with_name("value-size")
+                .help("Size in bytes of the values inserted in the stores")
+                .long("value-size")
+                .takes_value(true)
+                .value_name("SIZE"),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .help("Store backend to benchmark")
+                .long("backend")
+                .takes_value(true)
+                .value_name("BACKEND")
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(BenchParams::VALID_BACKENDS),
+        )
+        .arg(
+            Arg::with_name("s-cost")
+                .help("Balloon hashing s_cost parameter")
+                .long("s-cost")
+                .takes_value(true)
+                .value_name("S_COST"),
+        )
+        .arg(
+            Arg::with_name("t-cost")
+                .help("Balloon hashing t_cost parameter")
+                .long("t-cost")
+                .takes_value(true)
+                .value_name("T_COST"),
+        )
+        .arg(
+            Arg::with_name("delta")
+                .help("Balloon hashing delta parameter")
+                .long("delta")
+                .takes_value(true)
+                .value_name("DELTA"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_export_checkpoint` adds an export-checkpoint command to the `App`.
+fn add_export_checkpoint(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("export-checkpoint")
+        .about("Exports a checkpoint of the node state, signed with the node key")
+        .arg(
+            Arg::with_name("file")
+                .help("File where to export the checkpoint")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("Format of the exported checkpoint")
+                .short("F")
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["json", "cbor"])
+                .default_value("json"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_verify_checkpoint` adds a verify-checkpoint command to the `App`.
+fn add_verify_checkpoint(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("verify-checkpoint")
+        .about("Verifies the signature of an exported checkpoint")
+        .arg(
+            Arg::with_name("file")
+                .help("File with the JSON or the CBOR checkpoint")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("public-key")
+                .help("Public key of the node expected to have signed the checkpoint")
+                .long("public-key")
+                .takes_value(true)
+                .value_name("KEY"),
+        )
+        .arg(
+            Arg::with_name("store")
+                .help("Checks the checkpoint digest against the local store")
+                .long("store")
+                .takes_value(false),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_selftest` adds a selftest command to the `App`.
+fn add_selftest(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("selftest")
+        .about("Checks the serialization of the models against their test vectors")
+        .arg(
+            Arg::with_name("golden")
+                .help("Golden file with the test vectors to verify")
+                .long("golden")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("generate")
+                .help("Writes the test vectors of the release to a golden file")
+                .long("generate")
+                .takes_value(true)
+                .value_name("FILE"),
+        );
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `Decoded` is a model decoded by the decode command.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Decoded {
+    Transaction(Transaction),
+    ConsensusMessage(ConsensusMessage),
+}
+
 /// `CliClient` is the type of the CLI client.
 pub struct CliClient {}
 
@@ -944,13 +1877,26 @@ impl CliClient {
     /// `CLI_ABOUT` is the CLI client app description.
     pub const CLI_ABOUT: &'static str = "Alsacoin client";
 
+    /// `DEFAULT_SEND_TIMEOUT` is the default time in seconds to wait for the
+    /// acceptance of a sent `Transaction`.
+    pub const DEFAULT_SEND_TIMEOUT: u32 = 60;
+
+    /// `DEFAULT_UNLOCK_TIMEOUT` is the default time in seconds a wallet
+    /// stays unlocked on the daemon.
+    pub const DEFAULT_UNLOCK_TIMEOUT: u32 = 300;
+
+    /// `DEFAULT_BAN_DURATION` is the default duration in seconds of a node ban.
+    pub const DEFAULT_BAN_DURATION: u32 = 86_400;
+
     /// `app` returns the `CliClient` clap `App`.
     pub fn app() -> App<'static, 'static> {
         let mut app = common::app(Self::CLI_NAME, Self::CLI_ABOUT);
 
         app = add_hash(app);
         app = add_sign(app);
+        app = add_decode(app);
         app = add_balance(app);
+        app = add_history(app);
         app = add_send(app);
         app = add_mine(app);
 
@@ -964,6 +1910,24 @@ impl CliClient {
         app = add_consensus_message(app);
 
         app = add_store(app);
+        app = add_status(app);
+
+        app = add_console(app);
+        app = add_events(app);
+        app = add_watch(app);
+
+        app = add_ban(app);
+        app = add_unban(app);
+        app = add_lock_account(app);
+        app = add_stake_account(app);
+
+        app = add_simulate(app);
+        app = add_bench(app);
+
+        app = add_export_checkpoint(app);
+        app = add_verify_checkpoint(app);
+
+        app = add_selftest(app);
 
         app
     }
@@ -988,12 +1952,1334 @@ impl CliClient {
         common::destroy()
     }
 
+    /// `stage` returns the `Stage` of a command.
+    fn stage(matches: &ArgMatches) -> Result<Stage> {
+        common::parse_stage(matches.value_of("stage"))
+    }
+
+    /// `parse_u32` parses an optional integer argument of a command.
+    fn parse_u32(matches: &ArgMatches, name: &str) -> Result<Option<u32>> {
+        match matches.value_of(name) {
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                let msg = format!("invalid {}: {}", name, value);
+                Error::Parse { msg }
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// `store_stats` returns the `StoreStats` of the store of a stage.
+    pub fn store_stats(stage: Stage) -> Result<StoreStats> {
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        store.stats().map_err(|e| e.into())
+    }
+
+    /// `tx_telemetry` returns the `TxTelemetry` of a transaction in the store of a stage.
+    pub fn tx_telemetry(stage: Stage, id: &str) -> Result<TxTelemetry> {
+        let id = Digest::from_str(id)?;
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        TxTelemetry::get(&store, stage, &id).map_err(|e| e.into())
+    }
+
+    /// `print_timeline` prints the consensus timeline of a `TxTelemetry`, with the
+    /// times relative to the first sight of the transaction.
+    fn print_timeline(telemetry: &TxTelemetry) {
+        let first_seen = telemetry.first_seen;
+
+        println!("transaction: {}", telemetry.id.to_string());
+        println!("first seen: {}", first_seen.to_string());
+        println!(
+            "queries: {} ({} shown)",
+            telemetry.queries,
+            telemetry.samples.len()
+        );
+
+        for sample in telemetry.samples.iter() {
+            println!(
+                "  +{}s chit sum: {} chit: {} confidence: {}",
+                sample.time.diff(first_seen),
+                sample.chit_sum,
+                sample.chit,
+                sample.confidence
+            );
+        }
+
+        match telemetry.accepted_at {
+            Some(accepted_at) => println!(
+                "accepted: {} (+{}s)",
+                accepted_at.to_string(),
+                accepted_at.diff(first_seen)
+            ),
+            None => println!("accepted: no"),
+        }
+    }
+
+    /// `balance` returns the balance of an account address in the store of a stage.
+    pub fn balance(stage: Stage, address: &str) -> Result<u64> {
+        let address = address::parse(stage, address)?;
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        ledger::get_balance(&store, stage, &address).map_err(|e| e.into())
+    }
+
+    /// `history` returns a page of the history of an account address in the
+    /// store of a stage.
+    pub fn history(
+        stage: Stage,
+        address: &str,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<AddressHistory> {
+        let address = address::parse(stage, address)?;
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        ledger::get_history(&store, stage, &address, count, skip).map_err(|e| e.into())
+    }
+
+    /// `wallet_signers` returns the `Wallet` in the store of a stage whose
+    /// single signer `Signers` have a given address, together with the `Signers`.
+    fn wallet_signers<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Digest,
+    ) -> Result<(Wallet, Signers)> {
+        for wallet in Wallet::query(store, stage, None, None, None, None)? {
+            let signers = wallet.to_signers()?;
+
+            if &signers.address == address {
+                return Ok((wallet, signers));
+            }
+        }
+
+        let msg = format!("wallet not found: {}", address::encode(stage, address));
+        let err = Error::InvalidCommand { msg };
+        Err(err)
+    }
+
+    /// `encrypt_wallet` encrypts the secret key of the `Wallet` of an address
+    /// in the store of a stage with a passphrase read from the terminal.
+    pub fn encrypt_wallet(stage: Stage, address: &str) -> Result<()> {
+        let address = address::parse(stage, address)?;
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        let (mut wallet, _) = CliClient::wallet_signers(&store, stage, &address)?;
+
+        let passphrase = common::read_new_passphrase()?;
+        wallet.encrypt(passphrase.as_bytes())?;
+
+        Wallet::update(&mut store, stage, &wallet).map_err(|e| e.into())
+    }
+
+    /// `unlock_wallet` unlocks the `Wallet` of an address on the daemon at an
+    /// RPC address for a timeout in seconds, with a passphrase read from the terminal.
+    pub fn unlock_wallet(
+        stage: Stage,
+        wallet: &str,
+        address: &str,
+        timeout: u64,
+    ) -> Result<WalletStatus> {
+        let wallet = address::parse(stage, wallet)?;
+        let passphrase = common::read_passphrase("passphrase: ")?;
+
+        let mut client = common::connect_rpc(stage, address)?;
+
+        client
+            .unlock_wallet(&wallet, &passphrase, timeout)
+            .map_err(|e| e.into())
+    }
+
+    /// `lock_wallet` locks the `Wallet` of an address on the daemon at an RPC address.
+    pub fn lock_wallet(stage: Stage, wallet: &str, address: &str) -> Result<WalletStatus> {
+        let wallet = address::parse(stage, wallet)?;
+
+        let mut client = common::connect_rpc(stage, address)?;
+
+        client.lock_wallet(&wallet).map_err(|e| e.into())
+    }
+
+    /// `add_contact` adds a `Contact` with a label and an address to the address
+    /// book in the store of a stage.
+    pub fn add_contact(stage: Stage, label: &str, address: &str) -> Result<Contact> {
+        let address = address::parse(stage, address)?;
+        let contact = Contact::new(stage, label, &address)?;
+
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        if Contact::lookup(&store, stage, &contact.id)? {
+            let msg = format!("contact already found: {}", label);
+            let err = Error::InvalidCommand { msg };
+            return Err(err);
+        }
+
+        Contact::create(&mut store, stage, &contact)?;
+
+        Ok(contact)
+    }
+
+    /// `list_contacts` returns the `Contact`s of the address book in the store
+    /// of a stage, sorted by label.
+    pub fn list_contacts(stage: Stage) -> Result<Vec<Contact>> {
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let mut contacts: Vec<Contact> = Contact::query(&store, stage, None, None, None, None)?
+            .into_iter()
+            .collect();
+        contacts.sort_by(|a, b| a.label.cmp(&b.label));
+
+        Ok(contacts)
+    }
+
+    /// `remove_contact` removes the `Contact` with a label from the address book
+    /// in the store of a stage.
+    pub fn remove_contact(stage: Stage, label: &str) -> Result<()> {
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        let id = Contact::label_id(label);
+
+        if !Contact::lookup(&store, stage, &id)? {
+            let msg = format!("contact not found: {}", label);
+            let err = Error::InvalidCommand { msg };
+            return Err(err);
+        }
+
+        Contact::remove(&mut store, stage, &id).map_err(|e| e.into())
+    }
+
+    /// `add_watch` adds an address with an action to the watch-list in the store
+    /// of a stage.
+    pub fn add_watch(stage: Stage, address: &str, action: &str) -> Result<Watch> {
+        let address = address::parse(stage, address)?;
+        let action = WatchAction::parse(action)?;
+        let watch = Watch::new(stage, &address, &action)?;
+
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        if Watch::lookup(&store, stage, &address)? {
+            Watch::update(&mut store, stage, &watch)?;
+        } else {
+            Watch::create(&mut store, stage, &watch)?;
+        }
+
+        Ok(watch)
+    }
+
+    /// `list_watches` returns the `Watch`es of the watch-list in the store of
+    /// a stage.
+    pub fn list_watches(stage: Stage) -> Result<Vec<Watch>> {
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let watches = Watch::query(&store, stage, None, None, None, None)?
+            .into_iter()
+            .collect();
+
+        Ok(watches)
+    }
+
+    /// `remove_watch` removes an address from the watch-list in the store of
+    /// a stage.
+    pub fn remove_watch(stage: Stage, address: &str) -> Result<()> {
+        let address = address::parse(stage, address)?;
+
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        if !Watch::lookup(&store, stage, &address)? {
+            let msg = format!("watch not found: {}", address::encode(stage, &address));
+            let err = Error::InvalidCommand { msg };
+            return Err(err);
+        }
+
+        Watch::remove(&mut store, stage, &address).map_err(|e| e.into())
+    }
+
+    /// `print_wallet_status` prints a `WalletStatus`.
+    fn print_wallet_status(status: &WalletStatus) -> Result<()> {
+        println!("encrypted: {}", status.encrypted);
+        println!("unlocked: {}", status.unlocked);
+
+        if status.encrypted && status.unlocked {
+            let expires = Timestamp::from_i64(status.expires)?;
+            println!("expires: {}", expires.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// `event_filter` returns the `EventFilter` of the kinds and accounts of a command.
+    fn event_filter(stage: Stage, matches: &ArgMatches) -> Result<EventFilter> {
+        let mut filter = EventFilter::default();
+
+        for kind in matches.values_of("kind").into_iter().flatten() {
+            filter.kinds.insert(EventKind::parse(kind)?);
+        }
+
+        for account in matches.values_of("account").into_iter().flatten() {
+            filter.addresses.insert(address::parse(stage, account)?);
+        }
+
+        Ok(filter)
+    }
+
+    /// `print_event` prints a daemon event of a stage.
+    fn print_event(stage: Stage, event: &Event) {
+        let to_hex = |buf: &[u8]| {
+            Digest::from_slice(buf)
+                .map(|digest| digest.to_string())
+                .unwrap_or_default()
+        };
+
+        let to_address = |buf: &[u8]| {
+            Digest::from_slice(buf)
+                .map(|digest| address::encode(stage, &digest))
+                .unwrap_or_default()
+        };
+
+        match event.kind {
+            Some(event::Kind::TransactionAdded(ref added)) => {
+                println!("new_transaction {}", to_hex(&added.tx_id));
+            }
+            Some(event::Kind::TransactionAccepted(ref accepted)) => {
+                println!("accepted {}", to_hex(&accepted.tx_id));
+            }
+            Some(event::Kind::TransactionRejected(ref rejected)) => {
+                println!("rejected {} {}", to_hex(&rejected.tx_id), rejected.reason);
+            }
+            Some(event::Kind::ConflictDetected(ref conflict)) => {
+                println!(
+                    "conflict {} {} transactions",
+                    to_address(&conflict.address),
+                    conflict.transactions.len()
+                );
+            }
+            Some(event::Kind::WatchTriggered(ref watch)) => {
+                println!(
+                    "watch {} {}",
+                    to_address(&watch.address),
+                    to_hex(&watch.tx_id)
+                );
+            }
+            Some(event::Kind::ConsensusError(ref error)) => {
+                println!("error {}", error.msg);
+            }
+            Some(event::Kind::TransactionStalled(ref stalled)) => {
+                println!(
+                    "stalled {} {} requeries",
+                    to_hex(&stalled.tx_id),
+                    stalled.requeries
+                );
+            }
+            None => {}
+        }
+    }
+
+    /// `events` streams the events of a stage selected by an `EventFilter` of
+    /// the daemon at an RPC address, printing them.
+    pub fn events(stage: Stage, address: &str, filter: &EventFilter) -> Result<()> {
+        let mut client = common::connect_rpc(stage, address)?;
+
+        let mut handler = |event: &Event| {
+            CliClient::print_event(stage, event);
+            true
+        };
+
+        client.subscribe(filter, &mut handler).map_err(|e| e.into())
+    }
+
+    /// `ban` bans a node for a duration in seconds on the daemon at an RPC address.
+    pub fn ban(node: &str, address: &str, duration: u64, reason: &str) -> Result<NodeBan> {
+        let mut client = RpcClient::connect(address)?;
+
+        client
+            .ban_peer(node.as_bytes(), duration, reason)
+            .map_err(|e| e.into())
+    }
+
+    /// `unban` removes the ban of a node on the daemon at an RPC address.
+    pub fn unban(node: &str, address: &str) -> Result<()> {
+        let mut client = RpcClient::connect(address)?;
+
+        client.unban_peer(node.as_bytes()).map_err(|e| e.into())
+    }
+
+    /// `lock_account` locks the balance of an account of a wallet for a duration
+    /// in seconds, submitting to the daemon at an RPC address a `Transaction`
+    /// sending the balance back to the account with the locktime, and returning
+    /// the locktime.
+    pub fn lock_account(stage: Stage, account: &str, address: &str, duration: u64) -> Result<i64> {
+        let account = address::parse(stage, account)?;
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let (wallet, signers) = CliClient::wallet_signers(&store, stage, &account)?;
+
+        let mut client = common::connect_rpc(stage, address)?;
+        let balance = client.get_balance(&account)?;
+
+        let locktime = Timestamp::now().after(duration);
+
+        let mut transaction = TransactionBuilder::new(stage)?
+            .spend_from(&Account::new(stage, &signers, balance, None)?, balance)?
+            .with_locktime(locktime)?
+            .with_change(&account)
+            .build()?;
+
+        let wallet = if wallet.is_encrypted() {
+            let passphrase = common::read_passphrase("passphrase: ")?;
+            wallet.unlock(passphrase.as_bytes())?
+        } else {
+            wallet
+        };
+
+        let secret_key = SecretKey::from_slice(&wallet.secret_key)?;
+        transaction.sign_input(&secret_key, &account)?;
+
+        let tx_id = client.submit_transaction(&transaction)?;
+        println!("{}", tx_id.to_string());
+
+        Ok(locktime.to_i64())
+    }
+
+    /// `stake_account` stakes an amount of the balance of an account of a wallet
+    /// for a duration in seconds, submitting to the daemon at an RPC address a
+    /// `Transaction` sending the balance back to the account with the `Stake`,
+    /// and returning the locktime of the stake.
+    pub fn stake_account(
+        stage: Stage,
+        account: &str,
+        address: &str,
+        amount: u64,
+        duration: u64,
+    ) -> Result<i64> {
+        let account = address::parse(stage, account)?;
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let (wallet, signers) = CliClient::wallet_signers(&store, stage, &account)?;
+
+        let mut client = common::connect_rpc(stage, address)?;
+        let balance = client.get_balance(&account)?;
+
+        let stake = Stake::new(stage, &account, amount, duration)?;
+
+        let mut transaction = TransactionBuilder::new(stage)?
+            .spend_from(&Account::new(stage, &signers, balance, None)?, balance)?
+            .with_stake(&stake)?
+            .with_change(&account)
+            .build()?;
+
+        let wallet = if wallet.is_encrypted() {
+            let passphrase = common::read_passphrase("passphrase: ")?;
+            wallet.unlock(passphrase.as_bytes())?
+        } else {
+            wallet
+        };
+
+        let secret_key = SecretKey::from_slice(&wallet.secret_key)?;
+        transaction.sign_input(&secret_key, &account)?;
+
+        let tx_id = client.submit_transaction(&transaction)?;
+        println!("{}", tx_id.to_string());
+
+        Ok(stake.locktime.to_i64())
+    }
+
+    /// `print_node_ban` prints a `NodeBan`.
+    fn print_node_ban(ban: &NodeBan) -> Result<()> {
+        let until = Timestamp::from_i64(ban.until)?;
+
+        println!("node: {}", String::from_utf8_lossy(&ban.address));
+        println!("until: {}", until.to_string());
+
+        if !ban.reason.is_empty() {
+            println!("reason: {}", ban.reason);
+        }
+
+        Ok(())
+    }
+
+    /// `send` sends an amount from one or more account addresses to an other
+    /// through the RPC server at an address, printing the broadcast progress
+    /// until the `Transaction` is accepted or the timeout in seconds expires.
+    /// The sending accounts are chosen with a `CoinSelection` strategy.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send(
+        stage: Stage,
+        from: &[&str],
+        to: &str,
+        amount: u64,
+        strategy: CoinSelection,
+        change: Option<&str>,
+        dust: u64,
+        address: &str,
+        timeout: u64,
+    ) -> Result<BroadcastStatus> {
+        if from.is_empty() {
+            let msg = "missing sending address".into();
+            let err = Error::InvalidCommand { msg };
+            return Err(err);
+        }
+
+        let mut senders = Vec::new();
+
+        for from in from {
+            senders.push(address::parse(stage, from)?);
+        }
+
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let to = Contact::resolve(&store, stage, to)?;
+
+        let change = if let Some(change) = change {
+            address::parse(stage, change)?
+        } else {
+            senders[0]
+        };
+
+        let mut client = common::connect_rpc(stage, address)?;
+
+        let mut wallets = Vec::new();
+        let mut accounts = Vec::new();
+
+        for from in senders.iter() {
+            let (wallet, signers) = CliClient::wallet_signers(&store, stage, from)?;
+            let balance = client.get_balance(from)?;
+
+            wallets.push((*from, wallet));
+            accounts.push(Account::new(stage, &signers, balance, None)?);
+        }
+
+        let mut transaction = TransactionBuilder::new(stage)?
+            .spend_selected(&accounts, amount, strategy, dust)?
+            .pay_to(&to, amount)?
+            .with_change(&change)
+            .build()?;
+
+        for (from, wallet) in wallets {
+            if !transaction.lookup_input(&from) {
+                continue;
+            }
+
+            // NB: an encrypted wallet signs through the daemon while unlocked there,
+            // otherwise its passphrase is read from the terminal.
+            let daemon_signs = wallet.is_encrypted() && client.get_wallet_status(&from)?.unlocked;
+
+            if daemon_signs {
+                transaction = client.sign_transaction(&from, &transaction)?;
+                continue;
+            }
+
+            let wallet = if wallet.is_encrypted() {
+                let prompt = format!("passphrase ({}): ", address::encode(stage, &from));
+                let passphrase = common::read_passphrase(&prompt)?;
+                wallet.unlock(passphrase.as_bytes())?
+            } else {
+                wallet
+            };
+
+            let secret_key = SecretKey::from_slice(&wallet.secret_key)?;
+            transaction.sign_input(&secret_key, &from)?;
+        }
+
+        let tx_id = client.submit_transaction(&transaction)?;
+        println!("{}", tx_id.to_string());
+
+        let mut progress = |status: &BroadcastStatus| {
+            if let BroadcastStatus::Pending { confidence } = status {
+                println!("confidence: {}", confidence);
+            }
+        };
+
+        client
+            .track_transaction(&tx_id, timeout, &mut progress)
+            .map_err(|e| e.into())
+    }
+
+    /// `read_transaction` reads a `Transaction` from a JSON file.
+    fn read_transaction(path: &str) -> Result<Transaction> {
+        let buf = common::read_file(path)?;
+        let contents = String::from_utf8(buf)?;
+
+        Transaction::from_json(&contents).map_err(|e| e.into())
+    }
+
+    /// `export_unsigned` writes a `SigningRequest` of the `Transaction` in a JSON
+    /// file to a JSON file, to be signed on an offline machine.
+    pub fn export_unsigned(path: &str, output: &str) -> Result<()> {
+        let transaction = CliClient::read_transaction(path)?;
+        let request = SigningRequest::new(&transaction)?;
+
+        common::write_file(output, request.to_json()?.as_bytes())
+    }
+
+    /// `sign_offline` signs the inputs of the `SigningRequest` in a JSON file that
+    /// can be signed by the `Wallet` of an address in the store of a stage, writing
+    /// the signatures to a signatures file and returning their number. The passphrase
+    /// of an encrypted `Wallet` is read from the terminal.
+    pub fn sign_offline(stage: Stage, wallet: &str, path: &str, output: &str) -> Result<usize> {
+        let address = address::parse(stage, wallet)?;
+
+        let buf = common::read_file(path)?;
+        let request = SigningRequest::from_json(&String::from_utf8(buf)?)?;
+
+        if request.stage != stage {
+            let msg = format!("invalid signing request stage: {}", request.stage);
+            let err = Error::InvalidCommand { msg };
+            return Err(err);
+        }
+
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let (wallet, _) = CliClient::wallet_signers(&store, stage, &address)?;
+
+        let wallet = if wallet.is_encrypted() {
+            let passphrase = common::read_passphrase("passphrase: ")?;
+            wallet.unlock(passphrase.as_bytes())?
+        } else {
+            wallet
+        };
+
+        let secret_key = SecretKey::from_slice(&wallet.secret_key)?;
+        let psigs = request.sign(&secret_key)?;
+
+        common::write_file(output, &psigs.to_bytes()?)?;
+
+        Ok(psigs.len())
+    }
+
+    /// `export_signatures` writes the input signatures of the `Transaction` in a
+    /// JSON file to a signatures file.
+    pub fn export_signatures(path: &str, output: &str) -> Result<()> {
+        let transaction = CliClient::read_transaction(path)?;
+        let buf = transaction.export_signatures()?;
+
+        common::write_file(output, &buf)
+    }
+
+    /// `add_signatures` adds the input signatures of a signatures file to the
+    /// `Transaction` in a JSON file, returning its updated `SignaturesStatus`.
+    pub fn add_signatures(path: &str, signatures: &str) -> Result<Vec<SignaturesStatus>> {
+        let mut transaction = CliClient::read_transaction(path)?;
+
+        let buf = common::read_file(signatures)?;
+        transaction.import_signatures(&buf)?;
+
+        common::write_file(path, transaction.to_json()?.as_bytes())?;
+
+        transaction.signatures_status().map_err(|e| e.into())
+    }
+
+    /// `signatures_status` returns the `SignaturesStatus` of the `Transaction` in a JSON file.
+    pub fn signatures_status(path: &str) -> Result<Vec<SignaturesStatus>> {
+        let transaction = CliClient::read_transaction(path)?;
+        transaction.signatures_status().map_err(|e| e.into())
+    }
+
+    /// `decode` decodes a `Transaction` or a `ConsensusMessage` from a JSON string,
+    /// an hex string or a CBOR binary, given directly or in a file.
+    pub fn decode(input: &str) -> Result<Decoded> {
+        let buf = if Path::new(input).is_file() {
+            common::read_file(input)?
+        } else {
+            input.as_bytes().to_vec()
+        };
+
+        let text = String::from_utf8(buf.clone()).ok();
+        let text = text.as_ref().map(|text| text.trim());
+
+        if let Some(json) = text.filter(|text| text.starts_with('{')) {
+            if let Ok(transaction) = Transaction::from_json(json) {
+                return Ok(Decoded::Transaction(transaction));
+            }
+
+            if let Ok(cons_msg) = ConsensusMessage::from_json(json) {
+                return Ok(Decoded::ConsensusMessage(cons_msg));
+            }
+
+            let msg = "invalid JSON: not a transaction or a consensus message".into();
+            let err = Error::Parse { msg };
+            return Err(err);
+        }
+
+        let buf = match text {
+            Some(hex) => base16::decode(hex).map_err(|e| {
+                let msg = format!("invalid hex: {}", e);
+                Error::Parse { msg }
+            })?,
+            None => buf,
+        };
+
+        if let Ok(transaction) = Transaction::from_bytes(&buf) {
+            return Ok(Decoded::Transaction(transaction));
+        }
+
+        if let Ok(cons_msg) = ConsensusMessage::from_bytes(&buf) {
+            return Ok(Decoded::ConsensusMessage(cons_msg));
+        }
+
+        let msg = "invalid CBOR: not a transaction or a consensus message".into();
+        let err = Error::Parse { msg };
+        Err(err)
+    }
+
+    /// `print_checks` prints the results of the validation checks of a model,
+    /// with the reason of the failed ones.
+    fn print_checks(checks: &[(&str, ModelsResult<()>)]) {
+        let failed: Vec<_> = checks.iter().filter(|(_, res)| res.is_err()).collect();
+
+        println!("valid: {}", failed.is_empty());
+
+        for (name, res) in failed {
+            if let Err(err) = res {
+                println!("  invalid {}: {}", name, err);
+            }
+        }
+    }
+
+    /// `print_transaction` prints a breakdown of a `Transaction` and of its validation.
+    fn print_transaction(transaction: &Transaction) -> Result<()> {
+        println!("transaction: {}", transaction.id.to_string());
+        println!("version: {}", transaction.version);
+        println!("stage: {}", transaction.stage);
+        println!("time: {}", transaction.time.to_string());
+
+        if let Some(locktime) = transaction.locktime {
+            println!("locktime: {}", locktime.to_string());
+        }
+
+        if let Some(expiry) = transaction.expiry {
+            println!("expiry: {}", expiry.to_string());
+        }
+
+        println!("distance: {}", transaction.distance);
+
+        println!("inputs: {}", transaction.inputs.len());
+
+        for (address, input) in transaction.inputs.iter() {
+            let status = input.signatures_status()?;
+
+            println!(
+                "  {} amount: {} distance: {} signatures: {}/{}",
+                address::encode(transaction.stage, address),
+                input.amount,
+                input.distance,
+                status.weight,
+                status.threshold
+            );
+
+            if let Err(err) = transaction.validate_input(address) {
+                println!("    invalid: {}", err);
+            }
+        }
+
+        println!("outputs: {}", transaction.outputs.len());
+
+        for (address, output) in transaction.outputs.iter() {
+            println!(
+                "  {} amount: {}",
+                address::encode(transaction.stage, address),
+                output.amount
+            );
+        }
+
+        let input_balance = transaction.input_balance()?;
+        let output_balance = transaction.output_balance()?;
+        println!("input balance: {}", input_balance);
+        println!("output balance: {}", output_balance);
+
+        match transaction.coinbase {
+            Some(ref coinbase) => println!(
+                "coinbase: {} amount: {} difficulty: {} distance: {} mined: {}",
+                address::encode(transaction.stage, &coinbase.address),
+                coinbase.amount,
+                coinbase.difficulty,
+                coinbase.distance,
+                coinbase.proof.is_some()
+            ),
+            None => println!("coinbase: none"),
+        }
+
+        println!("fully signed: {}", transaction.is_fully_signed()?);
+
+        CliClient::print_checks(&[
+            ("id", transaction.validate_id()),
+            ("version", transaction.version.validate()),
+            ("times", transaction.validate_times()),
+            ("inputs", transaction.validate_inputs()),
+            ("outputs", transaction.validate_outputs()),
+            ("distance", transaction.validate_distance()),
+            ("balance", transaction.validate_balance()),
+            ("coinbase", transaction.validate_coinbase()),
+        ]);
+
+        Ok(())
+    }
+
+    /// `print_consensus_message` prints a breakdown of a `ConsensusMessage` and
+    /// of its validation.
+    fn print_consensus_message(cons_msg: &ConsensusMessage) -> Result<()> {
+        let kind = match serde_json::to_value(cons_msg)? {
+            serde_json::Value::Object(map) => map.keys().next().cloned().unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        println!("consensus message: {}", kind);
+        println!("id: {}", cons_msg.id());
+        println!("time: {}", cons_msg.time().to_string());
+        println!("sender: {}", String::from_utf8_lossy(&cons_msg.address()));
+        println!(
+            "recipient: {}",
+            String::from_utf8_lossy(&cons_msg.node().address)
+        );
+
+        CliClient::print_checks(&[("message", cons_msg.validate())]);
+
+        Ok(())
+    }
+
+    /// `compact_store` compacts the store of a stage.
+    pub fn compact_store(stage: Stage) -> Result<()> {
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        store.compact().map_err(|e| e.into())
+    }
+
+    /// `backup_store` backs up the items of a stage from its store to a file,
+    /// returning the number of backed up items.
+    pub fn backup_store(stage: Stage, path: &str) -> Result<u64> {
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        store
+            .backup(path, Some(&[stage as u8]))
+            .map_err(|e| e.into())
+    }
+
+    /// `restore_store` restores the items of a stage in its store from a backup
+    /// file of the same stage, returning the number of restored items.
+    pub fn restore_store(stage: Stage, path: &str) -> Result<u64> {
+        let snapshot = Snapshot::read(path)?;
+
+        if snapshot.prefix != Some(vec![stage as u8]) {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+
+        snapshot.restore(&mut store).map_err(|e| e.into())
+    }
+
+    /// `export_checkpoint` exports a `Checkpoint` of the store of a stage, signed
+    /// with the node key, to a JSON or CBOR file.
+    pub fn export_checkpoint(stage: Stage, path: &str, format: &str) -> Result<Checkpoint> {
+        let secret_key = common::read_node_key(stage)?;
+        let config = common::read_config(stage)?;
+        let store = common::open_store(stage, &config)?;
+
+        let mut checkpoint = Checkpoint::new(&store, stage, &secret_key.to_public())?;
+        checkpoint.sign(&secret_key)?;
+
+        let buf = match format {
+            "cbor" => checkpoint.to_bytes()?,
+            _ => checkpoint.to_json()?.into_bytes(),
+        };
+
+        common::write_file(path, &buf)?;
+
+        Ok(checkpoint)
+    }
+
+    /// `verify_checkpoint` verifies a `Checkpoint` of a stage read from a JSON or
+    /// CBOR file, optionally expecting it to be signed by a specific node and
+    /// checking its digest against the local store.
+    pub fn verify_checkpoint(
+        stage: Stage,
+        path: &str,
+        public_key: Option<&str>,
+        against_store: bool,
+    ) -> Result<Checkpoint> {
+        let buf = common::read_file(path)?;
+
+        let checkpoint = match String::from_utf8(buf.clone()) {
+            Ok(json) if json.trim().starts_with('{') => Checkpoint::from_json(json.trim())?,
+            _ => Checkpoint::from_bytes(&buf)?,
+        };
+
+        if checkpoint.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        if let Some(public_key) = public_key {
+            let public_key = PublicKey::from_str(public_key)?;
+            checkpoint.validate_signed(&public_key)?;
+        } else {
+            checkpoint.validate()?;
+        }
+
+        if against_store {
+            let config = common::read_config(stage)?;
+            let store = common::open_store(stage, &config)?;
+
+            checkpoint.verify_store(&store)?;
+        }
+
+        Ok(checkpoint)
+    }
+
+    /// `selftest` checks the roundtrip of the `TestVectors` of the release,
+    /// verifying them against a golden file and writing them to another, if any.
+    pub fn selftest(golden: Option<&str>, generate: Option<&str>) -> Result<TestVectors> {
+        let vectors = TestVectors::generate()?;
+        vectors.check()?;
+
+        if let Some(path) = golden {
+            let buf = common::read_file(path)?;
+            let contents = String::from_utf8(buf)?;
+
+            TestVectors::from_json(&contents)?.verify()?;
+        }
+
+        if let Some(path) = generate {
+            common::write_file(path, vectors.to_json()?.as_bytes())?;
+        }
+
+        Ok(vectors)
+    }
+
+    /// `simulate` runs a consensus `Simulation`, returning its report and
+    /// dumping the resulting DAG in JSON to a file, if any.
+    pub fn simulate(params: &SimulationParams, dag: Option<&str>) -> Result<SimulationReport> {
+        let mut simulation = Simulation::new(params)?;
+        let report = simulation.run()?;
+
+        if let Some(path) = dag {
+            let dag = serde_json::to_string_pretty(&simulation.dag()?)?;
+            common::write_file(path, dag.as_bytes())?;
+        }
+
+        Ok(report)
+    }
+
+    /// `bench_params` returns the `BenchParams` of the bench command matches.
+    fn bench_params(matches: &ArgMatches) -> Result<BenchParams> {
+        let count = CliClient::parse_u32(matches, "count")?.unwrap_or(BenchParams::DEFAULT_COUNT);
+        let value_size =
+            CliClient::parse_u32(matches, "value-size")?.unwrap_or(BenchParams::DEFAULT_VALUE_SIZE);
+        let s_cost =
+            CliClient::parse_u32(matches, "s-cost")?.unwrap_or(BalloonParams::DEFAULT_S_COST);
+        let t_cost =
+            CliClient::parse_u32(matches, "t-cost")?.unwrap_or(BalloonParams::DEFAULT_T_COST);
+        let delta = CliClient::parse_u32(matches, "delta")?.unwrap_or(BalloonParams::DEFAULT_DELTA);
+
+        let balloon = BalloonParams::new(s_cost, t_cost, delta)?;
+        let mut params = BenchParams::new(count, value_size, balloon);
+
+        if let Some(backends) = matches.values_of("backend") {
+            params.backends = backends.map(|backend| backend.to_string()).collect();
+        }
+
+        Ok(params)
+    }
+
+    /// `bench` runs the micro-benchmarks of a `BenchParams`, returning their report.
+    pub fn bench(params: &BenchParams) -> Result<BenchReport> {
+        bench::run(params)
+    }
+
     /// `run` runs the `CliClient` application.
     pub fn run() -> Result<()> {
         CliClient::init()?;
 
-        let _matches = CliClient::args();
+        let matches = CliClient::args();
+
+        match matches.subcommand() {
+            ("decode", Some(matches)) => {
+                let input = matches.value_of("input").unwrap_or_default();
+
+                match CliClient::decode(input)? {
+                    Decoded::Transaction(transaction) => {
+                        CliClient::print_transaction(&transaction)?
+                    }
+                    Decoded::ConsensusMessage(cons_msg) => {
+                        CliClient::print_consensus_message(&cons_msg)?
+                    }
+                }
+            }
+            ("balance", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let address = matches.value_of("address").unwrap_or_default();
+                println!("{}", CliClient::balance(stage, address)?);
+            }
+            ("history", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let address = matches.value_of("address").unwrap_or_default();
+                let count = CliClient::parse_u32(matches, "count")?;
+                let skip = CliClient::parse_u32(matches, "skip")?;
+                let history = CliClient::history(stage, address, count, skip)?;
+                let mut history_value = serde_json::to_value(&history)?;
+                history_value["address"] =
+                    serde_json::to_value(StagedAddress::new(stage, &history.address))?;
+                println!("{}", serde_json::to_string_pretty(&history_value)?);
+            }
+            ("send", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let from: Vec<&str> = matches
+                    .values_of("from")
+                    .map(|values| values.collect())
+                    .unwrap_or_default();
+                let to = matches.value_of("to").unwrap_or_default();
+                let amount = matches.value_of("amount").unwrap_or_default();
+                let amount = amount.parse().map_err(|_| {
+                    let msg = format!("invalid amount: {}", amount);
+                    Error::Parse { msg }
+                })?;
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                let timeout = CliClient::parse_u32(matches, "timeout")?
+                    .unwrap_or(CliClient::DEFAULT_SEND_TIMEOUT)
+                    as u64;
+                let strategy =
+                    CoinSelection::parse(matches.value_of("strategy").unwrap_or_default())?;
+                let change = matches.value_of("change");
+                let dust = match matches.value_of("dust") {
+                    Some(dust) => dust.parse().map_err(|_| {
+                        let msg = format!("invalid dust: {}", dust);
+                        Error::Parse { msg }
+                    })?,
+                    None => CoinSelection::DEFAULT_DUST_THRESHOLD,
+                };
+                let status = CliClient::send(
+                    stage, &from, to, amount, strategy, change, dust, address, timeout,
+                )?;
+                println!("{}", serde_json::to_string_pretty(&status)?);
+            }
+            ("wallet", Some(matches)) => match matches.subcommand() {
+                ("encrypt", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let wallet = matches.value_of("wallet").unwrap_or_default();
+                    CliClient::encrypt_wallet(stage, wallet)?;
+                }
+                ("unlock", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let wallet = matches.value_of("wallet").unwrap_or_default();
+                    let address = matches
+                        .value_of("address")
+                        .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                    let timeout = CliClient::parse_u32(matches, "timeout")?
+                        .unwrap_or(CliClient::DEFAULT_UNLOCK_TIMEOUT)
+                        as u64;
+                    let status = CliClient::unlock_wallet(stage, wallet, address, timeout)?;
+                    CliClient::print_wallet_status(&status)?;
+                }
+                ("lock", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let wallet = matches.value_of("wallet").unwrap_or_default();
+                    let address = matches
+                        .value_of("address")
+                        .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                    let status = CliClient::lock_wallet(stage, wallet, address)?;
+                    CliClient::print_wallet_status(&status)?;
+                }
+                ("sign-offline", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let wallet = matches.value_of("wallet").unwrap_or_default();
+                    let path = matches.value_of("request").unwrap_or_default();
+                    let output = matches.value_of("output").unwrap_or_default();
+                    let count = CliClient::sign_offline(stage, wallet, path, output)?;
+                    println!("signed: {}", count);
+                }
+                ("contacts", Some(matches)) => match matches.subcommand() {
+                    ("add", Some(matches)) => {
+                        let stage = CliClient::stage(matches)?;
+                        let label = matches.value_of("label").unwrap_or_default();
+                        let address = matches.value_of("address").unwrap_or_default();
+                        CliClient::add_contact(stage, label, address)?;
+                    }
+                    ("list", Some(matches)) => {
+                        let stage = CliClient::stage(matches)?;
+
+                        for contact in CliClient::list_contacts(stage)? {
+                            println!(
+                                "{}: {}",
+                                contact.label,
+                                address::encode(stage, &contact.address)
+                            );
+                        }
+                    }
+                    ("remove", Some(matches)) => {
+                        let stage = CliClient::stage(matches)?;
+                        let label = matches.value_of("label").unwrap_or_default();
+                        CliClient::remove_contact(stage, label)?;
+                    }
+                    _ => {}
+                },
+                _ => {}
+            },
+            ("status", Some(matches)) => match matches.subcommand() {
+                ("store", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let stats = CliClient::store_stats(stage)?;
+                    println!("{}", serde_json::to_string_pretty(&stats)?);
+                }
+                ("daemon", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let status = status::status(stage)?;
+                    println!("{}", serde_json::to_string_pretty(&status)?);
+                }
+                ("tx", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let id = matches.value_of("id").unwrap_or_default();
+                    let telemetry = CliClient::tx_telemetry(stage, id)?;
+                    CliClient::print_timeline(&telemetry);
+                }
+                _ => {}
+            },
+            ("transaction", Some(matches)) => match matches.subcommand() {
+                ("export-unsigned", Some(matches)) => {
+                    let path = matches.value_of("file").unwrap_or_default();
+                    let output = matches.value_of("output").unwrap_or_default();
+                    CliClient::export_unsigned(path, output)?;
+                }
+                ("export-signatures", Some(matches)) => {
+                    let path = matches.value_of("file").unwrap_or_default();
+                    let output = matches.value_of("output").unwrap_or_default();
+                    CliClient::export_signatures(path, output)?;
+                }
+                ("add-signatures", Some(matches)) => {
+                    let path = matches.value_of("file").unwrap_or_default();
+                    let signatures = matches.value_of("signatures").unwrap_or_default();
+                    let status = CliClient::add_signatures(path, signatures)?;
+                    println!("{}", serde_json::to_string_pretty(&status)?);
+                }
+                ("signatures-status", Some(matches)) => {
+                    let path = matches.value_of("file").unwrap_or_default();
+                    let status = CliClient::signatures_status(path)?;
+                    println!("{}", serde_json::to_string_pretty(&status)?);
+                }
+                _ => {}
+            },
+            ("store", Some(matches)) => match matches.subcommand() {
+                ("compact", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    CliClient::compact_store(stage)?;
+                }
+                ("backup", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let path = matches.value_of("file").unwrap_or_default();
+                    let count = CliClient::backup_store(stage, path)?;
+                    println!("backed up: {}", count);
+                }
+                ("restore", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let path = matches.value_of("file").unwrap_or_default();
+                    let count = CliClient::restore_store(stage, path)?;
+                    println!("restored: {}", count);
+                }
+                _ => {}
+            },
+            ("events", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                let filter = CliClient::event_filter(stage, matches)?;
+                CliClient::events(stage, address, &filter)?;
+            }
+            ("simulate", Some(matches)) => {
+                let nodes = CliClient::parse_u32(matches, "nodes")?.unwrap_or_default();
+                let txs = CliClient::parse_u32(matches, "txs")?.unwrap_or_default();
+                let loss = matches.value_of("loss").unwrap_or("0");
+                let loss = loss.parse().map_err(|_| {
+                    let msg = format!("invalid loss: {}", loss);
+                    Error::Parse { msg }
+                })?;
+
+                let mut params = SimulationParams::new(nodes as usize, txs as usize, loss);
+
+                if let Some(steps) = CliClient::parse_u32(matches, "steps")? {
+                    params.max_steps = steps as usize;
+                }
+
+                params.k = CliClient::parse_u32(matches, "k")?;
+                params.alpha = CliClient::parse_u32(matches, "alpha")?;
+
+                if let Some(seed) = matches.value_of("seed") {
+                    let seed = seed.parse().map_err(|_| {
+                        let msg = format!("invalid seed: {}", seed);
+                        Error::Parse { msg }
+                    })?;
+
+                    params.seed = Some(seed);
+                }
+
+                let report = CliClient::simulate(&params, matches.value_of("dag"))?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            ("bench", Some(matches)) => {
+                let params = CliClient::bench_params(matches)?;
+                let report = CliClient::bench(&params)?;
+                print!("{}", report);
+            }
+            ("export-checkpoint", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let path = matches.value_of("file").unwrap_or_default();
+                let format = matches.value_of("format").unwrap_or("json");
+                let checkpoint = CliClient::export_checkpoint(stage, path, format)?;
+                println!("{}", checkpoint.digest.to_string());
+            }
+            ("verify-checkpoint", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let path = matches.value_of("file").unwrap_or_default();
+                let public_key = matches.value_of("public-key");
+                let against_store = matches.is_present("store");
+                let checkpoint =
+                    CliClient::verify_checkpoint(stage, path, public_key, against_store)?;
+                println!("verified: {}", checkpoint.digest.to_string());
+            }
+            ("selftest", Some(matches)) => {
+                let golden = matches.value_of("golden");
+                let generate = matches.value_of("generate");
+                let vectors = CliClient::selftest(golden, generate)?;
+
+                for vector in vectors.vectors.iter() {
+                    println!("ok: {}", vector.name);
+                }
+            }
+            ("watch", Some(matches)) => match matches.subcommand() {
+                ("add", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let address = matches.value_of("address").unwrap_or_default();
+                    let action = matches.value_of("action").unwrap_or_default();
+                    CliClient::add_watch(stage, address, action)?;
+                }
+                ("list", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+
+                    for watch in CliClient::list_watches(stage)? {
+                        println!(
+                            "{}: {}",
+                            address::encode(stage, &watch.address),
+                            watch.action
+                        );
+                    }
+                }
+                ("remove", Some(matches)) => {
+                    let stage = CliClient::stage(matches)?;
+                    let address = matches.value_of("address").unwrap_or_default();
+                    CliClient::remove_watch(stage, address)?;
+                }
+                _ => {}
+            },
+            ("ban", Some(matches)) => {
+                let node = matches.value_of("node").unwrap_or_default();
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                let duration = CliClient::parse_u32(matches, "duration")?
+                    .unwrap_or(CliClient::DEFAULT_BAN_DURATION)
+                    as u64;
+                let reason = matches.value_of("reason").unwrap_or_default();
+                let ban = CliClient::ban(node, address, duration, reason)?;
+                CliClient::print_node_ban(&ban)?;
+            }
+            ("unban", Some(matches)) => {
+                let node = matches.value_of("node").unwrap_or_default();
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                CliClient::unban(node, address)?;
+            }
+            ("lock-account", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let account = matches.value_of("account").unwrap_or_default();
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                let duration =
+                    CliClient::parse_u32(matches, "duration")?.unwrap_or_default() as u64;
+                let locktime = CliClient::lock_account(stage, account, address, duration)?;
+                println!("locked until: {}", locktime);
+            }
+            ("stake-account", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let account = matches.value_of("account").unwrap_or_default();
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                let amount = matches.value_of("amount").unwrap_or_default();
+                let amount = amount.parse().map_err(|_| {
+                    let msg = format!("invalid amount: {}", amount);
+                    Error::Parse { msg }
+                })?;
+                let duration =
+                    CliClient::parse_u32(matches, "duration")?.unwrap_or_default() as u64;
+                let locktime = CliClient::stake_account(stage, account, address, amount, duration)?;
+                println!("staked until: {}", locktime);
+            }
+            ("console", Some(matches)) => {
+                let stage = CliClient::stage(matches)?;
+                let address = matches
+                    .value_of("address")
+                    .unwrap_or(RpcClient::DEFAULT_ADDRESS);
+                CliConsole::new(stage, address)?.run()?;
+            }
+            _ => {}
+        }
 
         Ok(())
     }
 }
+
+#[test]
+fn test_decode() {
+    use models::node::Node;
+    use std::collections::BTreeSet;
+
+    let stage = Stage::random().unwrap();
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.update_id().unwrap();
+
+    let json = transaction.to_json().unwrap();
+    let res = CliClient::decode(&json);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Decoded::Transaction(transaction.clone()));
+
+    let hex = base16::encode_lower(&transaction.to_bytes().unwrap());
+    let res = CliClient::decode(&hex);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Decoded::Transaction(transaction));
+
+    let node = Node::new(stage, b"127.0.0.1:2019");
+    let cons_msg =
+        ConsensusMessage::new_fetch_nodes(&node.address, &node, &BTreeSet::new()).unwrap();
+
+    let hex = base16::encode_lower(&cons_msg.to_bytes().unwrap());
+    let res = CliClient::decode(&hex);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Decoded::ConsensusMessage(cons_msg));
+
+    let res = CliClient::decode("not hex");
+    assert!(res.is_err());
+
+    let res = CliClient::decode("{}");
+    assert!(res.is_err());
+}
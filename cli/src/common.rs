@@ -5,13 +5,19 @@
 use crate::error::Error;
 use crate::result::Result;
 use clap::{App, AppSettings, Arg};
+use config::profile::Profile;
 use config::Config;
+use crypto::ecc::ed25519::{PublicKey, SecretKey};
+use models::genesis::Genesis;
+use models::migrations;
 use models::stage::Stage;
 use models::version::VERSION;
+use rpc::RpcClient;
 use std::env;
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
-use store::backend::UnQLiteStore;
+use std::path::Path;
+use store::backend::{CachedStore, UnQLiteStore, WalStore};
 use store::PoolFactory;
 use store::StoreFactory;
 
@@ -33,17 +39,23 @@ pub fn app(name: &'static str, about: &'static str) -> App<'static, 'static> {
 pub fn add_stage(app: App<'static, 'static>) -> App<'static, 'static> {
     app.arg(
         Arg::with_name("stage")
-            .help("Sets the stage used")
+            .help("Sets the stage used (defaults to $ALSACOIN_PROFILE or development)")
             .short("s")
             .long("stage")
             .takes_value(true)
             .value_name("STAGE")
-            .possible_values(&["development", "testing", "production"])
-            .default_value("development")
+            .possible_values(Profile::VALID_PROFILES)
             .required(false),
     )
 }
 
+/// `parse_stage` returns the `Stage` of the profile selected by a stage option,
+/// falling back to the `ALSACOIN_PROFILE` environment variable and then to development.
+pub fn parse_stage(stage: Option<&str>) -> Result<Stage> {
+    let profile = Profile::select(stage)?;
+    Ok(Stage::from(profile))
+}
+
 /// `add_verbose` adds a verbose option to a command.
 pub fn add_verbose(app: App<'static, 'static>) -> App<'static, 'static> {
     app.arg(
@@ -93,6 +105,31 @@ pub fn read_file(path: &str) -> Result<Vec<u8>> {
     Ok(buf)
 }
 
+/// `read_passphrase` reads a passphrase from the terminal without echoing it.
+pub fn read_passphrase(prompt: &str) -> Result<String> {
+    rpassword::read_password_from_tty(Some(prompt)).map_err(|e| e.into())
+}
+
+/// `read_new_passphrase` reads a new passphrase from the terminal, asking
+/// for a confirmation.
+pub fn read_new_passphrase() -> Result<String> {
+    let passphrase = read_passphrase("passphrase: ")?;
+
+    if passphrase.is_empty() {
+        let msg = "empty passphrase".into();
+        let err = Error::InvalidCommand { msg };
+        return Err(err);
+    }
+
+    if read_passphrase("confirm passphrase: ")? != passphrase {
+        let msg = "passphrases do not match".into();
+        let err = Error::InvalidCommand { msg };
+        return Err(err);
+    }
+
+    Ok(passphrase)
+}
+
 /// `destroy_file` destroys a file.
 pub fn destroy_file(path: &str) -> Result<()> {
     fs::remove_file(path).map_err(|e| e.into())
@@ -171,13 +208,168 @@ pub fn create_config(stage: Stage) -> Result<()> {
     write_config(stage, &config)
 }
 
-/// `read_config` reads an Alsacoin config.
+/// `read_config` reads an Alsacoin config, overridden by the `ALSACOIN_*`
+/// environment variables.
 pub fn read_config(stage: Stage) -> Result<Config> {
     let path = config_path(stage)?;
     let buf = read_file(&path)?;
 
     let contents = String::from_utf8(buf)?;
-    Config::from_toml(&contents).map_err(|e| e.into())
+    Config::load(&contents).map_err(|e| e.into())
+}
+
+/// `node_key_path` returns the path of the node key of a stage.
+pub fn node_key_path(stage: Stage) -> Result<String> {
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("config");
+    path.push(&format!("{}.key", stage));
+
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
+    } else {
+        let err = Error::InvalidPath;
+        Err(err)
+    }
+}
+
+/// `read_node_key` reads the node key of a stage, creating a new random one
+/// if missing.
+pub fn read_node_key(stage: Stage) -> Result<SecretKey> {
+    let path = node_key_path(stage)?;
+
+    if !Path::new(&path).exists() {
+        create_config_dir()?;
+
+        let secret_key = SecretKey::random()?;
+        write_file(&path, secret_key.to_string().as_bytes())?;
+
+        return Ok(secret_key);
+    }
+
+    let buf = read_file(&path)?;
+    let contents = String::from_utf8(buf)?;
+
+    SecretKey::from_str(contents.trim()).map_err(|e| e.into())
+}
+
+/// `admin_token_path` returns the path of the daemon admin token of a stage.
+pub fn admin_token_path(stage: Stage) -> Result<String> {
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("config");
+    path.push(&format!("{}.token", stage));
+
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
+    } else {
+        let err = Error::InvalidPath;
+        Err(err)
+    }
+}
+
+/// `read_admin_token` reads the daemon admin token of a stage, if any.
+pub fn read_admin_token(stage: Stage) -> Result<Option<String>> {
+    let path = admin_token_path(stage)?;
+
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+
+    let buf = read_file(&path)?;
+    let contents = String::from_utf8(buf)?;
+
+    Ok(Some(contents.trim().into()))
+}
+
+/// `connect_rpc` connects to the daemon gRPC server of a stage at an address,
+/// authorizing the admin and wallet requests with the admin token of the stage.
+pub fn connect_rpc(stage: Stage, address: &str) -> Result<RpcClient> {
+    let mut client = RpcClient::connect(address)?;
+
+    if let Some(token) = read_admin_token(stage)? {
+        client.set_admin_token(&token);
+    }
+
+    Ok(client)
+}
+
+/// `genesis_dir` returns the Alsacoin genesis files directory.
+pub fn genesis_dir() -> Result<String> {
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("genesis");
+
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
+    } else {
+        let err = Error::InvalidPath;
+        Err(err)
+    }
+}
+
+/// `genesis_path` returns an Alsacoin genesis file path.
+pub fn genesis_path(stage: Stage, config: &Config) -> Result<String> {
+    if let Some(ref path) = config.genesis.path {
+        return Ok(path.to_owned());
+    }
+
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("genesis");
+    path.push(&format!("{}.json", stage));
+
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
+    } else {
+        let err = Error::InvalidPath;
+        Err(err)
+    }
+}
+
+/// `write_genesis` writes an Alsacoin genesis file.
+pub fn write_genesis(stage: Stage, config: &Config, genesis: &Genesis) -> Result<()> {
+    if genesis.stage != stage {
+        let err = Error::InvalidStage;
+        return Err(err);
+    }
+
+    genesis.verify_signature()?;
+    genesis.validate()?;
+
+    create_dir(&genesis_dir()?)?;
+
+    let path = genesis_path(stage, config)?;
+    let buf = genesis.to_json()?.into_bytes();
+
+    write_file(&path, &buf)
+}
+
+/// `read_genesis` reads an Alsacoin genesis file, verifying it is signed by
+/// the configured genesis public key, if any.
+pub fn read_genesis(stage: Stage, config: &Config) -> Result<Genesis> {
+    config.validate()?;
+
+    let path = genesis_path(stage, config)?;
+    let buf = read_file(&path)?;
+
+    let contents = String::from_utf8(buf)?;
+    let genesis = Genesis::from_json(&contents)?;
+
+    if genesis.stage != stage {
+        let err = Error::InvalidStage;
+        return Err(err);
+    }
+
+    if let Some(ref public_key) = config.genesis.public_key {
+        let public_key = PublicKey::from_str(public_key)?;
+        genesis.validate_signed(&public_key)?;
+    } else {
+        genesis.validate()?;
+        genesis.verify_signature()?;
+    }
+
+    Ok(genesis)
 }
 
 /// `store_dir` returns the Alsacoin stores directory.
@@ -194,8 +386,12 @@ pub fn store_dir() -> Result<String> {
     }
 }
 
-/// `store_path` returns an Alsacoin store path.
-pub fn store_path(stage: Stage) -> Result<String> {
+/// `store_path` returns an Alsacoin store path, which is the configured one if any.
+pub fn store_path(stage: Stage, config: &Config) -> Result<String> {
+    if let Some(ref path) = config.store.path {
+        return Ok(path.to_owned());
+    }
+
     let mut path = env::current_dir()?;
     path.push("data");
     path.push("store");
@@ -219,43 +415,83 @@ pub fn destroy_store_dir() -> Result<()> {
     destroy_dir(&store_dir()?)
 }
 
-/// `create_store` creates an Alsacoin store.
+/// `create_store` creates an Alsacoin store at the latest schema version.
 pub fn create_store(stage: Stage, config: &Config) -> Result<()> {
+    open_store(stage, config).map(|_| ())
+}
+
+/// `open_store` opens an Alsacoin store, migrating it to the latest schema version.
+pub fn open_store(stage: Stage, config: &Config) -> Result<CachedStore<WalStore<UnQLiteStore>>> {
     config.validate()?;
 
     let kind = config.store.kind.clone().unwrap();
 
     let path = if &kind == "persistent" {
-        Some(store_path(stage)?)
+        Some(store_path(stage, config)?)
     } else {
         None
     };
 
-    StoreFactory::create(path, &config.store)
-        .map(|_| ())
-        .map_err(|e| e.into())
+    let mut store = StoreFactory::create(path, &config.store)?;
+    StoreFactory::migrate(&mut store, &migrations::migrations(stage))?;
+
+    Ok(store)
 }
 
-/// `open_store` opens an Alsacoin store.
-pub fn open_store(stage: Stage, config: &Config) -> Result<UnQLiteStore> {
+/// `open_pool` opens the Alsacoin pool of a specific stage.
+pub fn open_pool(stage: Stage, config: &Config) -> Result<UnQLiteStore> {
     config.validate()?;
 
-    let kind = config.store.kind.clone().unwrap();
+    PoolFactory::create_stage(&config.pool, &stage.to_string()).map_err(|e| e.into())
+}
 
-    let path = if &kind == "persistent" {
-        Some(store_path(stage)?)
+/// `run_dir` returns the Alsacoin daemon runtime files directory.
+pub fn run_dir() -> Result<String> {
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("run");
+
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
     } else {
-        None
-    };
+        let err = Error::InvalidPath;
+        Err(err)
+    }
+}
+
+/// `pid_path` returns the Alsacoin daemon PID file path of a stage.
+pub fn pid_path(stage: Stage) -> Result<String> {
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("run");
+    path.push(&format!("{}.pid", stage));
 
-    StoreFactory::create(path, &config.store).map_err(|e| e.into())
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
+    } else {
+        let err = Error::InvalidPath;
+        Err(err)
+    }
 }
 
-/// `open_pool` opens an Alsacoin pool.
-pub fn open_pool(config: &Config) -> Result<UnQLiteStore> {
-    config.validate()?;
+/// `socket_path` returns the Alsacoin daemon control socket path of a stage.
+pub fn socket_path(stage: Stage) -> Result<String> {
+    let mut path = env::current_dir()?;
+    path.push("data");
+    path.push("run");
+    path.push(&format!("{}.sock", stage));
+
+    if let Some(path) = path.to_str() {
+        Ok(path.into())
+    } else {
+        let err = Error::InvalidPath;
+        Err(err)
+    }
+}
 
-    PoolFactory::create(&config.pool).map_err(|e| e.into())
+/// `create_run_dir` creates the Alsacoin daemon runtime files directory if missing.
+pub fn create_run_dir() -> Result<()> {
+    create_dir(&run_dir()?)
 }
 
 /// `init_config` inits the Alsacoin config of a specific stage.
@@ -301,7 +537,8 @@ pub fn init() -> Result<()> {
 
 /// `destroy_store` destroys the Alsacoin store of a specific stage.
 pub fn destroy_store(stage: Stage) -> Result<()> {
-    let path = store_path(stage)?;
+    let config = read_config(stage).unwrap_or_default();
+    let path = store_path(stage, &config)?;
     destroy_file(&path)
 }
 
@@ -0,0 +1,537 @@
+//! # Console
+//!
+//! `console` contains the CLI console type and functions. The console is an
+//! interactive shell talking to a running daemon through its gRPC server.
+
+use crate::common;
+use crate::error::Error;
+use crate::result::Result;
+use crypto::ecc::ed25519::SecretKey;
+use crypto::hash::Digest;
+use models::account::Account;
+use models::address::{self, Address};
+use models::input::Input;
+use models::output::Output;
+use models::signers::Signers;
+use models::stage::Stage;
+use models::transaction::Transaction;
+use models::wallet::Wallet;
+use rpc::RpcClient;
+use std::io::{self, BufRead, Write};
+
+/// `HELP` is the help message of the console.
+const HELP: &str = "\
+Wallet:
+  wallet new                        creates a new session wallet
+  wallet show                       shows the session wallet
+  wallet balance                    shows the balance of the session wallet
+  wallet unspent                    shows the unspent outputs of the session wallet
+  balance <address>                 shows the balance of an address
+  unspent <address>                 shows the unspent outputs of an address
+  history <address> [count] [skip]  lists the transactions affecting an address
+
+Transactions:
+  tx new                            creates a new draft transaction
+  tx input <amount>                 spends an amount from the session wallet
+  tx output <address> <amount>      sends an amount to an address
+  tx sign                           signs the session wallet input
+  tx show                           shows the draft transaction
+  tx submit                         submits the draft transaction
+
+Store:
+  get <tx_id>                       shows a transaction
+  ancestors <tx_id>                 lists the known ancestors of a transaction
+  successors <tx_id>                lists the successors of a transaction
+  status <tx_id>                    shows the consensus status of a transaction
+
+Peers:
+  peers [count] [skip]              lists the known peers and their services
+  peer add <address>                adds a peer
+  peer remove <node_id>             removes a peer
+  metrics                           shows the consensus metrics
+
+  help                              shows this message
+  exit                              exits the console";
+
+/// `ConsoleCommand` is a command of the CLI console.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ConsoleCommand {
+    Help,
+    Exit,
+    WalletNew,
+    WalletShow,
+    WalletBalance,
+    WalletUnspent,
+    Balance {
+        address: Address,
+    },
+    Unspent {
+        address: Address,
+    },
+    History {
+        address: Address,
+        count: u32,
+        skip: u32,
+    },
+    TransactionNew,
+    TransactionInput {
+        amount: u64,
+    },
+    TransactionOutput {
+        address: Address,
+        amount: u64,
+    },
+    TransactionSign,
+    TransactionShow,
+    TransactionSubmit,
+    Get {
+        tx_id: Digest,
+    },
+    Ancestors {
+        tx_id: Digest,
+    },
+    Successors {
+        tx_id: Digest,
+    },
+    Status {
+        tx_id: Digest,
+    },
+    Peers {
+        count: u32,
+        skip: u32,
+    },
+    PeerAdd {
+        address: String,
+    },
+    PeerRemove {
+        node_id: Digest,
+    },
+    Metrics,
+}
+
+impl ConsoleCommand {
+    /// `invalid` returns an invalid command error.
+    fn invalid(line: &str) -> Error {
+        let msg = line.into();
+        Error::InvalidCommand { msg }
+    }
+
+    /// `parse_digest` parses a `Digest` argument.
+    fn parse_digest(line: &str, arg: Option<&str>) -> Result<Digest> {
+        let arg = arg.ok_or_else(|| Self::invalid(line))?;
+        Digest::from_str(arg).map_err(|e| e.into())
+    }
+
+    /// `parse_address` parses an `Address` argument of a `Stage`.
+    fn parse_address(stage: Stage, line: &str, arg: Option<&str>) -> Result<Address> {
+        let arg = arg.ok_or_else(|| Self::invalid(line))?;
+        address::parse(stage, arg).map_err(|e| e.into())
+    }
+
+    /// `parse_u64` parses an integer argument.
+    fn parse_u64(line: &str, arg: Option<&str>) -> Result<u64> {
+        let arg = arg.ok_or_else(|| Self::invalid(line))?;
+        arg.parse().map_err(|_| Self::invalid(line))
+    }
+
+    /// `parse` parses a `ConsoleCommand` of a `Stage` from a line, returning `None`
+    /// on empty lines.
+    pub fn parse(stage: Stage, line: &str) -> Result<Option<ConsoleCommand>> {
+        let line = line.trim();
+        let mut args = line.split_whitespace();
+
+        let cmd = match (args.next(), args.next()) {
+            (None, _) => return Ok(None),
+            (Some("help"), None) => ConsoleCommand::Help,
+            (Some("exit"), None) | (Some("quit"), None) => ConsoleCommand::Exit,
+            (Some("wallet"), Some("new")) => ConsoleCommand::WalletNew,
+            (Some("wallet"), Some("show")) => ConsoleCommand::WalletShow,
+            (Some("wallet"), Some("balance")) => ConsoleCommand::WalletBalance,
+            (Some("wallet"), Some("unspent")) => ConsoleCommand::WalletUnspent,
+            (Some("balance"), address) => ConsoleCommand::Balance {
+                address: Self::parse_address(stage, line, address)?,
+            },
+            (Some("unspent"), address) => ConsoleCommand::Unspent {
+                address: Self::parse_address(stage, line, address)?,
+            },
+            (Some("history"), address) => {
+                let address = Self::parse_address(stage, line, address)?;
+                let count = args
+                    .next()
+                    .map_or(Ok(0), |count| Self::parse_u64(line, Some(count)))?;
+                let skip = args
+                    .next()
+                    .map_or(Ok(0), |skip| Self::parse_u64(line, Some(skip)))?;
+
+                ConsoleCommand::History {
+                    address,
+                    count: count as u32,
+                    skip: skip as u32,
+                }
+            }
+            (Some("tx"), Some("new")) => ConsoleCommand::TransactionNew,
+            (Some("tx"), Some("input")) => ConsoleCommand::TransactionInput {
+                amount: Self::parse_u64(line, args.next())?,
+            },
+            (Some("tx"), Some("output")) => ConsoleCommand::TransactionOutput {
+                address: Self::parse_address(stage, line, args.next())?,
+                amount: Self::parse_u64(line, args.next())?,
+            },
+            (Some("tx"), Some("sign")) => ConsoleCommand::TransactionSign,
+            (Some("tx"), Some("show")) => ConsoleCommand::TransactionShow,
+            (Some("tx"), Some("submit")) => ConsoleCommand::TransactionSubmit,
+            (Some("get"), tx_id) => ConsoleCommand::Get {
+                tx_id: Self::parse_digest(line, tx_id)?,
+            },
+            (Some("ancestors"), tx_id) => ConsoleCommand::Ancestors {
+                tx_id: Self::parse_digest(line, tx_id)?,
+            },
+            (Some("successors"), tx_id) => ConsoleCommand::Successors {
+                tx_id: Self::parse_digest(line, tx_id)?,
+            },
+            (Some("status"), tx_id) => ConsoleCommand::Status {
+                tx_id: Self::parse_digest(line, tx_id)?,
+            },
+            (Some("peers"), count) => {
+                let count = count.map_or(Ok(0), |count| Self::parse_u64(line, Some(count)))?;
+                let skip = args
+                    .next()
+                    .map_or(Ok(0), |skip| Self::parse_u64(line, Some(skip)))?;
+
+                ConsoleCommand::Peers {
+                    count: count as u32,
+                    skip: skip as u32,
+                }
+            }
+            (Some("peer"), Some("add")) => ConsoleCommand::PeerAdd {
+                address: args.next().ok_or_else(|| Self::invalid(line))?.into(),
+            },
+            (Some("peer"), Some("remove")) => ConsoleCommand::PeerRemove {
+                node_id: Self::parse_digest(line, args.next())?,
+            },
+            (Some("metrics"), None) => ConsoleCommand::Metrics,
+            _ => return Err(Self::invalid(line)),
+        };
+
+        if args.next().is_some() {
+            return Err(Self::invalid(line));
+        }
+
+        Ok(Some(cmd))
+    }
+}
+
+/// `CliConsole` is the type of the CLI console. It keeps a session wallet
+/// and a draft transaction across commands.
+pub struct CliConsole {
+    pub stage: Stage,
+    pub wallet: Option<Wallet>,
+    pub transaction: Option<Transaction>,
+    client: RpcClient,
+}
+
+impl CliConsole {
+    /// `PROMPT` is the console prompt.
+    pub const PROMPT: &'static str = "alsac> ";
+
+    /// `new` creates a new `CliConsole` connected to the daemon gRPC server at an address.
+    pub fn new(stage: Stage, address: &str) -> Result<CliConsole> {
+        let client = common::connect_rpc(stage, address)?;
+
+        let console = CliConsole {
+            stage,
+            wallet: None,
+            transaction: None,
+            client,
+        };
+
+        Ok(console)
+    }
+
+    /// `wallet` returns the session `Wallet`.
+    fn wallet(&self) -> Result<&Wallet> {
+        self.wallet
+            .as_ref()
+            .ok_or_else(|| ConsoleCommand::invalid("no wallet: run `wallet new`"))
+    }
+
+    /// `transaction` returns the draft `Transaction`.
+    fn transaction(&mut self) -> Result<&mut Transaction> {
+        self.transaction
+            .as_mut()
+            .ok_or_else(|| ConsoleCommand::invalid("no transaction: run `tx new`"))
+    }
+
+    /// `wallet_signers` returns the `Signers` of the session `Wallet`.
+    fn wallet_signers(&self) -> Result<Signers> {
+        let mut signers = Signers::new()?;
+        signers.add(&self.wallet()?.to_signer(1)?)?;
+        signers.set_threshold(1)?;
+
+        Ok(signers)
+    }
+
+    /// `print_unspent` prints the unspent outputs of an `Address`.
+    fn print_unspent(&mut self, address: &Address) -> Result<()> {
+        for output in self.client.get_unspent_outputs(address)? {
+            println!("{} {}", output.transaction_id.to_string(), output.amount);
+        }
+
+        Ok(())
+    }
+
+    /// `execute` executes a `ConsoleCommand`, returning if the console should go on.
+    pub fn execute(&mut self, cmd: &ConsoleCommand) -> Result<bool> {
+        match cmd {
+            ConsoleCommand::Help => println!("{}", HELP),
+            ConsoleCommand::Exit => return Ok(false),
+            ConsoleCommand::WalletNew => {
+                self.wallet = Some(Wallet::new(self.stage)?);
+                let address = self.wallet_signers()?.address;
+                println!("{}", address::encode(self.stage, &address));
+            }
+            ConsoleCommand::WalletShow => {
+                // NB: the secret key is never printed.
+                let public_key = self.wallet()?.to_signer(1)?.public_key;
+
+                println!("public key: {}", public_key.to_string());
+                let address = self.wallet_signers()?.address;
+                println!("address: {}", address::encode(self.stage, &address));
+            }
+            ConsoleCommand::WalletBalance => {
+                let address = self.wallet_signers()?.address;
+                println!("{}", self.client.get_balance(&address)?);
+            }
+            ConsoleCommand::WalletUnspent => {
+                let address = self.wallet_signers()?.address;
+                self.print_unspent(&address)?;
+            }
+            ConsoleCommand::Balance { address } => {
+                println!("{}", self.client.get_balance(address)?);
+            }
+            ConsoleCommand::Unspent { address } => self.print_unspent(address)?,
+            ConsoleCommand::History {
+                address,
+                count,
+                skip,
+            } => {
+                let history = self.client.get_history(address, *count, *skip)?;
+
+                for entry in history.entries.iter() {
+                    println!(
+                        "{} {} {:+}{}",
+                        entry.time,
+                        entry.transaction_id.to_string(),
+                        entry.net(),
+                        if entry.applied { "" } else { " (pending)" }
+                    );
+                }
+
+                println!("total: {}", history.total);
+            }
+            ConsoleCommand::TransactionNew => {
+                let mut transaction = Transaction::new()?;
+                transaction.stage = self.stage;
+                transaction.update_id()?;
+
+                self.transaction = Some(transaction);
+            }
+            ConsoleCommand::TransactionInput { amount } => {
+                let signers = self.wallet_signers()?;
+                let balance = self.client.get_balance(&signers.address)?;
+                let account = Account::new(self.stage, &signers, balance, None)?;
+                let input = Input::new(&account, 1, *amount)?;
+
+                self.transaction()?.add_input(&input)?;
+            }
+            ConsoleCommand::TransactionOutput { address, amount } => {
+                let output = Output::new(address, *amount, &[]);
+
+                self.transaction()?.add_output(&output)?;
+            }
+            ConsoleCommand::TransactionSign => {
+                let secret_key = SecretKey::from_slice(&self.wallet()?.secret_key)?;
+                let address = self.wallet_signers()?.address;
+
+                self.transaction()?.sign_input(&secret_key, &address)?;
+            }
+            ConsoleCommand::TransactionShow => {
+                println!("{}", self.transaction()?.to_json()?);
+            }
+            ConsoleCommand::TransactionSubmit => {
+                let transaction = self.transaction()?.clone();
+                let tx_id = self.client.submit_transaction(&transaction)?;
+
+                self.transaction = None;
+                println!("{}", tx_id.to_string());
+            }
+            ConsoleCommand::Get { tx_id } => {
+                println!("{}", self.client.get_transaction(tx_id)?.to_json()?);
+            }
+            ConsoleCommand::Ancestors { tx_id } => {
+                for id in self.client.get_ancestors(tx_id)? {
+                    println!("{}", id.to_string());
+                }
+            }
+            ConsoleCommand::Successors { tx_id } => {
+                for id in self.client.get_successors(tx_id)? {
+                    println!("{}", id.to_string());
+                }
+            }
+            ConsoleCommand::Status { tx_id } => {
+                let status = self.client.get_status(tx_id)?;
+
+                println!("known: {}", status.known);
+                println!("queried: {}", status.queried);
+                println!("chit: {}", status.chit);
+                println!("confidence: {}", status.confidence);
+                println!("preferred: {}", status.preferred);
+                println!("strongly preferred: {}", status.strongly_preferred);
+                println!("accepted: {}", status.accepted);
+            }
+            ConsoleCommand::Peers { count, skip } => {
+                for node in self.client.list_peers(*count, *skip)? {
+                    let id = Digest::from_slice(&node.id)?;
+                    let address = String::from_utf8(node.address)?;
+                    let services = if node.services.is_empty() {
+                        "-".to_string()
+                    } else {
+                        node.services.join(",")
+                    };
+
+                    println!("{} {} {}", id.to_string(), address, services);
+                }
+            }
+            ConsoleCommand::PeerAdd { address } => {
+                let node = self.client.add_peer(address.as_bytes())?;
+                println!("{}", Digest::from_slice(&node.id)?.to_string());
+            }
+            ConsoleCommand::PeerRemove { node_id } => self.client.remove_peer(node_id)?,
+            ConsoleCommand::Metrics => {
+                let metrics = self.client.get_metrics()?;
+
+                println!("state id: {}", metrics.state_id);
+                println!("known transactions: {}", metrics.known_transactions);
+                println!("queried transactions: {}", metrics.queried_transactions);
+                println!("queued transactions: {}", metrics.queued_transactions);
+                println!("conflict sets: {}", metrics.conflict_sets);
+                println!("known nodes: {}", metrics.known_nodes);
+                println!("seed nodes: {}", metrics.seed_nodes);
+                println!("read only: {}", metrics.read_only);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// `run` runs the console loop until `exit` or the end of the input.
+    pub fn run(&mut self) -> Result<()> {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+
+        loop {
+            print!("{}", Self::PROMPT);
+            io::stdout().flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+
+            let res = ConsoleCommand::parse(self.stage, &line).and_then(|cmd| match cmd {
+                Some(cmd) => self.execute(&cmd),
+                None => Ok(true),
+            });
+
+            match res {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_console_command_parse() {
+    let stage = Stage::Testing;
+    let digest = Digest::random().unwrap();
+    let digest_str = digest.to_string();
+    let address_str = address::encode(stage, &digest);
+
+    let res = ConsoleCommand::parse(stage, "  ");
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let valid_cases = vec![
+        ("help".to_string(), ConsoleCommand::Help),
+        ("quit".to_string(), ConsoleCommand::Exit),
+        ("wallet new".to_string(), ConsoleCommand::WalletNew),
+        (
+            format!("balance {}", digest_str),
+            ConsoleCommand::Balance { address: digest },
+        ),
+        (
+            format!("balance {}", address_str),
+            ConsoleCommand::Balance { address: digest },
+        ),
+        (
+            format!("history {} 10 5", digest_str),
+            ConsoleCommand::History {
+                address: digest,
+                count: 10,
+                skip: 5,
+            },
+        ),
+        (
+            format!("tx output {} 10", address_str),
+            ConsoleCommand::TransactionOutput {
+                address: digest,
+                amount: 10,
+            },
+        ),
+        (
+            format!("status {}", digest_str),
+            ConsoleCommand::Status { tx_id: digest },
+        ),
+        (
+            "peers".to_string(),
+            ConsoleCommand::Peers { count: 0, skip: 0 },
+        ),
+        (
+            "peers 10 5".to_string(),
+            ConsoleCommand::Peers { count: 10, skip: 5 },
+        ),
+        (
+            "peer add 127.0.0.1:2019".to_string(),
+            ConsoleCommand::PeerAdd {
+                address: "127.0.0.1:2019".into(),
+            },
+        ),
+    ];
+
+    for (line, expected) in valid_cases {
+        let res = ConsoleCommand::parse(stage, &line);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), Some(expected));
+    }
+
+    let invalid_cases = vec![
+        "unknown".to_string(),
+        "help me".to_string(),
+        "balance".to_string(),
+        "balance abc".to_string(),
+        format!("balance {}", address::encode(Stage::Production, &digest)),
+        format!("tx output {}", digest_str),
+        format!("tx output {} ten", digest_str),
+        "peers ten".to_string(),
+        "peer remove".to_string(),
+    ];
+
+    for line in invalid_cases {
+        let res = ConsoleCommand::parse(stage, &line);
+        assert!(res.is_err());
+    }
+}
@@ -1,10 +1,49 @@
 //! # Daemon
 //!
-//! `daemon` contains the CLI daemon type and functions.
+//! `daemon` contains the CLI daemon type and functions. A started daemon can run
+//! in the background, and is managed through its PID file and its local control
+//! socket, answering to the `status`, `stop` and `reload` commands.
 
 use crate::common;
+use crate::error::Error;
 use crate::result::Result;
+use crate::status::{self, ControlCommand, ControlResponse, DaemonStatus};
 use clap::{App, Arg, ArgMatches, SubCommand};
+use config::error::Error as ConfigError;
+use config::network::NetworkConfig;
+use config::notifier::NotifierConfig;
+use config::preset::ConsensusPreset;
+use config::Config;
+use log::logger::Logger;
+use models::consensus_state::ConsensusState;
+use models::genesis::Genesis;
+use models::node::Service;
+use models::stage::Stage;
+use models::timestamp::Timestamp;
+use models::traits::Storable;
+use models::verify;
+use network::backend::{MultiNetwork, TcpNetwork};
+use network::NetworkFactory;
+use protocol::events;
+use protocol::notifier::{self, Notifier};
+use protocol::state::ProtocolState;
+use protocol::watch;
+use protocol::{ProtocolClientServer, ProtocolConsensusServer, ProtocolMinerServer};
+use serde_json;
+use std::collections::BTreeSet;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::net::ToSocketAddrs;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use store::stats::StoreOccupancy;
+use store::traits::Store;
+use store::verify::VerifyReport;
+use store::StoreFactory;
 
 /// `add_start` adds a start command to `App`.
 fn add_start(app: App<'static, 'static>) -> App<'static, 'static> {
@@ -30,6 +69,51 @@ fn add_start(app: App<'static, 'static>) -> App<'static, 'static> {
                 .long("without-client")
                 .takes_value(false)
                 .required(false),
+        )
+        .arg(
+            Arg::with_name("read-only")
+                .help("Runs a read-only replica that never queries or mines")
+                .long("read-only")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("without-client"),
+        )
+        .arg(
+            Arg::with_name("background")
+                .help("Runs the daemon in the background")
+                .long("background")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("log-events")
+                .help("Logs the accepted, rejected and conflicting transactions")
+                .long("log-events")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("repair-store")
+                .help("Removes the broken records found by the store check at startup")
+                .long("repair-store")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("archive")
+                .help("Keeps all the transactions, never pruning the store")
+                .long("archive")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .help("Overrides the consensus parameters with a preset")
+                .long("preset")
+                .takes_value(true)
+                .value_name("PRESET")
+                .possible_values(ConsensusPreset::VALID_PRESETS)
+                .required(false),
         );
 
     cmd = common::add_common(cmd);
@@ -37,7 +121,7 @@ fn add_start(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
-/// `add_stop` adds a start command to `App`.
+/// `add_stop` adds a stop command to `App`.
 fn add_stop(app: App<'static, 'static>) -> App<'static, 'static> {
     let mut cmd = SubCommand::with_name("stop").about("Stops the daemon");
 
@@ -46,9 +130,9 @@ fn add_stop(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
-/// `add_restart` adds a start command to `App`.
+/// `add_restart` adds a restart command to `App`.
 fn add_restart(app: App<'static, 'static>) -> App<'static, 'static> {
-    let mut cmd = SubCommand::with_name("start")
+    let mut cmd = SubCommand::with_name("restart")
         .about("Restarts the daemon")
         .arg(
             Arg::with_name("without-consensus")
@@ -70,6 +154,51 @@ fn add_restart(app: App<'static, 'static>) -> App<'static, 'static> {
                 .long("without-client")
                 .takes_value(false)
                 .required(false),
+        )
+        .arg(
+            Arg::with_name("read-only")
+                .help("Runs a read-only replica that never queries or mines")
+                .long("read-only")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("without-client"),
+        )
+        .arg(
+            Arg::with_name("background")
+                .help("Runs the daemon in the background")
+                .long("background")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("log-events")
+                .help("Logs the accepted, rejected and conflicting transactions")
+                .long("log-events")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("repair-store")
+                .help("Removes the broken records found by the store check at startup")
+                .long("repair-store")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("archive")
+                .help("Keeps all the transactions, never pruning the store")
+                .long("archive")
+                .takes_value(false)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .help("Overrides the consensus parameters with a preset")
+                .long("preset")
+                .takes_value(true)
+                .value_name("PRESET")
+                .possible_values(ConsensusPreset::VALID_PRESETS)
+                .required(false),
         );
 
     cmd = common::add_common(cmd);
@@ -77,6 +206,265 @@ fn add_restart(app: App<'static, 'static>) -> App<'static, 'static> {
     app.subcommand(cmd)
 }
 
+/// `add_status` adds a status command to `App`.
+fn add_status(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("status").about("Shows the status of the daemon");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `add_reload` adds a reload command to `App`.
+fn add_reload(app: App<'static, 'static>) -> App<'static, 'static> {
+    let mut cmd = SubCommand::with_name("reload").about("Reloads the daemon config");
+
+    cmd = common::add_common(cmd);
+
+    app.subcommand(cmd)
+}
+
+/// `DaemonOptions` are the options of a started daemon.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct DaemonOptions {
+    pub consensus: bool,
+    pub miner: bool,
+    pub client: bool,
+    pub read_only: bool,
+    pub log_events: bool,
+    pub background: bool,
+    pub repair_store: bool,
+    pub archive: bool,
+    pub preset: Option<ConsensusPreset>,
+}
+
+impl DaemonOptions {
+    /// `from_matches` creates a new `DaemonOptions` from clap `ArgMatches`.
+    /// A read-only daemon never mines, and its consensus loop only serves the
+    /// read paths.
+    pub fn from_matches(matches: &ArgMatches) -> DaemonOptions {
+        let read_only = matches.is_present("read-only");
+
+        DaemonOptions {
+            consensus: !matches.is_present("without-consensus"),
+            miner: !read_only && !matches.is_present("without-miner"),
+            client: !matches.is_present("without-client"),
+            read_only,
+            log_events: matches.is_present("log-events"),
+            background: matches.is_present("background"),
+            repair_store: matches.is_present("repair-store"),
+            archive: matches.is_present("archive"),
+            preset: matches
+                .value_of("preset")
+                .and_then(|preset| ConsensusPreset::parse(preset).ok()),
+        }
+    }
+
+    /// `services` returns the `Service`s advertised by a daemon with the
+    /// `DaemonOptions` and a `ProtocolState`.
+    pub fn services<S: Store, P: Store>(&self, state: &ProtocolState<S, P>) -> BTreeSet<Service> {
+        let mut services = BTreeSet::new();
+
+        if self.miner {
+            services.insert(Service::Mining);
+        }
+
+        if self.consensus && !self.read_only {
+            services.insert(Service::Relay);
+            services.insert(Service::CompactQuery);
+        }
+
+        if state.is_archive() {
+            services.insert(Service::Archival);
+        }
+
+        if self.client {
+            services.insert(Service::Rpc);
+        }
+
+        services
+    }
+
+    /// `set_state` sets the `DaemonOptions` on a `ProtocolState`.
+    pub fn set_state<S: Store, P: Store>(&self, state: &mut ProtocolState<S, P>) {
+        state.set_read_only(self.read_only);
+
+        if self.archive {
+            state.config.archive = Some(true);
+        }
+
+        if let Some(preset) = self.preset {
+            state.config.apply_preset(preset);
+        }
+
+        let services = self.services(state);
+        state.set_services(&services);
+    }
+
+    /// `subscribe_events` subscribes a `Logger` to the events of a `ProtocolState`,
+    /// if required by the `DaemonOptions`.
+    pub fn subscribe_events<S: Store, P: Store>(
+        &self,
+        state: &mut ProtocolState<S, P>,
+        logger: Arc<Logger>,
+    ) -> Option<JoinHandle<()>> {
+        if self.log_events {
+            Some(events::log_events(logger, state.subscribe()))
+        } else {
+            None
+        }
+    }
+
+    /// `subscribe_watches` subscribes a `Logger` to the watches triggered in a
+    /// `ProtocolState`, running their log and webhook actions.
+    pub fn subscribe_watches<S: Store, P: Store>(
+        &self,
+        state: &mut ProtocolState<S, P>,
+        logger: Arc<Logger>,
+    ) -> JoinHandle<()> {
+        watch::watch_events(state.stage, logger, state.subscribe())
+    }
+
+    /// `subscribe_notifier` subscribes a `Notifier` to the events of a `ProtocolState`,
+    /// if the `NotifierConfig` has any URL. The payloads are signed with the node key
+    /// of the `ProtocolState`.
+    pub fn subscribe_notifier<S: Store, P: Store>(
+        &self,
+        state: &mut ProtocolState<S, P>,
+        config: &NotifierConfig,
+        logger: Arc<Logger>,
+    ) -> Result<Option<JoinHandle<()>>> {
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+
+        let notifier = Notifier::new(state.stage, config, state.secret_key.clone())?;
+
+        Ok(Some(notifier::notify_events(
+            notifier,
+            logger,
+            state.subscribe(),
+        )))
+    }
+}
+
+/// `ControlServer` is the local control socket server of a running daemon.
+/// It owns the daemon PID file and socket, removing them when dropped.
+pub struct ControlServer {
+    pub status: DaemonStatus,
+    listener: UnixListener,
+    pool: Option<Box<dyn Fn() -> StoreOccupancy + Send>>,
+}
+
+impl ControlServer {
+    /// `bind` binds the control socket and writes the PID file of the daemon
+    /// of a stage, replacing the stale ones left by a dead daemon.
+    pub fn bind(stage: Stage) -> Result<ControlServer> {
+        if status::is_running(stage) {
+            let err = Error::AlreadyRunning;
+            return Err(err);
+        }
+
+        common::create_run_dir()?;
+
+        let path = common::socket_path(stage)?;
+
+        if Path::new(&path).exists() {
+            common::destroy_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        let status = DaemonStatus::new(stage);
+
+        status::write_pid(stage, status.pid)?;
+
+        let server = ControlServer {
+            status,
+            listener,
+            pool: None,
+        };
+
+        Ok(server)
+    }
+
+    /// `watch_pool` sets the pool whose occupancy is reported in the status.
+    pub fn watch_pool<P: Store + Send + Sync + 'static>(&mut self, pool: Arc<RwLock<P>>) {
+        self.pool = Some(Box::new(move || {
+            StoreOccupancy::new(&*pool.read().unwrap())
+        }));
+    }
+
+    /// `current_status` returns the `DaemonStatus` with the current pool occupancy.
+    fn current_status(&self) -> DaemonStatus {
+        let mut status = self.status.clone();
+        status.pool = self.pool.as_ref().map(|occupancy| occupancy());
+        status
+    }
+
+    /// `reload` reloads and validates the config and genesis of the daemon.
+    fn reload(&mut self) -> Result<()> {
+        let stage = self.status.stage;
+        let config = common::read_config(stage)?;
+        common::read_genesis(stage, &config)?;
+
+        self.status.reloaded_at = Some(Timestamp::now());
+
+        Ok(())
+    }
+
+    /// `handle` handles a connection to the control socket, returning if the
+    /// daemon should keep running.
+    fn handle(&mut self, mut stream: UnixStream) -> Result<bool> {
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+
+        let (res, running) = match ControlCommand::parse(&line) {
+            Ok(ControlCommand::Status) => (ControlResponse::Status(self.current_status()), true),
+            Ok(ControlCommand::Stop) => (ControlResponse::Stopping, false),
+            Ok(ControlCommand::Reload) => match self.reload() {
+                Ok(()) => (ControlResponse::Reloaded, true),
+                Err(err) => {
+                    let msg = format!("{}", err);
+                    (ControlResponse::Error { msg }, true)
+                }
+            },
+            Err(err) => {
+                let msg = format!("{}", err);
+                (ControlResponse::Error { msg }, true)
+            }
+        };
+
+        writeln!(stream, "{}", res.to_json()?)?;
+
+        Ok(running)
+    }
+
+    /// `serve` serves the control socket until a stop command is received.
+    pub fn serve(&mut self) -> Result<()> {
+        let listener = self.listener.try_clone()?;
+
+        for stream in listener.incoming() {
+            if !self.handle(stream?)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let stage = self.status.stage;
+
+        let _ = status::remove_pid(stage);
+
+        if let Ok(path) = common::socket_path(stage) {
+            let _ = common::destroy_file(&path);
+        }
+    }
+}
+
 /// `CliDaemon` is the type of the CLI daemon.
 pub struct CliDaemon {}
 
@@ -87,12 +475,17 @@ impl CliDaemon {
     /// `CLI_ABOUT` is the CLI daemon app description.
     pub const CLI_ABOUT: &'static str = "Alsacoin daemon";
 
+    /// `STOP_TIMEOUT` is the time in seconds waited for a daemon to stop.
+    pub const STOP_TIMEOUT: u64 = 10;
+
     /// `app` returns the `CliDaemon` clap `App`.
     pub fn app() -> App<'static, 'static> {
         let mut app = common::app(Self::CLI_NAME, Self::CLI_ABOUT);
         app = add_start(app);
         app = add_stop(app);
-        add_restart(app)
+        app = add_restart(app);
+        app = add_status(app);
+        add_reload(app)
     }
 
     /// `args` returns the `CliDaemon` clap `ArgMatches`.
@@ -115,12 +508,386 @@ impl CliDaemon {
         common::destroy()
     }
 
+    /// `load_genesis` loads the `Genesis` of a stage.
+    pub fn load_genesis(stage: Stage) -> Result<Genesis> {
+        let config = common::read_config(stage)?;
+        common::read_genesis(stage, &config)
+    }
+
+    /// `verify_store` checks the integrity of the store of a stage, removing the
+    /// broken records if required.
+    pub fn verify_store(stage: Stage, repair: bool) -> Result<VerifyReport> {
+        let config = common::read_config(stage)?;
+        let mut store = common::open_store(stage, &config)?;
+        let verifiers = verify::verifiers(stage);
+
+        StoreFactory::verify_and_repair(&mut store, &verifiers, repair).map_err(|e| e.into())
+    }
+
+    /// `open_state` opens the `ProtocolState` of a stage from its store, or creates
+    /// it from the `Genesis` if the store has no consensus state yet.
+    pub fn open_state<S: Store, P: Store>(
+        genesis: &Genesis,
+        config: &Config,
+        store: Arc<RwLock<S>>,
+        pool: Arc<RwLock<P>>,
+    ) -> Result<ProtocolState<S, P>> {
+        let stage = genesis.stage;
+        let mut network_config = config.network.clone();
+        network_config.populate();
+
+        let consensus_address = network_config.consensus_address.unwrap();
+        let address = TcpNetwork::new(&consensus_address)?.address_bytes()?;
+
+        let count = ConsensusState::count(&*store.read().unwrap(), stage, None, None, None)?;
+
+        let state = if count == 0 {
+            ProtocolState::create_from_genesis(genesis, &address, &BTreeSet::new(), store, pool)?
+        } else {
+            let mut consensus = genesis.consensus.clone();
+            ProtocolState::open(stage, &address, &mut consensus, store, pool)?
+        };
+
+        Ok(state)
+    }
+
+    /// `network_config` returns the `NetworkConfig` of a network kind.
+    fn network_config(config: &Config, kind: &str) -> NetworkConfig {
+        let mut network_config = config.network.clone();
+        network_config.kind = Some(kind.into());
+        network_config
+    }
+
+    /// `admin_token` returns the gRPC admin token of a stage, read from the
+    /// `NetworkConfig` or else from the admin token file of the stage. The
+    /// daemon cannot serve the admin service without a token.
+    fn admin_token(stage: Stage, config: &Config) -> Result<String> {
+        if let Some(ref token) = config.network.rpc_admin_token {
+            return Ok(token.to_owned());
+        }
+
+        if let Some(token) = common::read_admin_token(stage)? {
+            if !token.is_empty() {
+                return Ok(token);
+            }
+        }
+
+        let msg = format!(
+            "missing gRPC admin token: set network.rpc_admin_token or write {}",
+            common::admin_token_path(stage)?
+        );
+        let err = Error::Daemon { msg };
+        Err(err)
+    }
+
+    /// `start_rpc_server` starts in background the gRPC server on the RPC address
+    /// of the `NetworkConfig`, sharing the network of the client server. The
+    /// admin service is authorized by the admin token of the stage.
+    fn start_rpc_server(
+        config: &Config,
+        admin_token: &str,
+        state: Arc<Mutex<protocol::ProtocolState>>,
+        network: Arc<Mutex<MultiNetwork<TcpNetwork>>>,
+        logger: Arc<Logger>,
+    ) -> Result<()> {
+        let mut network_config = config.network.clone();
+        network_config.populate();
+
+        let rpc_address = network_config.rpc_address.unwrap_or_default();
+
+        let address = if let Some(address) = rpc_address.to_socket_addrs()?.next() {
+            address
+        } else {
+            let err: Error = ConfigError::InvalidAddress.into();
+            return Err(err);
+        };
+
+        let mut server = rpc::RpcServer::new(state, network, logger);
+        server.set_admin_token(admin_token)?;
+
+        thread::spawn(move || {
+            let _ = server.run(address);
+        });
+
+        Ok(())
+    }
+
+    /// `start_servers` starts in background the protocol servers selected by the
+    /// `DaemonOptions`, returning the handle of the consensus loop, if started.
+    /// The gRPC server is started with the client server when the `Rpc` service
+    /// is enabled, requiring an admin token. The listeners of the client and
+    /// miner servers are closed with the daemon.
+    pub fn start_servers(
+        config: &Config,
+        options: &DaemonOptions,
+        admin_token: Option<&str>,
+        state: Arc<Mutex<protocol::ProtocolState>>,
+        logger: Arc<Logger>,
+    ) -> Result<Option<JoinHandle<()>>> {
+        if options.client {
+            let rpc_enabled = state.lock().unwrap().services.contains(&Service::Rpc);
+
+            if rpc_enabled && admin_token.is_none() {
+                let msg = "missing gRPC admin token".into();
+                let err = Error::Daemon { msg };
+                return Err(err);
+            }
+
+            let network_config = CliDaemon::network_config(config, "client");
+            let network = Arc::new(Mutex::new(NetworkFactory::create_multi(&network_config)?));
+
+            let mut server =
+                ProtocolClientServer::new(state.clone(), network.clone(), logger.clone())?;
+
+            thread::spawn(move || {
+                let _ = server.run();
+            });
+
+            if rpc_enabled {
+                CliDaemon::start_rpc_server(
+                    config,
+                    admin_token.unwrap_or_default(),
+                    state.clone(),
+                    network,
+                    logger.clone(),
+                )?;
+            }
+        }
+
+        if options.miner {
+            let network_config = CliDaemon::network_config(config, "miner");
+            let network = NetworkFactory::create_multi(&network_config)?;
+
+            let mut server = ProtocolMinerServer::new(
+                state.clone(),
+                Arc::new(Mutex::new(network)),
+                logger.clone(),
+            )?;
+
+            server.start()?;
+
+            thread::spawn(move || {
+                let _ = server.run();
+            });
+        }
+
+        if !options.consensus {
+            return Ok(None);
+        }
+
+        let network_config = CliDaemon::network_config(config, "consensus");
+        let network = NetworkFactory::create_multi(&network_config)?;
+
+        let mut server =
+            ProtocolConsensusServer::new(state, Arc::new(Mutex::new(network)), logger)?;
+
+        let handle = thread::spawn(move || {
+            let _ = server.run();
+        });
+
+        Ok(Some(handle))
+    }
+
+    /// `daemonize` runs the current command again as a detached background
+    /// process, returning its PID.
+    fn daemonize() -> Result<u32> {
+        let exe = env::current_exe()?;
+        let args = env::args().skip(1).filter(|arg| arg != "--background");
+
+        let child = Command::new(exe)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        Ok(child.id())
+    }
+
+    /// `start` starts the `CliDaemon`.
+    pub fn start(stage: Stage, options: &DaemonOptions) -> Result<()> {
+        if status::is_running(stage) {
+            let err = Error::AlreadyRunning;
+            return Err(err);
+        }
+
+        let genesis = CliDaemon::load_genesis(stage)?;
+
+        if options.background {
+            let pid = CliDaemon::daemonize()?;
+            println!("{}", pid);
+            return Ok(());
+        }
+
+        let mut server = ControlServer::bind(stage)?;
+
+        let report = CliDaemon::verify_store(stage, options.repair_store)?;
+        if !report.is_ok() {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+
+        let config = common::read_config(stage)?;
+
+        let admin_token = if options.client {
+            Some(CliDaemon::admin_token(stage, &config)?)
+        } else {
+            None
+        };
+
+        let store = Arc::new(RwLock::new(common::open_store(stage, &config)?));
+        let pool = Arc::new(RwLock::new(common::open_pool(stage, &config)?));
+        server.watch_pool(pool.clone());
+
+        let logger = Arc::new(Logger::from_config(&config.log)?);
+
+        let mut state = CliDaemon::open_state(&genesis, &config, store, pool)?;
+
+        state.set_secret_key(common::read_node_key(stage)?);
+        options.set_state(&mut state);
+
+        let _events = options.subscribe_events(&mut state, logger.clone());
+        let _watches = options.subscribe_watches(&mut state, logger.clone());
+        let _notifier = options.subscribe_notifier(&mut state, &config.notifier, logger.clone())?;
+
+        let state = Arc::new(Mutex::new(state));
+        let consensus = CliDaemon::start_servers(
+            &config,
+            options,
+            admin_token.as_ref().map(|token| token.as_str()),
+            state.clone(),
+            logger,
+        )?;
+
+        let res = server.serve();
+
+        state.lock().unwrap().stop();
+        state.lock().unwrap().scheduler.stop();
+
+        if let Some(consensus) = consensus {
+            let _ = consensus.join();
+        }
+
+        res
+    }
+
+    /// `stop` stops the `CliDaemon`, waiting for it to release its control socket.
+    pub fn stop(stage: Stage) -> Result<()> {
+        status::stop(stage)?;
+
+        for _ in 0..Self::STOP_TIMEOUT * 10 {
+            if !status::is_running(stage) {
+                return Ok(());
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        let msg = "stop timed out".into();
+        let err = Error::Daemon { msg };
+        Err(err)
+    }
+
+    /// `restart` restarts the `CliDaemon`.
+    pub fn restart(stage: Stage, options: &DaemonOptions) -> Result<()> {
+        if status::is_running(stage) {
+            CliDaemon::stop(stage)?;
+        }
+
+        CliDaemon::start(stage, options)
+    }
+
+    /// `status` prints the status of the `CliDaemon`.
+    pub fn status(stage: Stage) -> Result<()> {
+        let status = status::status(stage)?;
+        println!("{}", serde_json::to_string_pretty(&status)?);
+
+        Ok(())
+    }
+
     /// `run` runs the `CliDaemon` application.
     pub fn run() -> Result<()> {
         CliDaemon::init()?;
 
-        let _matches = CliDaemon::args();
+        let matches = CliDaemon::args();
 
-        Ok(())
+        let (cmd, matches) = match matches.subcommand() {
+            (cmd, Some(matches)) => (cmd, matches),
+            _ => return Ok(()),
+        };
+
+        let stage = common::parse_stage(matches.value_of("stage"))?;
+
+        match cmd {
+            "start" => {
+                let options = DaemonOptions::from_matches(matches);
+                CliDaemon::start(stage, &options)
+            }
+            "stop" => CliDaemon::stop(stage),
+            "restart" => {
+                let options = DaemonOptions::from_matches(matches);
+                CliDaemon::restart(stage, &options)
+            }
+            "status" => CliDaemon::status(stage),
+            "reload" => status::reload(stage),
+            _ => Ok(()),
+        }
     }
 }
+
+#[test]
+fn test_daemon_open_state() {
+    use config::pool::PoolConfig;
+    use crypto::ecc::ed25519::SecretKey;
+    use store::backend::BTreeStore;
+
+    let secret_key = SecretKey::random().unwrap();
+    let mut genesis = Genesis::random(&secret_key).unwrap();
+    genesis.sign(&secret_key).unwrap();
+
+    let config = Config::default();
+
+    let new_store = || {
+        let store = BTreeStore::new(
+            PoolConfig::DEFAULT_MAX_VALUE_SIZE,
+            PoolConfig::DEFAULT_MAX_SIZE,
+        )
+        .unwrap();
+        Arc::new(RwLock::new(store))
+    };
+
+    let store = new_store();
+    let pool = new_store();
+
+    let res = CliDaemon::open_state(&genesis, &config, store.clone(), pool.clone());
+    assert!(res.is_ok());
+    let state = res.unwrap();
+    assert_eq!(state.stage, genesis.stage);
+    assert_eq!(state.state.id, 0);
+
+    let address = network::backend::address_from_bytes(&state.address).unwrap();
+    assert_eq!(
+        address.to_string(),
+        NetworkConfig::DEFAULT_CONSENSUS_ADDRESS
+    );
+
+    let res = CliDaemon::open_state(&genesis, &config, store, pool);
+    assert!(res.is_ok());
+    let mut state = res.unwrap();
+    assert_eq!(state.state.eve_transaction_id, genesis.eve_transaction.id);
+
+    let options = DaemonOptions {
+        consensus: true,
+        client: true,
+        read_only: true,
+        archive: true,
+        ..DaemonOptions::default()
+    };
+
+    options.set_state(&mut state);
+    assert!(state.read_only);
+    assert!(state.is_archive());
+    assert!(state.services.contains(&Service::Archival));
+    assert!(state.services.contains(&Service::Rpc));
+    assert!(!state.services.contains(&Service::Mining));
+    assert!(!state.services.contains(&Service::Relay));
+}
@@ -4,6 +4,12 @@
 
 use config::error::Error as ConfigError;
 use crypto::error::Error as CryptoError;
+use log::error::Error as LogError;
+use models::error::Error as ModelsError;
+use models::error_code::ErrorCode;
+use network::error::Error as NetworkError;
+use protocol::error::Error as ProtocolError;
+use rpc::error::Error as RpcError;
 use serde_cbor;
 use serde_json;
 use std::convert::From;
@@ -23,10 +29,28 @@ pub enum Error {
     Model { msg: String },
     #[fail(display = "Config: {}", msg)]
     Config { msg: String },
+    #[fail(display = "Network: {}", msg)]
+    Network { msg: String },
+    #[fail(display = "Protocol: {}", msg)]
+    Protocol { msg: String },
+    #[fail(display = "Log: {}", msg)]
+    Log { msg: String },
+    #[fail(display = "RPC {}: {}", code, msg)]
+    Rpc { code: ErrorCode, msg: String },
     #[fail(display = "Parse: {}", msg)]
     Parse { msg: String },
+    #[fail(display = "Invalid command: {}", msg)]
+    InvalidCommand { msg: String },
     #[fail(display = "Invalid path")]
     InvalidPath,
+    #[fail(display = "Invalid stage")]
+    InvalidStage,
+    #[fail(display = "Daemon: {}", msg)]
+    Daemon { msg: String },
+    #[fail(display = "Daemon already running")]
+    AlreadyRunning,
+    #[fail(display = "Daemon not running")]
+    NotRunning,
 }
 
 impl From<io::Error> for Error {
@@ -50,6 +74,42 @@ impl From<StoreError> for Error {
     }
 }
 
+impl From<ModelsError> for Error {
+    fn from(error: ModelsError) -> Error {
+        let msg = format!("{}", error);
+        Error::Model { msg }
+    }
+}
+
+impl From<NetworkError> for Error {
+    fn from(error: NetworkError) -> Error {
+        let msg = format!("{}", error);
+        Error::Network { msg }
+    }
+}
+
+impl From<ProtocolError> for Error {
+    fn from(error: ProtocolError) -> Error {
+        let msg = format!("{}", error);
+        Error::Protocol { msg }
+    }
+}
+
+impl From<LogError> for Error {
+    fn from(error: LogError) -> Error {
+        let msg = format!("{}", error);
+        Error::Log { msg }
+    }
+}
+
+impl From<RpcError> for Error {
+    fn from(error: RpcError) -> Error {
+        let code = error.code();
+        let msg = format!("{}", error);
+        Error::Rpc { code, msg }
+    }
+}
+
 impl From<ConfigError> for Error {
     fn from(error: ConfigError) -> Error {
         let msg = format!("{}", error);
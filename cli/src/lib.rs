@@ -20,5 +20,15 @@ pub mod client;
 /// `daemon` contains the CLI daemon type and functions.
 pub mod daemon;
 
+/// `status` contains the daemon control socket types and functions.
+pub mod status;
+
+/// `console` contains the CLI console type and functions.
+pub mod console;
+
+/// `bench` contains the store and crypto micro-benchmarks types and functions.
+pub mod bench;
+
 pub use self::client::CliClient;
+pub use self::console::CliConsole;
 pub use self::daemon::CliDaemon;
@@ -0,0 +1,241 @@
+//! # Status
+//!
+//! `status` contains the types and functions used to manage a running daemon
+//! through its PID file and its local control socket.
+
+use crate::common;
+use crate::error::Error;
+use crate::result::Result;
+use models::stage::Stage;
+use models::timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use store::stats::StoreOccupancy;
+
+/// `ControlCommand` is a command sent to the daemon control socket.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ControlCommand {
+    Status,
+    Stop,
+    Reload,
+}
+
+impl ControlCommand {
+    /// `parse` parses a `ControlCommand` from a line.
+    pub fn parse(line: &str) -> Result<ControlCommand> {
+        match line.trim() {
+            "status" => Ok(ControlCommand::Status),
+            "stop" => Ok(ControlCommand::Stop),
+            "reload" => Ok(ControlCommand::Reload),
+            line => {
+                let msg = line.into();
+                let err = Error::InvalidCommand { msg };
+                Err(err)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ControlCommand::Status => write!(f, "status"),
+            ControlCommand::Stop => write!(f, "stop"),
+            ControlCommand::Reload => write!(f, "reload"),
+        }
+    }
+}
+
+/// `DaemonStatus` is the status reported by a running daemon.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub stage: Stage,
+    pub started_at: Timestamp,
+    pub reloaded_at: Option<Timestamp>,
+    #[serde(default)]
+    pub pool: Option<StoreOccupancy>,
+}
+
+impl DaemonStatus {
+    /// `new` creates a new `DaemonStatus` for the current process.
+    pub fn new(stage: Stage) -> DaemonStatus {
+        DaemonStatus {
+            pid: std::process::id(),
+            stage,
+            started_at: Timestamp::now(),
+            reloaded_at: None,
+            pool: None,
+        }
+    }
+}
+
+/// `ControlResponse` is the response of the daemon to a `ControlCommand`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ControlResponse {
+    Status(DaemonStatus),
+    Stopping,
+    Reloaded,
+    Error { msg: String },
+}
+
+impl ControlResponse {
+    /// `to_json` converts the `ControlResponse` into a json string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` creates a new `ControlResponse` from a json string.
+    pub fn from_json(s: &str) -> Result<ControlResponse> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+/// `write_pid` writes the PID file of the daemon of a stage.
+pub fn write_pid(stage: Stage, pid: u32) -> Result<()> {
+    common::create_run_dir()?;
+
+    let path = common::pid_path(stage)?;
+    common::write_file(&path, pid.to_string().as_bytes())
+}
+
+/// `read_pid` reads the PID file of the daemon of a stage, if any.
+pub fn read_pid(stage: Stage) -> Result<Option<u32>> {
+    let path = common::pid_path(stage)?;
+
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+
+    let buf = common::read_file(&path)?;
+    let contents = String::from_utf8(buf)?;
+
+    let pid = contents.trim().parse().map_err(|_| {
+        let msg = format!("invalid pid file {}", path);
+        Error::Parse { msg }
+    })?;
+
+    Ok(Some(pid))
+}
+
+/// `remove_pid` removes the PID file of the daemon of a stage, if any.
+pub fn remove_pid(stage: Stage) -> Result<()> {
+    let path = common::pid_path(stage)?;
+
+    if Path::new(&path).exists() {
+        common::destroy_file(&path)?;
+    }
+
+    Ok(())
+}
+
+/// `send_command` sends a `ControlCommand` to the daemon of a stage and returns
+/// its response.
+pub fn send_command(stage: Stage, cmd: ControlCommand) -> Result<ControlResponse> {
+    let path = common::socket_path(stage)?;
+
+    let mut stream = UnixStream::connect(&path).map_err(|_| Error::NotRunning)?;
+    writeln!(stream, "{}", cmd)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    match ControlResponse::from_json(&line)? {
+        ControlResponse::Error { msg } => {
+            let err = Error::Daemon { msg };
+            Err(err)
+        }
+        res => Ok(res),
+    }
+}
+
+/// `is_running` returns if the daemon of a stage answers on its control socket.
+pub fn is_running(stage: Stage) -> bool {
+    status(stage).is_ok()
+}
+
+/// `status` returns the `DaemonStatus` of the daemon of a stage.
+pub fn status(stage: Stage) -> Result<DaemonStatus> {
+    match send_command(stage, ControlCommand::Status)? {
+        ControlResponse::Status(status) => Ok(status),
+        res => {
+            let msg = format!("unexpected response: {:?}", res);
+            let err = Error::Daemon { msg };
+            Err(err)
+        }
+    }
+}
+
+/// `stop` stops the daemon of a stage.
+pub fn stop(stage: Stage) -> Result<()> {
+    send_command(stage, ControlCommand::Stop).map(|_| ())
+}
+
+/// `reload` makes the daemon of a stage reload its config.
+pub fn reload(stage: Stage) -> Result<()> {
+    send_command(stage, ControlCommand::Reload).map(|_| ())
+}
+
+#[test]
+fn test_control_command_parse() {
+    let valid_cases = vec![
+        ("status", ControlCommand::Status),
+        (" stop\n", ControlCommand::Stop),
+        ("reload", ControlCommand::Reload),
+    ];
+
+    for (line, expected) in valid_cases {
+        let res = ControlCommand::parse(line);
+        assert!(res.is_ok());
+        let cmd = res.unwrap();
+        assert_eq!(cmd, expected);
+
+        let res = ControlCommand::parse(&cmd.to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), cmd);
+    }
+
+    let invalid_cases = vec!["", "start", "stop now"];
+
+    for line in invalid_cases {
+        let res = ControlCommand::parse(line);
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_control_response_json() {
+    let status = DaemonStatus::new(Stage::Testing);
+
+    let mut pool_status = status.clone();
+    pool_status.pool = Some(StoreOccupancy {
+        keys_count: 1,
+        size: 1 << 10,
+        max_size: 1 << 20,
+    });
+
+    let responses = vec![
+        ControlResponse::Status(status),
+        ControlResponse::Status(pool_status),
+        ControlResponse::Stopping,
+        ControlResponse::Reloaded,
+        ControlResponse::Error {
+            msg: "invalid config".into(),
+        },
+    ];
+
+    for response in responses {
+        let res = response.to_json();
+        assert!(res.is_ok());
+        let json = res.unwrap();
+        assert!(!json.contains('\n'));
+
+        let res = ControlResponse::from_json(&json);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), response);
+    }
+}
@@ -3,15 +3,21 @@
 //! `config` is the module containing the configuration type and functions.
 
 use crate::consensus::ConsensusConfig;
+use crate::error::Error;
+use crate::genesis::GenesisConfig;
 use crate::log::LogConfig;
 use crate::network::NetworkConfig;
+use crate::notifier::NotifierConfig;
 use crate::pool::PoolConfig;
+use crate::profile::Profile;
 use crate::result::Result;
 use crate::store::StoreConfig;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
+use std::env;
 use toml;
+use toml::value::{Table, Value};
 
 /// `Config` is the type representing an Alsacoin configuration.
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
@@ -21,9 +27,18 @@ pub struct Config {
     pub network: NetworkConfig,
     pub log: LogConfig,
     pub consensus: ConsensusConfig,
+    #[serde(default)]
+    pub genesis: GenesisConfig,
+    #[serde(default)]
+    pub notifier: NotifierConfig,
 }
 
 impl Config {
+    /// `ENV_PREFIX` is the prefix of the environment variables overriding the `Config`.
+    /// The rest of the name is the uppercase path of the field, joined by underscores
+    /// (e.g. `ALSACOIN_NETWORK_CONSENSUS_ADDRESS`).
+    pub const ENV_PREFIX: &'static str = "ALSACOIN_";
+
     /// `new` creates a new `Config`.
     pub fn new(
         store_conf: &StoreConfig,
@@ -31,11 +46,15 @@ impl Config {
         net_conf: &NetworkConfig,
         log_conf: &LogConfig,
         cons_conf: &ConsensusConfig,
+        genesis_conf: &GenesisConfig,
+        notifier_conf: &NotifierConfig,
     ) -> Result<Config> {
         store_conf.validate()?;
         net_conf.validate()?;
         log_conf.validate()?;
         cons_conf.validate()?;
+        genesis_conf.validate()?;
+        notifier_conf.validate()?;
 
         let conf = Config {
             store: store_conf.to_owned(),
@@ -43,6 +62,8 @@ impl Config {
             network: net_conf.to_owned(),
             log: log_conf.to_owned(),
             consensus: cons_conf.to_owned(),
+            genesis: genesis_conf.to_owned(),
+            notifier: notifier_conf.to_owned(),
         };
 
         Ok(conf)
@@ -56,18 +77,157 @@ impl Config {
         self.network.populate();
         self.log.populate();
         self.consensus.populate();
+        self.genesis.populate();
+        self.notifier.populate();
     }
 
     /// `validate` validates the `Config`.
     pub fn validate(&self) -> Result<()> {
         self.store.validate()?;
+        self.pool.validate()?;
         self.network.validate()?;
         self.log.validate()?;
         self.consensus.validate()?;
+        self.genesis.validate()?;
+        self.notifier.validate()?;
 
         Ok(())
     }
 
+    /// `load` loads a `Config` from a TOML string and the `ALSACOIN_*` environment
+    /// variables. See `load_with`.
+    pub fn load(s: &str) -> Result<Config> {
+        Config::load_with(s, env::vars())
+    }
+
+    /// `load_with` loads a `Config` from a TOML string and a set of variables.
+    /// The precedence order is: defaults, then the TOML string, then the variables.
+    /// The command-line flags, applied by the callers, come last.
+    pub fn load_with<I>(s: &str, vars: I) -> Result<Config>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut config = Config::from_toml(s)?;
+        config.populate();
+        config.apply_env(vars)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// `apply_env` overrides the `Config` fields with the `ALSACOIN_*` variables
+    /// of a set of variables.
+    pub fn apply_env<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut value = Value::try_from(&*self)?;
+        let mut overrides = Vec::new();
+
+        for (name, var) in vars {
+            if !name.starts_with(Config::ENV_PREFIX) || name == Profile::ENV_VAR {
+                continue;
+            }
+
+            let path = name[Config::ENV_PREFIX.len()..].to_lowercase();
+
+            let keys = if let Some(table) = value.as_table_mut() {
+                Config::set_var(table, &path, &name, &var)?
+            } else {
+                let err = Error::InvalidFormat;
+                return Err(err);
+            };
+
+            overrides.push((name, keys));
+        }
+
+        let config: Config = value.try_into()?;
+
+        // NB: the unknown fields are dropped when deserializing.
+        let check = Value::try_from(&config)?;
+
+        for (name, keys) in overrides {
+            let found = keys
+                .iter()
+                .try_fold(&check, |value, key| value.get(key))
+                .is_some();
+
+            if !found {
+                let err = Error::InvalidVariable { name };
+                return Err(err);
+            }
+        }
+
+        *self = config;
+
+        Ok(())
+    }
+
+    /// `set_var` sets the field of a table at the lowercase path of a variable,
+    /// returning the keys of the field.
+    fn set_var(table: &mut Table, path: &str, name: &str, var: &str) -> Result<Vec<String>> {
+        // NB: the keys contain underscores, so the longest matching key wins.
+        let mut keys: Vec<String> = table.keys().cloned().collect();
+        keys.sort_by(|a, b| b.len().cmp(&a.len()));
+
+        for key in keys {
+            if path == key {
+                let value = Config::parse_var(name, var, table.get(&key))?;
+                table.insert(key.clone(), value);
+                return Ok(vec![key]);
+            }
+
+            let prefix = format!("{}_", key);
+
+            if path.starts_with(&prefix) {
+                if let Some(Value::Table(ref mut inner)) = table.get_mut(&key) {
+                    let mut res = vec![key.clone()];
+                    res.extend(Config::set_var(inner, &path[prefix.len()..], name, var)?);
+                    return Ok(res);
+                }
+            }
+        }
+
+        let value = Config::parse_var(name, var, None)?;
+        table.insert(path.into(), value);
+
+        Ok(vec![path.into()])
+    }
+
+    /// `parse_var` parses the value of a variable with the type of the value
+    /// it overrides, if any. Arrays are comma-separated.
+    fn parse_var(name: &str, var: &str, prev: Option<&Value>) -> Result<Value> {
+        let invalid = || Error::InvalidVariable { name: name.into() };
+
+        match prev {
+            Some(Value::String(_)) => Ok(Value::String(var.into())),
+            Some(Value::Integer(_)) => var.parse().map(Value::Integer).map_err(|_| invalid()),
+            Some(Value::Float(_)) => var.parse().map(Value::Float).map_err(|_| invalid()),
+            Some(Value::Boolean(_)) => var.parse().map(Value::Boolean).map_err(|_| invalid()),
+            Some(Value::Array(ref items)) => {
+                let mut values = Vec::new();
+
+                for item in var.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                    values.push(Config::parse_var(name, item, items.first())?);
+                }
+
+                Ok(Value::Array(values))
+            }
+            Some(_) => Err(invalid()),
+            None => {
+                // NB: a missing value is parsed as a TOML literal, falling back to a string.
+                let literal = format!("value = {}", var);
+
+                match toml::from_str::<Table>(&literal) {
+                    Ok(mut table) => Ok(table
+                        .remove("value")
+                        .unwrap_or_else(|| Value::String(var.into()))),
+                    Err(_) => Ok(Value::String(var.into())),
+                }
+            }
+        }
+    }
+
     /// `to_bytes` converts the `Config` into a CBOR binary.
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
         serde_cbor::to_vec(self).map_err(|e| e.into())
@@ -106,6 +266,8 @@ impl Default for Config {
         let network = NetworkConfig::default();
         let log = LogConfig::default();
         let consensus = ConsensusConfig::default();
+        let genesis = GenesisConfig::default();
+        let notifier = NotifierConfig::default();
 
         Config {
             store,
@@ -113,6 +275,8 @@ impl Default for Config {
             network,
             log,
             consensus,
+            genesis,
+            notifier,
         }
     }
 }
@@ -128,6 +292,8 @@ fn test_config_new() {
     let net_conf = NetworkConfig::default();
     let log_conf = LogConfig::default();
     let cons_conf = ConsensusConfig::default();
+    let genesis_conf = GenesisConfig::default();
+    let notifier_conf = NotifierConfig::default();
 
     let mut invalid_store_conf = store_conf.clone();
     invalid_store_conf.kind = Some(invalid_kind.into());
@@ -141,7 +307,21 @@ fn test_config_new() {
     let mut invalid_cons_conf = cons_conf.clone();
     invalid_cons_conf.s_cost = Some(invalid_s_cost);
 
-    let res = Config::new(&store_conf, &pool_conf, &net_conf, &log_conf, &cons_conf);
+    let mut invalid_genesis_conf = genesis_conf.clone();
+    invalid_genesis_conf.public_key = Some(invalid_kind.into());
+
+    let mut invalid_notifier_conf = notifier_conf.clone();
+    invalid_notifier_conf.max_attempts = Some(0);
+
+    let res = Config::new(
+        &store_conf,
+        &pool_conf,
+        &net_conf,
+        &log_conf,
+        &cons_conf,
+        &genesis_conf,
+        &notifier_conf,
+    );
     assert!(res.is_ok());
 
     let res = Config::new(
@@ -150,6 +330,8 @@ fn test_config_new() {
         &net_conf,
         &log_conf,
         &cons_conf,
+        &genesis_conf,
+        &notifier_conf,
     );
     assert!(res.is_err());
 
@@ -159,6 +341,8 @@ fn test_config_new() {
         &invalid_net_conf,
         &log_conf,
         &cons_conf,
+        &genesis_conf,
+        &notifier_conf,
     );
     assert!(res.is_err());
 
@@ -168,6 +352,8 @@ fn test_config_new() {
         &net_conf,
         &invalid_log_conf,
         &cons_conf,
+        &genesis_conf,
+        &notifier_conf,
     );
     assert!(res.is_err());
 
@@ -177,6 +363,30 @@ fn test_config_new() {
         &net_conf,
         &log_conf,
         &invalid_cons_conf,
+        &genesis_conf,
+        &notifier_conf,
+    );
+    assert!(res.is_err());
+
+    let res = Config::new(
+        &store_conf,
+        &pool_conf,
+        &net_conf,
+        &log_conf,
+        &cons_conf,
+        &invalid_genesis_conf,
+        &notifier_conf,
+    );
+    assert!(res.is_err());
+
+    let res = Config::new(
+        &store_conf,
+        &pool_conf,
+        &net_conf,
+        &log_conf,
+        &cons_conf,
+        &genesis_conf,
+        &invalid_notifier_conf,
     );
     assert!(res.is_err());
 }
@@ -233,6 +443,69 @@ fn test_config_validate() {
     config.consensus = invalid_cons_conf;
     let res = config.validate();
     assert!(res.is_err());
+
+    config.consensus = ConsensusConfig::default();
+
+    config.notifier.urls = Some(vec!["ftp://localhost/".into()]);
+    let res = config.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_config_apply_env() {
+    let toml = Config::default().to_toml().unwrap();
+
+    let vars = vec![
+        ("PATH".to_string(), "/bin".to_string()),
+        (Profile::ENV_VAR.to_string(), "production".to_string()),
+        (
+            "ALSACOIN_NETWORK_CONSENSUS_ADDRESS".to_string(),
+            "127.0.0.1:3000".to_string(),
+        ),
+        ("ALSACOIN_CONSENSUS_K".to_string(), "20".to_string()),
+        ("ALSACOIN_CONSENSUS_ARCHIVE".to_string(), "true".to_string()),
+        ("ALSACOIN_STORE_MAX_AGE".to_string(), "3600".to_string()),
+        (
+            "ALSACOIN_STORE_PATH".to_string(),
+            "/var/alsacoin/store".to_string(),
+        ),
+        (
+            "ALSACOIN_NOTIFIER_URLS".to_string(),
+            "http://localhost/a, http://localhost/b".to_string(),
+        ),
+    ];
+
+    let res = Config::load_with(&toml, vars);
+    assert!(res.is_ok());
+    let config = res.unwrap();
+
+    assert_eq!(
+        config.network.consensus_address,
+        Some("127.0.0.1:3000".into())
+    );
+    assert_eq!(config.consensus.k, Some(20));
+    assert_eq!(config.consensus.archive, Some(true));
+    assert_eq!(config.store.max_age, Some(3600));
+    assert_eq!(config.store.path, Some("/var/alsacoin/store".into()));
+    assert_eq!(
+        config.notifier.urls,
+        Some(vec![
+            "http://localhost/a".into(),
+            "http://localhost/b".into()
+        ])
+    );
+
+    let vars = vec![("ALSACOIN_CONSENSUS_K".to_string(), "many".to_string())];
+    let res = Config::load_with(&toml, vars);
+    assert!(res.is_err());
+
+    let vars = vec![("ALSACOIN_STORE_UNKNOWN".to_string(), "1".to_string())];
+    let res = Config::load_with(&toml, vars);
+    assert!(res.is_err());
+
+    let vars = vec![("ALSACOIN_CONSENSUS_S_COST".to_string(), "0".to_string())];
+    let res = Config::load_with(&toml, vars);
+    assert!(res.is_err());
 }
 
 #[test]
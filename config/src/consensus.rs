@@ -2,7 +2,11 @@
 //!
 //! `consensus` is the module containing the consensus configuration type and functions.
 
+use crate::error::Error;
+use crate::preset::ConsensusPreset;
 use crate::result::Result;
+use crate::retry::RetryPolicy;
+use crypto::ecc::ed25519::PublicKey;
 use crypto::hash::balloon::BalloonParams;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
@@ -23,6 +27,53 @@ pub struct ConsensusConfig {
     pub max_retries: Option<u32>,
     pub timeout: Option<u64>,
     pub store_messages: Option<bool>,
+    pub target_mining_time: Option<u64>,
+    pub retarget_window: Option<u32>,
+    pub max_retarget_step: Option<u64>,
+    pub priority_policy: Option<Vec<String>>,
+    pub replay_window_size: Option<u32>,
+    pub replay_window_ttl: Option<u64>,
+    pub message_ttl: Option<u64>,
+    #[serde(default)]
+    pub authority_key: Option<String>,
+    #[serde(default)]
+    pub archive: Option<bool>,
+    #[serde(default)]
+    pub prune_depth: Option<u64>,
+    #[serde(default)]
+    pub mining_duty_cycle: Option<u32>,
+    #[serde(default)]
+    pub mining_threads: Option<u32>,
+    #[serde(default)]
+    pub mining_max_backlog: Option<u32>,
+    #[serde(default)]
+    pub preset: Option<ConsensusPreset>,
+    #[serde(default)]
+    pub latency_bias: Option<u32>,
+    #[serde(default)]
+    pub adaptive: Option<bool>,
+    #[serde(default)]
+    pub min_k: Option<u32>,
+    #[serde(default)]
+    pub max_k: Option<u32>,
+    #[serde(default)]
+    pub min_alpha: Option<u32>,
+    #[serde(default)]
+    pub sampling: Option<String>,
+    #[serde(default)]
+    pub max_orphans: Option<u32>,
+    #[serde(default)]
+    pub max_spooled: Option<u32>,
+    #[serde(default)]
+    pub sample_services: Option<Vec<String>>,
+    #[serde(default)]
+    pub coinbase_maturity: Option<u32>,
+    #[serde(default)]
+    pub requery_after: Option<u64>,
+    #[serde(default)]
+    pub max_requeries: Option<u32>,
+    // NB: TOML tables have to follow the values.
+    pub retry_policy: Option<RetryPolicy>,
 }
 
 impl ConsensusConfig {
@@ -51,6 +102,9 @@ impl ConsensusConfig {
     /// `DEFAULT_TIMEOUT` is the default consensus parameter timeout.
     pub const DEFAULT_TIMEOUT: u64 = 180;
 
+    /// `MAX_TIMEOUT` is the maximum consensus parameter timeout, in seconds.
+    pub const MAX_TIMEOUT: u64 = 3600;
+
     /// `DEFAULT_S_COST` is the default s_cost parameter value.
     pub const DEFAULT_S_COST: u32 = BalloonParams::DEFAULT_S_COST;
 
@@ -63,6 +117,137 @@ impl ConsensusConfig {
     /// `DEFAULT_STORE_MESSAGES` is the default store_messages value.
     pub const DEFAULT_STORE_MESSAGES: bool = false;
 
+    /// `DEFAULT_TARGET_MINING_TIME` is the default target time in seconds
+    /// between two mined transactions.
+    pub const DEFAULT_TARGET_MINING_TIME: u64 = 60;
+
+    /// `DEFAULT_RETARGET_WINDOW` is the default number of mined transactions
+    /// observed when retargeting the mining difficulty.
+    pub const DEFAULT_RETARGET_WINDOW: u32 = 16;
+
+    /// `DEFAULT_MAX_RETARGET_STEP` is the default maximum change of the mining
+    /// difficulty bits in a single retarget.
+    pub const DEFAULT_MAX_RETARGET_STEP: u64 = 4;
+
+    /// `VALID_PRIORITY_CRITERIA` sets the valid criteria of the priority policy.
+    pub const VALID_PRIORITY_CRITERIA: &'static [&'static str] = &["depth", "fee", "age"];
+
+    /// `DEFAULT_PRIORITY_POLICY` is the default priority policy: transactions are
+    /// queried by dependency depth first, then by higher fee, then by age.
+    pub const DEFAULT_PRIORITY_POLICY: &'static [&'static str] = &["depth", "fee", "age"];
+
+    /// `DEFAULT_REPLAY_WINDOW_SIZE` is the default maximum number of recently
+    /// seen consensus messages kept to detect replays.
+    pub const DEFAULT_REPLAY_WINDOW_SIZE: u32 = 1 << 16;
+
+    /// `DEFAULT_REPLAY_WINDOW_TTL` is the default time in seconds a seen consensus
+    /// message is kept to detect replays.
+    pub const DEFAULT_REPLAY_WINDOW_TTL: u64 = 600;
+
+    /// `DEFAULT_MESSAGE_TTL` is the default time in seconds a stored consensus
+    /// message is kept before expiring.
+    pub const DEFAULT_MESSAGE_TTL: u64 = 3600;
+
+    /// `DEFAULT_ARCHIVE` is the default archive value. An archive node never
+    /// prunes its store.
+    pub const DEFAULT_ARCHIVE: bool = false;
+
+    /// `DEFAULT_PRUNE_DEPTH` is the default distance from the deepest transaction
+    /// beyond which the transactions are pruned from the store of a node that
+    /// is not an archive.
+    pub const DEFAULT_PRUNE_DEPTH: u64 = 100_000;
+
+    /// `DEFAULT_MINING_DUTY_CYCLE` is the default percentage of the time a mining
+    /// thread spends mining, sleeping the rest of the time.
+    pub const DEFAULT_MINING_DUTY_CYCLE: u32 = 100;
+
+    /// `DEFAULT_MINING_THREADS` is the default maximum number of concurrent mining threads.
+    pub const DEFAULT_MINING_THREADS: u32 = 1;
+
+    /// `DEFAULT_MINING_MAX_BACKLOG` is the default number of queued transactions
+    /// beyond which the mining is paused, as the node falls behind on consensus.
+    pub const DEFAULT_MINING_MAX_BACKLOG: u32 = 1024;
+
+    /// `DEFAULT_LATENCY_BIAS` is the default factor of the sampled candidates to the
+    /// queried nodes, of which the ones with the lowest latency are queried.
+    /// A factor of 1 samples the queried nodes uniformly.
+    pub const DEFAULT_LATENCY_BIAS: u32 = 1;
+
+    /// `MAX_LATENCY_BIAS` is the maximum latency bias.
+    pub const MAX_LATENCY_BIAS: u32 = 8;
+
+    /// `DEFAULT_ADAPTIVE` is the default adaptive value. An adaptive node adjusts
+    /// k and alpha to the observed network size, failure rate and latency.
+    pub const DEFAULT_ADAPTIVE: bool = false;
+
+    /// `DEFAULT_MIN_K` is the default minimum k of the adaptive mode.
+    pub const DEFAULT_MIN_K: u32 = 1;
+
+    /// `DEFAULT_MAX_K` is the default maximum k of the adaptive mode.
+    pub const DEFAULT_MAX_K: u32 = 64;
+
+    /// `DEFAULT_MIN_ALPHA` is the default minimum alpha of the adaptive mode.
+    pub const DEFAULT_MIN_ALPHA: u32 = 1;
+
+    /// `VALID_SAMPLING_MODES` sets the valid node sampling modes: "uniform" samples
+    /// the queried nodes uniformly, "work" weights them by the mining work they
+    /// submitted, so that the identities created cheaply are seldom queried.
+    pub const VALID_SAMPLING_MODES: &'static [&'static str] = &["uniform", "work"];
+
+    /// `DEFAULT_SAMPLING` is the default node sampling mode.
+    pub const DEFAULT_SAMPLING: &'static str = "uniform";
+
+    /// `DEFAULT_MAX_ORPHANS` is the default maximum number of transactions with
+    /// missing ancestors kept while their ancestors are fetched.
+    pub const DEFAULT_MAX_ORPHANS: u32 = 1024;
+
+    /// `DEFAULT_MAX_SPOOLED` is the default maximum number of incoming messages
+    /// spilled to the store while the consensus loop is behind.
+    pub const DEFAULT_MAX_SPOOLED: u32 = 4096;
+
+    /// `VALID_SERVICES` sets the valid services a node can advertise.
+    pub const VALID_SERVICES: &'static [&'static str] =
+        &["mining", "archival", "relay", "rpc", "compact"];
+
+    /// `MIN_COINBASE_MATURITY` is the minimum confidence an accepted mined transaction
+    /// has to reach before its coinbase reward can be spent.
+    pub const MIN_COINBASE_MATURITY: u32 = 1;
+
+    /// `DEFAULT_COINBASE_MATURITY` is the default confidence an accepted mined transaction
+    /// has to reach before its coinbase reward can be spent.
+    pub const DEFAULT_COINBASE_MATURITY: u32 = 8;
+
+    /// `MAX_TRANSACTION_SIZE` is the maximum byte size of a CBOR encoded transaction.
+    /// It is enforced on every transaction, whatever the configuration.
+    pub const MAX_TRANSACTION_SIZE: u32 = 1 << 20;
+
+    /// `MAX_TRANSACTION_INPUTS` is the maximum number of inputs of a transaction.
+    /// It is enforced on every transaction, whatever the configuration.
+    pub const MAX_TRANSACTION_INPUTS: u32 = 1 << 12;
+
+    /// `MAX_TRANSACTION_OUTPUTS` is the maximum number of outputs of a transaction.
+    /// It is enforced on every transaction, whatever the configuration.
+    pub const MAX_TRANSACTION_OUTPUTS: u32 = 1 << 12;
+
+    /// `DEFAULT_REQUERY_AFTER` is the default number of seconds without progress
+    /// after which a transaction neither accepted nor rejected is queried again.
+    pub const DEFAULT_REQUERY_AFTER: u64 = 60;
+
+    /// `DEFAULT_MAX_REQUERIES` is the default maximum number of re-queries of a
+    /// stalled transaction before it is reported as stalled.
+    pub const DEFAULT_MAX_REQUERIES: u32 = 3;
+
+    /// `MAX_MAX_REQUERIES` is the maximum number of re-queries of a stalled transaction.
+    pub const MAX_MAX_REQUERIES: u32 = 64;
+
+    /// `default_priority_policy` returns the default priority policy.
+    pub fn default_priority_policy() -> Vec<String> {
+        Self::DEFAULT_PRIORITY_POLICY
+            .iter()
+            .map(|criterion| criterion.to_string())
+            .collect()
+    }
+
     /// `new` creates a new `ConsensusConfig`.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -77,14 +262,47 @@ impl ConsensusConfig {
         max_retries: Option<u32>,
         timeout: Option<u64>,
         store_messages: Option<bool>,
+        target_mining_time: Option<u64>,
+        retarget_window: Option<u32>,
+        max_retarget_step: Option<u64>,
+        priority_policy: Option<Vec<String>>,
+        replay_window_size: Option<u32>,
+        replay_window_ttl: Option<u64>,
+        message_ttl: Option<u64>,
+        retry_policy: Option<RetryPolicy>,
+        authority_key: Option<String>,
+        archive: Option<bool>,
+        prune_depth: Option<u64>,
+        mining_duty_cycle: Option<u32>,
+        mining_threads: Option<u32>,
+        mining_max_backlog: Option<u32>,
+        preset: Option<ConsensusPreset>,
+        latency_bias: Option<u32>,
+        adaptive: Option<bool>,
+        min_k: Option<u32>,
+        max_k: Option<u32>,
+        min_alpha: Option<u32>,
+        sampling: Option<String>,
+        max_orphans: Option<u32>,
+        max_spooled: Option<u32>,
+        sample_services: Option<Vec<String>>,
+        coinbase_maturity: Option<u32>,
+        requery_after: Option<u64>,
+        max_requeries: Option<u32>,
     ) -> Result<ConsensusConfig> {
-        let k = Some(k.unwrap_or(Self::DEFAULT_K));
+        let k = Some(k.unwrap_or_else(|| preset.map_or(Self::DEFAULT_K, ConsensusPreset::k)));
 
-        let alpha = Some(alpha.unwrap_or(Self::DEFAULT_ALPHA));
+        let alpha = Some(
+            alpha.unwrap_or_else(|| preset.map_or(Self::DEFAULT_ALPHA, ConsensusPreset::alpha)),
+        );
 
-        let beta1 = Some(beta1.unwrap_or(Self::DEFAULT_BETA1));
+        let beta1 = Some(
+            beta1.unwrap_or_else(|| preset.map_or(Self::DEFAULT_BETA1, ConsensusPreset::beta1)),
+        );
 
-        let beta2 = Some(beta2.unwrap_or(Self::DEFAULT_BETA2));
+        let beta2 = Some(
+            beta2.unwrap_or_else(|| preset.map_or(Self::DEFAULT_BETA2, ConsensusPreset::beta2)),
+        );
 
         let s_cost = s_cost.unwrap_or(Self::DEFAULT_S_COST);
 
@@ -98,10 +316,68 @@ impl ConsensusConfig {
 
         let max_retries = Some(max_retries.unwrap_or(Self::DEFAULT_MAX_RETRIES));
 
-        let timeout = Some(timeout.unwrap_or(Self::DEFAULT_TIMEOUT));
+        let timeout = Some(
+            timeout
+                .unwrap_or_else(|| preset.map_or(Self::DEFAULT_TIMEOUT, ConsensusPreset::timeout)),
+        );
 
         let store_messages = Some(store_messages.unwrap_or(Self::DEFAULT_STORE_MESSAGES));
 
+        let target_mining_time =
+            Some(target_mining_time.unwrap_or(Self::DEFAULT_TARGET_MINING_TIME));
+
+        let retarget_window = Some(retarget_window.unwrap_or(Self::DEFAULT_RETARGET_WINDOW));
+
+        let max_retarget_step = Some(max_retarget_step.unwrap_or(Self::DEFAULT_MAX_RETARGET_STEP));
+
+        let priority_policy = Some(priority_policy.unwrap_or_else(Self::default_priority_policy));
+
+        let replay_window_size =
+            Some(replay_window_size.unwrap_or(Self::DEFAULT_REPLAY_WINDOW_SIZE));
+
+        let replay_window_ttl = Some(replay_window_ttl.unwrap_or(Self::DEFAULT_REPLAY_WINDOW_TTL));
+
+        let message_ttl = Some(message_ttl.unwrap_or(Self::DEFAULT_MESSAGE_TTL));
+
+        let mut retry_policy = retry_policy.unwrap_or_default();
+        retry_policy.populate();
+        let retry_policy = Some(retry_policy);
+
+        let archive = Some(archive.unwrap_or(Self::DEFAULT_ARCHIVE));
+
+        let prune_depth = Some(prune_depth.unwrap_or(Self::DEFAULT_PRUNE_DEPTH));
+
+        let mining_duty_cycle = Some(mining_duty_cycle.unwrap_or(Self::DEFAULT_MINING_DUTY_CYCLE));
+
+        let mining_threads = Some(mining_threads.unwrap_or(Self::DEFAULT_MINING_THREADS));
+
+        let mining_max_backlog =
+            Some(mining_max_backlog.unwrap_or(Self::DEFAULT_MINING_MAX_BACKLOG));
+
+        let latency_bias = Some(latency_bias.unwrap_or(Self::DEFAULT_LATENCY_BIAS));
+
+        let adaptive = Some(adaptive.unwrap_or(Self::DEFAULT_ADAPTIVE));
+
+        let min_k = Some(min_k.unwrap_or(Self::DEFAULT_MIN_K));
+
+        let max_k = Some(max_k.unwrap_or(Self::DEFAULT_MAX_K));
+
+        let min_alpha = Some(min_alpha.unwrap_or(Self::DEFAULT_MIN_ALPHA));
+
+        let sampling = Some(sampling.unwrap_or_else(|| Self::DEFAULT_SAMPLING.into()));
+
+        let max_orphans = Some(max_orphans.unwrap_or(Self::DEFAULT_MAX_ORPHANS));
+
+        let max_spooled = Some(max_spooled.unwrap_or(Self::DEFAULT_MAX_SPOOLED));
+
+        let sample_services = Some(sample_services.unwrap_or_default());
+
+        let coinbase_maturity = Some(coinbase_maturity.unwrap_or(Self::DEFAULT_COINBASE_MATURITY));
+
+        let requery_after = Some(requery_after.unwrap_or(Self::DEFAULT_REQUERY_AFTER));
+
+        let max_requeries = Some(max_requeries.unwrap_or(Self::DEFAULT_MAX_REQUERIES));
+
         let config = ConsensusConfig {
             k,
             alpha,
@@ -114,28 +390,66 @@ impl ConsensusConfig {
             max_retries,
             timeout,
             store_messages,
+            target_mining_time,
+            retarget_window,
+            max_retarget_step,
+            priority_policy,
+            replay_window_size,
+            replay_window_ttl,
+            message_ttl,
+            retry_policy,
+            authority_key,
+            archive,
+            prune_depth,
+            mining_duty_cycle,
+            mining_threads,
+            mining_max_backlog,
+            preset,
+            latency_bias,
+            adaptive,
+            min_k,
+            max_k,
+            min_alpha,
+            sampling,
+            max_orphans,
+            max_spooled,
+            sample_services,
+            coinbase_maturity,
+            requery_after,
+            max_requeries,
         };
 
+        config.validate()?;
+
         Ok(config)
     }
 
     /// `populate` populates the `None` fields in the `ConsensusConfig` when there are
-    /// defaults.
+    /// defaults. The consensus parameters are populated from the preset, if any.
     pub fn populate(&mut self) {
         if self.k.is_none() {
-            self.k = Some(Self::DEFAULT_K);
+            self.k = Some(self.preset.map_or(Self::DEFAULT_K, ConsensusPreset::k));
         }
 
         if self.alpha.is_none() {
-            self.alpha = Some(Self::DEFAULT_ALPHA);
+            self.alpha = Some(
+                self.preset
+                    .map_or(Self::DEFAULT_ALPHA, ConsensusPreset::alpha),
+            );
         }
 
         if self.beta1.is_none() {
-            self.beta1 = Some(Self::DEFAULT_BETA1);
+            self.beta1 = Some(
+                self.preset
+                    .map_or(Self::DEFAULT_BETA1, ConsensusPreset::beta1),
+            );
         }
 
         if self.beta2.is_none() {
-            self.beta2 = Some(Self::DEFAULT_BETA2);
+            self.beta2 = Some(
+                self.preset
+                    .map_or(Self::DEFAULT_BETA2, ConsensusPreset::beta2),
+            );
         }
 
         if self.s_cost.is_none() {
@@ -159,23 +473,308 @@ impl ConsensusConfig {
         }
 
         if self.timeout.is_none() {
-            self.timeout = Some(Self::DEFAULT_TIMEOUT);
+            self.timeout = Some(
+                self.preset
+                    .map_or(Self::DEFAULT_TIMEOUT, ConsensusPreset::timeout),
+            );
         }
 
         if self.store_messages.is_none() {
             self.store_messages = Some(Self::DEFAULT_STORE_MESSAGES);
         }
+
+        if self.target_mining_time.is_none() {
+            self.target_mining_time = Some(Self::DEFAULT_TARGET_MINING_TIME);
+        }
+
+        if self.retarget_window.is_none() {
+            self.retarget_window = Some(Self::DEFAULT_RETARGET_WINDOW);
+        }
+
+        if self.max_retarget_step.is_none() {
+            self.max_retarget_step = Some(Self::DEFAULT_MAX_RETARGET_STEP);
+        }
+
+        if self.priority_policy.is_none() {
+            self.priority_policy = Some(Self::default_priority_policy());
+        }
+
+        if self.replay_window_size.is_none() {
+            self.replay_window_size = Some(Self::DEFAULT_REPLAY_WINDOW_SIZE);
+        }
+
+        if self.replay_window_ttl.is_none() {
+            self.replay_window_ttl = Some(Self::DEFAULT_REPLAY_WINDOW_TTL);
+        }
+
+        if self.message_ttl.is_none() {
+            self.message_ttl = Some(Self::DEFAULT_MESSAGE_TTL);
+        }
+
+        if let Some(ref mut retry_policy) = self.retry_policy {
+            retry_policy.populate();
+        } else {
+            self.retry_policy = Some(RetryPolicy::default());
+        }
+
+        if self.archive.is_none() {
+            self.archive = Some(Self::DEFAULT_ARCHIVE);
+        }
+
+        if self.prune_depth.is_none() {
+            self.prune_depth = Some(Self::DEFAULT_PRUNE_DEPTH);
+        }
+
+        if self.mining_duty_cycle.is_none() {
+            self.mining_duty_cycle = Some(Self::DEFAULT_MINING_DUTY_CYCLE);
+        }
+
+        if self.mining_threads.is_none() {
+            self.mining_threads = Some(Self::DEFAULT_MINING_THREADS);
+        }
+
+        if self.mining_max_backlog.is_none() {
+            self.mining_max_backlog = Some(Self::DEFAULT_MINING_MAX_BACKLOG);
+        }
+
+        if self.latency_bias.is_none() {
+            self.latency_bias = Some(Self::DEFAULT_LATENCY_BIAS);
+        }
+
+        if self.adaptive.is_none() {
+            self.adaptive = Some(Self::DEFAULT_ADAPTIVE);
+        }
+
+        if self.min_k.is_none() {
+            self.min_k = Some(Self::DEFAULT_MIN_K);
+        }
+
+        if self.max_k.is_none() {
+            self.max_k = Some(Self::DEFAULT_MAX_K);
+        }
+
+        if self.min_alpha.is_none() {
+            self.min_alpha = Some(Self::DEFAULT_MIN_ALPHA);
+        }
+
+        if self.sampling.is_none() {
+            self.sampling = Some(Self::DEFAULT_SAMPLING.into());
+        }
+
+        if self.max_orphans.is_none() {
+            self.max_orphans = Some(Self::DEFAULT_MAX_ORPHANS);
+        }
+
+        if self.max_spooled.is_none() {
+            self.max_spooled = Some(Self::DEFAULT_MAX_SPOOLED);
+        }
+
+        if self.sample_services.is_none() {
+            self.sample_services = Some(Vec::new());
+        }
+
+        if self.coinbase_maturity.is_none() {
+            self.coinbase_maturity = Some(Self::DEFAULT_COINBASE_MATURITY);
+        }
+
+        if self.requery_after.is_none() {
+            self.requery_after = Some(Self::DEFAULT_REQUERY_AFTER);
+        }
+
+        if self.max_requeries.is_none() {
+            self.max_requeries = Some(Self::DEFAULT_MAX_REQUERIES);
+        }
+    }
+
+    /// `apply_preset` sets the preset of the `ConsensusConfig`, overriding its
+    /// consensus parameters with the ones of the preset.
+    pub fn apply_preset(&mut self, preset: ConsensusPreset) {
+        self.preset = Some(preset);
+        self.k = Some(preset.k());
+        self.alpha = Some(preset.alpha());
+        self.beta1 = Some(preset.beta1());
+        self.beta2 = Some(preset.beta2());
+        self.timeout = Some(preset.timeout());
+    }
+
+    /// `check_params` checks the interdependencies of a set of consensus parameters:
+    /// alpha must be between 1 and k, beta1 between 1 and beta2, and the timeout
+    /// between 1 and `MAX_TIMEOUT` seconds.
+    pub fn check_params(k: u32, alpha: u32, beta1: u32, beta2: u32, timeout: u64) -> Result<()> {
+        let msg = if k == 0 {
+            "k must be positive".to_string()
+        } else if alpha == 0 || alpha > k {
+            format!("alpha ({}) must be between 1 and k ({})", alpha, k)
+        } else if beta1 == 0 || beta1 > beta2 {
+            format!("beta1 ({}) must be between 1 and beta2 ({})", beta1, beta2)
+        } else if timeout == 0 || timeout > Self::MAX_TIMEOUT {
+            format!(
+                "timeout ({}) must be between 1 and {} seconds",
+                timeout,
+                Self::MAX_TIMEOUT
+            )
+        } else {
+            return Ok(());
+        };
+
+        let err = Error::InvalidParams { msg };
+        Err(err)
+    }
+
+    /// `check_schedule` checks the mining difficulty retargeting schedule: the
+    /// target mining time and the retarget window must be positive.
+    pub fn check_schedule(target_mining_time: u64, retarget_window: u32) -> Result<()> {
+        if target_mining_time == 0 || retarget_window == 0 {
+            let err = Error::InvalidSchedule;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `check_adaptive_bounds` checks the bounds of the adaptive mode: min_k must
+    /// be between 1 and max_k, min_alpha between 1 and min_k, and k between
+    /// min_k and max_k.
+    pub fn check_adaptive_bounds(k: u32, min_k: u32, max_k: u32, min_alpha: u32) -> Result<()> {
+        let msg = if min_k == 0 || min_k > max_k {
+            format!("min_k ({}) must be between 1 and max_k ({})", min_k, max_k)
+        } else if min_alpha == 0 || min_alpha > min_k {
+            format!(
+                "min_alpha ({}) must be between 1 and min_k ({})",
+                min_alpha, min_k
+            )
+        } else if k < min_k || k > max_k {
+            format!("k ({}) must be between {} and {}", k, min_k, max_k)
+        } else {
+            return Ok(());
+        };
+
+        let err = Error::InvalidParams { msg };
+        Err(err)
+    }
+
+    /// `validate_params` validates the consensus parameters of the `ConsensusConfig`,
+    /// the missing ones taking the values of the preset or the defaults. The
+    /// adaptive bounds are checked only in adaptive mode.
+    pub fn validate_params(&self) -> Result<()> {
+        let mut config = self.clone();
+        config.populate();
+
+        Self::check_params(
+            config.k.unwrap(),
+            config.alpha.unwrap(),
+            config.beta1.unwrap(),
+            config.beta2.unwrap(),
+            config.timeout.unwrap(),
+        )?;
+
+        if config.adaptive.unwrap() {
+            Self::check_adaptive_bounds(
+                config.k.unwrap(),
+                config.min_k.unwrap(),
+                config.max_k.unwrap(),
+                config.min_alpha.unwrap(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// `authority_public_key` returns the `PublicKey` of the authority signing
+    /// the consensus parameters updates, if any.
+    pub fn authority_public_key(&self) -> Result<Option<PublicKey>> {
+        if let Some(ref authority_key) = self.authority_key {
+            let public_key = PublicKey::from_str(authority_key)?;
+            Ok(Some(public_key))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// `validate_authority_key` validates the `ConsensusConfig` authority key.
+    pub fn validate_authority_key(&self) -> Result<()> {
+        self.authority_public_key()?;
+
+        Ok(())
     }
 
     /// `validate` validates the `ConsensusConfig`.
     pub fn validate(&self) -> Result<()> {
+        self.validate_params()?;
+
         let s_cost = self.s_cost.unwrap_or(Self::DEFAULT_S_COST);
         let t_cost = self.t_cost.unwrap_or(Self::DEFAULT_T_COST);
         let delta = self.delta.unwrap_or(Self::DEFAULT_DELTA);
 
-        BalloonParams::new(s_cost, t_cost, delta)
-            .map_err(|e| e.into())
-            .map(|_| ())
+        BalloonParams::new(s_cost, t_cost, delta)?;
+
+        Self::check_schedule(
+            self.target_mining_time
+                .unwrap_or(Self::DEFAULT_TARGET_MINING_TIME),
+            self.retarget_window
+                .unwrap_or(Self::DEFAULT_RETARGET_WINDOW),
+        )?;
+
+        if self.replay_window_size == Some(0)
+            || self.replay_window_ttl == Some(0)
+            || self.message_ttl == Some(0)
+            || self.prune_depth == Some(0)
+            || self.mining_duty_cycle == Some(0)
+            || self.mining_duty_cycle.unwrap_or(0) > 100
+            || self.mining_threads == Some(0)
+            || self.mining_max_backlog == Some(0)
+            || self.latency_bias == Some(0)
+            || self.latency_bias.unwrap_or(0) > Self::MAX_LATENCY_BIAS
+            || self.max_orphans == Some(0)
+            || self.max_spooled == Some(0)
+            || self
+                .coinbase_maturity
+                .unwrap_or(Self::MIN_COINBASE_MATURITY)
+                < Self::MIN_COINBASE_MATURITY
+            || self.requery_after == Some(0)
+            || self.max_requeries == Some(0)
+            || self.max_requeries.unwrap_or(0) > Self::MAX_MAX_REQUERIES
+        {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        if let Some(ref retry_policy) = self.retry_policy {
+            retry_policy.validate()?;
+        }
+
+        self.validate_authority_key()?;
+
+        if let Some(ref priority_policy) = self.priority_policy {
+            for (i, criterion) in priority_policy.iter().enumerate() {
+                if !Self::VALID_PRIORITY_CRITERIA.contains(&criterion.as_str())
+                    || priority_policy[..i].contains(criterion)
+                {
+                    let err = Error::InvalidKind;
+                    return Err(err);
+                }
+            }
+        }
+
+        if let Some(ref sampling) = self.sampling {
+            if !Self::VALID_SAMPLING_MODES.contains(&sampling.as_str()) {
+                let err = Error::InvalidKind;
+                return Err(err);
+            }
+        }
+
+        if let Some(ref sample_services) = self.sample_services {
+            for (i, service) in sample_services.iter().enumerate() {
+                if !Self::VALID_SERVICES.contains(&service.as_str())
+                    || sample_services[..i].contains(service)
+                {
+                    let err = Error::InvalidKind;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
     }
 
     /// `to_bytes` converts the `ConsensusConfig` into a CBOR binary.
@@ -222,6 +821,33 @@ impl Default for ConsensusConfig {
         let max_retries = Some(ConsensusConfig::DEFAULT_MAX_RETRIES);
         let timeout = Some(ConsensusConfig::DEFAULT_TIMEOUT);
         let store_messages = Some(ConsensusConfig::DEFAULT_STORE_MESSAGES);
+        let target_mining_time = Some(ConsensusConfig::DEFAULT_TARGET_MINING_TIME);
+        let retarget_window = Some(ConsensusConfig::DEFAULT_RETARGET_WINDOW);
+        let max_retarget_step = Some(ConsensusConfig::DEFAULT_MAX_RETARGET_STEP);
+        let priority_policy = Some(ConsensusConfig::default_priority_policy());
+        let replay_window_size = Some(ConsensusConfig::DEFAULT_REPLAY_WINDOW_SIZE);
+        let replay_window_ttl = Some(ConsensusConfig::DEFAULT_REPLAY_WINDOW_TTL);
+        let message_ttl = Some(ConsensusConfig::DEFAULT_MESSAGE_TTL);
+        let retry_policy = Some(RetryPolicy::default());
+        let authority_key = None;
+        let archive = Some(ConsensusConfig::DEFAULT_ARCHIVE);
+        let prune_depth = Some(ConsensusConfig::DEFAULT_PRUNE_DEPTH);
+        let mining_duty_cycle = Some(ConsensusConfig::DEFAULT_MINING_DUTY_CYCLE);
+        let mining_threads = Some(ConsensusConfig::DEFAULT_MINING_THREADS);
+        let mining_max_backlog = Some(ConsensusConfig::DEFAULT_MINING_MAX_BACKLOG);
+        let preset = None;
+        let latency_bias = Some(ConsensusConfig::DEFAULT_LATENCY_BIAS);
+        let adaptive = Some(ConsensusConfig::DEFAULT_ADAPTIVE);
+        let min_k = Some(ConsensusConfig::DEFAULT_MIN_K);
+        let max_k = Some(ConsensusConfig::DEFAULT_MAX_K);
+        let min_alpha = Some(ConsensusConfig::DEFAULT_MIN_ALPHA);
+        let sampling = Some(ConsensusConfig::DEFAULT_SAMPLING.into());
+        let max_orphans = Some(ConsensusConfig::DEFAULT_MAX_ORPHANS);
+        let max_spooled = Some(ConsensusConfig::DEFAULT_MAX_SPOOLED);
+        let sample_services = Some(Vec::new());
+        let coinbase_maturity = Some(ConsensusConfig::DEFAULT_COINBASE_MATURITY);
+        let requery_after = Some(ConsensusConfig::DEFAULT_REQUERY_AFTER);
+        let max_requeries = Some(ConsensusConfig::DEFAULT_MAX_REQUERIES);
 
         ConsensusConfig {
             k,
@@ -235,6 +861,33 @@ impl Default for ConsensusConfig {
             max_retries,
             timeout,
             store_messages,
+            target_mining_time,
+            retarget_window,
+            max_retarget_step,
+            priority_policy,
+            replay_window_size,
+            replay_window_ttl,
+            message_ttl,
+            retry_policy,
+            authority_key,
+            archive,
+            prune_depth,
+            mining_duty_cycle,
+            mining_threads,
+            mining_max_backlog,
+            preset,
+            latency_bias,
+            adaptive,
+            min_k,
+            max_k,
+            min_alpha,
+            sampling,
+            max_orphans,
+            max_spooled,
+            sample_services,
+            coinbase_maturity,
+            requery_after,
+            max_requeries,
         }
     }
 }
@@ -260,6 +913,33 @@ fn test_consensus_new() {
         None,
         None,
         None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
     );
     assert!(res.is_err());
 
@@ -275,6 +955,33 @@ fn test_consensus_new() {
         None,
         None,
         None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
     );
     assert!(res.is_err());
 
@@ -290,6 +997,33 @@ fn test_consensus_new() {
         None,
         None,
         None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
     );
     assert!(res.is_err());
 
@@ -305,18 +1039,131 @@ fn test_consensus_new() {
         None,
         None,
         None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
     );
     assert!(res.is_ok());
-}
 
-#[test]
+    let res = ConsensusConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(0),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = ConsensusConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(0),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
 fn test_consensus_validate() {
     let invalid_s_cost = 0;
     let invalid_t_cost = 0;
     let invalid_delta = 0;
 
     let mut config = ConsensusConfig::new(
-        None, None, None, None, None, None, None, None, None, None, None,
+        None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
+        None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
+        None, None, None, None, None, None, None, None,
     )
     .unwrap();
 
@@ -348,6 +1195,309 @@ fn test_consensus_validate() {
 
     let res = config.validate();
     assert!(res.is_err());
+
+    config.delta = None;
+    config.populate();
+
+    config.target_mining_time = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.target_mining_time = None;
+    config.populate();
+
+    config.retarget_window = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.retarget_window = None;
+    config.populate();
+
+    config.replay_window_size = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.replay_window_size = None;
+    config.populate();
+
+    config.replay_window_ttl = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.replay_window_ttl = None;
+    config.populate();
+
+    config.message_ttl = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.message_ttl = None;
+    config.populate();
+
+    config.prune_depth = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.prune_depth = None;
+    config.populate();
+
+    config.mining_duty_cycle = Some(101);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.mining_duty_cycle = None;
+    config.populate();
+
+    config.mining_threads = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.mining_threads = None;
+    config.populate();
+
+    config.latency_bias = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.latency_bias = Some(ConsensusConfig::MAX_LATENCY_BIAS + 1);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.latency_bias = None;
+    config.populate();
+
+    config.max_orphans = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.max_orphans = None;
+    config.populate();
+
+    config.max_spooled = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.max_spooled = None;
+    config.populate();
+
+    config.coinbase_maturity = Some(ConsensusConfig::MIN_COINBASE_MATURITY - 1);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.coinbase_maturity = None;
+    config.populate();
+
+    config.requery_after = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.requery_after = None;
+    config.populate();
+
+    config.max_requeries = Some(ConsensusConfig::MAX_MAX_REQUERIES + 1);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.max_requeries = None;
+    config.populate();
+
+    config.retry_policy = Some(RetryPolicy {
+        multiplier: Some(0),
+        ..RetryPolicy::default()
+    });
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.retry_policy = None;
+    config.populate();
+
+    config.priority_policy = Some(vec!["fee".into(), "size".into()]);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.priority_policy = Some(vec!["fee".into(), "fee".into()]);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.priority_policy = Some(vec!["age".into()]);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.sampling = Some("stake".into());
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.sampling = Some("work".into());
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.sample_services = Some(vec!["relay".into(), "staking".into()]);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.sample_services = Some(vec!["relay".into(), "relay".into()]);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.sample_services = Some(vec!["relay".into(), "archival".into()]);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.authority_key = Some("invalid".into());
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    let public_key = PublicKey::random().unwrap();
+    config.authority_key = Some(public_key.to_string());
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    let res = config.authority_public_key();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Some(public_key));
+}
+
+#[test]
+fn test_consensus_validate_params() {
+    let mut config = ConsensusConfig::default();
+
+    let res = config.validate_params();
+    assert!(res.is_ok());
+
+    config.k = Some(5);
+    config.alpha = Some(6);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.alpha = Some(4);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.beta1 = Some(10);
+    config.beta2 = Some(5);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.beta2 = Some(10);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.timeout = Some(0);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.timeout = Some(ConsensusConfig::MAX_TIMEOUT + 1);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.timeout = Some(ConsensusConfig::MAX_TIMEOUT);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.adaptive = Some(true);
+    config.min_k = Some(6);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.min_k = Some(2);
+    config.min_alpha = Some(3);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.min_alpha = Some(2);
+    config.max_k = Some(4);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.max_k = Some(ConsensusConfig::DEFAULT_MAX_K);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_consensus_preset() {
+    let preset = ConsensusPreset::Secure;
+
+    let mut config = ConsensusConfig {
+        k: None,
+        alpha: None,
+        beta1: None,
+        beta2: None,
+        timeout: None,
+        preset: Some(preset),
+        ..ConsensusConfig::default()
+    };
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.k = Some(ConsensusConfig::DEFAULT_K);
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.k = None;
+    config.populate();
+
+    assert_eq!(config.k, Some(preset.k()));
+    assert_eq!(config.alpha, Some(preset.alpha()));
+    assert_eq!(config.beta1, Some(preset.beta1()));
+    assert_eq!(config.beta2, Some(preset.beta2()));
+    assert_eq!(config.timeout, Some(preset.timeout()));
+
+    let mut config = ConsensusConfig::default();
+    config.apply_preset(ConsensusPreset::Fast);
+
+    assert_eq!(config.preset, Some(ConsensusPreset::Fast));
+    assert_eq!(config.k, Some(ConsensusPreset::Fast.k()));
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    let res = config.to_toml();
+    assert!(res.is_ok());
+    let toml = res.unwrap();
+
+    let res = ConsensusConfig::from_toml(&toml);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), config);
 }
 
 #[test]
@@ -33,6 +33,22 @@ pub enum Error {
     InvalidAddress,
     #[fail(display = "Invalid format")]
     InvalidFormat,
+    #[fail(display = "Invalid schedule")]
+    InvalidSchedule,
+    #[fail(display = "Invalid compression")]
+    InvalidCompression,
+    #[fail(display = "Invalid codec")]
+    InvalidCodec,
+    #[fail(display = "Invalid admin token")]
+    InvalidAdminToken,
+    #[fail(display = "Out of bound")]
+    OutOfBound,
+    #[fail(display = "Invalid profile")]
+    InvalidProfile,
+    #[fail(display = "Invalid consensus params: {}", msg)]
+    InvalidParams { msg: String },
+    #[fail(display = "Invalid variable: {}", name)]
+    InvalidVariable { name: String },
 }
 
 impl From<io::Error> for Error {
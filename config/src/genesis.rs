@@ -0,0 +1,155 @@
+//! # Genesis Config
+//!
+//! `genesis` is the module containing the genesis configuration type and functions.
+
+use crate::result::Result;
+use crypto::ecc::ed25519::PublicKey;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use toml;
+
+/// `GenesisConfig` is the type representing a genesis configuration.
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    pub path: Option<String>,
+    pub public_key: Option<String>,
+}
+
+impl GenesisConfig {
+    /// `new` creates a new `GenesisConfig`.
+    pub fn new(path: Option<String>, public_key: Option<String>) -> Result<GenesisConfig> {
+        if let Some(ref public_key) = public_key {
+            PublicKey::from_str(public_key)?;
+        }
+
+        let config = GenesisConfig { path, public_key };
+
+        Ok(config)
+    }
+
+    /// `populate` populates the `None` fields in the `GenesisConfig` when there are
+    /// defaults.
+    pub fn populate(&mut self) {}
+
+    /// `validate` validates the `GenesisConfig`.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ref public_key) = self.public_key {
+            PublicKey::from_str(public_key)?;
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `GenesisConfig` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `GenesisConfig`.
+    pub fn from_bytes(b: &[u8]) -> Result<GenesisConfig> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `GenesisConfig` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `GenesisConfig`.
+    pub fn from_json(s: &str) -> Result<GenesisConfig> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+
+    /// `to_toml` converts the `GenesisConfig` into a TOML string.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_toml` converts a TOML string into an `GenesisConfig`.
+    pub fn from_toml(s: &str) -> Result<GenesisConfig> {
+        toml::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_genesis_new() {
+    use crypto::ecc::ed25519::SecretKey;
+
+    let invalid_public_key = "public_key";
+    let valid_public_key = SecretKey::random().unwrap().to_public().to_string();
+
+    let res = GenesisConfig::new(None, Some(invalid_public_key.into()));
+    assert!(res.is_err());
+
+    let res = GenesisConfig::new(None, Some(valid_public_key));
+    assert!(res.is_ok());
+
+    let res = GenesisConfig::new(Some("genesis.json".into()), None);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_genesis_validate() {
+    use crypto::ecc::ed25519::SecretKey;
+
+    let mut config = GenesisConfig::default();
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.public_key = Some("public_key".into());
+
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.public_key = Some(SecretKey::random().unwrap().to_public().to_string());
+
+    let res = config.validate();
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_genesis_serialize_bytes() {
+    let config_a = GenesisConfig::default();
+
+    let res = config_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = GenesisConfig::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let config_b = res.unwrap();
+
+    assert_eq!(config_a, config_b)
+}
+
+#[test]
+fn test_genesis_serialize_json() {
+    let config_a = GenesisConfig::default();
+
+    let res = config_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = GenesisConfig::from_json(&json);
+    assert!(res.is_ok());
+    let config_b = res.unwrap();
+
+    assert_eq!(config_a, config_b)
+}
+
+#[test]
+fn test_genesis_serialize_toml() {
+    let config_a = GenesisConfig::default();
+
+    let res = config_a.to_toml();
+    assert!(res.is_ok());
+    let toml = res.unwrap();
+
+    let res = GenesisConfig::from_toml(&toml);
+    assert!(res.is_ok());
+    let config_b = res.unwrap();
+
+    assert_eq!(config_a, config_b)
+}
@@ -23,9 +23,24 @@ pub mod network;
 /// `log` contains the logging configuration
 pub mod log;
 
+/// `retry` contains the retry policy type and functions.
+pub mod retry;
+
+/// `notifier` contains the notifier configuration type and functions.
+pub mod notifier;
+
+/// `preset` contains the consensus preset type and functions.
+pub mod preset;
+
 /// `consensus` contains the consensus configuration type and functions.
 pub mod consensus;
 
+/// `genesis` contains the genesis configuration type and functions.
+pub mod genesis;
+
+/// `profile` contains the configuration profile type and functions.
+pub mod profile;
+
 /// `config` contains the  configuration type and functions.
 pub mod config;
 
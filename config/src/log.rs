@@ -7,6 +7,7 @@ use crate::result::Result;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
+use std::collections::BTreeMap;
 use toml;
 
 /// `LogConfig` is the type representing a log configuration.
@@ -16,11 +17,18 @@ pub struct LogConfig {
     pub format: Option<String>,
     pub file: Option<String>,
     pub color: Option<bool>,
+    #[serde(default)]
+    pub asynchronous: Option<bool>,
+    #[serde(default)]
+    pub buffer_size: Option<u32>,
+    // NB: TOML tables have to follow the values.
+    #[serde(default)]
+    pub modules: Option<BTreeMap<String, String>>,
 }
 
 impl LogConfig {
     /// `VALID_LEVELS` sets the valid log levels.
-    pub const VALID_LEVELS: &'static [&'static str] = &["debug", "critical"];
+    pub const VALID_LEVELS: &'static [&'static str] = &["none", "critical", "info", "debug"];
 
     /// `DEFAULT_LEVEL` is the default log level.
     pub const DEFAULT_LEVEL: &'static str = "critical";
@@ -37,12 +45,26 @@ impl LogConfig {
     /// `DEFAULT_COLOR` is the default color option.
     pub const DEFAULT_COLOR: bool = false;
 
+    /// `DEFAULT_ASYNCHRONOUS` is the default asynchronous option.
+    pub const DEFAULT_ASYNCHRONOUS: bool = false;
+
+    /// `DEFAULT_BUFFER_SIZE` is the default number of log messages buffered
+    /// by an asynchronous logger.
+    pub const DEFAULT_BUFFER_SIZE: u32 = 1024;
+
+    /// `MAX_BUFFER_SIZE` is the maximum number of log messages buffered
+    /// by an asynchronous logger.
+    pub const MAX_BUFFER_SIZE: u32 = 1 << 20;
+
     /// `new` creates a new `LogConfig`.
     pub fn new(
         level: Option<String>,
         format: Option<String>,
         file: Option<String>,
         color: Option<bool>,
+        modules: Option<BTreeMap<String, String>>,
+        asynchronous: Option<bool>,
+        buffer_size: Option<u32>,
     ) -> Result<LogConfig> {
         let level = if let Some(level) = level {
             if !Self::VALID_LEVELS.contains(&level.as_str()) {
@@ -70,16 +92,71 @@ impl LogConfig {
 
         let color = Some(color.unwrap_or_else(|| Self::DEFAULT_COLOR));
 
+        let modules = modules.unwrap_or_default();
+        Self::validate_modules(&modules)?;
+
+        let asynchronous = Some(asynchronous.unwrap_or(Self::DEFAULT_ASYNCHRONOUS));
+
+        let buffer_size = buffer_size.unwrap_or(Self::DEFAULT_BUFFER_SIZE);
+        Self::validate_buffer_size(buffer_size)?;
+
         let config = LogConfig {
             level: Some(level),
             format: Some(format),
             file: Some(file),
             color,
+            modules: Some(modules),
+            asynchronous,
+            buffer_size: Some(buffer_size),
         };
 
         Ok(config)
     }
 
+    /// `parse_modules` parses the per-module log levels from a comma-separated
+    /// list of `module=level` pairs, as in `protocol=debug, network=critical`.
+    pub fn parse_modules(s: &str) -> Result<BTreeMap<String, String>> {
+        let mut modules = BTreeMap::new();
+
+        for pair in s.split(',').map(|pair| pair.trim()) {
+            if pair.is_empty() {
+                continue;
+            }
+
+            let mut parts = pair.splitn(2, '=');
+            let module = parts.next().unwrap_or_default().trim();
+            let level = parts.next().unwrap_or_default().trim();
+
+            modules.insert(module.to_string(), level.to_string());
+        }
+
+        Self::validate_modules(&modules)?;
+
+        Ok(modules)
+    }
+
+    /// `validate_modules` validates the per-module log levels.
+    fn validate_modules(modules: &BTreeMap<String, String>) -> Result<()> {
+        for (module, level) in modules.iter() {
+            if module.is_empty() || !Self::VALID_LEVELS.contains(&level.as_str()) {
+                let err = Error::InvalidKind;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_buffer_size` validates the asynchronous logger buffer size.
+    fn validate_buffer_size(buffer_size: u32) -> Result<()> {
+        if buffer_size == 0 || buffer_size > Self::MAX_BUFFER_SIZE {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     /// `populate` populates the `None` fields in the `LogConfig` when there are
     /// defaults.
     pub fn populate(&mut self) {
@@ -98,6 +175,18 @@ impl LogConfig {
         if self.color.is_none() {
             self.color = Some(Self::DEFAULT_COLOR);
         }
+
+        if self.modules.is_none() {
+            self.modules = Some(BTreeMap::new());
+        }
+
+        if self.asynchronous.is_none() {
+            self.asynchronous = Some(Self::DEFAULT_ASYNCHRONOUS);
+        }
+
+        if self.buffer_size.is_none() {
+            self.buffer_size = Some(Self::DEFAULT_BUFFER_SIZE);
+        }
     }
 
     /// `validate` validates the `LogConfig`.
@@ -116,6 +205,14 @@ impl LogConfig {
             }
         }
 
+        if let Some(ref modules) = self.modules {
+            Self::validate_modules(modules)?;
+        }
+
+        if let Some(buffer_size) = self.buffer_size {
+            Self::validate_buffer_size(buffer_size)?;
+        }
+
         Ok(())
     }
 
@@ -156,12 +253,18 @@ impl Default for LogConfig {
         let format = Some(LogConfig::DEFAULT_FORMAT.into());
         let file = Some(LogConfig::DEFAULT_FILE.into());
         let color = Some(LogConfig::DEFAULT_COLOR);
+        let modules = Some(BTreeMap::new());
+        let asynchronous = Some(LogConfig::DEFAULT_ASYNCHRONOUS);
+        let buffer_size = Some(LogConfig::DEFAULT_BUFFER_SIZE);
 
         LogConfig {
             level,
             format,
             file,
             color,
+            modules,
+            asynchronous,
+            buffer_size,
         }
     }
 }
@@ -171,10 +274,29 @@ fn test_log_new() {
     let invalid_level: String = "level".into();
     let invalid_format: String = "format".into();
 
-    let res = LogConfig::new(Some(invalid_level.into()), None, None, None);
+    let res = LogConfig::new(
+        Some(invalid_level.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = LogConfig::new(
+        None,
+        Some(invalid_format.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
     assert!(res.is_err());
 
-    let res = LogConfig::new(None, Some(invalid_format.into()), None, None);
+    let res = LogConfig::new(None, None, None, None, None, None, Some(0));
     assert!(res.is_err());
 
     for level in LogConfig::VALID_LEVELS.iter().copied() {
@@ -185,6 +307,9 @@ fn test_log_new() {
                     Some(format.into()),
                     Some(file.into()),
                     None,
+                    None,
+                    None,
+                    None,
                 );
 
                 assert!(res.is_ok());
@@ -223,6 +348,42 @@ fn test_log_validate() {
     assert!(res.is_err());
 }
 
+#[test]
+fn test_log_modules() {
+    let res = LogConfig::parse_modules("protocol=debug, network=critical");
+    assert!(res.is_ok());
+
+    let modules = res.unwrap();
+    assert_eq!(modules.get("protocol"), Some(&"debug".to_string()));
+    assert_eq!(modules.get("network"), Some(&"critical".to_string()));
+
+    let res = LogConfig::parse_modules("");
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_empty());
+
+    let invalid = ["protocol", "protocol=", "=debug", "protocol=warn"];
+
+    for s in invalid.iter() {
+        let res = LogConfig::parse_modules(s);
+        assert!(res.is_err());
+    }
+
+    let mut config = LogConfig::default();
+    config.modules = Some(modules);
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config
+        .modules
+        .as_mut()
+        .unwrap()
+        .insert("store".into(), "warn".into());
+
+    let res = config.validate();
+    assert!(res.is_err());
+}
+
 #[test]
 fn test_log_serialize_bytes() {
     let config_a = LogConfig::default();
@@ -16,6 +16,21 @@ pub struct NetworkConfig {
     pub consensus_address: Option<String>,
     pub miner_address: Option<String>,
     pub client_address: Option<String>,
+    pub advertised_addresses: Option<Vec<String>>,
+    pub proxy_address: Option<String>,
+    pub target_peers: Option<u32>,
+    #[serde(default)]
+    pub compression: Option<String>,
+    #[serde(default)]
+    pub blacklist_path: Option<String>,
+    #[serde(default)]
+    pub codec: Option<String>,
+    #[serde(default)]
+    pub listen_addresses: Option<Vec<String>>,
+    #[serde(default)]
+    pub rpc_address: Option<String>,
+    #[serde(default)]
+    pub rpc_admin_token: Option<String>,
 }
 
 impl NetworkConfig {
@@ -34,12 +49,145 @@ impl NetworkConfig {
     /// `DEFAULT_CLIENT_ADDRESS` is the default client server address.
     pub const DEFAULT_CLIENT_ADDRESS: &'static str = "127.0.0.1:2021";
 
+    /// `DEFAULT_RPC_ADDRESS` is the default gRPC server address.
+    pub const DEFAULT_RPC_ADDRESS: &'static str = "127.0.0.1:2022";
+
+    /// `MAX_ADVERTISED_ADDRESSES` is the maximum number of advertised addresses.
+    pub const MAX_ADVERTISED_ADDRESSES: usize = 16;
+
+    /// `MAX_LISTEN_ADDRESSES` is the maximum number of additional listen addresses.
+    pub const MAX_LISTEN_ADDRESSES: usize = 16;
+
+    /// `DEFAULT_TARGET_PEERS` is the default target number of outbound peers.
+    pub const DEFAULT_TARGET_PEERS: u32 = 8;
+
+    /// `VALID_COMPRESSIONS` sets the valid message compressions.
+    pub const VALID_COMPRESSIONS: &'static [&'static str] = &["none", "snappy"];
+
+    /// `DEFAULT_COMPRESSION` is the default message compression.
+    pub const DEFAULT_COMPRESSION: &'static str = "snappy";
+
+    /// `VALID_CODECS` sets the valid message codecs. Protobuf is only offered
+    /// by the gRPC interface, where the messages have a schema.
+    pub const VALID_CODECS: &'static [&'static str] = &["cbor", "msgpack"];
+
+    /// `DEFAULT_CODEC` is the default message codec.
+    pub const DEFAULT_CODEC: &'static str = "cbor";
+
+    /// `TOR_PROXY_ADDRESS` is the address of the SOCKS5 proxy of a local Tor daemon.
+    pub const TOR_PROXY_ADDRESS: &'static str = "127.0.0.1:9050";
+
+    /// `validate_address` validates a `host:port` address string. IPv6 hosts
+    /// are written in brackets, as in `[::1]:2019`.
+    pub fn validate_address(address: &str) -> Result<()> {
+        let mut parts = address.rsplitn(2, ':');
+
+        let port = parts.next().unwrap_or_default();
+        let host = parts.next().unwrap_or_default();
+
+        let port: u16 = port.parse().map_err(|_| Error::InvalidAddress)?;
+
+        if port == 0 {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        let host = if host.starts_with('[') && host.ends_with(']') {
+            &host[1..host.len() - 1]
+        } else {
+            host
+        };
+
+        if host.is_empty() || host.contains('[') || host.contains(']') {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_addresses` validates the addresses of the `NetworkConfig`.
+    fn validate_addresses(
+        consensus_address: &Option<String>,
+        miner_address: &Option<String>,
+        client_address: &Option<String>,
+        rpc_address: &Option<String>,
+        advertised_addresses: &Option<Vec<String>>,
+        listen_addresses: &Option<Vec<String>>,
+    ) -> Result<()> {
+        let same_addresses = (consensus_address.is_some() && consensus_address == miner_address)
+            || (consensus_address.is_some() && consensus_address == client_address)
+            || (miner_address.is_some() && miner_address == client_address);
+
+        if same_addresses {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        let same_rpc_address = rpc_address.is_some()
+            && (rpc_address == consensus_address
+                || rpc_address == miner_address
+                || rpc_address == client_address);
+
+        if same_rpc_address {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        for address in [
+            consensus_address,
+            miner_address,
+            client_address,
+            rpc_address,
+        ]
+        .iter()
+        {
+            if let Some(address) = address {
+                Self::validate_address(address)?;
+            }
+        }
+
+        if let Some(advertised_addresses) = advertised_addresses {
+            if advertised_addresses.len() > Self::MAX_ADVERTISED_ADDRESSES {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            for address in advertised_addresses {
+                Self::validate_address(address)?;
+            }
+        }
+
+        if let Some(listen_addresses) = listen_addresses {
+            if listen_addresses.len() > Self::MAX_LISTEN_ADDRESSES {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            for address in listen_addresses {
+                Self::validate_address(address)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// `new` creates a new `NetworkConfig`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         kind: Option<String>,
         consensus_address: Option<String>,
         miner_address: Option<String>,
         client_address: Option<String>,
+        advertised_addresses: Option<Vec<String>>,
+        proxy_address: Option<String>,
+        target_peers: Option<u32>,
+        compression: Option<String>,
+        blacklist_path: Option<String>,
+        codec: Option<String>,
+        listen_addresses: Option<Vec<String>>,
+        rpc_address: Option<String>,
+        rpc_admin_token: Option<String>,
     ) -> Result<NetworkConfig> {
         let kind = if let Some(kind) = kind {
             if !Self::VALID_KINDS.contains(&kind.as_str()) {
@@ -52,41 +200,54 @@ impl NetworkConfig {
             Self::DEFAULT_KIND.into()
         };
 
-        let same_addresses = consensus_address == miner_address
-            || consensus_address == client_address
-            || miner_address == client_address;
+        Self::validate_addresses(
+            &consensus_address,
+            &miner_address,
+            &client_address,
+            &rpc_address,
+            &advertised_addresses,
+            &listen_addresses,
+        )?;
+
+        if let Some(ref proxy_address) = proxy_address {
+            Self::validate_address(proxy_address)?;
+        }
 
-        let not_all_none =
-            consensus_address.is_some() || miner_address.is_some() || client_address.is_some();
+        Self::validate_target_peers(target_peers)?;
+        Self::validate_compression(&compression)?;
+        Self::validate_codec(&codec)?;
 
-        if same_addresses && not_all_none {
-            let err = Error::InvalidAddress;
-            return Err(err);
-        }
+        let consensus_address =
+            consensus_address.or_else(|| Some(Self::DEFAULT_CONSENSUS_ADDRESS.into()));
 
-        let consensus_address = if consensus_address.is_none() {
-            Some(Self::DEFAULT_CONSENSUS_ADDRESS.into())
-        } else {
-            None
-        };
+        let miner_address = miner_address.or_else(|| Some(Self::DEFAULT_MINER_ADDRESS.into()));
 
-        let miner_address = if miner_address.is_none() {
-            Some(Self::DEFAULT_MINER_ADDRESS.into())
-        } else {
-            None
-        };
+        let client_address = client_address.or_else(|| Some(Self::DEFAULT_CLIENT_ADDRESS.into()));
 
-        let client_address = if client_address.is_none() {
-            Some(Self::DEFAULT_CLIENT_ADDRESS.into())
-        } else {
-            None
-        };
+        let rpc_address = rpc_address.or_else(|| Some(Self::DEFAULT_RPC_ADDRESS.into()));
+
+        Self::validate_admin_token(&rpc_admin_token)?;
+
+        let target_peers = target_peers.or(Some(Self::DEFAULT_TARGET_PEERS));
+
+        let compression = compression.or_else(|| Some(Self::DEFAULT_COMPRESSION.into()));
+
+        let codec = codec.or_else(|| Some(Self::DEFAULT_CODEC.into()));
 
         let config = NetworkConfig {
             kind: Some(kind),
             consensus_address,
             miner_address,
             client_address,
+            advertised_addresses,
+            proxy_address,
+            target_peers,
+            compression,
+            blacklist_path,
+            codec,
+            listen_addresses,
+            rpc_address,
+            rpc_admin_token,
         };
 
         Ok(config)
@@ -110,6 +271,68 @@ impl NetworkConfig {
         if self.client_address.is_none() {
             self.client_address = Some(Self::DEFAULT_CLIENT_ADDRESS.into());
         }
+
+        if self.rpc_address.is_none() {
+            self.rpc_address = Some(Self::DEFAULT_RPC_ADDRESS.into());
+        }
+
+        if self.target_peers.is_none() {
+            self.target_peers = Some(Self::DEFAULT_TARGET_PEERS);
+        }
+
+        if self.compression.is_none() {
+            self.compression = Some(Self::DEFAULT_COMPRESSION.into());
+        }
+
+        if self.codec.is_none() {
+            self.codec = Some(Self::DEFAULT_CODEC.into());
+        }
+    }
+
+    /// `validate_target_peers` validates the target number of outbound peers.
+    fn validate_target_peers(target_peers: Option<u32>) -> Result<()> {
+        if target_peers == Some(0) {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_compression` validates the message compression.
+    fn validate_compression(compression: &Option<String>) -> Result<()> {
+        if let Some(ref compression) = compression {
+            if !Self::VALID_COMPRESSIONS.contains(&compression.as_str()) {
+                let err = Error::InvalidCompression;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_codec` validates the message codec.
+    fn validate_codec(codec: &Option<String>) -> Result<()> {
+        if let Some(ref codec) = codec {
+            if !Self::VALID_CODECS.contains(&codec.as_str()) {
+                let err = Error::InvalidCodec;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_admin_token` validates the gRPC admin token.
+    fn validate_admin_token(token: &Option<String>) -> Result<()> {
+        if let Some(ref token) = token {
+            if token.trim().is_empty() {
+                let err = Error::InvalidAdminToken;
+                return Err(err);
+            }
+        }
+
+        Ok(())
     }
 
     /// `validate` validates the `NetworkConfig`.
@@ -121,20 +344,23 @@ impl NetworkConfig {
             }
         }
 
-        let same_addresses = self.consensus_address == self.miner_address
-            || self.consensus_address == self.client_address
-            || self.miner_address == self.client_address;
-
-        let not_all_none = self.consensus_address.is_some()
-            || self.miner_address.is_some()
-            || self.client_address.is_some();
-
-        if same_addresses && not_all_none {
-            let err = Error::InvalidAddress;
-            return Err(err);
+        Self::validate_addresses(
+            &self.consensus_address,
+            &self.miner_address,
+            &self.client_address,
+            &self.rpc_address,
+            &self.advertised_addresses,
+            &self.listen_addresses,
+        )?;
+
+        if let Some(ref proxy_address) = self.proxy_address {
+            Self::validate_address(proxy_address)?;
         }
 
-        Ok(())
+        Self::validate_target_peers(self.target_peers)?;
+        Self::validate_compression(&self.compression)?;
+        Self::validate_codec(&self.codec)?;
+        Self::validate_admin_token(&self.rpc_admin_token)
     }
 
     /// `to_bytes` converts the `NetworkConfig` into a CBOR binary.
@@ -180,6 +406,15 @@ impl Default for NetworkConfig {
             consensus_address,
             miner_address,
             client_address,
+            advertised_addresses: None,
+            proxy_address: None,
+            target_peers: Some(NetworkConfig::DEFAULT_TARGET_PEERS),
+            compression: Some(NetworkConfig::DEFAULT_COMPRESSION.into()),
+            blacklist_path: None,
+            codec: Some(NetworkConfig::DEFAULT_CODEC.into()),
+            listen_addresses: None,
+            rpc_address: Some(NetworkConfig::DEFAULT_RPC_ADDRESS.into()),
+            rpc_admin_token: None,
         }
     }
 }
@@ -189,22 +424,336 @@ fn test_network_new() {
     let invalid_kind: String = "kind".into();
     let address = "address";
 
-    let res = NetworkConfig::new(Some(invalid_kind.into()), None, None, None);
+    let res = NetworkConfig::new(
+        Some(invalid_kind.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
     assert!(res.is_err());
 
-    let res = NetworkConfig::new(None, Some(address.into()), Some(address.into()), None);
+    let res = NetworkConfig::new(
+        None,
+        Some(address.into()),
+        Some(address.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
     assert!(res.is_err());
 
-    let res = NetworkConfig::new(None, Some(address.into()), None, Some(address.into()));
+    let res = NetworkConfig::new(
+        None,
+        Some(address.into()),
+        None,
+        Some(address.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
     assert!(res.is_err());
 
-    let res = NetworkConfig::new(None, None, Some(address.into()), Some(address.into()));
+    let res = NetworkConfig::new(
+        None,
+        None,
+        Some(address.into()),
+        Some(address.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
     assert!(res.is_err());
 
     for kind in NetworkConfig::VALID_KINDS.iter().copied() {
-        let res = NetworkConfig::new(Some(kind.into()), None, None, None);
+        let res = NetworkConfig::new(
+            Some(kind.into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert!(res.is_ok());
+    }
+
+    let consensus_address = "[::1]:2019";
+
+    let res = NetworkConfig::new(
+        None,
+        Some(consensus_address.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_ok());
+    assert_eq!(
+        res.unwrap().consensus_address,
+        Some(consensus_address.into())
+    );
+
+    let advertised_addresses = vec![
+        "[2001:db8::1]:2019".into(),
+        "node.alsacoin.org:2019".into(),
+        "127.0.0.1:2019".into(),
+    ];
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        Some(advertised_addresses),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_ok());
+
+    let listen_addresses = vec!["0.0.0.0:2019".into(), "[::]:2019".into()];
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(listen_addresses),
+        None,
+        None,
+    );
+    assert!(res.is_ok());
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(vec!["[::]".into()]),
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some("127.0.0.1".into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(NetworkConfig::TOR_PROXY_ADDRESS.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_ok());
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(0),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = NetworkConfig::new(
+        None, None, None, None, None, None, None, None, None, None, None, None, None,
+    );
+    assert!(res.is_ok());
+    let config = res.unwrap();
+    assert_eq!(
+        config.target_peers,
+        Some(NetworkConfig::DEFAULT_TARGET_PEERS)
+    );
+    assert_eq!(
+        config.compression,
+        Some(NetworkConfig::DEFAULT_COMPRESSION.into())
+    );
+
+    for compression in NetworkConfig::VALID_COMPRESSIONS.iter().copied() {
+        let res = NetworkConfig::new(
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(compression.into()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert!(res.is_ok());
+    }
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some("zip".into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    for codec in NetworkConfig::VALID_CODECS.iter().copied() {
+        let res = NetworkConfig::new(
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(codec.into()),
+            None,
+            None,
+            None,
+        );
+        assert!(res.is_ok());
+    }
+
+    let res = NetworkConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some("protobuf".into()),
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_network_validate_address() {
+    let valid_addresses = ["127.0.0.1:2019", "[::1]:2019", "node.alsacoin.org:2019"];
+
+    for address in valid_addresses.iter() {
+        let res = NetworkConfig::validate_address(address);
         assert!(res.is_ok());
     }
+
+    let invalid_addresses = [
+        "address",
+        "127.0.0.1",
+        "127.0.0.1:0",
+        "127.0.0.1:port",
+        ":2019",
+        "[]:2019",
+        "[::1:2019",
+    ];
+
+    for address in invalid_addresses.iter() {
+        let res = NetworkConfig::validate_address(address);
+        assert!(res.is_err());
+    }
 }
 
 #[test]
@@ -222,6 +771,58 @@ fn test_network_validate() {
     let res = config.validate();
     assert!(res.is_ok());
 
+    config.advertised_addresses = Some(vec!["127.0.0.1".into()]);
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.advertised_addresses = Some(vec!["127.0.0.1:2019".into()]);
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.rpc_address = config.client_address.clone();
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.rpc_address = Some("127.0.0.1".into());
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.rpc_address = Some(NetworkConfig::DEFAULT_RPC_ADDRESS.into());
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.rpc_admin_token = Some(" ".into());
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.rpc_admin_token = Some("token".into());
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.proxy_address = Some("127.0.0.1:0".into());
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.proxy_address = Some(NetworkConfig::TOR_PROXY_ADDRESS.into());
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.target_peers = Some(0);
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.target_peers = None;
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.codec = Some("xml".into());
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.codec = None;
+    let res = config.validate();
+    assert!(res.is_ok());
+
     config.kind = Some("".into());
     let res = config.validate();
     assert!(res.is_err());
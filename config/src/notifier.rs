@@ -0,0 +1,304 @@
+//! # Notifier Config
+//!
+//! `notifier` is the module containing the notifier configuration type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::retry::RetryPolicy;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use toml;
+
+/// `NotifierConfig` is the type representing the configuration of the notifier
+/// posting the protocol events to a set of HTTP callback URLs.
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct NotifierConfig {
+    #[serde(default)]
+    pub urls: Option<Vec<String>>,
+    #[serde(default)]
+    pub events: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    #[serde(default)]
+    pub sign: Option<bool>,
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+impl NotifierConfig {
+    /// `URL_SCHEME` is the scheme of the notifier URLs.
+    pub const URL_SCHEME: &'static str = "http://";
+
+    /// `MAX_URL_LEN` is the maximum length of a notifier URL.
+    pub const MAX_URL_LEN: usize = 256;
+
+    /// `MAX_URLS` is the maximum number of notifier URLs.
+    pub const MAX_URLS: usize = 16;
+
+    /// `VALID_EVENTS` sets the events that can be notified.
+    pub const VALID_EVENTS: &'static [&'static str] = &["accepted", "conflict", "error", "stalled"];
+
+    /// `DEFAULT_MAX_ATTEMPTS` is the default number of attempts of a notification.
+    pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+    /// `MAX_MAX_ATTEMPTS` is the maximum number of attempts of a notification.
+    pub const MAX_MAX_ATTEMPTS: u32 = 16;
+
+    /// `DEFAULT_SIGN` is the default option of signing the notifications with
+    /// the node key.
+    pub const DEFAULT_SIGN: bool = true;
+
+    /// `new` creates a new `NotifierConfig`.
+    pub fn new(
+        urls: Option<Vec<String>>,
+        events: Option<Vec<String>>,
+        max_attempts: Option<u32>,
+        sign: Option<bool>,
+        retry_policy: Option<RetryPolicy>,
+    ) -> Result<NotifierConfig> {
+        let urls = Some(urls.unwrap_or_default());
+
+        let events = Some(events.unwrap_or_else(Self::default_events));
+
+        let max_attempts = Some(max_attempts.unwrap_or(Self::DEFAULT_MAX_ATTEMPTS));
+
+        let sign = Some(sign.unwrap_or(Self::DEFAULT_SIGN));
+
+        let retry_policy = Some(retry_policy.unwrap_or_default());
+
+        let config = NotifierConfig {
+            urls,
+            events,
+            max_attempts,
+            sign,
+            retry_policy,
+        };
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// `default_events` returns the default notified events.
+    fn default_events() -> Vec<String> {
+        Self::VALID_EVENTS
+            .iter()
+            .map(|event| event.to_string())
+            .collect()
+    }
+
+    /// `is_enabled` returns if the `NotifierConfig` has any URL to notify.
+    pub fn is_enabled(&self) -> bool {
+        self.urls
+            .as_ref()
+            .map(|urls| !urls.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// `populate` populates the `None` fields in the `NotifierConfig` when there are
+    /// defaults.
+    pub fn populate(&mut self) {
+        if self.urls.is_none() {
+            self.urls = Some(Vec::new());
+        }
+
+        if self.events.is_none() {
+            self.events = Some(Self::default_events());
+        }
+
+        if self.max_attempts.is_none() {
+            self.max_attempts = Some(Self::DEFAULT_MAX_ATTEMPTS);
+        }
+
+        if self.sign.is_none() {
+            self.sign = Some(Self::DEFAULT_SIGN);
+        }
+
+        if let Some(ref mut retry_policy) = self.retry_policy {
+            retry_policy.populate();
+        } else {
+            self.retry_policy = Some(RetryPolicy::default());
+        }
+    }
+
+    /// `validate` validates the `NotifierConfig`.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ref urls) = self.urls {
+            if urls.len() > Self::MAX_URLS {
+                let err = Error::OutOfBound;
+                return Err(err);
+            }
+
+            for url in urls.iter() {
+                if !url.starts_with(Self::URL_SCHEME)
+                    || url.len() == Self::URL_SCHEME.len()
+                    || url.len() > Self::MAX_URL_LEN
+                {
+                    let msg = format!("invalid notifier url: {}", url);
+                    let err = Error::Parse { msg };
+                    return Err(err);
+                }
+            }
+        }
+
+        if let Some(ref events) = self.events {
+            for event in events.iter() {
+                if !Self::VALID_EVENTS.contains(&event.as_str()) {
+                    let err = Error::InvalidKind;
+                    return Err(err);
+                }
+            }
+        }
+
+        if let Some(max_attempts) = self.max_attempts {
+            if max_attempts == 0 || max_attempts > Self::MAX_MAX_ATTEMPTS {
+                let err = Error::OutOfBound;
+                return Err(err);
+            }
+        }
+
+        if let Some(ref retry_policy) = self.retry_policy {
+            retry_policy.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `NotifierConfig` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `NotifierConfig`.
+    pub fn from_bytes(b: &[u8]) -> Result<NotifierConfig> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `NotifierConfig` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `NotifierConfig`.
+    pub fn from_json(s: &str) -> Result<NotifierConfig> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+
+    /// `to_toml` converts the `NotifierConfig` into a TOML string.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_toml` converts a TOML string into an `NotifierConfig`.
+    pub fn from_toml(s: &str) -> Result<NotifierConfig> {
+        toml::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl Default for NotifierConfig {
+    fn default() -> NotifierConfig {
+        let urls = Some(Vec::new());
+        let events = Some(NotifierConfig::default_events());
+        let max_attempts = Some(NotifierConfig::DEFAULT_MAX_ATTEMPTS);
+        let sign = Some(NotifierConfig::DEFAULT_SIGN);
+        let retry_policy = Some(RetryPolicy::default());
+
+        NotifierConfig {
+            urls,
+            events,
+            max_attempts,
+            sign,
+            retry_policy,
+        }
+    }
+}
+
+#[test]
+fn test_notifier_new() {
+    let url = "http://localhost:8080/events".to_string();
+
+    let res = NotifierConfig::new(None, None, None, None, None);
+    assert!(res.is_ok());
+    assert!(!res.unwrap().is_enabled());
+
+    let res = NotifierConfig::new(Some(vec![url]), None, None, None, None);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_enabled());
+
+    let invalid_urls = ["https://localhost/", "http://", "localhost"];
+
+    for invalid_url in invalid_urls.iter() {
+        let res = NotifierConfig::new(Some(vec![invalid_url.to_string()]), None, None, None, None);
+        assert!(res.is_err());
+    }
+
+    let res = NotifierConfig::new(None, Some(vec!["rejected".into()]), None, None, None);
+    assert!(res.is_err());
+
+    let res = NotifierConfig::new(None, None, Some(0), None, None);
+    assert!(res.is_err());
+
+    let res = NotifierConfig::new(
+        None,
+        None,
+        Some(NotifierConfig::MAX_MAX_ATTEMPTS + 1),
+        None,
+        None,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_notifier_validate() {
+    let mut config = NotifierConfig::default();
+
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.urls = None;
+    config.events = None;
+    config.max_attempts = None;
+    let res = config.validate();
+    assert!(res.is_ok());
+
+    config.populate();
+    let res = config.validate();
+    assert!(res.is_ok());
+    assert_eq!(config, NotifierConfig::default());
+
+    config.urls = Some(vec![
+        "http://localhost/".into();
+        NotifierConfig::MAX_URLS + 1
+    ]);
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.urls = None;
+
+    config.events = Some(vec!["".into()]);
+    let res = config.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_notifier_serialize_toml() {
+    let config_a = NotifierConfig::new(
+        Some(vec!["http://localhost/".into()]),
+        Some(vec!["accepted".into()]),
+        None,
+        Some(false),
+        None,
+    )
+    .unwrap();
+
+    let res = config_a.to_toml();
+    assert!(res.is_ok());
+    let toml = res.unwrap();
+
+    let res = NotifierConfig::from_toml(&toml);
+    assert!(res.is_ok());
+    let config_b = res.unwrap();
+
+    assert_eq!(config_a, config_b)
+}
@@ -2,18 +2,32 @@
 //!
 //! `pool` is the module containing the pool configuration type and functions.
 
+use crate::error::Error;
 use crate::result::Result;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
+use std::collections::BTreeMap;
 use toml;
 
 /// `PoolConfig` is the type representing a pool configuration.
+/// The `stages` entries override the configuration of the pool of a stage,
+/// which is kept apart from the pools of the other stages.
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub struct PoolConfig {
     pub max_value_size: Option<u32>,
     pub max_size: Option<u32>,
     pub max_age: Option<u32>,
+    #[serde(default)]
+    pub min_fee: Option<u64>,
+    #[serde(default)]
+    pub max_tx_size: Option<u32>,
+    #[serde(default)]
+    pub max_inputs: Option<u32>,
+    #[serde(default)]
+    pub max_outputs: Option<u32>,
+    // NB: TOML tables have to follow the values.
+    pub stages: Option<BTreeMap<String, PoolConfig>>,
 }
 
 impl PoolConfig {
@@ -26,23 +40,62 @@ impl PoolConfig {
     /// `DEFAULT_MAX_AGE` is the default pool max age.
     pub const DEFAULT_MAX_AGE: u32 = 3600 * 72; // 3 days
 
+    /// `DEFAULT_MIN_FEE` is the default minimum fee of the pool transactions.
+    pub const DEFAULT_MIN_FEE: u64 = 0;
+
+    /// `DEFAULT_MAX_TX_SIZE` is the default maximum size of the pool transactions.
+    pub const DEFAULT_MAX_TX_SIZE: u32 = 1 << 20;
+
+    /// `DEFAULT_MAX_INPUTS` is the default maximum number of inputs of the pool transactions.
+    pub const DEFAULT_MAX_INPUTS: u32 = 256;
+
+    /// `DEFAULT_MAX_OUTPUTS` is the default maximum number of outputs of the pool transactions.
+    pub const DEFAULT_MAX_OUTPUTS: u32 = 256;
+
+    /// `VALID_STAGES` are the stages that can have their own pool configuration.
+    pub const VALID_STAGES: [&'static str; 3] = ["development", "testing", "production"];
+
     /// `new` creates a new `PoolConfig`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         max_value_size: Option<u32>,
         max_size: Option<u32>,
         max_age: Option<u32>,
-    ) -> PoolConfig {
+        stages: Option<BTreeMap<String, PoolConfig>>,
+        min_fee: Option<u64>,
+        max_tx_size: Option<u32>,
+        max_inputs: Option<u32>,
+        max_outputs: Option<u32>,
+    ) -> Result<PoolConfig> {
         let max_value_size = max_value_size.unwrap_or(Self::DEFAULT_MAX_VALUE_SIZE);
 
         let max_size = max_size.unwrap_or(Self::DEFAULT_MAX_SIZE);
 
         let max_age = max_age.unwrap_or(Self::DEFAULT_MAX_AGE);
 
-        PoolConfig {
+        let min_fee = min_fee.unwrap_or(Self::DEFAULT_MIN_FEE);
+
+        let max_tx_size =
+            max_tx_size.unwrap_or_else(|| Self::DEFAULT_MAX_TX_SIZE.min(max_value_size));
+
+        let max_inputs = max_inputs.unwrap_or(Self::DEFAULT_MAX_INPUTS);
+
+        let max_outputs = max_outputs.unwrap_or(Self::DEFAULT_MAX_OUTPUTS);
+
+        let config = PoolConfig {
             max_value_size: Some(max_value_size),
             max_size: Some(max_size),
             max_age: Some(max_age),
-        }
+            stages,
+            min_fee: Some(min_fee),
+            max_tx_size: Some(max_tx_size),
+            max_inputs: Some(max_inputs),
+            max_outputs: Some(max_outputs),
+        };
+
+        config.validate()?;
+
+        Ok(config)
     }
 
     /// `populate` populates the `None` fields in the `PoolConfig` when there are
@@ -59,6 +112,118 @@ impl PoolConfig {
         if self.max_age.is_none() {
             self.max_age = Some(Self::DEFAULT_MAX_AGE);
         }
+
+        if self.min_fee.is_none() {
+            self.min_fee = Some(Self::DEFAULT_MIN_FEE);
+        }
+
+        if self.max_tx_size.is_none() {
+            let max_value_size = self.max_value_size.unwrap_or(Self::DEFAULT_MAX_VALUE_SIZE);
+            self.max_tx_size = Some(Self::DEFAULT_MAX_TX_SIZE.min(max_value_size));
+        }
+
+        if self.max_inputs.is_none() {
+            self.max_inputs = Some(Self::DEFAULT_MAX_INPUTS);
+        }
+
+        if self.max_outputs.is_none() {
+            self.max_outputs = Some(Self::DEFAULT_MAX_OUTPUTS);
+        }
+    }
+
+    /// `validate_sizes` validates the sizes of the `PoolConfig`.
+    fn validate_sizes(&self) -> Result<()> {
+        if let (Some(max_value_size), Some(max_size)) = (self.max_value_size, self.max_size) {
+            if max_size < max_value_size {
+                let err = Error::OutOfBound;
+                return Err(err);
+            }
+        }
+
+        if let (Some(max_value_size), Some(max_tx_size)) = (self.max_value_size, self.max_tx_size) {
+            if max_tx_size > max_value_size {
+                let err = Error::OutOfBound;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_policy` validates the transaction acceptance limits of the `PoolConfig`.
+    fn validate_policy(&self) -> Result<()> {
+        for limit in [self.max_tx_size, self.max_inputs, self.max_outputs].iter() {
+            if *limit == Some(0) {
+                let err = Error::OutOfBound;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate` validates the `PoolConfig`.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_sizes()?;
+        self.validate_policy()?;
+
+        if let Some(ref stages) = self.stages {
+            for (stage, config) in stages.iter() {
+                if !Self::VALID_STAGES.contains(&stage.as_str()) {
+                    let err = Error::InvalidStage;
+                    return Err(err);
+                }
+
+                if config.stages.is_some() {
+                    let err = Error::InvalidFormat;
+                    return Err(err);
+                }
+
+                let stage_config = self.stage_config(stage)?;
+                stage_config.validate_sizes()?;
+                stage_config.validate_policy()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `stage_config` returns the `PoolConfig` of the pool of a stage, where
+    /// the missing stage values are taken from the `PoolConfig`.
+    pub fn stage_config(&self, stage: &str) -> Result<PoolConfig> {
+        if !Self::VALID_STAGES.contains(&stage) {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        let mut config = self
+            .stages
+            .as_ref()
+            .and_then(|stages| stages.get(stage))
+            .cloned()
+            .unwrap_or(PoolConfig {
+                max_value_size: None,
+                max_size: None,
+                max_age: None,
+                stages: None,
+                min_fee: None,
+                max_tx_size: None,
+                max_inputs: None,
+                max_outputs: None,
+            });
+
+        config.max_value_size = config.max_value_size.or(self.max_value_size);
+        config.max_size = config.max_size.or(self.max_size);
+        config.max_age = config.max_age.or(self.max_age);
+        config.min_fee = config.min_fee.or(self.min_fee);
+        config.max_tx_size = config.max_tx_size.or(self.max_tx_size);
+        config.max_inputs = config.max_inputs.or(self.max_inputs);
+        config.max_outputs = config.max_outputs.or(self.max_outputs);
+        config.stages = None;
+
+        config.populate();
+
+        Ok(config)
     }
 
     /// `to_bytes` converts the `PoolConfig` into a CBOR binary.
@@ -102,6 +267,11 @@ impl Default for PoolConfig {
             max_value_size,
             max_size,
             max_age,
+            stages: None,
+            min_fee: Some(PoolConfig::DEFAULT_MIN_FEE),
+            max_tx_size: Some(PoolConfig::DEFAULT_MAX_TX_SIZE),
+            max_inputs: Some(PoolConfig::DEFAULT_MAX_INPUTS),
+            max_outputs: Some(PoolConfig::DEFAULT_MAX_OUTPUTS),
         }
     }
 }
@@ -150,3 +320,167 @@ fn test_pool_serialize_toml() {
 
     assert_eq!(config_a, config_b)
 }
+
+#[test]
+fn test_pool_stages() {
+    let mut stages = BTreeMap::new();
+
+    let testing_config = PoolConfig {
+        max_value_size: None,
+        max_size: Some(1 << 20),
+        max_age: Some(3600),
+        stages: None,
+        min_fee: None,
+        max_tx_size: None,
+        max_inputs: None,
+        max_outputs: None,
+    };
+
+    stages.insert("testing".to_string(), testing_config);
+
+    let res = PoolConfig::new(
+        None,
+        None,
+        None,
+        Some(stages.clone()),
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = PoolConfig::new(
+        Some(1 << 10),
+        None,
+        None,
+        Some(stages.clone()),
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_ok());
+
+    let config = res.unwrap();
+
+    let res = config.stage_config("testing");
+    assert!(res.is_ok());
+
+    let testing_config = res.unwrap();
+    assert_eq!(testing_config.max_value_size, Some(1 << 10));
+    assert_eq!(testing_config.max_size, Some(1 << 20));
+    assert_eq!(testing_config.max_age, Some(3600));
+    assert!(testing_config.stages.is_none());
+
+    let res = config.stage_config("production");
+    assert!(res.is_ok());
+
+    let production_config = res.unwrap();
+    assert_eq!(
+        production_config.max_size,
+        Some(PoolConfig::DEFAULT_MAX_SIZE)
+    );
+    assert_eq!(production_config.max_age, Some(PoolConfig::DEFAULT_MAX_AGE));
+
+    let res = config.stage_config("staging");
+    assert!(res.is_err());
+
+    let mut invalid_stages = stages.clone();
+    invalid_stages.insert("staging".to_string(), PoolConfig::default());
+
+    let res = PoolConfig::new(
+        Some(1 << 10),
+        None,
+        None,
+        Some(invalid_stages),
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let mut nested_stages = stages;
+    nested_stages.insert("development".to_string(), config.clone());
+
+    let res = PoolConfig::new(
+        Some(1 << 10),
+        None,
+        None,
+        Some(nested_stages),
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = config.to_toml();
+    assert!(res.is_ok());
+
+    let res = PoolConfig::from_toml(&res.unwrap());
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), config);
+}
+
+#[test]
+fn test_pool_policy() {
+    let res = PoolConfig::new(Some(1 << 10), None, None, None, None, None, None, None);
+    assert!(res.is_ok());
+
+    let config = res.unwrap();
+    assert_eq!(config.min_fee, Some(PoolConfig::DEFAULT_MIN_FEE));
+    assert_eq!(config.max_tx_size, Some(1 << 10));
+    assert_eq!(config.max_inputs, Some(PoolConfig::DEFAULT_MAX_INPUTS));
+    assert_eq!(config.max_outputs, Some(PoolConfig::DEFAULT_MAX_OUTPUTS));
+
+    let res = PoolConfig::new(
+        Some(1 << 10),
+        None,
+        None,
+        None,
+        None,
+        Some(1 << 11),
+        None,
+        None,
+    );
+    assert!(res.is_err());
+
+    let res = PoolConfig::new(None, None, None, None, None, None, Some(0), None);
+    assert!(res.is_err());
+
+    let res = PoolConfig::new(None, None, None, None, None, None, None, Some(0));
+    assert!(res.is_err());
+
+    let mut stages = BTreeMap::new();
+
+    let testing_config = PoolConfig {
+        max_value_size: None,
+        max_size: None,
+        max_age: None,
+        stages: None,
+        min_fee: Some(100),
+        max_tx_size: None,
+        max_inputs: Some(16),
+        max_outputs: None,
+    };
+
+    stages.insert("testing".to_string(), testing_config);
+
+    let res = PoolConfig::new(None, None, None, Some(stages), Some(10), None, None, None);
+    assert!(res.is_ok());
+
+    let config = res.unwrap();
+
+    let testing_config = config.stage_config("testing").unwrap();
+    assert_eq!(testing_config.min_fee, Some(100));
+    assert_eq!(testing_config.max_inputs, Some(16));
+    assert_eq!(
+        testing_config.max_outputs,
+        Some(PoolConfig::DEFAULT_MAX_OUTPUTS)
+    );
+
+    let production_config = config.stage_config("production").unwrap();
+    assert_eq!(production_config.min_fee, Some(10));
+}
@@ -0,0 +1,116 @@
+//! # Preset
+//!
+//! `preset` is the module containing the consensus preset type and functions.
+//! A preset names a consistent set of the consensus parameters k, alpha,
+//! beta1, beta2 and timeout, trading the speed of the consensus for its safety.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// `ConsensusPreset` is the name of a set of consensus parameters.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsensusPreset {
+    /// Fast preset: small samples and thresholds, for small or test networks.
+    Fast,
+    /// Balanced preset.
+    Balanced,
+    /// Secure preset: large samples and thresholds, as in the Avalanche Consensus paper.
+    Secure,
+}
+
+impl ConsensusPreset {
+    /// `VALID_PRESETS` sets the valid preset names.
+    pub const VALID_PRESETS: &'static [&'static str] = &["fast", "balanced", "secure"];
+
+    /// `parse` parses a `ConsensusPreset` from its name.
+    pub fn parse(s: &str) -> Result<ConsensusPreset> {
+        match s {
+            "fast" => Ok(ConsensusPreset::Fast),
+            "balanced" => Ok(ConsensusPreset::Balanced),
+            "secure" => Ok(ConsensusPreset::Secure),
+            _ => {
+                let msg = format!(
+                    "unknown preset {}, expected one of {}",
+                    s,
+                    ConsensusPreset::VALID_PRESETS.join(", ")
+                );
+                let err = Error::InvalidParams { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `k` returns the consensus parameter k of the `ConsensusPreset`.
+    pub fn k(self) -> u32 {
+        match self {
+            ConsensusPreset::Fast => 10,
+            ConsensusPreset::Balanced => 20,
+            ConsensusPreset::Secure => 30,
+        }
+    }
+
+    /// `alpha` returns the consensus parameter alpha of the `ConsensusPreset`.
+    pub fn alpha(self) -> u32 {
+        match self {
+            ConsensusPreset::Fast => 7,
+            ConsensusPreset::Balanced => 14,
+            ConsensusPreset::Secure => 24,
+        }
+    }
+
+    /// `beta1` returns the consensus parameter beta1 of the `ConsensusPreset`.
+    pub fn beta1(self) -> u32 {
+        match self {
+            ConsensusPreset::Fast => 10,
+            ConsensusPreset::Balanced => 15,
+            ConsensusPreset::Secure => 30,
+        }
+    }
+
+    /// `beta2` returns the consensus parameter beta2 of the `ConsensusPreset`.
+    pub fn beta2(self) -> u32 {
+        match self {
+            ConsensusPreset::Fast => 20,
+            ConsensusPreset::Balanced => 50,
+            ConsensusPreset::Secure => 150,
+        }
+    }
+
+    /// `timeout` returns the consensus parameter timeout of the `ConsensusPreset`.
+    pub fn timeout(self) -> u64 {
+        match self {
+            ConsensusPreset::Fast => 30,
+            ConsensusPreset::Balanced => 90,
+            ConsensusPreset::Secure => 180,
+        }
+    }
+}
+
+impl fmt::Display for ConsensusPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConsensusPreset::Fast => write!(f, "fast"),
+            ConsensusPreset::Balanced => write!(f, "balanced"),
+            ConsensusPreset::Secure => write!(f, "secure"),
+        }
+    }
+}
+
+#[test]
+fn test_consensus_preset_parse() {
+    for name in ConsensusPreset::VALID_PRESETS.iter() {
+        let res = ConsensusPreset::parse(name);
+        assert!(res.is_ok());
+
+        let preset = res.unwrap();
+        assert_eq!(&preset.to_string(), name);
+        assert!(preset.alpha() <= preset.k());
+        assert!(preset.beta1() <= preset.beta2());
+    }
+
+    let res = ConsensusPreset::parse("paranoid");
+    assert!(res.is_err());
+}
@@ -0,0 +1,97 @@
+//! # Profile
+//!
+//! `profile` is the module containing the configuration profile type and functions.
+//! A profile names the set of configuration of a network stage.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+
+/// `Profile` is the name of a configuration profile.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Development profile.
+    Development,
+    /// Testing profile.
+    Testing,
+    /// Production profile.
+    Production,
+}
+
+impl Profile {
+    /// `ENV_VAR` is the environment variable selecting the profile.
+    pub const ENV_VAR: &'static str = "ALSACOIN_PROFILE";
+
+    /// `VALID_PROFILES` sets the valid profile names.
+    pub const VALID_PROFILES: &'static [&'static str] = &["development", "testing", "production"];
+
+    /// `parse` parses a `Profile` from its name.
+    pub fn parse(s: &str) -> Result<Profile> {
+        match s {
+            "development" => Ok(Profile::Development),
+            "testing" => Ok(Profile::Testing),
+            "production" => Ok(Profile::Production),
+            _ => {
+                let err = Error::InvalidProfile;
+                Err(err)
+            }
+        }
+    }
+
+    /// `from_env` returns the `Profile` selected in the environment, if any.
+    pub fn from_env() -> Result<Option<Profile>> {
+        match env::var(Profile::ENV_VAR) {
+            Ok(name) => Profile::parse(&name).map(Some),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(env::VarError::NotUnicode(_)) => {
+                let err = Error::InvalidProfile;
+                Err(err)
+            }
+        }
+    }
+
+    /// `select` returns the `Profile` with the precedence of an explicit name,
+    /// then of the environment, then of the default.
+    pub fn select(name: Option<&str>) -> Result<Profile> {
+        if let Some(name) = name {
+            return Profile::parse(name);
+        }
+
+        Profile::from_env().map(|profile| profile.unwrap_or_default())
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Profile::Development => write!(f, "development"),
+            Profile::Testing => write!(f, "testing"),
+            Profile::Production => write!(f, "production"),
+        }
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Profile {
+        Profile::Development
+    }
+}
+
+#[test]
+fn test_profile_parse() {
+    for name in Profile::VALID_PROFILES.iter() {
+        let res = Profile::parse(name);
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap().to_string(), name);
+    }
+
+    let res = Profile::parse("staging");
+    assert!(res.is_err());
+
+    let res = Profile::select(Some("production"));
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Profile::Production);
+}
@@ -0,0 +1,265 @@
+//! # Retry Policy
+//!
+//! `retry` is the module containing the retry policy type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use toml;
+
+/// `RetryPolicy` is the type representing the retry policy of the network
+/// operations: an exponential backoff with jitter, bounded by a timeout
+/// per operation.
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub base_delay: Option<u64>,
+    pub max_delay: Option<u64>,
+    pub multiplier: Option<u32>,
+    pub jitter: Option<u64>,
+    pub fetch_timeout: Option<u64>,
+    pub query_timeout: Option<u64>,
+}
+
+impl RetryPolicy {
+    /// `DEFAULT_BASE_DELAY` is the default delay in milliseconds before the first retry.
+    pub const DEFAULT_BASE_DELAY: u64 = 100;
+
+    /// `DEFAULT_MAX_DELAY` is the default maximum delay in milliseconds between two retries.
+    pub const DEFAULT_MAX_DELAY: u64 = 10_000;
+
+    /// `DEFAULT_MULTIPLIER` is the default factor the delay is multiplied by at each retry.
+    pub const DEFAULT_MULTIPLIER: u32 = 2;
+
+    /// `DEFAULT_JITTER` is the default maximum random delay in milliseconds
+    /// added to each retry delay.
+    pub const DEFAULT_JITTER: u64 = 100;
+
+    /// `DEFAULT_FETCH_TIMEOUT` is the default timeout in seconds of a fetch operation.
+    pub const DEFAULT_FETCH_TIMEOUT: u64 = 600;
+
+    /// `DEFAULT_QUERY_TIMEOUT` is the default timeout in seconds of a query operation.
+    pub const DEFAULT_QUERY_TIMEOUT: u64 = 300;
+
+    /// `new` creates a new `RetryPolicy`.
+    pub fn new(
+        base_delay: Option<u64>,
+        max_delay: Option<u64>,
+        multiplier: Option<u32>,
+        jitter: Option<u64>,
+        fetch_timeout: Option<u64>,
+        query_timeout: Option<u64>,
+    ) -> Result<RetryPolicy> {
+        let base_delay = base_delay.unwrap_or(Self::DEFAULT_BASE_DELAY);
+
+        let max_delay = max_delay.unwrap_or(Self::DEFAULT_MAX_DELAY);
+
+        let multiplier = multiplier.unwrap_or(Self::DEFAULT_MULTIPLIER);
+
+        let jitter = jitter.unwrap_or(Self::DEFAULT_JITTER);
+
+        let fetch_timeout = fetch_timeout.unwrap_or(Self::DEFAULT_FETCH_TIMEOUT);
+
+        let query_timeout = query_timeout.unwrap_or(Self::DEFAULT_QUERY_TIMEOUT);
+
+        let policy = RetryPolicy {
+            base_delay: Some(base_delay),
+            max_delay: Some(max_delay),
+            multiplier: Some(multiplier),
+            jitter: Some(jitter),
+            fetch_timeout: Some(fetch_timeout),
+            query_timeout: Some(query_timeout),
+        };
+
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    /// `populate` populates the `None` fields in the `RetryPolicy` when there are
+    /// defaults.
+    pub fn populate(&mut self) {
+        if self.base_delay.is_none() {
+            self.base_delay = Some(Self::DEFAULT_BASE_DELAY);
+        }
+
+        if self.max_delay.is_none() {
+            self.max_delay = Some(Self::DEFAULT_MAX_DELAY);
+        }
+
+        if self.multiplier.is_none() {
+            self.multiplier = Some(Self::DEFAULT_MULTIPLIER);
+        }
+
+        if self.jitter.is_none() {
+            self.jitter = Some(Self::DEFAULT_JITTER);
+        }
+
+        if self.fetch_timeout.is_none() {
+            self.fetch_timeout = Some(Self::DEFAULT_FETCH_TIMEOUT);
+        }
+
+        if self.query_timeout.is_none() {
+            self.query_timeout = Some(Self::DEFAULT_QUERY_TIMEOUT);
+        }
+    }
+
+    /// `validate` validates the `RetryPolicy`.
+    pub fn validate(&self) -> Result<()> {
+        let base_delay = self.base_delay.unwrap_or(Self::DEFAULT_BASE_DELAY);
+        let max_delay = self.max_delay.unwrap_or(Self::DEFAULT_MAX_DELAY);
+
+        if base_delay > max_delay || self.multiplier == Some(0) {
+            let err = Error::InvalidSchedule;
+            return Err(err);
+        }
+
+        if self.fetch_timeout == Some(0) || self.query_timeout == Some(0) {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `delay` returns the delay in milliseconds before a retry, without jitter.
+    /// The first retry has attempt zero.
+    pub fn delay(&self, attempt: u32) -> u64 {
+        let base_delay = self.base_delay.unwrap_or(Self::DEFAULT_BASE_DELAY);
+        let max_delay = self.max_delay.unwrap_or(Self::DEFAULT_MAX_DELAY);
+        let multiplier = self.multiplier.unwrap_or(Self::DEFAULT_MULTIPLIER);
+
+        let factor = u64::from(multiplier).saturating_pow(attempt);
+
+        base_delay.saturating_mul(factor).min(max_delay)
+    }
+
+    /// `to_bytes` converts the `RetryPolicy` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `RetryPolicy`.
+    pub fn from_bytes(b: &[u8]) -> Result<RetryPolicy> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `RetryPolicy` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `RetryPolicy`.
+    pub fn from_json(s: &str) -> Result<RetryPolicy> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+
+    /// `to_toml` converts the `RetryPolicy` into a TOML string.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_toml` converts a TOML string into an `RetryPolicy`.
+    pub fn from_toml(s: &str) -> Result<RetryPolicy> {
+        toml::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        let base_delay = Some(RetryPolicy::DEFAULT_BASE_DELAY);
+        let max_delay = Some(RetryPolicy::DEFAULT_MAX_DELAY);
+        let multiplier = Some(RetryPolicy::DEFAULT_MULTIPLIER);
+        let jitter = Some(RetryPolicy::DEFAULT_JITTER);
+        let fetch_timeout = Some(RetryPolicy::DEFAULT_FETCH_TIMEOUT);
+        let query_timeout = Some(RetryPolicy::DEFAULT_QUERY_TIMEOUT);
+
+        RetryPolicy {
+            base_delay,
+            max_delay,
+            multiplier,
+            jitter,
+            fetch_timeout,
+            query_timeout,
+        }
+    }
+}
+
+#[test]
+fn test_retry_policy_new() {
+    let res = RetryPolicy::new(Some(100), Some(10), None, None, None, None);
+    assert!(res.is_err());
+
+    let res = RetryPolicy::new(None, None, Some(0), None, None, None);
+    assert!(res.is_err());
+
+    let res = RetryPolicy::new(None, None, None, None, Some(0), None);
+    assert!(res.is_err());
+
+    let res = RetryPolicy::new(None, None, None, None, None, Some(0));
+    assert!(res.is_err());
+
+    let res = RetryPolicy::new(None, None, None, None, None, None);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_retry_policy_delay() {
+    let policy = RetryPolicy::new(Some(100), Some(1000), Some(2), None, None, None).unwrap();
+
+    assert_eq!(policy.delay(0), 100);
+    assert_eq!(policy.delay(1), 200);
+    assert_eq!(policy.delay(3), 800);
+    assert_eq!(policy.delay(4), 1000);
+    assert_eq!(policy.delay(u32::max_value()), 1000);
+
+    let policy = RetryPolicy::new(Some(100), Some(1000), Some(1), None, None, None).unwrap();
+
+    assert_eq!(policy.delay(10), 100);
+}
+
+#[test]
+fn test_retry_policy_serialize_bytes() {
+    let policy_a = RetryPolicy::default();
+
+    let res = policy_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = RetryPolicy::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let policy_b = res.unwrap();
+
+    assert_eq!(policy_a, policy_b)
+}
+
+#[test]
+fn test_retry_policy_serialize_json() {
+    let policy_a = RetryPolicy::default();
+
+    let res = policy_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = RetryPolicy::from_json(&json);
+    assert!(res.is_ok());
+    let policy_b = res.unwrap();
+
+    assert_eq!(policy_a, policy_b)
+}
+
+#[test]
+fn test_retry_policy_serialize_toml() {
+    let policy_a = RetryPolicy::default();
+
+    let res = policy_a.to_toml();
+    assert!(res.is_ok());
+    let toml = res.unwrap();
+
+    let res = RetryPolicy::from_toml(&toml);
+    assert!(res.is_ok());
+    let policy_b = res.unwrap();
+
+    assert_eq!(policy_a, policy_b)
+}
@@ -16,6 +16,14 @@ pub struct StoreConfig {
     pub max_value_size: Option<u32>,
     pub max_size: Option<u32>,
     pub max_age: Option<u32>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub cache_capacity: Option<u32>,
+    #[serde(default)]
+    pub wal: Option<bool>,
+    #[serde(default)]
+    pub wal_sync: Option<String>,
 }
 
 impl StoreConfig {
@@ -31,12 +39,30 @@ impl StoreConfig {
     /// `DEFAULT_MAX_SIZE` is the default store max_size.
     pub const DEFAULT_MAX_SIZE: u32 = 1 << 30;
 
+    /// `DEFAULT_CACHE_CAPACITY` is the default number of values kept in the store
+    /// read cache.
+    pub const DEFAULT_CACHE_CAPACITY: u32 = 1 << 12;
+
+    /// `DEFAULT_WAL` is the default of the write-ahead log of the persistent stores.
+    pub const DEFAULT_WAL: bool = true;
+
+    /// `VALID_WAL_SYNCS` sets the valid policies used to flush the write-ahead log to disk.
+    pub const VALID_WAL_SYNCS: &'static [&'static str] = &["always", "commit", "never"];
+
+    /// `DEFAULT_WAL_SYNC` is the default policy used to flush the write-ahead log to disk.
+    pub const DEFAULT_WAL_SYNC: &'static str = "commit";
+
     /// `new` creates a new `StoreConfig`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         kind: Option<String>,
         max_value_size: Option<u32>,
         max_size: Option<u32>,
         max_age: Option<u32>,
+        path: Option<String>,
+        cache_capacity: Option<u32>,
+        wal: Option<bool>,
+        wal_sync: Option<String>,
     ) -> Result<StoreConfig> {
         let kind = if let Some(kind) = kind {
             if !Self::VALID_KINDS.contains(&kind.as_str()) {
@@ -53,11 +79,30 @@ impl StoreConfig {
 
         let max_size = max_size.unwrap_or(Self::DEFAULT_MAX_SIZE);
 
+        let cache_capacity = cache_capacity.unwrap_or(Self::DEFAULT_CACHE_CAPACITY);
+
+        let wal = wal.unwrap_or(Self::DEFAULT_WAL);
+
+        let wal_sync = if let Some(wal_sync) = wal_sync {
+            if !Self::VALID_WAL_SYNCS.contains(&wal_sync.as_str()) {
+                let err = Error::InvalidKind;
+                return Err(err);
+            }
+
+            wal_sync
+        } else {
+            Self::DEFAULT_WAL_SYNC.into()
+        };
+
         let config = StoreConfig {
             kind: Some(kind),
             max_value_size: Some(max_value_size),
             max_size: Some(max_size),
             max_age,
+            path,
+            cache_capacity: Some(cache_capacity),
+            wal: Some(wal),
+            wal_sync: Some(wal_sync),
         };
 
         Ok(config)
@@ -77,6 +122,18 @@ impl StoreConfig {
         if self.max_size.is_none() {
             self.max_size = Some(Self::DEFAULT_MAX_SIZE);
         }
+
+        if self.cache_capacity.is_none() {
+            self.cache_capacity = Some(Self::DEFAULT_CACHE_CAPACITY);
+        }
+
+        if self.wal.is_none() {
+            self.wal = Some(Self::DEFAULT_WAL);
+        }
+
+        if self.wal_sync.is_none() {
+            self.wal_sync = Some(Self::DEFAULT_WAL_SYNC.into());
+        }
     }
 
     /// `validate` validates the `StoreConfig`.
@@ -88,6 +145,13 @@ impl StoreConfig {
             }
         }
 
+        if let Some(ref wal_sync) = self.wal_sync {
+            if !Self::VALID_WAL_SYNCS.contains(&wal_sync.as_str()) {
+                let err = Error::InvalidKind;
+                return Err(err);
+            }
+        }
+
         Ok(())
     }
 
@@ -128,12 +192,20 @@ impl Default for StoreConfig {
         let max_value_size = Some(StoreConfig::DEFAULT_MAX_VALUE_SIZE);
         let max_size = Some(StoreConfig::DEFAULT_MAX_SIZE);
         let max_age = None;
+        let path = None;
+        let cache_capacity = Some(StoreConfig::DEFAULT_CACHE_CAPACITY);
+        let wal = Some(StoreConfig::DEFAULT_WAL);
+        let wal_sync = Some(StoreConfig::DEFAULT_WAL_SYNC.into());
 
         StoreConfig {
             kind,
             max_value_size,
             max_size,
             max_age,
+            path,
+            cache_capacity,
+            wal,
+            wal_sync,
         }
     }
 }
@@ -142,13 +214,36 @@ impl Default for StoreConfig {
 fn test_store_new() {
     let invalid_kind: String = "kind".into();
 
-    let res = StoreConfig::new(Some(invalid_kind.into()), None, None, None);
+    let res = StoreConfig::new(
+        Some(invalid_kind.into()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
     assert!(res.is_err());
 
     for kind in StoreConfig::VALID_KINDS.iter().copied() {
-        let res = StoreConfig::new(Some(kind.into()), None, None, None);
+        let res = StoreConfig::new(Some(kind.into()), None, None, None, None, None, None, None);
         assert!(res.is_ok());
     }
+
+    let invalid_wal_sync: String = "sometimes".into();
+
+    let res = StoreConfig::new(
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(true),
+        Some(invalid_wal_sync),
+    );
+    assert!(res.is_err());
 }
 
 #[test]
@@ -159,12 +254,25 @@ fn test_store_validate() {
     assert!(res.is_ok());
 
     config.kind = None;
+    config.cache_capacity = None;
     let res = config.validate();
     assert!(res.is_ok());
 
     config.populate();
     let res = config.validate();
     assert!(res.is_ok());
+    assert_eq!(
+        config.cache_capacity,
+        Some(StoreConfig::DEFAULT_CACHE_CAPACITY)
+    );
+
+    config.wal_sync = Some("".into());
+    let res = config.validate();
+    assert!(res.is_err());
+
+    config.wal_sync = None;
+    config.populate();
+    assert_eq!(config.wal_sync, Some(StoreConfig::DEFAULT_WAL_SYNC.into()));
 
     config.kind = Some("".into());
     let res = config.validate();
@@ -0,0 +1,145 @@
+//! # Cipher
+//!
+//! `cipher` is the module containing the passphrase-based symmetric
+//! encryption types and functions.
+//! The key is derived from the passphrase with Balloon hashing, the
+//! message is encrypted and authenticated with ChaCha20-Poly1305.
+
+use crate::error::Error;
+use crate::hash::{BalloonHasher, BalloonParams, Digest};
+use crate::random::Random;
+use crate::result::Result;
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::ChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+
+/// `KEY_LEN` is the length of the ChaCha20-Poly1305 key of a `SealedBox`.
+pub const KEY_LEN: usize = 32;
+
+/// `NONCE_LEN` is the length of the nonce of a `SealedBox`.
+pub const NONCE_LEN: usize = 12;
+
+/// `TAG_LEN` is the length of the Poly1305 tag appended to the cyphertext
+/// of a `SealedBox`.
+pub const TAG_LEN: usize = 16;
+
+/// `SealedBox` is a message encrypted and authenticated with a key
+/// derived from a passphrase.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct SealedBox {
+    /// The salt used in the key derivation.
+    pub salt: Digest,
+    /// The Balloon hashing params used in the key derivation.
+    pub params: BalloonParams,
+    /// The nonce of the encryption.
+    pub nonce: Vec<u8>,
+    /// The encrypted message, followed by its authentication tag.
+    pub cyphertext: Vec<u8>,
+}
+
+impl SealedBox {
+    /// `seal` encrypts a message with a key derived from a passphrase.
+    pub fn seal(passphrase: &[u8], params: BalloonParams, msg: &[u8]) -> Result<SealedBox> {
+        params.validate()?;
+
+        // NB: the salt and the nonce are never drawn from a seeded RNG
+        let salt = Random::with_os_rng(|rng| Digest::from_rng(rng))?;
+        let nonce = Random::os_bytes(NONCE_LEN)?;
+        let cipher = SealedBox::cipher(passphrase, salt, params)?;
+
+        let cyphertext = cipher
+            .encrypt(GenericArray::from_slice(&nonce), msg)
+            .map_err(|_| Error::CypherText {
+                msg: "encryption failed".into(),
+            })?;
+
+        let sealed = SealedBox {
+            salt,
+            params,
+            nonce,
+            cyphertext,
+        };
+
+        Ok(sealed)
+    }
+
+    /// `open` authenticates and decrypts the `SealedBox` with a passphrase.
+    pub fn open(&self, passphrase: &[u8]) -> Result<Vec<u8>> {
+        self.validate()?;
+
+        let cipher = SealedBox::cipher(passphrase, self.salt, self.params)?;
+
+        cipher
+            .decrypt(GenericArray::from_slice(&self.nonce), &self.cyphertext[..])
+            .map_err(|_| Error::CypherText {
+                msg: "invalid passphrase or cyphertext".into(),
+            })
+    }
+
+    /// `cipher` returns the cipher keyed with the key derived from a passphrase.
+    fn cipher(passphrase: &[u8], salt: Digest, params: BalloonParams) -> Result<ChaCha20Poly1305> {
+        let key = BalloonHasher::new(salt, params)?.hash(passphrase)?;
+        let key = GenericArray::clone_from_slice(&key.to_bytes()[..KEY_LEN]);
+
+        Ok(ChaCha20Poly1305::new(key))
+    }
+
+    /// `validate` validates the `SealedBox`.
+    pub fn validate(&self) -> Result<()> {
+        self.params.validate()?;
+
+        if self.nonce.len() != NONCE_LEN || self.cyphertext.len() < TAG_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sealed_box_open() {
+    let params = BalloonParams::new(16, 1, 3).unwrap();
+    let passphrase = b"correct horse battery staple";
+
+    for len in &[0usize, 1, 32, 64, 65, 200] {
+        let msg = Random::bytes(*len).unwrap();
+
+        let res = SealedBox::seal(passphrase, params, &msg);
+        assert!(res.is_ok());
+        let mut sealed = res.unwrap();
+
+        assert_eq!(sealed.cyphertext.len(), msg.len() + TAG_LEN);
+
+        let res = sealed.open(passphrase);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), msg);
+
+        let res = sealed.open(b"wrong passphrase");
+        assert!(res.is_err());
+
+        sealed.cyphertext[0] ^= 1;
+
+        let res = sealed.open(passphrase);
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_sealed_box_seeded() {
+    let params = BalloonParams::new(16, 1, 3).unwrap();
+    let passphrase = b"correct horse battery staple";
+    let msg = b"message";
+
+    let guard = Random::seed(42);
+    let sealed_a = SealedBox::seal(passphrase, params, msg).unwrap();
+    drop(guard);
+
+    let _guard = Random::seed(42);
+    let sealed_b = SealedBox::seal(passphrase, params, msg).unwrap();
+
+    assert_ne!(sealed_a.salt, sealed_b.salt);
+    assert_ne!(sealed_a.nonce, sealed_b.nonce);
+    assert_ne!(sealed_a.cyphertext, sealed_b.cyphertext);
+}
@@ -7,7 +7,6 @@ use crate::error::Error;
 use crate::random::Random;
 use crate::result::Result;
 use rand_core::RngCore;
-use rand_os::OsRng;
 use serde::de;
 use serde::{Deserialize, Deserializer};
 use serde::{Serialize, Serializer};
@@ -33,13 +32,12 @@ impl Digest {
 
     /// `random` creates a random `Digest`.
     pub fn random() -> Result<Digest> {
-        let mut rng = OsRng::new()?;
-        Digest::from_rng(&mut rng)
+        Random::with_rng(|rng| Digest::from_rng(rng))
     }
 
     /// `from_rng` creates a new random `Digest`, but requires
     /// to specify a random generator.
-    pub fn from_rng<R: RngCore>(rng: &mut R) -> Result<Digest> {
+    pub fn from_rng<R: RngCore + ?Sized>(rng: &mut R) -> Result<Digest> {
         let bytes = Random::bytes_from_rng(rng, DIGEST_LEN);
         Digest::from_slice(&bytes)
     }
@@ -22,3 +22,7 @@ pub mod hash;
 /// `ecc` is the module containing the elliptic curve cryptography
 /// types and functions.
 pub mod ecc;
+
+/// `cipher` is the module containing the passphrase-based symmetric
+/// encryption types and functions.
+pub mod cipher;
@@ -1,43 +1,166 @@
 //! # Random
 //!
 //! `random` is the module containing the random functions used in Alsacoin.
+//!
+//! The functions draw from the OS entropy, unless the current thread has been
+//! seeded with `Random::seed`: in that case they draw from a `SeededRng`, so that
+//! simulations, tests and fuzzing runs can be reproduced, until the returned
+//! `SeedGuard` is dropped. The keys, nonces and salts are always generated from
+//! the OS entropy.
 
 use crate::error::Error;
 use crate::result::Result;
-use rand_core::RngCore;
+use rand_core::{self, impls, RngCore};
 use rand_os::OsRng;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::result;
+
+thread_local! {
+    static SEEDED_RNG: RefCell<Option<SeededRng>> = RefCell::new(None);
+}
+
+/// `SeededRng` is a deterministic, non-cryptographic RNG (xoshiro256**), whose
+/// state is expanded from a `u64` seed with splitmix64.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SeededRng {
+    state: [u64; 4],
+}
+
+impl SeededRng {
+    /// `new` creates a new `SeededRng` from a seed.
+    pub fn new(seed: u64) -> SeededRng {
+        let mut x = seed;
+        let mut state = [0u64; 4];
+
+        for word in state.iter_mut() {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+
+        SeededRng { state }
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let res = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        res
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> result::Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// `SeedGuard` keeps the RNG of the current thread seeded. When dropped, it
+/// restores the RNG the thread had before being seeded, by default the OS entropy.
+#[must_use]
+#[derive(Debug)]
+pub struct SeedGuard {
+    previous: Option<SeededRng>,
+    // NB: the guard restores a thread local, so it cannot be sent to another thread
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for SeedGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SEEDED_RNG.with(|rng| *rng.borrow_mut() = previous);
+    }
+}
 
 /// `Random` is the type implemeting random functions.
 pub struct Random;
 
 impl Random {
+    /// `seed` seeds the RNG of the current thread, making the following random
+    /// values of the thread deterministic until the returned `SeedGuard` is dropped.
+    /// Spawned threads are not seeded.
+    pub fn seed(seed: u64) -> SeedGuard {
+        let previous = SEEDED_RNG.with(|rng| rng.borrow_mut().replace(SeededRng::new(seed)));
+
+        SeedGuard {
+            previous,
+            _thread: PhantomData,
+        }
+    }
+
+    /// `is_seeded` returns if the RNG of the current thread is seeded.
+    pub fn is_seeded() -> bool {
+        SEEDED_RNG.with(|rng| rng.borrow().is_some())
+    }
+
+    /// `with_rng` calls a function with the RNG of the current thread: the
+    /// `SeededRng` if the thread is seeded, or else the OS entropy.
+    pub fn with_rng<T, F>(f: F) -> Result<T>
+    where
+        F: FnOnce(&mut dyn RngCore) -> Result<T>,
+    {
+        SEEDED_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+            Some(rng) => f(rng),
+            None => {
+                let mut rng = OsRng::new()?;
+                f(&mut rng)
+            }
+        })
+    }
+
+    /// `with_os_rng` calls a function with the OS entropy, whether the current
+    /// thread is seeded or not.
+    pub fn with_os_rng<T, F>(f: F) -> Result<T>
+    where
+        F: FnOnce(&mut dyn RngCore) -> Result<T>,
+    {
+        let mut rng = OsRng::new()?;
+        f(&mut rng)
+    }
+
     /// `u32_from_rng` returns a random `u32` using a given RNG.
     pub fn u32_from_rng<R>(rng: &mut R) -> u32
     where
-        R: RngCore,
+        R: RngCore + ?Sized,
     {
         rng.next_u32()
     }
 
     /// `u32` returns a random `u32`.
     pub fn u32() -> Result<u32> {
-        let mut rng = OsRng::new()?;
-        let res = Random::u32_from_rng(&mut rng);
-        Ok(res)
+        Random::with_rng(|rng| Ok(Random::u32_from_rng(rng)))
     }
 
     /// `u32_range` returns a random `u32` between a specific inclusive range.
     pub fn u32_range(from: u32, to: u32) -> Result<u32> {
-        let mut rng = OsRng::new()?;
-        let res = Random::u32_range_from_rng(&mut rng, from, to)?;
-        Ok(res)
+        Random::with_rng(|rng| Random::u32_range_from_rng(rng, from, to))
     }
 
     /// `u32_range_from_rng` returns a random `u32` between a specific range
     /// using a given RNG.
     pub fn u32_range_from_rng<R>(rng: &mut R, from: u32, to: u32) -> Result<u32>
     where
-        R: RngCore,
+        R: RngCore + ?Sized,
     {
         if from > to {
             let err = Error::InvalidRange;
@@ -103,9 +226,10 @@ impl Random {
             return Err(err);
         }
 
-        let mut res = Vec::new();
+        let count = u32::min(count, to - from) as usize;
+        let mut res = Vec::with_capacity(count);
 
-        for _ in 0..count as usize {
+        while res.len() < count {
             let value = Random::u32_range(from, to)?;
             if !res.contains(&value) {
                 res.push(value);
@@ -118,30 +242,26 @@ impl Random {
     /// `u64_from_rng` returns a random `u64` using a given RNG.
     pub fn u64_from_rng<R>(rng: &mut R) -> u64
     where
-        R: RngCore,
+        R: RngCore + ?Sized,
     {
         rng.next_u64()
     }
 
     /// `u64` returns a random `u64`.
     pub fn u64() -> Result<u64> {
-        let mut rng = OsRng::new()?;
-        let res = Random::u64_from_rng(&mut rng);
-        Ok(res)
+        Random::with_rng(|rng| Ok(Random::u64_from_rng(rng)))
     }
 
     /// `u64_range` returns a random `u64` between a specific inclusive range.
     pub fn u64_range(from: u64, to: u64) -> Result<u64> {
-        let mut rng = OsRng::new()?;
-        let res = Random::u64_range_from_rng(&mut rng, from, to)?;
-        Ok(res)
+        Random::with_rng(|rng| Random::u64_range_from_rng(rng, from, to))
     }
 
     /// `u64_range_from_rng` returns a random `u64` between a specific range
     /// using a given RNG.
     pub fn u64_range_from_rng<R>(rng: &mut R, from: u64, to: u64) -> Result<u64>
     where
-        R: RngCore,
+        R: RngCore + ?Sized,
     {
         if from > to {
             let err = Error::InvalidRange;
@@ -222,22 +342,23 @@ impl Random {
     /// `fill_bytes_from_rng` fills a slice with random bytes using a given RNG.
     pub fn fill_bytes_from_rng<R>(rng: &mut R, buf: &mut [u8])
     where
-        R: RngCore,
+        R: RngCore + ?Sized,
     {
         rng.fill_bytes(buf);
     }
 
     /// `fill_bytes` fills a slice with random bytes.
     pub fn fill_bytes(buf: &mut [u8]) -> Result<()> {
-        let mut rng = OsRng::new()?;
-        Random::fill_bytes_from_rng(&mut rng, buf);
-        Ok(())
+        Random::with_rng(|rng| {
+            Random::fill_bytes_from_rng(rng, buf);
+            Ok(())
+        })
     }
 
     /// `bytes_from_rng` creates a vector of random bytes using a given RNG.
     pub fn bytes_from_rng<R>(rng: &mut R, len: usize) -> Vec<u8>
     where
-        R: RngCore,
+        R: RngCore + ?Sized,
     {
         let mut buf = Vec::new();
         buf.resize(len, 0);
@@ -251,9 +372,13 @@ impl Random {
 
     /// `bytes` creates a vector of random bytes.
     pub fn bytes(len: usize) -> Result<Vec<u8>> {
-        let mut rng = OsRng::new()?;
-        let res = Random::bytes_from_rng(&mut rng, len);
-        Ok(res)
+        Random::with_rng(|rng| Ok(Random::bytes_from_rng(rng, len)))
+    }
+
+    /// `os_bytes` creates a vector of random bytes from the OS entropy, whether
+    /// the current thread is seeded or not.
+    pub fn os_bytes(len: usize) -> Result<Vec<u8>> {
+        Random::with_os_rng(|rng| Ok(Random::bytes_from_rng(rng, len)))
     }
 }
 
@@ -292,3 +417,37 @@ fn test_u64_range() {
         assert!(val >= valid_from && val < valid_to)
     }
 }
+
+#[test]
+fn test_seeded_random() {
+    use std::thread;
+
+    assert!(!Random::is_seeded());
+
+    let guard = Random::seed(42);
+    assert!(Random::is_seeded());
+
+    let values_a = (Random::u64().unwrap(), Random::bytes(10).unwrap());
+
+    let res = thread::spawn(Random::is_seeded).join();
+    assert!(!res.unwrap());
+
+    drop(guard);
+    assert!(!Random::is_seeded());
+
+    let _guard = Random::seed(42);
+    let values_b = (Random::u64().unwrap(), Random::bytes(10).unwrap());
+    assert_eq!(values_a, values_b);
+
+    {
+        let _guard = Random::seed(43);
+        let values_c = (Random::u64().unwrap(), Random::bytes(10).unwrap());
+        assert_ne!(values_a, values_c);
+    }
+
+    // NB: the inner guard restores the outer seed, and its RNG state
+    assert!(Random::is_seeded());
+
+    let values_d = (Random::u64().unwrap(), Random::bytes(10).unwrap());
+    assert_ne!(values_b, values_d);
+}
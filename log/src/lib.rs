@@ -26,5 +26,8 @@ pub mod file;
 /// `record` contains the log record type and functions.
 pub mod record;
 
+/// `trace` contains the message tracing type and functions.
+pub mod trace;
+
 /// `logger` contains the logger type and functions.
 pub mod logger;
@@ -9,9 +9,15 @@ use crate::format::LogFormat;
 use crate::level::LogLevel;
 use crate::record::LogRecord;
 use crate::result::Result;
+use crate::trace::TraceContext;
 use config::log::LogConfig;
+use std::collections::BTreeMap;
 use std::fs::OpenOptions;
 use std::io::{stderr, stdout, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::{Arc, RwLock};
+use std::thread;
 use term;
 
 /// `write_to_stdout` writes a binary message to stdout.
@@ -33,13 +39,25 @@ fn write_to_file(path: &str, msg: &[u8]) -> Result<()> {
     file.write_all(b"\n").map_err(|e| e.into())
 }
 
-/// `Logger` is the logger type used in Alsacoin.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+/// `LogEntry` is a log message sent to the worker of an asynchronous `Logger`.
+type LogEntry = (LogLevel, String);
+
+/// `Logger` is the logger type used in Alsacoin. An asynchronous `Logger`
+/// sends its messages to a worker thread through a bounded channel, dropping
+/// them when the channel is full.
+///
+/// The copies of a `Logger` share its level, so that it can be changed at runtime.
+#[derive(Clone, Debug, Default)]
 pub struct Logger {
-    level: LogLevel,
+    level: Arc<RwLock<LogLevel>>,
     format: LogFormat,
     file: LogFile,
     color: bool,
+    modules: BTreeMap<String, LogLevel>,
+    module: Option<String>,
+    trace: Option<TraceContext>,
+    sender: Option<SyncSender<LogEntry>>,
+    dropped: Arc<AtomicU64>,
 }
 
 impl Logger {
@@ -53,10 +71,15 @@ impl Logger {
         }
 
         let logger = Logger {
-            level,
+            level: Arc::new(RwLock::new(level)),
             format,
             file: file.to_owned(),
             color,
+            modules: BTreeMap::new(),
+            module: None,
+            trace: None,
+            sender: None,
+            dropped: Arc::new(AtomicU64::new(0)),
         };
 
         Ok(logger)
@@ -74,16 +97,114 @@ impl Logger {
         let file = LogFile::parse(&config.file.unwrap());
         let color = config.color.unwrap();
 
-        let logger = Logger {
-            level,
-            format,
-            file,
-            color,
-        };
+        let mut logger = Logger::new(level, format, &file, color)?;
+
+        for (module, level) in config.modules.unwrap().iter() {
+            logger.set_module_level(module, LogLevel::parse(level)?);
+        }
+
+        if config.asynchronous.unwrap() {
+            logger.start_async(config.buffer_size.unwrap() as usize)?;
+        }
 
         Ok(logger)
     }
 
+    /// `level` returns the level of the `Logger`.
+    pub fn level(&self) -> LogLevel {
+        *self.level.read().unwrap()
+    }
+
+    /// `set_level` sets the level of the `Logger` and of its copies.
+    pub fn set_level(&self, level: LogLevel) {
+        *self.level.write().unwrap() = level;
+    }
+
+    /// `set_module_level` overrides the `Logger` level for a module and its submodules.
+    pub fn set_module_level(&mut self, module: &str, level: LogLevel) {
+        self.modules.insert(module.into(), level);
+    }
+
+    /// `with_module` returns a copy of the `Logger` logging as a module.
+    /// The copy shares the asynchronous worker of the `Logger`, if any.
+    pub fn with_module(&self, module: &str) -> Logger {
+        let mut logger = self.clone();
+        logger.module = Some(module.into());
+        logger
+    }
+
+    /// `with_trace` returns a copy of the `Logger` tagging its messages with
+    /// the correlation id of a `TraceContext`.
+    /// The copy shares the asynchronous worker of the `Logger`, if any.
+    pub fn with_trace(&self, trace: &TraceContext) -> Logger {
+        let mut logger = self.clone();
+        logger.trace = Some(trace.to_owned());
+        logger
+    }
+
+    /// `trace` returns the `TraceContext` of the `Logger`, if any.
+    pub fn trace(&self) -> Option<&TraceContext> {
+        self.trace.as_ref()
+    }
+
+    /// `module_level` returns the level of a module, which is the level of its
+    /// closest overridden parent module or the `Logger` level.
+    pub fn module_level(&self, module: Option<&str>) -> LogLevel {
+        let mut module = match module {
+            Some(module) => module,
+            None => return self.level(),
+        };
+
+        loop {
+            if let Some(level) = self.modules.get(module) {
+                return *level;
+            }
+
+            match module.rfind("::") {
+                Some(idx) => module = &module[..idx],
+                None => return self.level(),
+            }
+        }
+    }
+
+    /// `start_async` makes the `Logger` asynchronous, spawning a worker that
+    /// writes the messages buffered in a channel of a given size.
+    pub fn start_async(&mut self, buffer_size: usize) -> Result<()> {
+        if self.sender.is_some() {
+            return Ok(());
+        }
+
+        let (sender, receiver) = sync_channel::<LogEntry>(buffer_size);
+
+        let format = self.format;
+        let file = self.file.clone();
+        let color = self.color;
+
+        thread::Builder::new()
+            .name("logger".into())
+            .spawn(move || {
+                for (level, msg) in receiver.iter() {
+                    // NB: there is no one to report a failed write to.
+                    let _ = Logger::write(level, format, &file, color, &msg);
+                }
+            })?;
+
+        self.sender = Some(sender);
+
+        Ok(())
+    }
+
+    /// `is_async` returns if the `Logger` is asynchronous.
+    pub fn is_async(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// `dropped` returns the number of messages dropped by an asynchronous
+    /// `Logger` because its buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
     /// `log_record` returns a `LogRecord` from a log message.
     pub fn log_record(level: LogLevel, msg: &str) -> Result<LogRecord> {
         if level.is_none() {
@@ -209,21 +330,65 @@ impl Logger {
         Ok(())
     }
 
-    /// `log` logs a message at a specific level. If the given
-    /// level is greater than the logger level, the logger does
-    /// nothing.
-    pub fn log(&self, level: LogLevel, msg: &str) -> Result<()> {
-        if self.level.is_none() || self.level < level {
+    /// `write` writes a message at a specific level on a `LogFile`.
+    fn write(
+        level: LogLevel,
+        format: LogFormat,
+        file: &LogFile,
+        color: bool,
+        msg: &str,
+    ) -> Result<()> {
+        match file {
+            LogFile::StdOut => Logger::log_to_stdout(level, format, color, msg),
+            LogFile::StdErr => Logger::log_to_stderr(level, format, color, msg),
+            LogFile::Path(ref path) => Logger::log_to_file(path, level, format, msg),
+        }
+    }
+
+    /// `log_as` logs a message at a specific level as a module. If the given
+    /// level is greater than the module level, the logger does nothing.
+    pub fn log_as(&self, module: Option<&str>, level: LogLevel, msg: &str) -> Result<()> {
+        let max_level = self.module_level(module);
+
+        if max_level.is_none() || max_level < level {
             return Ok(());
         }
 
-        match self.file {
-            LogFile::StdOut => Logger::log_to_stdout(level, self.format, self.color, msg),
-            LogFile::StdErr => Logger::log_to_stderr(level, self.format, self.color, msg),
-            LogFile::Path(ref path) => Logger::log_to_file(path, level, self.format, msg),
+        let traced_msg;
+
+        let msg = if let Some(ref trace) = self.trace {
+            traced_msg = format!("[{}] {}", trace, msg);
+            traced_msg.as_str()
+        } else {
+            msg
+        };
+
+        if let Some(ref sender) = self.sender {
+            match sender.try_send((level, msg.into())) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    let err = Error::IO {
+                        msg: "Logger worker not found".into(),
+                    };
+                    Err(err)
+                }
+            }
+        } else {
+            Logger::write(level, self.format, &self.file, self.color, msg)
         }
     }
 
+    /// `log` logs a message at a specific level. If the given
+    /// level is greater than the logger level, the logger does
+    /// nothing.
+    pub fn log(&self, level: LogLevel, msg: &str) -> Result<()> {
+        self.log_as(self.module.as_ref().map(|m| m.as_str()), level, msg)
+    }
+
     /// `log_critical` logs a message with a critical level.
     pub fn log_critical(&self, msg: &str) -> Result<()> {
         let level = LogLevel::Critical;
@@ -276,6 +441,69 @@ fn test_logger_from_config() {
     assert!(res.is_ok());
 }
 
+#[test]
+fn test_logger_modules() {
+    let mut logger = Logger::new(
+        LogLevel::Critical,
+        LogFormat::default(),
+        &LogFile::default(),
+        false,
+    )
+    .unwrap();
+
+    logger.set_module_level("protocol", LogLevel::Debug);
+    logger.set_module_level("protocol::network", LogLevel::None);
+
+    assert_eq!(logger.module_level(None), LogLevel::Critical);
+    assert_eq!(logger.module_level(Some("store")), LogLevel::Critical);
+    assert_eq!(logger.module_level(Some("protocol")), LogLevel::Debug);
+    assert_eq!(
+        logger.module_level(Some("protocol::state")),
+        LogLevel::Debug
+    );
+    assert_eq!(
+        logger.module_level(Some("protocol::network")),
+        LogLevel::None
+    );
+    assert_eq!(
+        logger.module_level(Some("protocol::network::tcp")),
+        LogLevel::None
+    );
+    assert_eq!(logger.module_level(Some("protocols")), LogLevel::Critical);
+
+    let network_logger = logger.with_module("protocol::network");
+    let res = network_logger.log_critical("abcd");
+    assert!(res.is_ok());
+
+    network_logger.set_level(LogLevel::Info);
+    assert_eq!(logger.level(), LogLevel::Info);
+    assert_eq!(logger.module_level(Some("store")), LogLevel::Info);
+}
+
+#[test]
+fn test_logger_async() {
+    let mut config = LogConfig::default();
+    config.level = Some("none".into());
+    config.asynchronous = Some(true);
+
+    let res = Logger::from_config(&config);
+    assert!(res.is_ok());
+
+    let mut logger = res.unwrap();
+    assert!(logger.is_async());
+
+    let res = logger.start_async(1);
+    assert!(res.is_ok());
+
+    let res = logger.log_critical("abcd");
+    assert!(res.is_ok());
+    assert_eq!(logger.dropped(), 0);
+
+    let res = Logger::from_config(&LogConfig::default());
+    assert!(res.is_ok());
+    assert!(!res.unwrap().is_async());
+}
+
 #[test]
 fn test_logger_log_record() {
     let valid_msg = "abcd";
@@ -307,3 +535,22 @@ fn test_logger_log_message() {
     assert!(res.is_ok());
     */
 }
+
+#[test]
+fn test_logger_trace() {
+    let logger = Logger::new(
+        LogLevel::None,
+        LogFormat::default(),
+        &LogFile::default(),
+        false,
+    )
+    .unwrap();
+    assert!(logger.trace().is_none());
+
+    let trace = TraceContext::new(b"127.0.0.1:2019", 10);
+    let traced_logger = logger.with_trace(&trace);
+    assert_eq!(traced_logger.trace(), Some(&trace));
+
+    let res = traced_logger.log_critical("abcd");
+    assert!(res.is_ok());
+}
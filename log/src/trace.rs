@@ -0,0 +1,90 @@
+//! # Trace
+//!
+//! `trace` is the module containing the message tracing type and functions.
+
+use crypto::hash::Blake512Hasher;
+use models::consensus_message::ConsensusMessage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// `TraceContext` is the context of a traced `ConsensusMessage`. Its
+/// correlation id is shared by a request and its reply, on both the
+/// requesting and the replying node, so that the lifecycle of a request
+/// can be reconstructed from the logs.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct TraceContext {
+    pub correlation_id: String,
+    pub message_id: u64,
+}
+
+impl TraceContext {
+    /// `REQUESTER_LEN` is the length of the requester part of the correlation id.
+    pub const REQUESTER_LEN: usize = 16;
+
+    /// `new` creates a new `TraceContext` from the address of the requesting
+    /// node and the id of the request.
+    pub fn new(requester: &[u8], request_id: u64) -> TraceContext {
+        let requester = Blake512Hasher::hash(requester).to_string();
+        let correlation_id = format!(
+            "{}-{:x}",
+            &requester[..TraceContext::REQUESTER_LEN],
+            request_id
+        );
+
+        TraceContext {
+            correlation_id,
+            message_id: request_id,
+        }
+    }
+
+    /// `from_message` creates a new `TraceContext` from a `ConsensusMessage`.
+    /// A reply is correlated with its request, sent by the reply recipient.
+    pub fn from_message(msg: &ConsensusMessage) -> TraceContext {
+        let id = msg.id();
+
+        let mut trace = if id % 2 == 0 {
+            TraceContext::new(&msg.address(), id)
+        } else {
+            TraceContext::new(&msg.node().address, id - 1)
+        };
+
+        trace.message_id = id;
+        trace
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.correlation_id)
+    }
+}
+
+#[test]
+fn test_trace_context() {
+    use models::node::Node;
+    use models::stage::Stage;
+    use std::collections::BTreeSet;
+
+    let stage = Stage::default();
+    let requester = Node::new(stage, b"127.0.0.1:2019");
+    let replier = Node::new(stage, b"127.0.0.1:2020");
+
+    let ids = BTreeSet::new();
+    let request = ConsensusMessage::new_fetch_nodes(&requester.address, &replier, &ids).unwrap();
+    let request_id = request.id();
+
+    let nodes = BTreeSet::new();
+    let reply =
+        ConsensusMessage::new_push_nodes(&replier.address, request_id, &requester, &nodes).unwrap();
+
+    let request_trace = TraceContext::from_message(&request);
+    let reply_trace = TraceContext::from_message(&reply);
+
+    assert_eq!(request_trace.correlation_id, reply_trace.correlation_id);
+    assert_eq!(request_trace.message_id, request_id);
+    assert_eq!(reply_trace.message_id, request_id + 1);
+    assert_eq!(&request_trace.to_string(), &request_trace.correlation_id);
+
+    let other_trace = TraceContext::new(&replier.address, request_id);
+    assert_ne!(request_trace.correlation_id, other_trace.correlation_id);
+}
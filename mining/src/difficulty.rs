@@ -6,6 +6,12 @@ use crate::common::riemmann_zeta_2;
 use crate::error::Error;
 use crate::result::Result;
 
+/// `MIN_DIFFICULTY` is the minimum difficulty bits.
+pub const MIN_DIFFICULTY: u64 = 1;
+
+/// `MAX_DIFFICULTY` is the maximum difficulty bits.
+pub const MAX_DIFFICULTY: u64 = 512;
+
 /// `difficulty` calculates the difficulty bits given a specific distance
 /// from the eve transaction and a specific amount.
 pub fn difficulty(h: u64, a: u64) -> Result<u64> {
@@ -20,6 +26,31 @@ pub fn difficulty(h: u64, a: u64) -> Result<u64> {
     Ok(res)
 }
 
+/// `retarget` adjusts the difficulty bits given the observed mean mining time
+/// and the target mining time, in seconds. Every difficulty bit doubles the
+/// mining work, so the difficulty moves by the base 2 logarithm of the ratio
+/// of the two times, capped at `max_step` bits and bound in `1..=MAX_DIFFICULTY`.
+pub fn retarget(difficulty: u64, mean_time: u64, target_time: u64, max_step: u64) -> Result<u64> {
+    if (difficulty < MIN_DIFFICULTY) || (difficulty > MAX_DIFFICULTY) || (target_time == 0) {
+        let err = Error::OutOfBound;
+        return Err(err);
+    }
+
+    let mean_time = mean_time.max(1);
+    let max_step = max_step.min(MAX_DIFFICULTY) as f64;
+
+    let step = (target_time as f64 / mean_time as f64)
+        .log2()
+        .round()
+        .max(-max_step)
+        .min(max_step) as i64;
+
+    let res = (difficulty as i64 + step)
+        .max(MIN_DIFFICULTY as i64)
+        .min(MAX_DIFFICULTY as i64) as u64;
+    Ok(res)
+}
+
 #[test]
 fn test_difficulty() {
     let hs = [1, 1_000, 1_000_000];
@@ -42,3 +73,31 @@ fn test_difficulty() {
         }
     }
 }
+
+#[test]
+fn test_retarget() {
+    let res = retarget(0, 60, 60, 4);
+    assert!(res.is_err());
+
+    let res = retarget(MAX_DIFFICULTY + 1, 60, 60, 4);
+    assert!(res.is_err());
+
+    let res = retarget(10, 60, 0, 4);
+    assert!(res.is_err());
+
+    let cases = [
+        (10, 60, 60, 4, 10),
+        (10, 15, 60, 4, 12),
+        (10, 0, 60, 4, 14),
+        (10, 240, 60, 4, 8),
+        (10, 240, 60, 0, 10),
+        (1, 6_000, 60, 4, 1),
+        (MAX_DIFFICULTY, 1, 60, 4, MAX_DIFFICULTY),
+    ];
+
+    for (difficulty, mean_time, target_time, max_step, expected) in cases.iter() {
+        let res = retarget(*difficulty, *mean_time, *target_time, *max_step);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), *expected);
+    }
+}
@@ -19,6 +19,8 @@ pub enum Error {
     NotFound,
     #[fail(display = "Invalid mining solution")]
     InvalidMiningSolution,
+    #[fail(display = "Stopped")]
+    Stopped,
 }
 
 impl From<CryptoError> for Error {
@@ -19,3 +19,9 @@ pub mod difficulty;
 
 /// `miner` contains the mining types and functions.
 pub mod miner;
+
+/// `proof` contains the mining proof type and functions.
+pub mod proof;
+
+/// `scheduler` contains the mining scheduler type and functions.
+pub mod scheduler;
@@ -4,10 +4,12 @@
 
 use crate::error::Error;
 use crate::result::Result;
+use crate::scheduler::Scheduler;
 use crypto::hash::Blake512Hasher;
 use crypto::hash::Digest;
 use crypto::hash::{BalloonHasher, BalloonParams};
 use std::mem::transmute;
+use std::time::Instant;
 
 /// `Miner` is the type used for mining.
 pub struct Miner {
@@ -84,6 +86,39 @@ impl Miner {
         Err(err)
     }
 
+    /// `mine_message_scheduled` mines a binary message in a thread scheduled by
+    /// a `Scheduler`, failing if the mining is stopped.
+    pub fn mine_message_scheduled(
+        &self,
+        msg: &[u8],
+        scheduler: &Scheduler,
+    ) -> Result<(u64, Digest)> {
+        let _permit = scheduler.acquire()?;
+
+        let mut nonce = 0u64;
+
+        while nonce <= u64::max_value() {
+            let start = Instant::now();
+
+            let nmsg = Miner::nonced_message(nonce, msg);
+            let hash = self.hash_message(&nmsg)?;
+            let bits = hash.leading_zeros();
+
+            if bits >= self.difficulty {
+                scheduler.record_mined();
+                return Ok((nonce, hash));
+            }
+
+            scheduler.throttle(start.elapsed());
+            scheduler.wait()?;
+
+            nonce += 1;
+        }
+
+        let err = Error::NotFound;
+        Err(err)
+    }
+
     /// `verify_message_mining` verifies the solution of a `mine_message` operation.
     pub fn verify_message_mining(&self, msg: &[u8], nonce: u64, digest: Digest) -> Result<()> {
         let nmsg = Miner::nonced_message(nonce, msg);
@@ -130,3 +165,27 @@ fn test_mine_message() {
         assert!(res.is_ok());
     }
 }
+
+#[test]
+fn test_mine_message_scheduled() {
+    use crypto::random::Random;
+
+    let params = BalloonParams::default();
+    let msg = Random::bytes(1000).unwrap();
+    let miner = Miner::new(params, 2).unwrap();
+
+    let scheduler = Scheduler::new(50, 1).unwrap();
+
+    let res = miner.mine_message_scheduled(&msg, &scheduler);
+    assert!(res.is_ok());
+
+    let (nonce, digest) = res.unwrap();
+    assert!(miner.verify_message_mining(&msg, nonce, digest).is_ok());
+    assert_eq!(scheduler.status().mined, 1);
+    assert_eq!(scheduler.status().active_threads, 0);
+
+    scheduler.stop();
+
+    let res = miner.mine_message_scheduled(&msg, &scheduler);
+    assert!(res.is_err());
+}
@@ -0,0 +1,160 @@
+//! # Proof
+//!
+//! The `proof` module contains the mining proof type and functions.
+
+use crate::error::Error;
+use crate::miner::Miner;
+use crate::result::Result;
+use crate::scheduler::Scheduler;
+use crypto::hash::BalloonParams;
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+
+/// `Proof` is the proof of a mined message. It carries everything needed to
+/// verify the mining of the message, so it can be checked on its own.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Proof {
+    pub params: BalloonParams,
+    pub difficulty: u64,
+    pub nonce: u64,
+    pub digest: Digest,
+}
+
+impl Proof {
+    /// `new` creates a new `Proof`.
+    pub fn new(
+        params: BalloonParams,
+        difficulty: u64,
+        nonce: u64,
+        digest: Digest,
+    ) -> Result<Proof> {
+        let proof = Proof {
+            params,
+            difficulty,
+            nonce,
+            digest,
+        };
+
+        proof.validate()?;
+
+        Ok(proof)
+    }
+
+    /// `mine` mines a binary message, returning its `Proof`.
+    pub fn mine(params: BalloonParams, difficulty: u64, msg: &[u8]) -> Result<Proof> {
+        let miner = Miner::new(params, difficulty)?;
+        let (nonce, digest) = miner.mine_message(msg)?;
+
+        Proof::new(params, difficulty, nonce, digest)
+    }
+
+    /// `mine_scheduled` mines a binary message in a thread scheduled by a
+    /// `Scheduler`, returning its `Proof`.
+    pub fn mine_scheduled(
+        params: BalloonParams,
+        difficulty: u64,
+        msg: &[u8],
+        scheduler: &Scheduler,
+    ) -> Result<Proof> {
+        let miner = Miner::new(params, difficulty)?;
+        let (nonce, digest) = miner.mine_message_scheduled(msg, scheduler)?;
+
+        Proof::new(params, difficulty, nonce, digest)
+    }
+
+    /// `validate` validates the `Proof` parameters.
+    pub fn validate(&self) -> Result<()> {
+        self.params.validate()?;
+
+        if self.difficulty > 512 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `verify` verifies the `Proof` against the mined binary message.
+    pub fn verify(&self, msg: &[u8]) -> Result<()> {
+        self.validate()?;
+
+        if self.digest.leading_zeros() < self.difficulty {
+            let err = Error::InvalidMiningSolution;
+            return Err(err);
+        }
+
+        let miner = Miner::new(self.params, self.difficulty)?;
+
+        miner.verify_message_mining(msg, self.nonce, self.digest)
+    }
+
+    /// `to_bytes` converts the `Proof` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Proof`.
+    pub fn from_bytes(b: &[u8]) -> Result<Proof> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Proof` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `Proof`.
+    pub fn from_json(s: &str) -> Result<Proof> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_proof_verify() {
+    use crypto::random::Random;
+
+    let params = BalloonParams::default();
+    let msg_len = 1000;
+    let msg = Random::bytes(msg_len).unwrap();
+    let difficulty = 2;
+
+    let res = Proof::mine(params, 513, &msg);
+    assert!(res.is_err());
+
+    let res = Proof::mine(params, difficulty, &msg);
+    assert!(res.is_ok());
+
+    let proof = res.unwrap();
+
+    let res = proof.verify(&msg);
+    assert!(res.is_ok());
+
+    let other_msg = Random::bytes(msg_len).unwrap();
+    let res = proof.verify(&other_msg);
+    assert!(res.is_err());
+
+    let mut invalid_proof = proof;
+    invalid_proof.nonce = proof.nonce.wrapping_add(1);
+
+    let res = invalid_proof.verify(&msg);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_proof_serialize() {
+    use crypto::random::Random;
+
+    let params = BalloonParams::default();
+    let msg = Random::bytes(100).unwrap();
+    let proof_a = Proof::mine(params, 1, &msg).unwrap();
+
+    let cbor = proof_a.to_bytes().unwrap();
+    let proof_b = Proof::from_bytes(&cbor).unwrap();
+    assert_eq!(proof_a, proof_b);
+
+    let json = proof_a.to_json().unwrap();
+    let proof_b = Proof::from_json(&json).unwrap();
+    assert_eq!(proof_a, proof_b);
+}
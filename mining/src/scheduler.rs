@@ -0,0 +1,241 @@
+//! # Scheduler
+//!
+//! The `scheduler` module contains the mining scheduler type and functions, used
+//! to limit the CPU used by the mining: the number of concurrent mining threads
+//! is capped, each thread sleeps for a share of its time given by the duty cycle,
+//! and the mining can be paused, as when the node falls behind on consensus.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// `SchedulerStatus` is the status of a `Scheduler`.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize,
+)]
+pub struct SchedulerStatus {
+    pub running: bool,
+    pub paused: bool,
+    pub duty_cycle: u32,
+    pub max_threads: u32,
+    pub active_threads: u32,
+    pub mined: u64,
+}
+
+/// `Scheduler` schedules the mining threads. Clones of a `Scheduler` share its state.
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    status: Arc<(Mutex<SchedulerStatus>, Condvar)>,
+}
+
+impl Scheduler {
+    /// `MAX_DUTY_CYCLE` is the maximum duty cycle, mining all the time.
+    pub const MAX_DUTY_CYCLE: u32 = 100;
+
+    /// `new` creates a new running `Scheduler` with a duty cycle, the percentage
+    /// of the time a thread spends mining, and a maximum number of mining threads.
+    pub fn new(duty_cycle: u32, max_threads: u32) -> Result<Scheduler> {
+        Scheduler::validate_params(duty_cycle, max_threads)?;
+
+        let status = SchedulerStatus {
+            running: true,
+            duty_cycle,
+            max_threads,
+            ..Default::default()
+        };
+
+        let scheduler = Scheduler {
+            status: Arc::new((Mutex::new(status), Condvar::new())),
+        };
+
+        Ok(scheduler)
+    }
+
+    /// `validate_params` validates the `Scheduler` parameters.
+    fn validate_params(duty_cycle: u32, max_threads: u32) -> Result<()> {
+        if duty_cycle == 0 || duty_cycle > Scheduler::MAX_DUTY_CYCLE || max_threads == 0 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `update` updates the `SchedulerStatus`, waking up the waiting threads.
+    fn update<F: FnOnce(&mut SchedulerStatus)>(&self, f: F) {
+        let (ref lock, ref cvar) = *self.status;
+
+        f(&mut lock.lock().unwrap());
+        cvar.notify_all();
+    }
+
+    /// `status` returns the `SchedulerStatus`.
+    pub fn status(&self) -> SchedulerStatus {
+        *self.status.0.lock().unwrap()
+    }
+
+    /// `set_limits` sets the duty cycle and the maximum number of mining threads.
+    pub fn set_limits(&self, duty_cycle: u32, max_threads: u32) -> Result<()> {
+        Scheduler::validate_params(duty_cycle, max_threads)?;
+
+        self.update(|status| {
+            status.duty_cycle = duty_cycle;
+            status.max_threads = max_threads;
+        });
+
+        Ok(())
+    }
+
+    /// `start` starts the mining.
+    pub fn start(&self) {
+        self.update(|status| status.running = true);
+    }
+
+    /// `stop` stops the mining, aborting the running mining threads.
+    pub fn stop(&self) {
+        self.update(|status| status.running = false);
+    }
+
+    /// `pause` pauses the mining threads until resumed.
+    pub fn pause(&self) {
+        self.update(|status| status.paused = true);
+    }
+
+    /// `resume` resumes the paused mining threads.
+    pub fn resume(&self) {
+        self.update(|status| status.paused = false);
+    }
+
+    /// `wait` blocks while the mining is paused, returning an error if the
+    /// mining is stopped.
+    pub fn wait(&self) -> Result<()> {
+        let (ref lock, ref cvar) = *self.status;
+        let mut status = lock.lock().unwrap();
+
+        while status.running && status.paused {
+            status = cvar.wait(status).unwrap();
+        }
+
+        if !status.running {
+            let err = Error::Stopped;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `acquire` blocks until a mining thread can run, returning a `Permit`
+    /// released when dropped, or an error if the mining is stopped.
+    pub fn acquire(&self) -> Result<Permit> {
+        let (ref lock, ref cvar) = *self.status;
+        let mut status = lock.lock().unwrap();
+
+        while status.running && (status.paused || status.active_threads >= status.max_threads) {
+            status = cvar.wait(status).unwrap();
+        }
+
+        if !status.running {
+            let err = Error::Stopped;
+            return Err(err);
+        }
+
+        status.active_threads += 1;
+
+        let permit = Permit {
+            scheduler: self.clone(),
+        };
+
+        Ok(permit)
+    }
+
+    /// `throttle` sleeps after a time spent mining, so that the mining time
+    /// is the duty cycle share of the total time.
+    pub fn throttle(&self, busy: Duration) {
+        let duty_cycle = self.status().duty_cycle.max(1);
+
+        if duty_cycle < Scheduler::MAX_DUTY_CYCLE {
+            let idle = busy * (Scheduler::MAX_DUTY_CYCLE - duty_cycle) / duty_cycle;
+            thread::sleep(idle);
+        }
+    }
+
+    /// `record_mined` records a mined message.
+    pub fn record_mined(&self) {
+        self.update(|status| status.mined += 1);
+    }
+}
+
+/// `Permit` is the permit of a mining thread to run, released when dropped.
+#[derive(Debug)]
+pub struct Permit {
+    scheduler: Scheduler,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler
+            .update(|status| status.active_threads = status.active_threads.saturating_sub(1));
+    }
+}
+
+#[test]
+fn test_scheduler() {
+    let res = Scheduler::new(0, 1);
+    assert!(res.is_err());
+
+    let res = Scheduler::new(101, 1);
+    assert!(res.is_err());
+
+    let res = Scheduler::new(50, 0);
+    assert!(res.is_err());
+
+    let scheduler = Scheduler::new(50, 1).unwrap();
+    let status = scheduler.status();
+    assert!(status.running);
+    assert!(!status.paused);
+
+    let permit = scheduler.acquire().unwrap();
+    assert_eq!(scheduler.status().active_threads, 1);
+
+    let other = scheduler.clone();
+    let handle = thread::spawn(move || other.acquire().map(|_| ()));
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(scheduler.status().active_threads, 1);
+
+    drop(permit);
+    assert!(handle.join().unwrap().is_ok());
+    assert_eq!(scheduler.status().active_threads, 0);
+
+    scheduler.pause();
+    assert!(scheduler.status().paused);
+
+    let other = scheduler.clone();
+    let handle = thread::spawn(move || other.wait());
+
+    thread::sleep(Duration::from_millis(50));
+    scheduler.resume();
+    assert!(handle.join().unwrap().is_ok());
+
+    scheduler.pause();
+
+    let other = scheduler.clone();
+    let handle = thread::spawn(move || other.wait());
+
+    thread::sleep(Duration::from_millis(50));
+    scheduler.stop();
+    assert!(handle.join().unwrap().is_err());
+
+    assert!(scheduler.acquire().is_err());
+
+    scheduler.start();
+    scheduler.resume();
+    assert!(scheduler.acquire().is_ok());
+
+    let res = scheduler.set_limits(100, 2);
+    assert!(res.is_ok());
+    assert_eq!(scheduler.status().max_threads, 2);
+}
@@ -76,8 +76,7 @@ impl Account {
 
     /// `set_locktime` sets a locktime to the `Account`.
     pub fn set_locktime(&mut self, locktime: Timestamp) -> Result<()> {
-        locktime.validate()?;
-
+        // NB: the locktime is in the future, so it is not validated as a timestamp
         if locktime < self.time {
             let err = Error::InvalidLocktime;
             return Err(err);
@@ -88,13 +87,30 @@ impl Account {
         Ok(())
     }
 
+    /// `is_locked` returns if the `Account` is locked at a given time.
+    pub fn is_locked(&self, time: Timestamp) -> bool {
+        if let Some(locktime) = self.locktime {
+            time < locktime
+        } else {
+            false
+        }
+    }
+
+    /// `validate_unlocked` validates that the `Account` can be spent at a given time.
+    pub fn validate_unlocked(&self, time: Timestamp) -> Result<()> {
+        if self.is_locked(time) {
+            let err = Error::LockedAccount;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     /// `validate` validates the `Account`.
     pub fn validate(&self) -> Result<()> {
         self.time.validate()?;
 
         if let Some(locktime) = self.locktime {
-            locktime.validate()?;
-
             if locktime < self.time {
                 let err = Error::InvalidLocktime;
                 return Err(err);
@@ -169,8 +185,10 @@ impl<S: Store> Storable<S> for Account {
 
     fn validate_all(store: &S, stage: Stage) -> Result<()> {
         let mut eve_count = 0;
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
 
-        for value in Self::query(store, stage, None, None, None, None)? {
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
             if value.is_eve()? {
                 eve_count += 1;
             }
@@ -316,7 +334,9 @@ impl<S: Store> Storable<S> for Account {
         let key = <Self as Storable<S>>::key(value);
         let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
         let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
     }
 
     fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
@@ -390,14 +410,19 @@ impl<S: Store> Storable<S> for Account {
         let to = Some(_to.to_vec());
         let to = to.as_ref().map(|to| to.as_slice());
 
-        for amount in store.query(from, to, None, None)? {
-            let account = Account::from_bytes(&amount)?;
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(from, to)? {
+            let account = Account::from_bytes(&value)?;
             if account.time < min_time {
-                let key = <Self as Storable<S>>::key_to_bytes(stage, &account.address())?;
-                store.remove(&key)?;
+                keys.push(key);
             }
         }
 
+        for key in keys {
+            store.remove(&key)?;
+        }
+
         Ok(())
     }
 
@@ -541,6 +566,37 @@ fn test_account_validate() {
     assert!(res.is_err());
 }
 
+#[test]
+fn test_account_locked() {
+    let stage = Stage::random().unwrap();
+    let signers = Signers::new().unwrap();
+    let mut account = Account::new_eve(stage, &signers).unwrap();
+
+    let now = Timestamp::now();
+    let locktime = now.after(1_000);
+
+    let res = account.set_locktime(locktime);
+    assert!(res.is_ok());
+    assert!(account.is_locked(now));
+    assert!(!account.is_locked(locktime));
+
+    let res = account.validate();
+    assert!(res.is_ok());
+
+    let res = account.validate_unlocked(now);
+    assert!(res.is_err());
+
+    let res = account.validate_unlocked(locktime);
+    assert!(res.is_ok());
+
+    let mut unlocked = account.clone();
+    unlocked.locktime = None;
+    assert!(!unlocked.is_locked(now));
+
+    let res = unlocked.validate_unlocked(now);
+    assert!(res.is_ok());
+}
+
 #[test]
 fn test_account_serialize_bytes() {
     use crypto::random::Random;
@@ -1,9 +1,356 @@
 //! # Address
 //!
-//! `address` contains the `Address` type and functions.
+//! `address` contains the `Address` type and functions, including its
+//! human-readable bech32 encoding.
+//!
+//! An encoded `Address` is prefixed by the human-readable part of its `Stage`,
+//! so that an address of a stage cannot be used by mistake in an other, and
+//! ends with a checksum catching the typos. The 64 bytes of an `Address` make
+//! the encoding longer than the 90 characters allowed by BIP-173, so that limit
+//! is not enforced.
 
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
 use crypto::hash::Digest;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 
 /// `Address` is the address of an Alsacoin `Account`.
 /// It's an alias of a `Blake512` `Digest`.
 pub type Address = Digest;
+
+/// `CHARSET` is the bech32 character set.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// `SEPARATOR` separates the human-readable part from the data of a bech32 string.
+const SEPARATOR: char = '1';
+
+/// `CHECKSUM_LEN` is the length of the bech32 checksum, in characters.
+const CHECKSUM_LEN: usize = 6;
+
+/// `GENERATOR` is the generator of the bech32 BCH code.
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// `hrp` returns the human-readable part of the encoded addresses of a `Stage`.
+pub fn hrp(stage: Stage) -> &'static str {
+    match stage {
+        Stage::Development => "ald",
+        Stage::Testing => "alt",
+        Stage::Production => "als",
+    }
+}
+
+/// `hrp_stage` returns the `Stage` of a human-readable part.
+fn hrp_stage(hrp: &str) -> Result<Stage> {
+    match hrp {
+        "ald" => Ok(Stage::Development),
+        "alt" => Ok(Stage::Testing),
+        "als" => Ok(Stage::Production),
+        _ => {
+            let err = Error::InvalidStage;
+            Err(err)
+        }
+    }
+}
+
+/// `polymod` returns the bech32 checksum state of a sequence of 5-bit values.
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk = 1u32;
+
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+
+        for (i, gen) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= gen;
+            }
+        }
+    }
+
+    chk
+}
+
+/// `hrp_expand` expands a human-readable part for the checksum computation.
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut buf: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    buf.push(0);
+    buf.extend(hrp.bytes().map(|c| c & 31));
+    buf
+}
+
+/// `checksum` returns the bech32 checksum of a human-readable part and its data.
+fn checksum(hrp: &str, data: &[u8]) -> Vec<u8> {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; CHECKSUM_LEN]);
+
+    let chk = polymod(&values) ^ 1;
+
+    (0..CHECKSUM_LEN)
+        .map(|i| ((chk >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as u8)
+        .collect()
+}
+
+/// `convert_bits` regroups a sequence of values of `from` bits into values
+/// of `to` bits, padding the last value if `pad` is set.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>> {
+    let max = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut buf = Vec::new();
+
+    for value in data {
+        let value = u32::from(*value);
+
+        if value >> from != 0 {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        acc = ((acc << from) | value) & max_acc;
+        bits += from;
+
+        while bits >= to {
+            bits -= to;
+            buf.push(((acc >> bits) & max) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            buf.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        let err = Error::InvalidEncoding;
+        return Err(err);
+    }
+
+    Ok(buf)
+}
+
+/// `encode` encodes an `Address` of a `Stage` in bech32.
+pub fn encode(stage: Stage, address: &Address) -> String {
+    let hrp = hrp(stage);
+    // NB: regrouping bytes into 5-bit values never fails.
+    let mut data = convert_bits(&address.to_bytes(), 8, 5, true).unwrap_or_default();
+    let chk = checksum(hrp, &data);
+    data.extend(chk);
+
+    let mut s = String::with_capacity(hrp.len() + 1 + data.len());
+    s.push_str(hrp);
+    s.push(SEPARATOR);
+    s.extend(data.iter().map(|v| CHARSET[*v as usize] as char));
+    s
+}
+
+/// `decode` decodes a bech32 encoded `Address`, returning it with its `Stage`.
+pub fn decode(s: &str) -> Result<(Stage, Address)> {
+    let has_lower = s.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = s.chars().any(|c| c.is_ascii_uppercase());
+
+    if !s.is_ascii() || (has_lower && has_upper) {
+        let err = Error::InvalidEncoding;
+        return Err(err);
+    }
+
+    let s = s.to_ascii_lowercase();
+
+    let pos = match s.rfind(SEPARATOR) {
+        Some(pos) if pos > 0 && pos + 1 + CHECKSUM_LEN <= s.len() => pos,
+        _ => {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+    };
+
+    let hrp = &s[..pos];
+    let stage = hrp_stage(hrp)?;
+
+    let mut data = Vec::with_capacity(s.len() - pos - 1);
+
+    for c in s[pos + 1..].bytes() {
+        match CHARSET.iter().position(|x| *x == c) {
+            Some(value) => data.push(value as u8),
+            None => {
+                let err = Error::InvalidEncoding;
+                return Err(err);
+            }
+        }
+    }
+
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+
+    if polymod(&values) != 1 {
+        let err = Error::InvalidChecksum;
+        return Err(err);
+    }
+
+    data.truncate(data.len() - CHECKSUM_LEN);
+    let buf = convert_bits(&data, 5, 8, false)?;
+    let address = Address::from_slice(&buf).map_err(|_| Error::InvalidLength)?;
+
+    Ok((stage, address))
+}
+
+/// `parse` parses an `Address` of a `Stage` from its bech32 encoding, or from
+/// its legacy hex encoding.
+pub fn parse(stage: Stage, s: &str) -> Result<Address> {
+    if let Ok(address) = Address::from_str(s) {
+        return Ok(address);
+    }
+
+    let (address_stage, address) = decode(s)?;
+
+    if address_stage != stage {
+        let err = Error::InvalidStage;
+        return Err(err);
+    }
+
+    Ok(address)
+}
+
+/// `StagedAddress` is an `Address` together with its `Stage`, displayed and
+/// serialized in bech32.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub struct StagedAddress {
+    pub stage: Stage,
+    pub address: Address,
+}
+
+impl StagedAddress {
+    /// `new` creates a new `StagedAddress`.
+    pub fn new(stage: Stage, address: &Address) -> StagedAddress {
+        StagedAddress {
+            stage,
+            address: *address,
+        }
+    }
+
+    /// `from_str` parses a `StagedAddress` from its bech32 encoding.
+    pub fn from_str(s: &str) -> Result<StagedAddress> {
+        let (stage, address) = decode(s)?;
+        Ok(StagedAddress { stage, address })
+    }
+}
+
+impl fmt::Display for StagedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", encode(self.stage, &self.address))
+    }
+}
+
+impl Serialize for StagedAddress {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+struct StagedAddressVisitor;
+
+impl<'de> de::Visitor<'de> for StagedAddressVisitor {
+    type Value = StagedAddress;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a bech32 encoded address")
+    }
+
+    fn visit_str<E>(self, value: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        StagedAddress::from_str(value).map_err(|e| E::custom(format!("{}", e)))
+    }
+}
+
+impl<'de> Deserialize<'de> for StagedAddress {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<StagedAddress, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(StagedAddressVisitor)
+    }
+}
+
+#[test]
+fn test_address_encode() {
+    let stages = [Stage::Development, Stage::Testing, Stage::Production];
+
+    for stage in stages.iter() {
+        let address = Address::random().unwrap();
+        let s = encode(*stage, &address);
+        assert!(s.starts_with(hrp(*stage)));
+
+        let res = decode(&s);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), (*stage, address));
+
+        let res = decode(&s.to_ascii_uppercase());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), (*stage, address));
+
+        let res = parse(*stage, &s);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), address);
+
+        let res = parse(*stage, &address.to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), address);
+    }
+
+    let address = Address::random().unwrap();
+    let s = encode(Stage::Testing, &address);
+
+    let res = parse(Stage::Production, &s);
+    assert!(res.is_err());
+
+    let mut typo = s.clone().into_bytes();
+    let last = typo.len() - 1;
+    typo[last] = if typo[last] == b'q' { b'p' } else { b'q' };
+    let typo = String::from_utf8(typo).unwrap();
+
+    let res = decode(&typo);
+    assert!(res.is_err());
+
+    let mut mixed = s.clone();
+    mixed.replace_range(..1, "A");
+
+    let res = decode(&mixed);
+    assert!(res.is_err());
+
+    let invalid_cases = vec!["", "1", "als1", "abc1qqqqqq", "als1bqqqqqq"];
+
+    for case in invalid_cases {
+        let res = decode(case);
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_staged_address_serialize_json() {
+    let address = Address::random().unwrap();
+    let staged_a = StagedAddress::new(Stage::Production, &address);
+
+    let res = serde_json::to_string(&staged_a);
+    assert!(res.is_ok());
+    let json = res.unwrap();
+    assert_eq!(json, format!("\"{}\"", encode(Stage::Production, &address)));
+
+    let res = serde_json::from_str::<StagedAddress>(&json);
+    assert!(res.is_ok());
+    let staged_b = res.unwrap();
+
+    assert_eq!(staged_a, staged_b);
+}
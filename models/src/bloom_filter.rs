@@ -0,0 +1,209 @@
+//! # Bloom Filter
+//!
+//! `bloom_filter` contains the `BloomFilter` type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use byteorder::{BigEndian, ByteOrder};
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+
+/// `BloomFilter` is a probabilistic set of `Digest`s. Lookups can return
+/// false positives, but never false negatives.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct BloomFilter {
+    pub hashes: u32,
+    pub bits: Vec<u8>,
+}
+
+impl BloomFilter {
+    /// `MAX_LEN` is the maximum length in bytes of a `BloomFilter`.
+    pub const MAX_LEN: usize = 1 << 16;
+
+    /// `MAX_HASHES` is the maximum number of hashes of a `BloomFilter`.
+    pub const MAX_HASHES: u32 = 32;
+
+    /// `BITS_PER_ITEM` is the number of bits per item of a `BloomFilter`
+    /// created from a set of items. Together with `DEFAULT_HASHES` it keeps
+    /// the false positive rate around 1%.
+    pub const BITS_PER_ITEM: usize = 10;
+
+    /// `DEFAULT_HASHES` is the default number of hashes of a `BloomFilter`.
+    pub const DEFAULT_HASHES: u32 = 7;
+
+    /// `new` creates a new empty `BloomFilter`.
+    pub fn new(len: usize, hashes: u32) -> Result<BloomFilter> {
+        let filter = BloomFilter {
+            hashes,
+            bits: vec![0; len],
+        };
+
+        filter.validate()?;
+
+        Ok(filter)
+    }
+
+    /// `from_digests` creates a new `BloomFilter` containing a set of `Digest`s.
+    pub fn from_digests(digests: &BTreeSet<Digest>) -> Result<BloomFilter> {
+        let len = (digests.len() * Self::BITS_PER_ITEM + 7) / 8;
+        let len = len.max(1).min(Self::MAX_LEN);
+
+        let mut filter = BloomFilter::new(len, Self::DEFAULT_HASHES)?;
+
+        for digest in digests.iter() {
+            filter.insert(digest);
+        }
+
+        Ok(filter)
+    }
+
+    /// `bits_len` returns the number of bits of the `BloomFilter`.
+    pub fn bits_len(&self) -> u64 {
+        self.bits.len() as u64 * 8
+    }
+
+    /// `positions` returns the bit positions of a `Digest` in the `BloomFilter`,
+    /// using double hashing over the `Digest` bytes.
+    fn positions(&self, digest: &Digest) -> Vec<u64> {
+        let buf = digest.to_bytes();
+        let h1 = BigEndian::read_u64(&buf[0..8]);
+        let h2 = BigEndian::read_u64(&buf[8..16]) | 1;
+        let bits_len = self.bits_len();
+
+        (0..u64::from(self.hashes))
+            .map(|i| h1.wrapping_add(i.wrapping_mul(h2)) % bits_len)
+            .collect()
+    }
+
+    /// `insert` inserts a `Digest` in the `BloomFilter`.
+    pub fn insert(&mut self, digest: &Digest) {
+        for pos in self.positions(digest) {
+            self.bits[(pos / 8) as usize] |= 1 << (pos % 8);
+        }
+    }
+
+    /// `lookup` returns if a `Digest` may be in the `BloomFilter`.
+    pub fn lookup(&self, digest: &Digest) -> bool {
+        self.positions(digest)
+            .iter()
+            .all(|pos| self.bits[(pos / 8) as usize] & (1 << (pos % 8)) != 0)
+    }
+
+    /// `difference` returns the `Digest`s of a set that are not in the `BloomFilter`.
+    pub fn difference(&self, digests: &BTreeSet<Digest>) -> BTreeSet<Digest> {
+        digests
+            .iter()
+            .filter(|digest| !self.lookup(digest))
+            .copied()
+            .collect()
+    }
+
+    /// `validate` validates the `BloomFilter`.
+    pub fn validate(&self) -> Result<()> {
+        if self.bits.is_empty() || self.bits.len() > Self::MAX_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if self.hashes == 0 || self.hashes > Self::MAX_HASHES {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `BloomFilter` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `BloomFilter`.
+    pub fn from_bytes(b: &[u8]) -> Result<BloomFilter> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `BloomFilter` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `BloomFilter`.
+    pub fn from_json(s: &str) -> Result<BloomFilter> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_bloom_filter_new() {
+    let res = BloomFilter::new(0, BloomFilter::DEFAULT_HASHES);
+    assert!(res.is_err());
+
+    let res = BloomFilter::new(BloomFilter::MAX_LEN + 1, BloomFilter::DEFAULT_HASHES);
+    assert!(res.is_err());
+
+    let res = BloomFilter::new(1, 0);
+    assert!(res.is_err());
+
+    let res = BloomFilter::new(1, BloomFilter::MAX_HASHES + 1);
+    assert!(res.is_err());
+
+    let res = BloomFilter::new(1, BloomFilter::DEFAULT_HASHES);
+    assert!(res.is_ok());
+
+    let res = BloomFilter::from_digests(&BTreeSet::new());
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_bloom_filter_lookup() {
+    let digests: BTreeSet<Digest> = (0..100).map(|_| Digest::random().unwrap()).collect();
+    let other_digests: BTreeSet<Digest> = (0..100).map(|_| Digest::random().unwrap()).collect();
+
+    let filter = BloomFilter::from_digests(&digests).unwrap();
+
+    for digest in digests.iter() {
+        assert!(filter.lookup(digest));
+    }
+
+    assert!(filter.difference(&digests).is_empty());
+
+    let missing = filter.difference(&other_digests);
+    assert!(missing.is_subset(&other_digests));
+    assert!(missing.len() > other_digests.len() / 2);
+}
+
+#[test]
+fn test_bloom_filter_serialize_bytes() {
+    let digests: BTreeSet<Digest> = (0..10).map(|_| Digest::random().unwrap()).collect();
+    let filter_a = BloomFilter::from_digests(&digests).unwrap();
+
+    let res = filter_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = BloomFilter::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let filter_b = res.unwrap();
+
+    assert_eq!(filter_a, filter_b)
+}
+
+#[test]
+fn test_bloom_filter_serialize_json() {
+    let digests: BTreeSet<Digest> = (0..10).map(|_| Digest::random().unwrap()).collect();
+    let filter_a = BloomFilter::from_digests(&digests).unwrap();
+
+    let res = filter_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = BloomFilter::from_json(&json);
+    assert!(res.is_ok());
+    let filter_b = res.unwrap();
+
+    assert_eq!(filter_a, filter_b)
+}
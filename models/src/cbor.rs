@@ -0,0 +1,365 @@
+//! # CBOR
+//!
+//! `cbor` contains the bounded CBOR decoding types and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::Deserialize;
+use serde_cbor;
+
+/// `CborLimits` are the limits enforced on a CBOR binary before decoding it.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct CborLimits {
+    pub max_length: u64,
+    pub max_collection_len: u64,
+    pub max_depth: u32,
+}
+
+impl CborLimits {
+    /// `DEFAULT_MAX_LENGTH` is the default maximum length of a CBOR binary.
+    pub const DEFAULT_MAX_LENGTH: u64 = 1 << 24;
+
+    /// `DEFAULT_MAX_COLLECTION_LEN` is the default maximum length of a CBOR
+    /// array, map or string.
+    pub const DEFAULT_MAX_COLLECTION_LEN: u64 = 1 << 16;
+
+    /// `DEFAULT_MAX_DEPTH` is the default maximum nesting depth of a CBOR binary.
+    pub const DEFAULT_MAX_DEPTH: u32 = 32;
+
+    /// `new` creates a new `CborLimits`.
+    pub fn new(max_length: u64, max_collection_len: u64, max_depth: u32) -> CborLimits {
+        CborLimits {
+            max_length,
+            max_collection_len,
+            max_depth,
+        }
+    }
+}
+
+impl Default for CborLimits {
+    fn default() -> CborLimits {
+        CborLimits::new(
+            Self::DEFAULT_MAX_LENGTH,
+            Self::DEFAULT_MAX_COLLECTION_LEN,
+            Self::DEFAULT_MAX_DEPTH,
+        )
+    }
+}
+
+/// `CBOR_BREAK` is the CBOR stop code of indefinite length items.
+const CBOR_BREAK: u8 = 0xff;
+
+/// `CborChecker` walks a CBOR binary checking it against a `CborLimits`
+/// without allocating.
+struct CborChecker<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    limits: CborLimits,
+}
+
+impl<'a> CborChecker<'a> {
+    /// `remaining` returns the number of unread bytes.
+    fn remaining(&self) -> u64 {
+        (self.buf.len() - self.pos) as u64
+    }
+
+    /// `read_u8` reads a byte.
+    fn read_u8(&mut self) -> Result<u8> {
+        if self.pos >= self.buf.len() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        let b = self.buf[self.pos];
+        self.pos += 1;
+
+        Ok(b)
+    }
+
+    /// `peek_u8` reads a byte without consuming it.
+    fn peek_u8(&self) -> Result<u8> {
+        if self.pos >= self.buf.len() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        Ok(self.buf[self.pos])
+    }
+
+    /// `skip` skips a number of bytes.
+    fn skip(&mut self, len: u64) -> Result<()> {
+        if len > self.remaining() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        self.pos += len as usize;
+
+        Ok(())
+    }
+
+    /// `read_argument` reads the argument of an item with a given additional
+    /// information. `None` is returned for indefinite length items.
+    fn read_argument(&mut self, info: u8) -> Result<Option<u64>> {
+        let len = match info {
+            0..=23 => return Ok(Some(u64::from(info))),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            31 => return Ok(None),
+            _ => {
+                let err = Error::InvalidEncoding;
+                return Err(err);
+            }
+        };
+
+        let mut arg = 0u64;
+
+        for _ in 0..len {
+            arg = (arg << 8) | u64::from(self.read_u8()?);
+        }
+
+        Ok(Some(arg))
+    }
+
+    /// `check_collection_len` checks the length of a collection against the
+    /// limits and the unread bytes, each item being at least `min_item_len`
+    /// bytes long.
+    fn check_collection_len(&self, len: u64, min_item_len: u64) -> Result<()> {
+        if len > self.limits.max_collection_len {
+            let err = Error::TooLargeCollection {
+                len,
+                max: self.limits.max_collection_len,
+            };
+            return Err(err);
+        }
+
+        if len.saturating_mul(min_item_len) > self.remaining() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `check_depth` checks a nesting depth against the limits.
+    fn check_depth(&self, depth: u32) -> Result<()> {
+        if depth > self.limits.max_depth {
+            let err = Error::TooDeepNesting {
+                depth,
+                max: self.limits.max_depth,
+            };
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `check_string` checks a byte or text string of a given major type.
+    fn check_string(&mut self, major: u8, info: u8) -> Result<()> {
+        match self.read_argument(info)? {
+            Some(len) => {
+                self.check_collection_len(len, 1)?;
+                self.skip(len)
+            }
+            None => {
+                while self.peek_u8()? != CBOR_BREAK {
+                    let b = self.read_u8()?;
+
+                    if b >> 5 != major || b & 0x1f == 31 {
+                        let err = Error::InvalidEncoding;
+                        return Err(err);
+                    }
+
+                    self.check_string(major, b & 0x1f)?;
+                }
+
+                self.read_u8().map(|_| ())
+            }
+        }
+    }
+
+    /// `check_items` checks a definite or indefinite sequence of items.
+    fn check_items(&mut self, len: Option<u64>, items_per_entry: u64, depth: u32) -> Result<()> {
+        self.check_depth(depth)?;
+
+        match len {
+            Some(len) => {
+                self.check_collection_len(len, items_per_entry)?;
+
+                for _ in 0..len * items_per_entry {
+                    self.check_item(depth)?;
+                }
+            }
+            None => {
+                let mut len = 0;
+
+                while self.peek_u8()? != CBOR_BREAK {
+                    len += 1;
+
+                    if len > self.limits.max_collection_len {
+                        let err = Error::TooLargeCollection {
+                            len,
+                            max: self.limits.max_collection_len,
+                        };
+                        return Err(err);
+                    }
+
+                    for _ in 0..items_per_entry {
+                        self.check_item(depth)?;
+                    }
+                }
+
+                self.read_u8()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `check_item` checks a single item at a given nesting depth.
+    fn check_item(&mut self, depth: u32) -> Result<()> {
+        let b = self.read_u8()?;
+        let major = b >> 5;
+        let info = b & 0x1f;
+
+        match major {
+            0 | 1 => {
+                if self.read_argument(info)?.is_none() {
+                    let err = Error::InvalidEncoding;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            2 | 3 => self.check_string(major, info),
+            4 => {
+                let len = self.read_argument(info)?;
+                self.check_items(len, 1, depth + 1)
+            }
+            5 => {
+                let len = self.read_argument(info)?;
+                self.check_items(len, 2, depth + 1)
+            }
+            6 => {
+                if self.read_argument(info)?.is_none() {
+                    let err = Error::InvalidEncoding;
+                    return Err(err);
+                }
+
+                self.check_depth(depth + 1)?;
+                self.check_item(depth + 1)
+            }
+            _ => match info {
+                0..=23 => Ok(()),
+                24 => self.skip(1),
+                25 => self.skip(2),
+                26 => self.skip(4),
+                27 => self.skip(8),
+                _ => {
+                    let err = Error::InvalidEncoding;
+                    Err(err)
+                }
+            },
+        }
+    }
+}
+
+/// `check` checks a CBOR binary against a `CborLimits`, without decoding it.
+/// The binary must contain exactly one well formed CBOR item.
+pub fn check(buf: &[u8], limits: &CborLimits) -> Result<()> {
+    let len = buf.len() as u64;
+
+    if len > limits.max_length {
+        let err = Error::TooLong {
+            len,
+            max: limits.max_length,
+        };
+        return Err(err);
+    }
+
+    let mut checker = CborChecker {
+        buf,
+        pos: 0,
+        limits: limits.to_owned(),
+    };
+
+    checker.check_item(0)?;
+
+    if checker.remaining() != 0 {
+        let err = Error::InvalidEncoding;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// `from_slice` decodes a CBOR binary after checking it against a `CborLimits`.
+pub fn from_slice<'a, T: Deserialize<'a>>(buf: &'a [u8], limits: &CborLimits) -> Result<T> {
+    check(buf, limits)?;
+
+    serde_cbor::from_slice(buf).map_err(|e| e.into())
+}
+
+#[test]
+fn test_cbor_check() {
+    use std::collections::BTreeMap;
+
+    let limits = CborLimits::default();
+
+    let mut value = BTreeMap::new();
+    value.insert("key".to_string(), vec![1u64, 2, 3]);
+
+    let buf = serde_cbor::to_vec(&value).unwrap();
+
+    let res = check(&buf, &limits);
+    assert!(res.is_ok());
+
+    let res = from_slice::<BTreeMap<String, Vec<u64>>>(&buf, &limits);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), value);
+
+    let mut invalid_buf = buf.clone();
+    invalid_buf.push(0);
+
+    let res = check(&invalid_buf, &limits);
+    assert!(res.is_err());
+
+    let res = check(&buf[..buf.len() - 1], &limits);
+    assert!(res.is_err());
+
+    let res = check(&[], &limits);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_cbor_limits() {
+    let limits = CborLimits::new(16, 4, 2);
+
+    let buf = serde_cbor::to_vec(&vec![0u8; 17]).unwrap();
+    let res = check(&buf, &limits);
+    match res {
+        Err(Error::TooLong { .. }) => {}
+        _ => panic!("expected TooLong"),
+    }
+
+    let buf = serde_cbor::to_vec(&vec![0u8; 5]).unwrap();
+    let res = check(&buf, &limits);
+    match res {
+        Err(Error::TooLargeCollection { .. }) => {}
+        _ => panic!("expected TooLargeCollection"),
+    }
+
+    let buf = serde_cbor::to_vec(&vec![vec![vec![0u8]]]).unwrap();
+    let res = check(&buf, &limits);
+    match res {
+        Err(Error::TooDeepNesting { .. }) => {}
+        _ => panic!("expected TooDeepNesting"),
+    }
+
+    // an array claiming 2^32 items in a 5 bytes binary
+    let buf = [0x9a, 0xff, 0xff, 0xff, 0xff];
+    let res = check(&buf, &CborLimits::default());
+    assert!(res.is_err());
+}
@@ -0,0 +1,267 @@
+//! # Checkpoint
+//!
+//! `checkpoint` contains the `Checkpoint` type and functions. A `Checkpoint` is
+//! a summary of the accepted `Transaction`s and of the store of a node, signed
+//! with the node key, so that the state of the node can be audited by third parties.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crate::transaction::Transaction;
+use crypto::ecc::ed25519::{PublicKey, SecretKey, Signature};
+use crypto::hash::{Blake512Hasher, Digest};
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::stats::StoreStats;
+use store::traits::Store;
+
+/// `Checkpoint` is the signed summary of the state of a node. Its digest is
+/// the hash of the ordered ids of the accepted `Transaction`s in the store.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub stage: Stage,
+    pub time: Timestamp,
+    pub digest: Digest,
+    pub accepted_count: u64,
+    pub stats: StoreStats,
+    pub public_key: PublicKey,
+    pub signature: Option<Signature>,
+}
+
+impl Checkpoint {
+    /// `new` creates a new unsigned `Checkpoint` of the store of a stage.
+    pub fn new<S: Store>(store: &S, stage: Stage, public_key: &PublicKey) -> Result<Checkpoint> {
+        let (digest, accepted_count) = Checkpoint::calc_digest(store, stage)?;
+        let stats = store.stats()?;
+
+        let checkpoint = Checkpoint {
+            stage,
+            time: Timestamp::now(),
+            digest,
+            accepted_count,
+            stats,
+            public_key: *public_key,
+            signature: None,
+        };
+
+        Ok(checkpoint)
+    }
+
+    /// `calc_digest` returns the digest of the accepted `Transaction`s in the
+    /// store of a stage, together with their count. A `Transaction` in the store
+    /// is accepted.
+    pub fn calc_digest<S: Store>(store: &S, stage: Stage) -> Result<(Digest, u64)> {
+        let (from, to) = <Transaction as Storable<S>>::stage_range(stage);
+        let mut ids = BTreeSet::new();
+
+        for (key, _) in store.iter(Some(&from), Some(&to))? {
+            let id = Digest::from_slice(&key[2..]).map_err(|_| Error::InvalidId)?;
+            ids.insert(id);
+        }
+
+        let mut buf = Vec::new();
+
+        for id in ids.iter() {
+            buf.extend_from_slice(&id.to_bytes());
+        }
+
+        let digest = Blake512Hasher::hash(&buf);
+
+        Ok((digest, ids.len() as u64))
+    }
+
+    /// `sign_message` returns the binary message to use when signing the `Checkpoint`.
+    pub fn sign_message(&self) -> Result<Vec<u8>> {
+        let mut clone = self.clone();
+        clone.signature = None;
+
+        clone.to_bytes()
+    }
+
+    /// `sign` signs the `Checkpoint` with the node `SecretKey`.
+    pub fn sign(&mut self, secret_key: &SecretKey) -> Result<()> {
+        if secret_key.to_public() != self.public_key {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        let msg = self.sign_message()?;
+        let signature = secret_key.sign(&msg);
+
+        self.signature = Some(signature);
+
+        Ok(())
+    }
+
+    /// `is_signed` returns if the `Checkpoint` is signed.
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    /// `verify_signature` verifies the `Checkpoint` signature.
+    pub fn verify_signature(&self) -> Result<()> {
+        if let Some(signature) = self.signature {
+            let msg = self.sign_message()?;
+
+            self.public_key
+                .verify(&signature, &msg)
+                .map_err(|_| Error::InvalidSignature)
+        } else {
+            let err = Error::NotSigned;
+            Err(err)
+        }
+    }
+
+    /// `validate` validates the `Checkpoint`, verifying its signature.
+    pub fn validate(&self) -> Result<()> {
+        self.time.validate()?;
+
+        self.verify_signature()
+    }
+
+    /// `validate_signed` validates the `Checkpoint` expecting it to be signed
+    /// by a specific `PublicKey`.
+    pub fn validate_signed(&self, public_key: &PublicKey) -> Result<()> {
+        if &self.public_key != public_key {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        self.validate()
+    }
+
+    /// `verify_store` verifies that the `Checkpoint` digest and accepted
+    /// `Transaction`s count match the ones of a store.
+    pub fn verify_store<S: Store>(&self, store: &S) -> Result<()> {
+        let (digest, accepted_count) = Checkpoint::calc_digest(store, self.stage)?;
+
+        if digest != self.digest || accepted_count != self.accepted_count {
+            let err = Error::InvalidChecksum;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Checkpoint` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Checkpoint`.
+    pub fn from_bytes(b: &[u8]) -> Result<Checkpoint> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Checkpoint` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `Checkpoint`.
+    pub fn from_json(s: &str) -> Result<Checkpoint> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_checkpoint_sign() {
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+    let public_key = secret_key.to_public();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.update_id().unwrap();
+
+    let key = <Transaction as Storable<BTreeStore>>::key_to_bytes(stage, &transaction.id).unwrap();
+    store
+        .insert(&key, &transaction.to_bytes().unwrap(), None)
+        .unwrap();
+
+    let res = Checkpoint::new(&store, stage, &public_key);
+    assert!(res.is_ok());
+    let mut checkpoint = res.unwrap();
+    assert_eq!(checkpoint.accepted_count, 1);
+
+    let res = checkpoint.validate();
+    assert!(res.is_err());
+
+    let invalid_secret_key = SecretKey::random().unwrap();
+
+    let res = checkpoint.sign(&invalid_secret_key);
+    assert!(res.is_err());
+
+    let res = checkpoint.sign(&secret_key);
+    assert!(res.is_ok());
+
+    let res = checkpoint.validate_signed(&public_key);
+    assert!(res.is_ok());
+
+    let res = checkpoint.validate_signed(&invalid_secret_key.to_public());
+    assert!(res.is_err());
+
+    let res = checkpoint.verify_store(&store);
+    assert!(res.is_ok());
+
+    let mut other = Transaction::new().unwrap();
+    other.stage = stage;
+    other.nonce += 1;
+    other.update_id().unwrap();
+
+    let key = <Transaction as Storable<BTreeStore>>::key_to_bytes(stage, &other.id).unwrap();
+    store
+        .insert(&key, &other.to_bytes().unwrap(), None)
+        .unwrap();
+
+    let res = checkpoint.verify_store(&store);
+    assert!(res.is_err());
+
+    checkpoint.accepted_count += 1;
+
+    let res = checkpoint.verify_signature();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_checkpoint_serialize() {
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+    let mut checkpoint_a = Checkpoint::new(&store, stage, &secret_key.to_public()).unwrap();
+    checkpoint_a.sign(&secret_key).unwrap();
+
+    let res = checkpoint_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = Checkpoint::from_bytes(&cbor);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), checkpoint_a);
+
+    let res = checkpoint_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = Checkpoint::from_json(&json);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), checkpoint_a);
+}
@@ -0,0 +1,384 @@
+//! # Coin Selection
+//!
+//! `coin_selection` contains the coin selection strategies used by the wallets
+//! to choose the owned accounts funding a payment.
+//!
+//! A `Coin` is the balance of an owned account. The selected coins are spent
+//! whole and the amount exceeding the payment is paid back as change, unless
+//! it is below the dust threshold: in that case it is left in the selected
+//! accounts, so that no dust output is created.
+
+use crate::address::Address;
+use crate::error::Error;
+use crate::result::Result;
+use crypto::random::Random;
+use std::fmt;
+
+/// `Coin` is the spendable amount of an owned account.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub struct Coin {
+    pub address: Address,
+    pub amount: u64,
+}
+
+impl Coin {
+    /// `new` creates a new `Coin`.
+    pub fn new(address: &Address, amount: u64) -> Coin {
+        Coin {
+            address: address.to_owned(),
+            amount,
+        }
+    }
+}
+
+/// `Selection` is the result of a coin selection: the amounts to spend from
+/// the selected coins and the change to pay back.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub struct Selection {
+    pub inputs: Vec<Coin>,
+    pub change: u64,
+}
+
+impl Selection {
+    /// `input_balance` returns the total amount spent by the `Selection`.
+    pub fn input_balance(&self) -> u64 {
+        self.inputs.iter().map(|coin| coin.amount).sum()
+    }
+}
+
+/// `CoinSelection` is the strategy used to select the coins funding a payment.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum CoinSelection {
+    LargestFirst,
+    BranchAndBound,
+    RandomImprove,
+}
+
+impl CoinSelection {
+    /// `VALID_STRATEGIES` sets the valid coin selection strategy names.
+    pub const VALID_STRATEGIES: &'static [&'static str] =
+        &["largest-first", "branch-and-bound", "random-improve"];
+
+    /// `DEFAULT_DUST_THRESHOLD` is the default amount below which the change
+    /// is not paid back.
+    pub const DEFAULT_DUST_THRESHOLD: u64 = 1000;
+
+    /// `MAX_TRIES` is the maximum number of branches explored by the
+    /// branch-and-bound strategy before falling back to largest-first.
+    pub const MAX_TRIES: u32 = 100_000;
+
+    /// `parse` parses a `CoinSelection` from a string.
+    pub fn parse(s: &str) -> Result<CoinSelection> {
+        match s {
+            "largest-first" => Ok(CoinSelection::LargestFirst),
+            "branch-and-bound" => Ok(CoinSelection::BranchAndBound),
+            "random-improve" => Ok(CoinSelection::RandomImprove),
+            _ => {
+                let msg = format!("invalid coin selection strategy: {}", s);
+                let err = Error::Parse { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `select` selects the coins paying an amount, leaving in the coins the
+    /// change below the dust threshold.
+    pub fn select(self, coins: &[Coin], amount: u64, dust: u64) -> Result<Selection> {
+        if amount == 0 {
+            let err = Error::InvalidAmount;
+            return Err(err);
+        }
+
+        let coins: Vec<Coin> = coins
+            .iter()
+            .filter(|coin| coin.amount > 0)
+            .copied()
+            .collect();
+
+        let total = coins
+            .iter()
+            .try_fold(0u64, |total, coin| total.checked_add(coin.amount))
+            .ok_or(Error::InvalidAmount)?;
+
+        if total < amount {
+            let err = Error::InvalidBalance;
+            return Err(err);
+        }
+
+        let selected = match self {
+            CoinSelection::LargestFirst => largest_first(&coins, amount),
+            CoinSelection::BranchAndBound => branch_and_bound(&coins, amount, dust)
+                .unwrap_or_else(|| largest_first(&coins, amount)),
+            CoinSelection::RandomImprove => random_improve(&coins, amount)?,
+        };
+
+        Ok(leave_dust(selected, amount, dust))
+    }
+}
+
+impl Default for CoinSelection {
+    fn default() -> CoinSelection {
+        CoinSelection::LargestFirst
+    }
+}
+
+impl fmt::Display for CoinSelection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoinSelection::LargestFirst => write!(f, "largest-first"),
+            CoinSelection::BranchAndBound => write!(f, "branch-and-bound"),
+            CoinSelection::RandomImprove => write!(f, "random-improve"),
+        }
+    }
+}
+
+/// `sum` returns the total amount of a list of coins.
+fn sum(coins: &[Coin]) -> u64 {
+    coins.iter().map(|coin| coin.amount).sum()
+}
+
+/// `largest_first` selects the largest coins until they cover an amount.
+fn largest_first(coins: &[Coin], amount: u64) -> Vec<Coin> {
+    let mut coins = coins.to_vec();
+    coins.sort_by(|a, b| b.amount.cmp(&a.amount));
+
+    let mut selected = Vec::new();
+    let mut total = 0;
+
+    for coin in coins {
+        if total >= amount {
+            break;
+        }
+
+        total += coin.amount;
+        selected.push(coin);
+    }
+
+    selected
+}
+
+/// `branch_and_bound` searches the coins covering an amount with a change not
+/// above the dust threshold, exploring the largest coins first.
+fn branch_and_bound(coins: &[Coin], amount: u64, dust: u64) -> Option<Vec<Coin>> {
+    let mut coins = coins.to_vec();
+    coins.sort_by(|a, b| b.amount.cmp(&a.amount));
+
+    // NB: remaining[i] is the total of the coins from i on, used to prune
+    // the branches that can not reach the amount.
+    let mut remaining = vec![0u64; coins.len() + 1];
+
+    for i in (0..coins.len()).rev() {
+        remaining[i] = remaining[i + 1] + coins[i].amount;
+    }
+
+    let target = amount.saturating_add(dust);
+    let mut included = vec![false; coins.len()];
+    let mut tries = 0;
+
+    if search(
+        &coins,
+        &remaining,
+        &mut included,
+        0,
+        0,
+        amount,
+        target,
+        &mut tries,
+    ) {
+        let selected = coins
+            .into_iter()
+            .zip(included)
+            .filter(|(_, included)| *included)
+            .map(|(coin, _)| coin)
+            .collect();
+
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+/// `search` is the depth-first search of `branch_and_bound`.
+#[allow(clippy::too_many_arguments)]
+fn search(
+    coins: &[Coin],
+    remaining: &[u64],
+    included: &mut [bool],
+    idx: usize,
+    total: u64,
+    amount: u64,
+    target: u64,
+    tries: &mut u32,
+) -> bool {
+    if total >= amount {
+        return total <= target;
+    }
+
+    if idx == coins.len() || total + remaining[idx] < amount || *tries >= CoinSelection::MAX_TRIES {
+        return false;
+    }
+
+    *tries += 1;
+
+    included[idx] = true;
+
+    if search(
+        coins,
+        remaining,
+        included,
+        idx + 1,
+        total + coins[idx].amount,
+        amount,
+        target,
+        tries,
+    ) {
+        return true;
+    }
+
+    included[idx] = false;
+
+    search(
+        coins,
+        remaining,
+        included,
+        idx + 1,
+        total,
+        amount,
+        target,
+        tries,
+    )
+}
+
+/// `random_improve` selects random coins until they cover an amount, then
+/// adds random coins bringing the total closer to twice the amount, without
+/// exceeding three times the amount, so that the change is comparable to the
+/// payment and can fund later ones.
+fn random_improve(coins: &[Coin], amount: u64) -> Result<Vec<Coin>> {
+    let mut coins = coins.to_vec();
+
+    for i in (1..coins.len()).rev() {
+        let j = Random::u32_range(0, i as u32 + 1)? as usize;
+        coins.swap(i, j);
+    }
+
+    let mut selected = Vec::new();
+    let mut total = 0u64;
+    let mut rest = coins.into_iter();
+
+    while total < amount {
+        match rest.next() {
+            Some(coin) => {
+                total += coin.amount;
+                selected.push(coin);
+            }
+            None => break,
+        }
+    }
+
+    let ideal = amount.saturating_mul(2);
+    let max = amount.saturating_mul(3);
+
+    for coin in rest {
+        let improved = total.saturating_add(coin.amount);
+
+        if improved <= max && ideal.saturating_sub(improved) < ideal.saturating_sub(total) {
+            total = improved;
+            selected.push(coin);
+        }
+    }
+
+    Ok(selected)
+}
+
+/// `leave_dust` returns the `Selection` of the selected coins, leaving the
+/// change not above the dust threshold in the largest coins.
+fn leave_dust(mut selected: Vec<Coin>, amount: u64, dust: u64) -> Selection {
+    let change = sum(&selected) - amount;
+
+    if change == 0 || change > dust {
+        return Selection {
+            inputs: selected,
+            change,
+        };
+    }
+
+    selected.sort_by(|a, b| b.amount.cmp(&a.amount));
+
+    let mut left = change;
+
+    for coin in selected.iter_mut() {
+        let kept = u64::min(left, coin.amount);
+        coin.amount -= kept;
+        left -= kept;
+    }
+
+    let inputs = selected
+        .into_iter()
+        .filter(|coin| coin.amount > 0)
+        .collect();
+
+    Selection { inputs, change: 0 }
+}
+
+#[test]
+fn test_coin_selection_parse() {
+    for name in CoinSelection::VALID_STRATEGIES.iter() {
+        let res = CoinSelection::parse(name);
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap().to_string(), name);
+    }
+
+    let res = CoinSelection::parse("smallest-first");
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_coin_selection_select() {
+    let coins: Vec<Coin> = [500, 3000, 1200, 800, 10_000]
+        .iter()
+        .map(|amount| Coin::new(&Address::random().unwrap(), *amount))
+        .collect();
+
+    for name in CoinSelection::VALID_STRATEGIES.iter() {
+        let strategy = CoinSelection::parse(name).unwrap();
+
+        let res = strategy.select(&coins, 0, 0);
+        assert!(res.is_err());
+
+        let res = strategy.select(&coins, 20_000, 0);
+        assert!(res.is_err());
+
+        let res = strategy.select(&coins, 4000, 100);
+        assert!(res.is_ok());
+
+        let selection = res.unwrap();
+        assert_eq!(selection.input_balance(), 4000 + selection.change);
+        assert!(selection.change == 0 || selection.change > 100);
+
+        for input in selection.inputs.iter() {
+            let coin = coins
+                .iter()
+                .find(|coin| coin.address == input.address)
+                .unwrap();
+            assert!(input.amount <= coin.amount);
+        }
+    }
+
+    let selection = CoinSelection::LargestFirst.select(&coins, 4000, 0).unwrap();
+    assert_eq!(selection.inputs.len(), 1);
+    assert_eq!(selection.inputs[0].amount, 10_000);
+    assert_eq!(selection.change, 6000);
+
+    let selection = CoinSelection::BranchAndBound
+        .select(&coins, 4200, 0)
+        .unwrap();
+    assert_eq!(selection.inputs.len(), 2);
+    assert_eq!(selection.change, 0);
+
+    let selection = CoinSelection::LargestFirst
+        .select(&coins, 9500, 600)
+        .unwrap();
+    assert_eq!(selection.inputs.len(), 1);
+    assert_eq!(selection.inputs[0].amount, 9500);
+    assert_eq!(selection.change, 0);
+}
@@ -8,7 +8,8 @@ use crate::result::Result;
 use crypto::hash::balloon::BalloonParams;
 use crypto::hash::Digest;
 use mining::common::riemmann_zeta_2;
-use mining::miner::Miner;
+use mining::proof::Proof;
+use mining::scheduler::Scheduler;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
@@ -22,15 +23,20 @@ pub struct Coinbase {
     pub custom_digest: Digest,
     pub amount: u64,
     pub params: BalloonParams,
-    pub nonce: u64,
-    pub digest: Digest,
-    pub mined: bool,
+    pub proof: Option<Proof>,
 }
 
 impl Coinbase {
     /// `BASE_AMOUNT` is the coinbase base amount.
     pub const BASE_AMOUNT: u64 = 1_000_000_000;
 
+    /// `EPOCH_LEN` is the number of distances from the eve `Transaction` in a
+    /// coinbase epoch.
+    pub const EPOCH_LEN: u64 = 1000;
+
+    /// `MAX_DIFFICULTY` is the maximum coinbase mining difficulty.
+    pub const MAX_DIFFICULTY: u64 = 512;
+
     /// `new` creates a new unmined `Coinbase`.
     pub fn new(address: &Address, distance: u64, difficulty: u64) -> Result<Coinbase> {
         if distance == 0 && difficulty != 0 {
@@ -68,9 +74,7 @@ impl Coinbase {
 
     /// `clear` clears the `Coinbase` of the mining proof.
     pub fn clear(&mut self) {
-        self.nonce = 0;
-        self.digest = Digest::default();
-        self.mined = false;
+        self.proof = None;
     }
 
     /// `calc_amount` calculates the `Coinbase` amount given the transaction
@@ -79,7 +83,7 @@ impl Coinbase {
         let distance = self.distance;
         let difficulty = self.difficulty;
 
-        if ((distance == 0) ^ (difficulty == 0)) || (difficulty > 512) {
+        if ((distance == 0) ^ (difficulty == 0)) || (difficulty > Coinbase::MAX_DIFFICULTY) {
             let err = Error::OutOfBound;
             return Err(err);
         }
@@ -88,13 +92,18 @@ impl Coinbase {
             return Ok(Coinbase::BASE_AMOUNT);
         }
 
-        let epoch = 1 + (distance as f64 / 1000f64) as u64;
+        let epoch = Coinbase::epoch(distance);
         let res = ((Coinbase::BASE_AMOUNT as f64) * riemmann_zeta_2(epoch)?
             / riemmann_zeta_2(difficulty)?)
         .floor() as u64;
         Ok(res)
     }
 
+    /// `epoch` returns the coinbase epoch of a distance from the `Eve` transaction.
+    pub fn epoch(distance: u64) -> u64 {
+        1 + (distance as f64 / Coinbase::EPOCH_LEN as f64) as u64
+    }
+
     /// `set_amount` sets the `Coinbase` amount give the transaction
     /// distance from the `Eve` transaction and mining difficulty.
     pub fn update_amount(&mut self) -> Result<()> {
@@ -105,7 +114,7 @@ impl Coinbase {
 
     /// `is_mined` returns if the `Coinbase` is mined.
     pub fn is_mined(&self) -> bool {
-        self.mined
+        self.proof.is_some()
     }
 
     /// `mining_message` returns the `Coinbase` mining message
@@ -127,20 +136,27 @@ impl Coinbase {
 
     /// `calc_mining_proof` mines the `Coinbase` without
     /// updating it.
-    pub fn calc_mining_proof(&self, msg: &[u8]) -> Result<(u64, Digest)> {
-        let miner = Miner::new(self.params, self.difficulty)?;
+    pub fn calc_mining_proof(&self, msg: &[u8]) -> Result<Proof> {
         let mmsg = self.mining_message(msg)?;
 
-        miner.mine_message(&mmsg).map_err(|e| e.into())
+        Proof::mine(self.params, self.difficulty, &mmsg).map_err(|e| e.into())
     }
 
     /// `mine` mines the `Coinbase`.
     pub fn mine(&mut self, msg: &[u8]) -> Result<()> {
-        let (nonce, digest) = self.calc_mining_proof(msg)?;
+        let proof = self.calc_mining_proof(msg)?;
 
-        self.nonce = nonce;
-        self.digest = digest;
-        self.mined = true;
+        self.proof = Some(proof);
+
+        Ok(())
+    }
+
+    /// `mine_scheduled` mines the `Coinbase` in a thread scheduled by a `Scheduler`.
+    pub fn mine_scheduled(&mut self, msg: &[u8], scheduler: &Scheduler) -> Result<()> {
+        let mmsg = self.mining_message(msg)?;
+        let proof = Proof::mine_scheduled(self.params, self.difficulty, &mmsg, scheduler)?;
+
+        self.proof = Some(proof);
 
         Ok(())
     }
@@ -149,7 +165,9 @@ impl Coinbase {
     pub fn validate(&self) -> Result<()> {
         self.params.validate()?;
 
-        if ((self.distance == 0) ^ (self.difficulty == 0)) || (self.difficulty > 512) {
+        if ((self.distance == 0) ^ (self.difficulty == 0))
+            || (self.difficulty > Coinbase::MAX_DIFFICULTY)
+        {
             let err = Error::OutOfBound;
             return Err(err);
         }
@@ -161,17 +179,22 @@ impl Coinbase {
     pub fn validate_mined(&self, msg: &[u8]) -> Result<()> {
         self.validate()?;
 
-        if !self.is_mined() {
-            let err = Error::NotMined;
+        let proof = match self.proof {
+            Some(proof) => proof,
+            None => {
+                let err = Error::NotMined;
+                return Err(err);
+            }
+        };
+
+        if proof.params != self.params || proof.difficulty != self.difficulty {
+            let err = Error::InvalidProof;
             return Err(err);
         }
 
-        let miner = Miner::new(self.params, self.difficulty)?;
         let mmsg = self.mining_message(msg)?;
 
-        miner
-            .verify_message_mining(&mmsg, self.nonce, self.digest)
-            .map_err(|e| e.into())
+        proof.verify(&mmsg).map_err(|e| e.into())
     }
 
     /// `to_bytes` converts the `Coinbase` into a CBOR binary.
@@ -203,10 +226,8 @@ impl Default for Coinbase {
             distance: 1,
             difficulty: 1,
             custom_digest: Digest::default(),
-            nonce: 0,
-            digest: Digest::default(),
             amount: 0,
-            mined: false,
+            proof: None,
         }
     }
 }
@@ -375,19 +396,29 @@ fn test_coinbase_mine() {
         let res = coinbase.calc_mining_proof(&msg);
         assert!(res.is_ok());
 
-        let (nonce, digest) = res.unwrap();
+        let proof = res.unwrap();
         let res = coinbase.mine(&msg);
         assert!(res.is_ok());
 
-        assert_eq!(coinbase.nonce, nonce);
-        assert_eq!(coinbase.digest, digest);
+        assert_eq!(coinbase.proof, Some(proof));
 
         let res = coinbase.validate_mined(&msg);
         assert!(res.is_ok());
 
+        let mmsg = coinbase.mining_message(&msg).unwrap();
+        let res = proof.verify(&mmsg);
+        assert!(res.is_ok());
+
+        let mut invalid_coinbase = coinbase;
+        invalid_coinbase.difficulty += 1;
+        invalid_coinbase.update_amount().unwrap();
+
+        let res = invalid_coinbase.validate_mined(&msg);
+        assert!(res.is_err());
+
         coinbase.clear();
-        assert_eq!(coinbase.nonce, 0);
-        assert_eq!(coinbase.digest, Digest::default());
+        assert!(!coinbase.is_mined());
+        assert_eq!(coinbase.proof, None);
     }
 }
 
@@ -2,8 +2,12 @@
 //!
 //! `conflict_set` is the module containing the type used to register mutually conflicting
 //! transactions.
+//!
+//! The `ConflictSet`s are stored keyed by `Address`, so that the sets of the addresses
+//! sharing a prefix form a contiguous shard of the store. The sets of a batch of
+//! addresses are fetched with a single range query per shard instead of one
+//! lookup per address.
 
-use crate::account::Account;
 use crate::address::Address;
 use crate::error::Error;
 use crate::result::Result;
@@ -15,7 +19,8 @@ use crypto::hash::Digest;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use store::index::next_key;
 use store::traits::Store;
 
 /// `ConflictSet` is the set used to represent a set of mutually conflicting transactions.
@@ -30,6 +35,10 @@ pub struct ConflictSet {
 }
 
 impl ConflictSet {
+    /// `SHARD_PREFIX_LEN` is the length of the `Address` prefix identifying the
+    /// shard of a `ConflictSet` in the store.
+    pub const SHARD_PREFIX_LEN: usize = 1;
+
     /// `new` creates a new `ConflictSet`.
     pub fn new(address: Address, stage: Stage) -> ConflictSet {
         let mut set = ConflictSet::default();
@@ -128,6 +137,59 @@ impl ConflictSet {
         Ok(())
     }
 
+    /// `shard` returns the shard of the `ConflictSet` of an `Address`.
+    pub fn shard(address: &Address) -> Vec<u8> {
+        address.to_bytes()[..Self::SHARD_PREFIX_LEN].to_vec()
+    }
+
+    /// `get_batch` returns the `ConflictSet`s of a set of `Address`es found in the
+    /// store. The addresses of a shard are fetched with a single range query from
+    /// the first to the last of them.
+    pub fn get_batch<S: Store>(
+        store: &S,
+        stage: Stage,
+        addresses: &BTreeSet<Address>,
+    ) -> Result<BTreeMap<Address, ConflictSet>> {
+        let mut shards: BTreeMap<Vec<u8>, Vec<Address>> = BTreeMap::new();
+
+        for address in addresses {
+            shards
+                .entry(ConflictSet::shard(address))
+                .or_default()
+                .push(*address);
+        }
+
+        let mut sets = BTreeMap::new();
+
+        for (_, shard_addresses) in shards {
+            let first = shard_addresses[0];
+            let last = shard_addresses[shard_addresses.len() - 1];
+
+            if first == last {
+                if <Self as Storable<S>>::lookup(store, stage, &first)? {
+                    let cs = <Self as Storable<S>>::get(store, stage, &first)?;
+                    sets.insert(first, cs);
+                }
+
+                continue;
+            }
+
+            let from = <Self as Storable<S>>::key_to_bytes(stage, &first)?;
+            let to = <Self as Storable<S>>::key_to_bytes(stage, &last)?;
+            let to = next_key(&to).ok_or(Error::OutOfBound)?;
+
+            for value in store.query(Some(&from), Some(&to), None, None)? {
+                let cs = ConflictSet::from_bytes(&value)?;
+
+                if addresses.contains(&cs.address) {
+                    sets.insert(cs.address, cs);
+                }
+            }
+        }
+
+        Ok(sets)
+    }
+
     /// `clear` clears the `ConflictSet`.
     pub fn clear(&mut self) {
         self.transactions.clear();
@@ -182,11 +244,8 @@ impl<S: Store> Storable<S> for ConflictSet {
 
         value.validate()?;
 
-        if !Account::lookup(store, stage, &value.address)? {
-            let err = Error::NotFound;
-            return Err(err);
-        }
-
+        // NB: the address of a conflict set is the one of an output, which may
+        // have no account yet
         for id in &value.transactions {
             if !Transaction::lookup(store, stage, &id)? {
                 let err = Error::NotFound;
@@ -212,7 +271,10 @@ impl<S: Store> Storable<S> for ConflictSet {
     }
 
     fn validate_all(store: &S, stage: Stage) -> Result<()> {
-        for value in Self::query(store, stage, None, None, None, None)? {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
             Self::validate_single(store, stage, &value)?;
         }
 
@@ -349,7 +411,9 @@ impl<S: Store> Storable<S> for ConflictSet {
         let key = <Self as Storable<S>>::key(value);
         let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
         let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
     }
 
     fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
@@ -524,6 +588,7 @@ fn test_conflict_set_serialize_json() {
 
 #[test]
 fn test_conflict_set_storable() {
+    use crate::account::Account;
     use crate::signers::Signers;
     use crate::wallet::Wallet;
     use store::backend::BTreeStore;
@@ -622,3 +687,58 @@ fn test_conflict_set_storable() {
     let found = res.unwrap();
     assert!(!found);
 }
+
+#[test]
+fn test_conflict_set_get_batch() {
+    use crate::account::Account;
+    use crate::signers::Signers;
+    use crate::wallet::Wallet;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let mut addresses = BTreeSet::new();
+
+    for _ in 0..8 {
+        let wallet = Wallet::new(stage).unwrap();
+        let weight = 1;
+        let signer = wallet.to_signer(weight).unwrap();
+        let mut signers = Signers::new().unwrap();
+        signers.set_threshold(weight).unwrap();
+        signers.add(&signer).unwrap();
+
+        let account = Account::new_eve(stage, &signers).unwrap();
+        Account::create(&mut store, stage, &account).unwrap();
+
+        let cs = ConflictSet::new(account.address(), stage);
+        ConflictSet::create(&mut store, stage, &cs).unwrap();
+
+        addresses.insert(account.address());
+    }
+
+    let mut shard_address = *addresses.iter().next().unwrap();
+    shard_address[ConflictSet::SHARD_PREFIX_LEN] ^= 0xff;
+
+    let mut requested: BTreeSet<Address> = addresses.iter().step_by(2).copied().collect();
+    requested.insert(shard_address);
+    requested.insert(Address::random().unwrap());
+
+    let res = ConflictSet::get_batch(&store, stage, &requested);
+    assert!(res.is_ok());
+
+    let sets = res.unwrap();
+    assert_eq!(sets.len(), (addresses.len() + 1) / 2);
+
+    for (address, cs) in sets.iter() {
+        assert!(addresses.contains(address));
+        assert_eq!(&cs.address, address);
+    }
+
+    let res = ConflictSet::get_batch(&store, stage, &BTreeSet::new());
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_empty());
+}
@@ -2,22 +2,38 @@
 //!
 //! `consensus_message` is the module containing the consensus message type.
 
+use crate::bloom_filter::BloomFilter;
+use crate::cbor::{self, CborLimits};
+use crate::consensus_params::ConsensusParams;
+use crate::consensus_state::TransactionState;
 use crate::error::Error;
-use crate::node::Node;
+use crate::error_code::ErrorCode;
+use crate::node::{Node, Service};
 use crate::result::Result;
 use crate::stage::Stage;
 use crate::timestamp::Timestamp;
 use crate::traits::Storable;
 use crate::transaction::Transaction;
-use byteorder::{BigEndian, WriteBytesExt};
-use crypto::hash::Digest;
-use crypto::random::Random;
+use crate::transaction_proof::TransactionProof;
+use crate::version::Version;
+use crate::work::Work;
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use crypto::hash::digest::DIGEST_LEN;
+use crypto::hash::{Blake512Hasher, Digest};
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use store::traits::Store;
 
+/// `ReplyHints` are the optional hints of a `Reply`: the confidence of the replying
+/// node in the queried `Transaction` and the preferred `Transaction` of its conflict set.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct ReplyHints {
+    pub confidence: Option<u64>,
+    pub preferred: Option<Digest>,
+}
+
 /// `ConsensusMessage` is the type representing a consensus message type.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -86,6 +102,14 @@ pub enum ConsensusMessage {
         time: Timestamp,
         transaction: Transaction,
     },
+    CompactQuery {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        tx_id: Digest,
+        tx_time: Timestamp,
+    },
     Reply {
         id: u64,
         address: Vec<u8>,
@@ -93,10 +117,261 @@ pub enum ConsensusMessage {
         time: Timestamp,
         tx_id: Digest,
         chit: bool,
+        #[serde(default)]
+        confidence: Option<u64>,
+        #[serde(default)]
+        preferred: Option<Digest>,
+    },
+    BatchQuery {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        count: u32,
+        ids: BTreeSet<Digest>,
+        transactions: BTreeSet<Transaction>,
+    },
+    BatchReply {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        count: u32,
+        ids: BTreeSet<Digest>,
+        chits: Vec<u8>,
+    },
+    Inventory {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        count: u32,
+        filter: BloomFilter,
+    },
+    FetchProof {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        tx_id: Digest,
+    },
+    PushProof {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        proof: TransactionProof,
+    },
+    ParamsUpdate {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        params: ConsensusParams,
+    },
+    Hello {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        version: Version,
+        stage: Stage,
+        services: BTreeSet<Service>,
+    },
+    Reject {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        code: u16,
+        reason: String,
+    },
+    GetWork {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+    },
+    PushWork {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        work: Work,
+    },
+    SubmitWork {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        tx_id: Digest,
+        nonce: u64,
+        digest: Digest,
+    },
+    FetchState {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        count: u32,
+        ids: BTreeSet<Digest>,
+    },
+    PushState {
+        id: u64,
+        address: Vec<u8>,
+        node: Node,
+        time: Timestamp,
+        count: u32,
+        ids: BTreeSet<Digest>,
+        states: BTreeSet<TransactionState>,
     },
 }
 
+/// `chits_to_bitmap` packs a sequence of chits in a bitmap.
+fn chits_to_bitmap<I: Iterator<Item = bool>>(chits: I) -> Vec<u8> {
+    let mut bitmap = Vec::new();
+
+    for (i, chit) in chits.enumerate() {
+        if i % 8 == 0 {
+            bitmap.push(0);
+        }
+
+        if chit {
+            bitmap[i / 8] |= 1 << (i % 8);
+        }
+    }
+
+    bitmap
+}
+
+/// `bitmap_len` returns the length of the bitmap of a number of chits.
+fn bitmap_len(count: u32) -> usize {
+    (count as usize + 7) / 8
+}
+
+/// `COMPACT_TAG` is the first byte of a compact `ConsensusMessage`. No CBOR
+/// `ConsensusMessage` starts with it, as CBOR encodes the variants as maps.
+pub const COMPACT_TAG: u8 = 0x00;
+
+/// `COMPACT_QUERY_KIND` is the kind byte of a compact `CompactQuery`.
+const COMPACT_QUERY_KIND: u8 = 0;
+
+/// `COMPACT_REPLY_KIND` is the kind byte of a compact `Reply`.
+const COMPACT_REPLY_KIND: u8 = 1;
+
+/// `COMPACT_CHIT_FLAG` is the flag of the chit of a compact `Reply`.
+const COMPACT_CHIT_FLAG: u8 = 1;
+
+/// `COMPACT_CONFIDENCE_FLAG` is the flag of the confidence hint of a compact `Reply`.
+const COMPACT_CONFIDENCE_FLAG: u8 = 1 << 1;
+
+/// `COMPACT_PREFERRED_FLAG` is the flag of the preferred hint of a compact `Reply`.
+const COMPACT_PREFERRED_FLAG: u8 = 1 << 2;
+
+/// `write_compact_address` writes a length-prefixed address in a compact binary.
+fn write_compact_address(buf: &mut Vec<u8>, address: &[u8]) -> Result<()> {
+    if address.len() > u16::max_value() as usize {
+        let err = Error::InvalidAddress;
+        return Err(err);
+    }
+
+    buf.extend_from_slice(&(address.len() as u16).to_be_bytes());
+    buf.extend_from_slice(address);
+
+    Ok(())
+}
+
+/// `write_compact_header` writes the fields shared by the compact `ConsensusMessage`s:
+/// the id, the time, the sender address and the bare `Node`.
+fn write_compact_header(
+    buf: &mut Vec<u8>,
+    id: u64,
+    address: &[u8],
+    node: &Node,
+    time: Timestamp,
+) -> Result<()> {
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&time.to_i64().to_be_bytes());
+    write_compact_address(buf, address)?;
+    buf.push(node.stage as u8);
+    buf.extend_from_slice(&node.last_seen.to_i64().to_be_bytes());
+    write_compact_address(buf, &node.address)
+}
+
+/// `CompactReader` reads the fields of a compact `ConsensusMessage`.
+struct CompactReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CompactReader<'a> {
+    /// `new` creates a new `CompactReader`.
+    fn new(buf: &'a [u8]) -> CompactReader<'a> {
+        CompactReader { buf, pos: 0 }
+    }
+
+    /// `read` reads a number of bytes.
+    fn read(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buf.len() - self.pos < len {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let res = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+
+        Ok(res)
+    }
+
+    /// `read_u8` reads a byte.
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read(1)?[0])
+    }
+
+    /// `read_u64` reads a big-endian `u64`.
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(BigEndian::read_u64(self.read(8)?))
+    }
+
+    /// `read_i64` reads a big-endian `i64`.
+    fn read_i64(&mut self) -> Result<i64> {
+        Ok(BigEndian::read_i64(self.read(8)?))
+    }
+
+    /// `read_address` reads a length-prefixed address.
+    fn read_address(&mut self) -> Result<Vec<u8>> {
+        let len = BigEndian::read_u16(self.read(2)?) as usize;
+        Ok(self.read(len)?.to_owned())
+    }
+
+    /// `read_digest` reads a `Digest`.
+    fn read_digest(&mut self) -> Result<Digest> {
+        Digest::from_slice(self.read(DIGEST_LEN)?).map_err(|e| e.into())
+    }
+
+    /// `finish` checks that all the bytes were read.
+    fn finish(&self) -> Result<()> {
+        if self.pos != self.buf.len() {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+}
+
 impl ConsensusMessage {
+    /// `MAX_BATCH_LEN` is the maximum number of `Transaction`s in a `BatchQuery`
+    /// or `BatchReply` `ConsensusMessage`.
+    pub const MAX_BATCH_LEN: u32 = 256;
+
+    /// `MAX_REJECT_REASON_LEN` is the maximum length of the reason of a `Reject`.
+    pub const MAX_REJECT_REASON_LEN: usize = 256;
+
+    /// `MAX_STATE_LEN` is the maximum number of `TransactionState`s in a `FetchState`
+    /// or `PushState` `ConsensusMessage`.
+    pub const MAX_STATE_LEN: u32 = 4096;
+
     /// `new_fetch_nodes` creates a new `FetchNodes` `ConsensusMessage`.
     pub fn new_fetch_nodes(
         address: &[u8],
@@ -111,7 +386,7 @@ impl ConsensusMessage {
         }
 
         let message = ConsensusMessage::FetchNodes {
-            id: Random::u64()?,
+            id: 0,
             address: address.to_owned(),
             node: node.to_owned(),
             time: Timestamp::now(),
@@ -119,7 +394,7 @@ impl ConsensusMessage {
             ids: ids.to_owned(),
         };
 
-        Ok(message)
+        message.with_content_id()
     }
 
     /// `new_fetch_random_nodes` creates a new `FetchRandomNodes` `ConsensusMessage`.
@@ -131,14 +406,14 @@ impl ConsensusMessage {
         node.validate()?;
 
         let message = ConsensusMessage::FetchRandomNodes {
-            id: Random::u64()?,
+            id: 0,
             address: address.to_owned(),
             node: node.to_owned(),
             time: Timestamp::now(),
             count,
         };
 
-        Ok(message)
+        message.with_content_id()
     }
 
     /// `new_push_nodes` creates a new `PushNodes` `ConsensusMessage`.
@@ -185,7 +460,7 @@ impl ConsensusMessage {
         }
 
         let message = ConsensusMessage::FetchTransactions {
-            id: Random::u64()?,
+            id: 0,
             address: address.to_owned(),
             node: node.to_owned(),
             time: Timestamp::now(),
@@ -193,7 +468,7 @@ impl ConsensusMessage {
             ids: ids.to_owned(),
         };
 
-        Ok(message)
+        message.with_content_id()
     }
 
     /// `new_fetch_random_transactions` creates a new `FetchRandomTransactions` `ConsensusMessage`.
@@ -205,14 +480,14 @@ impl ConsensusMessage {
         node.validate()?;
 
         let message = ConsensusMessage::FetchRandomTransactions {
-            id: Random::u64()?,
+            id: 0,
             address: address.to_owned(),
             node: node.to_owned(),
             time: Timestamp::now(),
             count,
         };
 
-        Ok(message)
+        message.with_content_id()
     }
 
     /// `new_push_transactions` creates a new `PushTransactions` `ConsensusMessage`.
@@ -267,7 +542,7 @@ impl ConsensusMessage {
         let count = ids.len() as u32;
 
         let message = ConsensusMessage::Mine {
-            id: Random::u64()?,
+            id: 0,
             address: address.to_owned(),
             node: node.to_owned(),
             time: Timestamp::now(),
@@ -276,7 +551,7 @@ impl ConsensusMessage {
             transactions: transactions.to_owned(),
         };
 
-        Ok(message)
+        message.with_content_id()
     }
 
     /// `new_query` creates a new `Query` `ConsensusMessage`.
@@ -289,14 +564,41 @@ impl ConsensusMessage {
         transaction.validate()?;
 
         let message = ConsensusMessage::Query {
-            id: Random::u64()?,
+            id: 0,
             address: address.to_owned(),
             node: node.to_owned(),
             time: Timestamp::now(),
             transaction: transaction.to_owned(),
         };
 
-        Ok(message)
+        message.with_content_id()
+    }
+
+    /// `new_compact_query` creates a new `CompactQuery` `ConsensusMessage`, carrying
+    /// only the id and the time of the queried `Transaction`.
+    pub fn new_compact_query(
+        address: &[u8],
+        node: &Node,
+        transaction: &Transaction,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+        transaction.validate()?;
+
+        if transaction.id == node.id {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        let message = ConsensusMessage::CompactQuery {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            tx_id: transaction.id,
+            tx_time: transaction.time,
+        };
+
+        message.with_content_id()
     }
 
     /// `new_reply` creates a new `Reply` `ConsensusMessage`.
@@ -321,312 +623,669 @@ impl ConsensusMessage {
             time: Timestamp::now(),
             tx_id,
             chit,
+            confidence: None,
+            preferred: None,
         };
 
         Ok(message)
     }
 
-    /// `id` returns the `ConsensusMessage` id.
-    pub fn id(&self) -> u64 {
-        match self {
-            ConsensusMessage::FetchNodes { id, .. } => *id,
-            ConsensusMessage::FetchRandomNodes { id, .. } => *id,
-            ConsensusMessage::PushNodes { id, .. } => *id,
-            ConsensusMessage::FetchTransactions { id, .. } => *id,
-            ConsensusMessage::FetchRandomTransactions { id, .. } => *id,
-            ConsensusMessage::PushTransactions { id, .. } => *id,
-            ConsensusMessage::Mine { id, .. } => *id,
-            ConsensusMessage::Query { id, .. } => *id,
-            ConsensusMessage::Reply { id, .. } => *id,
-        }
-    }
-
-    /// `time` returns the `ConsensusMessage` time.
-    pub fn time(&self) -> Timestamp {
-        match self {
-            ConsensusMessage::FetchNodes { time, .. } => *time,
-            ConsensusMessage::FetchRandomNodes { time, .. } => *time,
-            ConsensusMessage::PushNodes { time, .. } => *time,
-            ConsensusMessage::FetchTransactions { time, .. } => *time,
-            ConsensusMessage::FetchRandomTransactions { time, .. } => *time,
-            ConsensusMessage::PushTransactions { time, .. } => *time,
-            ConsensusMessage::Mine { time, .. } => *time,
-            ConsensusMessage::Query { time, .. } => *time,
-            ConsensusMessage::Reply { time, .. } => *time,
-        }
-    }
-
-    /// `node` returns the `ConsensusMessage` `Node`.
-    pub fn node(&self) -> Node {
-        match self {
-            ConsensusMessage::FetchNodes { node, .. } => node.clone(),
-            ConsensusMessage::FetchRandomNodes { node, .. } => node.clone(),
-            ConsensusMessage::PushNodes { node, .. } => node.clone(),
-            ConsensusMessage::FetchTransactions { node, .. } => node.clone(),
-            ConsensusMessage::FetchRandomTransactions { node, .. } => node.clone(),
-            ConsensusMessage::PushTransactions { node, .. } => node.clone(),
-            ConsensusMessage::Mine { node, .. } => node.clone(),
-            ConsensusMessage::Query { node, .. } => node.clone(),
-            ConsensusMessage::Reply { node, .. } => node.clone(),
-        }
-    }
-
-    /// `validate_fetch_nodes` validates a `FetchTransactions`
-    /// `ConsensusMessage`.
-    pub fn validate_fetch_nodes(&self) -> Result<()> {
+    /// `with_reply_hints` sets the `ReplyHints` of a `Reply` `ConsensusMessage`:
+    /// the confidence of the replying node in the queried `Transaction` and the
+    /// preferred `Transaction` of its conflict set. The hints do not change the chit.
+    pub fn with_reply_hints(self, hints: ReplyHints) -> Result<ConsensusMessage> {
         match self {
-            ConsensusMessage::FetchNodes {
+            ConsensusMessage::Reply {
+                id,
+                address,
                 node,
                 time,
-                count,
-                ids,
+                tx_id,
+                chit,
                 ..
             } => {
-                node.validate()?;
-                time.validate()?;
-
-                if ids.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
-
-                if ids.contains(&node.id) {
+                if hints.preferred == Some(node.id) {
                     let err = Error::InvalidId;
                     return Err(err);
                 }
 
-                Ok(())
+                let message = ConsensusMessage::Reply {
+                    id,
+                    address,
+                    node,
+                    time,
+                    tx_id,
+                    chit,
+                    confidence: hints.confidence,
+                    preferred: hints.preferred,
+                };
+
+                Ok(message)
             }
             _ => Err(Error::InvalidMessage),
         }
     }
 
-    /// `validate_fetch_random_nodes` validates a `FetchRandomTransactions`
-    /// `ConsensusMessage`.
-    pub fn validate_fetch_random_nodes(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::FetchRandomNodes { node, time, .. } => {
-                node.validate()?;
-                time.validate()
-            }
-            _ => Err(Error::InvalidMessage),
+    /// `new_batch_query` creates a new `BatchQuery` `ConsensusMessage`.
+    pub fn new_batch_query(
+        address: &[u8],
+        node: &Node,
+        transactions: &BTreeSet<Transaction>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        if transactions.is_empty() || transactions.len() as u32 > Self::MAX_BATCH_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
         }
-    }
 
-    /// `validate_push_nodes` validates a `PushTransactions`
-    /// `ConsensusMessage`.
-    pub fn validate_push_nodes(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::PushNodes {
-                node,
-                time,
-                count,
-                ids,
-                nodes,
-                ..
-            } => {
-                node.validate()?;
-                time.validate()?;
+        for transaction in transactions.iter() {
+            transaction.validate()?;
+        }
 
-                for node in nodes.iter() {
-                    node.validate()?;
-                }
+        let ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
 
-                if ids.contains(&node.id) {
-                    let err = Error::InvalidId;
-                    return Err(err);
-                }
+        let count = ids.len() as u32;
 
-                if ids.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+        let message = ConsensusMessage::BatchQuery {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            count,
+            ids,
+            transactions: transactions.to_owned(),
+        };
 
-                if ids.len() != nodes.len() {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+        message.with_content_id()
+    }
 
-                let found_ids: BTreeSet<Digest> = nodes.iter().map(|node| node.id).collect();
+    /// `new_batch_reply` creates a new `BatchReply` `ConsensusMessage`.
+    pub fn new_batch_reply(
+        address: &[u8],
+        query_id: u64,
+        node: &Node,
+        chits: &BTreeMap<Digest, bool>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
 
-                if ids != &found_ids {
-                    let err = Error::InvalidTransactions;
-                    return Err(err);
-                }
+        if chits.is_empty() || chits.len() as u32 > Self::MAX_BATCH_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
 
-                Ok(())
-            }
-            _ => Err(Error::InvalidMessage),
+        if chits.contains_key(&node.id) {
+            let err = Error::InvalidId;
+            return Err(err);
         }
-    }
 
-    /// `validate_fetch_transactions` validates a `FetchTransactions`
-    /// `ConsensusMessage`.
-    pub fn validate_fetch_transactions(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::FetchTransactions {
-                node,
-                time,
-                count,
-                ids,
-                ..
-            } => {
-                node.validate()?;
-                time.validate()?;
+        let ids: BTreeSet<Digest> = chits.keys().copied().collect();
 
-                if ids.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+        let count = ids.len() as u32;
 
-                if ids.contains(&node.id) {
-                    let err = Error::InvalidId;
-                    return Err(err);
-                }
+        let message = ConsensusMessage::BatchReply {
+            id: query_id + 1,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            count,
+            ids,
+            chits: chits_to_bitmap(chits.values().copied()),
+        };
 
-                Ok(())
-            }
-            _ => Err(Error::InvalidMessage),
-        }
+        Ok(message)
     }
 
-    /// `validate_fetch_random_transactions` validates a `FetchRandomTransactions`
-    /// `ConsensusMessage`.
-    pub fn validate_fetch_random_transactions(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::FetchRandomTransactions { node, time, .. } => {
-                node.validate()?;
-                time.validate()
-            }
-            _ => Err(Error::InvalidMessage),
+    /// `new_inventory` creates a new `Inventory` `ConsensusMessage`, carrying
+    /// a `BloomFilter` of the known `Transaction` ids and the maximum number of
+    /// missing `Transaction`s to be pushed back.
+    pub fn new_inventory(
+        address: &[u8],
+        node: &Node,
+        count: u32,
+        ids: &BTreeSet<Digest>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        if count == 0 {
+            let err = Error::InvalidLength;
+            return Err(err);
         }
-    }
 
-    /// `validate_push_transactions` validates a `PushTransactions`
-    /// `ConsensusMessage`.
-    pub fn validate_push_transactions(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::PushTransactions {
-                node,
-                time,
-                count,
-                ids,
-                transactions,
-                ..
-            } => {
-                node.validate()?;
-                time.validate()?;
+        let message = ConsensusMessage::Inventory {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            count,
+            filter: BloomFilter::from_digests(ids)?,
+        };
 
-                for transaction in transactions.iter() {
-                    transaction.validate()?;
-                }
+        message.with_content_id()
+    }
 
-                if ids.contains(&node.id) {
-                    let err = Error::InvalidId;
-                    return Err(err);
-                }
+    /// `new_fetch_proof` creates a new `FetchProof` `ConsensusMessage`, requesting
+    /// the `TransactionProof` of a `Transaction`.
+    pub fn new_fetch_proof(address: &[u8], node: &Node, tx_id: Digest) -> Result<ConsensusMessage> {
+        node.validate()?;
 
-                if ids.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+        if tx_id == node.id {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
 
-                if transactions.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+        let message = ConsensusMessage::FetchProof {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            tx_id,
+        };
 
-                let found_ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
+        message.with_content_id()
+    }
 
-                if ids != &found_ids {
-                    let err = Error::InvalidTransactions;
-                    return Err(err);
-                }
+    /// `new_push_proof` creates a new `PushProof` `ConsensusMessage`.
+    pub fn new_push_proof(
+        address: &[u8],
+        fetch_id: u64,
+        node: &Node,
+        proof: &TransactionProof,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+        proof.validate()?;
 
-                Ok(())
-            }
-            _ => Err(Error::InvalidMessage),
-        }
+        let message = ConsensusMessage::PushProof {
+            id: fetch_id + 1,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            proof: proof.to_owned(),
+        };
+
+        Ok(message)
     }
 
-    /// `validate_mine` validates a `Mine` `ConsensusMessage`.
-    pub fn validate_mine(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::Mine {
-                node,
-                time,
-                count,
-                ids,
-                transactions,
-                ..
-            } => {
-                node.validate()?;
-                time.validate()?;
+    /// `new_params_update` creates a new `ParamsUpdate` `ConsensusMessage`,
+    /// relaying a signed `ConsensusParams`.
+    pub fn new_params_update(
+        address: &[u8],
+        node: &Node,
+        params: &ConsensusParams,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+        params.validate()?;
 
-                for transaction in transactions.iter() {
-                    transaction.validate()?;
+        if params.stage != node.stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
 
-                    if transaction.is_mined() {
-                        let err = Error::InvalidTransaction;
-                        return Err(err);
-                    }
-                }
+        let message = ConsensusMessage::ParamsUpdate {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            params: params.to_owned(),
+        };
 
-                if ids.contains(&node.id) {
-                    let err = Error::InvalidId;
-                    return Err(err);
-                }
+        message.with_content_id()
+    }
 
-                if ids.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+    /// `new_hello` creates a new `Hello` `ConsensusMessage`, advertising the
+    /// `Version` and the `Service`s of the sender to a `Node` contacted for the first time.
+    pub fn new_hello(
+        address: &[u8],
+        node: &Node,
+        version: &Version,
+        services: &BTreeSet<Service>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+        version.validate()?;
 
-                if transactions.len() as u32 != *count {
-                    let err = Error::InvalidLength;
-                    return Err(err);
-                }
+        let message = ConsensusMessage::Hello {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            version: version.to_owned(),
+            stage: node.stage,
+            services: services.to_owned(),
+        };
 
-                let found_ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
+        message.with_content_id()
+    }
 
-                if ids != &found_ids {
-                    let err = Error::InvalidTransactions;
-                    return Err(err);
-                }
+    /// `new_hello_reply` creates a new `Hello` `ConsensusMessage` replying to a `Hello`.
+    pub fn new_hello_reply(
+        address: &[u8],
+        hello_id: u64,
+        node: &Node,
+        version: &Version,
+        services: &BTreeSet<Service>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+        version.validate()?;
 
-                Ok(())
-            }
-            _ => Err(Error::InvalidMessage),
-        }
+        let message = ConsensusMessage::Hello {
+            id: hello_id + 1,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            version: version.to_owned(),
+            stage: node.stage,
+            services: services.to_owned(),
+        };
+
+        Ok(message)
     }
 
-    /// `validate_query` validates a `Query` `ConsensusMessage`.
-    pub fn validate_query(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::Query {
-                node,
-                time,
-                transaction,
-                ..
-            } => {
-                node.validate()?;
-                time.validate()?;
-                transaction.validate()
-            }
-            _ => Err(Error::InvalidMessage),
+    /// `new_reject` creates a new `Reject` `ConsensusMessage`, replying to a request
+    /// that failed with the `ErrorCode` of the failure.
+    pub fn new_reject(
+        address: &[u8],
+        request_id: u64,
+        node: &Node,
+        code: ErrorCode,
+        reason: &str,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        let mut reason = reason.to_owned();
+        while reason.len() > Self::MAX_REJECT_REASON_LEN {
+            reason.pop();
         }
-    }
 
-    /// `validate_reply` validates a `Reply` `ConsensusMessage`.
-    pub fn validate_reply(&self) -> Result<()> {
+        let message = ConsensusMessage::Reject {
+            id: request_id + 1,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            code: code.to_u16(),
+            reason,
+        };
+
+        Ok(message)
+    }
+
+    /// `new_get_work` creates a new `GetWork` `ConsensusMessage`, requesting
+    /// a mining `Work` to a miner node.
+    pub fn new_get_work(address: &[u8], node: &Node) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        let message = ConsensusMessage::GetWork {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+        };
+
+        message.with_content_id()
+    }
+
+    /// `new_push_work` creates a new `PushWork` `ConsensusMessage`, replying
+    /// to a `GetWork` with a mining `Work`.
+    pub fn new_push_work(
+        address: &[u8],
+        get_id: u64,
+        node: &Node,
+        work: &Work,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+        work.validate()?;
+
+        let message = ConsensusMessage::PushWork {
+            id: get_id + 1,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            work: work.to_owned(),
+        };
+
+        Ok(message)
+    }
+
+    /// `new_submit_work` creates a new `SubmitWork` `ConsensusMessage`, submitting
+    /// the solved nonce and digest of a mining `Work`.
+    pub fn new_submit_work(
+        address: &[u8],
+        node: &Node,
+        tx_id: Digest,
+        nonce: u64,
+        digest: Digest,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        if tx_id == node.id {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        let message = ConsensusMessage::SubmitWork {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            tx_id,
+            nonce,
+            digest,
+        };
+
+        message.with_content_id()
+    }
+
+    /// `new_fetch_state` creates a new `FetchState` `ConsensusMessage`, requesting
+    /// the `TransactionState`s of known `Transaction`s to a trusted node.
+    pub fn new_fetch_state(
+        address: &[u8],
+        node: &Node,
+        ids: &BTreeSet<Digest>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        if ids.contains(&node.id) {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        let count = ids.len() as u32;
+
+        if count > Self::MAX_STATE_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let message = ConsensusMessage::FetchState {
+            id: 0,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            count,
+            ids: ids.to_owned(),
+        };
+
+        message.with_content_id()
+    }
+
+    /// `new_push_state` creates a new `PushState` `ConsensusMessage`, replying
+    /// to a `FetchState` with the `TransactionState`s of the requested `Transaction`s.
+    pub fn new_push_state(
+        address: &[u8],
+        fetch_id: u64,
+        node: &Node,
+        ids: &BTreeSet<Digest>,
+        states: &BTreeSet<TransactionState>,
+    ) -> Result<ConsensusMessage> {
+        node.validate()?;
+
+        for state in states.iter() {
+            if !ids.contains(&state.tx_id) {
+                let err = Error::InvalidId;
+                return Err(err);
+            }
+        }
+
+        let count = states.len() as u32;
+
+        if ids.len() as u32 > Self::MAX_STATE_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let message = ConsensusMessage::PushState {
+            id: fetch_id + 1,
+            address: address.to_owned(),
+            node: node.to_owned(),
+            time: Timestamp::now(),
+            count,
+            ids: ids.to_owned(),
+            states: states.to_owned(),
+        };
+
+        Ok(message)
+    }
+
+    /// `reject_code` returns the `ErrorCode` of a `Reject` `ConsensusMessage`.
+    pub fn reject_code(&self) -> Result<ErrorCode> {
+        match self {
+            ConsensusMessage::Reject { code, .. } => ErrorCode::from_u16(*code),
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `batch_chits` returns the chits of a `BatchReply` `ConsensusMessage`
+    /// by `Transaction` id.
+    pub fn batch_chits(&self) -> Result<BTreeMap<Digest, bool>> {
+        self.validate_batch_reply()?;
+
+        match self {
+            ConsensusMessage::BatchReply { ids, chits, .. } => {
+                let res = ids
+                    .iter()
+                    .enumerate()
+                    .map(|(i, id)| (*id, chits[i / 8] & (1 << (i % 8)) != 0))
+                    .collect();
+
+                Ok(res)
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `reply_hints` returns the `ReplyHints` of a `Reply` `ConsensusMessage`.
+    pub fn reply_hints(&self) -> Result<ReplyHints> {
         match self {
             ConsensusMessage::Reply {
-                node, time, tx_id, ..
+                confidence,
+                preferred,
+                ..
+            } => {
+                let hints = ReplyHints {
+                    confidence: *confidence,
+                    preferred: *preferred,
+                };
+
+                Ok(hints)
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `id` returns the `ConsensusMessage` id.
+    pub fn id(&self) -> u64 {
+        match self {
+            ConsensusMessage::FetchNodes { id, .. } => *id,
+            ConsensusMessage::FetchRandomNodes { id, .. } => *id,
+            ConsensusMessage::PushNodes { id, .. } => *id,
+            ConsensusMessage::FetchTransactions { id, .. } => *id,
+            ConsensusMessage::FetchRandomTransactions { id, .. } => *id,
+            ConsensusMessage::PushTransactions { id, .. } => *id,
+            ConsensusMessage::Mine { id, .. } => *id,
+            ConsensusMessage::Query { id, .. } => *id,
+            ConsensusMessage::CompactQuery { id, .. } => *id,
+            ConsensusMessage::Reply { id, .. } => *id,
+            ConsensusMessage::BatchQuery { id, .. } => *id,
+            ConsensusMessage::BatchReply { id, .. } => *id,
+            ConsensusMessage::Inventory { id, .. } => *id,
+            ConsensusMessage::FetchProof { id, .. } => *id,
+            ConsensusMessage::PushProof { id, .. } => *id,
+            ConsensusMessage::ParamsUpdate { id, .. } => *id,
+            ConsensusMessage::Hello { id, .. } => *id,
+            ConsensusMessage::Reject { id, .. } => *id,
+            ConsensusMessage::GetWork { id, .. } => *id,
+            ConsensusMessage::PushWork { id, .. } => *id,
+            ConsensusMessage::SubmitWork { id, .. } => *id,
+            ConsensusMessage::FetchState { id, .. } => *id,
+            ConsensusMessage::PushState { id, .. } => *id,
+        }
+    }
+
+    /// `set_id` sets the `ConsensusMessage` id.
+    fn set_id(&mut self, new_id: u64) {
+        match self {
+            ConsensusMessage::FetchNodes { id, .. } => *id = new_id,
+            ConsensusMessage::FetchRandomNodes { id, .. } => *id = new_id,
+            ConsensusMessage::PushNodes { id, .. } => *id = new_id,
+            ConsensusMessage::FetchTransactions { id, .. } => *id = new_id,
+            ConsensusMessage::FetchRandomTransactions { id, .. } => *id = new_id,
+            ConsensusMessage::PushTransactions { id, .. } => *id = new_id,
+            ConsensusMessage::Mine { id, .. } => *id = new_id,
+            ConsensusMessage::Query { id, .. } => *id = new_id,
+            ConsensusMessage::CompactQuery { id, .. } => *id = new_id,
+            ConsensusMessage::Reply { id, .. } => *id = new_id,
+            ConsensusMessage::BatchQuery { id, .. } => *id = new_id,
+            ConsensusMessage::BatchReply { id, .. } => *id = new_id,
+            ConsensusMessage::Inventory { id, .. } => *id = new_id,
+            ConsensusMessage::FetchProof { id, .. } => *id = new_id,
+            ConsensusMessage::PushProof { id, .. } => *id = new_id,
+            ConsensusMessage::ParamsUpdate { id, .. } => *id = new_id,
+            ConsensusMessage::Hello { id, .. } => *id = new_id,
+            ConsensusMessage::Reject { id, .. } => *id = new_id,
+            ConsensusMessage::GetWork { id, .. } => *id = new_id,
+            ConsensusMessage::PushWork { id, .. } => *id = new_id,
+            ConsensusMessage::SubmitWork { id, .. } => *id = new_id,
+            ConsensusMessage::FetchState { id, .. } => *id = new_id,
+            ConsensusMessage::PushState { id, .. } => *id = new_id,
+        }
+    }
+
+    /// `content_id` returns the id derived from the hash of the `ConsensusMessage`
+    /// content. The id is always even, so that the id of a reply, which is the
+    /// id of its request plus one, never overflows or collides with a request id.
+    pub fn content_id(&self) -> Result<u64> {
+        let mut message = self.clone();
+        message.set_id(0);
+
+        let digest = message.digest()?;
+        let id = BigEndian::read_u64(&digest.to_bytes()[0..8]) & !1;
+
+        Ok(id)
+    }
+
+    /// `with_content_id` returns the `ConsensusMessage` with its id set to its content id.
+    fn with_content_id(mut self) -> Result<ConsensusMessage> {
+        let id = self.content_id()?;
+        self.set_id(id);
+
+        Ok(self)
+    }
+
+    /// `digest` returns the hash of the `ConsensusMessage`, used to detect
+    /// duplicated or replayed `ConsensusMessage`s.
+    pub fn digest(&self) -> Result<Digest> {
+        let buf = self.to_bytes()?;
+        let digest = Blake512Hasher::hash(&buf);
+
+        Ok(digest)
+    }
+
+    /// `time` returns the `ConsensusMessage` time.
+    pub fn time(&self) -> Timestamp {
+        match self {
+            ConsensusMessage::FetchNodes { time, .. } => *time,
+            ConsensusMessage::FetchRandomNodes { time, .. } => *time,
+            ConsensusMessage::PushNodes { time, .. } => *time,
+            ConsensusMessage::FetchTransactions { time, .. } => *time,
+            ConsensusMessage::FetchRandomTransactions { time, .. } => *time,
+            ConsensusMessage::PushTransactions { time, .. } => *time,
+            ConsensusMessage::Mine { time, .. } => *time,
+            ConsensusMessage::Query { time, .. } => *time,
+            ConsensusMessage::CompactQuery { time, .. } => *time,
+            ConsensusMessage::Reply { time, .. } => *time,
+            ConsensusMessage::BatchQuery { time, .. } => *time,
+            ConsensusMessage::BatchReply { time, .. } => *time,
+            ConsensusMessage::Inventory { time, .. } => *time,
+            ConsensusMessage::FetchProof { time, .. } => *time,
+            ConsensusMessage::PushProof { time, .. } => *time,
+            ConsensusMessage::ParamsUpdate { time, .. } => *time,
+            ConsensusMessage::Hello { time, .. } => *time,
+            ConsensusMessage::Reject { time, .. } => *time,
+            ConsensusMessage::GetWork { time, .. } => *time,
+            ConsensusMessage::PushWork { time, .. } => *time,
+            ConsensusMessage::SubmitWork { time, .. } => *time,
+            ConsensusMessage::FetchState { time, .. } => *time,
+            ConsensusMessage::PushState { time, .. } => *time,
+        }
+    }
+
+    /// `node` returns the `ConsensusMessage` `Node`.
+    pub fn node(&self) -> Node {
+        match self {
+            ConsensusMessage::FetchNodes { node, .. } => node.clone(),
+            ConsensusMessage::FetchRandomNodes { node, .. } => node.clone(),
+            ConsensusMessage::PushNodes { node, .. } => node.clone(),
+            ConsensusMessage::FetchTransactions { node, .. } => node.clone(),
+            ConsensusMessage::FetchRandomTransactions { node, .. } => node.clone(),
+            ConsensusMessage::PushTransactions { node, .. } => node.clone(),
+            ConsensusMessage::Mine { node, .. } => node.clone(),
+            ConsensusMessage::Query { node, .. } => node.clone(),
+            ConsensusMessage::CompactQuery { node, .. } => node.clone(),
+            ConsensusMessage::Reply { node, .. } => node.clone(),
+            ConsensusMessage::BatchQuery { node, .. } => node.clone(),
+            ConsensusMessage::BatchReply { node, .. } => node.clone(),
+            ConsensusMessage::Inventory { node, .. } => node.clone(),
+            ConsensusMessage::FetchProof { node, .. } => node.clone(),
+            ConsensusMessage::PushProof { node, .. } => node.clone(),
+            ConsensusMessage::ParamsUpdate { node, .. } => node.clone(),
+            ConsensusMessage::Hello { node, .. } => node.clone(),
+            ConsensusMessage::Reject { node, .. } => node.clone(),
+            ConsensusMessage::GetWork { node, .. } => node.clone(),
+            ConsensusMessage::PushWork { node, .. } => node.clone(),
+            ConsensusMessage::SubmitWork { node, .. } => node.clone(),
+            ConsensusMessage::FetchState { node, .. } => node.clone(),
+            ConsensusMessage::PushState { node, .. } => node.clone(),
+        }
+    }
+
+    /// `address` returns the address of the `ConsensusMessage` sender.
+    pub fn address(&self) -> Vec<u8> {
+        match self {
+            ConsensusMessage::FetchNodes { address, .. } => address.clone(),
+            ConsensusMessage::FetchRandomNodes { address, .. } => address.clone(),
+            ConsensusMessage::PushNodes { address, .. } => address.clone(),
+            ConsensusMessage::FetchTransactions { address, .. } => address.clone(),
+            ConsensusMessage::FetchRandomTransactions { address, .. } => address.clone(),
+            ConsensusMessage::PushTransactions { address, .. } => address.clone(),
+            ConsensusMessage::Mine { address, .. } => address.clone(),
+            ConsensusMessage::Query { address, .. } => address.clone(),
+            ConsensusMessage::CompactQuery { address, .. } => address.clone(),
+            ConsensusMessage::Reply { address, .. } => address.clone(),
+            ConsensusMessage::BatchQuery { address, .. } => address.clone(),
+            ConsensusMessage::BatchReply { address, .. } => address.clone(),
+            ConsensusMessage::Inventory { address, .. } => address.clone(),
+            ConsensusMessage::FetchProof { address, .. } => address.clone(),
+            ConsensusMessage::PushProof { address, .. } => address.clone(),
+            ConsensusMessage::ParamsUpdate { address, .. } => address.clone(),
+            ConsensusMessage::Hello { address, .. } => address.clone(),
+            ConsensusMessage::Reject { address, .. } => address.clone(),
+            ConsensusMessage::GetWork { address, .. } => address.clone(),
+            ConsensusMessage::PushWork { address, .. } => address.clone(),
+            ConsensusMessage::SubmitWork { address, .. } => address.clone(),
+            ConsensusMessage::FetchState { address, .. } => address.clone(),
+            ConsensusMessage::PushState { address, .. } => address.clone(),
+        }
+    }
+
+    /// `validate_fetch_nodes` validates a `FetchTransactions`
+    /// `ConsensusMessage`.
+    pub fn validate_fetch_nodes(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchNodes {
+                node,
+                time,
+                count,
+                ids,
+                ..
             } => {
                 node.validate()?;
                 time.validate()?;
 
-                if tx_id == &node.id {
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.contains(&node.id) {
                     let err = Error::InvalidId;
                     return Err(err);
                 }
@@ -637,466 +1296,1968 @@ impl ConsensusMessage {
         }
     }
 
-    /// `is_fetch_nodes` returns if the `ConsensusMessage` is a `FetchNodes` message.
-    pub fn is_fetch_nodes(&self) -> Result<bool> {
-        self.validate()?;
+    /// `validate_fetch_random_nodes` validates a `FetchRandomTransactions`
+    /// `ConsensusMessage`.
+    pub fn validate_fetch_random_nodes(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchRandomNodes { node, time, .. } => {
+                node.validate()?;
+                time.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_push_nodes` validates a `PushTransactions`
+    /// `ConsensusMessage`.
+    pub fn validate_push_nodes(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::PushNodes {
+                node,
+                time,
+                count,
+                ids,
+                nodes,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                for node in nodes.iter() {
+                    node.validate()?;
+                }
+
+                if ids.contains(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.len() != nodes.len() {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                let found_ids: BTreeSet<Digest> = nodes.iter().map(|node| node.id).collect();
+
+                if ids != &found_ids {
+                    let err = Error::InvalidTransactions;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_fetch_transactions` validates a `FetchTransactions`
+    /// `ConsensusMessage`.
+    pub fn validate_fetch_transactions(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchTransactions {
+                node,
+                time,
+                count,
+                ids,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.contains(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_fetch_random_transactions` validates a `FetchRandomTransactions`
+    /// `ConsensusMessage`.
+    pub fn validate_fetch_random_transactions(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchRandomTransactions { node, time, .. } => {
+                node.validate()?;
+                time.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_push_transactions` validates a `PushTransactions`
+    /// `ConsensusMessage`.
+    pub fn validate_push_transactions(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::PushTransactions {
+                node,
+                time,
+                count,
+                ids,
+                transactions,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                for transaction in transactions.iter() {
+                    transaction.validate()?;
+                }
+
+                if ids.contains(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if transactions.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                let found_ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
+
+                if ids != &found_ids {
+                    let err = Error::InvalidTransactions;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_mine` validates a `Mine` `ConsensusMessage`.
+    pub fn validate_mine(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::Mine {
+                node,
+                time,
+                count,
+                ids,
+                transactions,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                for transaction in transactions.iter() {
+                    transaction.validate()?;
+
+                    if transaction.is_mined() {
+                        let err = Error::InvalidTransaction;
+                        return Err(err);
+                    }
+                }
+
+                if ids.contains(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if transactions.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                let found_ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
+
+                if ids != &found_ids {
+                    let err = Error::InvalidTransactions;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_query` validates a `Query` `ConsensusMessage`.
+    pub fn validate_query(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::Query {
+                node,
+                time,
+                transaction,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+                transaction.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_compact_query` validates a `CompactQuery` `ConsensusMessage`.
+    pub fn validate_compact_query(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::CompactQuery {
+                node,
+                time,
+                tx_id,
+                tx_time,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+                tx_time.validate()?;
+
+                if tx_id == &node.id {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_reply` validates a `Reply` `ConsensusMessage`.
+    pub fn validate_reply(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::Reply {
+                node,
+                time,
+                tx_id,
+                preferred,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if tx_id == &node.id || preferred.as_ref() == Some(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_batch_query` validates a `BatchQuery` `ConsensusMessage`.
+    pub fn validate_batch_query(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::BatchQuery {
+                node,
+                time,
+                count,
+                ids,
+                transactions,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                for transaction in transactions.iter() {
+                    transaction.validate()?;
+                }
+
+                if *count == 0 || *count > Self::MAX_BATCH_LEN {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if transactions.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                let found_ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
+
+                if ids != &found_ids {
+                    let err = Error::InvalidTransactions;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_batch_reply` validates a `BatchReply` `ConsensusMessage`.
+    pub fn validate_batch_reply(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::BatchReply {
+                node,
+                time,
+                count,
+                ids,
+                chits,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if *count == 0 || *count > Self::MAX_BATCH_LEN {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.len() as u32 != *count {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if chits.len() != bitmap_len(*count) {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.contains(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_inventory` validates an `Inventory` `ConsensusMessage`.
+    pub fn validate_inventory(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::Inventory {
+                node,
+                time,
+                count,
+                filter,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if *count == 0 {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                filter.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_fetch_proof` validates a `FetchProof` `ConsensusMessage`.
+    pub fn validate_fetch_proof(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchProof {
+                node, time, tx_id, ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if tx_id == &node.id {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_push_proof` validates a `PushProof` `ConsensusMessage`.
+    pub fn validate_push_proof(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::PushProof {
+                node, time, proof, ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+                proof.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_params_update` validates a `ParamsUpdate` `ConsensusMessage`.
+    pub fn validate_params_update(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::ParamsUpdate {
+                node, time, params, ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if params.stage != node.stage {
+                    let err = Error::InvalidStage;
+                    return Err(err);
+                }
+
+                params.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_hello` validates a `Hello` `ConsensusMessage`.
+    pub fn validate_hello(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::Hello {
+                node,
+                time,
+                version,
+                stage,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+                version.validate()?;
+
+                if *stage != node.stage {
+                    let err = Error::InvalidStage;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_reject` validates a `Reject` `ConsensusMessage`.
+    pub fn validate_reject(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::Reject {
+                id,
+                node,
+                time,
+                code,
+                reason,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+                ErrorCode::from_u16(*code)?;
+
+                if id % 2 == 0 {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                if reason.len() > Self::MAX_REJECT_REASON_LEN {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_get_work` validates a `GetWork` `ConsensusMessage`.
+    pub fn validate_get_work(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::GetWork { node, time, .. } => {
+                node.validate()?;
+                time.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_push_work` validates a `PushWork` `ConsensusMessage`.
+    pub fn validate_push_work(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::PushWork {
+                node, time, work, ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+                work.validate()
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_submit_work` validates a `SubmitWork` `ConsensusMessage`.
+    pub fn validate_submit_work(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::SubmitWork {
+                node, time, tx_id, ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if tx_id == &node.id {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_fetch_state` validates a `FetchState` `ConsensusMessage`.
+    pub fn validate_fetch_state(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchState {
+                node,
+                time,
+                count,
+                ids,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if ids.len() as u32 != *count || *count > Self::MAX_STATE_LEN {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                if ids.contains(&node.id) {
+                    let err = Error::InvalidId;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `validate_push_state` validates a `PushState` `ConsensusMessage`.
+    pub fn validate_push_state(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::PushState {
+                node,
+                time,
+                count,
+                ids,
+                states,
+                ..
+            } => {
+                node.validate()?;
+                time.validate()?;
+
+                if states.len() as u32 != *count || ids.len() as u32 > Self::MAX_STATE_LEN {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                for state in states.iter() {
+                    if !ids.contains(&state.tx_id) {
+                        let err = Error::InvalidId;
+                        return Err(err);
+                    }
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    /// `is_fetch_nodes` returns if the `ConsensusMessage` is a `FetchNodes` message.
+    pub fn is_fetch_nodes(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::FetchNodes { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_fetch_random_nodes` returns if the `ConsensusMessage` is a
+    /// `FetchRandomNodes` message.
+    pub fn is_fetch_random_nodes(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::FetchRandomNodes { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_push_nodes` returns if the `ConsensusMessage` is a `PushNodes` message.
+    pub fn is_push_nodes(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::PushNodes { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_fetch_transactions` returns if the `ConsensusMessage` is a `FetchTransactions` message.
+    pub fn is_fetch_transactions(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::FetchTransactions { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_fetch_random_transactions` returns if the `ConsensusMessage` is a
+    /// `FetchRandomTransactions` message.
+    pub fn is_fetch_random_transactions(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::FetchRandomTransactions { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_push_transactions` returns if the `ConsensusMessage` is a `PushTransactions` message.
+    pub fn is_push_transactions(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::PushTransactions { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_mine` returns if the `ConsensusMessage` is a `Mine` message.
+    pub fn is_mine(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::Mine { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_query` returns if the `ConsensusMessage` is a `Query` message.
+    pub fn is_query(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::Query { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_compact_query` returns if the `ConsensusMessage` is a `CompactQuery` message.
+    pub fn is_compact_query(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::CompactQuery { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_reply` returns if the `ConsensusMessage` is a `Reply` message.
+    pub fn is_reply(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::Reply { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_batch_query` returns if the `ConsensusMessage` is a `BatchQuery` message.
+    pub fn is_batch_query(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::BatchQuery { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_batch_reply` returns if the `ConsensusMessage` is a `BatchReply` message.
+    pub fn is_batch_reply(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::BatchReply { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_inventory` returns if the `ConsensusMessage` is an `Inventory` message.
+    pub fn is_inventory(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::Inventory { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_fetch_proof` returns if the `ConsensusMessage` is a `FetchProof` message.
+    pub fn is_fetch_proof(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::FetchProof { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_push_proof` returns if the `ConsensusMessage` is a `PushProof` message.
+    pub fn is_push_proof(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::PushProof { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_params_update` returns if the `ConsensusMessage` is a `ParamsUpdate` message.
+    pub fn is_params_update(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::ParamsUpdate { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_hello` returns if the `ConsensusMessage` is a `Hello` message.
+    pub fn is_hello(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::Hello { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_reject` returns if the `ConsensusMessage` is a `Reject` message.
+    pub fn is_reject(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::Reject { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_get_work` returns if the `ConsensusMessage` is a `GetWork` message.
+    pub fn is_get_work(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::GetWork { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_push_work` returns if the `ConsensusMessage` is a `PushWork` message.
+    pub fn is_push_work(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::PushWork { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_submit_work` returns if the `ConsensusMessage` is a `SubmitWork` message.
+    pub fn is_submit_work(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::SubmitWork { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_fetch_state` returns if the `ConsensusMessage` is a `FetchState` message.
+    pub fn is_fetch_state(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::FetchState { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `is_push_state` returns if the `ConsensusMessage` is a `PushState` message.
+    pub fn is_push_state(&self) -> Result<bool> {
+        self.validate()?;
+
+        let res = match self {
+            ConsensusMessage::PushState { .. } => true,
+            _ => false,
+        };
+
+        Ok(res)
+    }
+
+    /// `validate` validates a `ConsensusMessage`.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ConsensusMessage::FetchNodes { .. } => self.validate_fetch_nodes(),
+            ConsensusMessage::FetchRandomNodes { .. } => self.validate_fetch_random_nodes(),
+            ConsensusMessage::PushNodes { .. } => self.validate_push_nodes(),
+            ConsensusMessage::FetchTransactions { .. } => self.validate_fetch_transactions(),
+            ConsensusMessage::FetchRandomTransactions { .. } => {
+                self.validate_fetch_random_transactions()
+            }
+            ConsensusMessage::PushTransactions { .. } => self.validate_push_transactions(),
+            ConsensusMessage::Mine { .. } => self.validate_mine(),
+            ConsensusMessage::Query { .. } => self.validate_query(),
+            ConsensusMessage::CompactQuery { .. } => self.validate_compact_query(),
+            ConsensusMessage::Reply { .. } => self.validate_reply(),
+            ConsensusMessage::BatchQuery { .. } => self.validate_batch_query(),
+            ConsensusMessage::BatchReply { .. } => self.validate_batch_reply(),
+            ConsensusMessage::Inventory { .. } => self.validate_inventory(),
+            ConsensusMessage::FetchProof { .. } => self.validate_fetch_proof(),
+            ConsensusMessage::PushProof { .. } => self.validate_push_proof(),
+            ConsensusMessage::ParamsUpdate { .. } => self.validate_params_update(),
+            ConsensusMessage::Hello { .. } => self.validate_hello(),
+            ConsensusMessage::Reject { .. } => self.validate_reject(),
+            ConsensusMessage::GetWork { .. } => self.validate_get_work(),
+            ConsensusMessage::PushWork { .. } => self.validate_push_work(),
+            ConsensusMessage::SubmitWork { .. } => self.validate_submit_work(),
+            ConsensusMessage::FetchState { .. } => self.validate_fetch_state(),
+            ConsensusMessage::PushState { .. } => self.validate_push_state(),
+        }
+    }
+
+    /// `to_bytes` converts the `ConsensusMessage` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `ConsensusMessage`.
+    /// The binary is checked against the default `CborLimits` before decoding.
+    pub fn from_bytes(b: &[u8]) -> Result<ConsensusMessage> {
+        Self::from_bytes_with_limits(b, &CborLimits::default())
+    }
+
+    /// `from_bytes_with_limits` converts a CBOR binary into an `ConsensusMessage`,
+    /// checking it against a `CborLimits` before decoding.
+    pub fn from_bytes_with_limits(b: &[u8], limits: &CborLimits) -> Result<ConsensusMessage> {
+        cbor::from_slice(b, limits)
+    }
+
+    /// `is_compact_encodable` returns if the `ConsensusMessage` has a compact binary
+    /// encoding: it is a `CompactQuery` or a `Reply` and its `Node` is a bare one,
+    /// carrying only its stage, its address and its last seen time.
+    pub fn is_compact_encodable(&self) -> bool {
+        match self {
+            ConsensusMessage::CompactQuery { node, .. } | ConsensusMessage::Reply { node, .. } => {
+                let mut bare = Node::new(node.stage, &node.address);
+                bare.last_seen = node.last_seen;
+
+                node == &bare
+            }
+            _ => false,
+        }
+    }
+
+    /// `is_compact_bytes` returns if a binary is a compact `ConsensusMessage`.
+    pub fn is_compact_bytes(b: &[u8]) -> bool {
+        b.first() == Some(&COMPACT_TAG)
+    }
+
+    /// `to_compact_bytes` converts the `ConsensusMessage` into its compact binary
+    /// encoding, if it has one.
+    pub fn to_compact_bytes(&self) -> Result<Vec<u8>> {
+        if !self.is_compact_encodable() {
+            let err = Error::InvalidMessage;
+            return Err(err);
+        }
+
+        let mut buf = vec![COMPACT_TAG];
+
+        match self {
+            ConsensusMessage::CompactQuery {
+                id,
+                address,
+                node,
+                time,
+                tx_id,
+                tx_time,
+            } => {
+                buf.push(COMPACT_QUERY_KIND);
+                write_compact_header(&mut buf, *id, address, node, *time)?;
+                buf.extend_from_slice(&tx_id.to_bytes());
+                buf.extend_from_slice(&tx_time.to_i64().to_be_bytes());
+            }
+            ConsensusMessage::Reply {
+                id,
+                address,
+                node,
+                time,
+                tx_id,
+                chit,
+                confidence,
+                preferred,
+            } => {
+                buf.push(COMPACT_REPLY_KIND);
+                write_compact_header(&mut buf, *id, address, node, *time)?;
+                buf.extend_from_slice(&tx_id.to_bytes());
+
+                let mut flags = 0;
+
+                if *chit {
+                    flags |= COMPACT_CHIT_FLAG;
+                }
+
+                if confidence.is_some() {
+                    flags |= COMPACT_CONFIDENCE_FLAG;
+                }
+
+                if preferred.is_some() {
+                    flags |= COMPACT_PREFERRED_FLAG;
+                }
+
+                buf.push(flags);
+
+                if let Some(confidence) = confidence {
+                    buf.extend_from_slice(&confidence.to_be_bytes());
+                }
+
+                if let Some(preferred) = preferred {
+                    buf.extend_from_slice(&preferred.to_bytes());
+                }
+            }
+            _ => {
+                let err = Error::InvalidMessage;
+                return Err(err);
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// `from_compact_bytes` converts a compact binary into a `ConsensusMessage`.
+    pub fn from_compact_bytes(b: &[u8]) -> Result<ConsensusMessage> {
+        let mut reader = CompactReader::new(b);
+
+        if reader.read_u8()? != COMPACT_TAG {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        let kind = reader.read_u8()?;
+        let id = reader.read_u64()?;
+        let time = Timestamp::from_i64(reader.read_i64()?)?;
+        let address = reader.read_address()?;
+        let stage = Stage::from_u8(reader.read_u8()?)?;
+        let last_seen = Timestamp::from_i64(reader.read_i64()?)?;
+
+        let mut node = Node::new(stage, &reader.read_address()?);
+        node.last_seen = last_seen;
+
+        let tx_id = reader.read_digest()?;
+
+        let message = match kind {
+            COMPACT_QUERY_KIND => {
+                let tx_time = Timestamp::from_i64(reader.read_i64()?)?;
+
+                ConsensusMessage::CompactQuery {
+                    id,
+                    address,
+                    node,
+                    time,
+                    tx_id,
+                    tx_time,
+                }
+            }
+            COMPACT_REPLY_KIND => {
+                let flags = reader.read_u8()?;
+
+                let confidence = if flags & COMPACT_CONFIDENCE_FLAG != 0 {
+                    Some(reader.read_u64()?)
+                } else {
+                    None
+                };
+
+                let preferred = if flags & COMPACT_PREFERRED_FLAG != 0 {
+                    Some(reader.read_digest()?)
+                } else {
+                    None
+                };
+
+                ConsensusMessage::Reply {
+                    id,
+                    address,
+                    node,
+                    time,
+                    tx_id,
+                    chit: flags & COMPACT_CHIT_FLAG != 0,
+                    confidence,
+                    preferred,
+                }
+            }
+            _ => {
+                let err = Error::InvalidEncoding;
+                return Err(err);
+            }
+        };
+
+        reader.finish()?;
+        message.validate()?;
+
+        Ok(message)
+    }
+
+    /// `to_json` converts the `ConsensusMessage` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `ConsensusMessage`.
+    pub fn from_json(s: &str) -> Result<ConsensusMessage> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+
+    /// `insert_with_ttl` inserts a `ConsensusMessage` in the store, expiring
+    /// after a time-to-live in seconds.
+    pub fn insert_with_ttl<S: Store>(
+        store: &mut S,
+        stage: Stage,
+        value: &ConsensusMessage,
+        ttl: Option<u64>,
+    ) -> Result<()> {
+        <Self as Storable<S>>::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, ttl)
+            .map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for ConsensusMessage {
+    const KEY_PREFIX: u8 = 7;
+
+    type Key = u64;
+
+    fn key(&self) -> Self::Key {
+        self.id()
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.write_u64::<BigEndian>(*key)?;
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.node().stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+
+        let mut _from = Vec::new();
+        _from.push(stage as u8);
+        _from.push(<Self as Storable<S>>::KEY_PREFIX);
+        _from.write_u64::<BigEndian>(0)?;
+        let from = Some(_from);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let mut _to = Vec::new();
+        _to.push(stage as u8);
+        _to.push(<Self as Storable<S>>::KEY_PREFIX + 1);
+        _to.write_u64::<BigEndian>(0)?;
+        let to = Some(_to);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(from, to)? {
+            let msg = ConsensusMessage::from_bytes(&value)?;
+            if msg.time() < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_consensus_message() {
+    use crypto::random::Random;
+
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let query_id = Random::u64().unwrap();
+    let tx_id = Digest::random().unwrap();
+    let chit = Random::u32_range(0, 2).unwrap() != 0;
+
+    let mut invalid_node = node.clone();
+    invalid_node.id = Digest::default();
+
+    let res = ConsensusMessage::new_reply(&address, query_id, &invalid_node, tx_id, chit);
+    assert!(res.is_err());
+
+    let res = ConsensusMessage::new_reply(&address, query_id, &node, node.id, chit);
+    assert!(res.is_err());
+
+    let res = ConsensusMessage::new_reply(&address, query_id, &node, tx_id, chit);
+    assert!(res.is_ok());
+
+    let cons_msg = res.unwrap();
+
+    let res = cons_msg.validate_query();
+    assert!(res.is_err());
+
+    let res = cons_msg.validate_reply();
+    assert!(res.is_ok());
+
+    let res = cons_msg.validate();
+    assert!(res.is_ok());
+
+    assert_eq!(cons_msg.reply_hints().unwrap(), ReplyHints::default());
+
+    let hints = ReplyHints {
+        confidence: Some(Random::u64().unwrap()),
+        preferred: Some(Digest::random().unwrap()),
+    };
+
+    let res = cons_msg.clone().with_reply_hints(hints);
+    assert!(res.is_ok());
+
+    let hinted_msg = res.unwrap();
+    assert_eq!(hinted_msg.reply_hints().unwrap(), hints);
+
+    let res = hinted_msg.validate();
+    assert!(res.is_ok());
+
+    let res = ConsensusMessage::from_bytes(&hinted_msg.to_bytes().unwrap());
+    assert_eq!(res.unwrap(), hinted_msg);
+
+    let invalid_hints = ReplyHints {
+        confidence: None,
+        preferred: Some(node.id),
+    };
+
+    let res = cons_msg.clone().with_reply_hints(invalid_hints);
+    assert!(res.is_err());
+
+    let cons_msg = ConsensusMessage::Reply {
+        address,
+        id: query_id,
+        node: invalid_node.clone(),
+        time: Timestamp::now(),
+        tx_id,
+        chit,
+        confidence: None,
+        preferred: None,
+    };
+
+    let res = cons_msg.validate();
+    assert!(res.is_err());
+
+    let res = cons_msg.validate_reply();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_consensus_message_batch() {
+    use crypto::random::Random;
+
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+
+    let transactions: BTreeSet<Transaction> =
+        (0..10).map(|_| Transaction::new().unwrap()).collect();
 
-        let res = match self {
-            ConsensusMessage::FetchNodes { .. } => true,
-            _ => false,
-        };
+    let res = ConsensusMessage::new_batch_query(&address, &node, &BTreeSet::new());
+    assert!(res.is_err());
 
-        Ok(res)
-    }
+    let res = ConsensusMessage::new_batch_query(&address, &node, &transactions);
+    assert!(res.is_ok());
 
-    /// `is_fetch_random_nodes` returns if the `ConsensusMessage` is a
-    /// `FetchRandomNodes` message.
-    pub fn is_fetch_random_nodes(&self) -> Result<bool> {
-        self.validate()?;
+    let query = res.unwrap();
 
-        let res = match self {
-            ConsensusMessage::FetchRandomNodes { .. } => true,
-            _ => false,
-        };
+    let res = query.validate_batch_query();
+    assert!(res.is_ok());
 
-        Ok(res)
-    }
+    let res = query.validate_reply();
+    assert!(res.is_err());
 
-    /// `is_push_nodes` returns if the `ConsensusMessage` is a `PushNodes` message.
-    pub fn is_push_nodes(&self) -> Result<bool> {
-        self.validate()?;
+    let chits: BTreeMap<Digest, bool> = transactions
+        .iter()
+        .map(|tx| (tx.id, Random::u32_range(0, 2).unwrap() != 0))
+        .collect();
 
-        let res = match self {
-            ConsensusMessage::PushNodes { .. } => true,
-            _ => false,
-        };
+    let mut invalid_chits = chits.clone();
+    invalid_chits.insert(node.id, true);
 
-        Ok(res)
-    }
+    let res = ConsensusMessage::new_batch_reply(&address, query.id(), &node, &invalid_chits);
+    assert!(res.is_err());
 
-    /// `is_fetch_transactions` returns if the `ConsensusMessage` is a `FetchTransactions` message.
-    pub fn is_fetch_transactions(&self) -> Result<bool> {
-        self.validate()?;
+    let res = ConsensusMessage::new_batch_reply(&address, query.id(), &node, &chits);
+    assert!(res.is_ok());
 
-        let res = match self {
-            ConsensusMessage::FetchTransactions { .. } => true,
-            _ => false,
-        };
+    let reply = res.unwrap();
+    assert_eq!(reply.id(), query.id() + 1);
 
-        Ok(res)
-    }
+    let res = reply.validate_batch_reply();
+    assert!(res.is_ok());
 
-    /// `is_fetch_random_transactions` returns if the `ConsensusMessage` is a
-    /// `FetchRandomTransactions` message.
-    pub fn is_fetch_random_transactions(&self) -> Result<bool> {
-        self.validate()?;
+    let res = reply.batch_chits();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), chits);
 
-        let res = match self {
-            ConsensusMessage::FetchRandomTransactions { .. } => true,
-            _ => false,
+    if let ConsensusMessage::BatchReply {
+        id,
+        address,
+        node,
+        time,
+        count,
+        ids,
+        ..
+    } = reply
+    {
+        let invalid_reply = ConsensusMessage::BatchReply {
+            id,
+            address,
+            node,
+            time,
+            count,
+            ids,
+            chits: vec![],
         };
 
-        Ok(res)
+        let res = invalid_reply.validate();
+        assert!(res.is_err());
     }
+}
 
-    /// `is_push_transactions` returns if the `ConsensusMessage` is a `PushTransactions` message.
-    pub fn is_push_transactions(&self) -> Result<bool> {
-        self.validate()?;
+#[test]
+fn test_consensus_message_compact() {
+    use crypto::random::Random;
 
-        let res = match self {
-            ConsensusMessage::PushTransactions { .. } => true,
-            _ => false,
-        };
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::new(
+        Stage::random().unwrap(),
+        &Random::bytes(address_len).unwrap(),
+    );
+    let transaction = Transaction::new().unwrap();
 
-        Ok(res)
-    }
+    let res = ConsensusMessage::new_compact_query(&address, &node, &transaction);
+    assert!(res.is_ok());
 
-    /// `is_mine` returns if the `ConsensusMessage` is a `Mine` message.
-    pub fn is_mine(&self) -> Result<bool> {
-        self.validate()?;
+    let query = res.unwrap();
+    assert!(query.is_compact_query().unwrap());
+    assert!(query.is_compact_encodable());
 
-        let res = match self {
-            ConsensusMessage::Mine { .. } => true,
-            _ => false,
-        };
+    let res = query.validate_query();
+    assert!(res.is_err());
 
-        Ok(res)
-    }
+    let res = query.to_compact_bytes();
+    assert!(res.is_ok());
+    let buf = res.unwrap();
+    assert!(ConsensusMessage::is_compact_bytes(&buf));
 
-    /// `is_query` returns if the `ConsensusMessage` is a `Query` message.
-    pub fn is_query(&self) -> Result<bool> {
-        self.validate()?;
+    let full_query = ConsensusMessage::new_query(&address, &node, &transaction).unwrap();
+    assert!(buf.len() < full_query.to_bytes().unwrap().len());
+    assert!(!full_query.is_compact_encodable());
+    assert!(!ConsensusMessage::is_compact_bytes(
+        &full_query.to_bytes().unwrap()
+    ));
 
-        let res = match self {
-            ConsensusMessage::Query { .. } => true,
-            _ => false,
-        };
+    let res = ConsensusMessage::from_compact_bytes(&buf);
+    assert!(res.is_ok());
+    let decoded = res.unwrap();
+    assert_eq!(decoded, query);
+    assert_eq!(decoded.content_id().unwrap(), query.id());
 
-        Ok(res)
-    }
+    let res = ConsensusMessage::from_compact_bytes(&buf[..buf.len() - 1]);
+    assert!(res.is_err());
 
-    /// `is_reply` returns if the `ConsensusMessage` is a `Reply` message.
-    pub fn is_reply(&self) -> Result<bool> {
-        self.validate()?;
+    let mut long_buf = buf.clone();
+    long_buf.push(0);
+    let res = ConsensusMessage::from_compact_bytes(&long_buf);
+    assert!(res.is_err());
 
-        let res = match self {
-            ConsensusMessage::Reply { .. } => true,
-            _ => false,
-        };
+    let hints = ReplyHints {
+        confidence: Some(Random::u64().unwrap()),
+        preferred: None,
+    };
 
-        Ok(res)
-    }
+    let reply = ConsensusMessage::new_reply(&address, query.id(), &node, transaction.id, true)
+        .unwrap()
+        .with_reply_hints(hints)
+        .unwrap();
 
-    /// `validate` validates a `ConsensusMessage`.
-    pub fn validate(&self) -> Result<()> {
-        match self {
-            ConsensusMessage::FetchNodes { .. } => self.validate_fetch_nodes(),
-            ConsensusMessage::FetchRandomNodes { .. } => self.validate_fetch_random_nodes(),
-            ConsensusMessage::PushNodes { .. } => self.validate_push_nodes(),
-            ConsensusMessage::FetchTransactions { .. } => self.validate_fetch_transactions(),
-            ConsensusMessage::FetchRandomTransactions { .. } => {
-                self.validate_fetch_random_transactions()
-            }
-            ConsensusMessage::PushTransactions { .. } => self.validate_push_transactions(),
-            ConsensusMessage::Mine { .. } => self.validate_mine(),
-            ConsensusMessage::Query { .. } => self.validate_query(),
-            ConsensusMessage::Reply { .. } => self.validate_reply(),
-        }
-    }
+    let res = reply.to_compact_bytes();
+    assert!(res.is_ok());
 
-    /// `to_bytes` converts the `ConsensusMessage` into a CBOR binary.
-    pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        serde_cbor::to_vec(self).map_err(|e| e.into())
-    }
+    let res = ConsensusMessage::from_compact_bytes(&res.unwrap());
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), reply);
 
-    /// `from_bytes` converts a CBOR binary into an `ConsensusMessage`.
-    pub fn from_bytes(b: &[u8]) -> Result<ConsensusMessage> {
-        serde_cbor::from_slice(b).map_err(|e| e.into())
-    }
+    let mut services = BTreeSet::new();
+    services.insert(Service::Relay);
 
-    /// `to_json` converts the `ConsensusMessage` into a JSON string.
-    pub fn to_json(&self) -> Result<String> {
-        serde_json::to_string(self).map_err(|e| e.into())
-    }
+    let mut announced_node = node.clone();
+    announced_node.set_services(&services);
 
-    /// `from_json` converts a JSON string into an `ConsensusMessage`.
-    pub fn from_json(s: &str) -> Result<ConsensusMessage> {
-        serde_json::from_str(s).map_err(|e| e.into())
-    }
-}
+    let reply =
+        ConsensusMessage::new_reply(&address, query.id(), &announced_node, transaction.id, false)
+            .unwrap();
 
-impl<S: Store> Storable<S> for ConsensusMessage {
-    const KEY_PREFIX: u8 = 7;
+    assert!(!reply.is_compact_encodable());
 
-    type Key = u64;
+    let res = reply.to_compact_bytes();
+    assert!(res.is_err());
+}
 
-    fn key(&self) -> Self::Key {
-        self.id()
-    }
+#[test]
+fn test_consensus_message_inventory() {
+    use crypto::random::Random;
 
-    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        buf.push(stage as u8);
-        buf.push(<Self as Storable<S>>::KEY_PREFIX);
-        buf.write_u64::<BigEndian>(*key)?;
-        Ok(buf)
-    }
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let count = 10;
 
-    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
-        if value.node().stage != stage {
-            let err = Error::InvalidStage;
-            return Err(err);
-        }
+    let ids: BTreeSet<Digest> = (0..10).map(|_| Digest::random().unwrap()).collect();
 
-        value.validate()
-    }
+    let res = ConsensusMessage::new_inventory(&address, &node, 0, &ids);
+    assert!(res.is_err());
 
-    fn validate_all(store: &S, stage: Stage) -> Result<()> {
-        for value in Self::query(store, stage, None, None, None, None)? {
-            Self::validate_single(store, stage, &value)?;
-        }
+    let res = ConsensusMessage::new_inventory(&address, &node, count, &ids);
+    assert!(res.is_ok());
 
-        Ok(())
-    }
+    let inventory = res.unwrap();
 
-    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
-        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-        store.lookup(&key).map_err(|e| e.into())
-    }
+    let res = inventory.is_inventory();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
 
-    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
-        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-        let buf = store.get(&key)?;
-        Self::from_bytes(&buf)
+    if let ConsensusMessage::Inventory { ref filter, .. } = inventory {
+        for id in ids.iter() {
+            assert!(filter.lookup(id));
+        }
     }
 
-    fn query(
-        store: &S,
-        stage: Stage,
-        from: Option<Self::Key>,
-        to: Option<Self::Key>,
-        count: Option<u32>,
-        skip: Option<u32>,
-    ) -> Result<BTreeSet<Self>> {
-        let from = if let Some(ref key) = from {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            Some(key)
-        } else {
-            let mut _from = Digest::default();
-            _from[0] = stage as u8;
-            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
-            Some(_from.to_vec())
+    if let ConsensusMessage::Inventory {
+        id,
+        address,
+        node,
+        time,
+        count,
+        ..
+    } = inventory
+    {
+        let invalid_inventory = ConsensusMessage::Inventory {
+            id,
+            address,
+            node,
+            time,
+            count,
+            filter: BloomFilter::default(),
         };
 
-        let to = if let Some(ref key) = to {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            Some(key)
-        } else {
-            let mut _to = Digest::default();
-            _to[0] = stage as u8;
-            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
-            Some(_to.to_vec())
-        };
+        let res = invalid_inventory.validate();
+        assert!(res.is_err());
+    }
+}
 
-        let from = from.as_ref().map(|from| from.as_slice());
-        let to = to.as_ref().map(|to| to.as_slice());
-        let values = store.query(from, to, count, skip)?;
-        let mut items = BTreeSet::new();
+#[test]
+fn test_consensus_message_proof() {
+    use crate::address::Address;
+    use crypto::random::Random;
 
-        for value in values {
-            let item = Self::from_bytes(&value)?;
-            items.insert(item);
-        }
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
 
-        Ok(items)
-    }
+    let mut eve_transaction =
+        Transaction::new_eve(node.stage, &Address::random().unwrap()).unwrap();
+    eve_transaction.mine().unwrap();
+    eve_transaction.update_id().unwrap();
 
-    fn sample(
-        store: &S,
-        stage: Stage,
-        from: Option<Self::Key>,
-        to: Option<Self::Key>,
-        count: u32,
-    ) -> Result<BTreeSet<Self>> {
-        let from = if let Some(ref key) = from {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            Some(key)
-        } else {
-            let mut _from = Digest::default();
-            _from[0] = stage as u8;
-            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
-            Some(_from.to_vec())
-        };
+    let res = ConsensusMessage::new_fetch_proof(&address, &node, node.id);
+    assert!(res.is_err());
 
-        let to = if let Some(ref key) = to {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            Some(key)
-        } else {
-            let mut _to = Digest::default();
-            _to[0] = stage as u8;
-            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
-            Some(_to.to_vec())
-        };
+    let res = ConsensusMessage::new_fetch_proof(&address, &node, eve_transaction.id);
+    assert!(res.is_ok());
+
+    let fetch_proof = res.unwrap();
 
-        let from = from.as_ref().map(|from| from.as_slice());
-        let to = to.as_ref().map(|to| to.as_slice());
-        let values = store.sample(from, to, count)?;
-        let mut items = BTreeSet::new();
+    let res = fetch_proof.is_fetch_proof();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
 
-        for value in values {
-            let item = Self::from_bytes(&value)?;
-            items.insert(item);
-        }
+    let proof = TransactionProof::new(&[eve_transaction], 1, true).unwrap();
 
-        Ok(items)
-    }
+    let res = ConsensusMessage::new_push_proof(&address, fetch_proof.id(), &node, &proof);
+    assert!(res.is_ok());
 
-    fn count(
-        store: &S,
-        stage: Stage,
-        from: Option<Self::Key>,
-        to: Option<Self::Key>,
-        skip: Option<u32>,
-    ) -> Result<u32> {
-        let from = if let Some(ref key) = from {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            Some(key)
-        } else {
-            let mut _from = Digest::default();
-            _from[0] = stage as u8;
-            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
-            Some(_from.to_vec())
-        };
+    let push_proof = res.unwrap();
+    assert_eq!(push_proof.id(), fetch_proof.id() + 1);
 
-        let to = if let Some(ref key) = to {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            Some(key)
-        } else {
-            let mut _to = Digest::default();
-            _to[0] = stage as u8;
-            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
-            Some(_to.to_vec())
-        };
+    let res = push_proof.is_push_proof();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let res = ConsensusMessage::new_push_proof(
+        &address,
+        fetch_proof.id(),
+        &node,
+        &TransactionProof::default(),
+    );
+    assert!(res.is_err());
+}
 
-        let from = from.as_ref().map(|from| from.as_slice());
-        let to = to.as_ref().map(|to| to.as_slice());
-        store.count(from, to, skip).map_err(|e| e.into())
-    }
+#[test]
+fn test_consensus_message_params_update() {
+    use crypto::ecc::ed25519::SecretKey;
+    use crypto::random::Random;
 
-    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
-        Self::validate_single(store, stage, value)?;
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let secret_key = SecretKey::random().unwrap();
 
-        let key = <Self as Storable<S>>::key(value);
-        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-        let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
-    }
+    let mut params = ConsensusParams::new(node.stage, 1, 10, 8, 10, 150, 60).unwrap();
 
-    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
-        Self::validate_single(store, stage, value)?;
+    let res = ConsensusMessage::new_params_update(&address, &node, &params);
+    assert!(res.is_err());
 
-        let key = <Self as Storable<S>>::key(value);
-        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-        let store_value = value.to_bytes()?;
-        store.create(&store_key, &store_value).map_err(|e| e.into())
-    }
+    params.sign(&secret_key).unwrap();
 
-    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
-        Self::validate_single(store, stage, value)?;
+    let res = ConsensusMessage::new_params_update(&address, &node, &params);
+    assert!(res.is_ok());
 
-        let key = <Self as Storable<S>>::key(value);
-        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-        let store_value = value.to_bytes()?;
-        store.update(&store_key, &store_value).map_err(|e| e.into())
+    let params_update = res.unwrap();
+    assert_eq!(params_update.id(), params_update.content_id().unwrap());
+
+    let res = params_update.is_params_update();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let mut other_node = node.clone();
+    other_node.stage = Stage::random().unwrap();
+
+    if other_node.stage != node.stage {
+        let res = ConsensusMessage::new_params_update(&address, &other_node, &params);
+        assert!(res.is_err());
     }
+}
 
-    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
-        let mut items = BTreeSet::new();
+#[test]
+fn test_consensus_message_hello() {
+    use crypto::random::Random;
 
-        for value in values {
-            Self::validate_single(store, stage, value)?;
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let version = Version::default();
 
-            let key = <Self as Storable<S>>::key(value);
-            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-            let store_value = value.to_bytes()?;
-            let item = (store_key, store_value);
-            items.insert(item);
-        }
+    let mut services = BTreeSet::new();
+    services.insert(Service::Relay);
 
-        let items: Vec<(&[u8], &[u8])> = items
-            .iter()
-            .map(|(k, v)| (k.as_slice(), v.as_slice()))
-            .collect();
+    let res = ConsensusMessage::new_hello(&address, &node, &version, &services);
+    assert!(res.is_ok());
 
-        store.insert_batch(&items).map_err(|e| e.into())
-    }
+    let hello = res.unwrap();
+    assert_eq!(hello.id(), hello.content_id().unwrap());
 
-    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
-        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-        store.remove(&key).map_err(|e| e.into())
-    }
+    let res = hello.is_hello();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
 
-    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
-        let mut _keys = BTreeSet::new();
-        for key in keys {
-            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-            _keys.insert(key);
-        }
+    let res = ConsensusMessage::new_hello_reply(&address, hello.id(), &node, &version, &services);
+    assert!(res.is_ok());
 
-        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+    let hello_reply = res.unwrap();
+    assert_eq!(hello_reply.id(), hello.id() + 1);
 
-        store.remove_batch(&keys).map_err(|e| e.into())
-    }
+    let res = hello_reply.is_hello();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
 
-    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
-        let min_time = min_time.unwrap_or_default();
+    let stage = if node.stage == Stage::Development {
+        Stage::Testing
+    } else {
+        Stage::Development
+    };
 
-        let mut _from = Vec::new();
-        _from.push(stage as u8);
-        _from.push(<Self as Storable<S>>::KEY_PREFIX);
-        _from.write_u64::<BigEndian>(0)?;
-        let from = Some(_from);
-        let from = from.as_ref().map(|from| from.as_slice());
+    let invalid_hello = ConsensusMessage::Hello {
+        id: 0,
+        address,
+        node,
+        time: Timestamp::now(),
+        version,
+        stage,
+        services,
+    };
 
-        let mut _to = Vec::new();
-        _to.push(stage as u8);
-        _to.push(<Self as Storable<S>>::KEY_PREFIX + 1);
-        _to.write_u64::<BigEndian>(0)?;
-        let to = Some(_to);
-        let to = to.as_ref().map(|to| to.as_slice());
+    let res = invalid_hello.validate();
+    assert!(res.is_err());
+}
 
-        for value in store.query(from, to, None, None)? {
-            let msg = ConsensusMessage::from_bytes(&value)?;
-            if msg.time() < min_time {
-                let key = <Self as Storable<S>>::key_to_bytes(stage, &msg.id())?;
-                store.remove(&key)?;
-            }
-        }
+#[test]
+fn test_consensus_message_reject() {
+    use crypto::random::Random;
 
-        Ok(())
-    }
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let request_id = 2;
+
+    let res = ConsensusMessage::new_reject(
+        &address,
+        request_id,
+        &node,
+        ErrorCode::InvalidTransaction,
+        "Invalid transaction",
+    );
+    assert!(res.is_ok());
 
-    fn clear(store: &mut S, stage: Stage) -> Result<()> {
-        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
-        let from = from.as_ref().map(|from| from.as_slice());
+    let reject = res.unwrap();
+    assert_eq!(reject.id(), request_id + 1);
 
-        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
-        let to = to.as_ref().map(|to| to.as_slice());
+    let res = reject.is_reject();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
 
-        store.remove_range(from, to, None).map_err(|e| e.into())
-    }
+    let res = reject.reject_code();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), ErrorCode::InvalidTransaction);
+
+    let invalid_reject = ConsensusMessage::Reject {
+        id: request_id + 1,
+        address,
+        node,
+        time: Timestamp::now(),
+        code: 0,
+        reason: String::new(),
+    };
+
+    let res = invalid_reject.validate();
+    assert!(res.is_err());
 }
 
 #[test]
-fn test_consensus_message() {
+fn test_consensus_message_work() {
+    use crate::address::Address;
+    use crypto::random::Random;
+
     let address_len = 100;
     let address = Random::bytes(address_len).unwrap();
     let node = Node::random(address_len).unwrap();
-    let query_id = Random::u64().unwrap();
-    let tx_id = Digest::random().unwrap();
-    let chit = Random::u32_range(0, 2).unwrap() != 0;
 
-    let mut invalid_node = node.clone();
-    invalid_node.id = Digest::default();
+    let res = ConsensusMessage::new_get_work(&address, &node);
+    assert!(res.is_ok());
 
-    let res = ConsensusMessage::new_reply(&address, query_id, &invalid_node, tx_id, chit);
+    let get_work = res.unwrap();
+    assert_eq!(get_work.id(), get_work.content_id().unwrap());
+
+    let res = get_work.is_get_work();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let mut transaction = Transaction::default();
+    let tx_address = Address::random().unwrap();
+
+    transaction.set_coinbase(&tx_address, 1).unwrap();
+
+    let work = Work::from_transaction(&transaction).unwrap();
+
+    let res = ConsensusMessage::new_push_work(&address, get_work.id(), &node, &work);
+    assert!(res.is_ok());
+
+    let push_work = res.unwrap();
+    assert_eq!(push_work.id(), get_work.id() + 1);
+
+    let res = push_work.is_push_work();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let res = ConsensusMessage::new_push_work(&address, get_work.id(), &node, &Work::default());
     assert!(res.is_err());
 
-    let res = ConsensusMessage::new_reply(&address, query_id, &node, node.id, chit);
+    let proof = work.mine().unwrap();
+
+    let res =
+        ConsensusMessage::new_submit_work(&address, &node, node.id, proof.nonce, proof.digest);
     assert!(res.is_err());
 
-    let res = ConsensusMessage::new_reply(&address, query_id, &node, tx_id, chit);
+    let res =
+        ConsensusMessage::new_submit_work(&address, &node, work.tx_id, proof.nonce, proof.digest);
     assert!(res.is_ok());
 
-    let cons_msg = res.unwrap();
+    let submit_work = res.unwrap();
+    assert_eq!(submit_work.id(), submit_work.content_id().unwrap());
 
-    let res = cons_msg.validate_query();
+    let res = submit_work.is_submit_work();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+}
+
+#[test]
+fn test_consensus_message_state() {
+    use crypto::random::Random;
+
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+
+    let mut ids = BTreeSet::new();
+    ids.insert(node.id);
+
+    let res = ConsensusMessage::new_fetch_state(&address, &node, &ids);
     assert!(res.is_err());
 
-    let res = cons_msg.validate_reply();
+    let tx_id = Digest::random().unwrap();
+
+    let mut ids = BTreeSet::new();
+    ids.insert(tx_id);
+    ids.insert(Digest::random().unwrap());
+
+    let res = ConsensusMessage::new_fetch_state(&address, &node, &ids);
     assert!(res.is_ok());
 
-    let res = cons_msg.validate();
+    let fetch_state = res.unwrap();
+    assert_eq!(fetch_state.id(), fetch_state.content_id().unwrap());
+
+    let res = fetch_state.is_fetch_state();
     assert!(res.is_ok());
+    assert!(res.unwrap());
 
-    let cons_msg = ConsensusMessage::Reply {
-        address,
-        id: query_id,
-        node: invalid_node.clone(),
-        time: Timestamp::now(),
+    let mut states = BTreeSet::new();
+    states.insert(TransactionState {
         tx_id,
-        chit,
-    };
+        chit: true,
+        confidence: 1,
+        queried: true,
+    });
 
-    let res = cons_msg.validate();
-    assert!(res.is_err());
+    let res = ConsensusMessage::new_push_state(&address, fetch_state.id(), &node, &ids, &states);
+    assert!(res.is_ok());
 
-    let res = cons_msg.validate_reply();
+    let push_state = res.unwrap();
+    assert_eq!(push_state.id(), fetch_state.id() + 1);
+
+    let res = push_state.is_push_state();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    states.insert(TransactionState {
+        tx_id: Digest::random().unwrap(),
+        ..TransactionState::default()
+    });
+
+    let res = ConsensusMessage::new_push_state(&address, fetch_state.id(), &node, &ids, &states);
     assert!(res.is_err());
 }
 
 #[test]
 fn test_consensus_message_serialize_bytes() {
+    use crypto::random::Random;
+
     let address_len = 100;
 
     for _ in 0..10 {
@@ -1123,6 +3284,8 @@ fn test_consensus_message_serialize_bytes() {
 
 #[test]
 fn test_consensus_message_serialize_json() {
+    use crypto::random::Random;
+
     let address_len = 100;
 
     for _ in 0..10 {
@@ -1149,6 +3312,8 @@ fn test_consensus_message_serialize_json() {
 
 #[test]
 fn test_consensus_message_storable() {
+    use crypto::random::Random;
+
     use store::memory::MemoryStoreFactory;
 
     let max_value_size = 1 << 10;
@@ -1240,3 +3405,71 @@ fn test_consensus_message_storable() {
         assert!(!found);
     }
 }
+
+#[test]
+fn test_consensus_message_content_id() {
+    use crypto::random::Random;
+
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let count = 10;
+
+    let cons_msg = ConsensusMessage::new_fetch_random_nodes(&address, &node, count).unwrap();
+
+    let res = cons_msg.content_id();
+    assert!(res.is_ok());
+
+    let id = res.unwrap();
+    assert_eq!(cons_msg.id(), id);
+    assert_eq!(id % 2, 0);
+
+    let mut other_msg = cons_msg.clone();
+    other_msg.set_id(id + 1);
+    assert_eq!(other_msg.content_id().unwrap(), id);
+    assert_ne!(other_msg.digest().unwrap(), cons_msg.digest().unwrap());
+
+    let other_msg = ConsensusMessage::new_fetch_random_nodes(&address, &node, count + 1).unwrap();
+    assert_ne!(other_msg.id(), id);
+}
+
+#[test]
+fn test_consensus_message_ttl() {
+    use crypto::random::Random;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let stage = node.stage;
+
+    let cons_msg_a = ConsensusMessage::new_fetch_random_nodes(&address, &node, 1).unwrap();
+    let cons_msg_b = ConsensusMessage::new_fetch_random_nodes(&address, &node, 2).unwrap();
+
+    let res = ConsensusMessage::insert_with_ttl(&mut store, stage, &cons_msg_a, Some(0));
+    assert!(res.is_ok());
+
+    let res = ConsensusMessage::insert_with_ttl(&mut store, stage, &cons_msg_b, None);
+    assert!(res.is_ok());
+
+    let res = ConsensusMessage::lookup(&store, stage, &cons_msg_a.id());
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let res = ConsensusMessage::lookup(&store, stage, &cons_msg_a.id());
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = ConsensusMessage::lookup(&store, stage, &cons_msg_b.id());
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+}
@@ -0,0 +1,712 @@
+//! # Consensus Params
+//!
+//! `consensus_params` contains the `ConsensusParams` type and functions.
+//! The `ConsensusParams` are the consensus parameters updates signed by the
+//! authority of a stage and distributed over the network.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use byteorder::{BigEndian, WriteBytesExt};
+use config::consensus::ConsensusConfig;
+use crypto::ecc::ed25519::{PublicKey, SecretKey, Signature};
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `ConsensusParams` is the type representing an update of the consensus
+/// parameters of a stage. Updates are ordered by sequence number.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct ConsensusParams {
+    pub stage: Stage,
+    pub sequence: u64,
+    pub time: Timestamp,
+    pub k: u32,
+    pub alpha: u32,
+    pub beta1: u32,
+    pub beta2: u32,
+    pub timeout: u64,
+    pub target_mining_time: u64,
+    pub retarget_window: u32,
+    pub max_retarget_step: u64,
+    pub signature: Option<Signature>,
+}
+
+impl ConsensusParams {
+    /// `new` creates a new unsigned `ConsensusParams`, with the default mining
+    /// difficulty retargeting schedule.
+    pub fn new(
+        stage: Stage,
+        sequence: u64,
+        k: u32,
+        alpha: u32,
+        beta1: u32,
+        beta2: u32,
+        timeout: u64,
+    ) -> Result<ConsensusParams> {
+        let params = ConsensusParams {
+            stage,
+            sequence,
+            time: Timestamp::now(),
+            k,
+            alpha,
+            beta1,
+            beta2,
+            timeout,
+            target_mining_time: ConsensusConfig::DEFAULT_TARGET_MINING_TIME,
+            retarget_window: ConsensusConfig::DEFAULT_RETARGET_WINDOW,
+            max_retarget_step: ConsensusConfig::DEFAULT_MAX_RETARGET_STEP,
+            signature: None,
+        };
+
+        params.validate_params()?;
+
+        Ok(params)
+    }
+
+    /// `from_config` creates a new unsigned `ConsensusParams` from a `ConsensusConfig`.
+    pub fn from_config(
+        stage: Stage,
+        sequence: u64,
+        config: &ConsensusConfig,
+    ) -> Result<ConsensusParams> {
+        let mut config = config.clone();
+        config.populate();
+
+        let mut params = ConsensusParams::new(
+            stage,
+            sequence,
+            config.k.unwrap(),
+            config.alpha.unwrap(),
+            config.beta1.unwrap(),
+            config.beta2.unwrap(),
+            config.timeout.unwrap(),
+        )?;
+
+        params.set_schedule(
+            config.target_mining_time.unwrap(),
+            config.retarget_window.unwrap(),
+            config.max_retarget_step.unwrap(),
+        )?;
+
+        Ok(params)
+    }
+
+    /// `set_schedule` sets the mining difficulty retargeting schedule of the
+    /// unsigned `ConsensusParams`.
+    pub fn set_schedule(
+        &mut self,
+        target_mining_time: u64,
+        retarget_window: u32,
+        max_retarget_step: u64,
+    ) -> Result<()> {
+        ConsensusConfig::check_schedule(target_mining_time, retarget_window)?;
+
+        self.target_mining_time = target_mining_time;
+        self.retarget_window = retarget_window;
+        self.max_retarget_step = max_retarget_step;
+        self.signature = None;
+
+        Ok(())
+    }
+
+    /// `signature_message` returns the message signed by the authority.
+    pub fn signature_message(&self) -> Result<Vec<u8>> {
+        let content = (
+            self.stage,
+            self.sequence,
+            self.time,
+            self.k,
+            self.alpha,
+            self.beta1,
+            self.beta2,
+            self.timeout,
+            self.target_mining_time,
+            self.retarget_window,
+            self.max_retarget_step,
+        );
+        serde_cbor::to_vec(&content).map_err(|e| e.into())
+    }
+
+    /// `sign` signs the `ConsensusParams` with the authority `SecretKey`.
+    pub fn sign(&mut self, secret_key: &SecretKey) -> Result<()> {
+        let msg = self.signature_message()?;
+        self.signature = Some(secret_key.sign(&msg));
+
+        Ok(())
+    }
+
+    /// `is_signed` returns if the `ConsensusParams` is signed.
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    /// `verify_signature` verifies the `ConsensusParams` signature against the
+    /// authority `PublicKey`.
+    pub fn verify_signature(&self, public_key: &PublicKey) -> Result<()> {
+        if let Some(ref signature) = self.signature {
+            let msg = self.signature_message()?;
+            public_key
+                .verify(signature, &msg)
+                .map_err(|_| Error::InvalidSignature)
+        } else {
+            let err = Error::NotSigned;
+            Err(err)
+        }
+    }
+
+    /// `apply` returns a copy of a `ConsensusConfig` updated with the `ConsensusParams`.
+    pub fn apply(&self, config: &ConsensusConfig) -> Result<ConsensusConfig> {
+        self.validate_params()?;
+
+        let mut config = config.clone();
+        config.k = Some(self.k);
+        config.alpha = Some(self.alpha);
+        config.beta1 = Some(self.beta1);
+        config.beta2 = Some(self.beta2);
+        config.timeout = Some(self.timeout);
+        config.target_mining_time = Some(self.target_mining_time);
+        config.retarget_window = Some(self.retarget_window);
+        config.max_retarget_step = Some(self.max_retarget_step);
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// `latest` returns the `ConsensusParams` with the highest sequence in the store, if any.
+    pub fn latest<S: Store>(store: &S, stage: Stage) -> Result<Option<ConsensusParams>> {
+        let latest = <Self as Storable<S>>::query(store, stage, None, None, None, None)?
+            .into_iter()
+            .max_by_key(|params| params.sequence);
+
+        Ok(latest)
+    }
+
+    /// `validate_params` validates the `ConsensusParams` parameters.
+    pub fn validate_params(&self) -> Result<()> {
+        if self.sequence == 0 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        ConsensusConfig::check_params(self.k, self.alpha, self.beta1, self.beta2, self.timeout)?;
+
+        ConsensusConfig::check_schedule(self.target_mining_time, self.retarget_window)
+            .map_err(|e| e.into())
+    }
+
+    /// `validate` validates the `ConsensusParams`.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_params()?;
+        self.time.validate()?;
+
+        if !self.is_signed() {
+            let err = Error::NotSigned;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `ConsensusParams` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `ConsensusParams`.
+    pub fn from_bytes(b: &[u8]) -> Result<ConsensusParams> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `ConsensusParams` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `ConsensusParams`.
+    pub fn from_json(s: &str) -> Result<ConsensusParams> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for ConsensusParams {
+    const KEY_PREFIX: u8 = 11;
+
+    type Key = u64;
+
+    fn key(&self) -> Self::Key {
+        self.sequence
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.write_u64::<BigEndian>(*key)?;
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(_store: &mut S, _stage: Stage, _min_time: Option<Timestamp>) -> Result<()> {
+        Err(Error::NotImplemented)
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_consensus_params_new() {
+    let stage = Stage::random().unwrap();
+
+    let res = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 60);
+    assert!(res.is_ok());
+
+    let res = ConsensusParams::new(stage, 0, 10, 8, 10, 150, 60);
+    assert!(res.is_err());
+
+    let res = ConsensusParams::new(stage, 1, 0, 0, 10, 150, 60);
+    assert!(res.is_err());
+
+    let res = ConsensusParams::new(stage, 1, 10, 11, 10, 150, 60);
+    assert!(res.is_err());
+
+    let res = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 0);
+    assert!(res.is_err());
+
+    let res = ConsensusParams::new(stage, 1, 10, 8, 150, 10, 60);
+    assert!(res.is_err());
+
+    let timeout = ConsensusConfig::MAX_TIMEOUT + 1;
+    let res = ConsensusParams::new(stage, 1, 10, 8, 10, 150, timeout);
+    assert!(res.is_err());
+
+    let config = ConsensusConfig::default();
+
+    let res = ConsensusParams::from_config(stage, 1, &config);
+    assert!(res.is_ok());
+
+    let params = res.unwrap();
+
+    let res = params.apply(&config);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), config);
+}
+
+#[test]
+fn test_consensus_params_sign() {
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+    let public_key = secret_key.to_public();
+
+    let mut params = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 60).unwrap();
+
+    let res = params.validate();
+    assert!(res.is_err());
+
+    let res = params.verify_signature(&public_key);
+    assert!(res.is_err());
+
+    let res = params.sign(&secret_key);
+    assert!(res.is_ok());
+
+    let res = params.validate();
+    assert!(res.is_ok());
+
+    let res = params.verify_signature(&public_key);
+    assert!(res.is_ok());
+
+    let other_public_key = PublicKey::random().unwrap();
+
+    let res = params.verify_signature(&other_public_key);
+    assert!(res.is_err());
+
+    params.k = 20;
+
+    let res = params.verify_signature(&public_key);
+    assert!(res.is_err());
+
+    let config = ConsensusConfig::default();
+
+    let res = params.apply(&config);
+    assert!(res.is_ok());
+
+    let config = res.unwrap();
+    assert_eq!(config.k, Some(20));
+    assert_eq!(config.alpha, Some(8));
+    assert_eq!(config.timeout, Some(60));
+    assert_eq!(
+        config.target_mining_time,
+        Some(ConsensusConfig::DEFAULT_TARGET_MINING_TIME)
+    );
+}
+
+#[test]
+fn test_consensus_params_schedule() {
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+    let public_key = secret_key.to_public();
+
+    let mut params = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 60).unwrap();
+
+    let res = params.set_schedule(0, 16, 4);
+    assert!(res.is_err());
+
+    let res = params.set_schedule(60, 0, 4);
+    assert!(res.is_err());
+
+    params.sign(&secret_key).unwrap();
+
+    let res = params.set_schedule(30, 8, 2);
+    assert!(res.is_ok());
+    assert!(!params.is_signed());
+
+    params.sign(&secret_key).unwrap();
+
+    let res = params.verify_signature(&public_key);
+    assert!(res.is_ok());
+
+    params.retarget_window = 4;
+
+    let res = params.verify_signature(&public_key);
+    assert!(res.is_err());
+
+    params.retarget_window = 8;
+
+    let config = ConsensusConfig::default();
+
+    let res = params.apply(&config);
+    assert!(res.is_ok());
+
+    let config = res.unwrap();
+    assert_eq!(config.target_mining_time, Some(30));
+    assert_eq!(config.retarget_window, Some(8));
+    assert_eq!(config.max_retarget_step, Some(2));
+
+    let res = ConsensusParams::from_config(stage, 2, &config);
+    assert!(res.is_ok());
+
+    let other = res.unwrap();
+    assert_eq!(other.target_mining_time, 30);
+    assert_eq!(other.retarget_window, 8);
+    assert_eq!(other.max_retarget_step, 2);
+}
+
+#[test]
+fn test_consensus_params_serialize_bytes() {
+    let stage = Stage::random().unwrap();
+    let params_a = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 60).unwrap();
+
+    let res = params_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = ConsensusParams::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let params_b = res.unwrap();
+
+    assert_eq!(params_a, params_b)
+}
+
+#[test]
+fn test_consensus_params_serialize_json() {
+    let stage = Stage::random().unwrap();
+    let params_a = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 60).unwrap();
+
+    let res = params_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = ConsensusParams::from_json(&json);
+    assert!(res.is_ok());
+    let params_b = res.unwrap();
+
+    assert_eq!(params_a, params_b)
+}
+
+#[test]
+fn test_consensus_params_storable() {
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+
+    let res = ConsensusParams::latest(&store, stage);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let mut params = ConsensusParams::new(stage, 1, 10, 8, 10, 150, 60).unwrap();
+
+    let res = ConsensusParams::insert(&mut store, stage, &params);
+    assert!(res.is_err());
+
+    params.sign(&secret_key).unwrap();
+
+    let res = ConsensusParams::insert(&mut store, stage, &params);
+    assert!(res.is_ok());
+
+    let res = ConsensusParams::lookup(&store, stage, &params.sequence);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let res = ConsensusParams::get(&store, stage, &params.sequence);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), params);
+
+    let mut next_params = ConsensusParams::new(stage, 2, 20, 16, 20, 150, 60).unwrap();
+    next_params.sign(&secret_key).unwrap();
+
+    let res = ConsensusParams::insert(&mut store, stage, &next_params);
+    assert!(res.is_ok());
+
+    let res = ConsensusParams::count(&store, stage, None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 2);
+
+    let res = ConsensusParams::latest(&store, stage);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Some(next_params));
+
+    let res = <ConsensusParams as Storable<BTreeStore>>::remove(&mut store, stage, &2);
+    assert!(res.is_ok());
+
+    let res = ConsensusParams::latest(&store, stage);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Some(params.clone()));
+
+    let res = <ConsensusParams as Storable<BTreeStore>>::clear(&mut store, stage);
+    assert!(res.is_ok());
+
+    let res = ConsensusParams::lookup(&store, stage, &params.sequence);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
@@ -4,7 +4,6 @@
 
 use crate::account::Account;
 use crate::address::Address;
-use crate::conflict_set::ConflictSet;
 use crate::error::Error;
 use crate::node::Node;
 use crate::result::Result;
@@ -20,7 +19,54 @@ use serde_json;
 use std::collections::{BTreeMap, BTreeSet};
 use store::traits::Store;
 
+/// `ConsensusMutation` is a journaled mutation of a `ConsensusState`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub enum ConsensusMutation {
+    AddKnownTransaction { tx_id: Digest },
+    RemoveKnownTransaction { tx_id: Digest },
+    AddQueriedTransaction { tx_id: Digest },
+    RemoveQueriedTransaction { tx_id: Digest },
+    SetTransactionChit { tx_id: Digest, chit: bool },
+    SetTransactionConfidence { tx_id: Digest, confidence: u64 },
+    SetTransactionConflictSet { tx_id: Digest, address: Address },
+    AddKnownNode { node_id: Digest },
+    RemoveKnownNode { node_id: Digest },
+    Clear,
+}
+
+impl ConsensusMutation {
+    /// `to_bytes` converts the `ConsensusMutation` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `ConsensusMutation`.
+    pub fn from_bytes(b: &[u8]) -> Result<ConsensusMutation> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+}
+
+/// `ConfidenceCounters` are the incremental confidence counters of a `Transaction`:
+/// the number of its successors and of the ones with a positive chit.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct ConfidenceCounters {
+    pub successors: u64,
+    pub chits: u64,
+}
+
+/// `TransactionState` is the consensus state of a known `Transaction`, exchanged
+/// between trusted nodes to bootstrap the `ConsensusState` of a new replica.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct TransactionState {
+    pub tx_id: Digest,
+    pub chit: bool,
+    pub confidence: u64,
+    pub queried: bool,
+}
+
 /// `ConsensusState` represents the Avalanche Consensus state.
+/// The confidence counters are a cache of the successors chits, not persisted:
+/// a `Transaction` without counters has to be seeded from its successors.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
 pub struct ConsensusState {
     pub id: u64,
@@ -29,15 +75,21 @@ pub struct ConsensusState {
     pub eve_transaction_id: Digest,
     pub seed_nodes: BTreeSet<Digest>,
     pub known_transactions: BTreeSet<Digest>,
-    pub transaction_successors: BTreeMap<Digest, BTreeSet<Digest>>,
     pub queried_transactions: BTreeSet<Digest>,
     pub transaction_conflict_set: BTreeMap<Digest, Address>,
     pub transaction_chit: BTreeMap<Digest, bool>,
     pub transaction_confidence: BTreeMap<Digest, u64>,
     pub known_nodes: BTreeSet<Digest>,
+    #[serde(skip)]
+    pub transaction_counters: BTreeMap<Digest, ConfidenceCounters>,
+    #[serde(skip)]
+    pub transaction_parents: BTreeMap<Digest, BTreeSet<Digest>>,
 }
 
 impl ConsensusState {
+    /// `JOURNAL_KEY_PREFIX` is the prefix of the `ConsensusState` journal keys.
+    pub const JOURNAL_KEY_PREFIX: u8 = 9;
+
     /// `new` creates a new `ConsensusState`.
     pub fn new(
         id: u64,
@@ -75,71 +127,37 @@ impl ConsensusState {
         }
 
         self.known_transactions.remove(tx_id);
+        self.invalidate_transaction_counters(tx_id);
 
         Ok(())
     }
 
-    /// `lookup_transaction_successors` looks up the transaction_successors of a `Transaction`.
-    pub fn lookup_transaction_successors(&self, tx_id: &Digest) -> bool {
-        self.transaction_successors.contains_key(tx_id)
-    }
-
-    /// `get_transaction_successors` returns a `Transaction` transaction_successors.
-    pub fn get_transaction_successors(&self, tx_id: &Digest) -> Option<BTreeSet<Digest>> {
-        self.transaction_successors.get(tx_id).cloned()
-    }
-
-    /// `add_transaction_successors` adds the transaction_successors of a `Transaction`.
-    pub fn add_transaction_successors(
-        &mut self,
-        tx_id: Digest,
-        succ_ids: BTreeSet<Digest>,
-    ) -> Result<()> {
-        if !self.lookup_known_transaction(&tx_id) {
-            let err = Error::NotFound;
-            return Err(err);
-        }
-
-        if succ_ids.contains(&tx_id) {
-            let err = Error::InvalidId;
-            return Err(err);
-        }
-
-        self.transaction_successors.insert(tx_id, succ_ids);
-
-        Ok(())
-    }
-
-    /// `add_transaction_successor` adds a single successor of a `Transaction`
-    pub fn add_transaction_successor(&mut self, tx_id: &Digest, succ_id: Digest) -> Result<()> {
-        if tx_id == &succ_id {
-            let err = Error::InvalidId;
-            return Err(err);
-        }
-
+    /// `forget_transaction` removes a known `Transaction` from the `ConsensusState`,
+    /// together with its query, conflict set, chit and confidence, and from the
+    /// confidence counters of its parents.
+    pub fn forget_transaction(&mut self, tx_id: &Digest) -> Result<()> {
         if !self.lookup_known_transaction(tx_id) {
             let err = Error::NotFound;
             return Err(err);
         }
 
-        let mut succ_ids = self.get_transaction_successors(tx_id).unwrap_or_default();
-        succ_ids.insert(succ_id);
-
-        self.transaction_successors.insert(*tx_id, succ_ids);
-
-        Ok(())
-    }
+        if self.lookup_transaction_chit(tx_id) {
+            self.remove_transaction_chit(tx_id)?;
+        }
 
-    /// `remove_transaction_successors` removes the transaction_successors of a `Transaction`.
-    pub fn remove_transaction_successors(&mut self, tx_id: &Digest) -> Result<()> {
-        if !self.lookup_transaction_successors(tx_id) {
-            let err = Error::NotFound;
-            return Err(err);
+        if let Some(parents) = self.transaction_parents.remove(tx_id) {
+            for parent_id in parents {
+                if let Some(counters) = self.transaction_counters.get_mut(&parent_id) {
+                    counters.successors = counters.successors.saturating_sub(1);
+                }
+            }
         }
 
-        self.transaction_successors.remove(tx_id);
+        self.queried_transactions.remove(tx_id);
+        self.transaction_conflict_set.remove(tx_id);
+        self.transaction_confidence.remove(tx_id);
 
-        Ok(())
+        self.remove_known_transaction(tx_id)
     }
 
     /// `lookup_queried_transaction` looks up a `Transaction` id in the queried transactions set of the `ConsensusState`.
@@ -197,6 +215,30 @@ impl ConsensusState {
         Ok(())
     }
 
+    /// `partition_by_conflict_set` partitions a list of `Transaction` ids by their
+    /// conflict sets, keeping the order of the ids within each partition. The
+    /// ids without a conflict set have a partition each.
+    pub fn partition_by_conflict_set(&self, tx_ids: &[Digest]) -> Vec<Vec<Digest>> {
+        let mut partitions: Vec<Vec<Digest>> = Vec::new();
+        let mut idxs: BTreeMap<Address, usize> = BTreeMap::new();
+
+        for tx_id in tx_ids {
+            match self.get_transaction_conflict_set(tx_id) {
+                Some(cs_id) => {
+                    if let Some(idx) = idxs.get(&cs_id) {
+                        partitions[*idx].push(*tx_id);
+                    } else {
+                        idxs.insert(cs_id, partitions.len());
+                        partitions.push(vec![*tx_id]);
+                    }
+                }
+                None => partitions.push(vec![*tx_id]),
+            }
+        }
+
+        partitions
+    }
+
     /// `remove_transaction_conflict_set` removes a known `Transaction` conflict set id in
     /// the `ConsensusState`.
     pub fn remove_transaction_conflict_set(&mut self, tx_id: &Digest) -> Result<()> {
@@ -234,7 +276,11 @@ impl ConsensusState {
             return Err(err);
         }
 
-        self.transaction_chit.insert(tx_id, chit);
+        let prev_chit = self.transaction_chit.insert(tx_id, chit).unwrap_or(false);
+
+        if prev_chit != chit {
+            self.update_parents_chits(&tx_id, chit);
+        }
 
         Ok(())
     }
@@ -252,7 +298,9 @@ impl ConsensusState {
             return Err(err);
         }
 
-        self.transaction_chit.remove(tx_id);
+        if self.transaction_chit.remove(tx_id).unwrap_or(false) {
+            self.update_parents_chits(tx_id, false);
+        }
 
         Ok(())
     }
@@ -299,6 +347,146 @@ impl ConsensusState {
         Ok(())
     }
 
+    /// `get_transaction_counters` gets the confidence counters of a `Transaction`.
+    pub fn get_transaction_counters(&self, tx_id: &Digest) -> Option<ConfidenceCounters> {
+        self.transaction_counters.get(tx_id).copied()
+    }
+
+    /// `seed_transaction_counters` sets the confidence counters of a known `Transaction`
+    /// from the set of its successors.
+    pub fn seed_transaction_counters(
+        &mut self,
+        tx_id: Digest,
+        successors: &BTreeSet<Digest>,
+    ) -> Result<()> {
+        if !self.lookup_known_transaction(&tx_id) {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        let mut counters = ConfidenceCounters::default();
+
+        for succ_id in successors {
+            counters.successors += 1;
+            counters.chits += self.get_transaction_chit(succ_id).unwrap_or(false) as u64;
+
+            self.transaction_parents
+                .entry(*succ_id)
+                .or_insert_with(BTreeSet::new)
+                .insert(tx_id);
+        }
+
+        self.transaction_counters.insert(tx_id, counters);
+
+        Ok(())
+    }
+
+    /// `add_transaction_successor` adds a known successor to a `Transaction`,
+    /// updating its confidence counters if it has any.
+    pub fn add_transaction_successor(&mut self, tx_id: Digest, succ_id: Digest) -> Result<()> {
+        if !self.lookup_known_transaction(&succ_id) {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        let added = self
+            .transaction_parents
+            .entry(succ_id)
+            .or_insert_with(BTreeSet::new)
+            .insert(tx_id);
+
+        let chit = self.get_transaction_chit(&succ_id).unwrap_or(false);
+
+        if let Some(counters) = self.transaction_counters.get_mut(&tx_id) {
+            if added {
+                counters.successors += 1;
+                counters.chits += chit as u64;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `update_parents_chits` updates the confidence counters of the parents of a
+    /// `Transaction` whose chit changed.
+    fn update_parents_chits(&mut self, tx_id: &Digest, chit: bool) {
+        if let Some(parents) = self.transaction_parents.get(tx_id) {
+            for parent_id in parents {
+                if let Some(counters) = self.transaction_counters.get_mut(parent_id) {
+                    if chit {
+                        counters.chits += 1;
+                    } else {
+                        counters.chits = counters.chits.saturating_sub(1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `invalidate_transaction_counters` removes the confidence counters of a
+    /// `Transaction`, so that they are seeded again from its successors.
+    pub fn invalidate_transaction_counters(&mut self, tx_id: &Digest) {
+        self.transaction_counters.remove(tx_id);
+    }
+
+    /// `invalidate_counters` removes the confidence counters of all the `Transaction`s.
+    pub fn invalidate_counters(&mut self) {
+        self.transaction_counters.clear();
+        self.transaction_parents.clear();
+    }
+
+    /// `counted_transaction_confidence` returns the confidence of a `Transaction`
+    /// from its confidence counters, if it has any.
+    pub fn counted_transaction_confidence(&self, tx_id: &Digest) -> Option<u64> {
+        self.get_transaction_counters(tx_id).map(|counters| {
+            if counters.successors == 0 {
+                0
+            } else {
+                counters.chits + self.get_transaction_chit(tx_id).unwrap_or(false) as u64
+            }
+        })
+    }
+
+    /// `get_transaction_state` returns the `TransactionState` of a known `Transaction`.
+    pub fn get_transaction_state(&self, tx_id: &Digest) -> Option<TransactionState> {
+        if !self.lookup_known_transaction(tx_id) {
+            return None;
+        }
+
+        let state = TransactionState {
+            tx_id: *tx_id,
+            chit: self.get_transaction_chit(tx_id).unwrap_or(false),
+            confidence: self.get_transaction_confidence(tx_id).unwrap_or(0),
+            queried: self.lookup_queried_transaction(tx_id),
+        };
+
+        Some(state)
+    }
+
+    /// `transaction_state_mutations` returns the `ConsensusMutation`s setting a
+    /// `TransactionState` in the `ConsensusState`.
+    pub fn transaction_state_mutations(state: &TransactionState) -> Vec<ConsensusMutation> {
+        let tx_id = state.tx_id;
+
+        let mut mutations = vec![
+            ConsensusMutation::AddKnownTransaction { tx_id },
+            ConsensusMutation::SetTransactionChit {
+                tx_id,
+                chit: state.chit,
+            },
+            ConsensusMutation::SetTransactionConfidence {
+                tx_id,
+                confidence: state.confidence,
+            },
+        ];
+
+        if state.queried {
+            mutations.push(ConsensusMutation::AddQueriedTransaction { tx_id });
+        }
+
+        mutations
+    }
+
     /// `lookup_known_node` looks up a `Node` id in the known nodes set of the `ConsensusState`.
     pub fn lookup_known_node(&self, node_id: &Digest) -> bool {
         self.known_nodes.contains(node_id)
@@ -323,6 +511,142 @@ impl ConsensusState {
         Ok(())
     }
 
+    /// `apply` applies a `ConsensusMutation` to the `ConsensusState`.
+    pub fn apply(&mut self, mutation: &ConsensusMutation) -> Result<()> {
+        match mutation {
+            ConsensusMutation::AddKnownTransaction { tx_id } => {
+                self.add_known_transaction(*tx_id);
+                Ok(())
+            }
+            ConsensusMutation::RemoveKnownTransaction { tx_id } => self.forget_transaction(tx_id),
+            ConsensusMutation::AddQueriedTransaction { tx_id } => {
+                self.add_queried_transaction(*tx_id)
+            }
+            ConsensusMutation::RemoveQueriedTransaction { tx_id } => {
+                self.remove_queried_transaction(tx_id)
+            }
+            ConsensusMutation::SetTransactionChit { tx_id, chit } => {
+                self.set_transaction_chit(*tx_id, *chit)
+            }
+            ConsensusMutation::SetTransactionConfidence { tx_id, confidence } => {
+                self.set_transaction_confidence(*tx_id, *confidence)
+            }
+            ConsensusMutation::SetTransactionConflictSet { tx_id, address } => {
+                self.set_transaction_conflict_set(*tx_id, *address)
+            }
+            ConsensusMutation::AddKnownNode { node_id } => {
+                self.add_known_node(*node_id);
+                Ok(())
+            }
+            ConsensusMutation::RemoveKnownNode { node_id } => self.remove_known_node(node_id),
+            ConsensusMutation::Clear => {
+                self.clear();
+                Ok(())
+            }
+        }
+    }
+
+    /// `journal_key` returns the store key of a journal entry of a `ConsensusState`.
+    pub fn journal_key(stage: Stage, id: u64, seq: u64) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(Self::JOURNAL_KEY_PREFIX);
+        buf.write_u64::<BigEndian>(id)?;
+        buf.write_u64::<BigEndian>(seq)?;
+        Ok(buf)
+    }
+
+    /// `journal_range` returns the store range of the journal entries of a `ConsensusState`.
+    fn journal_range(stage: Stage, id: u64) -> Result<(Vec<u8>, Vec<u8>)> {
+        let from = Self::journal_key(stage, id, 0)?;
+
+        let to = if id == u64::max_value() {
+            vec![stage as u8, Self::JOURNAL_KEY_PREFIX + 1]
+        } else {
+            Self::journal_key(stage, id + 1, 0)?
+        };
+
+        Ok((from, to))
+    }
+
+    /// `journal_len` returns the number of entries in the journal of a `ConsensusState`.
+    pub fn journal_len<S: Store>(store: &S, stage: Stage, id: u64) -> Result<u64> {
+        let (from, to) = Self::journal_range(stage, id)?;
+        let len = store.count(Some(&from), Some(&to), None)?;
+        Ok(u64::from(len))
+    }
+
+    /// `journal` appends a `ConsensusMutation` to the journal of a `ConsensusState`
+    /// in the store, at the sequence number following the last entry.
+    pub fn journal<S: Store>(
+        store: &mut S,
+        stage: Stage,
+        id: u64,
+        seq: u64,
+        mutation: &ConsensusMutation,
+    ) -> Result<()> {
+        let key = Self::journal_key(stage, id, seq)?;
+        let value = mutation.to_bytes()?;
+        store.create(&key, &value).map_err(|e| e.into())
+    }
+
+    /// `journal_entries` returns the journaled `ConsensusMutation`s of a `ConsensusState`,
+    /// in order.
+    pub fn journal_entries<S: Store>(
+        store: &S,
+        stage: Stage,
+        id: u64,
+    ) -> Result<Vec<ConsensusMutation>> {
+        let (from, to) = Self::journal_range(stage, id)?;
+
+        let mut mutations = Vec::new();
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let mutation = ConsensusMutation::from_bytes(&value)?;
+            mutations.push(mutation);
+        }
+
+        Ok(mutations)
+    }
+
+    /// `clear_journal` removes the journal of a `ConsensusState` from the store.
+    pub fn clear_journal<S: Store>(store: &mut S, stage: Stage, id: u64) -> Result<()> {
+        let (from, to) = Self::journal_range(stage, id)?;
+        store
+            .remove_range(Some(&from), Some(&to), None)
+            .map_err(|e| e.into())
+    }
+
+    /// `recover` restores the latest `ConsensusState` in the store, replaying its
+    /// journaled mutations.
+    pub fn recover<S: Store>(store: &S, stage: Stage) -> Result<ConsensusState> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+        let mut latest: Option<ConsensusState> = None;
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let state = ConsensusState::from_bytes(&value)?;
+            if latest.as_ref().map(|l| state.id > l.id).unwrap_or(true) {
+                latest = Some(state);
+            }
+        }
+
+        let mut state = match latest {
+            Some(state) => state,
+            None => {
+                let err = Error::NotFound;
+                return Err(err);
+            }
+        };
+
+        for mutation in Self::journal_entries(store, stage, state.id)? {
+            state.apply(&mutation)?;
+        }
+
+        state.validate()?;
+
+        Ok(state)
+    }
+
     /// `validate` validates the `ConsensusState`.
     pub fn validate(&self) -> Result<()> {
         for id in &self.queried_transactions {
@@ -364,6 +688,7 @@ impl ConsensusState {
         self.transaction_chit.clear();
         self.transaction_confidence.clear();
         self.known_nodes.clear();
+        self.invalidate_counters();
     }
 
     /// `to_bytes` converts the `ConsensusState` into a CBOR binary.
@@ -429,19 +754,8 @@ impl<S: Store> Storable<S> for ConsensusState {
             }
         }
 
-        for id in &value.known_transactions {
-            if !Transaction::lookup(store, stage, &id)? {
-                let err = Error::NotFound;
-                return Err(err);
-            }
-        }
-
-        for address in value.transaction_conflict_set.keys() {
-            if !ConflictSet::lookup(store, stage, &address)? {
-                let err = Error::NotFound;
-                return Err(err);
-            }
-        }
+        // NB: the known transactions and their conflict sets are kept in the pool
+        // until accepted, so they are not looked up in the store
 
         for id in &value.known_nodes {
             if !Node::lookup(store, stage, &id)? {
@@ -454,7 +768,10 @@ impl<S: Store> Storable<S> for ConsensusState {
     }
 
     fn validate_all(store: &S, stage: Stage) -> Result<()> {
-        for value in Self::query(store, stage, None, None, None, None)? {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
             Self::validate_single(store, stage, &value)?;
         }
 
@@ -591,7 +908,9 @@ impl<S: Store> Storable<S> for ConsensusState {
         let key = <Self as Storable<S>>::key(value);
         let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
         let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
     }
 
     fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
@@ -634,6 +953,8 @@ impl<S: Store> Storable<S> for ConsensusState {
     }
 
     fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        Self::clear_journal(store, stage, *key)?;
+
         let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
         store.remove(&key).map_err(|e| e.into())
     }
@@ -641,6 +962,8 @@ impl<S: Store> Storable<S> for ConsensusState {
     fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
         let mut _keys = BTreeSet::new();
         for key in keys {
+            Self::clear_journal(store, stage, *key)?;
+
             let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
             _keys.insert(key);
         }
@@ -651,15 +974,19 @@ impl<S: Store> Storable<S> for ConsensusState {
     }
 
     fn cleanup(store: &mut S, stage: Stage, _min_time: Option<Timestamp>) -> Result<()> {
-        let states = ConsensusState::query(store, stage, None, None, None, None)?;
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+        let mut ids = Vec::new();
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let state = ConsensusState::from_bytes(&value)?;
+            ids.push(state.id);
+        }
 
-        let max_id: u64 = states.iter().map(|state| state.id).max().unwrap_or(0);
+        let max_id: u64 = ids.iter().copied().max().unwrap_or(0);
 
-        if states.len() > 1 {
-            for state in states {
-                if state.id != max_id {
-                    ConsensusState::remove(store, stage, &state.id)?;
-                }
+        for id in ids {
+            if id != max_id {
+                ConsensusState::remove(store, stage, &id)?;
             }
         }
 
@@ -673,7 +1000,14 @@ impl<S: Store> Storable<S> for ConsensusState {
         let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
         let to = to.as_ref().map(|to| to.as_slice());
 
-        store.remove_range(from, to, None).map_err(|e| e.into())
+        store.remove_range(from, to, None)?;
+
+        let from = vec![stage as u8, Self::JOURNAL_KEY_PREFIX];
+        let to = vec![stage as u8, Self::JOURNAL_KEY_PREFIX + 1];
+
+        store
+            .remove_range(Some(&from), Some(&to), None)
+            .map_err(|e| e.into())
     }
 }
 
@@ -725,6 +1059,57 @@ fn test_consensus_state_known_transactions_ops() {
     assert!(res.is_ok());
 }
 
+#[test]
+fn test_consensus_state_partition_by_conflict_set() {
+    use crypto::random::Random;
+
+    let id = Random::u64().unwrap();
+    let stage = Stage::random().unwrap();
+
+    let eve_account_address = Address::random().unwrap();
+    let eve_transaction_id = Digest::random().unwrap();
+
+    let mut seed = BTreeSet::new();
+    for _ in 0..10 {
+        let id = Digest::random().unwrap();
+        seed.insert(id);
+    }
+
+    let mut state =
+        ConsensusState::new(id, stage, &eve_account_address, &eve_transaction_id, &seed);
+
+    let cs_id_a = Address::random().unwrap();
+    let cs_id_b = Address::random().unwrap();
+
+    let tx_ids: Vec<Digest> = (0..5).map(|_| Digest::random().unwrap()).collect();
+
+    for tx_id in tx_ids.iter() {
+        state.add_known_transaction(*tx_id);
+    }
+
+    state
+        .set_transaction_conflict_set(tx_ids[0], cs_id_a)
+        .unwrap();
+    state
+        .set_transaction_conflict_set(tx_ids[1], cs_id_b)
+        .unwrap();
+    state
+        .set_transaction_conflict_set(tx_ids[2], cs_id_a)
+        .unwrap();
+    state
+        .set_transaction_conflict_set(tx_ids[4], cs_id_b)
+        .unwrap();
+
+    let partitions = state.partition_by_conflict_set(&tx_ids);
+    assert_eq!(partitions.len(), 3);
+    assert_eq!(partitions[0], vec![tx_ids[0], tx_ids[2]]);
+    assert_eq!(partitions[1], vec![tx_ids[1], tx_ids[4]]);
+    assert_eq!(partitions[2], vec![tx_ids[3]]);
+
+    let partitions = state.partition_by_conflict_set(&[]);
+    assert!(partitions.is_empty());
+}
+
 #[test]
 fn test_consensus_state_queried_transactions_ops() {
     use crypto::random::Random;
@@ -954,6 +1339,138 @@ fn test_consensus_state_transaction_confidence_ops() {
     assert!(res.is_ok());
 }
 
+#[test]
+fn test_consensus_state_confidence_counters() {
+    let stage = Stage::random().unwrap();
+    let eve_account_address = Address::random().unwrap();
+    let eve_transaction_id = Digest::random().unwrap();
+    let seed = BTreeSet::new();
+
+    let mut state = ConsensusState::new(0, stage, &eve_account_address, &eve_transaction_id, &seed);
+
+    let tx_id = Digest::random().unwrap();
+    let succ_a = Digest::random().unwrap();
+    let succ_b = Digest::random().unwrap();
+
+    let res = state.seed_transaction_counters(tx_id, &BTreeSet::new());
+    assert!(res.is_err());
+
+    for id in [tx_id, succ_a, succ_b].iter() {
+        state.add_known_transaction(*id);
+    }
+
+    assert!(state.counted_transaction_confidence(&tx_id).is_none());
+
+    let res = state.seed_transaction_counters(tx_id, &BTreeSet::new());
+    assert!(res.is_ok());
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(0));
+
+    state.set_transaction_chit(tx_id, true).unwrap();
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(0));
+
+    state.set_transaction_chit(succ_a, true).unwrap();
+
+    let res = state.add_transaction_successor(tx_id, succ_a);
+    assert!(res.is_ok());
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(2));
+
+    let res = state.add_transaction_successor(tx_id, succ_a);
+    assert!(res.is_ok());
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(2));
+
+    state.add_transaction_successor(tx_id, succ_b).unwrap();
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(2));
+
+    state.set_transaction_chit(succ_b, true).unwrap();
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(3));
+
+    state.set_transaction_chit(succ_a, false).unwrap();
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(2));
+
+    state.remove_transaction_chit(&succ_b).unwrap();
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(1));
+
+    let counters = state.get_transaction_counters(&tx_id).unwrap();
+    assert_eq!(counters.successors, 2);
+    assert_eq!(counters.chits, 0);
+
+    let mut successors = BTreeSet::new();
+    successors.insert(succ_a);
+    successors.insert(succ_b);
+
+    state.invalidate_transaction_counters(&tx_id);
+    assert!(state.counted_transaction_confidence(&tx_id).is_none());
+
+    state.set_transaction_chit(succ_b, true).unwrap();
+
+    state.seed_transaction_counters(tx_id, &successors).unwrap();
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(2));
+
+    let res = state.forget_transaction(&succ_b);
+    assert!(res.is_ok());
+    assert!(!state.lookup_known_transaction(&succ_b));
+    assert_eq!(state.counted_transaction_confidence(&tx_id), Some(1));
+
+    let counters = state.get_transaction_counters(&tx_id).unwrap();
+    assert_eq!(counters.successors, 1);
+
+    let res = state.forget_transaction(&succ_b);
+    assert!(res.is_err());
+
+    state.clear();
+    assert!(state.transaction_counters.is_empty());
+    assert!(state.transaction_parents.is_empty());
+}
+
+#[test]
+fn test_consensus_state_transaction_state() {
+    use crypto::random::Random;
+
+    let id = Random::u64().unwrap();
+    let stage = Stage::random().unwrap();
+
+    let eve_account_address = Address::random().unwrap();
+    let eve_transaction_id = Digest::random().unwrap();
+    let seed = BTreeSet::new();
+
+    let mut state =
+        ConsensusState::new(id, stage, &eve_account_address, &eve_transaction_id, &seed);
+
+    let tx_id = Digest::random().unwrap();
+    assert!(state.get_transaction_state(&tx_id).is_none());
+
+    let tx_state = TransactionState {
+        tx_id,
+        chit: true,
+        confidence: 3,
+        queried: true,
+    };
+
+    for mutation in ConsensusState::transaction_state_mutations(&tx_state) {
+        let res = state.apply(&mutation);
+        assert!(res.is_ok());
+    }
+
+    assert_eq!(state.get_transaction_state(&tx_id), Some(tx_state));
+
+    let res = state.apply(&ConsensusMutation::RemoveKnownTransaction { tx_id });
+    assert!(res.is_ok());
+    assert!(state.get_transaction_state(&tx_id).is_none());
+    assert!(!state.lookup_queried_transaction(&tx_id));
+    assert!(!state.lookup_transaction_chit(&tx_id));
+    assert!(!state.lookup_transaction_confidence(&tx_id));
+
+    let mut replica =
+        ConsensusState::new(id, stage, &eve_account_address, &eve_transaction_id, &seed);
+    replica.add_known_transaction(tx_id);
+
+    let expected = TransactionState {
+        tx_id,
+        ..TransactionState::default()
+    };
+    assert_eq!(replica.get_transaction_state(&tx_id), Some(expected));
+}
+
 #[test]
 fn test_consensus_state_known_nodes_ops() {
     use crypto::random::Random;
@@ -1174,3 +1691,95 @@ fn test_consensus_state_storable() {
     let found = res.unwrap();
     assert!(!found);
 }
+
+#[test]
+fn test_consensus_state_journal() {
+    use crate::account::Account;
+    use crate::signers::Signers;
+    use crate::transaction::Transaction;
+    use crate::wallet::Wallet;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 12;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+
+    let wallet = Wallet::new(stage).unwrap();
+    let weight = 1;
+    let signer = wallet.to_signer(weight).unwrap();
+    let mut signers = Signers::new().unwrap();
+    signers.set_threshold(weight).unwrap();
+    signers.add(&signer).unwrap();
+
+    let mut account = Account::new_eve(stage, &signers).unwrap();
+    let transaction = Transaction::new_eve(stage, &account.address()).unwrap();
+
+    Transaction::create(&mut store, stage, &transaction).unwrap();
+
+    account.transaction_id = Some(transaction.id);
+    account.counter += 1;
+
+    Account::create(&mut store, stage, &account).unwrap();
+
+    let id = 0;
+    let seed = BTreeSet::new();
+    let mut state = ConsensusState::new(id, stage, &account.address(), &transaction.id, &seed);
+
+    let res = ConsensusState::recover(&store, stage);
+    assert!(res.is_err());
+
+    ConsensusState::create(&mut store, stage, &state).unwrap();
+
+    let tx_id = Digest::random().unwrap();
+    let succ_id = Digest::random().unwrap();
+    let node_id = Digest::random().unwrap();
+
+    let mutations = vec![
+        ConsensusMutation::AddKnownTransaction { tx_id },
+        ConsensusMutation::AddKnownTransaction { tx_id: succ_id },
+        ConsensusMutation::AddQueriedTransaction { tx_id },
+        ConsensusMutation::AddQueriedTransaction { tx_id: succ_id },
+        ConsensusMutation::RemoveQueriedTransaction { tx_id: succ_id },
+        ConsensusMutation::SetTransactionChit { tx_id, chit: true },
+        ConsensusMutation::SetTransactionConfidence {
+            tx_id,
+            confidence: 2,
+        },
+        ConsensusMutation::AddKnownNode { node_id },
+        ConsensusMutation::RemoveKnownNode { node_id },
+    ];
+
+    for (seq, mutation) in mutations.iter().enumerate() {
+        let res = state.apply(mutation);
+        assert!(res.is_ok());
+
+        let res = ConsensusState::journal(&mut store, stage, id, seq as u64, mutation);
+        assert!(res.is_ok());
+    }
+
+    let res = ConsensusState::journal_len(&store, stage, id);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), mutations.len() as u64);
+
+    let res = ConsensusState::journal_entries(&store, stage, id);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), mutations);
+
+    let res = ConsensusState::recover(&store, stage);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), state);
+
+    let res = ConsensusState::remove(&mut store, stage, &id);
+    assert!(res.is_ok());
+
+    let res = ConsensusState::journal_entries(&store, stage, id);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_empty());
+
+    let res = ConsensusState::journal_len(&store, stage, id);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+}
@@ -0,0 +1,473 @@
+//! # Contact
+//!
+//! `contact` contains the `Contact` type and functions. The contacts are the
+//! address book of the wallets, used to refer to the addresses by label.
+
+use crate::address::{self, Address};
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crypto::hash::{Blake512Hasher, Digest};
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `Contact` is an `Address` of a `Stage` with a label, identified by the hash
+/// of its label, so that every label refers to a single address.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct Contact {
+    pub id: Digest,
+    pub label: String,
+    pub address: Address,
+    pub stage: Stage,
+    pub time: Timestamp,
+}
+
+impl Contact {
+    /// `MAX_LABEL_LEN` is the maximum length in bytes of a `Contact` label.
+    pub const MAX_LABEL_LEN: usize = 64;
+
+    /// `new` creates a new `Contact` of an `Address` with a label.
+    pub fn new(stage: Stage, label: &str, address: &Address) -> Result<Contact> {
+        let contact = Contact {
+            id: Contact::label_id(label),
+            label: label.into(),
+            address: *address,
+            stage,
+            time: Timestamp::now(),
+        };
+
+        contact.validate()?;
+
+        Ok(contact)
+    }
+
+    /// `label_id` returns the id of the `Contact` with a label.
+    pub fn label_id(label: &str) -> Digest {
+        Blake512Hasher::hash(label.as_bytes())
+    }
+
+    /// `validate_label` validates a `Contact` label. The labels cannot have
+    /// surrounding or control characters, and cannot be parsed as addresses,
+    /// so that the addresses and the labels are never ambiguous.
+    pub fn validate_label(stage: Stage, label: &str) -> Result<()> {
+        if label.is_empty() || label.len() > Self::MAX_LABEL_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if label.trim() != label || label.chars().any(char::is_control) {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        if address::parse(stage, label).is_ok() || address::decode(label).is_ok() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `resolve` returns the `Address` of a string, parsed as an address or
+    /// looked up as the label of a `Contact` in a `Store`.
+    pub fn resolve<S: Store>(store: &S, stage: Stage, s: &str) -> Result<Address> {
+        match address::parse(stage, s) {
+            Ok(address) => Ok(address),
+            Err(err) => {
+                let id = Contact::label_id(s);
+
+                if Contact::lookup(store, stage, &id)? {
+                    let contact = Contact::get(store, stage, &id)?;
+                    Ok(contact.address)
+                } else {
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    /// `validate` validates the `Contact`.
+    pub fn validate(&self) -> Result<()> {
+        if self.id != Contact::label_id(&self.label) {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        Contact::validate_label(self.stage, &self.label)?;
+
+        self.time.validate()
+    }
+
+    /// `to_bytes` converts the `Contact` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `Contact`.
+    pub fn from_bytes(b: &[u8]) -> Result<Contact> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Contact` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `Contact`.
+    pub fn from_json(s: &str) -> Result<Contact> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for Contact {
+    const KEY_PREFIX: u8 = 18;
+
+    type Key = Digest;
+
+    fn key(&self) -> Self::Key {
+        self.id
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let contact = Contact::from_bytes(&value)?;
+            if contact.time < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_contact_ops() {
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+
+    let contact = Contact::new(stage, "alice", &address).unwrap();
+    assert_eq!(contact.id, Contact::label_id("alice"));
+    assert!(contact.validate().is_ok());
+
+    let long_label = "a".repeat(Contact::MAX_LABEL_LEN + 1);
+
+    for label in &["", " alice", "al\nice", long_label.as_str()] {
+        let res = Contact::new(stage, label, &address);
+        assert!(res.is_err());
+    }
+
+    let res = Contact::new(stage, &address::encode(stage, &address), &address);
+    assert!(res.is_err());
+
+    let res = Contact::new(stage, &address.to_string(), &address);
+    assert!(res.is_err());
+
+    let mut invalid = contact;
+    invalid.label = "bob".into();
+    let res = invalid.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_contact_serialize_bytes() {
+    let contact_a = Contact::new(Stage::default(), "alice", &Address::default()).unwrap();
+
+    let res = contact_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = Contact::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let contact_b = res.unwrap();
+
+    assert_eq!(contact_a, contact_b)
+}
+
+#[test]
+fn test_contact_storable() {
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let contact = Contact::new(stage, "alice", &address).unwrap();
+
+    let res = Contact::resolve(&store, stage, "alice");
+    assert!(res.is_err());
+
+    let res = Contact::create(&mut store, stage, &contact);
+    assert!(res.is_ok());
+
+    let res = Contact::create(&mut store, stage, &contact);
+    assert!(res.is_err());
+
+    let res = Contact::get(&store, stage, &contact.id);
+    assert!(res.is_ok());
+    assert_eq!(&res.unwrap(), &contact);
+
+    let res = Contact::resolve(&store, stage, "alice");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), address);
+
+    let encoded = address::encode(stage, &address);
+    let res = Contact::resolve(&store, stage, &encoded);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), address);
+
+    let res = Contact::query(&store, stage, None, None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().len(), 1);
+
+    let res = Contact::remove(&mut store, stage, &contact.id);
+    assert!(res.is_ok());
+
+    let res = Contact::resolve(&store, stage, "alice");
+    assert!(res.is_err());
+}
@@ -0,0 +1,347 @@
+//! # Emission
+//!
+//! `emission` contains the coin emission schedule and the accounting of the
+//! amounts issued by the mined coinbases of the accepted `Transaction`s.
+//!
+//! The schedule expects `COINBASES_PER_DISTANCE` coinbases at each distance from
+//! the eve `Transaction`, each one of the `Coinbase` amount at that distance and
+//! difficulty. The eve coinbase is part of the genesis and is not scheduled.
+//!
+//! The schedule is enforced on the ancestry issuance of a `Transaction`: the
+//! amount of its own mined `Coinbase` added to the highest ancestry issuance of
+//! its ancestors. It depends only on the ancestors, so that all the nodes agree
+//! on it, and it cannot exceed what the schedule issues up to the distance of
+//! the `Transaction`, which is bound by the distances of its ancestors.
+
+use crate::coinbase::Coinbase;
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::transaction::Transaction;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use crypto::hash::Digest;
+use mining::common::riemmann_zeta_2;
+use mining::difficulty::MIN_DIFFICULTY;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use store::error::Error as StoreError;
+use store::traits::Store;
+
+/// `EMISSION_KEY_PREFIX` is the prefix of the emission keys.
+pub const EMISSION_KEY_PREFIX: u8 = 16;
+
+/// `ISSUANCE_TAG` is the tag of the `Issuance` key.
+pub const ISSUANCE_TAG: u8 = 0;
+
+/// `RECORDED_TAG` is the tag of the keys of the ancestry issuances of the `Transaction`s
+/// recorded in the `Issuance`.
+pub const RECORDED_TAG: u8 = 1;
+
+/// `COINBASES_PER_DISTANCE` is the number of coinbases expected by the schedule
+/// at each distance from the eve `Transaction`.
+pub const COINBASES_PER_DISTANCE: u64 = 16;
+
+/// `Issuance` is the total amount issued by the mined coinbases of the
+/// accepted `Transaction`s of a stage.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct Issuance {
+    pub amount: u64,
+    pub coinbases: u64,
+    pub max_distance: u64,
+}
+
+impl Issuance {
+    /// `record` records the amount of a `Coinbase` in the `Issuance`.
+    pub fn record(&mut self, coinbase: &Coinbase) {
+        self.amount = self.amount.saturating_add(coinbase.amount);
+        self.coinbases += 1;
+
+        if coinbase.distance > self.max_distance {
+            self.max_distance = coinbase.distance;
+        }
+    }
+
+    /// `to_bytes` converts the `Issuance` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Issuance`.
+    pub fn from_bytes(b: &[u8]) -> Result<Issuance> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+}
+
+/// `expected_issuance` returns the cumulative amount the schedule expects to be
+/// issued by the coinbases up to a distance, mined at a difficulty.
+pub fn expected_issuance(distance: u64, difficulty: u64) -> Result<u64> {
+    if distance == 0 {
+        return Ok(0);
+    }
+
+    if difficulty == 0 || difficulty > Coinbase::MAX_DIFFICULTY {
+        let err = Error::OutOfBound;
+        return Err(err);
+    }
+
+    let difficulty_zeta = riemmann_zeta_2(difficulty)?;
+
+    // NB: the epoch zeta is summed in the same order as in `riemmann_zeta_2`,
+    // so that the amounts are the same as the `Coinbase` ones
+    let mut epoch = 0u64;
+    let mut epoch_zeta = 0f64;
+
+    let mut total = 0u64;
+    let mut from = 1u64;
+
+    loop {
+        while epoch < Coinbase::epoch(from) {
+            epoch += 1;
+            epoch_zeta += 1f64 / epoch.pow(2) as f64;
+        }
+
+        let amount = ((Coinbase::BASE_AMOUNT as f64) * epoch_zeta / difficulty_zeta).floor() as u64;
+
+        let epoch_end = (from / Coinbase::EPOCH_LEN + 1)
+            .saturating_mul(Coinbase::EPOCH_LEN)
+            .saturating_sub(1);
+        let to = epoch_end.min(distance);
+
+        let coinbases = (to - from + 1).saturating_mul(COINBASES_PER_DISTANCE);
+        total = total.saturating_add(amount.saturating_mul(coinbases));
+
+        if to == distance {
+            break;
+        }
+
+        from = to + 1;
+    }
+
+    Ok(total)
+}
+
+/// `max_issuance` returns the cumulative amount the schedule issues at most
+/// up to a distance, with all the coinbases mined at the minimum difficulty.
+pub fn max_issuance(distance: u64) -> Result<u64> {
+    expected_issuance(distance, MIN_DIFFICULTY)
+}
+
+/// `issuance_key` returns the `Store` key of the `Issuance` of a stage.
+fn issuance_key(stage: Stage) -> Vec<u8> {
+    vec![stage as u8, EMISSION_KEY_PREFIX, ISSUANCE_TAG]
+}
+
+/// `recorded_key` returns the `Store` key of the ancestry issuance of a recorded `Transaction`.
+fn recorded_key(stage: Stage, tx_id: &Digest) -> Vec<u8> {
+    let mut buf = vec![stage as u8, EMISSION_KEY_PREFIX, RECORDED_TAG];
+    buf.extend_from_slice(&tx_id.to_bytes());
+    buf
+}
+
+/// `get_issuance` returns the `Issuance` of a stage.
+pub fn get_issuance<S: Store>(store: &S, stage: Stage) -> Result<Issuance> {
+    match store.get(&issuance_key(stage)) {
+        Ok(buf) => Issuance::from_bytes(&buf),
+        Err(StoreError::NotFound) => Ok(Issuance::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `is_recorded` returns if a `Transaction` has been recorded in the `Issuance`.
+pub fn is_recorded<S: Store>(store: &S, stage: Stage, tx_id: &Digest) -> Result<bool> {
+    store
+        .lookup(&recorded_key(stage, tx_id))
+        .map_err(|e| e.into())
+}
+
+/// `get_ancestry_issuance` returns the ancestry issuance of a recorded `Transaction`, if any.
+pub fn get_ancestry_issuance<S: Store>(
+    store: &S,
+    stage: Stage,
+    tx_id: &Digest,
+) -> Result<Option<u64>> {
+    match store.get(&recorded_key(stage, tx_id)) {
+        Ok(buf) => {
+            let issued = buf.as_slice().read_u64::<BigEndian>()?;
+            Ok(Some(issued))
+        }
+        Err(StoreError::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `scheduled_coinbase` returns the mined, non-eve `Coinbase` of a `Transaction`, if any.
+fn scheduled_coinbase(transaction: &Transaction) -> Option<Coinbase> {
+    transaction
+        .coinbase
+        .filter(|coinbase| coinbase.is_mined() && coinbase.distance != 0)
+}
+
+/// `ancestry_issuance` returns the ancestry issuance of a `Transaction` given the
+/// ancestry issuances of its ancestors: the highest of them, plus the amount of
+/// the mined `Coinbase` of the `Transaction`.
+pub fn ancestry_issuance(transaction: &Transaction, ancestors_issued: &[u64]) -> u64 {
+    let issued = ancestors_issued.iter().copied().max().unwrap_or(0);
+    let amount = scheduled_coinbase(transaction).map_or(0, |coinbase| coinbase.amount);
+
+    issued.saturating_add(amount)
+}
+
+/// `validate_issuance` validates that the mined `Coinbase` of a `Transaction`
+/// has the scheduled amount and that the ancestry issuance of the `Transaction`
+/// does not exceed the schedule at its distance.
+pub fn validate_issuance(transaction: &Transaction, issued: u64) -> Result<()> {
+    let coinbase = match scheduled_coinbase(transaction) {
+        Some(coinbase) => coinbase,
+        None => return Ok(()),
+    };
+
+    if coinbase.amount != coinbase.calc_amount()? {
+        let err = Error::InvalidCoinbase;
+        return Err(err);
+    }
+
+    if issued > max_issuance(transaction.distance)? {
+        let err = Error::ExcessiveIssuance;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// `record_issuance` records the ancestry issuance of an accepted `Transaction`,
+/// adding the amount of its mined `Coinbase` to the `Issuance`. A `Transaction`
+/// is recorded only once.
+pub fn record_issuance<S: Store>(
+    store: &mut S,
+    stage: Stage,
+    transaction: &Transaction,
+    issued: u64,
+) -> Result<()> {
+    if is_recorded(store, stage, &transaction.id)? {
+        return Ok(());
+    }
+
+    if let Some(coinbase) = scheduled_coinbase(transaction) {
+        let mut issuance = get_issuance(store, stage)?;
+        issuance.record(&coinbase);
+
+        store.insert(&issuance_key(stage), &issuance.to_bytes()?, None)?;
+    }
+
+    let mut buf = Vec::new();
+    buf.write_u64::<BigEndian>(issued)?;
+
+    store
+        .insert(&recorded_key(stage, &transaction.id), &buf, None)
+        .map_err(|e| e.into())
+}
+
+#[test]
+fn test_expected_issuance() {
+    use crate::address::Address;
+
+    let res = expected_issuance(0, 1);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+
+    let res = expected_issuance(1, 0);
+    assert!(res.is_err());
+
+    let res = expected_issuance(1, Coinbase::MAX_DIFFICULTY + 1);
+    assert!(res.is_err());
+
+    let address = Address::random().unwrap();
+    let difficulty = 3;
+    let distance = 2 * Coinbase::EPOCH_LEN + 10;
+
+    let mut expected = 0;
+
+    for d in 1..=distance {
+        let coinbase = Coinbase::new(&address, d, difficulty).unwrap();
+        expected += coinbase.amount * COINBASES_PER_DISTANCE;
+
+        if d % 500 == 0 || d == distance {
+            let res = expected_issuance(d, difficulty);
+            assert!(res.is_ok());
+            assert_eq!(res.unwrap(), expected);
+        }
+    }
+
+    let higher = expected_issuance(distance, difficulty + 1).unwrap();
+    assert!(higher < expected);
+}
+
+#[test]
+fn test_issuance_record() {
+    use crate::address::Address;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let difficulty = 1;
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.set_coinbase(&address, difficulty).unwrap();
+    transaction.mine().unwrap();
+
+    let amount = transaction.coinbase_amount();
+
+    let res = get_issuance(&store, stage);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Issuance::default());
+
+    let res = get_ancestry_issuance(&store, stage, &transaction.id);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let issued = ancestry_issuance(&transaction, &[]);
+    assert_eq!(issued, amount);
+
+    let res = validate_issuance(&transaction, issued);
+    assert!(res.is_ok());
+
+    for _ in 0..2 {
+        let res = record_issuance(&mut store, stage, &transaction, issued);
+        assert!(res.is_ok());
+    }
+
+    let issuance = get_issuance(&store, stage).unwrap();
+    assert_eq!(issuance.amount, amount);
+    assert_eq!(issuance.coinbases, 1);
+    assert_eq!(issuance.max_distance, transaction.distance);
+
+    let res = get_ancestry_issuance(&store, stage, &transaction.id);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Some(issued));
+
+    let max = max_issuance(transaction.distance).unwrap();
+    assert_eq!(max, amount * COINBASES_PER_DISTANCE);
+
+    let issued = ancestry_issuance(&transaction, &[max - amount, 0]);
+    assert_eq!(issued, max);
+
+    let res = validate_issuance(&transaction, issued);
+    assert!(res.is_ok());
+
+    let issued = ancestry_issuance(&transaction, &[max]);
+
+    let res = validate_issuance(&transaction, issued);
+    assert!(res.is_err());
+
+    let unmined = Transaction::new().unwrap();
+    assert_eq!(ancestry_issuance(&unmined, &[max]), max);
+
+    let mut coinbase = transaction.coinbase.unwrap();
+    coinbase.amount += 1;
+    transaction.coinbase = Some(coinbase);
+
+    let res = validate_issuance(&transaction, amount);
+    assert!(res.is_err());
+}
@@ -2,7 +2,9 @@
 //!
 //! `error` contains the `models` crate `Error` type.
 
+use crate::error_code::ErrorCode;
 use chrono;
+use config;
 use crypto;
 use mining;
 use regex;
@@ -28,8 +30,12 @@ pub enum Error {
     Mining { msg: String },
     #[fail(display = "Store: {}", msg)]
     Store { msg: String },
+    #[fail(display = "Config: {}", msg)]
+    Config { msg: String },
     #[fail(display = "Out of bound")]
     OutOfBound,
+    #[fail(display = "Invalid consensus params: {}", msg)]
+    InvalidParams { msg: String },
     #[fail(display = "No regex match")]
     NoRegexMatch,
     #[fail(display = "Invalid version")]
@@ -76,12 +82,24 @@ pub enum Error {
     InvalidCoinbase,
     #[fail(display = "Not mined")]
     NotMined,
+    #[fail(display = "Invalid proof")]
+    InvalidProof,
     #[fail(display = "Invalid threshold")]
     InvalidThreshold,
     #[fail(display = "Invalid node")]
     InvalidNode,
     #[fail(display = "Invalid account")]
     InvalidAccount,
+    #[fail(display = "Locked account")]
+    LockedAccount,
+    #[fail(display = "Locked balance")]
+    LockedBalance,
+    #[fail(display = "Invalid stake")]
+    InvalidStake,
+    #[fail(display = "Excessive issuance")]
+    ExcessiveIssuance,
+    #[fail(display = "Immature coinbase")]
+    ImmatureCoinbase,
     #[fail(display = "Invalid input")]
     InvalidInput,
     #[fail(display = "Invalid transaction")]
@@ -90,6 +108,80 @@ pub enum Error {
     InvalidTransactions,
     #[fail(display = "Invalid message")]
     InvalidMessage,
+    #[fail(display = "Invalid encoding")]
+    InvalidEncoding,
+    #[fail(display = "Wallet locked")]
+    WalletLocked,
+    #[fail(display = "Already encrypted")]
+    AlreadyEncrypted,
+    #[fail(display = "Not encrypted")]
+    NotEncrypted,
+    #[fail(display = "Too long: {} > {}", len, max)]
+    TooLong { len: u64, max: u64 },
+    #[fail(display = "Too large collection: {} > {}", len, max)]
+    TooLargeCollection { len: u64, max: u64 },
+    #[fail(display = "Too deep nesting: {} > {}", depth, max)]
+    TooDeepNesting { depth: u32, max: u32 },
+    #[fail(display = "Test vector {}: {}", name, msg)]
+    TestVector { name: String, msg: String },
+}
+
+impl Error {
+    /// `code` returns the `ErrorCode` of the `Error`.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::IO { .. } | Error::Config { .. } | Error::Crypto { .. } => ErrorCode::Internal,
+            Error::Chrono { .. }
+            | Error::Regex { .. }
+            | Error::Parse { .. }
+            | Error::NoRegexMatch
+            | Error::InvalidChecksum
+            | Error::InvalidEncoding
+            | Error::TestVector { .. } => ErrorCode::InvalidEncoding,
+            Error::Mining { .. } => ErrorCode::Mining,
+            Error::Store { .. } => ErrorCode::Store,
+            Error::OutOfBound | Error::InvalidParams { .. } => ErrorCode::OutOfBound,
+            Error::InvalidVersion | Error::AlreadyEncrypted | Error::NotEncrypted => {
+                ErrorCode::InvalidArgument
+            }
+            Error::InvalidStage => ErrorCode::InvalidStage,
+            Error::InvalidTimestamp | Error::InvalidLocktime => ErrorCode::InvalidTimestamp,
+            Error::InvalidId => ErrorCode::InvalidId,
+            Error::InvalidPublicKey
+            | Error::InvalidSignature
+            | Error::NotSigned
+            | Error::NotFullySigned
+            | Error::InvalidThreshold => ErrorCode::InvalidSignature,
+            Error::NotImplemented => ErrorCode::NotImplemented,
+            Error::AlreadyFound => ErrorCode::AlreadyFound,
+            Error::NotFound => ErrorCode::NotFound,
+            Error::InvalidLength
+            | Error::TooLong { .. }
+            | Error::TooLargeCollection { .. }
+            | Error::TooDeepNesting { .. } => ErrorCode::InvalidLength,
+            Error::InvalidAmount
+            | Error::InvalidBalance
+            | Error::InvalidFee
+            | Error::InvalidInput
+            | Error::InvalidAccount => ErrorCode::InvalidAccount,
+            Error::InvalidAddress => ErrorCode::InvalidAddress,
+            Error::InvalidDistance
+            | Error::InvalidDifficulty
+            | Error::InvalidCoinbase
+            | Error::InvalidStake
+            | Error::InvalidTransaction
+            | Error::InvalidTransactions => ErrorCode::InvalidTransaction,
+            Error::NotMined => ErrorCode::NotMined,
+            Error::InvalidProof => ErrorCode::InvalidProof,
+            Error::InvalidNode => ErrorCode::InvalidNode,
+            Error::InvalidMessage => ErrorCode::InvalidMessage,
+            Error::WalletLocked => ErrorCode::WalletLocked,
+            Error::LockedAccount => ErrorCode::LockedAccount,
+            Error::ExcessiveIssuance => ErrorCode::ExcessiveIssuance,
+            Error::ImmatureCoinbase => ErrorCode::ImmatureCoinbase,
+            Error::LockedBalance => ErrorCode::LockedBalance,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -150,7 +242,25 @@ impl From<mining::error::Error> for Error {
 
 impl From<store::error::Error> for Error {
     fn from(err: store::error::Error) -> Error {
-        let msg = format!("{}", err);
-        Error::Store { msg }
+        match err {
+            store::error::Error::NotFound => Error::NotFound,
+            store::error::Error::AlreadyFound => Error::AlreadyFound,
+            _ => {
+                let msg = format!("{}", err);
+                Error::Store { msg }
+            }
+        }
+    }
+}
+
+impl From<config::error::Error> for Error {
+    fn from(err: config::error::Error) -> Error {
+        match err {
+            config::error::Error::InvalidParams { msg } => Error::InvalidParams { msg },
+            _ => {
+                let msg = format!("{}", err);
+                Error::Config { msg }
+            }
+        }
     }
 }
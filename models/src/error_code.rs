@@ -0,0 +1,206 @@
+//! # Error Code
+//!
+//! `error_code` contains the error codes shared by the crates `Error` types.
+//! The codes are stable numbers grouped by category, surfaced through the RPC
+//! and the `Reject` network message, so that the clients can distinguish
+//! the retryable errors from the fatal ones.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// `ErrorCategory` is the category of an `ErrorCode`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum ErrorCategory {
+    /// The request or the data are invalid.
+    Validation,
+    /// The store failed or the item was not found.
+    Storage,
+    /// The network or the remote node failed.
+    Network,
+    /// The consensus or the mining failed.
+    Consensus,
+    /// The node failed internally.
+    Internal,
+}
+
+impl ErrorCategory {
+    /// `from_code` returns the `ErrorCategory` of a numeric code.
+    pub fn from_code(code: u16) -> Result<ErrorCategory> {
+        match code / 1000 {
+            1 => Ok(ErrorCategory::Validation),
+            2 => Ok(ErrorCategory::Storage),
+            3 => Ok(ErrorCategory::Network),
+            4 => Ok(ErrorCategory::Consensus),
+            5 => Ok(ErrorCategory::Internal),
+            _ => {
+                let err = Error::OutOfBound;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorCategory::Validation => write!(f, "validation"),
+            ErrorCategory::Storage => write!(f, "storage"),
+            ErrorCategory::Network => write!(f, "network"),
+            ErrorCategory::Consensus => write!(f, "consensus"),
+            ErrorCategory::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+/// `ErrorCode` is the numeric code of an error. The thousands of the code are
+/// its `ErrorCategory`.
+#[repr(u16)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum ErrorCode {
+    InvalidArgument = 1000,
+    InvalidId = 1001,
+    InvalidLength = 1002,
+    InvalidStage = 1003,
+    InvalidTimestamp = 1004,
+    InvalidSignature = 1005,
+    InvalidAddress = 1006,
+    InvalidAccount = 1007,
+    InvalidTransaction = 1008,
+    InvalidNode = 1009,
+    InvalidMessage = 1010,
+    InvalidEncoding = 1011,
+    OutOfBound = 1012,
+    WalletLocked = 1013,
+    ExpiredTransaction = 1014,
+    LockedAccount = 1015,
+    ExcessiveIssuance = 1016,
+    ImmatureCoinbase = 1017,
+    LockedBalance = 1018,
+    NotFound = 2000,
+    AlreadyFound = 2001,
+    Store = 2002,
+    ReadOnly = 2003,
+    Network = 3000,
+    BannedNode = 3001,
+    IncompatibleVersion = 3002,
+    ReplayedMessage = 3003,
+    Consensus = 4000,
+    AlreadyMined = 4001,
+    NotMined = 4002,
+    Mining = 4003,
+    InvalidProof = 4004,
+    DoubleSpend = 4005,
+    Internal = 5000,
+    NotImplemented = 5001,
+    NotAllowed = 5002,
+}
+
+impl ErrorCode {
+    /// `VALUES` are all the `ErrorCode`s.
+    pub const VALUES: &'static [ErrorCode] = &[
+        ErrorCode::InvalidArgument,
+        ErrorCode::InvalidId,
+        ErrorCode::InvalidLength,
+        ErrorCode::InvalidStage,
+        ErrorCode::InvalidTimestamp,
+        ErrorCode::InvalidSignature,
+        ErrorCode::InvalidAddress,
+        ErrorCode::InvalidAccount,
+        ErrorCode::InvalidTransaction,
+        ErrorCode::InvalidNode,
+        ErrorCode::InvalidMessage,
+        ErrorCode::InvalidEncoding,
+        ErrorCode::OutOfBound,
+        ErrorCode::WalletLocked,
+        ErrorCode::ExpiredTransaction,
+        ErrorCode::LockedAccount,
+        ErrorCode::ExcessiveIssuance,
+        ErrorCode::ImmatureCoinbase,
+        ErrorCode::LockedBalance,
+        ErrorCode::NotFound,
+        ErrorCode::AlreadyFound,
+        ErrorCode::Store,
+        ErrorCode::ReadOnly,
+        ErrorCode::Network,
+        ErrorCode::BannedNode,
+        ErrorCode::IncompatibleVersion,
+        ErrorCode::ReplayedMessage,
+        ErrorCode::Consensus,
+        ErrorCode::AlreadyMined,
+        ErrorCode::NotMined,
+        ErrorCode::Mining,
+        ErrorCode::InvalidProof,
+        ErrorCode::DoubleSpend,
+        ErrorCode::Internal,
+        ErrorCode::NotImplemented,
+        ErrorCode::NotAllowed,
+    ];
+
+    /// `to_u16` returns the numeric code of the `ErrorCode`.
+    pub fn to_u16(self) -> u16 {
+        self as u16
+    }
+
+    /// `from_u16` returns the `ErrorCode` of a numeric code.
+    pub fn from_u16(code: u16) -> Result<ErrorCode> {
+        for value in ErrorCode::VALUES.iter() {
+            if value.to_u16() == code {
+                return Ok(*value);
+            }
+        }
+
+        let err = Error::OutOfBound;
+        Err(err)
+    }
+
+    /// `category` returns the `ErrorCategory` of the `ErrorCode`.
+    pub fn category(self) -> ErrorCategory {
+        // NB: all the values have a valid category, as checked in the tests
+        ErrorCategory::from_code(self.to_u16()).unwrap_or(ErrorCategory::Internal)
+    }
+
+    /// `is_retryable` returns if the operation failed with the `ErrorCode` can
+    /// be retried, as the error is expected to be transient.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            ErrorCode::Store
+            | ErrorCode::Network
+            | ErrorCode::Consensus
+            | ErrorCode::NotMined
+            | ErrorCode::WalletLocked => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "E{}", self.to_u16())
+    }
+}
+
+#[test]
+fn test_error_code_u16() {
+    for code in ErrorCode::VALUES.iter() {
+        let res = ErrorCode::from_u16(code.to_u16());
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap(), code);
+
+        let res = ErrorCategory::from_code(code.to_u16());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), code.category());
+    }
+
+    let res = ErrorCode::from_u16(0);
+    assert!(res.is_err());
+
+    let res = ErrorCode::from_u16(1999);
+    assert!(res.is_err());
+
+    assert_eq!(ErrorCode::NotFound.category(), ErrorCategory::Storage);
+    assert_eq!(&ErrorCode::NotFound.to_string(), "E2000");
+    assert!(ErrorCode::Network.is_retryable());
+    assert!(!ErrorCode::InvalidTransaction.is_retryable());
+}
@@ -0,0 +1,300 @@
+//! # Genesis
+//!
+//! `genesis` contains the `Genesis` type and functions.
+
+use crate::account::Account;
+use crate::error::Error;
+use crate::result::Result;
+use crate::signer::Signer;
+use crate::signers::Signers;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::transaction::Transaction;
+use config::consensus::ConsensusConfig;
+use crypto::ecc::ed25519::{PublicKey, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+
+/// `Genesis` contains the chain parameters of an Alsacoin network: the eve
+/// `Account`, the eve `Transaction`, the initial mining difficulty and the
+/// consensus parameters. It is signed by the key of the network creator.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Genesis {
+    pub stage: Stage,
+    pub time: Timestamp,
+    pub eve_account: Account,
+    pub eve_transaction: Transaction,
+    pub difficulty: u64,
+    pub consensus: ConsensusConfig,
+    pub public_key: PublicKey,
+    pub signature: Option<Signature>,
+}
+
+impl Genesis {
+    /// `MAX_DIFFICULTY` is the maximum initial mining difficulty.
+    pub const MAX_DIFFICULTY: u64 = 512;
+
+    /// `new` creates a new unsigned `Genesis`, mining its eve `Transaction`.
+    pub fn new(
+        stage: Stage,
+        eve_account: &Account,
+        difficulty: u64,
+        consensus: &ConsensusConfig,
+        public_key: &PublicKey,
+    ) -> Result<Genesis> {
+        let mut eve_transaction = Transaction::new_eve(stage, &eve_account.address())?;
+        eve_transaction.mine()?;
+        eve_transaction.update_id()?;
+
+        let mut consensus = consensus.to_owned();
+        consensus.populate();
+
+        let genesis = Genesis {
+            stage,
+            time: Timestamp::now(),
+            eve_account: eve_account.to_owned(),
+            eve_transaction,
+            difficulty,
+            consensus,
+            public_key: public_key.to_owned(),
+            signature: None,
+        };
+
+        genesis.validate()?;
+
+        Ok(genesis)
+    }
+
+    /// `random` creates a random unsigned `Genesis` with an eve `Account` owned
+    /// by a `SecretKey`.
+    pub fn random(secret_key: &SecretKey) -> Result<Genesis> {
+        let stage = Stage::random()?;
+        let public_key = secret_key.to_public();
+
+        let signer = Signer {
+            public_key,
+            weight: 1,
+        };
+
+        let mut signers = Signers::new()?;
+        signers.add(&signer)?;
+        signers.set_threshold(1)?;
+
+        let eve_account = Account::new_eve(stage, &signers)?;
+        let consensus = ConsensusConfig::default();
+
+        Genesis::new(stage, &eve_account, 1, &consensus, &public_key)
+    }
+
+    /// `sign_message` returns the binary message to use when signing the `Genesis`.
+    pub fn sign_message(&self) -> Result<Vec<u8>> {
+        let mut clone = self.clone();
+        clone.signature = None;
+
+        clone.to_bytes()
+    }
+
+    /// `sign` signs the `Genesis`.
+    pub fn sign(&mut self, secret_key: &SecretKey) -> Result<()> {
+        if secret_key.to_public() != self.public_key {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        let msg = self.sign_message()?;
+        let signature = secret_key.sign(&msg);
+
+        self.signature = Some(signature);
+
+        Ok(())
+    }
+
+    /// `is_signed` returns if the `Genesis` is signed.
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+
+    /// `verify_signature` verifies the `Genesis` signature.
+    pub fn verify_signature(&self) -> Result<()> {
+        if let Some(signature) = self.signature {
+            let msg = self.sign_message()?;
+
+            self.public_key
+                .verify(&signature, &msg)
+                .map_err(|_| Error::InvalidSignature)
+        } else {
+            let err = Error::NotSigned;
+            Err(err)
+        }
+    }
+
+    /// `validate` validates the `Genesis`.
+    pub fn validate(&self) -> Result<()> {
+        if self.eve_account.stage != self.stage || self.eve_transaction.stage != self.stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        if !self.eve_account.is_eve()? {
+            let err = Error::InvalidAccount;
+            return Err(err);
+        }
+
+        self.eve_account.validate()?;
+
+        self.eve_transaction.validate()?;
+
+        if !self.eve_transaction.is_eve()? {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        if let Some(coinbase) = self.eve_transaction.coinbase {
+            if coinbase.address != self.eve_account.address() {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+        }
+
+        self.eve_transaction.validate_mined(None)?;
+
+        if self.difficulty == 0 || self.difficulty > Self::MAX_DIFFICULTY {
+            let err = Error::InvalidDifficulty;
+            return Err(err);
+        }
+
+        self.consensus.validate()?;
+
+        Ok(())
+    }
+
+    /// `validate_signed` validates the `Genesis` expecting it to be signed
+    /// by a specific `PublicKey`.
+    pub fn validate_signed(&self, public_key: &PublicKey) -> Result<()> {
+        if &self.public_key != public_key {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        self.validate()?;
+
+        self.verify_signature()
+    }
+
+    /// `to_bytes` converts the `Genesis` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Genesis`.
+    pub fn from_bytes(b: &[u8]) -> Result<Genesis> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Genesis` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `Genesis`.
+    pub fn from_json(s: &str) -> Result<Genesis> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_genesis_new() {
+    let secret_key = SecretKey::random().unwrap();
+    let genesis = Genesis::random(&secret_key).unwrap();
+
+    let res = genesis.validate();
+    assert!(res.is_ok());
+
+    let mut invalid_genesis = genesis.clone();
+    invalid_genesis.difficulty = 0;
+
+    let res = invalid_genesis.validate();
+    assert!(res.is_err());
+
+    let mut invalid_genesis = genesis.clone();
+    invalid_genesis.difficulty = Genesis::MAX_DIFFICULTY + 1;
+
+    let res = invalid_genesis.validate();
+    assert!(res.is_err());
+
+    let other_secret_key = SecretKey::random().unwrap();
+    let other_genesis = Genesis::random(&other_secret_key).unwrap();
+
+    let mut invalid_genesis = genesis;
+    invalid_genesis.eve_account = other_genesis.eve_account;
+
+    let res = invalid_genesis.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_genesis_sign() {
+    let secret_key = SecretKey::random().unwrap();
+    let public_key = secret_key.to_public();
+    let mut genesis = Genesis::random(&secret_key).unwrap();
+
+    assert!(!genesis.is_signed());
+
+    let res = genesis.verify_signature();
+    assert!(res.is_err());
+
+    let invalid_secret_key = SecretKey::random().unwrap();
+
+    let res = genesis.sign(&invalid_secret_key);
+    assert!(res.is_err());
+
+    let res = genesis.sign(&secret_key);
+    assert!(res.is_ok());
+    assert!(genesis.is_signed());
+
+    let res = genesis.validate_signed(&public_key);
+    assert!(res.is_ok());
+
+    let res = genesis.validate_signed(&invalid_secret_key.to_public());
+    assert!(res.is_err());
+
+    genesis.difficulty += 1;
+
+    let res = genesis.verify_signature();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_genesis_serialize_bytes() {
+    let secret_key = SecretKey::random().unwrap();
+    let mut genesis_a = Genesis::random(&secret_key).unwrap();
+    genesis_a.sign(&secret_key).unwrap();
+
+    let res = genesis_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = Genesis::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let genesis_b = res.unwrap();
+
+    assert_eq!(genesis_a, genesis_b)
+}
+
+#[test]
+fn test_genesis_serialize_json() {
+    let secret_key = SecretKey::random().unwrap();
+    let mut genesis_a = Genesis::random(&secret_key).unwrap();
+    genesis_a.sign(&secret_key).unwrap();
+
+    let res = genesis_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = Genesis::from_json(&json);
+    assert!(res.is_ok());
+    let genesis_b = res.unwrap();
+
+    assert_eq!(genesis_a, genesis_b)
+}
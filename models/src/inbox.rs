@@ -0,0 +1,453 @@
+//! # Inbox
+//!
+//! `inbox` contains the type used to spill the incoming messages to the store
+//! while the consensus loop is behind, so that they are handled later instead
+//! of blocking the transport or being dropped.
+
+use crate::consensus_message::ConsensusMessage;
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use byteorder::{BigEndian, WriteBytesExt};
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `Inbox` is an incoming `ConsensusMessage` spilled to the store, with the
+/// sequence number of its arrival. The `Inbox` messages are handled in order.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Inbox {
+    pub seq: u64,
+    pub id: Digest,
+    pub stage: Stage,
+    pub message: ConsensusMessage,
+    pub received_at: Timestamp,
+}
+
+impl Inbox {
+    /// `new` creates a new `Inbox` of a `ConsensusMessage` with a sequence number.
+    pub fn new(seq: u64, message: &ConsensusMessage) -> Result<Inbox> {
+        message.validate()?;
+
+        let inbox = Inbox {
+            seq,
+            id: message.digest()?,
+            stage: message.node().stage,
+            message: message.to_owned(),
+            received_at: Timestamp::now(),
+        };
+
+        inbox.validate()?;
+
+        Ok(inbox)
+    }
+
+    /// `next_seq` returns the sequence number of the next `Inbox` of a stage.
+    pub fn next_seq<S: Store>(store: &S, stage: Stage) -> Result<u64> {
+        let last = <Self as Storable<S>>::query(store, stage, None, None, None, None)?
+            .into_iter()
+            .map(|inbox| inbox.seq)
+            .max()
+            .unwrap_or(0);
+
+        Ok(last + 1)
+    }
+
+    /// `oldest` returns up to a number of the oldest `Inbox` messages of a stage, in order.
+    pub fn oldest<S: Store>(store: &S, stage: Stage, count: u32) -> Result<Vec<Inbox>> {
+        let oldest = <Self as Storable<S>>::query(store, stage, None, None, Some(count), None)?
+            .into_iter()
+            .take(count as usize)
+            .collect();
+
+        Ok(oldest)
+    }
+
+    /// `validate` validates the `Inbox`.
+    pub fn validate(&self) -> Result<()> {
+        self.message.validate()?;
+        self.received_at.validate()?;
+
+        if self.seq == 0 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        if self.id != self.message.digest()? {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        if self.stage != self.message.node().stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Inbox` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Inbox`.
+    pub fn from_bytes(b: &[u8]) -> Result<Inbox> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Inbox` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `Inbox`.
+    pub fn from_json(s: &str) -> Result<Inbox> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for Inbox {
+    const KEY_PREFIX: u8 = 19;
+
+    type Key = u64;
+
+    fn key(&self) -> Self::Key {
+        self.seq
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.write_u64::<BigEndian>(*key)?;
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let inbox = Inbox::from_bytes(&value)?;
+            if inbox.received_at < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_inbox_ops() {
+    use crate::node::Node;
+    use crypto::random::Random;
+
+    let address = Random::bytes(100).unwrap();
+    let node = Node::random(100).unwrap();
+    let message = ConsensusMessage::new_fetch_random_nodes(&address, &node, 10).unwrap();
+
+    let res = Inbox::new(0, &message);
+    assert!(res.is_err());
+
+    let res = Inbox::new(1, &message);
+    assert!(res.is_ok());
+
+    let mut inbox = res.unwrap();
+    assert_eq!(inbox.stage, node.stage);
+
+    let res = Inbox::from_json(&inbox.to_json().unwrap());
+    assert_eq!(res.unwrap(), inbox);
+
+    inbox.id = Digest::random().unwrap();
+
+    let res = inbox.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_inbox_storable() {
+    use crate::node::Node;
+    use crypto::random::Random;
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 16;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let address = Random::bytes(100).unwrap();
+    let node = Node::random(100).unwrap();
+    let stage = node.stage;
+
+    let res = Inbox::next_seq(&store, stage);
+    assert_eq!(res.unwrap(), 1);
+
+    for count in 1..=3 {
+        let message = ConsensusMessage::new_fetch_random_nodes(&address, &node, count).unwrap();
+        let seq = Inbox::next_seq(&store, stage).unwrap();
+        let inbox = Inbox::new(seq, &message).unwrap();
+
+        let res = Inbox::create(&mut store, stage, &inbox);
+        assert!(res.is_ok());
+    }
+
+    let res = Inbox::count(&store, stage, None, None, None);
+    assert_eq!(res.unwrap(), 3);
+
+    let res = Inbox::oldest(&store, stage, 2);
+    assert!(res.is_ok());
+
+    let oldest = res.unwrap();
+    let seqs: Vec<u64> = oldest.iter().map(|inbox| inbox.seq).collect();
+    assert_eq!(seqs, vec![1, 2]);
+
+    let res = Inbox::remove(&mut store, stage, &1);
+    assert!(res.is_ok());
+
+    let res = Inbox::lookup(&store, stage, &1);
+    assert!(!res.unwrap());
+
+    let res = Inbox::next_seq(&store, stage);
+    assert_eq!(res.unwrap(), 4);
+
+    let res = <Inbox as Storable<BTreeStore>>::clear(&mut store, stage);
+    assert!(res.is_ok());
+
+    let res = Inbox::count(&store, stage, None, None, None);
+    assert_eq!(res.unwrap(), 0);
+}
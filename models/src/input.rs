@@ -9,7 +9,28 @@ use crate::result::Result;
 use crate::transaction::Transaction;
 use crypto::ecc::ed25519::{KeyPair, PublicKey, SecretKey, Signature};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `SignaturesStatus` is the signing status of a multisig `Input`.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SignaturesStatus {
+    pub address: Address,
+    pub weight: u64,
+    pub threshold: u64,
+    pub missing_signers: BTreeSet<PublicKey>,
+}
+
+impl SignaturesStatus {
+    /// `is_complete` returns if the signatures weight reached the threshold.
+    pub fn is_complete(&self) -> bool {
+        self.weight >= self.threshold
+    }
+
+    /// `missing_weight` returns the weight still needed to reach the threshold.
+    pub fn missing_weight(&self) -> u64 {
+        self.threshold.saturating_sub(self.weight)
+    }
+}
 
 /// `Input` is an input in an Alsacoin `Transaction`.
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize)]
@@ -132,6 +153,71 @@ impl Input {
         public_key.verify(&signature, &msg).map_err(|e| e.into())
     }
 
+    /// `validate_signature` validates a signature of the `Input` given a binary seed,
+    /// without adding it to the `Input`.
+    pub fn validate_signature(
+        &self,
+        public_key: &PublicKey,
+        signature: &Signature,
+        seed: &[u8],
+    ) -> Result<()> {
+        if !self.account.signers.lookup(public_key) {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        let msg = self.signature_message(seed)?;
+
+        public_key.verify(signature, &msg).map_err(|e| e.into())
+    }
+
+    /// `add_signature` adds a signature calculated by another signer to the `Input`,
+    /// validating it first. Signatures already in the `Input` are kept.
+    pub fn add_signature(
+        &mut self,
+        public_key: &PublicKey,
+        signature: &Signature,
+        seed: &[u8],
+    ) -> Result<()> {
+        self.validate_signature(public_key, signature, seed)?;
+
+        if let Some(found) = self.signatures.get(public_key) {
+            if found != signature {
+                let err = Error::InvalidSignature;
+                return Err(err);
+            }
+
+            return Ok(());
+        }
+
+        self.signatures.insert(*public_key, *signature);
+
+        Ok(())
+    }
+
+    /// `missing_signers` returns the signers of the `Input` account that did not sign it yet.
+    pub fn missing_signers(&self) -> BTreeSet<PublicKey> {
+        self.account
+            .signers
+            .signers
+            .keys()
+            .filter(|pk| !self.signatures.contains_key(pk))
+            .copied()
+            .collect()
+    }
+
+    /// `signatures_status` returns the `SignaturesStatus` of the `Input`.
+    pub fn signatures_status(&self) -> Result<SignaturesStatus> {
+        let status = SignaturesStatus {
+            address: self.address(),
+            weight: self.signatures_weight()?,
+            threshold: self.account.signers.threshold,
+            missing_signers: self.missing_signers(),
+        };
+
+        Ok(status)
+    }
+
     /// `is_signed` returns if the `Input` has been signed by someone.
     pub fn is_signed(&self) -> bool {
         let signatures_len = self.signatures.len();
@@ -343,6 +429,83 @@ fn test_input_sign() {
     assert!(res.unwrap());
 }
 
+#[test]
+fn test_input_add_signature() {
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crate::stage::Stage;
+    use crypto::hash::Digest;
+    use crypto::random::Random;
+
+    let stage = Stage::random().unwrap();
+
+    let secret_key_a = SecretKey::random().unwrap();
+    let public_key_a = secret_key_a.to_public();
+    let secret_key_b = SecretKey::random().unwrap();
+    let public_key_b = secret_key_b.to_public();
+    let secret_key_c = SecretKey::random().unwrap();
+    let msg = Random::bytes(1000).unwrap();
+    let weight = 10;
+
+    let mut signers = Signers::new().unwrap();
+    signers.threshold = 20;
+
+    signers
+        .add(&Signer {
+            public_key: public_key_a,
+            weight,
+        })
+        .unwrap();
+    signers
+        .add(&Signer {
+            public_key: public_key_b,
+            weight,
+        })
+        .unwrap();
+
+    let amount = Random::u64().unwrap();
+    let tx_id = Digest::random().unwrap();
+    let account = Account::new(stage, &signers, amount, Some(tx_id)).unwrap();
+    let mut input = Input::new(&account, 1, amount).unwrap();
+
+    let status = input.signatures_status().unwrap();
+    assert_eq!(status.weight, 0);
+    assert_eq!(status.missing_weight(), 20);
+    assert_eq!(status.missing_signers.len(), 2);
+    assert!(!status.is_complete());
+
+    let signature_a = input.calc_signature(&secret_key_a, &msg).unwrap();
+    let signature_b = input.calc_signature(&secret_key_b, &msg).unwrap();
+
+    let res = input.validate_signature(&public_key_a, &signature_b, &msg);
+    assert!(res.is_err());
+
+    let res = input.validate_signature(&secret_key_c.to_public(), &signature_a, &msg);
+    assert!(res.is_err());
+
+    let res = input.add_signature(&public_key_a, &signature_a, &msg);
+    assert!(res.is_ok());
+
+    let res = input.add_signature(&public_key_a, &signature_a, &msg);
+    assert!(res.is_ok());
+
+    let status = input.signatures_status().unwrap();
+    assert_eq!(status.weight, 10);
+    assert!(status.missing_signers.contains(&public_key_b));
+    assert!(!status.is_complete());
+
+    let res = input.add_signature(&public_key_b, &signature_b, &msg);
+    assert!(res.is_ok());
+
+    let status = input.signatures_status().unwrap();
+    assert_eq!(status.missing_weight(), 0);
+    assert!(status.missing_signers.is_empty());
+    assert!(status.is_complete());
+
+    let res = input.verify_fully_signed(&msg);
+    assert!(res.is_ok());
+}
+
 #[test]
 fn test_input_validate() {
     use crate::signers::Signers;
@@ -6,12 +6,24 @@ extern crate failure;
 /// `error` contains the error type used in the crate.
 pub mod error;
 
+/// `error_code` contains the error codes shared by the crates.
+pub mod error_code;
+
 /// `result` contains the result type used in the crate.
 pub mod result;
 
 /// `utils` contains various utilities used in the crate.
 pub mod utils;
 
+/// `cbor` contains the bounded CBOR decoding types and functions.
+pub mod cbor;
+
+/// `msgpack` contains the bounded MessagePack checking functions.
+pub mod msgpack;
+
+/// `bloom_filter` contains the Bloom filter type and functions.
+pub mod bloom_filter;
+
 /// `traits` contains traits used in the crate.
 pub mod traits;
 
@@ -24,6 +36,9 @@ pub mod stage;
 /// `version` contains the version type and functions.
 pub mod version;
 
+/// `net_address` contains the network address types and functions.
+pub mod net_address;
+
 /// `node` contains the node type and functions.
 pub mod node;
 
@@ -33,6 +48,9 @@ pub mod wallet;
 /// `address` contains the address type and functions.
 pub mod address;
 
+/// `contact` contains the wallet address book contact type and functions.
+pub mod contact;
+
 /// `signer` contains the signer type and functions.
 pub mod signer;
 
@@ -51,14 +69,77 @@ pub mod output;
 /// `coinbase` contains the coinbase type and functions.
 pub mod coinbase;
 
+/// `emission` contains the coin emission schedule and issuance functions.
+pub mod emission;
+
 /// `transaction` contains the transaction type and functions.
 pub mod transaction;
 
+/// `coin_selection` contains the wallet coin selection types and functions.
+pub mod coin_selection;
+
+/// `transaction_builder` contains the transaction builder type and functions.
+pub mod transaction_builder;
+
+/// `partial_signatures` contains the partial signatures type and functions.
+pub mod partial_signatures;
+
+/// `signing_request` contains the offline signing request type and functions.
+pub mod signing_request;
+
+/// `transaction_proof` contains the transaction proof type and functions.
+pub mod transaction_proof;
+
+/// `validation_cache` contains the transaction validation cache type and functions.
+pub mod validation_cache;
+
+/// `genesis` contains the genesis type and functions.
+pub mod genesis;
+
 /// `conflict_set` contains the conflict set type and functions.
 pub mod conflict_set;
 
 /// `consensus_state` contains the consensus state type and functions.
 pub mod consensus_state;
 
+/// `consensus_params` contains the consensus parameters update type and functions.
+pub mod consensus_params;
+
 /// `consensus_message` contains the consensus message type and functions.
 pub mod consensus_message;
+
+/// `tx_telemetry` contains the transaction consensus telemetry type and functions.
+pub mod tx_telemetry;
+
+/// `node_ban` contains the node ban type and functions.
+pub mod node_ban;
+
+/// `node_reputation` contains the node reputation type and functions.
+pub mod node_reputation;
+
+/// `work` contains the mining work template type and functions.
+pub mod work;
+
+/// `outbox` contains the outbound messages queue type and functions.
+pub mod outbox;
+
+/// `inbox` contains the inbound messages spool type and functions.
+pub mod inbox;
+
+/// `watch` contains the watched address type and functions.
+pub mod watch;
+
+/// `stake` contains the locked balance type and functions.
+pub mod stake;
+
+/// `checkpoint` contains the signed node state checkpoint type and functions.
+pub mod checkpoint;
+
+/// `verify` contains the store verifiers of the models.
+pub mod verify;
+
+/// `migrations` contains the store migrations of the models.
+pub mod migrations;
+
+/// `testvectors` contains the serialization test vectors of the models.
+pub mod testvectors;
@@ -0,0 +1,277 @@
+//! # Migrations
+//!
+//! `migrations` contains the `Store` migrations of the models, run at startup
+//! to upgrade the records written with a previous serialization of the models.
+
+use crate::address::Address;
+use crate::coinbase::Coinbase;
+use crate::input::Input;
+use crate::output::Output;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crate::transaction::Transaction;
+use crate::version::Version;
+use crypto::hash::balloon::BalloonParams;
+use crypto::hash::Digest;
+use mining::proof::Proof;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use store::migrations::Migration;
+use store::result::Result as StoreResult;
+use store::traits::Store;
+
+/// `StorableMigration` is the `Migration` of the instances of a `Storable` model
+/// from a previous version of the model, decoding the records as the previous
+/// version and converting them into the current one.
+pub struct StorableMigration<O, T> {
+    version: u32,
+    name: String,
+    stage: Stage,
+    model: PhantomData<(O, T)>,
+}
+
+impl<O, T> StorableMigration<O, T> {
+    /// `new` creates a new `StorableMigration`.
+    pub fn new(version: u32, name: &str, stage: Stage) -> StorableMigration<O, T> {
+        StorableMigration {
+            version,
+            name: name.into(),
+            stage,
+            model: PhantomData,
+        }
+    }
+}
+
+impl<S, O, T> Migration<S> for StorableMigration<O, T>
+where
+    S: Store,
+    O: DeserializeOwned,
+    T: Storable<S> + Serialize + From<O>,
+{
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn migrate(&self, store: &mut S) -> StoreResult<()> {
+        let (from, to) = <T as Storable<S>>::stage_range(self.stage);
+
+        let mut items = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let old: O = serde_cbor::from_slice(&value)?;
+            let value = serde_cbor::to_vec(&T::from(old))?;
+            items.push((key, value));
+        }
+
+        for (key, value) in items {
+            store.update(&key, &value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `CoinbaseV1` is the `Coinbase` layout of the schema version 1, keeping the
+/// mining nonce and digest in the `Coinbase` instead of in a mining `Proof`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CoinbaseV1 {
+    pub address: Address,
+    pub distance: u64,
+    pub difficulty: u64,
+    pub custom_digest: Digest,
+    pub amount: u64,
+    pub params: BalloonParams,
+    pub nonce: u64,
+    pub digest: Digest,
+    pub mined: bool,
+}
+
+impl From<CoinbaseV1> for Coinbase {
+    fn from(old: CoinbaseV1) -> Coinbase {
+        let proof = if old.mined {
+            Some(Proof {
+                params: old.params,
+                difficulty: old.difficulty,
+                nonce: old.nonce,
+                digest: old.digest,
+            })
+        } else {
+            None
+        };
+
+        Coinbase {
+            address: old.address,
+            distance: old.distance,
+            difficulty: old.difficulty,
+            custom_digest: old.custom_digest,
+            amount: old.amount,
+            params: old.params,
+            proof,
+        }
+    }
+}
+
+/// `TransactionV1` is the `Transaction` layout of the schema version 1, with a
+/// `CoinbaseV1` coinbase.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TransactionV1 {
+    pub id: Digest,
+    pub version: Version,
+    pub stage: Stage,
+    pub time: Timestamp,
+    pub locktime: Option<Timestamp>,
+    #[serde(default)]
+    pub expiry: Option<Timestamp>,
+    pub distance: u64,
+    pub inputs: BTreeMap<Address, Input>,
+    pub outputs: BTreeMap<Address, Output>,
+    pub coinbase: Option<CoinbaseV1>,
+    pub nonce: u64,
+}
+
+impl From<TransactionV1> for Transaction {
+    fn from(old: TransactionV1) -> Transaction {
+        Transaction {
+            id: old.id,
+            version: old.version,
+            stage: old.stage,
+            time: old.time,
+            locktime: old.locktime,
+            expiry: old.expiry,
+            distance: old.distance,
+            inputs: old.inputs,
+            outputs: old.outputs,
+            coinbase: old.coinbase.map(Coinbase::from),
+            nonce: old.nonce,
+            stakes: BTreeMap::new(),
+        }
+    }
+}
+
+/// `migrations` returns the `Migration`s of all the models of a stage, in schema
+/// version order. A `Migration` is appended when the serialization of a model
+/// changes, with the version following the last one.
+pub fn migrations<S: Store>(stage: Stage) -> Vec<Box<dyn Migration<S>>> {
+    vec![Box::new(
+        StorableMigration::<TransactionV1, Transaction>::new(2, "coinbase_proof", stage),
+    )]
+}
+
+#[test]
+fn test_storable_migration() {
+    use crate::node::Node;
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+    use store::migrations::migrate;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let node = Node::new(stage, b"127.0.0.1:2019");
+    Node::insert(&mut store, stage, &node).unwrap();
+
+    let res = migrate(&mut store, &migrations(stage));
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_migrated());
+
+    let res = migrate(&mut store, &migrations(stage));
+    assert!(res.is_ok());
+    assert!(!res.unwrap().is_migrated());
+
+    let node_migrations: Vec<Box<dyn Migration<_>>> = vec![Box::new(
+        StorableMigration::<Node, Node>::new(3, "node", stage),
+    )];
+
+    let res = migrate(&mut store, &node_migrations);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().applied, vec!["node".to_string()]);
+    assert_eq!(Node::get(&store, stage, &node.id).unwrap(), node);
+
+    let (mut key, _) = <Node as Storable<BTreeStore>>::stage_range(stage);
+    key.push(0);
+    store.insert(&key, &[0xff], None).unwrap();
+
+    let res = StorableMigration::<Node, Node>::new(4, "node", stage).migrate(&mut store);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_coinbase_proof_migration() {
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+    use store::migrations::migrate;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+
+    let coinbase = Coinbase::default();
+
+    let mut old = TransactionV1 {
+        id: transaction.id,
+        version: transaction.version.clone(),
+        stage,
+        time: transaction.time,
+        locktime: transaction.locktime,
+        expiry: transaction.expiry,
+        distance: transaction.distance,
+        inputs: transaction.inputs.clone(),
+        outputs: transaction.outputs.clone(),
+        coinbase: Some(CoinbaseV1 {
+            address: coinbase.address,
+            distance: coinbase.distance,
+            difficulty: coinbase.difficulty,
+            custom_digest: coinbase.custom_digest,
+            amount: coinbase.amount,
+            params: coinbase.params,
+            nonce: 7,
+            digest: Digest::default(),
+            mined: true,
+        }),
+        nonce: transaction.nonce,
+    };
+
+    let (mut mined_key, _) = <Transaction as Storable<BTreeStore>>::stage_range(stage);
+    mined_key.push(0);
+    let value = serde_cbor::to_vec(&old).unwrap();
+    store.insert(&mined_key, &value, None).unwrap();
+
+    old.coinbase.as_mut().unwrap().mined = false;
+
+    let (mut unmined_key, _) = <Transaction as Storable<BTreeStore>>::stage_range(stage);
+    unmined_key.push(1);
+    let value = serde_cbor::to_vec(&old).unwrap();
+    store.insert(&unmined_key, &value, None).unwrap();
+
+    let res = migrate(&mut store, &migrations(stage));
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().applied, vec!["coinbase_proof".to_string()]);
+
+    let value = store.get(&mined_key).unwrap();
+    let mined: Transaction = serde_cbor::from_slice(&value).unwrap();
+    let proof = mined.coinbase.unwrap().proof.unwrap();
+    assert_eq!(proof.nonce, 7);
+    assert_eq!(proof.params, coinbase.params);
+    assert_eq!(proof.difficulty, coinbase.difficulty);
+
+    let value = store.get(&unmined_key).unwrap();
+    let unmined: Transaction = serde_cbor::from_slice(&value).unwrap();
+    assert_eq!(unmined.coinbase, Some(coinbase));
+}
@@ -0,0 +1,260 @@
+//! # MessagePack
+//!
+//! `msgpack` contains the bounded MessagePack checking functions. A MessagePack
+//! binary is checked against the same `CborLimits` of a CBOR binary before
+//! decoding it.
+
+use crate::cbor::CborLimits;
+use crate::error::Error;
+use crate::result::Result;
+
+/// `MsgPackChecker` walks a MessagePack binary checking it against a `CborLimits`
+/// without allocating.
+struct MsgPackChecker<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    limits: CborLimits,
+}
+
+impl<'a> MsgPackChecker<'a> {
+    /// `remaining` returns the number of unread bytes.
+    fn remaining(&self) -> u64 {
+        (self.buf.len() - self.pos) as u64
+    }
+
+    /// `read_u8` reads a byte.
+    fn read_u8(&mut self) -> Result<u8> {
+        if self.pos >= self.buf.len() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        let b = self.buf[self.pos];
+        self.pos += 1;
+
+        Ok(b)
+    }
+
+    /// `skip` skips a number of bytes.
+    fn skip(&mut self, len: u64) -> Result<()> {
+        if len > self.remaining() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        self.pos += len as usize;
+
+        Ok(())
+    }
+
+    /// `read_len` reads a big endian length of a given number of bytes.
+    fn read_len(&mut self, size: usize) -> Result<u64> {
+        let mut len = 0u64;
+
+        for _ in 0..size {
+            len = (len << 8) | u64::from(self.read_u8()?);
+        }
+
+        Ok(len)
+    }
+
+    /// `check_collection_len` checks the length of a collection against the
+    /// limits and the unread bytes, each item being at least `min_item_len`
+    /// bytes long.
+    fn check_collection_len(&self, len: u64, min_item_len: u64) -> Result<()> {
+        if len > self.limits.max_collection_len {
+            let err = Error::TooLargeCollection {
+                len,
+                max: self.limits.max_collection_len,
+            };
+            return Err(err);
+        }
+
+        if len.saturating_mul(min_item_len) > self.remaining() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `check_depth` checks a nesting depth against the limits.
+    fn check_depth(&self, depth: u32) -> Result<()> {
+        if depth > self.limits.max_depth {
+            let err = Error::TooDeepNesting {
+                depth,
+                max: self.limits.max_depth,
+            };
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `check_bytes` checks a string, a binary or an extension payload.
+    fn check_bytes(&mut self, len: u64) -> Result<()> {
+        self.check_collection_len(len, 1)?;
+        self.skip(len)
+    }
+
+    /// `check_ext` checks an extension of a given payload length size.
+    fn check_ext(&mut self, size: usize) -> Result<()> {
+        let len = self.read_len(size)?;
+        self.skip(1)?;
+        self.check_bytes(len)
+    }
+
+    /// `check_items` checks a sequence of items.
+    fn check_items(&mut self, len: u64, items_per_entry: u64, depth: u32) -> Result<()> {
+        self.check_depth(depth)?;
+        self.check_collection_len(len, items_per_entry)?;
+
+        for _ in 0..len * items_per_entry {
+            self.check_item(depth)?;
+        }
+
+        Ok(())
+    }
+
+    /// `check_item` checks a single item at a given nesting depth.
+    fn check_item(&mut self, depth: u32) -> Result<()> {
+        let b = self.read_u8()?;
+
+        match b {
+            0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => Ok(()),
+            0x80..=0x8f => self.check_items(u64::from(b & 0x0f), 2, depth + 1),
+            0x90..=0x9f => self.check_items(u64::from(b & 0x0f), 1, depth + 1),
+            0xa0..=0xbf => self.check_bytes(u64::from(b & 0x1f)),
+            0xc4 | 0xd9 => {
+                let len = self.read_len(1)?;
+                self.check_bytes(len)
+            }
+            0xc5 | 0xda => {
+                let len = self.read_len(2)?;
+                self.check_bytes(len)
+            }
+            0xc6 | 0xdb => {
+                let len = self.read_len(4)?;
+                self.check_bytes(len)
+            }
+            0xc7 => self.check_ext(1),
+            0xc8 => self.check_ext(2),
+            0xc9 => self.check_ext(4),
+            0xcc | 0xd0 => self.skip(1),
+            0xcd | 0xd1 => self.skip(2),
+            0xca | 0xce | 0xd2 => self.skip(4),
+            0xcb | 0xcf | 0xd3 => self.skip(8),
+            0xd4 => self.skip(2),
+            0xd5 => self.skip(3),
+            0xd6 => self.skip(5),
+            0xd7 => self.skip(9),
+            0xd8 => self.skip(17),
+            0xdc => {
+                let len = self.read_len(2)?;
+                self.check_items(len, 1, depth + 1)
+            }
+            0xdd => {
+                let len = self.read_len(4)?;
+                self.check_items(len, 1, depth + 1)
+            }
+            0xde => {
+                let len = self.read_len(2)?;
+                self.check_items(len, 2, depth + 1)
+            }
+            0xdf => {
+                let len = self.read_len(4)?;
+                self.check_items(len, 2, depth + 1)
+            }
+            _ => {
+                let err = Error::InvalidEncoding;
+                Err(err)
+            }
+        }
+    }
+}
+
+/// `check` checks a MessagePack binary against a `CborLimits`, without decoding it.
+/// The binary must contain exactly one well formed MessagePack item.
+pub fn check(buf: &[u8], limits: &CborLimits) -> Result<()> {
+    let len = buf.len() as u64;
+
+    if len > limits.max_length {
+        let err = Error::TooLong {
+            len,
+            max: limits.max_length,
+        };
+        return Err(err);
+    }
+
+    let mut checker = MsgPackChecker {
+        buf,
+        pos: 0,
+        limits: limits.to_owned(),
+    };
+
+    checker.check_item(0)?;
+
+    if checker.remaining() != 0 {
+        let err = Error::InvalidEncoding;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_msgpack_check() {
+    let limits = CborLimits::default();
+
+    // {"key": [1, 2, 3]}
+    let buf = [0x81, 0xa3, b'k', b'e', b'y', 0x93, 0x01, 0x02, 0x03];
+
+    let res = check(&buf, &limits);
+    assert!(res.is_ok());
+
+    let mut invalid_buf = buf.to_vec();
+    invalid_buf.push(0);
+
+    let res = check(&invalid_buf, &limits);
+    assert!(res.is_err());
+
+    let res = check(&buf[..buf.len() - 1], &limits);
+    assert!(res.is_err());
+
+    let res = check(&[], &limits);
+    assert!(res.is_err());
+
+    let res = check(&[0xc1], &limits);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_msgpack_limits() {
+    let limits = CborLimits::new(16, 4, 2);
+
+    let buf = [0xc4, 0x0f, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let res = check(&buf, &limits);
+    match res {
+        Err(Error::TooLong { .. }) => {}
+        _ => panic!("expected TooLong"),
+    }
+
+    let buf = [0x95, 0, 0, 0, 0, 0];
+    let res = check(&buf, &limits);
+    match res {
+        Err(Error::TooLargeCollection { .. }) => {}
+        _ => panic!("expected TooLargeCollection"),
+    }
+
+    let buf = [0x91, 0x91, 0x91, 0x00];
+    let res = check(&buf, &limits);
+    match res {
+        Err(Error::TooDeepNesting { .. }) => {}
+        _ => panic!("expected TooDeepNesting"),
+    }
+
+    // an array claiming 2^32 - 1 items in a 5 bytes binary
+    let buf = [0xdd, 0xff, 0xff, 0xff, 0xff];
+    let res = check(&buf, &CborLimits::default());
+    assert!(res.is_err());
+}
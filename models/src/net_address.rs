@@ -0,0 +1,365 @@
+//! # Net Address
+//!
+//! `net_address` contains the `NetAddress` type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+/// `NetAddress` is a network endpoint a `Node` can be reached at.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum NetAddress {
+    Ipv4 { ip: [u8; 4], port: u16 },
+    Ipv6 { ip: [u8; 16], port: u16 },
+    Host { host: String, port: u16 },
+    Onion { host: String, port: u16 },
+}
+
+impl NetAddress {
+    /// `MAX_HOST_LEN` is the maximum length of a `NetAddress` host name.
+    pub const MAX_HOST_LEN: usize = 253;
+
+    /// `MAX_LABEL_LEN` is the maximum length of a label of a `NetAddress` host name.
+    pub const MAX_LABEL_LEN: usize = 63;
+
+    /// `ONION_SUFFIX` is the suffix of the onion service host names.
+    pub const ONION_SUFFIX: &'static str = ".onion";
+
+    /// `ONION_V2_LEN` is the length of the base32 part of a v2 onion host name.
+    pub const ONION_V2_LEN: usize = 16;
+
+    /// `ONION_V3_LEN` is the length of the base32 part of a v3 onion host name.
+    pub const ONION_V3_LEN: usize = 56;
+
+    /// `new_ipv4` creates a new IPv4 `NetAddress`.
+    pub fn new_ipv4(ip: [u8; 4], port: u16) -> Result<NetAddress> {
+        let address = NetAddress::Ipv4 { ip, port };
+        address.validate()?;
+
+        Ok(address)
+    }
+
+    /// `new_ipv6` creates a new IPv6 `NetAddress`.
+    pub fn new_ipv6(ip: [u8; 16], port: u16) -> Result<NetAddress> {
+        let address = NetAddress::Ipv6 { ip, port };
+        address.validate()?;
+
+        Ok(address)
+    }
+
+    /// `new_host` creates a new host name `NetAddress`. Onion host names
+    /// are returned as onion `NetAddress`es.
+    pub fn new_host(host: &str, port: u16) -> Result<NetAddress> {
+        let host = host.to_lowercase();
+
+        let address = if host.ends_with(Self::ONION_SUFFIX) {
+            NetAddress::Onion { host, port }
+        } else {
+            NetAddress::Host { host, port }
+        };
+
+        address.validate()?;
+
+        Ok(address)
+    }
+
+    /// `from_socket_addr` creates a new `NetAddress` from a `SocketAddr`.
+    pub fn from_socket_addr(address: &SocketAddr) -> Result<NetAddress> {
+        match address.ip() {
+            IpAddr::V4(ip) => Self::new_ipv4(ip.octets(), address.port()),
+            IpAddr::V6(ip) => Self::new_ipv6(ip.octets(), address.port()),
+        }
+    }
+
+    /// `parse` parses a `NetAddress` from a `host:port` string. IPv6
+    /// addresses are written in brackets, as in `[::1]:2019`.
+    pub fn parse(s: &str) -> Result<NetAddress> {
+        if let Ok(address) = s.parse::<SocketAddr>() {
+            return Self::from_socket_addr(&address);
+        }
+
+        let mut parts = s.rsplitn(2, ':');
+
+        let port = parts.next().unwrap_or_default();
+        let host = match parts.next() {
+            Some(host) => host,
+            None => {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+        };
+
+        let port: u16 = port.parse().map_err(|_| Error::InvalidAddress)?;
+
+        Self::new_host(host, port)
+    }
+
+    /// `port` returns the `NetAddress` port.
+    pub fn port(&self) -> u16 {
+        match self {
+            NetAddress::Ipv4 { port, .. } => *port,
+            NetAddress::Ipv6 { port, .. } => *port,
+            NetAddress::Host { port, .. } => *port,
+            NetAddress::Onion { port, .. } => *port,
+        }
+    }
+
+    /// `is_ip` returns if the `NetAddress` is an IP address.
+    pub fn is_ip(&self) -> bool {
+        match self {
+            NetAddress::Ipv4 { .. } | NetAddress::Ipv6 { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// `is_onion` returns if the `NetAddress` is an onion service address.
+    pub fn is_onion(&self) -> bool {
+        match self {
+            NetAddress::Onion { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// `to_socket_addrs` resolves the `NetAddress` into `SocketAddr`s.
+    /// Onion addresses cannot be resolved without a proxy.
+    pub fn to_socket_addrs(&self) -> Result<Vec<SocketAddr>> {
+        self.validate()?;
+
+        match self {
+            NetAddress::Ipv4 { ip, port } => {
+                let ip = IpAddr::V4(Ipv4Addr::from(*ip));
+                Ok(vec![SocketAddr::new(ip, *port)])
+            }
+            NetAddress::Ipv6 { ip, port } => {
+                let ip = IpAddr::V6(Ipv6Addr::from(*ip));
+                Ok(vec![SocketAddr::new(ip, *port)])
+            }
+            NetAddress::Host { host, port } => {
+                let addresses = (host.as_str(), *port).to_socket_addrs()?.collect();
+                Ok(addresses)
+            }
+            NetAddress::Onion { .. } => {
+                let err = Error::NotImplemented;
+                Err(err)
+            }
+        }
+    }
+
+    /// `validate_host` validates a host name.
+    fn validate_host(host: &str) -> Result<()> {
+        if host.is_empty() || host.len() > Self::MAX_HOST_LEN {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        for label in host.split('.') {
+            let valid_label = !label.is_empty()
+                && label.len() <= Self::MAX_LABEL_LEN
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+            if !valid_label {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_onion` validates an onion service host name.
+    fn validate_onion(host: &str) -> Result<()> {
+        if !host.ends_with(Self::ONION_SUFFIX) {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        let name = &host[..host.len() - Self::ONION_SUFFIX.len()];
+
+        let valid_len = name.len() == Self::ONION_V2_LEN || name.len() == Self::ONION_V3_LEN;
+        let valid_chars = name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || ('2'..='7').contains(&c));
+
+        if !valid_len || !valid_chars {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate` validates the `NetAddress`.
+    pub fn validate(&self) -> Result<()> {
+        if self.port() == 0 {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        match self {
+            NetAddress::Ipv4 { ip, .. } => {
+                if Ipv4Addr::from(*ip).is_unspecified() {
+                    let err = Error::InvalidAddress;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            NetAddress::Ipv6 { ip, .. } => {
+                if Ipv6Addr::from(*ip).is_unspecified() {
+                    let err = Error::InvalidAddress;
+                    return Err(err);
+                }
+
+                Ok(())
+            }
+            NetAddress::Host { host, .. } => {
+                if host.ends_with(Self::ONION_SUFFIX) {
+                    let err = Error::InvalidAddress;
+                    return Err(err);
+                }
+
+                Self::validate_host(host)
+            }
+            NetAddress::Onion { host, .. } => Self::validate_onion(host),
+        }
+    }
+
+    /// `to_bytes` converts the `NetAddress` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `NetAddress`.
+    pub fn from_bytes(b: &[u8]) -> Result<NetAddress> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `NetAddress` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `NetAddress`.
+    pub fn from_json(s: &str) -> Result<NetAddress> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl fmt::Display for NetAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetAddress::Ipv4 { ip, port } => write!(f, "{}:{}", Ipv4Addr::from(*ip), port),
+            NetAddress::Ipv6 { ip, port } => write!(f, "[{}]:{}", Ipv6Addr::from(*ip), port),
+            NetAddress::Host { host, port } => write!(f, "{}:{}", host, port),
+            NetAddress::Onion { host, port } => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+#[test]
+fn test_net_address_parse() {
+    let valid = [
+        "127.0.0.1:2019",
+        "[::1]:2019",
+        "[2001:db8::1]:8080",
+        "node.alsacoin.org:2019",
+        "expyuzz4wqqyqhjn.onion:2019",
+        "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion:2019",
+    ];
+
+    for s in valid.iter() {
+        let res = NetAddress::parse(s);
+        assert!(res.is_ok());
+
+        let address = res.unwrap();
+        assert_eq!(&address.to_string(), s);
+
+        let res = NetAddress::parse(&address.to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), address);
+    }
+
+    let invalid = [
+        "127.0.0.1",
+        "127.0.0.1:0",
+        "0.0.0.0:2019",
+        "[::]:2019",
+        "::1:2019",
+        ":2019",
+        "node..alsacoin.org:2019",
+        "-node.alsacoin.org:2019",
+        "node_1.alsacoin.org:2019",
+        "node.alsacoin.org:port",
+        "short.onion:2019",
+        "expyuzz4wqqyqhj1.onion:2019",
+    ];
+
+    for s in invalid.iter() {
+        let res = NetAddress::parse(s);
+        assert!(res.is_err());
+    }
+
+    let res = NetAddress::parse("127.0.0.1:2019");
+    assert!(res.unwrap().is_ip());
+
+    let res = NetAddress::parse("expyuzz4wqqyqhjn.onion:2019");
+    assert!(res.unwrap().is_onion());
+}
+
+#[test]
+fn test_net_address_socket_addrs() {
+    let address = NetAddress::new_ipv6(Ipv6Addr::LOCALHOST.octets(), 2019).unwrap();
+
+    let res = address.to_socket_addrs();
+    assert!(res.is_ok());
+
+    let socket_addr = res.unwrap()[0];
+    assert_eq!(socket_addr.port(), 2019);
+
+    let res = NetAddress::from_socket_addr(&socket_addr);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), address);
+
+    let address = NetAddress::parse("expyuzz4wqqyqhjn.onion:2019").unwrap();
+
+    let res = address.to_socket_addrs();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_net_address_serialize_bytes() {
+    let address_a = NetAddress::parse("[2001:db8::1]:8080").unwrap();
+
+    let res = address_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = NetAddress::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let address_b = res.unwrap();
+
+    assert_eq!(address_a, address_b)
+}
+
+#[test]
+fn test_net_address_serialize_json() {
+    let address_a = NetAddress::parse("node.alsacoin.org:2019").unwrap();
+
+    let res = address_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = NetAddress::from_json(&json);
+    assert!(res.is_ok());
+    let address_b = res.unwrap();
+
+    assert_eq!(address_a, address_b)
+}
@@ -3,28 +3,130 @@
 //! `node` contains the Node model.
 
 use crate::error::Error;
+use crate::net_address::NetAddress;
 use crate::result::Result;
 use crate::stage::Stage;
 use crate::timestamp::Timestamp;
 use crate::traits::Storable;
+use crypto::ecc::ed25519::{PublicKey, SecretKey, Signature};
 use crypto::hash::{Blake512Hasher, Digest};
 use crypto::random::Random;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
 use std::collections::BTreeSet;
+use std::fmt;
 use store::traits::Store;
 
+/// `NodeAnnouncement` is the self-signed announcement of a `Node`. It is relayed
+/// unchanged by the other nodes.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct NodeAnnouncement {
+    pub public_key: PublicKey,
+    pub time: Timestamp,
+    pub signature: Signature,
+}
+
+/// `KeyRotation` links the old and the new identity keys of a `Node`. It is
+/// signed by both keys, so that the other nodes can move the `Node` to the new
+/// key without treating it as a new one.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub old_public_key: PublicKey,
+    pub new_public_key: PublicKey,
+    pub time: Timestamp,
+    pub old_signature: Signature,
+    pub new_signature: Signature,
+}
+
+/// `Service` is a service advertised by a `Node` in its handshake.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Service {
+    /// The node mines the transactions.
+    Mining,
+    /// The node keeps all the transactions, never pruning its store.
+    Archival,
+    /// The node takes part in the consensus, relaying the transactions.
+    Relay,
+    /// The node serves the client gRPC API.
+    Rpc,
+    /// The node accepts the compact queries, carrying only the queried transaction id.
+    CompactQuery,
+}
+
+impl Service {
+    /// `parse` parses a `Service` from a string.
+    pub fn parse(s: &str) -> Result<Service> {
+        match s {
+            "mining" => Ok(Service::Mining),
+            "archival" => Ok(Service::Archival),
+            "relay" => Ok(Service::Relay),
+            "rpc" => Ok(Service::Rpc),
+            "compact" => Ok(Service::CompactQuery),
+            _ => {
+                let msg = format!("invalid service: {}", s);
+                let err = Error::Parse { msg };
+                Err(err)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Service::Mining => write!(f, "mining"),
+            Service::Archival => write!(f, "archival"),
+            Service::Relay => write!(f, "relay"),
+            Service::Rpc => write!(f, "rpc"),
+            Service::CompactQuery => write!(f, "compact"),
+        }
+    }
+}
+
 /// Type representing a node in the distributed ledger network.
+/// The latency is the rolling average round-trip time in milliseconds of the
+/// queries to the `Node`, and the work is the mining work of the valid proofs
+/// submitted by the `Node`. Both are measured locally and never trusted from
+/// the network.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize)]
 pub struct Node {
     pub id: Digest,
     pub address: Vec<u8>,
     pub stage: Stage,
     pub last_seen: Timestamp,
+    #[serde(default)]
+    pub addresses: BTreeSet<NetAddress>,
+    #[serde(default)]
+    pub announcement: Option<NodeAnnouncement>,
+    #[serde(default)]
+    pub services: BTreeSet<Service>,
+    #[serde(default)]
+    pub latency: Option<u64>,
+    #[serde(default)]
+    pub work: u64,
+    #[serde(default)]
+    pub rotation: Option<KeyRotation>,
 }
 
 impl Node {
+    /// `MAX_ADDRESSES` is the maximum number of `NetAddress`es a `Node` can advertise.
+    pub const MAX_ADDRESSES: usize = 16;
+
+    /// `MAX_ANNOUNCEMENT_AGE` is the maximum age in seconds of a `NodeAnnouncement`
+    /// accepted from the network.
+    pub const MAX_ANNOUNCEMENT_AGE: i64 = 7 * 24 * 3600;
+
+    /// `IPV4_SUBNET_LEN` is the length of the subnet prefix of an IPv4 `Node` address (/16).
+    pub const IPV4_SUBNET_LEN: usize = 2;
+
+    /// `IPV6_SUBNET_LEN` is the length of the subnet prefix of an IPv6 `Node` address (/32).
+    pub const IPV6_SUBNET_LEN: usize = 4;
+
+    /// `LATENCY_WEIGHT` is the inverse of the weight of a new round-trip time
+    /// in the rolling average latency of a `Node`.
+    pub const LATENCY_WEIGHT: u64 = 8;
+
     /// Creates a new `Node`.
     pub fn new(stage: Stage, address: &[u8]) -> Node {
         let hash = Blake512Hasher::hash(address);
@@ -34,6 +136,12 @@ impl Node {
             address: address.into(),
             stage,
             last_seen: Timestamp::now(),
+            addresses: BTreeSet::new(),
+            announcement: None,
+            services: BTreeSet::new(),
+            latency: None,
+            work: 0,
+            rotation: None,
         }
     }
 
@@ -47,11 +155,245 @@ impl Node {
             address,
             stage: Stage::random()?,
             last_seen: Timestamp::now(),
+            addresses: BTreeSet::new(),
+            announcement: None,
+            services: BTreeSet::new(),
+            latency: None,
+            work: 0,
+            rotation: None,
         };
 
         Ok(node)
     }
 
+    /// `lookup_address` returns if the `Node` advertises a `NetAddress`.
+    pub fn lookup_address(&self, address: &NetAddress) -> bool {
+        self.addresses.contains(address)
+    }
+
+    /// `add_address` adds a `NetAddress` to the `Node` advertised addresses.
+    pub fn add_address(&mut self, address: &NetAddress) -> Result<()> {
+        address.validate()?;
+
+        if self.lookup_address(address) {
+            let err = Error::AlreadyFound;
+            return Err(err);
+        }
+
+        if self.addresses.len() >= Self::MAX_ADDRESSES {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        self.addresses.insert(address.to_owned());
+
+        Ok(())
+    }
+
+    /// `remove_address` removes a `NetAddress` from the `Node` advertised addresses.
+    pub fn remove_address(&mut self, address: &NetAddress) -> Result<()> {
+        if !self.lookup_address(address) {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        self.addresses.remove(address);
+
+        Ok(())
+    }
+
+    /// `has_service` returns if the `Node` advertises a `Service`.
+    pub fn has_service(&self, service: Service) -> bool {
+        self.services.contains(&service)
+    }
+
+    /// `has_services` returns if the `Node` advertises all of a set of `Service`s.
+    pub fn has_services(&self, services: &BTreeSet<Service>) -> bool {
+        services.is_subset(&self.services)
+    }
+
+    /// `set_services` sets the `Node` advertised `Service`s.
+    pub fn set_services(&mut self, services: &BTreeSet<Service>) {
+        self.services = services.to_owned();
+    }
+
+    /// `record_latency` records a round-trip time in milliseconds in the rolling
+    /// average latency of the `Node`.
+    pub fn record_latency(&mut self, rtt: u64) {
+        let latency = match self.latency {
+            Some(latency) if rtt >= latency => latency + (rtt - latency) / Self::LATENCY_WEIGHT,
+            Some(latency) => latency - (latency - rtt) / Self::LATENCY_WEIGHT,
+            None => rtt,
+        };
+
+        self.latency = Some(latency);
+    }
+
+    /// `record_work` records in the work of the `Node` a valid proof of a mining
+    /// difficulty. Every difficulty bit doubles the work.
+    pub fn record_work(&mut self, difficulty: u64) {
+        let work = 1u64 << difficulty.min(63);
+        self.work = self.work.saturating_add(work);
+    }
+
+    /// `announcement_message` returns the message signed by a `NodeAnnouncement`.
+    pub fn announcement_message(&self, time: Timestamp) -> Result<Vec<u8>> {
+        let content = (&self.id, &self.address, self.stage, &self.addresses, time);
+        serde_cbor::to_vec(&content).map_err(|e| e.into())
+    }
+
+    /// `announce` signs the `Node` with its own `SecretKey`.
+    pub fn announce(&mut self, secret_key: &SecretKey) -> Result<()> {
+        let time = Timestamp::now();
+        let msg = self.announcement_message(time)?;
+
+        let announcement = NodeAnnouncement {
+            public_key: secret_key.to_public(),
+            time,
+            signature: secret_key.sign(&msg),
+        };
+
+        self.announcement = Some(announcement);
+
+        Ok(())
+    }
+
+    /// `is_announced` returns if the `Node` carries a `NodeAnnouncement`.
+    pub fn is_announced(&self) -> bool {
+        self.announcement.is_some()
+    }
+
+    /// `verify_announcement_signature` verifies the `NodeAnnouncement` signature.
+    pub fn verify_announcement_signature(&self) -> Result<()> {
+        if let Some(ref announcement) = self.announcement {
+            let msg = self.announcement_message(announcement.time)?;
+            announcement
+                .public_key
+                .verify(&announcement.signature, &msg)
+                .map_err(|_| Error::InvalidSignature)
+        } else {
+            let err = Error::NotSigned;
+            Err(err)
+        }
+    }
+
+    /// `verify_announcement` verifies the `NodeAnnouncement` signature and time.
+    pub fn verify_announcement(&self) -> Result<()> {
+        self.verify_announcement_signature()?;
+
+        if let Some(ref announcement) = self.announcement {
+            announcement.time.validate()?;
+
+            if Timestamp::now().diff(announcement.time) > Self::MAX_ANNOUNCEMENT_AGE {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `rotation_message` returns the message signed by a `KeyRotation`.
+    pub fn rotation_message(
+        &self,
+        old_public_key: &PublicKey,
+        new_public_key: &PublicKey,
+        time: Timestamp,
+    ) -> Result<Vec<u8>> {
+        let content = (&self.id, self.stage, old_public_key, new_public_key, time);
+        serde_cbor::to_vec(&content).map_err(|e| e.into())
+    }
+
+    /// `rotate_key` moves the `Node` identity from an old `SecretKey` to a new one,
+    /// signing a `KeyRotation` with both and announcing the `Node` with the new one.
+    pub fn rotate_key(
+        &mut self,
+        old_secret_key: &SecretKey,
+        new_secret_key: &SecretKey,
+    ) -> Result<()> {
+        let old_public_key = old_secret_key.to_public();
+        let new_public_key = new_secret_key.to_public();
+
+        if old_public_key == new_public_key {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        let time = Timestamp::now();
+        let msg = self.rotation_message(&old_public_key, &new_public_key, time)?;
+
+        let rotation = KeyRotation {
+            old_public_key,
+            new_public_key,
+            time,
+            old_signature: old_secret_key.sign(&msg),
+            new_signature: new_secret_key.sign(&msg),
+        };
+
+        self.rotation = Some(rotation);
+        self.announce(new_secret_key)
+    }
+
+    /// `verify_key_rotation` verifies the `KeyRotation` signatures and that its
+    /// new key is the one of the `NodeAnnouncement`.
+    pub fn verify_key_rotation(&self) -> Result<()> {
+        let rotation = match self.rotation {
+            Some(ref rotation) => rotation,
+            None => {
+                let err = Error::NotSigned;
+                return Err(err);
+            }
+        };
+
+        rotation.time.validate()?;
+
+        if let Some(ref announcement) = self.announcement {
+            if announcement.public_key != rotation.new_public_key {
+                let err = Error::InvalidPublicKey;
+                return Err(err);
+            }
+        }
+
+        let msg = self.rotation_message(
+            &rotation.old_public_key,
+            &rotation.new_public_key,
+            rotation.time,
+        )?;
+
+        rotation
+            .old_public_key
+            .verify(&rotation.old_signature, &msg)
+            .map_err(|_| Error::InvalidSignature)?;
+
+        rotation
+            .new_public_key
+            .verify(&rotation.new_signature, &msg)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    /// `is_rotated_from` returns if the `Node` carries a valid `KeyRotation` from
+    /// a known `NodeAnnouncement` to its current one.
+    pub fn is_rotated_from(&self, known: &NodeAnnouncement) -> bool {
+        match self.rotation {
+            Some(ref rotation) => {
+                rotation.old_public_key == known.public_key
+                    && rotation.time >= known.time
+                    && self.verify_key_rotation().is_ok()
+            }
+            None => false,
+        }
+    }
+
+    /// `subnet` returns the subnet prefix of the `Node` address. Addresses
+    /// that are not TCP addresses are returned whole.
+    pub fn subnet(&self) -> Vec<u8> {
+        match self.address.len() {
+            6 => self.address[..Self::IPV4_SUBNET_LEN].to_vec(),
+            18 => self.address[..Self::IPV6_SUBNET_LEN].to_vec(),
+            _ => self.address.clone(),
+        }
+    }
+
     /// `calc_id` calculates the `Node` id.
     pub fn calc_id(&self) -> Digest {
         Blake512Hasher::hash(&self.address)
@@ -64,6 +406,23 @@ impl Node {
             return Err(err);
         }
 
+        if self.addresses.len() > Self::MAX_ADDRESSES {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        for address in self.addresses.iter() {
+            address.validate()?;
+        }
+
+        if self.is_announced() {
+            self.verify_announcement_signature()?;
+        }
+
+        if self.rotation.is_some() {
+            self.verify_key_rotation()?;
+        }
+
         self.last_seen.validate()
     }
 
@@ -115,7 +474,10 @@ impl<S: Store> Storable<S> for Node {
     }
 
     fn validate_all(store: &S, stage: Stage) -> Result<()> {
-        for value in Self::query(store, stage, None, None, None, None)? {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
             Self::validate_single(store, stage, &value)?;
         }
 
@@ -252,7 +614,9 @@ impl<S: Store> Storable<S> for Node {
         let key = <Self as Storable<S>>::key(value);
         let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
         let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
     }
 
     fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
@@ -326,14 +690,19 @@ impl<S: Store> Storable<S> for Node {
         let to = Some(_to.to_vec());
         let to = to.as_ref().map(|to| to.as_slice());
 
-        for value in store.query(from, to, None, None)? {
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(from, to)? {
             let node = Node::from_bytes(&value)?;
             if node.last_seen < min_time {
-                let key = <Self as Storable<S>>::key_to_bytes(stage, &node.id)?;
-                store.remove(&key)?;
+                keys.push(key);
             }
         }
 
+        for key in keys {
+            store.remove(&key)?;
+        }
+
         Ok(())
     }
 
@@ -383,6 +752,247 @@ fn test_node_validate() {
     assert!(res.is_err());
 }
 
+#[test]
+fn test_node_addresses() {
+    let address_len = 10;
+    let address = Random::bytes(address_len).unwrap();
+    let stage = Stage::random().unwrap();
+
+    let mut node = Node::new(stage, &address);
+
+    let ipv4_address = NetAddress::parse("127.0.0.1:2019").unwrap();
+    let ipv6_address = NetAddress::parse("[::1]:2019").unwrap();
+
+    assert!(!node.lookup_address(&ipv4_address));
+
+    let res = node.add_address(&ipv4_address);
+    assert!(res.is_ok());
+    assert!(node.lookup_address(&ipv4_address));
+
+    let res = node.add_address(&ipv4_address);
+    assert!(res.is_err());
+
+    let res = node.add_address(&ipv6_address);
+    assert!(res.is_ok());
+    assert_eq!(node.addresses.len(), 2);
+
+    let res = node.validate();
+    assert!(res.is_ok());
+
+    let cbor = node.to_bytes().unwrap();
+    let res = Node::from_bytes(&cbor);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), node);
+
+    let res = node.remove_address(&ipv4_address);
+    assert!(res.is_ok());
+    assert!(!node.lookup_address(&ipv4_address));
+
+    let res = node.remove_address(&ipv4_address);
+    assert!(res.is_err());
+
+    node.addresses.insert(NetAddress::Ipv4 {
+        ip: [127, 0, 0, 1],
+        port: 0,
+    });
+
+    let res = node.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_node_services() {
+    let address_len = 10;
+    let address = Random::bytes(address_len).unwrap();
+    let stage = Stage::random().unwrap();
+
+    let mut node = Node::new(stage, &address);
+    assert!(!node.has_service(Service::Mining));
+
+    let mut services = BTreeSet::new();
+    services.insert(Service::Mining);
+    services.insert(Service::Relay);
+
+    node.set_services(&services);
+    assert!(node.has_service(Service::Mining));
+    assert!(node.has_service(Service::Relay));
+    assert!(!node.has_service(Service::Archival));
+    assert!(node.has_services(&BTreeSet::new()));
+
+    let mut required = BTreeSet::new();
+    required.insert(Service::Relay);
+    assert!(node.has_services(&required));
+
+    required.insert(Service::Rpc);
+    assert!(!node.has_services(&required));
+
+    let all = [
+        Service::Mining,
+        Service::Archival,
+        Service::Relay,
+        Service::Rpc,
+        Service::CompactQuery,
+    ];
+
+    for service in all.iter() {
+        assert_eq!(Service::parse(&service.to_string()).unwrap(), *service);
+    }
+
+    assert!(Service::parse("staking").is_err());
+
+    let cbor = node.to_bytes().unwrap();
+    let res = Node::from_bytes(&cbor);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), node);
+}
+
+#[test]
+fn test_node_latency() {
+    let address_len = 6;
+    let address = Random::bytes(address_len).unwrap();
+    let stage = Stage::random().unwrap();
+
+    let mut node = Node::new(stage, &address);
+    assert_eq!(node.latency, None);
+
+    node.record_latency(100);
+    assert_eq!(node.latency, Some(100));
+
+    node.record_latency(180);
+    assert_eq!(node.latency, Some(110));
+
+    node.record_latency(30);
+    assert_eq!(node.latency, Some(100));
+
+    node.record_work(4);
+    node.record_work(2);
+    assert_eq!(node.work, 20);
+
+    node.record_work(100);
+    node.record_work(100);
+    assert_eq!(node.work, u64::max_value());
+
+    let cbor = node.to_bytes().unwrap();
+    let res = Node::from_bytes(&cbor);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), node);
+}
+
+#[test]
+fn test_node_announcement() {
+    let address_len = 6;
+    let address = Random::bytes(address_len).unwrap();
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+
+    let mut node = Node::new(stage, &address);
+    assert!(!node.is_announced());
+
+    let res = node.verify_announcement();
+    assert!(res.is_err());
+
+    let res = node.announce(&secret_key);
+    assert!(res.is_ok());
+    assert!(node.is_announced());
+
+    let res = node.verify_announcement();
+    assert!(res.is_ok());
+
+    let res = node.validate();
+    assert!(res.is_ok());
+
+    node.last_seen = Timestamp::now();
+    let res = node.verify_announcement();
+    assert!(res.is_ok());
+
+    let mut forged_node = node.clone();
+    forged_node
+        .add_address(&NetAddress::parse("127.0.0.1:2019").unwrap())
+        .unwrap();
+
+    let res = forged_node.verify_announcement();
+    assert!(res.is_err());
+
+    let res = forged_node.validate();
+    assert!(res.is_err());
+
+    let mut old_node = node.clone();
+    let old_time =
+        Timestamp::from_i64(Timestamp::now().to_i64() - Node::MAX_ANNOUNCEMENT_AGE - 1).unwrap();
+    let msg = old_node.announcement_message(old_time).unwrap();
+    old_node.announcement = Some(NodeAnnouncement {
+        public_key: secret_key.to_public(),
+        time: old_time,
+        signature: secret_key.sign(&msg),
+    });
+
+    let res = old_node.verify_announcement_signature();
+    assert!(res.is_ok());
+
+    let res = old_node.verify_announcement();
+    assert!(res.is_err());
+
+    assert_eq!(node.subnet(), address[..Node::IPV4_SUBNET_LEN].to_vec());
+}
+
+#[test]
+fn test_node_key_rotation() {
+    let address_len = 6;
+    let address = Random::bytes(address_len).unwrap();
+    let stage = Stage::random().unwrap();
+    let old_secret_key = SecretKey::random().unwrap();
+    let new_secret_key = SecretKey::random().unwrap();
+
+    let mut node = Node::new(stage, &address);
+    node.announce(&old_secret_key).unwrap();
+
+    let known = node.announcement.clone().unwrap();
+
+    let res = node.verify_key_rotation();
+    assert!(res.is_err());
+    assert!(!node.is_rotated_from(&known));
+
+    let res = node.rotate_key(&old_secret_key, &old_secret_key);
+    assert!(res.is_err());
+
+    let res = node.rotate_key(&old_secret_key, &new_secret_key);
+    assert!(res.is_ok());
+    assert_eq!(
+        node.announcement.as_ref().unwrap().public_key,
+        new_secret_key.to_public()
+    );
+
+    let res = node.verify_key_rotation();
+    assert!(res.is_ok());
+    assert!(node.is_rotated_from(&known));
+
+    let res = node.validate();
+    assert!(res.is_ok());
+
+    let node = Node::from_bytes(&node.to_bytes().unwrap()).unwrap();
+    assert!(node.is_rotated_from(&known));
+
+    let mut other_node = Node::new(stage, &address);
+    other_node.announce(&SecretKey::random().unwrap()).unwrap();
+    assert!(!node.is_rotated_from(other_node.announcement.as_ref().unwrap()));
+
+    let mut forged_node = node.clone();
+    forged_node.announce(&SecretKey::random().unwrap()).unwrap();
+
+    let res = forged_node.verify_key_rotation();
+    assert!(res.is_err());
+
+    let res = forged_node.validate();
+    assert!(res.is_err());
+
+    let mut forged_node = node;
+    forged_node.rotation.as_mut().unwrap().old_public_key =
+        other_node.announcement.unwrap().public_key;
+
+    let res = forged_node.verify_key_rotation();
+    assert!(res.is_err());
+}
+
 #[test]
 fn test_node_serialize_bytes() {
     let address_len = 100;
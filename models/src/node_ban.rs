@@ -0,0 +1,477 @@
+//! # Node Ban
+//!
+//! `node_ban` contains the type used to record the ban of a node, whose
+//! messages are refused and which is never sampled until the ban expires.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crypto::hash::{Blake512Hasher, Digest};
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `NodeBan` is the ban of a node, identified by the id of its address, until a time.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct NodeBan {
+    pub id: Digest,
+    pub address: Vec<u8>,
+    pub stage: Stage,
+    pub reason: String,
+    pub banned_at: Timestamp,
+    pub until: Timestamp,
+}
+
+impl NodeBan {
+    /// `MAX_DURATION` is the maximum duration in seconds of a `NodeBan`.
+    pub const MAX_DURATION: u64 = 365 * 24 * 3600;
+
+    /// `MAX_REASON_LEN` is the maximum length of the reason of a `NodeBan`.
+    pub const MAX_REASON_LEN: usize = 256;
+
+    /// `new` creates a new `NodeBan` of a node address lasting a duration in seconds.
+    pub fn new(stage: Stage, address: &[u8], duration: u64, reason: &str) -> Result<NodeBan> {
+        if duration == 0 || duration > Self::MAX_DURATION {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        let banned_at = Timestamp::now();
+        let until = banned_at.after(duration);
+
+        let ban = NodeBan {
+            id: Blake512Hasher::hash(address),
+            address: address.to_owned(),
+            stage,
+            reason: reason.to_owned(),
+            banned_at,
+            until,
+        };
+
+        ban.validate()?;
+
+        Ok(ban)
+    }
+
+    /// `is_active` returns if the `NodeBan` is active at a given time.
+    pub fn is_active(&self, time: Timestamp) -> bool {
+        self.until > time
+    }
+
+    /// `validate` validates the `NodeBan`.
+    pub fn validate(&self) -> Result<()> {
+        if self.id != Blake512Hasher::hash(&self.address) {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        if self.reason.len() > Self::MAX_REASON_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if self.until < self.banned_at {
+            let err = Error::InvalidTimestamp;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `NodeBan` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `NodeBan`.
+    pub fn from_bytes(b: &[u8]) -> Result<NodeBan> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `NodeBan` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `NodeBan`.
+    pub fn from_json(s: &str) -> Result<NodeBan> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for NodeBan {
+    const KEY_PREFIX: u8 = 13;
+
+    type Key = Digest;
+
+    fn key(&self) -> Self::Key {
+        self.id
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_else(Timestamp::now);
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let ban = NodeBan::from_bytes(&value)?;
+            if ban.until <= min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_node_ban_ops() {
+    let stage = Stage::random().unwrap();
+    let address = b"127.0.0.1:2019";
+
+    let res = NodeBan::new(stage, address, 0, "");
+    assert!(res.is_err());
+
+    let res = NodeBan::new(stage, address, NodeBan::MAX_DURATION + 1, "");
+    assert!(res.is_err());
+
+    let res = NodeBan::new(stage, address, 3600, "invalid messages");
+    assert!(res.is_ok());
+    let mut ban = res.unwrap();
+
+    assert_eq!(ban.id, Blake512Hasher::hash(address));
+    assert!(ban.is_active(Timestamp::now()));
+
+    let later = ban.until.after(1);
+    assert!(!ban.is_active(later));
+
+    ban.address = b"127.0.0.1:2020".to_vec();
+    let res = ban.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_node_ban_serialize_bytes() {
+    let ban_a = NodeBan::new(Stage::default(), b"address", 60, "reason").unwrap();
+
+    let res = ban_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = NodeBan::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let ban_b = res.unwrap();
+
+    assert_eq!(ban_a, ban_b)
+}
+
+#[test]
+fn test_node_ban_serialize_json() {
+    let ban_a = NodeBan::new(Stage::default(), b"address", 60, "reason").unwrap();
+
+    let res = ban_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = NodeBan::from_json(&json);
+    assert!(res.is_ok());
+    let ban_b = res.unwrap();
+
+    assert_eq!(ban_a, ban_b)
+}
+
+#[test]
+fn test_node_ban_storable() {
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let ban = NodeBan::new(stage, b"address", 60, "reason").unwrap();
+    let id = ban.id;
+
+    let res = NodeBan::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = NodeBan::get(&store, stage, &id);
+    assert!(res.is_err());
+
+    let res = NodeBan::insert(&mut store, stage, &ban);
+    assert!(res.is_ok());
+
+    let res = NodeBan::count(&store, stage, None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let res = NodeBan::get(&store, stage, &id);
+    assert!(res.is_ok());
+    assert_eq!(&res.unwrap(), &ban);
+
+    let res = <NodeBan as Storable<BTreeStore>>::cleanup(&mut store, stage, None);
+    assert!(res.is_ok());
+
+    let res = NodeBan::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let min_time = ban.until.after(1);
+    let res = <NodeBan as Storable<BTreeStore>>::cleanup(&mut store, stage, Some(min_time));
+    assert!(res.is_ok());
+
+    let res = NodeBan::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = NodeBan::insert(&mut store, stage, &ban);
+    assert!(res.is_ok());
+
+    let res = <NodeBan as Storable<BTreeStore>>::remove(&mut store, stage, &id);
+    assert!(res.is_ok());
+
+    let res = NodeBan::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
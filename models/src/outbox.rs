@@ -0,0 +1,474 @@
+//! # Outbox
+//!
+//! `outbox` contains the type used to queue the outbound messages that failed
+//! to be sent, so that they are sent again instead of being lost on a
+//! transient network error.
+
+use crate::consensus_message::ConsensusMessage;
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `Outbox` is an outbound `ConsensusMessage` that failed to be sent, with the
+/// metadata of its send attempts.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Outbox {
+    pub id: Digest,
+    pub stage: Stage,
+    pub message: ConsensusMessage,
+    pub attempts: u32,
+    pub queued_at: Timestamp,
+    pub next_attempt: Timestamp,
+    pub last_error: String,
+}
+
+impl Outbox {
+    /// `MAX_ERROR_LEN` is the maximum length of the last error of an `Outbox`.
+    pub const MAX_ERROR_LEN: usize = 256;
+
+    /// `new` creates a new `Outbox` of a `ConsensusMessage` whose first send
+    /// failed with an error, to be sent again after a delay in seconds.
+    pub fn new(message: &ConsensusMessage, error: &str, delay: u64) -> Result<Outbox> {
+        message.validate()?;
+
+        let queued_at = Timestamp::now();
+
+        let mut outbox = Outbox {
+            id: message.digest()?,
+            stage: message.node().stage,
+            message: message.to_owned(),
+            attempts: 0,
+            queued_at,
+            next_attempt: queued_at,
+            last_error: String::new(),
+        };
+
+        outbox.record_failure(error, delay)?;
+
+        Ok(outbox)
+    }
+
+    /// `record_failure` records a failed send attempt of the `Outbox`, scheduling
+    /// the next attempt after a delay in seconds.
+    pub fn record_failure(&mut self, error: &str, delay: u64) -> Result<()> {
+        let mut error = error.to_owned();
+        while error.len() > Self::MAX_ERROR_LEN {
+            error.pop();
+        }
+
+        self.attempts += 1;
+        self.last_error = error;
+        self.next_attempt = Timestamp::now().after(delay);
+
+        Ok(())
+    }
+
+    /// `is_due` returns if the next send attempt of the `Outbox` is due at a given time.
+    pub fn is_due(&self, time: Timestamp) -> bool {
+        self.next_attempt <= time
+    }
+
+    /// `validate` validates the `Outbox`.
+    pub fn validate(&self) -> Result<()> {
+        self.message.validate()?;
+
+        if self.id != self.message.digest()? {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        if self.stage != self.message.node().stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        if self.attempts == 0 || self.last_error.len() > Self::MAX_ERROR_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if self.next_attempt < self.queued_at {
+            let err = Error::InvalidTimestamp;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Outbox` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Outbox`.
+    pub fn from_bytes(b: &[u8]) -> Result<Outbox> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Outbox` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `Outbox`.
+    pub fn from_json(s: &str) -> Result<Outbox> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for Outbox {
+    const KEY_PREFIX: u8 = 15;
+
+    type Key = Digest;
+
+    fn key(&self) -> Self::Key {
+        self.id
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let outbox = Outbox::from_bytes(&value)?;
+            if outbox.queued_at < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_outbox_ops() {
+    use crate::node::Node;
+    use crypto::random::Random;
+
+    let address = Random::bytes(100).unwrap();
+    let node = Node::random(100).unwrap();
+    let message = ConsensusMessage::new_fetch_random_nodes(&address, &node, 10).unwrap();
+
+    let res = Outbox::new(&message, "Connection refused", 0);
+    assert!(res.is_ok());
+
+    let mut outbox = res.unwrap();
+    assert_eq!(outbox.attempts, 1);
+    assert_eq!(outbox.stage, node.stage);
+    assert!(outbox.is_due(Timestamp::now()));
+
+    let res = outbox.validate();
+    assert!(res.is_ok());
+
+    let error = "e".repeat(Outbox::MAX_ERROR_LEN + 1);
+
+    let res = outbox.record_failure(&error, 3600);
+    assert!(res.is_ok());
+    assert_eq!(outbox.attempts, 2);
+    assert_eq!(outbox.last_error.len(), Outbox::MAX_ERROR_LEN);
+    assert!(!outbox.is_due(Timestamp::now()));
+
+    let res = outbox.validate();
+    assert!(res.is_ok());
+
+    outbox.id = Digest::random().unwrap();
+
+    let res = outbox.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_outbox_storable() {
+    use crate::node::Node;
+    use crypto::random::Random;
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 16;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let address = Random::bytes(100).unwrap();
+    let node = Node::random(100).unwrap();
+    let stage = node.stage;
+    let message = ConsensusMessage::new_fetch_random_nodes(&address, &node, 10).unwrap();
+    let mut outbox = Outbox::new(&message, "Connection refused", 0).unwrap();
+
+    let res = Outbox::lookup(&store, stage, &outbox.id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = Outbox::insert(&mut store, stage, &outbox);
+    assert!(res.is_ok());
+
+    let res = Outbox::count(&store, stage, None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    outbox.record_failure("Connection refused", 10).unwrap();
+
+    let res = Outbox::update(&mut store, stage, &outbox);
+    assert!(res.is_ok());
+
+    let res = Outbox::get(&store, stage, &outbox.id);
+    assert!(res.is_ok());
+    assert_eq!(&res.unwrap(), &outbox);
+
+    let res = <Outbox as Storable<BTreeStore>>::cleanup(&mut store, stage, None);
+    assert!(res.is_ok());
+
+    let res = Outbox::lookup(&store, stage, &outbox.id);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let res = Outbox::remove(&mut store, stage, &outbox.id);
+    assert!(res.is_ok());
+
+    let res = Outbox::lookup(&store, stage, &outbox.id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
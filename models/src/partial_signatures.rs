@@ -0,0 +1,204 @@
+//! # Partial Signatures
+//!
+//! `partial_signatures` contains the `PartialSignatures` type and functions.
+
+use crate::address::Address;
+use crate::cbor::{self, CborLimits};
+use crate::error::Error;
+use crate::result::Result;
+use crate::transaction::Transaction;
+use crypto::ecc::ed25519::{PublicKey, Signature};
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeMap;
+
+/// `PartialSignatures` are the `Input` signatures of a `Transaction` exchanged
+/// between the signers of multisig accounts, until the signatures weights reach
+/// the accounts thresholds. The digest binds them to the signed message of the
+/// `Transaction`, so that they cannot be imported in a different one.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct PartialSignatures {
+    pub digest: Digest,
+    pub signatures: BTreeMap<Address, BTreeMap<PublicKey, Signature>>,
+}
+
+impl PartialSignatures {
+    /// `new` creates a new `PartialSignatures` from the signatures of a `Transaction`.
+    pub fn new(transaction: &Transaction) -> Result<PartialSignatures> {
+        let digest = transaction.input_sign_digest()?;
+
+        let signatures = transaction
+            .inputs
+            .iter()
+            .filter(|(_, input)| !input.signatures.is_empty())
+            .map(|(address, input)| (*address, input.signatures.clone()))
+            .collect();
+
+        let psigs = PartialSignatures { digest, signatures };
+
+        Ok(psigs)
+    }
+
+    /// `len` returns the number of signatures in the `PartialSignatures`.
+    pub fn len(&self) -> usize {
+        self.signatures.values().map(|sigs| sigs.len()).sum()
+    }
+
+    /// `is_empty` returns if the `PartialSignatures` contain no signatures.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `merge` merges an other `PartialSignatures` of the same `Transaction`.
+    pub fn merge(&mut self, other: &PartialSignatures) -> Result<()> {
+        if self.digest != other.digest {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        for (address, sigs) in &other.signatures {
+            let entry = self.signatures.entry(*address).or_default();
+
+            for (public_key, signature) in sigs {
+                if let Some(found) = entry.get(public_key) {
+                    if found != signature {
+                        let err = Error::InvalidSignature;
+                        return Err(err);
+                    }
+                }
+
+                entry.insert(*public_key, *signature);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate` validates the `PartialSignatures` against a `Transaction`.
+    pub fn validate(&self, transaction: &Transaction) -> Result<()> {
+        if self.digest != transaction.input_sign_digest()? {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        let msg = transaction.input_sign_message()?;
+
+        for (address, sigs) in &self.signatures {
+            let input = transaction.get_input(address)?;
+
+            for (public_key, signature) in sigs {
+                input.validate_signature(public_key, signature, &msg)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `PartialSignatures` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `PartialSignatures`.
+    /// The binary is checked against the default `CborLimits` before decoding.
+    pub fn from_bytes(b: &[u8]) -> Result<PartialSignatures> {
+        cbor::from_slice(b, &CborLimits::default())
+    }
+
+    /// `to_json` converts the `PartialSignatures` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `PartialSignatures`.
+    pub fn from_json(s: &str) -> Result<PartialSignatures> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_partial_signatures() {
+    use crate::account::Account;
+    use crate::input::Input;
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crate::stage::Stage;
+    use crypto::ecc::ed25519::SecretKey;
+    use crypto::random::Random;
+
+    let stage = Stage::random().unwrap();
+    let secret_key_a = SecretKey::random().unwrap();
+    let secret_key_b = SecretKey::random().unwrap();
+    let weight = 1;
+
+    let mut signers = Signers::new().unwrap();
+    signers.threshold = 2;
+
+    for secret_key in &[&secret_key_a, &secret_key_b] {
+        let public_key = secret_key.to_public();
+        signers.add(&Signer { public_key, weight }).unwrap();
+    }
+
+    let amount = Random::u64_range(1, 1000).unwrap();
+    let tx_id = Digest::random().unwrap();
+    let account = Account::new(stage, &signers, amount, Some(tx_id)).unwrap();
+    let address = account.address();
+    let input = Input::new(&account, 1, amount).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.add_input(&input).unwrap();
+
+    let mut transaction_a = transaction.clone();
+    transaction_a.sign_input(&secret_key_a, &address).unwrap();
+
+    let mut transaction_b = transaction.clone();
+    transaction_b.sign_input(&secret_key_b, &address).unwrap();
+
+    let psigs_a = PartialSignatures::new(&transaction_a).unwrap();
+    assert_eq!(psigs_a.len(), 1);
+
+    let res = psigs_a.validate(&transaction);
+    assert!(res.is_ok());
+
+    let buf = transaction_b.export_signatures().unwrap();
+    let psigs_b = PartialSignatures::from_bytes(&buf).unwrap();
+    assert_eq!(psigs_b.len(), 1);
+
+    let mut merged = psigs_a.clone();
+    let res = merged.merge(&psigs_b);
+    assert!(res.is_ok());
+    assert_eq!(merged.len(), 2);
+
+    let res = transaction.import_signatures(&psigs_a.to_bytes().unwrap());
+    assert!(res.is_ok());
+    assert!(!transaction.is_fully_signed().unwrap());
+
+    let status = transaction.signatures_status().unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].missing_weight(), 1);
+
+    let res = transaction.import_signatures(&buf);
+    assert!(res.is_ok());
+    assert!(transaction.is_fully_signed().unwrap());
+
+    let res = transaction.validate_fully_signed_inputs();
+    assert!(res.is_ok());
+
+    let mut other = Transaction::new().unwrap();
+    other.stage = stage;
+    other
+        .add_input(&Input::new(&account, 2, amount).unwrap())
+        .unwrap();
+
+    let res = psigs_a.validate(&other);
+    assert!(res.is_err());
+
+    let mut invalid = psigs_a;
+    invalid.digest = Digest::random().unwrap();
+
+    let res = merged.merge(&invalid);
+    assert!(res.is_err());
+}
@@ -0,0 +1,200 @@
+//! # Signing Request
+//!
+//! `signing_request` contains the `SigningRequest` type and functions. A `SigningRequest`
+//! carries an unsigned `Transaction` to an offline machine holding the secret keys,
+//! where its inputs are signed and the signatures are exported back as `PartialSignatures`.
+
+use crate::cbor::{self, CborLimits};
+use crate::error::Error;
+use crate::partial_signatures::PartialSignatures;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::transaction::Transaction;
+use crypto::ecc::ed25519::SecretKey;
+use crypto::hash::{Blake512Hasher, Digest};
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeMap;
+
+/// `SigningRequest` is a `Transaction` to be signed offline, together with the
+/// hex of its input sign message and the digest of the message, so that the
+/// offline signer can check that they sign exactly the `Transaction` they review.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SigningRequest {
+    pub stage: Stage,
+    pub transaction: Transaction,
+    pub message: String,
+    pub digest: Digest,
+}
+
+impl SigningRequest {
+    /// `new` creates a new `SigningRequest` from a `Transaction`.
+    pub fn new(transaction: &Transaction) -> Result<SigningRequest> {
+        let msg = transaction.input_sign_message()?;
+
+        let request = SigningRequest {
+            stage: transaction.stage,
+            transaction: transaction.to_owned(),
+            message: base16::encode_lower(&msg),
+            digest: Blake512Hasher::hash(&msg),
+        };
+
+        Ok(request)
+    }
+
+    /// `sign_message` returns the binary input sign message of the `SigningRequest`.
+    pub fn sign_message(&self) -> Result<Vec<u8>> {
+        base16::decode(&self.message).map_err(|_| Error::InvalidEncoding)
+    }
+
+    /// `validate` validates the `SigningRequest`, checking that the message and
+    /// its digest are the ones of the `Transaction`.
+    pub fn validate(&self) -> Result<()> {
+        if self.transaction.stage != self.stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        let msg = self.sign_message()?;
+
+        if Blake512Hasher::hash(&msg) != self.digest {
+            let err = Error::InvalidChecksum;
+            return Err(err);
+        }
+
+        if msg != self.transaction.input_sign_message()? {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `sign` signs the `Input`s of the `SigningRequest` `Transaction` that can be
+    /// signed by a `SecretKey`, returning their signatures as `PartialSignatures`.
+    pub fn sign(&self, secret_key: &SecretKey) -> Result<PartialSignatures> {
+        self.validate()?;
+
+        let public_key = secret_key.to_public();
+        let mut transaction = self.transaction.clone();
+        let mut signatures = BTreeMap::new();
+
+        for (address, input) in self.transaction.inputs.iter() {
+            if !input.account.signers.lookup(&public_key) {
+                continue;
+            }
+
+            transaction.sign_input(secret_key, address)?;
+
+            let input = transaction.get_input(address)?;
+            let signature = input
+                .signatures
+                .get(&public_key)
+                .copied()
+                .ok_or(Error::NotSigned)?;
+
+            let mut sigs = BTreeMap::new();
+            sigs.insert(public_key, signature);
+
+            signatures.insert(*address, sigs);
+        }
+
+        if signatures.is_empty() {
+            let err = Error::InvalidPublicKey;
+            return Err(err);
+        }
+
+        let psigs = PartialSignatures {
+            digest: self.digest,
+            signatures,
+        };
+
+        Ok(psigs)
+    }
+
+    /// `to_bytes` converts the `SigningRequest` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `SigningRequest`.
+    /// The binary is checked against the default `CborLimits` before decoding.
+    pub fn from_bytes(b: &[u8]) -> Result<SigningRequest> {
+        cbor::from_slice(b, &CborLimits::default())
+    }
+
+    /// `to_json` converts the `SigningRequest` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `SigningRequest`.
+    pub fn from_json(s: &str) -> Result<SigningRequest> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_signing_request() {
+    use crate::account::Account;
+    use crate::input::Input;
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crypto::random::Random;
+
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+    let public_key = secret_key.to_public();
+
+    let mut signers = Signers::new().unwrap();
+    signers.threshold = 1;
+    signers
+        .add(&Signer {
+            public_key,
+            weight: 1,
+        })
+        .unwrap();
+
+    let amount = Random::u64_range(1, 1000).unwrap();
+    let tx_id = Digest::random().unwrap();
+    let account = Account::new(stage, &signers, amount, Some(tx_id)).unwrap();
+    let address = account.address();
+    let input = Input::new(&account, 1, amount).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.add_input(&input).unwrap();
+
+    let request = SigningRequest::new(&transaction).unwrap();
+    assert!(request.validate().is_ok());
+
+    let request = SigningRequest::from_json(&request.to_json().unwrap()).unwrap();
+    let request = SigningRequest::from_bytes(&request.to_bytes().unwrap()).unwrap();
+    assert!(request.validate().is_ok());
+
+    let res = request.sign(&SecretKey::random().unwrap());
+    assert!(res.is_err());
+
+    let psigs = request.sign(&secret_key).unwrap();
+    assert_eq!(psigs.len(), 1);
+
+    let res = transaction.import_signatures(&psigs.to_bytes().unwrap());
+    assert!(res.is_ok());
+    assert!(transaction.is_fully_signed().unwrap());
+
+    let res = transaction.verify_input_signature(&public_key, &address);
+    assert!(res.is_ok());
+
+    let mut invalid = request.clone();
+    invalid.transaction.locktime = Some(invalid.transaction.time);
+
+    let res = invalid.validate();
+    assert!(res.is_err());
+
+    let mut invalid = request;
+    invalid.message = "zz".into();
+
+    let res = invalid.validate();
+    assert!(res.is_err());
+}
@@ -4,6 +4,7 @@
 
 use crate::error::Error;
 use crate::result::Result;
+use config::profile::Profile;
 use crypto::random::Random;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -65,6 +66,26 @@ impl fmt::Display for Stage {
     }
 }
 
+impl From<Profile> for Stage {
+    fn from(profile: Profile) -> Stage {
+        match profile {
+            Profile::Development => Stage::Development,
+            Profile::Testing => Stage::Testing,
+            Profile::Production => Stage::Production,
+        }
+    }
+}
+
+impl From<Stage> for Profile {
+    fn from(stage: Stage) -> Profile {
+        match stage {
+            Stage::Development => Profile::Development,
+            Stage::Testing => Profile::Testing,
+            Stage::Production => Profile::Production,
+        }
+    }
+}
+
 impl Default for Stage {
     fn default() -> Stage {
         Stage::Development
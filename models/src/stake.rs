@@ -0,0 +1,551 @@
+//! # Stake
+//!
+//! `stake` contains the `Stake` type and functions. A stake is the balance of an
+//! account locked until a locktime, the primitive of the staked or bonded accounts:
+//! the spends of the account cannot lower its balance below the locked amount.
+//! The `Stake`s are carried on-chain by the `Transaction`s spending from the staked
+//! accounts, and are kept in the `Store` by address when the `Transaction`s are stored.
+
+use crate::address::Address;
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `Stake` is an amount of the balance of an `Address` of a `Stage` locked
+/// until a locktime.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct Stake {
+    pub address: Address,
+    pub stage: Stage,
+    pub amount: u64,
+    pub time: Timestamp,
+    pub locktime: Timestamp,
+}
+
+impl Stake {
+    /// `MAX_DURATION` is the maximum duration in seconds of a `Stake`.
+    pub const MAX_DURATION: u64 = 4 * 365 * 24 * 3600;
+
+    /// `new` creates a new `Stake` of an amount of the balance of an `Address`,
+    /// lasting a duration in seconds.
+    pub fn new(stage: Stage, address: &Address, amount: u64, duration: u64) -> Result<Stake> {
+        Stake::with_locktime(stage, address, amount, Timestamp::now().after(duration))
+    }
+
+    /// `with_locktime` creates a new `Stake` of an amount of the balance of an
+    /// `Address`, lasting until a locktime.
+    pub fn with_locktime(
+        stage: Stage,
+        address: &Address,
+        amount: u64,
+        locktime: Timestamp,
+    ) -> Result<Stake> {
+        let time = Timestamp::now();
+        let duration = locktime.diff(time);
+
+        if duration <= 0 || duration as u64 > Self::MAX_DURATION {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        let stake = Stake {
+            address: *address,
+            stage,
+            amount,
+            time,
+            locktime,
+        };
+
+        stake.validate()?;
+
+        Ok(stake)
+    }
+
+    /// `is_active` returns if the `Stake` is active at a given time.
+    pub fn is_active(&self, time: Timestamp) -> bool {
+        time < self.locktime
+    }
+
+    /// `locked_amount` returns the amount locked by the `Stake` at a given time.
+    pub fn locked_amount(&self, time: Timestamp) -> u64 {
+        if self.is_active(time) {
+            self.amount
+        } else {
+            0
+        }
+    }
+
+    /// `validate_balance` validates that the balance of the `Address` left by a
+    /// spend at a given time does not go below the locked amount.
+    pub fn validate_balance(&self, balance: u64, time: Timestamp) -> Result<()> {
+        if balance < self.locked_amount(time) {
+            let err = Error::LockedBalance;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_spend` validates the balance left by a spend of an `Address`
+    /// against its `Stake` in a `Store`, if any.
+    pub fn validate_spend<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        balance: u64,
+        time: Timestamp,
+    ) -> Result<()> {
+        if Stake::lookup(store, stage, address)? {
+            Stake::get(store, stage, address)?.validate_balance(balance, time)?;
+        }
+
+        Ok(())
+    }
+
+    /// `validate_replacement` validates the `Stake` as the replacement of the
+    /// current `Stake` of its `Address`: an active `Stake` can only be replaced
+    /// by a larger and longer one.
+    pub fn validate_replacement(&self, current: &Stake) -> Result<()> {
+        if current.is_active(self.time)
+            && (self.amount < current.amount || self.locktime < current.locktime)
+        {
+            let err = Error::LockedBalance;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate` validates the `Stake`.
+    pub fn validate(&self) -> Result<()> {
+        self.time.validate()?;
+
+        if self.amount == 0 {
+            let err = Error::InvalidAmount;
+            return Err(err);
+        }
+
+        // NB: the locktime is in the future, so it is not validated as a timestamp
+        if self.locktime < self.time {
+            let err = Error::InvalidLocktime;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Stake` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `Stake`.
+    pub fn from_bytes(b: &[u8]) -> Result<Stake> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Stake` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `Stake`.
+    pub fn from_json(s: &str) -> Result<Stake> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for Stake {
+    const KEY_PREFIX: u8 = 21;
+
+    type Key = Address;
+
+    fn key(&self) -> Self::Key {
+        self.address
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let stake = Stake::from_bytes(&value)?;
+            if stake.locktime < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_stake_ops() {
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+
+    let res = Stake::new(stage, &address, 100, 0);
+    assert!(res.is_err());
+
+    let res = Stake::new(stage, &address, 100, Stake::MAX_DURATION + 1);
+    assert!(res.is_err());
+
+    let res = Stake::new(stage, &address, 0, 1_000);
+    assert!(res.is_err());
+
+    let res = Stake::with_locktime(stage, &address, 100, Timestamp::now());
+    assert!(res.is_err());
+
+    let locktime = Timestamp::now().after(1_000);
+    let res = Stake::with_locktime(stage, &address, 100, locktime);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().locktime, locktime);
+
+    let stake = Stake::new(stage, &address, 100, 1_000).unwrap();
+    let now = stake.time;
+
+    assert!(stake.is_active(now));
+    assert!(!stake.is_active(stake.locktime));
+    assert_eq!(stake.locked_amount(now), 100);
+    assert_eq!(stake.locked_amount(stake.locktime), 0);
+
+    let res = stake.validate_balance(100, now);
+    assert!(res.is_ok());
+
+    let res = stake.validate_balance(99, now);
+    assert!(res.is_err());
+
+    let res = stake.validate_balance(0, stake.locktime);
+    assert!(res.is_ok());
+
+    let mut replacement = stake.clone();
+    let res = replacement.validate_replacement(&stake);
+    assert!(res.is_ok());
+
+    replacement.amount -= 1;
+    let res = replacement.validate_replacement(&stake);
+    assert!(res.is_err());
+
+    replacement.amount += 2;
+    replacement.locktime = stake.locktime.after(1);
+    let res = replacement.validate_replacement(&stake);
+    assert!(res.is_ok());
+
+    replacement.locktime = Timestamp::now();
+    let res = replacement.validate_replacement(&stake);
+    assert!(res.is_err());
+
+    replacement.time = stake.locktime;
+    let res = replacement.validate_replacement(&stake);
+    assert!(res.is_ok());
+
+    let mut invalid = stake;
+    invalid.locktime = Timestamp::min_value();
+    let res = invalid.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_stake_serialize_bytes() {
+    let stake_a = Stake::new(Stage::default(), &Address::default(), 1, 1).unwrap();
+
+    let res = stake_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = Stake::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let stake_b = res.unwrap();
+
+    assert_eq!(stake_a, stake_b)
+}
+
+#[test]
+fn test_stake_storable() {
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let stake = Stake::new(stage, &address, 100, 1_000).unwrap();
+    let now = stake.time;
+
+    let res = Stake::validate_spend(&store, stage, &address, 0, now);
+    assert!(res.is_ok());
+
+    let res = Stake::create(&mut store, stage, &stake);
+    assert!(res.is_ok());
+
+    let res = Stake::get(&store, stage, &address);
+    assert!(res.is_ok());
+    assert_eq!(&res.unwrap(), &stake);
+
+    let res = Stake::validate_spend(&store, stage, &address, 50, now);
+    assert!(res.is_err());
+
+    let res = Stake::validate_spend(&store, stage, &address, 150, now);
+    assert!(res.is_ok());
+
+    let res = <Stake as Storable<BTreeStore>>::cleanup(&mut store, stage, Some(now));
+    assert!(res.is_ok());
+
+    let res = Stake::lookup(&store, stage, &address);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let res =
+        <Stake as Storable<BTreeStore>>::cleanup(&mut store, stage, Some(stake.locktime.after(1)));
+    assert!(res.is_ok());
+
+    let res = Stake::lookup(&store, stage, &address);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
@@ -0,0 +1,425 @@
+//! # Test Vectors
+//!
+//! `testvectors` contains the deterministic test vectors of the serialized models.
+//!
+//! The vectors are built from fixed keys, times and amounts, so that a release
+//! always generates the same CBOR and JSON encodings. A golden file generated by
+//! a release is verified by the later ones: a vector encoded differently is a
+//! serialization change that would break the wire protocol or the stores.
+
+use crate::account::Account;
+use crate::consensus_message::ConsensusMessage;
+use crate::error::Error;
+use crate::input::Input;
+use crate::net_address::NetAddress;
+use crate::node::{Node, NodeAnnouncement, Service};
+use crate::output::Output;
+use crate::result::Result;
+use crate::signer::Signer;
+use crate::signers::Signers;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::transaction::Transaction;
+use crate::version::Version;
+use crypto::ecc::ed25519::SecretKey;
+use crypto::hash::{Blake512Hasher, Digest};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `TEST_VECTORS_VERSION` is the version of the test vectors format.
+pub const TEST_VECTORS_VERSION: u32 = 1;
+
+/// `TEST_VECTORS_TIME` is the unix time of the test vectors models.
+pub const TEST_VECTORS_TIME: i64 = 1_577_836_800;
+
+/// `TEST_VECTORS_STAGE` is the stage of the test vectors models.
+pub const TEST_VECTORS_STAGE: Stage = Stage::Testing;
+
+/// `TEST_VECTORS_VERSION_STRING` is the protocol version of the test vectors models.
+const TEST_VECTORS_VERSION_STRING: &str = "0.1.0";
+
+/// `TestVector` is the CBOR and JSON encoding of a model. The CBOR binary is
+/// hex encoded.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct TestVector {
+    pub name: String,
+    pub cbor: String,
+    pub json: String,
+}
+
+impl TestVector {
+    /// `new` creates a new `TestVector` encoding a value.
+    pub fn new<T: Serialize>(name: &str, value: &T) -> Result<TestVector> {
+        let cbor = serde_cbor::to_vec(value)?;
+        let json = serde_json::to_string(value)?;
+
+        let vector = TestVector {
+            name: name.into(),
+            cbor: base16::encode_lower(&cbor),
+            json,
+        };
+
+        Ok(vector)
+    }
+
+    /// `cbor_bytes` returns the CBOR binary of the `TestVector`.
+    pub fn cbor_bytes(&self) -> Result<Vec<u8>> {
+        base16::decode(&self.cbor).map_err(|e| {
+            let msg = format!("invalid hex: {}", e);
+            Error::Parse { msg }
+        })
+    }
+
+    /// `mismatch` returns the error of a `TestVector` check.
+    fn mismatch(&self, msg: &str) -> Error {
+        Error::TestVector {
+            name: self.name.clone(),
+            msg: msg.into(),
+        }
+    }
+
+    /// `roundtrip` checks that the encodings of the `TestVector` decode into
+    /// the same value, which encodes back into the same encodings.
+    fn roundtrip<T: Serialize + DeserializeOwned + PartialEq>(&self) -> Result<()> {
+        let cbor = self.cbor_bytes()?;
+
+        let from_cbor: T = serde_cbor::from_slice(&cbor)?;
+        let from_json: T = serde_json::from_str(&self.json)?;
+
+        if from_cbor != from_json {
+            let err = self.mismatch("CBOR and JSON decode to different values");
+            return Err(err);
+        }
+
+        if serde_cbor::to_vec(&from_cbor)? != cbor {
+            let err = self.mismatch("CBOR does not encode back to the same binary");
+            return Err(err);
+        }
+
+        if serde_json::to_string(&from_json)? != self.json {
+            let err = self.mismatch("JSON does not encode back to the same string");
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `check` checks the roundtrip of the `TestVector` encodings in the model
+    /// type of its name.
+    pub fn check(&self) -> Result<()> {
+        match self.name.as_str() {
+            "account" => self.roundtrip::<Account>(),
+            "transaction" => self.roundtrip::<Transaction>(),
+            "node" => self.roundtrip::<Node>(),
+            "consensus_message_hello" | "consensus_message_query" | "consensus_message_reply" => {
+                self.roundtrip::<ConsensusMessage>()
+            }
+            _ => {
+                let err = self.mismatch("unknown test vector");
+                Err(err)
+            }
+        }
+    }
+}
+
+/// `TestVectors` is a set of `TestVector`s, as stored in a golden file.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u32,
+    pub vectors: Vec<TestVector>,
+}
+
+impl TestVectors {
+    /// `generate` generates the `TestVectors` of the current release.
+    pub fn generate() -> Result<TestVectors> {
+        let secret_key = test_secret_key()?;
+        let account = test_account(&secret_key)?;
+        let transaction = test_transaction(&secret_key, &account)?;
+        let node = test_node(&secret_key)?;
+
+        let vectors = vec![
+            TestVector::new("account", &account)?,
+            TestVector::new("transaction", &transaction)?,
+            TestVector::new("node", &node)?,
+            TestVector::new("consensus_message_hello", &test_hello(&node)?)?,
+            TestVector::new("consensus_message_query", &test_query(&node, &transaction)?)?,
+            TestVector::new("consensus_message_reply", &test_reply(&node, &transaction)?)?,
+        ];
+
+        let test_vectors = TestVectors {
+            version: TEST_VECTORS_VERSION,
+            vectors,
+        };
+
+        Ok(test_vectors)
+    }
+
+    /// `get` returns the `TestVector` with a given name, if any.
+    pub fn get(&self, name: &str) -> Option<&TestVector> {
+        self.vectors.iter().find(|vector| vector.name == name)
+    }
+
+    /// `check` checks the roundtrip of all the `TestVector`s.
+    pub fn check(&self) -> Result<()> {
+        if self.version != TEST_VECTORS_VERSION {
+            let err = Error::InvalidVersion;
+            return Err(err);
+        }
+
+        for vector in self.vectors.iter() {
+            vector.check()?;
+        }
+
+        Ok(())
+    }
+
+    /// `verify` verifies that the `TestVectors`, as read from a golden file,
+    /// are encoded in the same way by the current release.
+    pub fn verify(&self) -> Result<()> {
+        self.check()?;
+
+        let current = TestVectors::generate()?;
+
+        for vector in self.vectors.iter() {
+            match current.get(&vector.name) {
+                Some(current_vector) if current_vector == vector => {}
+                Some(_) => {
+                    let err = vector.mismatch("encoding changed");
+                    return Err(err);
+                }
+                None => {
+                    let err = vector.mismatch("not generated by this release");
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `to_json` converts the `TestVectors` into a pretty JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `TestVectors`.
+    pub fn from_json(s: &str) -> Result<TestVectors> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+/// `test_time` returns the `Timestamp` of the test vectors models, shifted
+/// by some seconds.
+fn test_time(secs: i64) -> Result<Timestamp> {
+    Timestamp::from_i64(TEST_VECTORS_TIME + secs)
+}
+
+/// `test_digest` returns the `Digest` of a label.
+fn test_digest(label: &str) -> Digest {
+    Blake512Hasher::hash(label.as_bytes())
+}
+
+/// `test_secret_key` returns the `SecretKey` signing the test vectors models.
+fn test_secret_key() -> Result<SecretKey> {
+    SecretKey::from_bytes([1u8; 32]).map_err(|e| e.into())
+}
+
+/// `test_account` returns the test vectors `Account`.
+fn test_account(secret_key: &SecretKey) -> Result<Account> {
+    let signer = Signer {
+        public_key: secret_key.to_public(),
+        weight: 1,
+    };
+
+    let mut signers = Signers::new()?;
+    signers.add(&signer)?;
+    signers.set_threshold(1)?;
+
+    let account = Account {
+        signers,
+        stage: TEST_VECTORS_STAGE,
+        time: test_time(0)?,
+        locktime: None,
+        amount: 10_000,
+        counter: 1,
+        transaction_id: Some(test_digest("transaction")),
+    };
+
+    Ok(account)
+}
+
+/// `test_transaction` returns the test vectors `Transaction`, spending the
+/// test vectors `Account`.
+fn test_transaction(secret_key: &SecretKey, account: &Account) -> Result<Transaction> {
+    let mut transaction = Transaction {
+        id: Digest::default(),
+        version: Version::parse(TEST_VECTORS_VERSION_STRING)?,
+        stage: TEST_VECTORS_STAGE,
+        time: test_time(60)?,
+        locktime: None,
+        expiry: Some(test_time(3600)?),
+        distance: 1,
+        inputs: BTreeMap::new(),
+        outputs: BTreeMap::new(),
+        coinbase: None,
+        nonce: 0,
+        stakes: BTreeMap::new(),
+    };
+
+    let input = Input::new(account, 1, 10_000)?;
+    transaction.add_input(&input)?;
+
+    let output = Output::new(&test_digest("output"), 9_000, b"alsacoin");
+    transaction.add_output(&output)?;
+
+    transaction.set_coinbase(&account.address(), 1)?;
+    transaction.sign_input(secret_key, &account.address())?;
+    transaction.update_id()?;
+
+    Ok(transaction)
+}
+
+/// `test_node` returns the test vectors `Node`, announced with the test
+/// vectors `SecretKey`.
+fn test_node(secret_key: &SecretKey) -> Result<Node> {
+    let address = vec![127, 0, 0, 1, 7, 227];
+
+    let mut addresses = BTreeSet::new();
+    addresses.insert(NetAddress::parse("127.0.0.1:2019")?);
+    addresses.insert(NetAddress::parse("[::1]:2019")?);
+    addresses.insert(NetAddress::parse("node.alsacoin.org:2019")?);
+
+    let mut services = BTreeSet::new();
+    services.insert(Service::Relay);
+    services.insert(Service::Archival);
+
+    let mut node = Node {
+        id: Blake512Hasher::hash(&address),
+        address,
+        stage: TEST_VECTORS_STAGE,
+        last_seen: test_time(120)?,
+        addresses,
+        announcement: None,
+        services,
+        latency: None,
+        work: 0,
+        rotation: None,
+    };
+
+    let time = test_time(120)?;
+    let msg = node.announcement_message(time)?;
+
+    node.announcement = Some(NodeAnnouncement {
+        public_key: secret_key.to_public(),
+        time,
+        signature: secret_key.sign(&msg),
+    });
+
+    Ok(node)
+}
+
+/// `test_hello` returns the test vectors `ConsensusMessage::Hello`.
+fn test_hello(node: &Node) -> Result<ConsensusMessage> {
+    let message = ConsensusMessage::Hello {
+        id: 1,
+        address: node.address.clone(),
+        node: node.clone(),
+        time: test_time(180)?,
+        version: Version::parse(TEST_VECTORS_VERSION_STRING)?,
+        stage: TEST_VECTORS_STAGE,
+        services: node.services.clone(),
+    };
+
+    Ok(message)
+}
+
+/// `test_query` returns the test vectors `ConsensusMessage::Query`.
+fn test_query(node: &Node, transaction: &Transaction) -> Result<ConsensusMessage> {
+    let message = ConsensusMessage::Query {
+        id: 2,
+        address: node.address.clone(),
+        node: node.clone(),
+        time: test_time(240)?,
+        transaction: transaction.clone(),
+    };
+
+    Ok(message)
+}
+
+/// `test_reply` returns the test vectors `ConsensusMessage::Reply`.
+fn test_reply(node: &Node, transaction: &Transaction) -> Result<ConsensusMessage> {
+    let message = ConsensusMessage::Reply {
+        id: 3,
+        address: node.address.clone(),
+        node: node.clone(),
+        time: test_time(300)?,
+        tx_id: transaction.id,
+        chit: true,
+        confidence: None,
+        preferred: None,
+    };
+
+    Ok(message)
+}
+
+#[test]
+fn test_testvectors_generate() {
+    let res = TestVectors::generate();
+    assert!(res.is_ok());
+    let vectors = res.unwrap();
+
+    let res = TestVectors::generate();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), vectors);
+
+    let res = vectors.check();
+    assert!(res.is_ok());
+
+    let transaction = vectors.get("transaction").unwrap();
+    let res = Transaction::from_bytes(&transaction.cbor_bytes().unwrap());
+    assert!(res.is_ok());
+    let transaction = res.unwrap();
+    assert!(transaction.validate_id().is_ok());
+    assert!(transaction.is_fully_signed().unwrap());
+}
+
+#[test]
+fn test_testvectors_verify() {
+    let res = TestVectors::from_json(include_str!("../testdata/vectors.json"));
+    assert!(res.is_ok());
+    let golden = res.unwrap();
+
+    let res = golden.verify();
+    assert!(res.is_ok());
+
+    let vectors = TestVectors::generate().unwrap();
+
+    let res = vectors.to_json();
+    assert!(res.is_ok());
+
+    let res = TestVectors::from_json(&res.unwrap());
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), vectors);
+
+    let mut changed = golden.clone();
+    changed.vectors[0].json = changed.vectors[1].json.clone();
+    changed.vectors[0].cbor = changed.vectors[1].cbor.clone();
+
+    let res = changed.verify();
+    assert!(res.is_err());
+
+    let mut unknown = golden.clone();
+    unknown.vectors[0].name = "unknown".into();
+
+    let res = unknown.verify();
+    assert!(res.is_err());
+
+    let mut outdated = golden;
+    outdated.version += 1;
+
+    let res = outdated.verify();
+    assert!(res.is_err());
+}
@@ -110,6 +110,11 @@ impl Timestamp {
         Timestamp(self.0 + MAX_TIMENOISE)
     }
 
+    /// Returns the `Timestamp` a number of seconds after this `Timestamp`.
+    pub fn after(self, secs: u64) -> Timestamp {
+        Timestamp(self.0.saturating_add(secs as i64))
+    }
+
     /// Returns the time difference between this `Timestamp` and an other.
     pub fn diff(self, other: Timestamp) -> i64 {
         self.0 - other.0
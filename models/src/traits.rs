@@ -25,6 +25,13 @@ pub trait Storable<S: Store>: Sized {
     /// `validate_single` validates the model instance against the store.
     fn validate_single(store: &S, stage: Stage, value: &Self) -> Result<()>;
 
+    /// `stage_range` returns the `Store` keys range of the model instances of a stage.
+    fn stage_range(stage: Stage) -> (Vec<u8>, Vec<u8>) {
+        let from = vec![stage as u8, Self::KEY_PREFIX];
+        let to = vec![stage as u8, Self::KEY_PREFIX + 1];
+        (from, to)
+    }
+
     /// `validate_all` validates all the models instances in the store.
     fn validate_all(store: &S, stage: Stage) -> Result<()>;
 
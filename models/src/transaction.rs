@@ -4,21 +4,29 @@
 
 use crate::account::Account;
 use crate::address::Address;
+use crate::cbor::{self, CborLimits};
 use crate::coinbase::Coinbase;
 use crate::error::Error;
-use crate::input::Input;
+use crate::input::{Input, SignaturesStatus};
 use crate::output::Output;
+use crate::partial_signatures::PartialSignatures;
 use crate::result::Result;
 use crate::stage::Stage;
+use crate::stake::Stake;
 use crate::timestamp::Timestamp;
 use crate::traits::Storable;
 use crate::version::Version;
+use config::consensus::ConsensusConfig;
 use crypto::ecc::ed25519::{PublicKey, SecretKey};
 use crypto::hash::{Blake512Hasher, Digest};
 use crypto::random::Random;
+use mining::difficulty::{retarget, MIN_DIFFICULTY};
+use mining::scheduler::Scheduler;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
+use store::index::{Index, StoreIndex};
 use store::traits::Store;
+use store::wal;
 
 /// `Transaction` is the Alsacoin transaction type. It is built
 /// around the HybridTx model defined in `Chimeric Ledgers` papers.
@@ -29,14 +37,36 @@ pub struct Transaction {
     pub stage: Stage,
     pub time: Timestamp,
     pub locktime: Option<Timestamp>,
+    #[serde(default)]
+    pub expiry: Option<Timestamp>,
     pub distance: u64,
     pub inputs: BTreeMap<Address, Input>,
     pub outputs: BTreeMap<Address, Output>,
     pub coinbase: Option<Coinbase>,
     pub nonce: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stakes: BTreeMap<Address, Stake>,
 }
 
 impl Transaction {
+    /// `ADDRESS_INDEX_PREFIX` is the prefix of the `Transaction` addresses indexes keys.
+    pub const ADDRESS_INDEX_PREFIX: u8 = 4;
+
+    /// `MINING_TIME_INDEX_PREFIX` is the prefix of the mined `Transaction`s time index keys.
+    pub const MINING_TIME_INDEX_PREFIX: u8 = 22;
+
+    /// `INPUT_ADDRESS_INDEX` is the tag of the `Transaction` input addresses index.
+    pub const INPUT_ADDRESS_INDEX: u8 = 0;
+
+    /// `OUTPUT_ADDRESS_INDEX` is the tag of the `Transaction` output addresses index.
+    pub const OUTPUT_ADDRESS_INDEX: u8 = 1;
+
+    /// `SUCCESSOR_INDEX` is the tag of the `Transaction` successors index.
+    pub const SUCCESSOR_INDEX: u8 = 3;
+
+    /// `COINBASE_ADDRESS_INDEX` is the tag of the `Transaction` coinbase addresses index.
+    pub const COINBASE_ADDRESS_INDEX: u8 = 4;
+
     /// `new` creates a new `Transaction`.
     pub fn new() -> Result<Transaction> {
         let mut transaction = Transaction {
@@ -45,11 +75,13 @@ impl Transaction {
             stage: Stage::default(),
             time: Timestamp::default(),
             locktime: None,
+            expiry: None,
             distance: 1,
             inputs: BTreeMap::default(),
             outputs: BTreeMap::default(),
             coinbase: None,
             nonce: Random::u64()?,
+            stakes: BTreeMap::default(),
         };
 
         transaction.update_id()?;
@@ -67,11 +99,13 @@ impl Transaction {
             stage,
             time: Timestamp::default(),
             locktime: None,
+            expiry: None,
             distance: 0,
             inputs: BTreeMap::default(),
             outputs: BTreeMap::default(),
             coinbase: Some(coinbase),
             nonce: Random::u64()?,
+            stakes: BTreeMap::default(),
         };
 
         transaction.update_id()?;
@@ -104,10 +138,10 @@ impl Transaction {
         self.update_id()
     }
 
-    /// `set_locktime` sets the `Transaction` locktime.
+    /// `set_locktime` sets the `Transaction` locktime, the time until which the
+    /// `Output`s of the `Transaction` cannot be spent.
     pub fn set_locktime(&mut self, locktime: Timestamp) -> Result<()> {
-        locktime.validate()?;
-
+        // NB: the locktime is in the future, so it is not validated as a timestamp
         if locktime < self.time {
             let err = Error::InvalidTimestamp;
             return Err(err);
@@ -118,13 +152,73 @@ impl Transaction {
         self.update_id()
     }
 
+    /// `is_locked` returns if the `Output`s of the `Transaction` are locked at a given time.
+    pub fn is_locked(&self, time: Timestamp) -> bool {
+        if let Some(locktime) = self.locktime {
+            time < locktime
+        } else {
+            false
+        }
+    }
+
+    /// `set_expiry` sets the `Transaction` expiry, the time after which the
+    /// `Transaction` cannot be finalized anymore.
+    pub fn set_expiry(&mut self, expiry: Timestamp) -> Result<()> {
+        expiry.validate()?;
+
+        if expiry < self.time {
+            let err = Error::InvalidTimestamp;
+            return Err(err);
+        }
+
+        if let Some(locktime) = self.locktime {
+            if expiry < locktime {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+        }
+
+        self.expiry = Some(expiry);
+
+        self.update_id()
+    }
+
+    /// `is_expired` returns if the `Transaction` is expired at a given time.
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        if let Some(expiry) = self.expiry {
+            expiry < now
+        } else {
+            false
+        }
+    }
+
+    /// `add_stake` adds a `Stake` of the address of an `Input` to the `Transaction`,
+    /// replacing its previous one, if any. The `Stake` is signed with the `Input`.
+    pub fn add_stake(&mut self, stake: &Stake) -> Result<()> {
+        stake.validate()?;
+
+        if stake.stage != self.stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        if !self.lookup_input(&stake.address) {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        self.stakes.insert(stake.address, stake.to_owned());
+
+        self.update_id()
+    }
+
     /// `input_balance` returns the `Transaction` inputs balance.
     pub fn input_balance(&self) -> Result<u64> {
-        let mut res = 0;
+        let mut res: u64 = 0;
 
         for input in self.inputs.values() {
-            if let Some(amount) = input.amount.checked_add(res) {
-                res += amount;
+            if let Some(amount) = res.checked_add(input.amount) {
+                res = amount;
             } else {
                 let err = Error::InvalidBalance;
                 return Err(err);
@@ -136,11 +230,11 @@ impl Transaction {
 
     /// `output_balance` returns the `Transaction` outputs balance.
     pub fn output_balance(&self) -> Result<u64> {
-        let mut res = 0;
+        let mut res: u64 = 0;
 
         for output in self.outputs.values() {
-            if let Some(amount) = output.amount.checked_add(res) {
-                res += amount;
+            if let Some(amount) = res.checked_add(output.amount) {
+                res = amount;
             } else {
                 let err = Error::InvalidBalance;
                 return Err(err);
@@ -190,6 +284,19 @@ impl Transaction {
         Ok(ancestors)
     }
 
+    /// `addresses` returns the addresses of the inputs, the outputs and the
+    /// coinbase of the `Transaction`.
+    pub fn addresses(&self) -> BTreeSet<Address> {
+        let mut addresses: BTreeSet<Address> = self.inputs.keys().copied().collect();
+        addresses.extend(self.outputs.keys().copied());
+
+        if let Some(ref coinbase) = self.coinbase {
+            addresses.insert(coinbase.address);
+        }
+
+        addresses
+    }
+
     /// `lookup_input` look ups an `Input` in the `Transaction`.
     pub fn lookup_input(&self, address: &Address) -> bool {
         self.inputs.contains_key(address)
@@ -379,6 +486,55 @@ impl Transaction {
         clone.to_bytes()
     }
 
+    /// `input_sign_digest` returns the digest of the binary message used when
+    /// signing an `Input` in the `Transaction`.
+    pub fn input_sign_digest(&self) -> Result<Digest> {
+        let msg = self.input_sign_message()?;
+        let digest = Blake512Hasher::hash(&msg);
+        Ok(digest)
+    }
+
+    /// `export_signatures` exports the `Input` signatures of the `Transaction`
+    /// as a `PartialSignatures` CBOR binary, to be shared with the other signers.
+    pub fn export_signatures(&self) -> Result<Vec<u8>> {
+        PartialSignatures::new(self)?.to_bytes()
+    }
+
+    /// `import_signatures` imports the `Input` signatures of a `PartialSignatures`
+    /// CBOR binary exported from the same `Transaction`.
+    pub fn import_signatures(&mut self, buf: &[u8]) -> Result<()> {
+        let psigs = PartialSignatures::from_bytes(buf)?;
+        self.add_partial_signatures(&psigs)
+    }
+
+    /// `add_partial_signatures` adds the signatures of a `PartialSignatures` to
+    /// the `Input`s of the `Transaction`.
+    pub fn add_partial_signatures(&mut self, psigs: &PartialSignatures) -> Result<()> {
+        psigs.validate(self)?;
+
+        let msg = self.input_sign_message()?;
+
+        for (address, sigs) in &psigs.signatures {
+            let mut input = self.get_input(address)?;
+
+            for (public_key, signature) in sigs {
+                input.add_signature(public_key, signature, &msg)?;
+            }
+
+            self.update_input(&input)?;
+        }
+
+        Ok(())
+    }
+
+    /// `signatures_status` returns the `SignaturesStatus` of the `Input`s of the `Transaction`.
+    pub fn signatures_status(&self) -> Result<Vec<SignaturesStatus>> {
+        self.inputs
+            .values()
+            .map(|input| input.signatures_status())
+            .collect()
+    }
+
     /// `sign_input` signs an `Input` in the `Transaction`.
     pub fn sign_input(&mut self, secret_key: &SecretKey, address: &Address) -> Result<()> {
         let mut input = self.get_input(&address)?;
@@ -481,14 +637,36 @@ impl Transaction {
         Ok(())
     }
 
-    /// `validate_mined` verifies the `Transaction` mined `Coinbase` proof.
-    pub fn validate_mined(&self) -> Result<()> {
+    /// `mine_scheduled` mines the `Transaction` in a thread scheduled by a `Scheduler`.
+    pub fn mine_scheduled(&mut self, scheduler: &Scheduler) -> Result<()> {
+        if let Some(mut coinbase) = self.coinbase {
+            let msg = self.mining_message()?;
+            coinbase.mine_scheduled(&msg, scheduler)?;
+            self.coinbase = Some(coinbase);
+
+            Ok(())
+        } else {
+            let err = Error::InvalidCoinbase;
+            Err(err)
+        }
+    }
+
+    /// `validate_mined` verifies the `Transaction` mined `Coinbase` proof and,
+    /// if given, that it is mined at least at the expected retargeted difficulty.
+    pub fn validate_mined(&self, difficulty: Option<u64>) -> Result<()> {
         if self.coinbase.is_none() {
             let err = Error::InvalidCoinbase;
             return Err(err);
         }
 
         if let Some(coinbase) = self.coinbase {
+            if let Some(difficulty) = difficulty {
+                if coinbase.difficulty < difficulty {
+                    let err = Error::InvalidDifficulty;
+                    return Err(err);
+                }
+            }
+
             let msg = self.mining_message()?;
             coinbase.validate_mined(&msg)?;
         }
@@ -669,28 +847,82 @@ impl Transaction {
         Ok(())
     }
 
+    /// `validate_ancestors_distance` validates the `Transaction` distance against
+    /// its ancestors: an `Input` can be at most one further from the eve `Transaction`
+    /// than the ancestor it spends, and so can the `Transaction` and its `Coinbase`
+    /// than the farthest of its ancestors. A `Transaction` with no ancestors is at
+    /// distance one at most.
+    pub fn validate_ancestors_distance(&self, ancestors: &[Transaction]) -> Result<()> {
+        if self.is_eve()? {
+            return Ok(());
+        }
+
+        let ancestors: BTreeMap<Digest, &Transaction> = ancestors
+            .iter()
+            .map(|ancestor| (ancestor.id, ancestor))
+            .collect();
+
+        if ancestors.keys().copied().collect::<BTreeSet<Digest>>() != self.ancestors()? {
+            let err = Error::InvalidTransactions;
+            return Err(err);
+        }
+
+        let mut max_distance = 1;
+
+        for input in self.inputs.values() {
+            let tx_id = input.account.transaction_id.ok_or(Error::InvalidInput)?;
+            let ancestor_distance = ancestors[&tx_id].distance.saturating_add(1);
+
+            if input.distance > ancestor_distance {
+                let err = Error::InvalidDistance;
+                return Err(err);
+            }
+
+            max_distance = max_distance.max(ancestor_distance);
+        }
+
+        if self.distance > max_distance {
+            let err = Error::InvalidDistance;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     /// `validate_times` validates the `Transaction` time and locktime.
     pub fn validate_times(&self) -> Result<()> {
         self.time.validate()?;
 
         if let Some(locktime) = self.locktime {
-            locktime.validate()?;
-
             if self.time > locktime {
                 let err = Error::InvalidTimestamp;
                 return Err(err);
             }
         }
 
-        for input in self.inputs.values() {
-            if let Some(locktime) = input.account.locktime {
-                if self.time < locktime {
+        if let Some(expiry) = self.expiry {
+            expiry.validate()?;
+
+            if self.time > expiry {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+
+            if let Some(locktime) = self.locktime {
+                if locktime > expiry {
                     let err = Error::InvalidTimestamp;
                     return Err(err);
                 }
             }
         }
 
+        for input in self.inputs.values() {
+            if input.account.is_locked(self.time) {
+                let err = Error::LockedAccount;
+                return Err(err);
+            }
+        }
+
         Ok(())
     }
 
@@ -704,8 +936,149 @@ impl Transaction {
         Ok(())
     }
 
+    /// `validate_stakes` validates the `Stake`s of the `Transaction`. A `Stake` has
+    /// to be of the address of an `Input`, so that it is signed by its `Signers`,
+    /// and its amount has to be sent back to the address.
+    pub fn validate_stakes(&self) -> Result<()> {
+        for (address, stake) in self.stakes.iter() {
+            stake.validate()?;
+
+            if &stake.address != address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            if stake.stage != self.stage {
+                let err = Error::InvalidStage;
+                return Err(err);
+            }
+
+            if !self.lookup_input(address) {
+                let err = Error::InvalidStake;
+                return Err(err);
+            }
+
+            let duration = stake.locktime.diff(self.time);
+
+            if duration <= 0 || duration as u64 > Stake::MAX_DURATION {
+                let err = Error::InvalidStake;
+                return Err(err);
+            }
+
+            if self.sent_back(address) < stake.amount {
+                let err = Error::InvalidStake;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `sent_back` returns the amount sent back by the `Transaction` to the
+    /// address of an `Input`.
+    fn sent_back(&self, address: &Address) -> u64 {
+        self.outputs
+            .get(address)
+            .map(|output| output.amount)
+            .unwrap_or(0)
+    }
+
+    /// `validate_staked_spends` validates the spends of the `Transaction` against
+    /// the `Stake`s in a `Store` of the spent addresses. While a `Stake` is active,
+    /// its amount has to be sent back to the address, and it can be replaced
+    /// only by a larger and longer one.
+    pub fn validate_staked_spends<S: Store>(&self, store: &S, stage: Stage) -> Result<()> {
+        for address in self.inputs.keys() {
+            if !Stake::lookup(store, stage, address)? {
+                continue;
+            }
+
+            let current = Stake::get(store, stage, address)?;
+            current.validate_balance(self.sent_back(address), self.time)?;
+
+            if let Some(stake) = self.stakes.get(address) {
+                stake.validate_replacement(&current)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_unlocked_spends` validates the spends of the `Transaction` against
+    /// the locktimes of the spent `Transaction`s in a `Store`. The locktime of the
+    /// `Account` of an `Input` is set by the spender, so the one signed in the
+    /// `Transaction` funding the `Account` is the one enforced.
+    pub fn validate_unlocked_spends<S: Store>(&self, store: &S, stage: Stage) -> Result<()> {
+        for input in self.inputs.values() {
+            let prev_id = match input.account.transaction_id {
+                Some(prev_id) => prev_id,
+                None => continue,
+            };
+
+            if !Transaction::lookup(store, stage, &prev_id)? {
+                continue;
+            }
+
+            if Transaction::get(store, stage, &prev_id)?.is_locked(self.time) {
+                let err = Error::LockedAccount;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `validate_limits` validates the number of `Input`s and `Output`s of the
+    /// `Transaction` and its CBOR encoded size against a set of maximums. The
+    /// counts are checked first, so that the oversized `Transaction`s are not encoded.
+    pub fn validate_limits(&self, max_size: u32, max_inputs: u32, max_outputs: u32) -> Result<()> {
+        let inputs_len = self.inputs.len() as u64;
+
+        if inputs_len > u64::from(max_inputs) {
+            let err = Error::TooLargeCollection {
+                len: inputs_len,
+                max: u64::from(max_inputs),
+            };
+            return Err(err);
+        }
+
+        let outputs_len = self.outputs.len() as u64;
+
+        if outputs_len > u64::from(max_outputs) {
+            let err = Error::TooLargeCollection {
+                len: outputs_len,
+                max: u64::from(max_outputs),
+            };
+            return Err(err);
+        }
+
+        let size = self.to_bytes()?.len() as u64;
+
+        if size > u64::from(max_size) {
+            let err = Error::TooLong {
+                len: size,
+                max: u64::from(max_size),
+            };
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_max_limits` validates the `Transaction` against the maximum size
+    /// and the maximum `Input`s and `Output`s counts allowed by the consensus.
+    pub fn validate_max_limits(&self) -> Result<()> {
+        self.validate_limits(
+            ConsensusConfig::MAX_TRANSACTION_SIZE,
+            ConsensusConfig::MAX_TRANSACTION_INPUTS,
+            ConsensusConfig::MAX_TRANSACTION_OUTPUTS,
+        )
+    }
+
     /// `validate` validates the `Transaction`.
     pub fn validate(&self) -> Result<()> {
+        self.validate_max_limits()?;
+
         self.validate_id()?;
 
         self.version.validate()?;
@@ -718,6 +1091,8 @@ impl Transaction {
 
         self.validate_balance()?;
 
+        self.validate_stakes()?;
+
         self.validate_coinbase()?;
 
         Ok(())
@@ -726,6 +1101,8 @@ impl Transaction {
     /// `validate_fully_signed` validates the `Transaction` expecting it to be fully
     /// signed.
     pub fn validate_fully_signed(&self) -> Result<()> {
+        self.validate_max_limits()?;
+
         self.validate_id()?;
 
         self.version.validate()?;
@@ -738,96 +1115,555 @@ impl Transaction {
 
         self.validate_balance()?;
 
+        self.validate_stakes()?;
+
         self.validate_coinbase()?;
 
         Ok(())
     }
 
-    /// `to_bytes` converts the `Transaction` into a CBOR binary.
-    pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        serde_cbor::to_vec(self).map_err(|e| e.into())
+    /// `input_address_index` returns the `Store` index of the `Transaction`s
+    /// by input `Address`.
+    pub fn input_address_index(stage: Stage) -> StoreIndex {
+        StoreIndex::new(&[
+            stage as u8,
+            Self::ADDRESS_INDEX_PREFIX,
+            Self::INPUT_ADDRESS_INDEX,
+        ])
     }
 
-    /// `from_bytes` converts a CBOR binary into an `Transaction`.
-    pub fn from_bytes(b: &[u8]) -> Result<Transaction> {
-        serde_cbor::from_slice(b).map_err(|e| e.into())
+    /// `output_address_index` returns the `Store` index of the `Transaction`s
+    /// by output `Address`.
+    pub fn output_address_index(stage: Stage) -> StoreIndex {
+        StoreIndex::new(&[
+            stage as u8,
+            Self::ADDRESS_INDEX_PREFIX,
+            Self::OUTPUT_ADDRESS_INDEX,
+        ])
     }
 
-    /// `to_json` converts the `Transaction` into a JSON string.
-    pub fn to_json(&self) -> Result<String> {
-        serde_json::to_string(self).map_err(|e| e.into())
+    /// `coinbase_address_index` returns the `Store` index of the `Transaction`s
+    /// by coinbase `Address`.
+    pub fn coinbase_address_index(stage: Stage) -> StoreIndex {
+        StoreIndex::new(&[
+            stage as u8,
+            Self::ADDRESS_INDEX_PREFIX,
+            Self::COINBASE_ADDRESS_INDEX,
+        ])
     }
 
-    /// `from_json` converts a JSON string into an `Transaction`.
-    pub fn from_json(s: &str) -> Result<Transaction> {
-        serde_json::from_str(s).map_err(|e| e.into())
+    /// `mining_time_index` returns the `Store` index of the mined `Transaction`s
+    /// by time.
+    pub fn mining_time_index(stage: Stage) -> StoreIndex {
+        StoreIndex::new(&[stage as u8, Self::MINING_TIME_INDEX_PREFIX])
     }
-}
 
-impl Default for Transaction {
-    fn default() -> Transaction {
-        Transaction::new().unwrap()
+    /// `successor_index` returns the `Store` index of the `Transaction`s
+    /// by ancestor `Transaction` id.
+    pub fn successor_index(stage: Stage) -> StoreIndex {
+        StoreIndex::new(&[
+            stage as u8,
+            Self::ADDRESS_INDEX_PREFIX,
+            Self::SUCCESSOR_INDEX,
+        ])
     }
-}
-
-impl<S: Store> Storable<S> for Transaction {
-    const KEY_PREFIX: u8 = 3;
-
-    type Key = Digest;
 
-    fn key(&self) -> Self::Key {
-        self.id
+    /// `mining_time_key` returns the mining time index key of a `Timestamp`.
+    fn mining_time_key(time: Timestamp) -> [u8; 8] {
+        (time.to_i64().max(0) as u64).to_be_bytes()
     }
 
-    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        buf.push(stage as u8);
-        buf.push(<Self as Storable<S>>::KEY_PREFIX);
-        buf.extend_from_slice(&key.to_bytes());
-        Ok(buf)
+    /// `is_retargetable` returns if the `Transaction` is a mined non-eve `Transaction`,
+    /// and so takes part in the mining difficulty retargeting.
+    pub fn is_retargetable(&self) -> bool {
+        match self.coinbase {
+            Some(coinbase) => coinbase.is_mined() && coinbase.difficulty != 0,
+            None => false,
+        }
     }
 
-    fn validate_single(store: &S, stage: Stage, value: &Self) -> Result<()> {
-        if value.stage != stage {
-            let err = Error::InvalidStage;
-            return Err(err);
-        }
+    /// `index` inserts the `Transaction` index entries in the `Store`.
+    pub fn index<S: Store>(&self, store: &mut S, stage: Stage) -> Result<()> {
+        self.index_addresses(store, stage)?;
+        self.index_ancestors(store, stage)?;
 
-        value.validate()?;
+        if self.is_retargetable() {
+            let index = Self::mining_time_index(stage);
+            index.insert(store, &Self::mining_time_key(self.time), &self.id.to_vec())?;
+        }
 
-        for input in value.inputs.values() {
-            let account = input.account.clone();
-            Account::validate_single(store, stage, &account)?;
+        for stake in self.stakes.values() {
+            Stake::insert(store, stage, stake)?;
         }
 
         Ok(())
     }
 
-    fn validate_all(store: &S, stage: Stage) -> Result<()> {
-        for value in Self::query(store, stage, None, None, None, None)? {
-            Self::validate_single(store, stage, &value)?;
+    /// `unindex` removes the `Transaction` index entries from the `Store`.
+    pub fn unindex<S: Store>(&self, store: &mut S, stage: Stage) -> Result<()> {
+        self.unindex_addresses(store, stage)?;
+        self.unindex_ancestors(store, stage)?;
+
+        let index = Self::mining_time_index(stage);
+        index.remove(store, &Self::mining_time_key(self.time), &self.id.to_vec())?;
+
+        for (address, stake) in self.stakes.iter() {
+            // NB: the Stake may have been replaced by a later Transaction.
+            let indexed = Stake::lookup(store, stage, address)?
+                && &Stake::get(store, stage, address)? == stake;
+
+            if indexed {
+                Stake::remove(store, stage, address)?;
+            }
         }
 
         Ok(())
     }
 
-    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
-        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-        store.lookup(&key).map_err(|e| e.into())
-    }
+    /// `spent_rewards` returns the ids of the ancestors whose mined `Coinbase`
+    /// rewards are spent by the `Transaction`: the ancestors funding an `Input`
+    /// `Account` with the address of their `Coinbase`. All the ancestors of the
+    /// `Transaction` have to be given.
+    pub fn spent_rewards(&self, ancestors: &[Transaction]) -> Result<BTreeSet<Digest>> {
+        let ancestors: BTreeMap<Digest, &Transaction> = ancestors
+            .iter()
+            .map(|ancestor| (ancestor.id, ancestor))
+            .collect();
 
-    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
-        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
-        let buf = store.get(&key)?;
-        Self::from_bytes(&buf)
-    }
+        if ancestors.keys().copied().collect::<BTreeSet<Digest>>() != self.ancestors()? {
+            let err = Error::InvalidTransactions;
+            return Err(err);
+        }
 
-    fn query(
-        store: &S,
-        stage: Stage,
-        from: Option<Self::Key>,
-        to: Option<Self::Key>,
-        count: Option<u32>,
+        let mut rewards = BTreeSet::new();
+
+        for input in self.inputs.values() {
+            let tx_id = input.account.transaction_id.ok_or(Error::InvalidInput)?;
+            let funding = ancestors[&tx_id];
+
+            let rewarded = match funding.coinbase {
+                Some(coinbase) => coinbase.address == input.account.address(),
+                None => false,
+            };
+
+            if rewarded && funding.is_retargetable() {
+                rewards.insert(tx_id);
+            }
+        }
+
+        Ok(rewards)
+    }
+
+    /// `expected_difficulty` returns the minimum mining difficulty of the `Transaction`
+    /// given its ancestors, retargeted following the `ConsensusConfig` schedule.
+    /// The difficulty depends only on the `Transaction` time and on its ancestors, so
+    /// that all the nodes expect the same one: the latest `retarget_window` mined
+    /// ancestors are retargeted from the highest of their difficulties by the mean
+    /// time elapsed from them to the `Transaction`. A `Transaction` with no mined
+    /// ancestors is expected the minimum difficulty.
+    pub fn expected_difficulty(
+        &self,
+        ancestors: &[Transaction],
+        config: &ConsensusConfig,
+    ) -> Result<u64> {
+        let ids: BTreeSet<Digest> = ancestors.iter().map(|ancestor| ancestor.id).collect();
+
+        if ids != self.ancestors()? {
+            let err = Error::InvalidTransactions;
+            return Err(err);
+        }
+
+        let window = config
+            .retarget_window
+            .unwrap_or(ConsensusConfig::DEFAULT_RETARGET_WINDOW);
+        let target_time = config
+            .target_mining_time
+            .unwrap_or(ConsensusConfig::DEFAULT_TARGET_MINING_TIME);
+        let max_step = config
+            .max_retarget_step
+            .unwrap_or(ConsensusConfig::DEFAULT_MAX_RETARGET_STEP);
+
+        let mut mined: Vec<&Transaction> = ancestors
+            .iter()
+            .filter(|ancestor| ancestor.is_retargetable())
+            .collect();
+
+        if mined.is_empty() {
+            return Ok(MIN_DIFFICULTY);
+        }
+
+        mined.sort_by(|a, b| (b.time, b.id).cmp(&(a.time, a.id)));
+        mined.truncate(window.max(1) as usize);
+
+        let difficulty = mined
+            .iter()
+            .filter_map(|ancestor| ancestor.coinbase)
+            .map(|coinbase| coinbase.difficulty)
+            .max()
+            .unwrap_or(MIN_DIFFICULTY);
+
+        let earliest = mined[mined.len() - 1];
+
+        let elapsed = self.time.diff(earliest.time).max(0) as u64;
+        let mean_time = elapsed / mined.len() as u64;
+
+        retarget(difficulty, mean_time, target_time, max_step).map_err(|e| e.into())
+    }
+
+    /// `set_difficulty` sets the mining difficulty of the unmined `Coinbase` of
+    /// the `Transaction`, updating its amount. The difficulty cannot be set once
+    /// an `Input` is signed, as the signatures cover the `Coinbase`.
+    pub fn set_difficulty(&mut self, difficulty: u64) -> Result<()> {
+        let mut coinbase = match self.coinbase {
+            Some(coinbase) => coinbase,
+            None => {
+                let err = Error::InvalidCoinbase;
+                return Err(err);
+            }
+        };
+
+        if coinbase.difficulty == difficulty {
+            return Ok(());
+        }
+
+        if coinbase.is_mined() || self.is_signed() {
+            let err = Error::InvalidDifficulty;
+            return Err(err);
+        }
+
+        if difficulty == 0 || difficulty > Coinbase::MAX_DIFFICULTY {
+            let err = Error::InvalidDifficulty;
+            return Err(err);
+        }
+
+        coinbase.difficulty = difficulty;
+        coinbase.update_amount()?;
+        self.coinbase = Some(coinbase);
+
+        self.update_id()
+    }
+
+    /// `index_addresses` inserts the `Transaction` addresses index entries in the `Store`.
+    pub fn index_addresses<S: Store>(&self, store: &mut S, stage: Stage) -> Result<()> {
+        let key = self.id.to_vec();
+
+        let index = Self::input_address_index(stage);
+        for address in self.inputs.keys() {
+            index.insert(store, &address.to_vec(), &key)?;
+        }
+
+        let index = Self::output_address_index(stage);
+        for address in self.outputs.keys() {
+            index.insert(store, &address.to_vec(), &key)?;
+        }
+
+        if let Some(ref coinbase) = self.coinbase {
+            let index = Self::coinbase_address_index(stage);
+            index.insert(store, &coinbase.address.to_vec(), &key)?;
+        }
+
+        Ok(())
+    }
+
+    /// `unindex_addresses` removes the `Transaction` addresses index entries from the `Store`.
+    pub fn unindex_addresses<S: Store>(&self, store: &mut S, stage: Stage) -> Result<()> {
+        let key = self.id.to_vec();
+
+        let index = Self::input_address_index(stage);
+        for address in self.inputs.keys() {
+            index.remove(store, &address.to_vec(), &key)?;
+        }
+
+        let index = Self::output_address_index(stage);
+        for address in self.outputs.keys() {
+            index.remove(store, &address.to_vec(), &key)?;
+        }
+
+        if let Some(ref coinbase) = self.coinbase {
+            let index = Self::coinbase_address_index(stage);
+            index.remove(store, &coinbase.address.to_vec(), &key)?;
+        }
+
+        Ok(())
+    }
+
+    /// `indexed_ancestors` returns the ancestor ids of the `Transaction` inputs
+    /// kept in the successors index.
+    fn indexed_ancestors(&self) -> BTreeSet<Digest> {
+        self.inputs
+            .values()
+            .filter_map(|input| input.account.transaction_id)
+            .collect()
+    }
+
+    /// `index_ancestors` inserts the `Transaction` successors index entries of its
+    /// ancestors in the `Store`.
+    pub fn index_ancestors<S: Store>(&self, store: &mut S, stage: Stage) -> Result<()> {
+        let key = self.id.to_vec();
+
+        let index = Self::successor_index(stage);
+        for ancestor in self.indexed_ancestors() {
+            index.insert(store, &ancestor.to_vec(), &key)?;
+        }
+
+        Ok(())
+    }
+
+    /// `unindex_ancestors` removes the `Transaction` successors index entries of its
+    /// ancestors from the `Store`.
+    pub fn unindex_ancestors<S: Store>(&self, store: &mut S, stage: Stage) -> Result<()> {
+        let key = self.id.to_vec();
+
+        let index = Self::successor_index(stage);
+        for ancestor in self.indexed_ancestors() {
+            index.remove(store, &ancestor.to_vec(), &key)?;
+        }
+
+        Ok(())
+    }
+
+    /// `query_successors` returns the ids of the `Transaction`s in the `Store`
+    /// having a `Transaction` as ancestor.
+    pub fn query_successors<S: Store>(
+        store: &S,
+        stage: Stage,
+        tx_id: &Digest,
+    ) -> Result<BTreeSet<Digest>> {
+        let index = Self::successor_index(stage);
+        let mut successors = BTreeSet::new();
+
+        for key in index.query(store, &tx_id.to_vec(), None, None)? {
+            let id = Digest::from_slice(&key)?;
+            successors.insert(id);
+        }
+
+        Ok(successors)
+    }
+
+    /// `count_successors` counts the `Transaction`s in the `Store` having a
+    /// `Transaction` as ancestor.
+    pub fn count_successors<S: Store>(store: &S, stage: Stage, tx_id: &Digest) -> Result<u32> {
+        let index = Self::successor_index(stage);
+        index
+            .count(store, &tx_id.to_vec(), None)
+            .map_err(|e| e.into())
+    }
+
+    /// `query_index` returns the `Transaction`s indexed by `Address` in a `Store` index.
+    fn query_index<S: Store>(
+        store: &S,
+        stage: Stage,
+        index: &StoreIndex,
+        address: &Address,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Transaction>> {
+        let mut transactions = BTreeSet::new();
+
+        for key in index.query(store, &address.to_vec(), count, skip)? {
+            let id = Digest::from_slice(&key)?;
+            let transaction = <Self as Storable<S>>::get(store, stage, &id)?;
+            transactions.insert(transaction);
+        }
+
+        Ok(transactions)
+    }
+
+    /// `query_by_input_address` returns the `Transaction`s having an input `Address`.
+    pub fn query_by_input_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Transaction>> {
+        let index = Self::input_address_index(stage);
+        Self::query_index(store, stage, &index, address, count, skip)
+    }
+
+    /// `query_by_output_address` returns the `Transaction`s having an output `Address`.
+    pub fn query_by_output_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Transaction>> {
+        let index = Self::output_address_index(stage);
+        Self::query_index(store, stage, &index, address, count, skip)
+    }
+
+    /// `query_by_coinbase_address` returns the `Transaction`s having a coinbase `Address`.
+    pub fn query_by_coinbase_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Transaction>> {
+        let index = Self::coinbase_address_index(stage);
+        Self::query_index(store, stage, &index, address, count, skip)
+    }
+
+    /// `query_by_address` returns the `Transaction`s having an input, an output
+    /// or a coinbase `Address`.
+    pub fn query_by_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+    ) -> Result<BTreeSet<Transaction>> {
+        let mut transactions = Self::query_by_input_address(store, stage, address, None, None)?;
+
+        for transaction in Self::query_by_output_address(store, stage, address, None, None)? {
+            transactions.insert(transaction);
+        }
+
+        for transaction in Self::query_by_coinbase_address(store, stage, address, None, None)? {
+            transactions.insert(transaction);
+        }
+
+        Ok(transactions)
+    }
+
+    /// `count_by_input_address` counts the `Transaction`s having an input `Address`.
+    pub fn count_by_input_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let index = Self::input_address_index(stage);
+        index
+            .count(store, &address.to_vec(), skip)
+            .map_err(|e| e.into())
+    }
+
+    /// `count_by_output_address` counts the `Transaction`s having an output `Address`.
+    pub fn count_by_output_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let index = Self::output_address_index(stage);
+        index
+            .count(store, &address.to_vec(), skip)
+            .map_err(|e| e.into())
+    }
+
+    /// `count_by_coinbase_address` counts the `Transaction`s having a coinbase `Address`.
+    pub fn count_by_coinbase_address<S: Store>(
+        store: &S,
+        stage: Stage,
+        address: &Address,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let index = Self::coinbase_address_index(stage);
+        index
+            .count(store, &address.to_vec(), skip)
+            .map_err(|e| e.into())
+    }
+
+    /// `to_bytes` converts the `Transaction` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `cbor_limits` returns the `CborLimits` of a CBOR encoded `Transaction`,
+    /// bounding its length by the maximum size allowed by the consensus.
+    pub fn cbor_limits() -> CborLimits {
+        CborLimits {
+            max_length: u64::from(ConsensusConfig::MAX_TRANSACTION_SIZE),
+            ..CborLimits::default()
+        }
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `Transaction`.
+    /// The binary is checked against the `Transaction` `CborLimits` before decoding.
+    pub fn from_bytes(b: &[u8]) -> Result<Transaction> {
+        cbor::from_slice(b, &Self::cbor_limits())
+    }
+
+    /// `to_json` converts the `Transaction` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `Transaction`.
+    pub fn from_json(s: &str) -> Result<Transaction> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl Default for Transaction {
+    fn default() -> Transaction {
+        Transaction::new().unwrap()
+    }
+}
+
+impl<S: Store> Storable<S> for Transaction {
+    const KEY_PREFIX: u8 = 3;
+
+    type Key = Digest;
+
+    fn key(&self) -> Self::Key {
+        self.id
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()?;
+
+        for input in value.inputs.values() {
+            let account = input.account.clone();
+            Account::validate_single(store, stage, &account)?;
+            account.validate_unlocked(value.time)?;
+        }
+
+        value.validate_unlocked_spends(store, stage)?;
+        value.validate_staked_spends(store, stage)
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
         skip: Option<u32>,
     ) -> Result<BTreeSet<Self>> {
         let from = if let Some(ref key) = from {
@@ -938,107 +1774,122 @@ impl<S: Store> Storable<S> for Transaction {
     fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
         Self::validate_single(store, stage, value)?;
 
-        let mut stored_accounts = BTreeSet::new();
-        let mut clean_accounts = false;
+        // NB: the accounts, the transaction and its indexes are written in a single batch
+        wal::atomic(store, |store| {
+            let mut stored_accounts = BTreeSet::new();
+            let mut clean_accounts = false;
 
-        for input in value.inputs.values() {
-            if !clean_accounts {
-                let account = input.account.clone();
+            for input in value.inputs.values() {
+                if !clean_accounts {
+                    let account = input.account.clone();
 
-                if !Account::lookup(store, stage, &account.address())? {
-                    let res = Account::insert(store, stage, &account);
+                    if !Account::lookup(store, stage, &account.address())? {
+                        let res = Account::insert(store, stage, &account);
 
-                    if res.is_err() {
-                        clean_accounts = true;
-                    } else {
-                        stored_accounts.insert(account);
+                        if res.is_err() {
+                            clean_accounts = true;
+                        } else {
+                            stored_accounts.insert(account);
+                        }
                     }
+                } else {
+                    break;
                 }
-            } else {
-                break;
             }
-        }
 
-        if clean_accounts {
-            for account in stored_accounts {
-                Account::remove(store, stage, &account.address())?;
+            if clean_accounts {
+                for account in stored_accounts {
+                    Account::remove(store, stage, &account.address())?;
+                }
             }
-        }
 
-        let key = <Self as Storable<S>>::key(value);
-        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-        let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            store.insert(&store_key, &store_value, None)?;
+
+            value.index(store, stage)
+        })
     }
 
     fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
         Self::validate_single(store, stage, value)?;
 
-        let mut stored_accounts = BTreeSet::new();
-        let mut clean_accounts = false;
+        // NB: the accounts, the transaction and its indexes are written in a single batch
+        wal::atomic(store, |store| {
+            let mut stored_accounts = BTreeSet::new();
+            let mut clean_accounts = false;
 
-        for input in value.inputs.values() {
-            if !clean_accounts {
-                let account = input.account.clone();
+            for input in value.inputs.values() {
+                if !clean_accounts {
+                    let account = input.account.clone();
 
-                if !Account::lookup(store, stage, &account.address())? {
-                    let res = Account::insert(store, stage, &account);
+                    if !Account::lookup(store, stage, &account.address())? {
+                        let res = Account::insert(store, stage, &account);
 
-                    if res.is_err() {
-                        clean_accounts = true;
-                    } else {
-                        stored_accounts.insert(account);
+                        if res.is_err() {
+                            clean_accounts = true;
+                        } else {
+                            stored_accounts.insert(account);
+                        }
                     }
+                } else {
+                    break;
                 }
-            } else {
-                break;
             }
-        }
 
-        if clean_accounts {
-            for account in stored_accounts {
-                Account::remove(store, stage, &account.address())?;
+            if clean_accounts {
+                for account in stored_accounts {
+                    Account::remove(store, stage, &account.address())?;
+                }
             }
-        }
 
-        let key = <Self as Storable<S>>::key(value);
-        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-        let store_value = value.to_bytes()?;
-        store.create(&store_key, &store_value).map_err(|e| e.into())
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            store.create(&store_key, &store_value)?;
+
+            value.index(store, stage)
+        })
     }
 
     fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
         Self::validate_single(store, stage, value)?;
 
-        let mut stored_accounts = BTreeSet::new();
-        let mut clean_accounts = false;
+        // NB: the accounts, the transaction and its indexes are written in a single batch
+        wal::atomic(store, |store| {
+            let mut stored_accounts = BTreeSet::new();
+            let mut clean_accounts = false;
 
-        for input in value.inputs.values() {
-            if !clean_accounts {
-                let account = input.account.clone();
+            for input in value.inputs.values() {
+                if !clean_accounts {
+                    let account = input.account.clone();
 
-                let res = <Account as Storable<S>>::update(store, stage, &account);
+                    let res = <Account as Storable<S>>::update(store, stage, &account);
 
-                if res.is_err() {
-                    clean_accounts = true;
+                    if res.is_err() {
+                        clean_accounts = true;
+                    } else {
+                        stored_accounts.insert(account);
+                    }
                 } else {
-                    stored_accounts.insert(account);
+                    break;
                 }
-            } else {
-                break;
             }
-        }
 
-        if clean_accounts {
-            for account in stored_accounts {
-                Account::remove(store, stage, &account.address())?;
+            if clean_accounts {
+                for account in stored_accounts {
+                    Account::remove(store, stage, &account.address())?;
+                }
             }
-        }
 
-        let key = <Self as Storable<S>>::key(value);
-        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
-        let store_value = value.to_bytes()?;
-        store.update(&store_key, &store_value).map_err(|e| e.into())
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            store.update(&store_key, &store_value)?;
+
+            value.index(store, stage)
+        })
     }
 
     fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
@@ -1071,10 +1922,21 @@ impl<S: Store> Storable<S> for Transaction {
             .map(|(k, v)| (k.as_slice(), v.as_slice()))
             .collect();
 
-        store.insert_batch(&items).map_err(|e| e.into())
+        store.insert_batch(&items)?;
+
+        for value in values {
+            value.index(store, stage)?;
+        }
+
+        Ok(())
     }
 
     fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        if <Self as Storable<S>>::lookup(store, stage, key)? {
+            let value = <Self as Storable<S>>::get(store, stage, key)?;
+            value.unindex(store, stage)?;
+        }
+
         let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
         store.remove(&key).map_err(|e| e.into())
     }
@@ -1082,6 +1944,11 @@ impl<S: Store> Storable<S> for Transaction {
     fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
         let mut _keys = BTreeSet::new();
         for key in keys {
+            if <Self as Storable<S>>::lookup(store, stage, key)? {
+                let value = <Self as Storable<S>>::get(store, stage, key)?;
+                value.unindex(store, stage)?;
+            }
+
             let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
             _keys.insert(key);
         }
@@ -1106,14 +1973,20 @@ impl<S: Store> Storable<S> for Transaction {
         let to = Some(_to.to_vec());
         let to = to.as_ref().map(|to| to.as_slice());
 
-        for value in store.query(from, to, None, None)? {
+        let mut expired = Vec::new();
+
+        for (key, value) in store.iter(from, to)? {
             let tx = Transaction::from_bytes(&value)?;
             if tx.time < min_time {
-                let key = <Self as Storable<S>>::key_to_bytes(stage, &tx.id)?;
-                store.remove(&key)?;
+                expired.push((key, tx));
             }
         }
 
+        for (key, tx) in expired {
+            tx.unindex(store, stage)?;
+            store.remove(&key)?;
+        }
+
         Ok(())
     }
 
@@ -1124,7 +1997,13 @@ impl<S: Store> Storable<S> for Transaction {
         let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
         let to = to.as_ref().map(|to| to.as_slice());
 
-        store.remove_range(from, to, None).map_err(|e| e.into())
+        store.remove_range(from, to, None)?;
+
+        Index::<S>::clear(&Self::input_address_index(stage), store)?;
+        Index::<S>::clear(&Self::output_address_index(stage), store)?;
+        Index::<S>::clear(&Self::coinbase_address_index(stage), store)?;
+        Index::<S>::clear(&Self::successor_index(stage), store)?;
+        Index::<S>::clear(&Self::mining_time_index(stage), store).map_err(|e| e.into())
     }
 }
 
@@ -1209,6 +2088,35 @@ fn test_transaction_times() {
     transaction.locktime = Some(invalid_locktime);
     let res = transaction.validate_times();
     assert!(res.is_err());
+
+    transaction.locktime = None;
+
+    let res = transaction.set_expiry(invalid_locktime);
+    assert!(res.is_err());
+
+    transaction.expiry = Some(invalid_locktime);
+    let res = transaction.validate_times();
+    assert!(res.is_err());
+
+    let expiry_i64 = transaction.time.to_i64() + 1_000;
+    let expiry = Timestamp::from_i64(expiry_i64).unwrap();
+
+    let res = transaction.set_expiry(expiry);
+    assert!(res.is_ok());
+    assert!(!transaction.is_expired(transaction.time));
+
+    let res = transaction.validate_times();
+    assert!(res.is_ok());
+
+    let locktime = Timestamp::from_i64(expiry_i64 + 1_000).unwrap();
+
+    transaction.locktime = Some(locktime);
+    let res = transaction.validate_times();
+    assert!(res.is_err());
+
+    let late_i64 = expiry_i64 + 1;
+    let late = Timestamp::from_i64(late_i64).unwrap();
+    assert!(transaction.is_expired(late));
 }
 
 #[test]
@@ -1580,20 +2488,24 @@ fn test_transaction_mine() {
         let res = transaction.validate_coinbase();
         assert!(res.is_ok());
 
-        let res = transaction.validate_mined();
+        let res = transaction.validate_mined(None);
+        assert!(res.is_ok());
+
+        let res = transaction.validate_mined(Some(difficulty));
         assert!(res.is_ok());
 
+        let res = transaction.validate_mined(Some(difficulty + 1));
+        assert!(res.is_err());
+
         let mut coinbase = transaction.coinbase.unwrap();
+        let mut proof = coinbase.proof.unwrap();
 
-        if coinbase.nonce == u64::max_value() {
-            coinbase.nonce = 0;
-        } else {
-            coinbase.nonce += 1;
-        }
+        proof.nonce = proof.nonce.wrapping_add(1);
+        coinbase.proof = Some(proof);
 
         transaction.coinbase = Some(coinbase);
 
-        let res = transaction.validate_mined();
+        let res = transaction.validate_mined(None);
         assert!(res.is_err());
     }
 }
@@ -1721,3 +2633,636 @@ fn test_transaction_storable() {
         assert!(!found);
     }
 }
+
+#[test]
+fn test_transaction_address_index() {
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let output = Output::new(&address, 0, &[]);
+
+    let transactions: Vec<Transaction> = (0..10)
+        .map(|_| {
+            let mut transaction = Transaction::new().unwrap();
+            transaction.stage = stage;
+            transaction.add_output(&output).unwrap();
+            transaction.update_id().unwrap();
+
+            transaction
+        })
+        .collect();
+
+    for (i, transaction) in transactions.iter().enumerate() {
+        let res = Transaction::count_by_output_address(&store, stage, &address, None);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), i as u32);
+
+        let res = Transaction::insert(&mut store, stage, transaction);
+        assert!(res.is_ok());
+
+        let res = Transaction::count_by_output_address(&store, stage, &address, None);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), i as u32 + 1);
+
+        let res = Transaction::count_by_input_address(&store, stage, &address, None);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 0);
+
+        let res = Transaction::query_by_output_address(&store, stage, &address, None, None);
+        assert!(res.is_ok());
+        assert!(res.unwrap().contains(transaction));
+    }
+
+    let res = Transaction::query_by_address(&store, stage, &address);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().len(), transactions.len());
+
+    let mut coinbase_tx = Transaction::new().unwrap();
+    coinbase_tx.stage = stage;
+    coinbase_tx.set_coinbase(&address, 1).unwrap();
+    coinbase_tx.update_id().unwrap();
+
+    let res = Transaction::insert(&mut store, stage, &coinbase_tx);
+    assert!(res.is_ok());
+
+    let res = Transaction::count_by_coinbase_address(&store, stage, &address, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let res = Transaction::query_by_address(&store, stage, &address);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().len(), transactions.len() + 1);
+
+    let res = Transaction::remove(&mut store, stage, &coinbase_tx.id);
+    assert!(res.is_ok());
+
+    let res = Transaction::count_by_coinbase_address(&store, stage, &address, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+
+    let transaction = &transactions[0];
+
+    let res = Transaction::remove(&mut store, stage, &transaction.id);
+    assert!(res.is_ok());
+
+    let res = Transaction::query_by_output_address(&store, stage, &address, None, None);
+    assert!(res.is_ok());
+    let found = res.unwrap();
+    assert_eq!(found.len(), transactions.len() - 1);
+    assert!(!found.contains(transaction));
+
+    let res = Transaction::clear(&mut store, stage);
+    assert!(res.is_ok());
+
+    let res = Transaction::count_by_output_address(&store, stage, &address, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[test]
+fn test_transaction_successor_index() {
+    use crate::signers::Signers;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let ancestor_id = Digest::random().unwrap();
+    let signers = Signers::new().unwrap();
+    let amount = 10;
+
+    let transactions: Vec<Transaction> = (0..10)
+        .map(|_| {
+            let account = Account::new(stage, &signers, amount, Some(ancestor_id)).unwrap();
+            let input = Input::new(&account, 1, amount).unwrap();
+
+            let mut transaction = Transaction::new().unwrap();
+            transaction.stage = stage;
+            transaction.add_input(&input).unwrap();
+
+            transaction
+        })
+        .collect();
+
+    for (i, transaction) in transactions.iter().enumerate() {
+        let res = Transaction::count_successors(&store, stage, &ancestor_id);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), i as u32);
+
+        let res = transaction.index_ancestors(&mut store, stage);
+        assert!(res.is_ok());
+
+        let res = Transaction::query_successors(&store, stage, &ancestor_id);
+        assert!(res.is_ok());
+        assert!(res.unwrap().contains(&transaction.id));
+
+        let res = Transaction::query_successors(&store, stage, &transaction.id);
+        assert!(res.is_ok());
+        assert!(res.unwrap().is_empty());
+    }
+
+    let transaction = &transactions[0];
+
+    let res = transaction.unindex_ancestors(&mut store, stage);
+    assert!(res.is_ok());
+
+    let res = Transaction::query_successors(&store, stage, &ancestor_id);
+    assert!(res.is_ok());
+    let found = res.unwrap();
+    assert_eq!(found.len(), transactions.len() - 1);
+    assert!(!found.contains(&transaction.id));
+
+    let res = Transaction::clear(&mut store, stage);
+    assert!(res.is_ok());
+
+    let res = Transaction::count_successors(&store, stage, &ancestor_id);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[test]
+fn test_transaction_expected_difficulty() {
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let config = ConsensusConfig::default();
+    let now = Timestamp::now();
+    let mining_time = 15;
+    let difficulty = 3;
+    let amount = 10;
+
+    let ancestors: Vec<Transaction> = (0..4)
+        .map(|i| {
+            let mut ancestor = Transaction::new().unwrap();
+            ancestor.stage = stage;
+            ancestor.time = Timestamp::from_i64(now.to_i64() - (4 - i) * mining_time).unwrap();
+            ancestor.set_coinbase(&address, difficulty).unwrap();
+            ancestor.mine().unwrap();
+            ancestor.update_id().unwrap();
+
+            assert!(ancestor.is_retargetable());
+
+            ancestor
+        })
+        .collect();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.time = now;
+
+    for ancestor in ancestors.iter() {
+        let mut signers = Signers::new().unwrap();
+        signers
+            .add(&Signer {
+                public_key: PublicKey::random().unwrap(),
+                weight: 1,
+            })
+            .unwrap();
+
+        let account = Account::new(stage, &signers, amount, Some(ancestor.id)).unwrap();
+        let input = Input::new(&account, 1, amount).unwrap();
+        transaction.add_input(&input).unwrap();
+    }
+
+    let res = transaction.expected_difficulty(&ancestors, &config);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), difficulty + 2);
+
+    let res = transaction.expected_difficulty(&ancestors[1..], &config);
+    assert!(res.is_err());
+
+    let mut slow = transaction.clone();
+    slow.time = Timestamp::from_i64(now.to_i64() + 240).unwrap();
+
+    let res = slow.expected_difficulty(&ancestors, &config);
+    assert!(res.is_ok());
+    assert!(res.unwrap() < difficulty + 2);
+
+    let mut unmined = ancestors.clone();
+
+    for ancestor in unmined.iter_mut() {
+        let mut coinbase = ancestor.coinbase.unwrap();
+        coinbase.clear();
+        ancestor.coinbase = Some(coinbase);
+    }
+
+    let res = transaction.expected_difficulty(&unmined, &config);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), MIN_DIFFICULTY);
+
+    let res = Transaction::new()
+        .unwrap()
+        .expected_difficulty(&[], &config);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), MIN_DIFFICULTY);
+}
+
+#[test]
+fn test_transaction_ancestors_distance() {
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+
+    let stage = Stage::random().unwrap();
+    let amount = 10;
+
+    let mut ancestor = Transaction::new().unwrap();
+    ancestor.stage = stage;
+    ancestor.distance = 3;
+    ancestor.update_id().unwrap();
+
+    let mut signers = Signers::new().unwrap();
+    signers
+        .add(&Signer {
+            public_key: PublicKey::random().unwrap(),
+            weight: 1,
+        })
+        .unwrap();
+
+    let account = Account::new(stage, &signers, amount, Some(ancestor.id)).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+
+    let input = Input::new(&account, ancestor.distance + 1, amount).unwrap();
+    transaction.add_input(&input).unwrap();
+
+    let res = transaction.validate_ancestors_distance(&[ancestor.clone()]);
+    assert!(res.is_ok());
+
+    let res = transaction.validate_ancestors_distance(&[]);
+    assert!(res.is_err());
+
+    let mut far = Transaction::new().unwrap();
+    far.stage = stage;
+
+    let input = Input::new(&account, ancestor.distance + 2, amount).unwrap();
+    far.add_input(&input).unwrap();
+
+    let res = far.validate_ancestors_distance(&[ancestor]);
+    assert!(res.is_err());
+
+    let mut orphan = Transaction::new().unwrap();
+    orphan.distance = 2;
+
+    let res = orphan.validate_ancestors_distance(&[]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_transaction_set_difficulty() {
+    let address = Address::random().unwrap();
+    let difficulty = 1;
+
+    let mut transaction = Transaction::new().unwrap();
+
+    let res = transaction.set_difficulty(difficulty);
+    assert!(res.is_err());
+
+    transaction.set_coinbase(&address, difficulty).unwrap();
+    transaction.update_id().unwrap();
+
+    let amount = transaction.coinbase_amount();
+    let id = transaction.id;
+
+    let res = transaction.set_difficulty(0);
+    assert!(res.is_err());
+
+    let res = transaction.set_difficulty(difficulty + 1);
+    assert!(res.is_ok());
+    assert!(transaction.coinbase_amount() < amount);
+    assert_ne!(transaction.id, id);
+
+    transaction.mine().unwrap();
+
+    let res = transaction.validate_mined(Some(difficulty));
+    assert!(res.is_ok());
+
+    let res = transaction.validate_mined(Some(difficulty + 1));
+    assert!(res.is_ok());
+
+    let res = transaction.validate_mined(Some(difficulty + 2));
+    assert!(res.is_err());
+
+    let res = transaction.set_difficulty(difficulty + 2);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_transaction_spent_rewards() {
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+
+    let stage = Stage::random().unwrap();
+    let difficulty = 1;
+
+    let secret_key = SecretKey::random().unwrap();
+    let mut signers = Signers::new().unwrap();
+    signers.threshold = 1;
+    signers
+        .add(&Signer {
+            public_key: secret_key.to_public(),
+            weight: 1,
+        })
+        .unwrap();
+
+    let address = signers.address;
+
+    let mut coinbase_tx = Transaction::new().unwrap();
+    coinbase_tx.stage = stage;
+    coinbase_tx.set_coinbase(&address, difficulty).unwrap();
+    coinbase_tx.mine().unwrap();
+    coinbase_tx.update_id().unwrap();
+
+    let mut funding_tx = Transaction::new().unwrap();
+    funding_tx.stage = stage;
+    funding_tx
+        .set_coinbase(&Address::random().unwrap(), difficulty)
+        .unwrap();
+    funding_tx.mine().unwrap();
+    funding_tx.update_id().unwrap();
+
+    let amount = coinbase_tx.coinbase_amount();
+    let account = Account::new(stage, &signers, amount, Some(coinbase_tx.id)).unwrap();
+    let input = Input::new(&account, 1, amount).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.add_input(&input).unwrap();
+
+    let res = transaction.spent_rewards(&[coinbase_tx.clone()]);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), vec![coinbase_tx.id].into_iter().collect());
+
+    let res = transaction.spent_rewards(&[]);
+    assert!(res.is_err());
+
+    let mut unmined = coinbase_tx.clone();
+    let mut coinbase = unmined.coinbase.unwrap();
+    coinbase.clear();
+    unmined.coinbase = Some(coinbase);
+
+    let res = transaction.spent_rewards(&[unmined]);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_empty());
+
+    let account = Account::new(stage, &signers, amount, Some(funding_tx.id)).unwrap();
+    let input = Input::new(&account, 1, amount).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction.add_input(&input).unwrap();
+
+    let res = transaction.spent_rewards(&[funding_tx]);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_empty());
+}
+
+#[test]
+fn test_transaction_limits() {
+    let custom_len = 10;
+    let mut transaction = Transaction::new().unwrap();
+
+    for _ in 0..3 {
+        let output = Output::random(custom_len).unwrap();
+        transaction.add_output(&output).unwrap();
+    }
+
+    let res = transaction.validate_max_limits();
+    assert!(res.is_ok());
+
+    let res = transaction.validate_limits(1 << 16, 1, 3);
+    assert!(res.is_ok());
+
+    let res = transaction.validate_limits(1 << 16, 1, 2);
+    match res {
+        Err(Error::TooLargeCollection { len: 3, max: 2 }) => {}
+        _ => panic!("expected TooLargeCollection"),
+    }
+
+    let size = transaction.to_bytes().unwrap().len() as u32;
+
+    let res = transaction.validate_limits(size, 1, 3);
+    assert!(res.is_ok());
+
+    let res = transaction.validate_limits(size - 1, 1, 3);
+    match res {
+        Err(Error::TooLong { .. }) => {}
+        _ => panic!("expected TooLong"),
+    }
+
+    let mut buf = transaction.to_bytes().unwrap();
+    buf.resize(ConsensusConfig::MAX_TRANSACTION_SIZE as usize + 1, 0);
+
+    let res = Transaction::from_bytes(&buf);
+    match res {
+        Err(Error::TooLong { .. }) => {}
+        _ => panic!("expected TooLong"),
+    }
+}
+
+#[test]
+fn test_transaction_stakes() {
+    use crate::account::Account;
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crate::transaction_builder::TransactionBuilder;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 16;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+
+    let secret_key = SecretKey::random().unwrap();
+    let signer = Signer {
+        public_key: secret_key.to_public(),
+        weight: 1,
+    };
+    let mut signers = Signers::new().unwrap();
+    signers.add(&signer).unwrap();
+    signers.set_threshold(1).unwrap();
+
+    let mut eve_transaction = Transaction::new_eve(stage, &Address::random().unwrap()).unwrap();
+    eve_transaction.mine().unwrap();
+    eve_transaction.update_id().unwrap();
+    Transaction::create(&mut store, stage, &eve_transaction).unwrap();
+
+    let account = Account::new(stage, &signers, 100, Some(eve_transaction.id)).unwrap();
+    let address = account.address();
+    let to = Address::random().unwrap();
+
+    let stake = Stake::new(stage, &address, 50, 1_000).unwrap();
+
+    let spend = |amount: u64, stake: Option<&Stake>| -> Result<Transaction> {
+        let mut transaction = TransactionBuilder::new(stage)
+            .and_then(|builder| builder.spend_from(&account, 100))
+            .and_then(|builder| builder.pay_to(&to, amount))
+            .map(|builder| builder.with_change(&address))
+            .and_then(|builder| builder.build())
+            .unwrap();
+
+        if let Some(stake) = stake {
+            transaction.add_stake(stake)?;
+        }
+
+        transaction.sign_input(&secret_key, &address)?;
+
+        Ok(transaction)
+    };
+
+    let res = spend(60, Some(&stake));
+    assert!(res.is_ok());
+    let transaction = res.unwrap();
+
+    let res = transaction.validate();
+    match res {
+        Err(Error::InvalidStake) => {}
+        _ => panic!("expected InvalidStake"),
+    }
+
+    let staking = spend(40, Some(&stake)).unwrap();
+    assert!(staking.validate().is_ok());
+
+    let mut unsigned = staking.clone();
+    unsigned.stakes.clear();
+    unsigned.update_id().unwrap();
+    assert!(unsigned.validate().is_err());
+
+    let res = Transaction::insert(&mut store, stage, &staking);
+    assert!(res.is_ok());
+    assert_eq!(Stake::get(&store, stage, &address).unwrap(), stake);
+
+    let transaction = spend(60, None).unwrap();
+    let res = Transaction::validate_single(&store, stage, &transaction);
+    match res {
+        Err(Error::LockedBalance) => {}
+        _ => panic!("expected LockedBalance"),
+    }
+
+    let transaction = spend(50, None).unwrap();
+    let res = Transaction::validate_single(&store, stage, &transaction);
+    assert!(res.is_ok());
+
+    let mut smaller = stake.clone();
+    smaller.amount -= 1;
+
+    let transaction = spend(50, Some(&smaller)).unwrap();
+    let res = Transaction::validate_single(&store, stage, &transaction);
+    match res {
+        Err(Error::LockedBalance) => {}
+        _ => panic!("expected LockedBalance"),
+    }
+
+    let res = Transaction::remove(&mut store, stage, &staking.id);
+    assert!(res.is_ok());
+    assert!(!Stake::lookup(&store, stage, &address).unwrap());
+
+    let transaction = spend(100, None).unwrap();
+    let res = Transaction::validate_single(&store, stage, &transaction);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_transaction_locked_spends() {
+    use crate::account::Account;
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crate::transaction_builder::TransactionBuilder;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 16;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+
+    let secret_key = SecretKey::random().unwrap();
+    let signer = Signer {
+        public_key: secret_key.to_public(),
+        weight: 1,
+    };
+    let mut signers = Signers::new().unwrap();
+    signers.add(&signer).unwrap();
+    signers.set_threshold(1).unwrap();
+
+    let mut eve_transaction = Transaction::new_eve(stage, &Address::random().unwrap()).unwrap();
+    eve_transaction.mine().unwrap();
+    eve_transaction.update_id().unwrap();
+    Transaction::create(&mut store, stage, &eve_transaction).unwrap();
+
+    let account = Account::new(stage, &signers, 100, Some(eve_transaction.id)).unwrap();
+    let address = account.address();
+    let to = Address::random().unwrap();
+
+    let locktime = Timestamp::now().after(1_000);
+
+    let fund = |locktime: Option<Timestamp>| -> Result<Transaction> {
+        let mut builder = TransactionBuilder::new(stage)?.spend_from(&account, 100)?;
+
+        if let Some(locktime) = locktime {
+            builder = builder.with_locktime(locktime)?;
+        }
+
+        let mut transaction = builder.with_change(&address).build()?;
+        transaction.sign_input(&secret_key, &address)?;
+
+        Ok(transaction)
+    };
+
+    let spend = |tx_id: Digest| -> Result<Transaction> {
+        // NB: the spender does not carry the locktime in the account
+        let funded = Account::new(stage, &signers, 100, Some(tx_id))?;
+
+        let mut transaction = TransactionBuilder::new(stage)?
+            .spend_from(&funded, 100)?
+            .pay_to(&to, 100)?
+            .build()?;
+        transaction.sign_input(&secret_key, &address)?;
+
+        Ok(transaction)
+    };
+
+    let locking = fund(Some(locktime)).unwrap();
+    assert!(locking.is_locked(locking.time));
+    assert!(!locking.is_locked(locktime));
+
+    let res = locking.validate();
+    assert!(res.is_ok());
+
+    let res = Transaction::insert(&mut store, stage, &locking);
+    assert!(res.is_ok());
+
+    let transaction = spend(locking.id).unwrap();
+    let input = &transaction.inputs[&address];
+    assert!(!input.account.is_locked(transaction.time));
+
+    let res = Transaction::validate_single(&store, stage, &transaction);
+    match res {
+        Err(Error::LockedAccount) => {}
+        _ => panic!("expected LockedAccount"),
+    }
+
+    let unlocked = fund(None).unwrap();
+    assert!(!unlocked.is_locked(unlocked.time));
+
+    let res = Transaction::insert(&mut store, stage, &unlocked);
+    assert!(res.is_ok());
+
+    let transaction = spend(unlocked.id).unwrap();
+    let res = transaction.validate_unlocked_spends(&store, stage);
+    assert!(res.is_ok());
+}
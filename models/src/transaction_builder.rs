@@ -0,0 +1,355 @@
+//! # Transaction Builder
+//!
+//! `transaction_builder` contains the `TransactionBuilder` type and functions.
+
+use crate::account::Account;
+use crate::address::Address;
+use crate::coin_selection::{Coin, CoinSelection};
+use crate::error::Error;
+use crate::input::Input;
+use crate::output::Output;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::stake::Stake;
+use crate::timestamp::Timestamp;
+use crate::transaction::Transaction;
+use config::consensus::ConsensusConfig;
+
+/// `TransactionBuilder` builds a `Transaction`, propagating the inputs distance
+/// to the `Transaction` and its `Coinbase`, checking the accounts and the balance
+/// and updating the `Transaction` id.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TransactionBuilder {
+    transaction: Transaction,
+    change: Option<Address>,
+}
+
+impl TransactionBuilder {
+    /// `new` creates a new `TransactionBuilder`.
+    pub fn new(stage: Stage) -> Result<TransactionBuilder> {
+        let mut transaction = Transaction::new()?;
+        transaction.stage = stage;
+        transaction.update_id()?;
+
+        let builder = TransactionBuilder {
+            transaction,
+            change: None,
+        };
+
+        Ok(builder)
+    }
+
+    /// `spend_from` spends an amount from an `Account`. Spending multiple times
+    /// from the same `Account` sums up the amounts.
+    pub fn spend_from(self, account: &Account, amount: u64) -> Result<TransactionBuilder> {
+        let input = Input::new(account, 1, amount)?;
+        self.spend_input(input)
+    }
+
+    /// `spend_selected` spends an amount from a list of `Account`s, chosen with
+    /// a `CoinSelection` strategy. The selected `Account`s are spent whole,
+    /// except for a change not above the dust threshold, and the change must be
+    /// paid back with `with_change`.
+    pub fn spend_selected(
+        mut self,
+        accounts: &[Account],
+        amount: u64,
+        strategy: CoinSelection,
+        dust: u64,
+    ) -> Result<TransactionBuilder> {
+        let coins: Vec<Coin> = accounts
+            .iter()
+            .map(|account| Coin::new(&account.address(), account.amount))
+            .collect();
+
+        let selection = strategy.select(&coins, amount, dust)?;
+
+        for input in selection.inputs.iter() {
+            let account = accounts
+                .iter()
+                .find(|account| account.address() == input.address)
+                .ok_or(Error::NotFound)?;
+
+            self = self.spend_from(account, input.amount)?;
+        }
+
+        Ok(self)
+    }
+
+    /// `spend_from_transaction` spends the output of a previous `Transaction`
+    /// to an `Account`, at the distance of the previous `Transaction`.
+    pub fn spend_from_transaction(
+        self,
+        account: &Account,
+        transaction: &Transaction,
+    ) -> Result<TransactionBuilder> {
+        let input = Input::from_transaction(account, transaction)?;
+        self.spend_input(input)
+    }
+
+    /// `spend_input` adds an `Input` to the `Transaction`, or adds its amount
+    /// to the `Input` of the same address.
+    fn spend_input(mut self, mut input: Input) -> Result<TransactionBuilder> {
+        if input.account.stage != self.transaction.stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        let address = input.address();
+
+        if self.transaction.lookup_input(&address) {
+            let prev_input = self.transaction.get_input(&address)?;
+
+            if prev_input.account != input.account {
+                let err = Error::InvalidAccount;
+                return Err(err);
+            }
+
+            if let Some(amount) = prev_input.amount.checked_add(input.amount) {
+                input.amount = amount;
+            } else {
+                let err = Error::InvalidAmount;
+                return Err(err);
+            }
+
+            if input.distance < prev_input.distance {
+                input.distance = prev_input.distance;
+            }
+
+            self.transaction.update_input(&input)?;
+        } else {
+            self.transaction.add_input(&input)?;
+        }
+
+        Ok(self)
+    }
+
+    /// `pay_to` pays an amount to an `Address`. Paying multiple times to the
+    /// same `Address` sums up the amounts.
+    pub fn pay_to(mut self, address: &Address, amount: u64) -> Result<TransactionBuilder> {
+        if self.transaction.lookup_output(address) {
+            let mut output = self.transaction.get_output(address)?;
+
+            if let Some(amount) = output.amount.checked_add(amount) {
+                output.amount = amount;
+            } else {
+                let err = Error::InvalidAmount;
+                return Err(err);
+            }
+
+            self.transaction.update_output(&output)?;
+        } else {
+            let output = Output::new(address, amount, &[]);
+            output.validate()?;
+
+            self.transaction.add_output(&output)?;
+        }
+
+        Ok(self)
+    }
+
+    /// `with_change` pays the inputs balance exceeding the outputs to an `Address`
+    /// when building the `Transaction`.
+    pub fn with_change(mut self, address: &Address) -> TransactionBuilder {
+        self.change = Some(address.to_owned());
+        self
+    }
+
+    /// `with_stake` stakes an amount of the balance of a spent `Account` until a
+    /// locktime. The staked amount has to be paid back to the `Account` address.
+    pub fn with_stake(mut self, stake: &Stake) -> Result<TransactionBuilder> {
+        self.transaction.add_stake(stake)?;
+        Ok(self)
+    }
+
+    /// `with_time` sets the `Transaction` time.
+    pub fn with_time(mut self, time: Timestamp) -> Result<TransactionBuilder> {
+        self.transaction.set_time(time)?;
+        Ok(self)
+    }
+
+    /// `with_locktime` sets the `Transaction` locktime.
+    pub fn with_locktime(mut self, locktime: Timestamp) -> Result<TransactionBuilder> {
+        self.transaction.set_locktime(locktime)?;
+        Ok(self)
+    }
+
+    /// `with_expiry` sets the `Transaction` expiry.
+    pub fn with_expiry(mut self, expiry: Timestamp) -> Result<TransactionBuilder> {
+        self.transaction.set_expiry(expiry)?;
+        Ok(self)
+    }
+
+    /// `with_coinbase` sets the `Transaction` `Coinbase` paying to an `Address`
+    /// at a given difficulty.
+    pub fn with_coinbase(
+        mut self,
+        address: &Address,
+        difficulty: u64,
+    ) -> Result<TransactionBuilder> {
+        self.transaction.set_coinbase(address, difficulty)?;
+        self.transaction.update_id()?;
+        Ok(self)
+    }
+
+    /// `with_expected_coinbase` sets the `Transaction` `Coinbase` paying to an
+    /// `Address` at the difficulty expected from its ancestors, which have to
+    /// be the ones of the `Input`s already spent. The time must be set first.
+    pub fn with_expected_coinbase(
+        self,
+        address: &Address,
+        ancestors: &[Transaction],
+        config: &ConsensusConfig,
+    ) -> Result<TransactionBuilder> {
+        let difficulty = self.transaction.expected_difficulty(ancestors, config)?;
+        self.with_coinbase(address, difficulty)
+    }
+
+    /// `build` builds the `Transaction`, paying the change if set and validating
+    /// the `Transaction` balance.
+    pub fn build(self) -> Result<Transaction> {
+        let input_balance = self.transaction.input_balance()?;
+        let output_balance = self.transaction.output_balance()?;
+
+        if input_balance < output_balance {
+            let err = Error::InvalidBalance;
+            return Err(err);
+        }
+
+        let change = input_balance - output_balance;
+
+        let mut builder = if change == 0 {
+            self
+        } else if let Some(address) = self.change {
+            self.pay_to(&address, change)?
+        } else {
+            let err = Error::InvalidBalance;
+            return Err(err);
+        };
+
+        builder.transaction.update_distance()?;
+        builder.transaction.update_id()?;
+        builder.transaction.validate()?;
+
+        Ok(builder.transaction)
+    }
+}
+
+#[test]
+fn test_transaction_builder() {
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crypto::ecc::ed25519::SecretKey;
+    use crypto::hash::Digest;
+
+    let stage = Stage::random().unwrap();
+
+    let secret_key = SecretKey::random().unwrap();
+    let public_key = secret_key.to_public();
+    let signer = Signer {
+        public_key,
+        weight: 1,
+    };
+    let mut signers = Signers::new().unwrap();
+    signers.add(&signer).unwrap();
+    signers.set_threshold(1).unwrap();
+
+    let account = Account::new(stage, &signers, 100, Some(Digest::default())).unwrap();
+    let address = account.address();
+    let to = Address::random().unwrap();
+
+    let res = TransactionBuilder::new(stage)
+        .unwrap()
+        .spend_from(&account, 101);
+    assert!(res.is_err());
+
+    let res = TransactionBuilder::new(stage)
+        .and_then(|builder| builder.spend_from(&account, 40))
+        .and_then(|builder| builder.spend_from(&account, 30))
+        .and_then(|builder| builder.pay_to(&to, 50))
+        .and_then(|builder| builder.build());
+    assert!(res.is_err());
+
+    let res = TransactionBuilder::new(stage)
+        .and_then(|builder| builder.spend_from(&account, 40))
+        .and_then(|builder| builder.spend_from(&account, 30))
+        .and_then(|builder| builder.pay_to(&to, 50))
+        .map(|builder| builder.with_change(&address))
+        .and_then(|builder| builder.with_coinbase(&to, 3))
+        .and_then(|builder| builder.build());
+    assert!(res.is_ok());
+
+    let transaction = res.unwrap();
+    assert!(transaction.validate().is_ok());
+    assert_eq!(transaction.inputs.len(), 1);
+    assert_eq!(transaction.input_balance().unwrap(), 70);
+    assert_eq!(transaction.get_output(&to).unwrap().amount, 50);
+    assert_eq!(transaction.get_output(&address).unwrap().amount, 20);
+    assert_eq!(transaction.coinbase.unwrap().distance, transaction.distance);
+
+    let config = ConsensusConfig::default();
+
+    let res = TransactionBuilder::new(stage)
+        .and_then(|builder| builder.spend_from(&account, 70))
+        .and_then(|builder| builder.with_expected_coinbase(&to, &[], &config));
+    assert!(res.is_err());
+
+    let res = TransactionBuilder::new(stage)
+        .and_then(|builder| builder.pay_to(&to, 50))
+        .and_then(|builder| builder.build());
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_transaction_builder_spend_selected() {
+    use crate::signer::Signer;
+    use crate::signers::Signers;
+    use crypto::ecc::ed25519::SecretKey;
+    use crypto::hash::Digest;
+
+    let stage = Stage::random().unwrap();
+
+    let accounts: Vec<Account> = [3000, 1200, 800]
+        .iter()
+        .map(|amount| {
+            let signer = Signer {
+                public_key: SecretKey::random().unwrap().to_public(),
+                weight: 1,
+            };
+            let mut signers = Signers::new().unwrap();
+            signers.add(&signer).unwrap();
+            signers.set_threshold(1).unwrap();
+
+            Account::new(stage, &signers, *amount, Some(Digest::default())).unwrap()
+        })
+        .collect();
+
+    let to = Address::random().unwrap();
+    let change = accounts[0].address();
+
+    let res = TransactionBuilder::new(stage).and_then(|builder| {
+        builder.spend_selected(&accounts, 6000, CoinSelection::LargestFirst, 0)
+    });
+    assert!(res.is_err());
+
+    let res = TransactionBuilder::new(stage)
+        .and_then(|builder| builder.spend_selected(&accounts, 3500, CoinSelection::LargestFirst, 0))
+        .and_then(|builder| builder.pay_to(&to, 3500))
+        .map(|builder| builder.with_change(&change))
+        .and_then(|builder| builder.build());
+    assert!(res.is_ok());
+
+    let transaction = res.unwrap();
+    assert_eq!(transaction.inputs.len(), 2);
+    assert_eq!(transaction.input_balance().unwrap(), 4200);
+    assert_eq!(transaction.get_output(&change).unwrap().amount, 700);
+
+    let res = TransactionBuilder::new(stage)
+        .and_then(|builder| {
+            builder.spend_selected(&accounts, 3800, CoinSelection::BranchAndBound, 0)
+        })
+        .and_then(|builder| builder.pay_to(&to, 3800))
+        .and_then(|builder| builder.build());
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().inputs.len(), 2);
+}
@@ -0,0 +1,152 @@
+//! # Transaction Proof
+//!
+//! `transaction_proof` contains the `TransactionProof` type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::transaction::Transaction;
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+
+/// `TransactionProof` is the proof of a `Transaction` served to light clients.
+/// It contains the path of ancestors linking the `Transaction` to the eve
+/// `Transaction` checkpoint, and the acceptance evidence of the serving node.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct TransactionProof {
+    pub tx_id: Digest,
+    pub checkpoint: Digest,
+    pub path: Vec<Transaction>,
+    pub confidence: u64,
+    pub accepted: bool,
+}
+
+impl TransactionProof {
+    /// `MAX_PATH_LEN` is the maximum number of `Transaction`s in the path
+    /// of a `TransactionProof`.
+    pub const MAX_PATH_LEN: usize = 1024;
+
+    /// `new` creates a new `TransactionProof`. The path starts with the proven
+    /// `Transaction` and ends with the eve `Transaction`.
+    pub fn new(path: &[Transaction], confidence: u64, accepted: bool) -> Result<TransactionProof> {
+        let tx_id = path.first().map(|tx| tx.id).unwrap_or_default();
+        let checkpoint = path.last().map(|tx| tx.id).unwrap_or_default();
+
+        let proof = TransactionProof {
+            tx_id,
+            checkpoint,
+            path: path.to_owned(),
+            confidence,
+            accepted,
+        };
+
+        proof.validate()?;
+
+        Ok(proof)
+    }
+
+    /// `validate` validates the `TransactionProof` path: each `Transaction`
+    /// has to be valid and an ancestor of the previous one, and the last one
+    /// has to be the checkpoint eve `Transaction`.
+    pub fn validate(&self) -> Result<()> {
+        if self.path.is_empty() || self.path.len() > Self::MAX_PATH_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if self.path[0].id != self.tx_id {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        for (i, transaction) in self.path.iter().enumerate() {
+            transaction.validate()?;
+
+            if i > 0 && !self.path[i - 1].ancestors()?.contains(&transaction.id) {
+                let err = Error::InvalidTransaction;
+                return Err(err);
+            }
+        }
+
+        let last = &self.path[self.path.len() - 1];
+
+        if last.id != self.checkpoint || !last.is_eve()? {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `verify` verifies the `TransactionProof` against a checkpoint eve `Transaction` id.
+    pub fn verify(&self, checkpoint: &Digest) -> Result<()> {
+        if &self.checkpoint != checkpoint {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        self.validate()
+    }
+
+    /// `to_bytes` converts the `TransactionProof` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `TransactionProof`.
+    pub fn from_bytes(b: &[u8]) -> Result<TransactionProof> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `TransactionProof` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `TransactionProof`.
+    pub fn from_json(s: &str) -> Result<TransactionProof> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_transaction_proof() {
+    use crate::address::Address;
+    use crate::stage::Stage;
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+
+    let mut eve_transaction = Transaction::new_eve(stage, &address).unwrap();
+    eve_transaction.mine().unwrap();
+    eve_transaction.update_id().unwrap();
+
+    let res = TransactionProof::new(&[], 0, false);
+    assert!(res.is_err());
+
+    let res = TransactionProof::new(&[eve_transaction.clone()], 1, true);
+    assert!(res.is_ok());
+
+    let proof = res.unwrap();
+    assert_eq!(proof.tx_id, eve_transaction.id);
+    assert_eq!(proof.checkpoint, eve_transaction.id);
+
+    let res = proof.verify(&eve_transaction.id);
+    assert!(res.is_ok());
+
+    let res = proof.verify(&Digest::default());
+    assert!(res.is_err());
+
+    let mut invalid_proof = proof.clone();
+    invalid_proof.path.push(eve_transaction);
+
+    let res = invalid_proof.validate();
+    assert!(res.is_err());
+
+    let cbor = proof.to_bytes().unwrap();
+    assert_eq!(TransactionProof::from_bytes(&cbor).unwrap(), proof);
+
+    let json = proof.to_json().unwrap();
+    assert_eq!(TransactionProof::from_json(&json).unwrap(), proof);
+}
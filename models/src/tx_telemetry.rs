@@ -0,0 +1,632 @@
+//! # Transaction Telemetry
+//!
+//! `tx_telemetry` contains the type used to record the consensus telemetry of a
+//! transaction, used to diagnose a slow convergence of the consensus.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `ChitSample` is the outcome of a single consensus query of a transaction.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct ChitSample {
+    pub time: Timestamp,
+    pub chit_sum: u32,
+    pub chit: bool,
+    pub confidence: u64,
+}
+
+/// `TxTelemetry` is the consensus telemetry of a transaction: when it was first seen,
+/// the queries issued for it, the history of their chit sums, the re-queries scheduled
+/// while it stalled and when it was accepted or given up.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct TxTelemetry {
+    pub id: Digest,
+    pub stage: Stage,
+    pub first_seen: Timestamp,
+    pub queries: u32,
+    pub samples: Vec<ChitSample>,
+    pub accepted_at: Option<Timestamp>,
+    #[serde(default)]
+    pub requeries: u32,
+    #[serde(default)]
+    pub last_requery: Option<Timestamp>,
+    #[serde(default)]
+    pub stalled_at: Option<Timestamp>,
+}
+
+impl TxTelemetry {
+    /// `MAX_SAMPLES` is the maximum number of `ChitSample`s kept by a `TxTelemetry`.
+    /// Older samples are dropped first.
+    pub const MAX_SAMPLES: usize = 128;
+
+    /// `new` creates a new `TxTelemetry` of a transaction first seen now.
+    pub fn new(stage: Stage, id: Digest) -> TxTelemetry {
+        TxTelemetry {
+            id,
+            stage,
+            first_seen: Timestamp::now(),
+            ..Default::default()
+        }
+    }
+
+    /// `record_query` records the outcome of a consensus query of the transaction.
+    pub fn record_query(&mut self, chit_sum: u32, chit: bool, confidence: u64) {
+        self.queries += 1;
+
+        let sample = ChitSample {
+            time: Timestamp::now(),
+            chit_sum,
+            chit,
+            confidence,
+        };
+
+        self.samples.push(sample);
+
+        if self.samples.len() > Self::MAX_SAMPLES {
+            let excess = self.samples.len() - Self::MAX_SAMPLES;
+            self.samples.drain(..excess);
+        }
+    }
+
+    /// `set_accepted` records the acceptance of the transaction, if not already recorded.
+    pub fn set_accepted(&mut self) {
+        if self.accepted_at.is_none() {
+            self.accepted_at = Some(Timestamp::now());
+        }
+    }
+
+    /// `last_activity` returns the time of the last progress of the transaction:
+    /// its last query, its last re-query or its first sight.
+    pub fn last_activity(&self) -> Timestamp {
+        let mut last_time = self.first_seen;
+
+        if let Some(sample) = self.samples.last() {
+            last_time = last_time.max(sample.time);
+        }
+
+        if let Some(requery_time) = self.last_requery {
+            last_time = last_time.max(requery_time);
+        }
+
+        last_time
+    }
+
+    /// `is_stalled` returns if the transaction, neither accepted nor given up,
+    /// made no progress in the last `secs` seconds before a time.
+    pub fn is_stalled(&self, time: Timestamp, secs: u64) -> bool {
+        !self.is_accepted() && !self.is_given_up() && time.diff(self.last_activity()) >= secs as i64
+    }
+
+    /// `record_requery` records a re-query of the stalled transaction.
+    pub fn record_requery(&mut self) {
+        self.requeries += 1;
+        self.last_requery = Some(Timestamp::now());
+    }
+
+    /// `set_stalled` records that the re-queries of the transaction were given up,
+    /// if not already recorded.
+    pub fn set_stalled(&mut self) {
+        if self.stalled_at.is_none() {
+            self.stalled_at = Some(Timestamp::now());
+        }
+    }
+
+    /// `is_given_up` returns if the re-queries of the transaction were given up.
+    pub fn is_given_up(&self) -> bool {
+        self.stalled_at.is_some()
+    }
+
+    /// `is_accepted` returns if the transaction acceptance has been recorded.
+    pub fn is_accepted(&self) -> bool {
+        self.accepted_at.is_some()
+    }
+
+    /// `time_to_accept` returns the seconds elapsed from the first sight of the
+    /// transaction to its acceptance, if accepted.
+    pub fn time_to_accept(&self) -> Option<i64> {
+        self.accepted_at.map(|time| time.diff(self.first_seen))
+    }
+
+    /// `validate` validates the `TxTelemetry`.
+    pub fn validate(&self) -> Result<()> {
+        if self.samples.len() > Self::MAX_SAMPLES {
+            let err = Error::TooLargeCollection {
+                len: self.samples.len() as u64,
+                max: Self::MAX_SAMPLES as u64,
+            };
+            return Err(err);
+        }
+
+        if self.samples.len() > self.queries as usize {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let mut last_time = self.first_seen;
+
+        for sample in self.samples.iter() {
+            if sample.time < last_time {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+
+            last_time = sample.time;
+        }
+
+        if let Some(accepted_at) = self.accepted_at {
+            if accepted_at < self.first_seen {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+        }
+
+        if let Some(last_requery) = self.last_requery {
+            if last_requery < self.first_seen {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+        }
+
+        if let Some(stalled_at) = self.stalled_at {
+            if stalled_at < self.first_seen {
+                let err = Error::InvalidTimestamp;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `TxTelemetry` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `TxTelemetry`.
+    pub fn from_bytes(b: &[u8]) -> Result<TxTelemetry> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `TxTelemetry` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `TxTelemetry`.
+    pub fn from_json(s: &str) -> Result<TxTelemetry> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for TxTelemetry {
+    const KEY_PREFIX: u8 = 12;
+
+    type Key = Digest;
+
+    fn key(&self) -> Self::Key {
+        self.id
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let telemetry = TxTelemetry::from_bytes(&value)?;
+            if telemetry.first_seen < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_tx_telemetry_ops() {
+    let stage = Stage::random().unwrap();
+    let id = Digest::random().unwrap();
+    let mut telemetry = TxTelemetry::new(stage, id);
+
+    let res = telemetry.validate();
+    assert!(res.is_ok());
+    assert!(!telemetry.is_accepted());
+    assert_eq!(telemetry.time_to_accept(), None);
+
+    let count = TxTelemetry::MAX_SAMPLES as u32 + 10;
+
+    for i in 0..count {
+        telemetry.record_query(i, true, i as u64);
+    }
+
+    assert_eq!(telemetry.queries, count);
+    assert_eq!(telemetry.samples.len(), TxTelemetry::MAX_SAMPLES);
+    assert_eq!(telemetry.samples[0].chit_sum, 10);
+
+    let res = telemetry.validate();
+    assert!(res.is_ok());
+
+    telemetry.set_accepted();
+    assert!(telemetry.is_accepted());
+
+    let accepted_at = telemetry.accepted_at;
+    telemetry.set_accepted();
+    assert_eq!(telemetry.accepted_at, accepted_at);
+
+    let res = telemetry.time_to_accept();
+    assert!(res.is_some());
+    assert!(res.unwrap() >= 0);
+
+    telemetry.samples[0].time = Timestamp::min_value();
+    let res = telemetry.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_tx_telemetry_stalled() {
+    let stage = Stage::random().unwrap();
+    let id = Digest::random().unwrap();
+    let mut telemetry = TxTelemetry::new(stage, id);
+
+    let now = Timestamp::now();
+    assert_eq!(telemetry.last_activity(), telemetry.first_seen);
+    assert!(telemetry.is_stalled(now, 0));
+    assert!(!telemetry.is_stalled(now, 60));
+
+    telemetry.record_requery();
+    assert_eq!(telemetry.requeries, 1);
+    assert!(telemetry.last_requery.is_some());
+    assert_eq!(telemetry.last_activity(), telemetry.last_requery.unwrap());
+
+    let res = telemetry.validate();
+    assert!(res.is_ok());
+
+    telemetry.set_stalled();
+    assert!(telemetry.is_given_up());
+    assert!(!telemetry.is_stalled(now, 0));
+
+    let stalled_at = telemetry.stalled_at;
+    telemetry.set_stalled();
+    assert_eq!(telemetry.stalled_at, stalled_at);
+
+    telemetry.stalled_at = Some(Timestamp::min_value());
+    let res = telemetry.validate();
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_tx_telemetry_serialize_bytes() {
+    let telemetry_a = TxTelemetry::default();
+
+    let res = telemetry_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = TxTelemetry::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let telemetry_b = res.unwrap();
+
+    assert_eq!(telemetry_a, telemetry_b)
+}
+
+#[test]
+fn test_tx_telemetry_serialize_json() {
+    let telemetry_a = TxTelemetry::default();
+
+    let res = telemetry_a.to_json();
+    assert!(res.is_ok());
+    let json = res.unwrap();
+
+    let res = TxTelemetry::from_json(&json);
+    assert!(res.is_ok());
+    let telemetry_b = res.unwrap();
+
+    assert_eq!(telemetry_a, telemetry_b)
+}
+
+#[test]
+fn test_tx_telemetry_storable() {
+    use store::backend::BTreeStore;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let id = Digest::random().unwrap();
+    let mut telemetry = TxTelemetry::new(stage, id);
+
+    let res = TxTelemetry::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = TxTelemetry::get(&store, stage, &id);
+    assert!(res.is_err());
+
+    let res = TxTelemetry::insert(&mut store, stage, &telemetry);
+    assert!(res.is_ok());
+
+    let res = TxTelemetry::count(&store, stage, None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    telemetry.record_query(1, false, 0);
+
+    let res = TxTelemetry::update(&mut store, stage, &telemetry);
+    assert!(res.is_ok());
+
+    let res = TxTelemetry::get(&store, stage, &id);
+    assert!(res.is_ok());
+    assert_eq!(&res.unwrap(), &telemetry);
+
+    let res = <TxTelemetry as Storable<BTreeStore>>::cleanup(&mut store, stage, None);
+    assert!(res.is_ok());
+
+    let res = TxTelemetry::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    let min_time = Timestamp::now().with_noise();
+    let res = <TxTelemetry as Storable<BTreeStore>>::cleanup(&mut store, stage, Some(min_time));
+    assert!(res.is_ok());
+
+    let res = TxTelemetry::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = TxTelemetry::insert(&mut store, stage, &telemetry);
+    assert!(res.is_ok());
+
+    let res = <TxTelemetry as Storable<BTreeStore>>::clear(&mut store, stage);
+    assert!(res.is_ok());
+
+    let res = TxTelemetry::lookup(&store, stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
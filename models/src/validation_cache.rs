@@ -0,0 +1,266 @@
+//! # Validation Cache
+//!
+//! `validation_cache` contains the `Transaction` validation cache type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::transaction::Transaction;
+use crypto::hash::{Blake512Hasher, Digest};
+use std::collections::{BTreeMap, VecDeque};
+
+/// `ValidationEntry` is the entry of a validated `Transaction` in the `ValidationCache`.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Default, Debug)]
+pub struct ValidationEntry {
+    pub digest: Digest,
+    pub valid: bool,
+    pub fully_signed: bool,
+    pub mined: bool,
+}
+
+impl ValidationEntry {
+    /// `new` creates a new `ValidationEntry`.
+    pub fn new(digest: Digest) -> ValidationEntry {
+        ValidationEntry {
+            digest,
+            valid: false,
+            fully_signed: false,
+            mined: false,
+        }
+    }
+}
+
+/// `ValidationCache` memoizes the successful validations of `Transaction`s.
+/// The entries are keyed by `Transaction` id and digest, so that a mutated
+/// `Transaction` is always validated again.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ValidationCache {
+    pub capacity: usize,
+    entries: BTreeMap<Digest, ValidationEntry>,
+    order: VecDeque<Digest>,
+}
+
+impl ValidationCache {
+    /// `DEFAULT_CAPACITY` is the default capacity of the `ValidationCache`.
+    pub const DEFAULT_CAPACITY: usize = 1 << 16;
+
+    /// `new` creates a new `ValidationCache`.
+    pub fn new(capacity: usize) -> ValidationCache {
+        ValidationCache {
+            capacity,
+            entries: BTreeMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// `len` returns the number of entries of the `ValidationCache`.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `is_empty` returns if the `ValidationCache` is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// `transaction_digest` returns the digest of a `Transaction`.
+    pub fn transaction_digest(transaction: &Transaction) -> Result<Digest> {
+        let buf = transaction.to_bytes()?;
+        let digest = Blake512Hasher::hash(&buf);
+        Ok(digest)
+    }
+
+    /// `lookup` returns the entry of a `Transaction` if cached and unchanged.
+    pub fn lookup(&self, transaction: &Transaction) -> Result<Option<ValidationEntry>> {
+        if let Some(entry) = self.entries.get(&transaction.id) {
+            if entry.digest == Self::transaction_digest(transaction)? {
+                return Ok(Some(*entry));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// `entry` returns the up-to-date entry of a `Transaction`, invalidating the
+    /// stale one if the `Transaction` changed.
+    fn entry(&mut self, transaction: &Transaction) -> Result<ValidationEntry> {
+        let digest = Self::transaction_digest(transaction)?;
+
+        match self.entries.get(&transaction.id) {
+            Some(entry) if entry.digest == digest => Ok(*entry),
+            Some(_) => {
+                self.invalidate(&transaction.id);
+                Ok(ValidationEntry::new(digest))
+            }
+            None => Ok(ValidationEntry::new(digest)),
+        }
+    }
+
+    /// `set_entry` sets the entry of a `Transaction`, evicting the oldest entries
+    /// if the `ValidationCache` is full.
+    fn set_entry(&mut self, id: Digest, entry: ValidationEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.insert(id, entry).is_none() {
+            self.order.push_back(id);
+        }
+
+        while self.entries.len() > self.capacity {
+            if let Some(old_id) = self.order.pop_front() {
+                self.entries.remove(&old_id);
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// `validate` validates a `Transaction`, memoizing the result.
+    pub fn validate(&mut self, transaction: &Transaction) -> Result<()> {
+        let mut entry = self.entry(transaction)?;
+
+        if !entry.valid {
+            transaction.validate()?;
+            entry.valid = true;
+            self.set_entry(transaction.id, entry);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_fully_signed` validates a fully signed `Transaction`, memoizing the result.
+    pub fn validate_fully_signed(&mut self, transaction: &Transaction) -> Result<()> {
+        let mut entry = self.entry(transaction)?;
+
+        if !entry.fully_signed {
+            transaction.validate_fully_signed()?;
+            entry.valid = true;
+            entry.fully_signed = true;
+            self.set_entry(transaction.id, entry);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_mined` validates the mining of a `Transaction`, memoizing the result.
+    /// The minimum difficulty, if any, depends on the ancestors and is always checked.
+    pub fn validate_mined(
+        &mut self,
+        transaction: &Transaction,
+        difficulty: Option<u64>,
+    ) -> Result<()> {
+        let mut entry = self.entry(transaction)?;
+
+        if let Some(difficulty) = difficulty {
+            if transaction
+                .coinbase
+                .map_or(true, |coinbase| coinbase.difficulty < difficulty)
+            {
+                let err = Error::InvalidDifficulty;
+                return Err(err);
+            }
+        }
+
+        if !entry.mined {
+            transaction.validate_mined(None)?;
+            entry.mined = true;
+            self.set_entry(transaction.id, entry);
+        }
+
+        Ok(())
+    }
+
+    /// `invalidate` removes a `Transaction` entry from the `ValidationCache`.
+    pub fn invalidate(&mut self, id: &Digest) {
+        if self.entries.remove(id).is_some() {
+            self.order.retain(|entry_id| entry_id != id);
+        }
+    }
+
+    /// `clear` clears the `ValidationCache`.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+impl Default for ValidationCache {
+    fn default() -> ValidationCache {
+        ValidationCache::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+#[test]
+fn test_validation_cache_validate() {
+    let mut cache = ValidationCache::new(ValidationCache::DEFAULT_CAPACITY);
+    assert!(cache.is_empty());
+
+    let mut transaction = Transaction::new().unwrap();
+
+    let res = cache.lookup(&transaction);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = cache.validate(&transaction);
+    assert!(res.is_ok());
+    assert_eq!(cache.len(), 1);
+
+    let res = cache.lookup(&transaction);
+    assert!(res.is_ok());
+    let entry = res.unwrap().unwrap();
+    assert!(entry.valid);
+    assert!(!entry.fully_signed);
+    assert!(!entry.mined);
+
+    transaction.nonce += 1;
+
+    let res = cache.lookup(&transaction);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = cache.validate(&transaction);
+    assert!(res.is_err());
+    assert!(cache.is_empty());
+
+    transaction.update_id().unwrap();
+
+    let res = cache.validate(&transaction);
+    assert!(res.is_ok());
+    assert_eq!(cache.len(), 1);
+
+    cache.invalidate(&transaction.id);
+    assert!(cache.is_empty());
+
+    let res = cache.lookup(&transaction);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+}
+
+#[test]
+fn test_validation_cache_capacity() {
+    let capacity = 4;
+    let mut cache = ValidationCache::new(capacity);
+
+    let transactions: Vec<Transaction> = (0..capacity * 2)
+        .map(|_| Transaction::new().unwrap())
+        .collect();
+
+    for transaction in &transactions {
+        let res = cache.validate(transaction);
+        assert!(res.is_ok());
+        assert!(cache.len() <= capacity);
+    }
+
+    assert_eq!(cache.len(), capacity);
+
+    let res = cache.lookup(&transactions[0]);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = cache.lookup(&transactions[transactions.len() - 1]);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_some());
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
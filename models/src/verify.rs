@@ -0,0 +1,261 @@
+//! # Verify
+//!
+//! `verify` contains the `Store` verifiers of the models, used to check and
+//! repair the `Store` at startup.
+
+use crate::account::Account;
+use crate::conflict_set::ConflictSet;
+use crate::consensus_message::ConsensusMessage;
+use crate::consensus_params::ConsensusParams;
+use crate::consensus_state::ConsensusState;
+use crate::contact::Contact;
+use crate::inbox::Inbox;
+use crate::node::Node;
+use crate::node_ban::NodeBan;
+use crate::node_reputation::NodeReputation;
+use crate::outbox::Outbox;
+use crate::stage::Stage;
+use crate::stake::Stake;
+use crate::traits::Storable;
+use crate::transaction::Transaction;
+use crate::tx_telemetry::TxTelemetry;
+use crate::wallet::Wallet;
+use crate::watch::Watch;
+use crypto::hash::Digest;
+use serde::de::DeserializeOwned;
+use serde_cbor;
+use std::marker::PhantomData;
+use store::error::Error as StoreError;
+use store::index::{next_key, Index, StoreIndex};
+use store::result::Result as StoreResult;
+use store::traits::Store;
+use store::verify::{Fault, Verifier};
+
+/// `StorableVerifier` is the `Verifier` of the instances of a `Storable` model.
+pub struct StorableVerifier<T> {
+    name: String,
+    stage: Stage,
+    model: PhantomData<T>,
+}
+
+impl<T> StorableVerifier<T> {
+    /// `new` creates a new `StorableVerifier`.
+    pub fn new(name: &str, stage: Stage) -> StorableVerifier<T> {
+        StorableVerifier {
+            name: name.into(),
+            stage,
+            model: PhantomData,
+        }
+    }
+}
+
+impl<S, T> Verifier<S> for StorableVerifier<T>
+where
+    S: Store,
+    T: Storable<S> + DeserializeOwned,
+{
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn range(&self) -> (Vec<u8>, Option<Vec<u8>>) {
+        let (from, to) = <T as Storable<S>>::stage_range(self.stage);
+        (from, Some(to))
+    }
+
+    fn verify(&self, store: &S, key: &[u8], value: &[u8]) -> StoreResult<Option<Fault>> {
+        let value: T = match serde_cbor::from_slice(value) {
+            Ok(value) => value,
+            Err(_) => return Ok(Some(Fault::Corrupted)),
+        };
+
+        match T::key_to_bytes(self.stage, &value.key()) {
+            Ok(ref value_key) if value_key.as_slice() == key => {}
+            _ => return Ok(Some(Fault::Corrupted)),
+        }
+
+        if T::validate_single(store, self.stage, &value).is_err() {
+            return Ok(Some(Fault::Invalid));
+        }
+
+        Ok(None)
+    }
+
+    fn verify_all(&self, store: &S) -> StoreResult<Option<String>> {
+        let res = T::validate_all(store, self.stage)
+            .err()
+            .map(|err| format!("{}", err));
+
+        Ok(res)
+    }
+}
+
+/// `TransactionIndexVerifier` is the `Verifier` of the entries of a `Transaction` index,
+/// finding the entries of the `Transaction`s missing from the `Store`.
+pub struct TransactionIndexVerifier {
+    name: String,
+    stage: Stage,
+    index: StoreIndex,
+}
+
+impl TransactionIndexVerifier {
+    /// `new` creates a new `TransactionIndexVerifier`.
+    pub fn new(name: &str, stage: Stage, index: StoreIndex) -> TransactionIndexVerifier {
+        TransactionIndexVerifier {
+            name: name.into(),
+            stage,
+            index,
+        }
+    }
+}
+
+impl<S: Store> Verifier<S> for TransactionIndexVerifier {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn range(&self) -> (Vec<u8>, Option<Vec<u8>>) {
+        let from = Index::<S>::prefix(&self.index);
+        let to = next_key(&from);
+        (from, to)
+    }
+
+    fn verify(&self, store: &S, key: &[u8], value: &[u8]) -> StoreResult<Option<Fault>> {
+        if !key.ends_with(value) {
+            return Ok(Some(Fault::Corrupted));
+        }
+
+        let id = match Digest::from_slice(value) {
+            Ok(id) => id,
+            Err(_) => return Ok(Some(Fault::Corrupted)),
+        };
+
+        let found = <Transaction as Storable<S>>::lookup(store, self.stage, &id).map_err(|e| {
+            let msg = format!("{}", e);
+            StoreError::Store { msg }
+        })?;
+
+        if found {
+            Ok(None)
+        } else {
+            Ok(Some(Fault::Orphaned))
+        }
+    }
+}
+
+/// `verifiers` returns the `Verifier`s of all the models of a stage, in repair order:
+/// the `Transaction` indexes are verified after the `Transaction`s, so that the
+/// entries of the removed `Transaction`s are found as orphaned.
+pub fn verifiers<S: Store>(stage: Stage) -> Vec<Box<dyn Verifier<S>>> {
+    vec![
+        Box::new(StorableVerifier::<Node>::new("node", stage)),
+        Box::new(StorableVerifier::<Wallet>::new("wallet", stage)),
+        Box::new(StorableVerifier::<Contact>::new("contact", stage)),
+        Box::new(StorableVerifier::<Account>::new("account", stage)),
+        Box::new(StorableVerifier::<Transaction>::new("transaction", stage)),
+        Box::new(StorableVerifier::<ConflictSet>::new("conflict set", stage)),
+        Box::new(StorableVerifier::<ConsensusState>::new(
+            "consensus state",
+            stage,
+        )),
+        Box::new(StorableVerifier::<ConsensusMessage>::new(
+            "consensus message",
+            stage,
+        )),
+        Box::new(StorableVerifier::<ConsensusParams>::new(
+            "consensus params",
+            stage,
+        )),
+        Box::new(StorableVerifier::<TxTelemetry>::new(
+            "transaction telemetry",
+            stage,
+        )),
+        Box::new(StorableVerifier::<NodeBan>::new("node ban", stage)),
+        Box::new(StorableVerifier::<NodeReputation>::new(
+            "node reputation",
+            stage,
+        )),
+        Box::new(StorableVerifier::<Outbox>::new("outbox", stage)),
+        Box::new(StorableVerifier::<Inbox>::new("inbox", stage)),
+        Box::new(StorableVerifier::<Watch>::new("watch", stage)),
+        Box::new(StorableVerifier::<Stake>::new("stake", stage)),
+        Box::new(TransactionIndexVerifier::new(
+            "transaction input address index",
+            stage,
+            Transaction::input_address_index(stage),
+        )),
+        Box::new(TransactionIndexVerifier::new(
+            "transaction output address index",
+            stage,
+            Transaction::output_address_index(stage),
+        )),
+        Box::new(TransactionIndexVerifier::new(
+            "transaction coinbase address index",
+            stage,
+            Transaction::coinbase_address_index(stage),
+        )),
+        Box::new(TransactionIndexVerifier::new(
+            "transaction mining time index",
+            stage,
+            Transaction::mining_time_index(stage),
+        )),
+        Box::new(TransactionIndexVerifier::new(
+            "transaction successor index",
+            stage,
+            Transaction::successor_index(stage),
+        )),
+    ]
+}
+
+#[test]
+fn test_verifiers() {
+    use crate::address::Address;
+    use crate::output::Output;
+    use store::backend::UnQLiteStore;
+    use store::memory::MemoryStoreFactory;
+    use store::StoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction
+        .add_output(&Output::new(&address, 0, &[]))
+        .unwrap();
+    transaction.update_id().unwrap();
+
+    Transaction::insert(&mut store, stage, &transaction).unwrap();
+
+    let res = StoreFactory::verify_and_repair(&mut store, &verifiers(stage), false);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert!(report.is_ok());
+    assert!(report.checked > 0);
+
+    let key =
+        <Transaction as Storable<UnQLiteStore>>::key_to_bytes(stage, &transaction.id).unwrap();
+    store.insert(&key, &[0, 1, 2], None).unwrap();
+
+    let res = StoreFactory::verify_and_repair(&mut store, &verifiers(stage), false);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert_eq!(report.faulty.len(), 1);
+    assert_eq!(report.faulty[0].fault, Fault::Corrupted);
+
+    let res = StoreFactory::verify_and_repair(&mut store, &verifiers(stage), true);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert_eq!(report.faulty[0].fault, Fault::Corrupted);
+    assert_eq!(report.faulty.len(), 2);
+    assert_eq!(report.faulty[1].fault, Fault::Orphaned);
+
+    let res = StoreFactory::verify_and_repair(&mut store, &verifiers(stage), false);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_ok());
+}
@@ -5,11 +5,13 @@
 use crate::error::Error;
 use crate::result::Result;
 use crate::signer::Signer;
+use crate::signers::Signers;
 use crate::stage::Stage;
 use crate::timestamp::Timestamp;
 use crate::traits::Storable;
+use crypto::cipher::SealedBox;
 use crypto::ecc::ed25519::{KeyPair, PublicKey, SecretKey, Signature, PUBLIC_KEY_LEN};
-use crypto::hash::{Blake512Hasher, Digest};
+use crypto::hash::{BalloonParams, Blake512Hasher, Digest};
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json;
@@ -18,6 +20,8 @@ use store::traits::Store;
 
 /// `Wallet` is the type used to represent an Alsacoin wallet
 /// of a user, wallet which is identified by a `PublicKey`.
+/// When the wallet is encrypted the secret key is kept in a `SealedBox`
+/// and the wallet has to be unlocked with its passphrase before signing.
 /// NB: ehrg on the use of vecs. It may not be a problem. It may.
 /// NB: good for a PoC, should put more thinking after though.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
@@ -27,9 +31,20 @@ pub struct Wallet {
     pub stage: Stage,
     pub time: Timestamp,
     pub checksum: Digest,
+    #[serde(default)]
+    pub sealed: Option<SealedBox>,
 }
 
 impl Wallet {
+    /// `KDF_S_COST` is the Balloon s_cost used to derive the encryption key.
+    pub const KDF_S_COST: u32 = 1 << 12;
+
+    /// `KDF_T_COST` is the Balloon t_cost used to derive the encryption key.
+    pub const KDF_T_COST: u32 = 3;
+
+    /// `KDF_DELTA` is the Balloon delta used to derive the encryption key.
+    pub const KDF_DELTA: u32 = 3;
+
     /// `new` creates a new `Wallet`.
     pub fn new(stage: Stage) -> Result<Wallet> {
         let keypair = KeyPair::new()?;
@@ -55,6 +70,7 @@ impl Wallet {
             stage,
             time,
             checksum,
+            sealed: None,
         };
 
         wallet.update_checksum()?;
@@ -80,8 +96,64 @@ impl Wallet {
         Ok(digest)
     }
 
+    /// `is_encrypted` returns if the `Wallet` secret key is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.sealed.is_some()
+    }
+
+    /// `encrypt` encrypts the `Wallet` secret key with a passphrase.
+    pub fn encrypt(&mut self, passphrase: &[u8]) -> Result<()> {
+        if self.is_encrypted() {
+            let err = Error::AlreadyEncrypted;
+            return Err(err);
+        }
+
+        self.validate()?;
+
+        let params = BalloonParams::new(Wallet::KDF_S_COST, Wallet::KDF_T_COST, Wallet::KDF_DELTA)?;
+        let sealed = SealedBox::seal(passphrase, params, &self.secret_key)?;
+
+        self.secret_key = Vec::new();
+        self.sealed = Some(sealed);
+
+        self.update_checksum()
+    }
+
+    /// `unlock` returns an in-memory copy of the encrypted `Wallet` with
+    /// the secret key decrypted with a passphrase. The copy should never be stored.
+    pub fn unlock(&self, passphrase: &[u8]) -> Result<Wallet> {
+        let sealed = if let Some(ref sealed) = self.sealed {
+            sealed
+        } else {
+            let err = Error::NotEncrypted;
+            return Err(err);
+        };
+
+        let secret_key = SecretKey::from_slice(&sealed.open(passphrase)?)?;
+
+        let mut wallet = self.clone();
+        wallet.secret_key = secret_key.to_vec();
+        wallet.sealed = None;
+        wallet.update_checksum()?;
+        wallet.validate()?;
+
+        Ok(wallet)
+    }
+
+    /// `decrypt` removes the encryption of the `Wallet` secret key.
+    pub fn decrypt(&mut self, passphrase: &[u8]) -> Result<()> {
+        *self = self.unlock(passphrase)?;
+
+        Ok(())
+    }
+
     /// `sign` signs a binary message with the `Wallet`.
     pub fn sign(&self, msg: &[u8]) -> Result<Signature> {
+        if self.is_encrypted() {
+            let err = Error::WalletLocked;
+            return Err(err);
+        }
+
         let public_key = PublicKey::from_slice(&self.public_key)?;
         let secret_key = SecretKey::from_slice(&self.secret_key)?;
 
@@ -97,15 +169,8 @@ impl Wallet {
     /// `validate_signature` validates a `Signature` against the `Wallet` and a binary message.
     pub fn validate_signature(&self, sig: &Signature, msg: &[u8]) -> Result<()> {
         let public_key = PublicKey::from_slice(&self.public_key)?;
-        let secret_key = SecretKey::from_slice(&self.secret_key)?;
 
-        let keypair = KeyPair {
-            public_key,
-            secret_key,
-        };
-        keypair.validate()?;
-
-        keypair.verify(sig, msg).map_err(|e| e.into())
+        public_key.verify(sig, msg).map_err(|e| e.into())
     }
 
     /// `to_signer` returns a `Wallet` `Signer`.
@@ -119,16 +184,35 @@ impl Wallet {
         Ok(signer)
     }
 
+    /// `to_signers` returns the single signer `Signers` of the `Wallet`.
+    pub fn to_signers(&self) -> Result<Signers> {
+        let mut signers = Signers::new()?;
+        signers.add(&self.to_signer(1)?)?;
+        signers.set_threshold(1)?;
+
+        Ok(signers)
+    }
+
     /// `validate` validates the `Wallet`.
     pub fn validate(&self) -> Result<()> {
         let public_key = PublicKey::from_slice(&self.public_key)?;
-        let secret_key = SecretKey::from_slice(&self.secret_key)?;
 
-        let keypair = KeyPair {
-            public_key,
-            secret_key,
-        };
-        keypair.validate()?;
+        if let Some(ref sealed) = self.sealed {
+            if !self.secret_key.is_empty() {
+                let err = Error::InvalidLength;
+                return Err(err);
+            }
+
+            sealed.validate()?;
+        } else {
+            let secret_key = SecretKey::from_slice(&self.secret_key)?;
+
+            let keypair = KeyPair {
+                public_key,
+                secret_key,
+            };
+            keypair.validate()?;
+        }
 
         self.time.validate()?;
 
@@ -188,7 +272,10 @@ impl<S: Store> Storable<S> for Wallet {
     }
 
     fn validate_all(store: &S, stage: Stage) -> Result<()> {
-        for value in Self::query(store, stage, None, None, None, None)? {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
             Self::validate_single(store, stage, &value)?;
         }
 
@@ -325,7 +412,9 @@ impl<S: Store> Storable<S> for Wallet {
         let key = <Self as Storable<S>>::key(value);
         let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
         let store_value = value.to_bytes()?;
-        store.insert(&store_key, &store_value).map_err(|e| e.into())
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
     }
 
     fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
@@ -401,14 +490,19 @@ impl<S: Store> Storable<S> for Wallet {
         let to = Some(_to.to_vec());
         let to = to.as_ref().map(|to| to.as_slice());
 
-        for value in store.query(from, to, None, None)? {
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(from, to)? {
             let wallet = Wallet::from_bytes(&value)?;
             if wallet.time < min_time {
-                let key = <Self as Storable<S>>::key_to_bytes(stage, &wallet.public_key)?;
-                store.remove(&key)?;
+                keys.push(key);
             }
         }
 
+        for key in keys {
+            store.remove(&key)?;
+        }
+
         Ok(())
     }
 
@@ -515,6 +609,58 @@ fn test_wallet_validate() {
     assert!(res.is_err());
 }
 
+#[test]
+fn test_wallet_encrypt() {
+    let stage = Stage::default();
+    let passphrase = b"passphrase";
+    let msg = b"message";
+
+    let plain = Wallet::new(stage).unwrap();
+    let mut wallet = plain.clone();
+
+    let res = wallet.unlock(passphrase);
+    assert!(res.is_err());
+
+    let res = wallet.encrypt(passphrase);
+    assert!(res.is_ok());
+    assert!(wallet.is_encrypted());
+    assert!(wallet.secret_key.is_empty());
+
+    let res = wallet.validate();
+    assert!(res.is_ok());
+
+    let res = wallet.to_signer(1);
+    assert!(res.is_ok());
+
+    let res = wallet.encrypt(passphrase);
+    assert!(res.is_err());
+
+    let res = wallet.sign(msg);
+    assert!(res.is_err());
+
+    let res = wallet.unlock(b"wrong passphrase");
+    assert!(res.is_err());
+
+    let res = wallet.unlock(passphrase);
+    assert!(res.is_ok());
+    let unlocked = res.unwrap();
+    assert!(!unlocked.is_encrypted());
+    assert_eq!(unlocked.secret_key, plain.secret_key);
+
+    let sig = unlocked.sign(msg).unwrap();
+    let res = wallet.validate_signature(&sig, msg);
+    assert!(res.is_ok());
+
+    let res = Wallet::from_bytes(&wallet.to_bytes().unwrap());
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), wallet);
+
+    let res = wallet.decrypt(passphrase);
+    assert!(res.is_ok());
+    assert!(!wallet.is_encrypted());
+    assert_eq!(wallet.secret_key, plain.secret_key);
+}
+
 #[test]
 fn test_wallet_serialize_bytes() {
     let stage = Stage::default();
@@ -0,0 +1,541 @@
+//! # Watch
+//!
+//! `watch` contains the `Watch` type and functions. The watches are the addresses
+//! whose accepted transactions trigger an action, such as the detection of the
+//! deposits of a merchant or an exchange.
+
+use crate::address::Address;
+use crate::error::Error;
+use crate::result::Result;
+use crate::stage::Stage;
+use crate::timestamp::Timestamp;
+use crate::traits::Storable;
+use crypto::hash::Digest;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use std::fmt;
+use store::traits::Store;
+
+/// `WatchAction` is the action triggered when an accepted `Transaction` touches
+/// a watched `Address`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum WatchAction {
+    /// The `Transaction` is logged by the node.
+    Log,
+    /// The `Transaction` is streamed to the gRPC event subscribers.
+    Event,
+    /// The `Transaction` is posted in JSON to an HTTP URL.
+    Webhook { url: String },
+}
+
+impl WatchAction {
+    /// `WEBHOOK_SCHEME` is the scheme of the `Webhook` URLs.
+    pub const WEBHOOK_SCHEME: &'static str = "http://";
+
+    /// `MAX_URL_LEN` is the maximum length in bytes of a `Webhook` URL.
+    pub const MAX_URL_LEN: usize = 256;
+
+    /// `parse` parses a `WatchAction` from a string: "log", "event" or the
+    /// URL of a webhook.
+    pub fn parse(s: &str) -> Result<WatchAction> {
+        let action = match s {
+            "log" => WatchAction::Log,
+            "event" => WatchAction::Event,
+            url if url.starts_with(Self::WEBHOOK_SCHEME) => {
+                WatchAction::Webhook { url: url.into() }
+            }
+            _ => {
+                let msg = format!("invalid watch action: {}", s);
+                let err = Error::Parse { msg };
+                return Err(err);
+            }
+        };
+
+        action.validate()?;
+
+        Ok(action)
+    }
+
+    /// `split_url` splits a `Webhook` URL in its host, with the port if any,
+    /// and its path.
+    pub fn split_url(url: &str) -> Result<(&str, &str)> {
+        if !url.starts_with(Self::WEBHOOK_SCHEME) {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        let rest = &url[Self::WEBHOOK_SCHEME.len()..];
+
+        let (host, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+
+        if host.is_empty() {
+            let err = Error::InvalidEncoding;
+            return Err(err);
+        }
+
+        Ok((host, path))
+    }
+
+    /// `validate` validates the `WatchAction`. The `Webhook` URLs have to be
+    /// plain HTTP URLs with a host.
+    pub fn validate(&self) -> Result<()> {
+        if let WatchAction::Webhook { url } = self {
+            if url.len() > Self::MAX_URL_LEN {
+                let err = Error::InvalidLength;
+                return Err(err);
+            }
+
+            if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                let err = Error::InvalidEncoding;
+                return Err(err);
+            }
+
+            WatchAction::split_url(url)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for WatchAction {
+    fn default() -> WatchAction {
+        WatchAction::Log
+    }
+}
+
+impl fmt::Display for WatchAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WatchAction::Log => write!(f, "log"),
+            WatchAction::Event => write!(f, "event"),
+            WatchAction::Webhook { url } => write!(f, "{}", url),
+        }
+    }
+}
+
+/// `Watch` is a watched `Address` of a `Stage`, with the action triggered by
+/// the accepted `Transaction`s touching it.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct Watch {
+    pub address: Address,
+    pub stage: Stage,
+    pub action: WatchAction,
+    pub time: Timestamp,
+}
+
+impl Watch {
+    /// `new` creates a new `Watch` of an `Address`.
+    pub fn new(stage: Stage, address: &Address, action: &WatchAction) -> Result<Watch> {
+        let watch = Watch {
+            address: *address,
+            stage,
+            action: action.to_owned(),
+            time: Timestamp::now(),
+        };
+
+        watch.validate()?;
+
+        Ok(watch)
+    }
+
+    /// `watched` returns the `Watch`es of a set of `Address`es found in a `Store`.
+    pub fn watched<S: Store>(
+        store: &S,
+        stage: Stage,
+        addresses: &BTreeSet<Address>,
+    ) -> Result<Vec<Watch>> {
+        let mut watches = Vec::new();
+
+        for address in addresses {
+            if Watch::lookup(store, stage, address)? {
+                watches.push(Watch::get(store, stage, address)?);
+            }
+        }
+
+        Ok(watches)
+    }
+
+    /// `validate` validates the `Watch`.
+    pub fn validate(&self) -> Result<()> {
+        self.action.validate()?;
+
+        self.time.validate()
+    }
+
+    /// `to_bytes` converts the `Watch` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `Watch`.
+    pub fn from_bytes(b: &[u8]) -> Result<Watch> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Watch` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `Watch`.
+    pub fn from_json(s: &str) -> Result<Watch> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl<S: Store> Storable<S> for Watch {
+    const KEY_PREFIX: u8 = 20;
+
+    type Key = Address;
+
+    fn key(&self) -> Self::Key {
+        self.address
+    }
+
+    fn key_to_bytes(stage: Stage, key: &Self::Key) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.push(stage as u8);
+        buf.push(<Self as Storable<S>>::KEY_PREFIX);
+        buf.extend_from_slice(&key.to_bytes());
+        Ok(buf)
+    }
+
+    fn validate_single(_store: &S, stage: Stage, value: &Self) -> Result<()> {
+        if value.stage != stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        value.validate()
+    }
+
+    fn validate_all(store: &S, stage: Stage) -> Result<()> {
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        for (_, value) in store.iter(Some(&from), Some(&to))? {
+            let value = Self::from_bytes(&value)?;
+            Self::validate_single(store, stage, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn lookup(store: &S, stage: Stage, key: &Self::Key) -> Result<bool> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.lookup(&key).map_err(|e| e.into())
+    }
+
+    fn get(store: &S, stage: Stage, key: &Self::Key) -> Result<Self> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        let buf = store.get(&key)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn query(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.query(from, to, count, skip)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn sample(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        count: u32,
+    ) -> Result<BTreeSet<Self>> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        let values = store.sample(from, to, count)?;
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            let item = Self::from_bytes(&value)?;
+            items.insert(item);
+        }
+
+        Ok(items)
+    }
+
+    fn count(
+        store: &S,
+        stage: Stage,
+        from: Option<Self::Key>,
+        to: Option<Self::Key>,
+        skip: Option<u32>,
+    ) -> Result<u32> {
+        let from = if let Some(ref key) = from {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _from = Digest::default();
+            _from[0] = stage as u8;
+            _from[1] = <Self as Storable<S>>::KEY_PREFIX;
+            Some(_from.to_vec())
+        };
+
+        let to = if let Some(ref key) = to {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            Some(key)
+        } else {
+            let mut _to = Digest::default();
+            _to[0] = stage as u8;
+            _to[1] = <Self as Storable<S>>::KEY_PREFIX + 1;
+            Some(_to.to_vec())
+        };
+
+        let from = from.as_ref().map(|from| from.as_slice());
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(from, to, skip).map_err(|e| e.into())
+    }
+
+    fn insert(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store
+            .insert(&store_key, &store_value, None)
+            .map_err(|e| e.into())
+    }
+
+    fn create(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.create(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn update(store: &mut S, stage: Stage, value: &Self) -> Result<()> {
+        Self::validate_single(store, stage, value)?;
+
+        let key = <Self as Storable<S>>::key(value);
+        let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+        let store_value = value.to_bytes()?;
+        store.update(&store_key, &store_value).map_err(|e| e.into())
+    }
+
+    fn insert_batch(store: &mut S, stage: Stage, values: &BTreeSet<Self>) -> Result<()> {
+        let mut items = BTreeSet::new();
+
+        for value in values {
+            Self::validate_single(store, stage, value)?;
+
+            let key = <Self as Storable<S>>::key(value);
+            let store_key = <Self as Storable<S>>::key_to_bytes(stage, &key)?;
+            let store_value = value.to_bytes()?;
+            let item = (store_key, store_value);
+            items.insert(item);
+        }
+
+        let items: Vec<(&[u8], &[u8])> = items
+            .iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+
+        store.insert_batch(&items).map_err(|e| e.into())
+    }
+
+    fn remove(store: &mut S, stage: Stage, key: &Self::Key) -> Result<()> {
+        let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+        store.remove(&key).map_err(|e| e.into())
+    }
+
+    fn remove_batch(store: &mut S, stage: Stage, keys: &BTreeSet<Self::Key>) -> Result<()> {
+        let mut _keys = BTreeSet::new();
+        for key in keys {
+            let key = <Self as Storable<S>>::key_to_bytes(stage, key)?;
+            _keys.insert(key);
+        }
+
+        let keys: Vec<&[u8]> = _keys.iter().map(|k| k.as_slice()).collect();
+
+        store.remove_batch(&keys).map_err(|e| e.into())
+    }
+
+    fn cleanup(store: &mut S, stage: Stage, min_time: Option<Timestamp>) -> Result<()> {
+        let min_time = min_time.unwrap_or_default();
+        let (from, to) = <Self as Storable<S>>::stage_range(stage);
+
+        let mut keys = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), Some(&to))? {
+            let watch = Watch::from_bytes(&value)?;
+            if watch.time < min_time {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            store.remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(store: &mut S, stage: Stage) -> Result<()> {
+        let from = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX]);
+        let from = from.as_ref().map(|from| from.as_slice());
+
+        let to = Some(vec![stage as u8, <Self as Storable<S>>::KEY_PREFIX + 1]);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        store.remove_range(from, to, None).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_watch_action() {
+    assert_eq!(WatchAction::parse("log").unwrap(), WatchAction::Log);
+    assert_eq!(WatchAction::parse("event").unwrap(), WatchAction::Event);
+
+    let url = "http://127.0.0.1:8080/deposits";
+    let action = WatchAction::parse(url).unwrap();
+    assert_eq!(action, WatchAction::Webhook { url: url.into() });
+    assert_eq!(action.to_string(), url);
+
+    let res = WatchAction::split_url(url);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), ("127.0.0.1:8080", "/deposits"));
+
+    let res = WatchAction::split_url("http://localhost");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), ("localhost", "/"));
+
+    let long_url = format!("http://{}", "a".repeat(WatchAction::MAX_URL_LEN));
+
+    for s in &[
+        "",
+        "mail",
+        "https://example.com",
+        "http://",
+        "http:///path",
+        "http://a b",
+        long_url.as_str(),
+    ] {
+        let res = WatchAction::parse(s);
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_watch_serialize_bytes() {
+    let action = WatchAction::parse("http://127.0.0.1/").unwrap();
+    let watch_a = Watch::new(Stage::default(), &Address::default(), &action).unwrap();
+
+    let res = watch_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = Watch::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let watch_b = res.unwrap();
+
+    assert_eq!(watch_a, watch_b)
+}
+
+#[test]
+fn test_watch_storable() {
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let watch = Watch::new(stage, &address, &WatchAction::Event).unwrap();
+
+    let res = Watch::create(&mut store, stage, &watch);
+    assert!(res.is_ok());
+
+    let res = Watch::create(&mut store, stage, &watch);
+    assert!(res.is_err());
+
+    let res = Watch::get(&store, stage, &address);
+    assert!(res.is_ok());
+    assert_eq!(&res.unwrap(), &watch);
+
+    let mut addresses = BTreeSet::new();
+    addresses.insert(address);
+    addresses.insert(Address::random().unwrap());
+
+    let res = Watch::watched(&store, stage, &addresses);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), vec![watch]);
+
+    let res = Watch::count(&store, stage, None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let res = Watch::remove(&mut store, stage, &address);
+    assert!(res.is_ok());
+
+    let res = Watch::lookup(&store, stage, &address);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
@@ -0,0 +1,200 @@
+//! # Work
+//!
+//! `work` contains the mining work template type, handed to the remote miners
+//! so that they can mine a `Transaction` without receiving it.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::transaction::Transaction;
+use crypto::hash::balloon::BalloonParams;
+use crypto::hash::Digest;
+use mining::proof::Proof;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+
+/// `Work` is the mining template of a `Transaction`: the message to mine
+/// and the parameters and difficulty of its mining.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct Work {
+    pub tx_id: Digest,
+    pub params: BalloonParams,
+    pub difficulty: u64,
+    pub mining_message: Vec<u8>,
+}
+
+impl Work {
+    /// `MAX_DIFFICULTY` is the maximum difficulty of a `Work`.
+    pub const MAX_DIFFICULTY: u64 = 512;
+
+    /// `from_transaction` creates the `Work` of an unmined `Transaction`.
+    pub fn from_transaction(transaction: &Transaction) -> Result<Work> {
+        transaction.validate_coinbase()?;
+
+        if transaction.is_mined() {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        let coinbase = match transaction.coinbase {
+            Some(coinbase) => coinbase,
+            None => {
+                let err = Error::InvalidCoinbase;
+                return Err(err);
+            }
+        };
+
+        let msg = transaction.mining_message()?;
+
+        let work = Work {
+            tx_id: transaction.id,
+            params: coinbase.params,
+            difficulty: coinbase.difficulty,
+            mining_message: coinbase.mining_message(&msg)?,
+        };
+
+        work.validate()?;
+
+        Ok(work)
+    }
+
+    /// `mine` mines the `Work`, returning its `Proof`.
+    pub fn mine(&self) -> Result<Proof> {
+        self.validate()?;
+
+        Proof::mine(self.params, self.difficulty, &self.mining_message).map_err(|e| e.into())
+    }
+
+    /// `proof` returns the `Proof` of a solution of the `Work`, failing if
+    /// the solution is not valid.
+    pub fn proof(&self, nonce: u64, digest: Digest) -> Result<Proof> {
+        self.validate()?;
+
+        let proof = Proof::new(self.params, self.difficulty, nonce, digest)?;
+        proof.verify(&self.mining_message)?;
+
+        Ok(proof)
+    }
+
+    /// `apply` sets the `Proof` of a solution of the `Work` in the `Coinbase`
+    /// of its `Transaction`.
+    pub fn apply(&self, transaction: &mut Transaction, nonce: u64, digest: Digest) -> Result<()> {
+        if transaction.id != self.tx_id {
+            let err = Error::InvalidId;
+            return Err(err);
+        }
+
+        if &Work::from_transaction(transaction)? != self {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        let proof = self.proof(nonce, digest)?;
+
+        if let Some(mut coinbase) = transaction.coinbase {
+            coinbase.proof = Some(proof);
+            transaction.coinbase = Some(coinbase);
+        }
+
+        transaction.validate_mined(Some(self.difficulty))
+    }
+
+    /// `validate` validates the `Work`.
+    pub fn validate(&self) -> Result<()> {
+        self.params.validate()?;
+
+        if self.difficulty == 0 || self.difficulty > Self::MAX_DIFFICULTY {
+            let err = Error::InvalidDifficulty;
+            return Err(err);
+        }
+
+        if self.mining_message.is_empty() {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Work` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `Work`.
+    pub fn from_bytes(b: &[u8]) -> Result<Work> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `Work` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into a `Work`.
+    pub fn from_json(s: &str) -> Result<Work> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+#[test]
+fn test_work() {
+    use crate::address::Address;
+    use crypto::random::Random;
+
+    let mut transaction = Transaction::default();
+    let address = Address::random().unwrap();
+    let difficulty = Random::u64_range(1, 10).unwrap();
+
+    let res = Work::from_transaction(&transaction);
+    assert!(res.is_err());
+
+    transaction.set_coinbase(&address, difficulty).unwrap();
+
+    let res = Work::from_transaction(&transaction);
+    assert!(res.is_ok());
+
+    let work = res.unwrap();
+    assert_eq!(work.tx_id, transaction.id);
+    assert_eq!(work.difficulty, difficulty);
+
+    let proof = work.mine().unwrap();
+
+    let res = work.proof(proof.nonce.wrapping_add(1), proof.digest);
+    assert!(res.is_err());
+
+    let mut other = transaction.clone();
+    other.id = Digest::random().unwrap();
+
+    let res = work.apply(&mut other, proof.nonce, proof.digest);
+    assert!(res.is_err());
+
+    let res = work.apply(&mut transaction, proof.nonce, proof.digest);
+    assert!(res.is_ok());
+    assert!(transaction.is_mined());
+
+    let res = Work::from_transaction(&transaction);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_work_serialize_bytes() {
+    use crate::address::Address;
+
+    let mut transaction = Transaction::default();
+    let address = Address::random().unwrap();
+
+    transaction.set_coinbase(&address, 1).unwrap();
+
+    let work_a = Work::from_transaction(&transaction).unwrap();
+
+    let res = work_a.to_bytes();
+    assert!(res.is_ok());
+    let cbor = res.unwrap();
+
+    let res = Work::from_bytes(&cbor);
+    assert!(res.is_ok());
+    let work_b = res.unwrap();
+
+    assert_eq!(work_a, work_b)
+}
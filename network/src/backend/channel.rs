@@ -6,6 +6,7 @@ use crate::error::Error;
 use crate::message::Message;
 use crate::result::Result;
 use crate::traits::Network;
+use bytes::Bytes;
 use crypto::hash::{Blake512Hasher, Digest};
 use crypto::random::Random;
 use std::collections::BTreeMap;
@@ -53,6 +54,17 @@ impl ChannelNetwork {
         Random::bytes(Self::ADDRESS_LEN as usize).map_err(|e| e.into())
     }
 
+    /// `id` returns the `ChannelNetwork` id.
+    pub fn id(&self) -> Digest {
+        self.id
+    }
+
+    /// `sender` returns the `Sender` used by other `ChannelNetwork`s to reach
+    /// the `ChannelNetwork`.
+    pub fn sender(&self) -> Sender<Message> {
+        self.channels.get(&self.id).unwrap().clone()
+    }
+
     /// `calc_id` calculates the `ChannelNetwork` id.
     pub fn calc_id(&self) -> Digest {
         Blake512Hasher::hash(&self.address)
@@ -121,8 +133,8 @@ impl ChannelNetwork {
 
         if let Some(ref sender) = self.channels.get(&id) {
             let msg = Message {
-                address: self.address.clone(),
-                data: data.to_owned(),
+                address: self.address.clone().into(),
+                data: Bytes::copy_from_slice(data),
             };
 
             sender.send(msg).map_err(|e| e.into())
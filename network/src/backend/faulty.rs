@@ -0,0 +1,327 @@
+//! # Faulty Network
+//!
+//! `faulty` contains the fault-injecting network types and functions, used to
+//! test the protocol retry and timeout logic against an unreliable network.
+
+use crate::error::Error;
+use crate::message::Message;
+use crate::result::Result;
+use crate::traits::Network;
+use crypto::random::Random;
+use std::ops::FnMut;
+use std::thread;
+use std::time::Duration;
+
+/// `Faults` are the faults injected by a `FaultyNetwork` in the sent data.
+/// Rates are expressed in per-mille and latencies in milliseconds.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Faults {
+    pub drop_rate: u32,
+    pub duplicate_rate: u32,
+    pub reorder_rate: u32,
+    pub min_latency: u64,
+    pub max_latency: u64,
+}
+
+impl Faults {
+    /// `MAX_RATE` is the maximum rate of a fault.
+    pub const MAX_RATE: u32 = 1000;
+
+    /// `new` creates a new `Faults`.
+    pub fn new(
+        drop_rate: u32,
+        duplicate_rate: u32,
+        reorder_rate: u32,
+        min_latency: u64,
+        max_latency: u64,
+    ) -> Result<Faults> {
+        let faults = Faults {
+            drop_rate,
+            duplicate_rate,
+            reorder_rate,
+            min_latency,
+            max_latency,
+        };
+
+        faults.validate()?;
+
+        Ok(faults)
+    }
+
+    /// `validate` validates the `Faults`.
+    pub fn validate(&self) -> Result<()> {
+        if self.drop_rate > Self::MAX_RATE
+            || self.duplicate_rate > Self::MAX_RATE
+            || self.reorder_rate > Self::MAX_RATE
+            || self.min_latency > self.max_latency
+        {
+            let err = Error::InvalidFaults;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `happens` returns if a fault with a given rate happens.
+    fn happens(rate: u32) -> Result<bool> {
+        if rate == 0 {
+            return Ok(false);
+        }
+
+        let roll = Random::u32_range(0, Self::MAX_RATE)?;
+        Ok(roll < rate)
+    }
+
+    /// `latency` returns a random latency between the `Faults` latencies.
+    fn latency(&self) -> Result<u64> {
+        Random::u64_range(self.min_latency, self.max_latency + 1).map_err(|e| e.into())
+    }
+}
+
+/// `FaultStats` are the counts of the faults injected by a `FaultyNetwork`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct FaultStats {
+    pub sent: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+}
+
+/// `Payload` is the payload of a send held back by a `FaultyNetwork`.
+#[derive(Clone)]
+enum Payload {
+    Data(Vec<u8>),
+    Message(Message),
+}
+
+/// `Pending` is a send held back by a `FaultyNetwork` to reorder it.
+#[derive(Clone)]
+struct Pending {
+    address: Vec<u8>,
+    payload: Payload,
+    timeout: Option<u64>,
+}
+
+/// `FaultyNetwork` is a network wrapping another one and injecting drops,
+/// duplications, reorderings and latency in the sent data.
+#[derive(Clone)]
+pub struct FaultyNetwork<N: Network> {
+    inner: N,
+    faults: Faults,
+    stats: FaultStats,
+    pending: Option<Pending>,
+}
+
+/// `FaultyTransport` is an alias of `FaultyNetwork`.
+pub type FaultyTransport<N> = FaultyNetwork<N>;
+
+impl<N: Network> FaultyNetwork<N> {
+    /// `new` creates a new `FaultyNetwork` wrapping a network.
+    pub fn new(inner: N, faults: Faults) -> Result<FaultyNetwork<N>> {
+        faults.validate()?;
+
+        let network = FaultyNetwork {
+            inner,
+            faults,
+            stats: FaultStats::default(),
+            pending: None,
+        };
+
+        Ok(network)
+    }
+
+    /// `faults` returns the `FaultyNetwork` faults.
+    pub fn faults(&self) -> Faults {
+        self.faults
+    }
+
+    /// `set_faults` sets the `FaultyNetwork` faults.
+    pub fn set_faults(&mut self, faults: Faults) -> Result<()> {
+        faults.validate()?;
+        self.faults = faults;
+
+        Ok(())
+    }
+
+    /// `stats` returns the `FaultyNetwork` fault counts.
+    pub fn stats(&self) -> FaultStats {
+        self.stats
+    }
+
+    /// `inner` returns a reference to the wrapped network.
+    pub fn inner(&self) -> &N {
+        &self.inner
+    }
+
+    /// `inner_mut` returns a mutable reference to the wrapped network.
+    pub fn inner_mut(&mut self) -> &mut N {
+        &mut self.inner
+    }
+
+    /// `into_inner` returns the wrapped network.
+    pub fn into_inner(self) -> N {
+        self.inner
+    }
+
+    /// `flush` sends the send held back for reordering, if any.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(pending) = self.pending.take() {
+            self.deliver(&pending)?;
+        }
+
+        Ok(())
+    }
+
+    /// `deliver` sends a `Pending` through the wrapped network.
+    fn deliver(&mut self, pending: &Pending) -> Result<()> {
+        let address = &pending.address;
+        let timeout = pending.timeout;
+
+        match &pending.payload {
+            Payload::Data(data) => self.inner.send(address, data, timeout),
+            Payload::Message(msg) => self.inner.send_message(address, msg, timeout),
+        }
+    }
+
+    /// `inject` sends a `Pending` injecting the `FaultyNetwork` faults.
+    fn inject(&mut self, pending: Pending) -> Result<()> {
+        self.stats.sent += 1;
+
+        let latency = self.faults.latency()?;
+        if latency > 0 {
+            thread::sleep(Duration::from_millis(latency));
+        }
+
+        if Faults::happens(self.faults.drop_rate)? {
+            self.stats.dropped += 1;
+            return Ok(());
+        }
+
+        if self.pending.is_none() && Faults::happens(self.faults.reorder_rate)? {
+            self.stats.reordered += 1;
+            self.pending = Some(pending);
+            return Ok(());
+        }
+
+        self.deliver(&pending)?;
+
+        if Faults::happens(self.faults.duplicate_rate)? {
+            self.stats.duplicated += 1;
+            self.deliver(&pending)?;
+        }
+
+        self.flush()
+    }
+}
+
+impl<N: Network> Network for FaultyNetwork<N> {
+    fn local_address(&self) -> Result<Vec<u8>> {
+        self.inner.local_address()
+    }
+
+    fn send(&mut self, address: &[u8], data: &[u8], timeout: Option<u64>) -> Result<()> {
+        let pending = Pending {
+            address: address.to_vec(),
+            payload: Payload::Data(data.to_vec()),
+            timeout,
+        };
+
+        self.inject(pending)
+    }
+
+    fn send_message(&mut self, address: &[u8], msg: &Message, timeout: Option<u64>) -> Result<()> {
+        let pending = Pending {
+            address: address.to_vec(),
+            payload: Payload::Message(msg.clone()),
+            timeout,
+        };
+
+        self.inject(pending)
+    }
+
+    fn recv(&mut self, timeout: Option<u64>) -> Result<Message> {
+        self.inner.recv(timeout)
+    }
+
+    fn serve(
+        &mut self,
+        timeout: Option<u64>,
+        handler: Box<dyn FnMut(Message) -> Result<()>>,
+    ) -> Result<()> {
+        self.inner.serve(timeout, handler)
+    }
+}
+
+#[test]
+fn test_faults_new() {
+    let res = Faults::new(0, 0, 0, 0, 0);
+    assert!(res.is_ok());
+
+    let res = Faults::new(Faults::MAX_RATE, Faults::MAX_RATE, Faults::MAX_RATE, 1, 2);
+    assert!(res.is_ok());
+
+    let res = Faults::new(Faults::MAX_RATE + 1, 0, 0, 0, 0);
+    assert!(res.is_err());
+
+    let res = Faults::new(0, 0, 0, 2, 1);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_faulty_network() {
+    use crate::backend::channel::ChannelNetwork;
+    use std::time::Instant;
+
+    let data_a = Random::bytes(100).unwrap();
+    let data_b = Random::bytes(100).unwrap();
+
+    let inner = ChannelNetwork::new().unwrap();
+    let address = inner.local_address().unwrap();
+
+    let faults = Faults::new(Faults::MAX_RATE, 0, 0, 0, 0).unwrap();
+    let mut network = FaultyNetwork::new(inner, faults).unwrap();
+
+    let res = network.send(&address, &data_a, None);
+    assert!(res.is_ok());
+    assert_eq!(network.stats().dropped, 1);
+
+    let faults = Faults::new(0, Faults::MAX_RATE, 0, 0, 0).unwrap();
+    network.set_faults(faults).unwrap();
+
+    let res = network.send(&address, &data_a, None);
+    assert!(res.is_ok());
+    assert_eq!(network.stats().duplicated, 1);
+
+    for _ in 0..2 {
+        let msg = network.recv(None).unwrap();
+        assert_eq!(msg.data, data_a);
+    }
+
+    let faults = Faults::new(0, 0, Faults::MAX_RATE, 0, 0).unwrap();
+    network.set_faults(faults).unwrap();
+
+    let res = network.send(&address, &data_a, None);
+    assert!(res.is_ok());
+
+    let res = network.send(&address, &data_b, None);
+    assert!(res.is_ok());
+    assert_eq!(network.stats().reordered, 1);
+
+    let msg = network.recv(None).unwrap();
+    assert_eq!(msg.data, data_b);
+
+    let msg = network.recv(None).unwrap();
+    assert_eq!(msg.data, data_a);
+
+    let faults = Faults::new(0, 0, 0, 20, 20).unwrap();
+    network.set_faults(faults).unwrap();
+
+    let start = Instant::now();
+    let res = network.send(&address, &data_a, None);
+    assert!(res.is_ok());
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    let msg = network.recv(None).unwrap();
+    assert_eq!(msg.data, data_a);
+    assert_eq!(network.stats().sent, 5);
+}
@@ -3,3 +3,9 @@ pub use channel::*;
 
 pub mod tcp;
 pub use tcp::*;
+
+pub mod faulty;
+pub use faulty::*;
+
+pub mod multi;
+pub use multi::*;
@@ -0,0 +1,189 @@
+//! # Multi Network
+//!
+//! `multi` contains the multiplexing network types and functions, used to
+//! listen on multiple transports at the same time.
+
+use crate::error::Error;
+use crate::message::Message;
+use crate::result::Result;
+use crate::traits::Network;
+use std::ops::FnMut;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// `MultiNetwork` is a network listening on multiple `Network`s, as an
+/// IPv4 and an IPv6 `TcpNetwork`, and multiplexing their incoming `Message`s
+/// in a single stream. The `Message`s are sent through the primary `Network`,
+/// the first one.
+///
+/// The listeners are started on the first `recv` or `serve`, each one in its
+/// own thread, and keep running for the lifetime of the `MultiNetwork`.
+#[derive(Clone)]
+pub struct MultiNetwork<N: Network + Clone + Send + 'static> {
+    networks: Vec<N>,
+    receiver: Arc<Mutex<Option<Receiver<Result<Message>>>>>,
+}
+
+impl<N: Network + Clone + Send + 'static> MultiNetwork<N> {
+    /// `new` creates a new `MultiNetwork` from a non-empty list of `Network`s.
+    pub fn new(networks: Vec<N>) -> Result<MultiNetwork<N>> {
+        if networks.is_empty() {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let network = MultiNetwork {
+            networks,
+            receiver: Arc::new(Mutex::new(None)),
+        };
+
+        Ok(network)
+    }
+
+    /// `primary` returns the primary `Network` of the `MultiNetwork`.
+    pub fn primary(&self) -> &N {
+        &self.networks[0]
+    }
+
+    /// `networks` returns the `Network`s of the `MultiNetwork`.
+    pub fn networks(&self) -> &[N] {
+        &self.networks
+    }
+
+    /// `is_listening` returns if the listeners of the `MultiNetwork` are running.
+    pub fn is_listening(&self) -> bool {
+        self.receiver.lock().unwrap().is_some()
+    }
+
+    /// `listen` starts the listeners of the `MultiNetwork`, if not already running.
+    /// A listener that fails sends its error to the stream before stopping.
+    fn listen(&self, timeout: Option<u64>) {
+        let mut receiver = self.receiver.lock().unwrap();
+
+        if receiver.is_some() {
+            return;
+        }
+
+        let (sender, rx) = channel();
+
+        for network in self.networks.iter() {
+            let mut network = network.clone();
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let msg_sender = sender.clone();
+
+                let handler = move |msg: Message| {
+                    msg_sender.send(Ok(msg)).map_err(|e| {
+                        let msg = format!("{}", e);
+                        Error::IO { msg }
+                    })
+                };
+
+                if let Err(err) = network.serve(timeout, Box::new(handler)) {
+                    let _ = sender.send(Err(err));
+                }
+            });
+        }
+
+        *receiver = Some(rx);
+    }
+
+    /// `next_message` returns the next incoming `Message` of the listeners.
+    fn next_message(&self, timeout: Option<u64>) -> Result<Message> {
+        let receiver = self.receiver.lock().unwrap();
+        let receiver = receiver.as_ref().ok_or(Error::NotFound)?;
+
+        if let Some(timeout) = timeout {
+            receiver.recv_timeout(Duration::from_secs(timeout))?
+        } else {
+            receiver.recv()?
+        }
+    }
+}
+
+impl<N: Network + Clone + Send + 'static> Network for MultiNetwork<N> {
+    fn local_address(&self) -> Result<Vec<u8>> {
+        self.primary().local_address()
+    }
+
+    fn send(&mut self, address: &[u8], data: &[u8], timeout: Option<u64>) -> Result<()> {
+        self.networks[0].send(address, data, timeout)
+    }
+
+    fn send_message(&mut self, address: &[u8], msg: &Message, timeout: Option<u64>) -> Result<()> {
+        self.networks[0].send_message(address, msg, timeout)
+    }
+
+    fn recv(&mut self, timeout: Option<u64>) -> Result<Message> {
+        self.listen(timeout);
+        self.next_message(timeout)
+    }
+
+    fn serve(
+        &mut self,
+        timeout: Option<u64>,
+        mut handler: Box<dyn FnMut(Message) -> Result<()>>,
+    ) -> Result<()> {
+        self.listen(timeout);
+
+        loop {
+            let msg = self.next_message(None)?;
+            handler(msg)?;
+        }
+    }
+}
+
+#[test]
+fn test_multi_network_new() {
+    use crate::backend::TcpNetwork;
+
+    let res = MultiNetwork::<TcpNetwork>::new(vec![]);
+    assert!(res.is_err());
+
+    let network_a = TcpNetwork::from_parts([127, 0, 0, 1], 2040).unwrap();
+    let network_b = TcpNetwork::from_parts([127, 0, 0, 1], 2041).unwrap();
+
+    let res = MultiNetwork::new(vec![network_a.clone(), network_b]);
+    assert!(res.is_ok());
+
+    let network = res.unwrap();
+    assert_eq!(network.networks().len(), 2);
+    assert_eq!(
+        network.local_address().unwrap(),
+        network_a.local_address().unwrap()
+    );
+    assert!(!network.is_listening());
+}
+
+#[test]
+fn test_multi_network_recv() {
+    use crate::backend::TcpNetwork;
+    use crypto::random::Random;
+
+    let network_a = TcpNetwork::from_parts([127, 0, 0, 1], 2034).unwrap();
+    let network_b = TcpNetwork::from_parts([127, 0, 0, 1], 2035).unwrap();
+
+    let mut network = MultiNetwork::new(vec![network_a.clone(), network_b.clone()]).unwrap();
+
+    let res = network.recv(Some(1));
+    assert!(res.is_err());
+    assert!(network.is_listening());
+
+    let mut sender = TcpNetwork::local().unwrap();
+    let sender_address = sender.local_address().unwrap();
+
+    for target in &[network_a, network_b] {
+        let data = Random::bytes(100).unwrap();
+        let msg = Message::new(sender_address.clone(), data.clone()).unwrap();
+
+        let res = sender.send_message(&target.local_address().unwrap(), &msg, None);
+        assert!(res.is_ok());
+
+        let res = network.recv(Some(3));
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().data, data);
+    }
+}
@@ -2,24 +2,45 @@
 //!
 //! `tcp` contains the Tcp network backend types and functions.
 
+use crate::blacklist::Blacklist;
+use crate::codec::Codec;
+use crate::compression::Compression;
 use crate::error::Error;
 use crate::message::Message;
 use crate::result::Result;
+use crate::socks;
 use crate::traits::Network;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use crypto::hash::{Blake512Hasher, Digest};
-use std::io::{Cursor, Read, Write};
-use std::net::{Ipv4Addr, SocketAddrV4};
+use models::net_address::NetAddress;
+use std::io::{BufWriter, Cursor, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::net::{TcpListener, TcpStream};
 use std::ops::FnMut;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-/// `address_to_bytes` converts a SocketAddrV4 to a vector of bytes.
-pub fn address_to_bytes(address: &SocketAddrV4) -> Result<Vec<u8>> {
+/// `IPV4_ADDRESS_LEN` is the length of a binary IPv4 address.
+const IPV4_ADDRESS_LEN: usize = 6;
+
+/// `IPV6_ADDRESS_LEN` is the length of a binary IPv6 address.
+const IPV6_ADDRESS_LEN: usize = 18;
+
+/// `address_to_bytes` converts a SocketAddr to a vector of bytes.
+pub fn address_to_bytes(address: &SocketAddr) -> Result<Vec<u8>> {
     let mut buf = Vec::new();
 
-    for n in &address.ip().octets() {
-        buf.write_u8(*n)?;
+    match address.ip() {
+        IpAddr::V4(ip) => {
+            for n in &ip.octets() {
+                buf.write_u8(*n)?;
+            }
+        }
+        IpAddr::V6(ip) => {
+            for n in &ip.octets() {
+                buf.write_u8(*n)?;
+            }
+        }
     }
 
     buf.write_u16::<BigEndian>(address.port())?;
@@ -28,48 +49,58 @@ pub fn address_to_bytes(address: &SocketAddrV4) -> Result<Vec<u8>> {
 }
 
 /// `address_from_bytes` returns an address from a slice of bytes.
-pub fn address_from_bytes(buf: &[u8]) -> Result<SocketAddrV4> {
+pub fn address_from_bytes(buf: &[u8]) -> Result<SocketAddr> {
     let mut reader = Cursor::new(buf);
 
-    let mut ip = [0u8; 4];
-
-    #[allow(clippy::needless_range_loop)]
-    for i in 0..4 {
-        ip[i] = reader.read_u8()?;
-    }
+    let ip_addr = match buf.len() {
+        IPV4_ADDRESS_LEN => {
+            let mut ip = [0u8; 4];
+            reader.read_exact(&mut ip)?;
+            IpAddr::V4(Ipv4Addr::from(ip))
+        }
+        IPV6_ADDRESS_LEN => {
+            let mut ip = [0u8; 16];
+            reader.read_exact(&mut ip)?;
+            IpAddr::V6(Ipv6Addr::from(ip))
+        }
+        _ => {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+    };
 
     let port = reader.read_u16::<BigEndian>()?;
 
-    let ip_addr = Ipv4Addr::from(ip);
-    let address = SocketAddrV4::new(ip_addr, port);
+    let address = SocketAddr::new(ip_addr, port);
     Ok(address)
 }
 
 /// `TcpNetwork` is a network network using a Tcp network.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub struct TcpNetwork {
     id: Digest,
-    address: SocketAddrV4,
+    address: SocketAddr,
+    proxy: Option<SocketAddr>,
+    compression: Compression,
+    codec: Codec,
+    blacklist: Arc<Mutex<Blacklist>>,
 }
 
 impl TcpNetwork {
     /// `DEFAULT_PORT` is the default port of the `TcpNetwork`.
     pub const DEFAULT_PORT: u16 = 2019;
 
-    /// `new` creates a new `TcpNetwork` from an IPv4 address.
+    /// `new` creates a new `TcpNetwork` from an address string. The address
+    /// can be an IP, in which case the default port is used, or a
+    /// `host:port` string, with IPv6 hosts written in brackets.
     pub fn new(addr: &str) -> Result<TcpNetwork> {
-        let ip_addr: Ipv4Addr = addr.parse()?;
-        let address = SocketAddrV4::new(ip_addr, Self::DEFAULT_PORT);
-
-        let addr_buf = address_to_bytes(&address)?;
-
-        let id = Blake512Hasher::hash(&addr_buf);
-
-        let address = SocketAddrV4::new(ip_addr, Self::DEFAULT_PORT);
-
-        let network = TcpNetwork { id, address };
+        if let Ok(ip_addr) = addr.parse::<IpAddr>() {
+            let address = SocketAddr::new(ip_addr, Self::DEFAULT_PORT);
+            return Self::from_socket_addr(&address);
+        }
 
-        Ok(network)
+        let net_address = NetAddress::parse(addr)?;
+        Self::from_net_address(&net_address)
     }
 
     /// `local` buids a local `TcpNetwork`.
@@ -79,18 +110,100 @@ impl TcpNetwork {
 
     /// `from_parts` creates a new `TcpNetwork` with an ip octet and a port.
     pub fn from_parts(ip: [u8; 4], port: u16) -> Result<TcpNetwork> {
-        let ip_addr = Ipv4Addr::from(ip);
-        let address = SocketAddrV4::new(ip_addr, port);
+        let ip_addr = IpAddr::V4(Ipv4Addr::from(ip));
+        let address = SocketAddr::new(ip_addr, port);
 
-        let addr_buf = address_to_bytes(&address)?;
+        Self::from_socket_addr(&address)
+    }
+
+    /// `from_socket_addr` creates a new `TcpNetwork` from a `SocketAddr`.
+    pub fn from_socket_addr(address: &SocketAddr) -> Result<TcpNetwork> {
+        let addr_buf = address_to_bytes(address)?;
 
         let id = Blake512Hasher::hash(&addr_buf);
 
-        let network = TcpNetwork { id, address };
+        let network = TcpNetwork {
+            id,
+            address: address.to_owned(),
+            proxy: None,
+            compression: Compression::None,
+            codec: Codec::Cbor,
+            blacklist: Arc::new(Mutex::new(Blacklist::default())),
+        };
 
         Ok(network)
     }
 
+    /// `from_net_address` creates a new `TcpNetwork` from a `NetAddress`,
+    /// resolving it if it is a host name.
+    pub fn from_net_address(address: &NetAddress) -> Result<TcpNetwork> {
+        let socket_addrs = address.to_socket_addrs()?;
+
+        if let Some(socket_addr) = socket_addrs.first() {
+            Self::from_socket_addr(socket_addr)
+        } else {
+            let err = Error::InvalidAddress;
+            Err(err)
+        }
+    }
+
+    /// `set_proxy` sets the SOCKS5 proxy the `TcpNetwork` dials through.
+    pub fn set_proxy(&mut self, proxy: Option<SocketAddr>) {
+        self.proxy = proxy;
+    }
+
+    /// `proxy` returns the SOCKS5 proxy the `TcpNetwork` dials through, if any.
+    pub fn proxy(&self) -> Option<SocketAddr> {
+        self.proxy
+    }
+
+    /// `set_compression` sets the `Compression` the `TcpNetwork` offers and accepts.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// `compression` returns the `Compression` the `TcpNetwork` offers and accepts.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// `set_codec` sets the `Codec` the `TcpNetwork` offers and accepts.
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    /// `codec` returns the `Codec` the `TcpNetwork` offers and accepts.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// `set_blacklist` sets the `Blacklist` of the addresses the `TcpNetwork`
+    /// refuses to accept from and to dial.
+    pub fn set_blacklist(&mut self, blacklist: Arc<Mutex<Blacklist>>) {
+        self.blacklist = blacklist;
+    }
+
+    /// `blacklist` returns the `Blacklist` of the `TcpNetwork`.
+    pub fn blacklist(&self) -> Arc<Mutex<Blacklist>> {
+        self.blacklist.clone()
+    }
+
+    /// `is_blacklisted` returns if an address is blacklisted, reloading the
+    /// `Blacklist` file if modified.
+    fn is_blacklisted(&self, address: &SocketAddr) -> bool {
+        let mut blacklist = self.blacklist.lock().unwrap();
+
+        // NB: an unreadable or invalid file keeps the previous ranges
+        let _ = blacklist.reload();
+
+        blacklist.contains_ip(address.ip())
+    }
+
+    /// `net_address` returns the `TcpNetwork` address as a `NetAddress`.
+    pub fn net_address(&self) -> Result<NetAddress> {
+        NetAddress::from_socket_addr(&self.address).map_err(|e| e.into())
+    }
+
     /// `address_bytes` converts the `TcpNetwork` address to a vector of bytes.
     pub fn address_bytes(&self) -> Result<Vec<u8>> {
         address_to_bytes(&self.address)
@@ -113,34 +226,92 @@ impl TcpNetwork {
         Ok(())
     }
 
-    /// `_send` sends binary data to a `TcpNetwork`.
-    fn _send(&self, address: &[u8], data: &[u8], timeout: Option<u64>) -> Result<()> {
+    /// `connect` opens a stream to a `TcpNetwork`, through the proxy if any.
+    fn connect(&self, address: &[u8], timeout: Option<u64>) -> Result<TcpStream> {
         let socketaddr = address_from_bytes(address)?;
-        let mut stream = TcpStream::connect(&socketaddr)?;
+
+        if self.is_blacklisted(&socketaddr) {
+            let err = Error::Blacklisted;
+            return Err(err);
+        }
+
+        let stream = if let Some(ref proxy) = self.proxy {
+            let target = NetAddress::from_socket_addr(&socketaddr)?;
+            socks::connect(proxy, &target, timeout)?
+        } else {
+            TcpStream::connect(&socketaddr)?
+        };
 
         let timeout = timeout.map(Duration::from_secs);
 
         stream.set_write_timeout(timeout)?;
 
-        stream.write_all(data)?;
+        Ok(stream)
+    }
 
-        Ok(())
+    /// `_send` sends binary data to a `TcpNetwork`.
+    fn _send(&self, address: &[u8], data: &[u8], timeout: Option<u64>) -> Result<()> {
+        let msg = Message::from_slice(data)?;
+        self._send_message(address, &msg, timeout)
     }
 
-    /// `_recv` receives a `Message` from a known `TcpNetwork`.
-    fn _recv(&mut self, timeout: Option<u64>) -> Result<Message> {
-        let listener = TcpListener::bind(&self.address)?;
-        let (mut stream, _) = listener.accept()?;
+    /// `_send_message` streams a `Message` to a `TcpNetwork`, encoded and
+    /// compressed as negotiated in the connection handshake.
+    fn _send_message(&self, address: &[u8], msg: &Message, timeout: Option<u64>) -> Result<()> {
+        let mut stream = self.connect(address, timeout)?;
 
-        let mut buf = Vec::new();
+        stream.set_read_timeout(timeout.map(Duration::from_secs))?;
+
+        self.compression.write_handshake(&mut stream)?;
+        self.codec.write_handshake(&mut stream)?;
+        let compression = Compression::read_handshake(&mut stream)?;
+        let codec = Codec::read_handshake(&mut stream)?;
+
+        let msg = msg.transcode(Codec::Cbor, codec)?.compress(compression)?;
 
+        let mut stream = BufWriter::new(stream);
+
+        msg.write_to(&mut stream)?;
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    /// `read_message` answers the handshake of an incoming connection and reads
+    /// its `Message`.
+    fn read_message(&self, stream: &mut TcpStream, timeout: Option<u64>) -> Result<Message> {
         let timeout = timeout.map(Duration::from_secs);
 
         stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+
+        let offered = Compression::read_handshake(stream)?;
+        let compression = self.compression.negotiate(offered);
+        let offered = Codec::read_handshake(stream)?;
+        let codec = self.codec.negotiate(offered);
+        compression.write_handshake(stream)?;
+        codec.write_handshake(stream)?;
+
+        let mut buf = Vec::new();
+
+        stream.take(Message::MAX_LEN + 1).read_to_end(&mut buf)?;
 
-        stream.read_to_end(&mut buf)?;
+        Message::from_bytes(buf.into())?
+            .decompress(compression)?
+            .transcode(codec, Codec::Cbor)
+    }
+
+    /// `_recv` receives a `Message` from a known `TcpNetwork`.
+    fn _recv(&mut self, timeout: Option<u64>) -> Result<Message> {
+        let listener = TcpListener::bind(&self.address)?;
+        let (mut stream, peer) = listener.accept()?;
+
+        if self.is_blacklisted(&peer) {
+            let err = Error::Blacklisted;
+            return Err(err);
+        }
 
-        Message::from_bytes(&buf)
+        self.read_message(&mut stream, timeout)
     }
 
     /// `_serve` handles incoming `Message`s.
@@ -153,15 +324,11 @@ impl TcpNetwork {
         for stream in listener.incoming() {
             let mut stream = stream?;
 
-            let mut buf = Vec::new();
-
-            let timeout = timeout.map(Duration::from_secs);
-
-            stream.set_read_timeout(timeout)?;
+            if self.is_blacklisted(&stream.peer_addr()?) {
+                continue;
+            }
 
-            stream.read_to_end(&mut buf)?;
-
-            let msg = Message::from_bytes(&buf)?;
+            let msg = self.read_message(&mut stream, timeout)?;
 
             handler(msg)?;
         }
@@ -179,6 +346,10 @@ impl Network for TcpNetwork {
         self._send(address, data, timeout)
     }
 
+    fn send_message(&mut self, address: &[u8], msg: &Message, timeout: Option<u64>) -> Result<()> {
+        self._send_message(address, msg, timeout)
+    }
+
     fn recv(&mut self, timeout: Option<u64>) -> Result<Message> {
         self._recv(timeout)
     }
@@ -211,6 +382,7 @@ fn test_tcp_network_ops() {
     let data = Random::bytes(data_len).unwrap();
     let data_arc = Arc::new(data.clone());
     let trsp_a_addr = trsp_a.address.clone();
+    let mut server = trsp_a.clone();
 
     let handler = move |msg: Message| {
         let trsp_a_addr_buf = address_to_bytes(&trsp_a_addr).unwrap();
@@ -221,11 +393,150 @@ fn test_tcp_network_ops() {
     };
 
     thread::spawn(move || {
-        let _ = trsp_a.serve(None, Box::new(handler));
+        let _ = server.serve(None, Box::new(handler));
     });
 
     thread::sleep(Duration::from_secs(3));
     let trsp_a_addr_buf = address_to_bytes(&trsp_a_addr).unwrap();
-    let res = trsp_a.send(&trsp_a_addr_buf, &data, None);
+    let msg = Message::new(trsp_a_addr_buf.clone(), data).unwrap();
+    let res = trsp_a.send_message(&trsp_a_addr_buf, &msg, None);
     assert!(res.is_ok());
 }
+
+#[test]
+fn test_tcp_network_compression() {
+    use std::thread;
+    use std::time::Duration;
+
+    let mut trsp_a = TcpNetwork::from_parts([127, 0, 0, 1], 2029).unwrap();
+    trsp_a.set_compression(Compression::Snappy);
+
+    let mut trsp_b = TcpNetwork::from_parts([127, 0, 0, 1], 2030).unwrap();
+    trsp_b.set_compression(Compression::Snappy);
+
+    let data = vec![7u8; 1 << 16];
+    let trsp_a_addr_buf = trsp_a.address_bytes().unwrap();
+    let msg = Message::new(trsp_a_addr_buf.clone(), data).unwrap();
+    let expected = msg.clone();
+
+    let mut receiver = trsp_a.clone();
+    let handle = thread::spawn(move || receiver.recv(Some(10)));
+
+    thread::sleep(Duration::from_secs(1));
+    let res = trsp_b.send_message(&trsp_a_addr_buf, &msg, None);
+    assert!(res.is_ok());
+
+    let res = handle.join().unwrap();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), expected);
+
+    trsp_a.set_compression(Compression::None);
+
+    let mut receiver = trsp_a.clone();
+    let handle = thread::spawn(move || receiver.recv(Some(10)));
+
+    thread::sleep(Duration::from_secs(1));
+    let res = trsp_b.send_message(&trsp_a_addr_buf, &msg, None);
+    assert!(res.is_ok());
+
+    let res = handle.join().unwrap();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), expected);
+}
+
+#[test]
+fn test_tcp_network_codec() {
+    use crypto::hash::Digest;
+    use crypto::random::Random;
+    use models::consensus_message::ConsensusMessage;
+    use models::node::Node;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut trsp_a = TcpNetwork::from_parts([127, 0, 0, 1], 2032).unwrap();
+    trsp_a.set_codec(Codec::MessagePack);
+
+    let mut trsp_b = TcpNetwork::from_parts([127, 0, 0, 1], 2033).unwrap();
+    trsp_b.set_codec(Codec::MessagePack);
+
+    let trsp_a_addr_buf = trsp_a.address_bytes().unwrap();
+    let node = Node::random(trsp_a_addr_buf.len()).unwrap();
+    let query_id = Random::u64().unwrap();
+    let tx_id = Digest::random().unwrap();
+
+    let cons_msg =
+        ConsensusMessage::new_reply(&trsp_a_addr_buf, query_id, &node, tx_id, true).unwrap();
+    let msg = Message::from_consensus_message(&cons_msg).unwrap();
+    let expected = msg.clone();
+
+    for codec in &[Codec::MessagePack, Codec::Cbor] {
+        trsp_a.set_codec(*codec);
+
+        let mut receiver = trsp_a.clone();
+        let handle = thread::spawn(move || receiver.recv(Some(10)));
+
+        thread::sleep(Duration::from_secs(1));
+        let res = trsp_b.send_message(&trsp_a_addr_buf, &msg, None);
+        assert!(res.is_ok());
+
+        let res = handle.join().unwrap();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_tcp_network_address_bytes() {
+    let addresses = ["127.0.0.1:2019", "[::1]:2019", "[2001:db8::1]:8080"];
+
+    for addr in addresses.iter() {
+        let address: SocketAddr = addr.parse().unwrap();
+
+        let res = address_to_bytes(&address);
+        assert!(res.is_ok());
+        let buf = res.unwrap();
+
+        let res = address_from_bytes(&buf);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), address);
+
+        let res = TcpNetwork::new(addr);
+        assert!(res.is_ok());
+
+        let network = res.unwrap();
+        assert_eq!(network.address, address);
+
+        let res = network.validate();
+        assert!(res.is_ok());
+
+        let res = network.net_address();
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap().to_string(), addr);
+    }
+
+    let res = address_from_bytes(&[0u8; 5]);
+    assert!(res.is_err());
+
+    let res = TcpNetwork::new("address");
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_tcp_network_blacklist() {
+    use crate::blacklist::IpRange;
+
+    let mut network = TcpNetwork::from_parts([127, 0, 0, 1], 2031).unwrap();
+    let address = network.address_bytes().unwrap();
+    let msg = Message::new(address.clone(), vec![1, 2, 3]).unwrap();
+
+    let range = IpRange::parse("127.0.0.0/8").unwrap();
+    network.blacklist().lock().unwrap().insert(range);
+
+    let res = network.send_message(&address, &msg, Some(1));
+    assert!(res.is_err());
+
+    match res.unwrap_err() {
+        Error::Blacklisted => {}
+        err => panic!("unexpected error: {}", err),
+    }
+}
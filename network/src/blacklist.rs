@@ -0,0 +1,293 @@
+//! # Blacklist
+//!
+//! `blacklist` contains the address blacklist type and functions. A blacklist
+//! file lists an IP address or a CIDR range per line, with `#` starting the
+//! comments, and is reloaded when modified, so that the operators can block
+//! abusive ranges without restarting the node.
+
+use crate::backend::address_from_bytes;
+use crate::error::Error;
+use crate::result::Result;
+use models::net_address::NetAddress;
+use std::collections::BTreeSet;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::SystemTime;
+
+/// `IpRange` is a CIDR range of IP addresses.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct IpRange {
+    pub ip: IpAddr,
+    pub prefix: u8,
+}
+
+impl IpRange {
+    /// `new` creates a new `IpRange`.
+    pub fn new(ip: IpAddr, prefix: u8) -> Result<IpRange> {
+        if prefix > IpRange::max_prefix(ip) {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        let range = IpRange { ip, prefix };
+
+        Ok(range)
+    }
+
+    /// `max_prefix` returns the maximum prefix length of the family of an IP address.
+    fn max_prefix(ip: IpAddr) -> u8 {
+        match ip {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// `parse` parses an `IpRange` from an IP address or a CIDR string,
+    /// as in `10.0.0.0/8` or `2001:db8::/32`.
+    pub fn parse(s: &str) -> Result<IpRange> {
+        let mut parts = s.splitn(2, '/');
+
+        let ip: IpAddr = parts.next().unwrap_or_default().trim().parse()?;
+
+        let prefix = if let Some(prefix) = parts.next() {
+            prefix.trim().parse().map_err(|_| Error::InvalidAddress)?
+        } else {
+            IpRange::max_prefix(ip)
+        };
+
+        IpRange::new(ip, prefix)
+    }
+
+    /// `contains` returns if the `IpRange` contains an IP address.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.ip, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                let mask = u32::max_value()
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(range) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                let mask = u128::max_value()
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(range) & mask == u128::from(ip) & mask
+            }
+            (IpAddr::V4(_), IpAddr::V6(ip)) => {
+                // NB: IPv4-mapped IPv6 addresses are checked as IPv4 addresses
+                if let Some(ip) = ip.to_ipv4() {
+                    self.contains(IpAddr::V4(ip))
+                } else {
+                    false
+                }
+            }
+            (IpAddr::V6(_), IpAddr::V4(ip)) => self.contains(IpAddr::V6(ip.to_ipv6_mapped())),
+        }
+    }
+}
+
+/// `Blacklist` is the set of the blocked `IpRange`s, optionally loaded from a file.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Blacklist {
+    path: Option<String>,
+    modified: Option<SystemTime>,
+    ranges: BTreeSet<IpRange>,
+}
+
+impl Blacklist {
+    /// `parse` parses a `Blacklist` from the content of a blacklist file.
+    pub fn parse(s: &str) -> Result<Blacklist> {
+        let mut ranges = BTreeSet::new();
+
+        for line in s.lines() {
+            let line = line.splitn(2, '#').next().unwrap_or_default().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            ranges.insert(IpRange::parse(line)?);
+        }
+
+        let blacklist = Blacklist {
+            path: None,
+            modified: None,
+            ranges,
+        };
+
+        Ok(blacklist)
+    }
+
+    /// `load` loads a `Blacklist` from a blacklist file.
+    pub fn load(path: &str) -> Result<Blacklist> {
+        let modified = fs::metadata(path)?.modified().ok();
+        let content = fs::read_to_string(path)?;
+
+        let mut blacklist = Blacklist::parse(&content)?;
+        blacklist.path = Some(path.into());
+        blacklist.modified = modified;
+
+        Ok(blacklist)
+    }
+
+    /// `reload` reloads the `Blacklist` file if modified since the last load,
+    /// returning if it was reloaded. On error the previous ranges are kept.
+    pub fn reload(&mut self) -> Result<bool> {
+        let path = if let Some(ref path) = self.path {
+            path.clone()
+        } else {
+            return Ok(false);
+        };
+
+        let modified = fs::metadata(&path)?.modified().ok();
+
+        if modified.is_some() && modified == self.modified {
+            return Ok(false);
+        }
+
+        *self = Blacklist::load(&path)?;
+
+        Ok(true)
+    }
+
+    /// `path` returns the path of the `Blacklist` file, if any.
+    pub fn path(&self) -> Option<String> {
+        self.path.clone()
+    }
+
+    /// `len` returns the number of `IpRange`s of the `Blacklist`.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// `is_empty` returns if the `Blacklist` has no `IpRange`s.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// `insert` adds an `IpRange` to the `Blacklist`.
+    pub fn insert(&mut self, range: IpRange) {
+        self.ranges.insert(range);
+    }
+
+    /// `contains_ip` returns if an IP address is blacklisted.
+    pub fn contains_ip(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+
+    /// `contains_address` returns if a binary node address is blacklisted.
+    /// Addresses that are not TCP addresses are never blacklisted.
+    pub fn contains_address(&self, address: &[u8]) -> bool {
+        if let Ok(address) = address_from_bytes(address) {
+            self.contains_ip(address.ip())
+        } else {
+            false
+        }
+    }
+
+    /// `contains_net_address` returns if a `NetAddress` is blacklisted. Host
+    /// names are not resolved, and so they are never blacklisted.
+    pub fn contains_net_address(&self, address: &NetAddress) -> bool {
+        match address {
+            NetAddress::Ipv4 { ip, .. } => self.contains_ip(IpAddr::V4(Ipv4Addr::from(*ip))),
+            NetAddress::Ipv6 { ip, .. } => self.contains_ip(IpAddr::V6(Ipv6Addr::from(*ip))),
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn test_ip_range() {
+    let range = IpRange::parse("10.0.0.0/8").unwrap();
+    assert!(range.contains("10.1.2.3".parse().unwrap()));
+    assert!(!range.contains("11.0.0.1".parse().unwrap()));
+    assert!(range.contains("::ffff:10.0.0.1".parse().unwrap()));
+
+    let range = IpRange::parse("2001:db8::/32").unwrap();
+    assert!(range.contains("2001:db8::1".parse().unwrap()));
+    assert!(!range.contains("2001:db9::1".parse().unwrap()));
+    assert!(!range.contains("10.0.0.1".parse().unwrap()));
+
+    let range = IpRange::parse("0.0.0.0/0").unwrap();
+    assert!(range.contains("192.168.1.1".parse().unwrap()));
+
+    let range = IpRange::parse("127.0.0.1").unwrap();
+    assert_eq!(range.prefix, 32);
+    assert!(range.contains("127.0.0.1".parse().unwrap()));
+    assert!(!range.contains("127.0.0.2".parse().unwrap()));
+
+    let invalid_ranges = ["10.0.0.0/33", "::/129", "10.0.0.0/x", "host", ""];
+
+    for range in invalid_ranges.iter() {
+        let res = IpRange::parse(range);
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_blacklist() {
+    use crate::backend::address_to_bytes;
+    use std::net::SocketAddr;
+
+    let content = "# abusive ranges\n10.0.0.0/8\n\n192.168.1.1 # single host\n2001:db8::/32\n";
+
+    let res = Blacklist::parse(content);
+    assert!(res.is_ok());
+    let blacklist = res.unwrap();
+    assert_eq!(blacklist.len(), 3);
+
+    let blocked: SocketAddr = "10.2.3.4:2019".parse().unwrap();
+    let allowed: SocketAddr = "192.168.1.2:2019".parse().unwrap();
+
+    assert!(blacklist.contains_address(&address_to_bytes(&blocked).unwrap()));
+    assert!(!blacklist.contains_address(&address_to_bytes(&allowed).unwrap()));
+    assert!(!blacklist.contains_address(b"channel"));
+
+    let address = NetAddress::parse("[2001:db8::1]:2019").unwrap();
+    assert!(blacklist.contains_net_address(&address));
+
+    let address = NetAddress::parse("node.alsacoin.org:2019").unwrap();
+    assert!(!blacklist.contains_net_address(&address));
+
+    let res = Blacklist::parse("10.0.0.0/8\nnot an address\n");
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_blacklist_reload() {
+    use std::env;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut path = env::temp_dir();
+    path.push(format!("alsacoin-blacklist-{}", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+
+    fs::write(&path, "10.0.0.0/8\n").unwrap();
+
+    let res = Blacklist::load(&path);
+    assert!(res.is_ok());
+    let mut blacklist = res.unwrap();
+    assert_eq!(blacklist.len(), 1);
+
+    let res = blacklist.reload();
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    thread::sleep(Duration::from_millis(1100));
+    fs::write(&path, "10.0.0.0/8\n172.16.0.0/12\n").unwrap();
+
+    let res = blacklist.reload();
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+    assert_eq!(blacklist.len(), 2);
+
+    fs::write(&path, "invalid\n").unwrap();
+    blacklist.modified = None;
+
+    let res = blacklist.reload();
+    assert!(res.is_err());
+    assert_eq!(blacklist.len(), 2);
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,260 @@
+//! # Codec
+//!
+//! `codec` contains the message codec type and functions.
+//!
+//! The peers exchange their `ConsensusMessage`s in CBOR. Clients that cannot
+//! parse CBOR can ask for another `Codec`: the codec of a connection is
+//! negotiated in a handshake following the compression one, and the `TcpNetwork`
+//! transcodes the `Message` payloads between CBOR and the negotiated `Codec`.
+
+use crate::error::Error;
+use crate::result::Result;
+use models::cbor::{self, CborLimits};
+use models::msgpack;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// `HANDSHAKE_TAG` is the first byte of the codec handshake.
+pub const HANDSHAKE_TAG: u8 = 0xad;
+
+/// `HANDSHAKE_LEN` is the length of the codec handshake.
+pub const HANDSHAKE_LEN: usize = 2;
+
+/// `Codec` is the serialization format of the `Message` payloads and of the
+/// binary fields of the gRPC messages.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Codec {
+    Cbor,
+    MessagePack,
+    Protobuf,
+}
+
+impl Codec {
+    /// `VALID_CODECS` sets the valid codec names.
+    pub const VALID_CODECS: &'static [&'static str] = &["cbor", "msgpack", "protobuf"];
+
+    /// `parse` parses a `Codec` from a string.
+    pub fn parse(s: &str) -> Result<Codec> {
+        match s {
+            "cbor" => Ok(Codec::Cbor),
+            "msgpack" => Ok(Codec::MessagePack),
+            "protobuf" => Ok(Codec::Protobuf),
+            _ => {
+                let msg = format!("invalid codec {}", s);
+                let err = Error::Codec { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `to_u8` returns the binary tag of the `Codec`.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Codec::Cbor => 0,
+            Codec::MessagePack => 1,
+            Codec::Protobuf => 2,
+        }
+    }
+
+    /// `from_u8` returns the `Codec` of a binary tag.
+    pub fn from_u8(tag: u8) -> Result<Codec> {
+        match tag {
+            0 => Ok(Codec::Cbor),
+            1 => Ok(Codec::MessagePack),
+            2 => Ok(Codec::Protobuf),
+            _ => {
+                let msg = format!("invalid codec tag {}", tag);
+                let err = Error::Codec { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `is_schemaless` returns if the `Codec` can encode any serializable type.
+    /// Protobuf needs a message schema, so it is only used by the gRPC interface.
+    pub fn is_schemaless(self) -> bool {
+        self != Codec::Protobuf
+    }
+
+    /// `negotiate` returns the `Codec` accepted for an offered one. The
+    /// offered `Codec` is accepted if it is the configured one and it can
+    /// encode the `Message` payloads, falling back to CBOR otherwise.
+    pub fn negotiate(self, offered: Codec) -> Codec {
+        if self == offered && offered.is_schemaless() {
+            offered
+        } else {
+            Codec::Cbor
+        }
+    }
+
+    /// `encode` encodes a value. MessagePack values are encoded as maps, so that
+    /// clients can read them without knowing the order of the fields.
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Codec::Cbor => serde_cbor::to_vec(value).map_err(|e| e.into()),
+            Codec::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.into()),
+            Codec::Protobuf => {
+                let msg = "protobuf needs a message schema".into();
+                let err = Error::Codec { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `decode` decodes a value. The binary is checked against the default
+    /// `CborLimits` before decoding, whatever the `Codec`.
+    pub fn decode<T: DeserializeOwned>(self, buf: &[u8]) -> Result<T> {
+        self.decode_with_limits(buf, &CborLimits::default())
+    }
+
+    /// `decode_with_limits` decodes a value, checking the binary against a
+    /// `CborLimits` before decoding.
+    pub fn decode_with_limits<T: DeserializeOwned>(
+        self,
+        buf: &[u8],
+        limits: &CborLimits,
+    ) -> Result<T> {
+        match self {
+            Codec::Cbor => cbor::from_slice(buf, limits).map_err(|e| e.into()),
+            Codec::MessagePack => {
+                msgpack::check(buf, limits)?;
+                rmp_serde::from_slice(buf).map_err(|e| e.into())
+            }
+            Codec::Protobuf => {
+                let msg = "protobuf needs a message schema".into();
+                let err = Error::Codec { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `write_handshake` writes the codec handshake of a `Codec`.
+    pub fn write_handshake<W: Write>(self, writer: &mut W) -> Result<()> {
+        writer.write_all(&[HANDSHAKE_TAG, self.to_u8()])?;
+        writer.flush().map_err(|e| e.into())
+    }
+
+    /// `read_handshake` reads a `Codec` from a codec handshake.
+    pub fn read_handshake<R: Read>(reader: &mut R) -> Result<Codec> {
+        let mut buf = [0u8; HANDSHAKE_LEN];
+        reader.read_exact(&mut buf)?;
+
+        if buf[0] != HANDSHAKE_TAG {
+            let msg = "invalid handshake".into();
+            let err = Error::Codec { msg };
+            return Err(err);
+        }
+
+        Codec::from_u8(buf[1])
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Codec {
+        Codec::Cbor
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Codec::Cbor => write!(f, "cbor"),
+            Codec::MessagePack => write!(f, "msgpack"),
+            Codec::Protobuf => write!(f, "protobuf"),
+        }
+    }
+}
+
+#[test]
+fn test_codec_parse() {
+    for name in Codec::VALID_CODECS.iter() {
+        let res = Codec::parse(name);
+        assert!(res.is_ok());
+        let codec = res.unwrap();
+        assert_eq!(&codec.to_string(), name);
+
+        let res = Codec::from_u8(codec.to_u8());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), codec);
+    }
+
+    let res = Codec::parse("xml");
+    assert!(res.is_err());
+
+    let res = Codec::from_u8(3);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_codec_encode() {
+    use models::node::Node;
+
+    let node = Node::random(100).unwrap();
+
+    for codec in &[Codec::Cbor, Codec::MessagePack] {
+        let res = codec.encode(&node);
+        assert!(res.is_ok());
+        let buf = res.unwrap();
+
+        let res = codec.decode::<Node>(&buf);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), node);
+    }
+
+    let cbor = Codec::Cbor.encode(&node).unwrap();
+    let res = Codec::MessagePack.decode::<Node>(&cbor);
+    assert!(res.is_err());
+
+    let res = Codec::Protobuf.encode(&node);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_codec_decode_limits() {
+    let limits = CborLimits::new(1 << 10, 4, 2);
+
+    for codec in &[Codec::Cbor, Codec::MessagePack] {
+        let buf = codec.encode(&vec![0u64; 4]).unwrap();
+        let res = codec.decode_with_limits::<Vec<u64>>(&buf, &limits);
+        assert!(res.is_ok());
+
+        let buf = codec.encode(&vec![0u64; 5]).unwrap();
+        let res = codec.decode_with_limits::<Vec<u64>>(&buf, &limits);
+        assert!(res.is_err());
+
+        let buf = codec.encode(&vec![vec![vec![0u64]]]).unwrap();
+        let res = codec.decode_with_limits::<Vec<Vec<Vec<u64>>>>(&buf, &limits);
+        assert!(res.is_err());
+
+        let len = CborLimits::DEFAULT_MAX_COLLECTION_LEN as usize + 1;
+        let buf = codec.encode(&vec![0u8; len]).unwrap();
+        let res = codec.decode::<Vec<u8>>(&buf);
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn test_codec_handshake() {
+    use std::io::Cursor;
+
+    assert_eq!(
+        Codec::MessagePack.negotiate(Codec::MessagePack),
+        Codec::MessagePack
+    );
+    assert_eq!(Codec::Cbor.negotiate(Codec::MessagePack), Codec::Cbor);
+    assert_eq!(Codec::Protobuf.negotiate(Codec::Protobuf), Codec::Cbor);
+
+    let mut buf = Vec::new();
+    let res = Codec::MessagePack.write_handshake(&mut buf);
+    assert!(res.is_ok());
+    assert_eq!(buf.len(), HANDSHAKE_LEN);
+
+    let res = Codec::read_handshake(&mut Cursor::new(&buf));
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Codec::MessagePack);
+
+    let res = Codec::read_handshake(&mut Cursor::new(&[0u8, 1]));
+    assert!(res.is_err());
+}
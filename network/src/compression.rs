@@ -0,0 +1,205 @@
+//! # Compression
+//!
+//! `compression` contains the message compression type and functions.
+//!
+//! The compression of a connection is negotiated in a handshake: the dialing
+//! peer offers its configured `Compression`, and the listening peer accepts it
+//! only if it is configured to compress too, answering `Compression::None` otherwise.
+
+use crate::error::Error;
+use crate::result::Result;
+use bytes::Bytes;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// `HANDSHAKE_TAG` is the first byte of the compression handshake.
+pub const HANDSHAKE_TAG: u8 = 0xac;
+
+/// `HANDSHAKE_LEN` is the length of the compression handshake.
+pub const HANDSHAKE_LEN: usize = 2;
+
+/// `Compression` is the compression of the `Message` payloads.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Compression {
+    None,
+    Snappy,
+}
+
+impl Compression {
+    /// `parse` parses a `Compression` from a string.
+    pub fn parse(s: &str) -> Result<Compression> {
+        match s {
+            "none" => Ok(Compression::None),
+            "snappy" => Ok(Compression::Snappy),
+            _ => {
+                let msg = format!("invalid compression {}", s);
+                let err = Error::Compression { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `to_u8` returns the binary tag of the `Compression`.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Snappy => 1,
+        }
+    }
+
+    /// `from_u8` returns the `Compression` of a binary tag.
+    pub fn from_u8(tag: u8) -> Result<Compression> {
+        match tag {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Snappy),
+            _ => {
+                let msg = format!("invalid compression tag {}", tag);
+                let err = Error::Compression { msg };
+                Err(err)
+            }
+        }
+    }
+
+    /// `negotiate` returns the `Compression` accepted for an offered one.
+    pub fn negotiate(self, offered: Compression) -> Compression {
+        if self == offered {
+            offered
+        } else {
+            Compression::None
+        }
+    }
+
+    /// `compress` compresses a binary.
+    pub fn compress(self, buf: &[u8]) -> Result<Bytes> {
+        match self {
+            Compression::None => Ok(Bytes::copy_from_slice(buf)),
+            Compression::Snappy => {
+                let buf = snap::raw::Encoder::new().compress_vec(buf)?;
+                Ok(buf.into())
+            }
+        }
+    }
+
+    /// `decompress` decompresses a binary, failing if the decompressed binary
+    /// would be longer than a maximum length.
+    pub fn decompress(self, buf: Bytes, max_len: u64) -> Result<Bytes> {
+        match self {
+            Compression::None => Ok(buf),
+            Compression::Snappy => {
+                if snap::raw::decompress_len(&buf)? as u64 > max_len {
+                    let err = Error::InvalidLength;
+                    return Err(err);
+                }
+
+                let buf = snap::raw::Decoder::new().decompress_vec(&buf)?;
+                Ok(buf.into())
+            }
+        }
+    }
+
+    /// `write_handshake` writes the compression handshake of a `Compression`.
+    pub fn write_handshake<W: Write>(self, writer: &mut W) -> Result<()> {
+        writer.write_all(&[HANDSHAKE_TAG, self.to_u8()])?;
+        writer.flush().map_err(|e| e.into())
+    }
+
+    /// `read_handshake` reads a `Compression` from a compression handshake.
+    pub fn read_handshake<R: Read>(reader: &mut R) -> Result<Compression> {
+        let mut buf = [0u8; HANDSHAKE_LEN];
+        reader.read_exact(&mut buf)?;
+
+        if buf[0] != HANDSHAKE_TAG {
+            let msg = "invalid handshake".into();
+            let err = Error::Compression { msg };
+            return Err(err);
+        }
+
+        Compression::from_u8(buf[1])
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Compression {
+        Compression::None
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Snappy => write!(f, "snappy"),
+        }
+    }
+}
+
+#[test]
+fn test_compression_parse() {
+    for compression in &[Compression::None, Compression::Snappy] {
+        let res = Compression::parse(&compression.to_string());
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap(), compression);
+
+        let res = Compression::from_u8(compression.to_u8());
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap(), compression);
+    }
+
+    let res = Compression::parse("zip");
+    assert!(res.is_err());
+
+    let res = Compression::from_u8(2);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_compression_compress() {
+    let buf = vec![7u8; 1 << 12];
+
+    for compression in &[Compression::None, Compression::Snappy] {
+        let res = compression.compress(&buf);
+        assert!(res.is_ok());
+        let compressed = res.unwrap();
+
+        if compression == &Compression::Snappy {
+            assert!(compressed.len() < buf.len());
+
+            let res = compression.decompress(compressed.clone(), buf.len() as u64 - 1);
+            assert!(res.is_err());
+        }
+
+        let res = compression.decompress(compressed, buf.len() as u64);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), buf);
+    }
+}
+
+#[test]
+fn test_compression_handshake() {
+    use std::io::Cursor;
+
+    assert_eq!(
+        Compression::Snappy.negotiate(Compression::Snappy),
+        Compression::Snappy
+    );
+    assert_eq!(
+        Compression::None.negotiate(Compression::Snappy),
+        Compression::None
+    );
+    assert_eq!(
+        Compression::Snappy.negotiate(Compression::None),
+        Compression::None
+    );
+
+    let mut buf = Vec::new();
+    let res = Compression::Snappy.write_handshake(&mut buf);
+    assert!(res.is_ok());
+    assert_eq!(buf.len(), HANDSHAKE_LEN);
+
+    let res = Compression::read_handshake(&mut Cursor::new(&buf));
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Compression::Snappy);
+
+    let res = Compression::read_handshake(&mut Cursor::new(&[0u8, 1]));
+    assert!(res.is_err());
+}
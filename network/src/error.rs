@@ -7,12 +7,13 @@ use config::error::Error as ConfigError;
 use crypto::error::Error as CryptoError;
 use mining::error::Error as MiningError;
 use models::error::Error as ModelError;
+use models::error_code::ErrorCode;
 use serde_cbor;
 use serde_json;
 use std::convert::From;
 use std::io;
 use std::net;
-use std::sync::mpsc::{RecvError, SendError};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError};
 use store::error::Error as StoreError;
 
 #[derive(Debug, Fail)]
@@ -26,13 +27,19 @@ pub enum Error {
     #[fail(display = "Store: {}", msg)]
     Store { msg: String },
     #[fail(display = "Model: {}", msg)]
-    Model { msg: String },
+    Model { code: ErrorCode, msg: String },
     #[fail(display = "Config: {}", msg)]
     Config { msg: String },
     #[fail(display = "Consensus: {}", msg)]
-    Consensus { msg: String },
+    Consensus { code: ErrorCode, msg: String },
     #[fail(display = "Parse: {}", msg)]
     Parse { msg: String },
+    #[fail(display = "Proxy: {}", msg)]
+    Proxy { msg: String },
+    #[fail(display = "Compression: {}", msg)]
+    Compression { msg: String },
+    #[fail(display = "Codec: {}", msg)]
+    Codec { msg: String },
     #[fail(display = "Invalid id")]
     InvalidId,
     #[fail(display = "Invalid length")]
@@ -49,6 +56,36 @@ pub enum Error {
     InvalidAddress,
     #[fail(display = "Invalid kind")]
     InvalidKind,
+    #[fail(display = "Invalid faults")]
+    InvalidFaults,
+    #[fail(display = "Blacklisted address")]
+    Blacklisted,
+}
+
+impl Error {
+    /// `code` returns the `ErrorCode` of the `Error`.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::IO { .. }
+            | Error::Proxy { .. }
+            | Error::Compression { .. }
+            | Error::Codec { .. } => ErrorCode::Network,
+            Error::Crypto { .. } | Error::Config { .. } => ErrorCode::Internal,
+            Error::Mining { .. } => ErrorCode::Mining,
+            Error::Store { .. } => ErrorCode::Store,
+            Error::Model { code, .. } | Error::Consensus { code, .. } => *code,
+            Error::Parse { .. } => ErrorCode::InvalidEncoding,
+            Error::InvalidId => ErrorCode::InvalidId,
+            Error::InvalidLength => ErrorCode::InvalidLength,
+            Error::NotImplemented => ErrorCode::NotImplemented,
+            Error::NotAllowed => ErrorCode::NotAllowed,
+            Error::AlreadyFound => ErrorCode::AlreadyFound,
+            Error::NotFound => ErrorCode::NotFound,
+            Error::InvalidAddress => ErrorCode::InvalidAddress,
+            Error::InvalidKind | Error::InvalidFaults => ErrorCode::InvalidArgument,
+            Error::Blacklisted => ErrorCode::BannedNode,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -58,6 +95,13 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<snap::Error> for Error {
+    fn from(error: snap::Error) -> Error {
+        let msg = format!("{}", error);
+        Error::Compression { msg }
+    }
+}
+
 impl From<net::AddrParseError> for Error {
     fn from(error: net::AddrParseError) -> Error {
         let msg = format!("{}", error);
@@ -72,6 +116,20 @@ impl From<serde_cbor::error::Error> for Error {
     }
 }
 
+impl From<rmp_serde::encode::Error> for Error {
+    fn from(err: rmp_serde::encode::Error) -> Error {
+        let msg = format!("{}", err);
+        Error::Parse { msg }
+    }
+}
+
+impl From<rmp_serde::decode::Error> for Error {
+    fn from(err: rmp_serde::decode::Error) -> Error {
+        let msg = format!("{}", err);
+        Error::Parse { msg }
+    }
+}
+
 impl From<serde_json::error::Error> for Error {
     fn from(err: serde_json::error::Error) -> Error {
         let msg = format!("{}", err);
@@ -93,6 +151,13 @@ impl From<RecvError> for Error {
     }
 }
 
+impl From<RecvTimeoutError> for Error {
+    fn from(error: RecvTimeoutError) -> Error {
+        let msg = format!("{}", error);
+        Error::IO { msg }
+    }
+}
+
 impl From<CryptoError> for Error {
     fn from(error: CryptoError) -> Error {
         let msg = format!("{}", error);
@@ -116,8 +181,9 @@ impl From<StoreError> for Error {
 
 impl From<ModelError> for Error {
     fn from(error: ModelError) -> Error {
+        let code = error.code();
         let msg = format!("{}", error);
-        Error::Model { msg }
+        Error::Model { code, msg }
     }
 }
 
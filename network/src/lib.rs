@@ -14,9 +14,21 @@ pub mod result;
 /// `message` contains the networking message type and functions.
 pub mod message;
 
+/// `compression` contains the message compression type and functions.
+pub mod compression;
+
+/// `codec` contains the message codec type and functions.
+pub mod codec;
+
 /// `traits` contains the networking traits used in the crate.
 pub mod traits;
 
+/// `blacklist` contains the address blacklist type and functions.
+pub mod blacklist;
+
+/// `socks` contains the SOCKS5 proxy client functions.
+pub mod socks;
+
 /// `backend` contains the networking backends.
 pub mod backend;
 
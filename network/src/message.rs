@@ -2,25 +2,57 @@
 //!
 //! `message` contains the network message used in the crate.
 
+use crate::codec::Codec;
+use crate::compression::Compression;
 use crate::error::Error;
 use crate::result::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use crypto::random::Random;
+use models::cbor::CborLimits;
 use models::consensus_message::ConsensusMessage;
 use serde::{Deserialize, Serialize};
+use std::io::Write;
 
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
-/// `Message` is the network message used in the crate.
+/// `Message` is the network message used in the crate. Its fields are
+/// reference-counted buffers, so that decoding a `Message` slices the
+/// received buffer instead of copying it.
 pub struct Message {
-    pub address: Vec<u8>,
-    pub data: Vec<u8>,
+    pub address: Bytes,
+    pub data: Bytes,
 }
 
 impl Message {
+    /// `MAX_DATA_LEN` is the maximum length of the `Message` data.
+    pub const MAX_DATA_LEN: u64 = CborLimits::DEFAULT_MAX_LENGTH;
+
+    /// `MAX_ADDRESS_LEN` is the maximum length of the `Message` address.
+    pub const MAX_ADDRESS_LEN: u64 = 1 << 10;
+
+    /// `HEADER_LEN` is the length of the binary `Message` header, made of the
+    /// big-endian lengths of the address and of the data.
+    pub const HEADER_LEN: u64 = 8;
+
+    /// `MAX_LEN` is the maximum length of a binary `Message`.
+    pub const MAX_LEN: u64 = Self::HEADER_LEN + Self::MAX_ADDRESS_LEN + Self::MAX_DATA_LEN;
+
+    /// `new` creates a new `Message`.
+    pub fn new<A: Into<Bytes>, D: Into<Bytes>>(address: A, data: D) -> Result<Message> {
+        let msg = Message {
+            address: address.into(),
+            data: data.into(),
+        };
+
+        msg.validate()?;
+
+        Ok(msg)
+    }
+
     /// `random` creates a random `Message`.
     pub fn random(address_len: u32, data_len: u32) -> Result<Message> {
         let msg = Message {
-            address: Random::bytes(address_len as usize)?,
-            data: Random::bytes(data_len as usize)?,
+            address: Random::bytes(address_len as usize)?.into(),
+            data: Random::bytes(data_len as usize)?.into(),
         };
 
         Ok(msg)
@@ -33,14 +65,33 @@ impl Message {
         let address = cons_msg.node().address;
         let data = cons_msg.to_bytes()?;
 
-        let msg = Message { address, data };
+        Message::new(address, data)
+    }
 
-        Ok(msg)
+    /// `from_compact_consensus_message` creates a `Message` from a `ConsensusMessage`
+    /// in its compact binary encoding.
+    pub fn from_compact_consensus_message(cons_msg: &ConsensusMessage) -> Result<Message> {
+        cons_msg.validate()?;
+
+        let address = cons_msg.node().address;
+        let data = cons_msg.to_compact_bytes()?;
+
+        Message::new(address, data)
     }
 
-    /// `to_consensus_message` converts the `Message` to a `ConsensusMessage`.
+    /// `is_compact` returns if the `Message` data is a compact `ConsensusMessage`.
+    pub fn is_compact(&self) -> bool {
+        ConsensusMessage::is_compact_bytes(&self.data)
+    }
+
+    /// `to_consensus_message` converts the `Message` to a `ConsensusMessage`,
+    /// decoding either its CBOR or its compact binary encoding.
     pub fn to_consensus_message(&self) -> Result<ConsensusMessage> {
-        let cons_msg = ConsensusMessage::from_bytes(&self.data)?;
+        let cons_msg = if self.is_compact() {
+            ConsensusMessage::from_compact_bytes(&self.data)?
+        } else {
+            ConsensusMessage::from_bytes(&self.data)?
+        };
         cons_msg.validate()?;
 
         if cons_msg.node().address != self.address {
@@ -51,14 +102,141 @@ impl Message {
         Ok(cons_msg)
     }
 
-    /// `to_bytes` converts the `Message` into a CBOR binary.
-    pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        serde_cbor::to_vec(self).map_err(|e| e.into())
+    /// `validate` validates the `Message`.
+    pub fn validate(&self) -> Result<()> {
+        if self.address.len() as u64 > Self::MAX_ADDRESS_LEN {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        if self.data.len() as u64 > Self::MAX_DATA_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `transcode` returns the `Message` with its `ConsensusMessage` data
+    /// re-encoded from a `Codec` to another one. The compact `ConsensusMessage`s
+    /// are exchanged only between peers and are never transcoded.
+    pub fn transcode(&self, from: Codec, to: Codec) -> Result<Message> {
+        if from == to || self.is_compact() {
+            return Ok(self.clone());
+        }
+
+        let cons_msg: ConsensusMessage = from.decode(&self.data)?;
+
+        let msg = Message {
+            address: self.address.clone(),
+            data: to.encode(&cons_msg)?.into(),
+        };
+
+        msg.validate()?;
+
+        Ok(msg)
+    }
+
+    /// `compress` returns the `Message` with its data compressed.
+    pub fn compress(&self, compression: Compression) -> Result<Message> {
+        if compression == Compression::None {
+            return Ok(self.clone());
+        }
+
+        let msg = Message {
+            address: self.address.clone(),
+            data: compression.compress(&self.data)?,
+        };
+
+        msg.validate()?;
+
+        Ok(msg)
     }
 
-    /// `from_bytes` converts a CBOR binary into an `Message`.
-    pub fn from_bytes(b: &[u8]) -> Result<Message> {
-        serde_cbor::from_slice(b).map_err(|e| e.into())
+    /// `decompress` returns the `Message` with its data decompressed.
+    pub fn decompress(self, compression: Compression) -> Result<Message> {
+        let msg = Message {
+            address: self.address,
+            data: compression.decompress(self.data, Self::MAX_DATA_LEN)?,
+        };
+
+        Ok(msg)
+    }
+
+    /// `encoded_len` returns the length of the binary `Message`.
+    pub fn encoded_len(&self) -> usize {
+        Self::HEADER_LEN as usize + self.address.len() + self.data.len()
+    }
+
+    /// `encode` appends the binary `Message` to a buffer.
+    pub fn encode(&self, buf: &mut BytesMut) -> Result<()> {
+        self.validate()?;
+
+        buf.reserve(self.encoded_len());
+        buf.put_u32(self.address.len() as u32);
+        buf.put_u32(self.data.len() as u32);
+        buf.put_slice(&self.address);
+        buf.put_slice(&self.data);
+
+        Ok(())
+    }
+
+    /// `write_to` streams the binary `Message` into a writer, without
+    /// buffering its payload.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.validate()?;
+
+        let mut header = [0u8; Self::HEADER_LEN as usize];
+        header[..4].copy_from_slice(&(self.address.len() as u32).to_be_bytes());
+        header[4..].copy_from_slice(&(self.data.len() as u32).to_be_bytes());
+
+        writer.write_all(&header)?;
+        writer.write_all(&self.address)?;
+        writer.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Message` into a binary.
+    pub fn to_bytes(&self) -> Result<Bytes> {
+        let mut buf = BytesMut::with_capacity(self.encoded_len());
+        self.encode(&mut buf)?;
+
+        Ok(buf.freeze())
+    }
+
+    /// `from_bytes` converts a binary into a `Message`. The address and the
+    /// data of the `Message` are slices of the binary, which is not copied.
+    pub fn from_bytes(mut buf: Bytes) -> Result<Message> {
+        if (buf.len() as u64) < Self::HEADER_LEN {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let address_len = buf.get_u32() as u64;
+        let data_len = buf.get_u32() as u64;
+
+        if address_len > Self::MAX_ADDRESS_LEN {
+            let err = Error::InvalidAddress;
+            return Err(err);
+        }
+
+        if data_len > Self::MAX_DATA_LEN || buf.len() as u64 != address_len + data_len {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let address = buf.split_to(address_len as usize);
+        let data = buf;
+
+        let msg = Message { address, data };
+
+        Ok(msg)
+    }
+
+    /// `from_slice` converts a binary slice into a `Message`, copying it once.
+    pub fn from_slice(b: &[u8]) -> Result<Message> {
+        Message::from_bytes(Bytes::copy_from_slice(b))
     }
 
     /// `to_json` converts the `Message` into a JSON string.
@@ -99,6 +277,66 @@ fn test_message_consensus_message() {
     assert_eq!(cons_msg_a, cons_msg_b)
 }
 
+#[test]
+fn test_message_compact_consensus_message() {
+    use models::node::Node;
+    use models::stage::Stage;
+    use models::transaction::Transaction;
+
+    let address = Random::bytes(100).unwrap();
+    let node = Node::new(Stage::Testing, &Random::bytes(100).unwrap());
+    let transaction = Transaction::new().unwrap();
+
+    let cons_msg_a = ConsensusMessage::new_compact_query(&address, &node, &transaction).unwrap();
+
+    let res = Message::from_compact_consensus_message(&cons_msg_a);
+    assert!(res.is_ok());
+
+    let msg = res.unwrap();
+    assert!(msg.is_compact());
+
+    let res = msg.transcode(Codec::Cbor, Codec::MessagePack);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), msg);
+
+    let res = msg.to_consensus_message();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), cons_msg_a);
+
+    let cbor_msg = Message::from_consensus_message(&cons_msg_a).unwrap();
+    assert!(!cbor_msg.is_compact());
+    assert!(msg.data.len() < cbor_msg.data.len());
+    assert_eq!(cbor_msg.to_consensus_message().unwrap(), cons_msg_a);
+}
+
+#[test]
+fn test_message_transcode() {
+    use crypto::hash::Digest;
+    use models::node::Node;
+
+    let address_len = 100;
+    let address = Random::bytes(address_len).unwrap();
+    let node = Node::random(address_len).unwrap();
+    let query_id = Random::u64().unwrap();
+    let tx_id = Digest::random().unwrap();
+
+    let cons_msg = ConsensusMessage::new_reply(&address, query_id, &node, tx_id, true).unwrap();
+    let msg = Message::from_consensus_message(&cons_msg).unwrap();
+
+    let res = msg.transcode(Codec::Cbor, Codec::MessagePack);
+    assert!(res.is_ok());
+    let msgpack_msg = res.unwrap();
+    assert_eq!(msgpack_msg.address, msg.address);
+    assert_ne!(msgpack_msg.data, msg.data);
+
+    let res = msgpack_msg.transcode(Codec::MessagePack, Codec::Cbor);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), msg);
+
+    let res = msg.transcode(Codec::Cbor, Codec::Protobuf);
+    assert!(res.is_err());
+}
+
 #[test]
 fn test_message_serialize_bytes() {
     let address_len = 100;
@@ -109,9 +347,15 @@ fn test_message_serialize_bytes() {
 
         let res = message_a.to_bytes();
         assert!(res.is_ok());
-        let cbor = res.unwrap();
+        let buf = res.unwrap();
+
+        let res = Message::from_slice(&buf);
+        assert!(res.is_ok());
+        let message_b = res.unwrap();
 
-        let res = Message::from_bytes(&cbor);
+        assert_eq!(message_a, message_b);
+
+        let res = Message::from_bytes(buf);
         assert!(res.is_ok());
         let message_b = res.unwrap();
 
@@ -138,3 +382,61 @@ fn test_message_serialize_json() {
         assert_eq!(message_a, message_b)
     }
 }
+
+#[test]
+fn test_message_limits() {
+    let address_len = 100;
+    let data_len = 1000;
+
+    let msg = Message::random(address_len, data_len).unwrap();
+    let buf = msg.to_bytes().unwrap();
+    assert_eq!(buf.len(), msg.encoded_len());
+
+    let res = Message::from_bytes(buf.clone());
+    assert!(res.is_ok());
+
+    let res = Message::from_bytes(buf.slice(..buf.len() - 1));
+    assert!(res.is_err());
+
+    let res = Message::from_bytes(buf.slice(..4));
+    assert!(res.is_err());
+
+    let invalid_address_len = Message::MAX_ADDRESS_LEN as u32 + 1;
+    let invalid_msg = Message::random(invalid_address_len, data_len).unwrap();
+
+    let res = invalid_msg.to_bytes();
+    assert!(res.is_err());
+
+    let mut invalid_buf = BytesMut::new();
+    invalid_buf.put_u32(invalid_address_len);
+    invalid_buf.put_u32(data_len);
+    invalid_buf.put_slice(&invalid_msg.address);
+    invalid_buf.put_slice(&invalid_msg.data);
+
+    let res = Message::from_bytes(invalid_buf.freeze());
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_message_write_to() {
+    let address_len = 100;
+    let data_len = 1000;
+
+    let msg = Message::random(address_len, data_len).unwrap();
+
+    let mut buf = Vec::new();
+    let res = msg.write_to(&mut buf);
+    assert!(res.is_ok());
+
+    assert_eq!(buf.as_slice(), &msg.to_bytes().unwrap()[..]);
+
+    let mut bytes_buf = BytesMut::new();
+    msg.encode(&mut bytes_buf).unwrap();
+    msg.encode(&mut bytes_buf).unwrap();
+
+    let first = bytes_buf.split_to(msg.encoded_len()).freeze();
+    let second = bytes_buf.freeze();
+
+    assert_eq!(Message::from_bytes(first).unwrap(), msg);
+    assert_eq!(Message::from_bytes(second).unwrap(), msg);
+}
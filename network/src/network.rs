@@ -2,10 +2,17 @@
 //!
 //! `network` is the module containing the network type and functions.
 
-use crate::backend::TcpNetwork;
+use crate::backend::{MultiNetwork, TcpNetwork};
+use crate::blacklist::Blacklist;
+use crate::codec::Codec;
+use crate::compression::Compression;
 use crate::error::Error;
 use crate::result::Result;
 use config::network::NetworkConfig;
+use models::net_address::NetAddress;
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 
 /// `NetworkFactory` is the factory for network types.
 pub struct NetworkFactory {}
@@ -18,7 +25,7 @@ impl NetworkFactory {
         let mut config = config.clone();
         config.populate();
 
-        match config.kind.unwrap().as_str() {
+        let mut network = match config.kind.clone().unwrap().as_str() {
             "consensus" => {
                 let addr = config.consensus_address.clone().unwrap();
                 TcpNetwork::new(&addr)
@@ -35,6 +42,91 @@ impl NetworkFactory {
                 let err = Error::InvalidKind;
                 Err(err)
             }
+        }?;
+
+        network.set_proxy(NetworkFactory::proxy(&config)?);
+        network.set_compression(Compression::parse(&config.compression.clone().unwrap())?);
+        network.set_codec(Codec::parse(&config.codec.clone().unwrap())?);
+        network.set_blacklist(Arc::new(Mutex::new(NetworkFactory::blacklist(&config)?)));
+
+        Ok(network)
+    }
+
+    /// `create_multi` creates a new network from the configs, listening on the
+    /// address of the network kind and on the additional listen addresses.
+    /// The listeners share the proxy, compression, codec and blacklist settings.
+    pub fn create_multi(config: &NetworkConfig) -> Result<MultiNetwork<TcpNetwork>> {
+        let primary = NetworkFactory::create(config)?;
+        let mut networks = vec![primary.clone()];
+
+        if let Some(ref listen_addresses) = config.listen_addresses {
+            for address in listen_addresses {
+                let mut network = TcpNetwork::new(address)?;
+
+                network.set_proxy(primary.proxy());
+                network.set_compression(primary.compression());
+                network.set_codec(primary.codec());
+                network.set_blacklist(primary.blacklist());
+
+                networks.push(network);
+            }
         }
+
+        MultiNetwork::new(networks)
+    }
+
+    /// `proxy` returns the resolved SOCKS5 proxy address of the configs, if any.
+    pub fn proxy(config: &NetworkConfig) -> Result<Option<SocketAddr>> {
+        if let Some(ref proxy_address) = config.proxy_address {
+            let address = NetAddress::parse(proxy_address)?;
+
+            if let Some(proxy) = address.to_socket_addrs()?.first() {
+                Ok(Some(*proxy))
+            } else {
+                let err = Error::InvalidAddress;
+                Err(err)
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// `blacklist` loads the `Blacklist` file of the configs, if any.
+    pub fn blacklist(config: &NetworkConfig) -> Result<Blacklist> {
+        if let Some(ref path) = config.blacklist_path {
+            Blacklist::load(path)
+        } else {
+            Ok(Blacklist::default())
+        }
+    }
+
+    /// `advertised_addresses` returns the `NetAddress`es the node advertises
+    /// to its peers. When none are configured, the listening addresses of the
+    /// network kind are advertised.
+    pub fn advertised_addresses(config: &NetworkConfig) -> Result<BTreeSet<NetAddress>> {
+        config.validate()?;
+
+        let mut config = config.clone();
+        config.populate();
+
+        let mut addresses = BTreeSet::new();
+
+        if let Some(ref advertised_addresses) = config.advertised_addresses {
+            for address in advertised_addresses {
+                let address = NetAddress::parse(address)?;
+                addresses.insert(address);
+            }
+        }
+
+        if addresses.is_empty() {
+            let network = NetworkFactory::create_multi(&config)?;
+
+            for network in network.networks() {
+                let address = network.net_address()?;
+                addresses.insert(address);
+            }
+        }
+
+        Ok(addresses)
     }
 }
@@ -0,0 +1,192 @@
+//! # Socks
+//!
+//! `socks` contains the SOCKS5 proxy client functions used to dial through
+//! a proxy, as the one exposed by a Tor daemon.
+
+use crate::error::Error;
+use crate::result::Result;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use models::net_address::NetAddress;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// `VERSION` is the SOCKS protocol version.
+const VERSION: u8 = 5;
+
+/// `NO_AUTH` is the SOCKS5 no-authentication method.
+const NO_AUTH: u8 = 0;
+
+/// `CONNECT` is the SOCKS5 connect command.
+const CONNECT: u8 = 1;
+
+/// `ATYP_IPV4` is the SOCKS5 IPv4 address type.
+const ATYP_IPV4: u8 = 1;
+
+/// `ATYP_DOMAIN` is the SOCKS5 domain name address type.
+const ATYP_DOMAIN: u8 = 3;
+
+/// `ATYP_IPV6` is the SOCKS5 IPv6 address type.
+const ATYP_IPV6: u8 = 4;
+
+/// `reply_message` returns the description of a SOCKS5 reply code.
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// `write_target` writes the SOCKS5 address and port of a `NetAddress`.
+fn write_target(buf: &mut Vec<u8>, target: &NetAddress) -> Result<()> {
+    target.validate()?;
+
+    match target {
+        NetAddress::Ipv4 { ip, .. } => {
+            buf.write_u8(ATYP_IPV4)?;
+            buf.extend_from_slice(ip);
+        }
+        NetAddress::Ipv6 { ip, .. } => {
+            buf.write_u8(ATYP_IPV6)?;
+            buf.extend_from_slice(ip);
+        }
+        NetAddress::Host { host, .. } | NetAddress::Onion { host, .. } => {
+            // NB: host names are resolved by the proxy, so that they do not leak
+            buf.write_u8(ATYP_DOMAIN)?;
+            buf.write_u8(host.len() as u8)?;
+            buf.extend_from_slice(host.as_bytes());
+        }
+    }
+
+    buf.write_u16::<BigEndian>(target.port())?;
+
+    Ok(())
+}
+
+/// `connect` opens a `TcpStream` to a target `NetAddress` through a SOCKS5 proxy.
+pub fn connect(proxy: &SocketAddr, target: &NetAddress, timeout: Option<u64>) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy)?;
+
+    let timeout = timeout.map(Duration::from_secs);
+
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+
+    stream.write_all(&[VERSION, 1, NO_AUTH])?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+
+    if reply[0] != VERSION {
+        let err = Error::Proxy {
+            msg: "invalid version".into(),
+        };
+        return Err(err);
+    }
+
+    if reply[1] != NO_AUTH {
+        let err = Error::Proxy {
+            msg: "authentication required".into(),
+        };
+        return Err(err);
+    }
+
+    let mut request = vec![VERSION, CONNECT, 0];
+    write_target(&mut request, target)?;
+
+    stream.write_all(&request)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+
+    if reply[0] != VERSION {
+        let err = Error::Proxy {
+            msg: "invalid version".into(),
+        };
+        return Err(err);
+    }
+
+    if reply[1] != 0 {
+        let err = Error::Proxy {
+            msg: reply_message(reply[1]).into(),
+        };
+        return Err(err);
+    }
+
+    let bound_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8()? as usize,
+        _ => {
+            let err = Error::Proxy {
+                msg: reply_message(8).into(),
+            };
+            return Err(err);
+        }
+    };
+
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound)?;
+
+    Ok(stream)
+}
+
+#[test]
+fn test_socks_connect() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = listener.local_addr().unwrap();
+
+    let data = b"alsacoin".to_vec();
+    let expected = data.clone();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let mut greeting = [0u8; 3];
+        stream.read_exact(&mut greeting).unwrap();
+        assert_eq!(greeting, [VERSION, 1, NO_AUTH]);
+
+        stream.write_all(&[VERSION, NO_AUTH]).unwrap();
+
+        let mut request = [0u8; 4];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request, [VERSION, CONNECT, 0, ATYP_DOMAIN]);
+
+        let len = stream.read_u8().unwrap() as usize;
+        let mut host = vec![0u8; len];
+        stream.read_exact(&mut host).unwrap();
+        let port = stream.read_u16::<BigEndian>().unwrap();
+
+        assert_eq!(&host, b"node.alsacoin.org");
+        assert_eq!(port, 2019);
+
+        stream
+            .write_all(&[VERSION, 0, 0, ATYP_IPV4, 127, 0, 0, 1, 0, 0])
+            .unwrap();
+
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+    });
+
+    let target = NetAddress::parse("node.alsacoin.org:2019").unwrap();
+
+    let res = connect(&proxy, &target, Some(10));
+    assert!(res.is_ok());
+
+    let mut stream = res.unwrap();
+    stream.write_all(&data).unwrap();
+    drop(stream);
+
+    server.join().unwrap();
+}
@@ -14,6 +14,13 @@ pub trait Network {
     /// `send` sends data to a `Node`.
     fn send(&mut self, address: &[u8], data: &[u8], timeout: Option<u64>) -> Result<()>;
 
+    /// `send_message` sends a `Message` to a `Node`. Transports writing to a
+    /// stream override it to encode the `Message` directly in the stream.
+    fn send_message(&mut self, address: &[u8], msg: &Message, timeout: Option<u64>) -> Result<()> {
+        let data = msg.to_bytes()?;
+        self.send(address, &data, timeout)
+    }
+
     /// `recv` receives data from a `Node`.
     fn recv(&mut self, timeout: Option<u64>) -> Result<Message>;
 
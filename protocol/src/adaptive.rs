@@ -0,0 +1,201 @@
+//! # Adaptive
+//!
+//! `adaptive` contains the adaptive consensus parameters type and functions.
+//!
+//! In adaptive mode the sample size k and the threshold alpha are adjusted to
+//! the observed network: k grows to compensate the failed queries, shrinks to
+//! the number of known nodes and to the replies that fit in the timeout, and
+//! alpha keeps its configured ratio to the replies expected from k nodes.
+//! Both stay within the bounds of the `ConsensusConfig`.
+
+use config::consensus::ConsensusConfig;
+
+/// `AdaptiveParams` keeps the metrics used to adjust k and alpha.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct AdaptiveParams {
+    enabled: bool,
+    k: u32,
+    alpha: u32,
+    min_k: u32,
+    max_k: u32,
+    min_alpha: u32,
+    timeout: u64,
+    failure_rate: u32,
+    latency: Option<u64>,
+}
+
+impl AdaptiveParams {
+    /// `WEIGHT` is the weight of the last observation in the rolling averages.
+    pub const WEIGHT: u64 = 8;
+
+    /// `SCALE` is the scale of the failure rate, in parts per thousand.
+    pub const SCALE: u32 = 1000;
+
+    /// `new` creates a new `AdaptiveParams` from a `ConsensusConfig`.
+    pub fn new(config: &ConsensusConfig) -> AdaptiveParams {
+        let mut params = AdaptiveParams::default();
+        params.set_config(config);
+        params
+    }
+
+    /// `set_config` sets the nominal parameters and the bounds of the
+    /// `AdaptiveParams` from a `ConsensusConfig`, keeping the metrics.
+    pub fn set_config(&mut self, config: &ConsensusConfig) {
+        let mut config = config.to_owned();
+        config.populate();
+
+        self.enabled = config.adaptive.unwrap();
+        self.k = config.k.unwrap();
+        self.alpha = config.alpha.unwrap();
+        self.min_k = config.min_k.unwrap();
+        self.max_k = config.max_k.unwrap();
+        self.min_alpha = config.min_alpha.unwrap();
+        self.timeout = config.timeout.unwrap();
+    }
+
+    /// `is_enabled` returns if the adaptive mode is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// `failure_rate` returns the rolling average of the failed queries, in
+    /// parts per thousand.
+    pub fn failure_rate(&self) -> u32 {
+        self.failure_rate
+    }
+
+    /// `latency` returns the rolling average of the replies round-trip time,
+    /// in milliseconds.
+    pub fn latency(&self) -> Option<u64> {
+        self.latency
+    }
+
+    /// `record_query` records the outcome of a query to a single node.
+    pub fn record_query(&mut self, success: bool) {
+        let weight = Self::WEIGHT as u32;
+        let rate = self.failure_rate * (weight - 1) / weight;
+
+        self.failure_rate = if success {
+            rate
+        } else {
+            rate + Self::SCALE / weight
+        };
+    }
+
+    /// `record_latency` records a reply round-trip time in milliseconds.
+    pub fn record_latency(&mut self, rtt: u64) {
+        let latency = match self.latency {
+            Some(latency) if rtt >= latency => latency + (rtt - latency) / Self::WEIGHT,
+            Some(latency) => latency - (latency - rtt) / Self::WEIGHT,
+            None => rtt,
+        };
+
+        self.latency = Some(latency);
+    }
+
+    /// `clear` resets the metrics of the `AdaptiveParams`.
+    pub fn clear(&mut self) {
+        self.failure_rate = 0;
+        self.latency = None;
+    }
+
+    /// `k` returns the sample size given the number of known nodes. Without
+    /// the adaptive mode, it is the configured k.
+    pub fn k(&self, known_nodes: usize) -> u32 {
+        if !self.enabled {
+            return self.k;
+        }
+
+        // NB: the expected replies of k nodes are k * (1 - failure_rate)
+        let success_rate = u64::from(Self::SCALE - self.failure_rate.min(Self::SCALE - 1));
+        let mut k = (u64::from(self.k) * u64::from(Self::SCALE) + success_rate - 1) / success_rate;
+
+        k = k.min(known_nodes as u64);
+
+        // NB: the nodes are queried one after the other, so the replies of
+        // at most timeout / latency nodes fit in the timeout
+        if let Some(latency) = self.latency {
+            if latency > 0 {
+                k = k.min(self.timeout * 1000 / latency);
+            }
+        }
+
+        (k as u32).max(self.min_k).min(self.max_k)
+    }
+
+    /// `alpha` returns the threshold for a sample size, keeping the configured
+    /// ratio of alpha to k of the expected replies. Without the adaptive mode,
+    /// it is the configured alpha.
+    pub fn alpha(&self, k: u32) -> u32 {
+        if !self.enabled {
+            return self.alpha;
+        }
+
+        let success_rate = u64::from(Self::SCALE - self.failure_rate.min(Self::SCALE));
+        let expected = u64::from(k) * success_rate;
+        let scale = u64::from(self.k) * u64::from(Self::SCALE);
+        let alpha = (u64::from(self.alpha) * expected + scale - 1) / scale;
+
+        (alpha as u32).max(self.min_alpha).min(k)
+    }
+}
+
+#[test]
+fn test_adaptive_params_disabled() {
+    let mut config = ConsensusConfig::default();
+    config.k = Some(10);
+    config.alpha = Some(7);
+
+    let mut params = AdaptiveParams::new(&config);
+    assert!(!params.is_enabled());
+
+    params.record_query(false);
+    params.record_latency(1000);
+
+    assert_eq!(params.k(3), 10);
+    assert_eq!(params.alpha(3), 7);
+}
+
+#[test]
+fn test_adaptive_params_enabled() {
+    let mut config = ConsensusConfig::default();
+    config.k = Some(10);
+    config.alpha = Some(7);
+    config.min_k = Some(2);
+    config.max_k = Some(20);
+    config.min_alpha = Some(2);
+    config.timeout = Some(10);
+    config.adaptive = Some(true);
+
+    let mut params = AdaptiveParams::new(&config);
+    assert!(params.is_enabled());
+
+    assert_eq!(params.k(100), 10);
+    assert_eq!(params.alpha(10), 7);
+
+    assert_eq!(params.k(4), 4);
+    assert_eq!(params.alpha(4), 3);
+
+    assert_eq!(params.k(1), 2);
+    assert_eq!(params.alpha(2), 2);
+
+    for _ in 0..32 {
+        params.record_query(false);
+    }
+
+    assert!(params.failure_rate() > 900);
+    assert_eq!(params.k(100), 20);
+    assert_eq!(params.alpha(20), 2);
+
+    params.clear();
+
+    for _ in 0..4 {
+        params.record_query(true);
+        params.record_latency(2000);
+    }
+
+    assert_eq!(params.failure_rate(), 0);
+    assert_eq!(params.latency(), Some(2000));
+    assert_eq!(params.k(100), 5);
+    assert_eq!(params.alpha(5), 4);
+}
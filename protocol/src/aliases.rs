@@ -5,20 +5,33 @@
 use crate::client;
 use crate::client_server;
 use crate::consensus_server;
+use crate::light;
 use crate::miner_server;
 use crate::state;
-use network::backend::TcpNetwork;
-use store::backend::UnQLiteStore;
+use network::backend::{MultiNetwork, TcpNetwork};
+use store::backend::{CachedStore, UnQLiteStore, WalStore};
 
-pub type ProtocolState = state::ProtocolState<UnQLiteStore, UnQLiteStore>;
+pub type ProtocolState = state::ProtocolState<CachedStore<WalStore<UnQLiteStore>>, UnQLiteStore>;
 
-pub type ProtocolClient = client::ProtocolClient<UnQLiteStore, UnQLiteStore, TcpNetwork>;
+pub type ProtocolClient =
+    client::ProtocolClient<CachedStore<WalStore<UnQLiteStore>>, UnQLiteStore, TcpNetwork>;
 
-pub type ProtocolClientServer =
-    client_server::ProtocolClientServer<UnQLiteStore, UnQLiteStore, TcpNetwork>;
+pub type LightClient = light::LightClient<TcpNetwork>;
 
-pub type ProtocolConsensusServer =
-    consensus_server::ProtocolConsensusServer<UnQLiteStore, UnQLiteStore, TcpNetwork>;
+pub type ProtocolClientServer = client_server::ProtocolClientServer<
+    CachedStore<WalStore<UnQLiteStore>>,
+    UnQLiteStore,
+    MultiNetwork<TcpNetwork>,
+>;
 
-pub type ProtocolMinerServer =
-    miner_server::ProtocolMinerServer<UnQLiteStore, UnQLiteStore, TcpNetwork>;
+pub type ProtocolConsensusServer = consensus_server::ProtocolConsensusServer<
+    CachedStore<WalStore<UnQLiteStore>>,
+    UnQLiteStore,
+    MultiNetwork<TcpNetwork>,
+>;
+
+pub type ProtocolMinerServer = miner_server::ProtocolMinerServer<
+    CachedStore<WalStore<UnQLiteStore>>,
+    UnQLiteStore,
+    MultiNetwork<TcpNetwork>,
+>;
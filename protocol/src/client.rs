@@ -2,6 +2,7 @@
 //!
 //! `client` is the module containing the protocol client type and functions.
 
+use crate::events::ProtocolEvent;
 use crate::network as protocol_network;
 use crate::result::{handle_result, Result};
 use crate::state::ProtocolState;
@@ -9,16 +10,43 @@ use crypto::hash::Digest;
 use log::logger::Logger;
 use models::node::Node;
 use models::transaction::Transaction;
+use models::work::Work;
 use network::traits::Network;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use store::traits::Store;
 
+/// `BroadcastStatus` is the status of a broadcast `Transaction`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BroadcastStatus {
+    Sent { reached: u32 },
+    Pending { confidence: u64 },
+    Accepted,
+    Rejected { reason: String },
+    TimedOut,
+}
+
+impl BroadcastStatus {
+    /// `is_final` returns if the `BroadcastStatus` ends the tracking of the `Transaction`.
+    pub fn is_final(&self) -> bool {
+        match self {
+            BroadcastStatus::Accepted
+            | BroadcastStatus::Rejected { .. }
+            | BroadcastStatus::TimedOut => true,
+            _ => false,
+        }
+    }
+}
+
 /// `ProtocolClient` is the protocol client type.
 pub struct ProtocolClient<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 {
     pub state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -28,8 +56,8 @@ where
 
 impl<S, P, N> ProtocolClient<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 {
     /// `new` creates a new `ProtocolClient`.
@@ -248,6 +276,102 @@ where
         handle_result(self.logger.clone(), res, "Protocol client query error")
     }
 
+    /// `BROADCAST_POLL_TIME` is the time in milliseconds between two checks of
+    /// a broadcast `Transaction` acceptance.
+    pub const BROADCAST_POLL_TIME: u64 = 1000;
+
+    /// `broadcast_transaction` sends a `Transaction` to the sampled nodes and tracks
+    /// it until it is accepted, rejected or the timeout in seconds expires, reporting
+    /// the progress to a callback. The outcome is taken from the protocol events and,
+    /// when they are missed, from polling the `ProtocolState`.
+    pub fn broadcast_transaction(
+        &mut self,
+        transaction: &Transaction,
+        timeout: u64,
+        progress: &mut dyn FnMut(&BroadcastStatus),
+    ) -> Result<BroadcastStatus> {
+        // NB: subscribe first, not to miss the events of the broadcast itself.
+        let receiver = self.state.lock().unwrap().subscribe();
+
+        let res = protocol_network::broadcast_transaction(
+            self.state.clone(),
+            self.network.clone(),
+            self.logger.clone(),
+            transaction,
+        )
+        .and_then(|reached| {
+            progress(&BroadcastStatus::Sent { reached });
+            self.track_transaction(&receiver, &transaction.id, timeout, progress)
+        });
+
+        handle_result(
+            self.logger.clone(),
+            res,
+            "Protocol client broadcast_transaction error",
+        )
+    }
+
+    /// `track_transaction` tracks a `Transaction` until it is accepted, rejected
+    /// or the timeout in seconds expires.
+    fn track_transaction(
+        &self,
+        receiver: &Receiver<ProtocolEvent>,
+        tx_id: &Digest,
+        timeout: u64,
+        progress: &mut dyn FnMut(&BroadcastStatus),
+    ) -> Result<BroadcastStatus> {
+        let deadline = Instant::now() + Duration::from_secs(timeout);
+        let poll_time = Duration::from_millis(Self::BROADCAST_POLL_TIME);
+        let mut last_confidence = None;
+
+        let status = loop {
+            let event = match receiver.recv_timeout(poll_time) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(poll_time);
+                    None
+                }
+            };
+
+            match event {
+                Some(ProtocolEvent::TransactionAccepted { tx_id: id, .. }) if &id == tx_id => {
+                    break BroadcastStatus::Accepted;
+                }
+                Some(ProtocolEvent::TransactionRejected {
+                    tx_id: id, reason, ..
+                }) if &id == tx_id => {
+                    break BroadcastStatus::Rejected { reason };
+                }
+                Some(_) => {}
+                None => {
+                    let state = self.state.lock().unwrap();
+
+                    if state.is_accepted(tx_id)? {
+                        break BroadcastStatus::Accepted;
+                    }
+
+                    let confidence = state.state.get_transaction_confidence(tx_id);
+
+                    if confidence != last_confidence {
+                        last_confidence = confidence;
+                        progress(&BroadcastStatus::Pending {
+                            confidence: confidence.unwrap_or(0),
+                        });
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                break BroadcastStatus::TimedOut;
+            }
+        };
+
+        progress(&status);
+
+        Ok(status)
+    }
+
     /// `mine` mines a set of `Transaction`s.
     pub fn mine(&mut self, address: &[u8], transactions: &BTreeSet<Transaction>) -> Result<()> {
         let res = protocol_network::mine(
@@ -260,4 +384,58 @@ where
 
         handle_result(self.logger.clone(), res, "Protocol client mine error")
     }
+
+    /// `fetch_state` fetches the `TransactionState`s of the pending `Transaction`s
+    /// from a trusted node, returning the number of the applied ones.
+    pub fn fetch_state(&mut self, address: &[u8]) -> Result<u32> {
+        let res = protocol_network::fetch_state(
+            self.state.clone(),
+            self.network.clone(),
+            self.logger.clone(),
+            address,
+        );
+
+        handle_result(
+            self.logger.clone(),
+            res,
+            "Protocol client fetch_state error",
+        )
+    }
+
+    /// `get_work` requests a mining `Work` to a miner node.
+    pub fn get_work(&mut self, address: &[u8]) -> Result<Work> {
+        let res = protocol_network::get_work(
+            self.state.clone(),
+            self.network.clone(),
+            self.logger.clone(),
+            address,
+        );
+
+        handle_result(self.logger.clone(), res, "Protocol client get_work error")
+    }
+
+    /// `submit_work` submits the solution of a mining `Work` to a miner node.
+    pub fn submit_work(
+        &mut self,
+        address: &[u8],
+        work: &Work,
+        nonce: u64,
+        digest: Digest,
+    ) -> Result<()> {
+        let res = protocol_network::submit_work(
+            self.state.clone(),
+            self.network.clone(),
+            self.logger.clone(),
+            address,
+            work,
+            nonce,
+            digest,
+        );
+
+        handle_result(
+            self.logger.clone(),
+            res,
+            "Protocol client submit_work error",
+        )
+    }
 }
@@ -13,9 +13,9 @@ use store::traits::Store;
 /// `ProtocolClientServer` is the protocol client server type.
 pub struct ProtocolClientServer<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
-    N: Network + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Clone + Send + 'static,
 {
     pub state: Arc<Mutex<ProtocolState<S, P>>>,
     pub network: Arc<Mutex<N>>,
@@ -24,9 +24,9 @@ where
 
 impl<S, P, N> ProtocolClientServer<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
-    N: Network + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Clone + Send + 'static,
 {
     /// `new` creates a new `ProtocolClientServer`.
     pub fn new(
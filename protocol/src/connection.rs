@@ -0,0 +1,241 @@
+//! # Connection Manager
+//!
+//! `connection` contains the outbound connection manager type and functions.
+//! The manager keeps a target number of outbound peers, the nodes sampled by
+//! the consensus, and rotates a fraction of them periodically so that the
+//! Avalanche samples are drawn from a changing set of nodes.
+
+use crate::error::Error;
+use crate::result::Result;
+use config::network::NetworkConfig;
+use crypto::hash::Digest;
+use crypto::random::Random;
+use models::node::Node;
+use models::stage::Stage;
+use models::traits::Storable;
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `ConnectionManager` is the outbound connection manager type.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConnectionManager {
+    pub target_peers: u32,
+    pub peers: BTreeSet<Digest>,
+}
+
+impl ConnectionManager {
+    /// `ROTATION_PERIOD` is the number of avalanche steps between two peers rotations.
+    pub const ROTATION_PERIOD: u64 = 64;
+
+    /// `ROTATION_DIVISOR` is the inverse of the fraction of peers replaced at
+    /// each rotation.
+    pub const ROTATION_DIVISOR: usize = 4;
+
+    /// `new` creates a new `ConnectionManager`.
+    pub fn new(target_peers: u32) -> Result<ConnectionManager> {
+        if target_peers == 0 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        let manager = ConnectionManager {
+            target_peers,
+            peers: BTreeSet::new(),
+        };
+
+        Ok(manager)
+    }
+
+    /// `from_config` creates a new `ConnectionManager` from a `NetworkConfig`.
+    pub fn from_config(config: &NetworkConfig) -> Result<ConnectionManager> {
+        config.validate()?;
+
+        let mut config = config.clone();
+        config.populate();
+
+        ConnectionManager::new(config.target_peers.unwrap())
+    }
+
+    /// `len` returns the number of peers of the `ConnectionManager`.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// `is_empty` returns if the `ConnectionManager` has no peers.
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// `lookup_peer` looks up a peer in the `ConnectionManager`.
+    pub fn lookup_peer(&self, node_id: &Digest) -> bool {
+        self.peers.contains(node_id)
+    }
+
+    /// `add_peer` adds a peer to the `ConnectionManager`.
+    pub fn add_peer(&mut self, node_id: Digest) -> Result<()> {
+        if self.lookup_peer(&node_id) {
+            let err = Error::AlreadyFound;
+            return Err(err);
+        }
+
+        if self.missing_peers() == 0 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        self.peers.insert(node_id);
+
+        Ok(())
+    }
+
+    /// `remove_peer` removes a peer from the `ConnectionManager`.
+    pub fn remove_peer(&mut self, node_id: &Digest) -> Result<()> {
+        if !self.peers.remove(node_id) {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `missing_peers` returns the number of peers needed to reach the target.
+    pub fn missing_peers(&self) -> u32 {
+        self.target_peers.saturating_sub(self.len() as u32)
+    }
+
+    /// `random_peers` returns a maximum of count random peers.
+    fn random_peers(&self, count: usize) -> Result<BTreeSet<Digest>> {
+        let mut peers: Vec<Digest> = self.peers.iter().copied().collect();
+        let count = count.min(peers.len());
+
+        let mut sample = BTreeSet::new();
+
+        while sample.len() < count {
+            let idx = Random::u32_range(0, peers.len() as u32)? as usize;
+            sample.insert(peers.swap_remove(idx));
+        }
+
+        Ok(sample)
+    }
+
+    /// `rotate` removes a random fraction of the peers, returning them.
+    pub fn rotate(&mut self) -> Result<BTreeSet<Digest>> {
+        let count = (self.len() + Self::ROTATION_DIVISOR - 1) / Self::ROTATION_DIVISOR;
+        let rotated = self.random_peers(count)?;
+
+        for node_id in rotated.iter() {
+            self.peers.remove(node_id);
+        }
+
+        Ok(rotated)
+    }
+
+    /// `candidates` samples from the store a maximum of count nodes to dial,
+    /// skipping the current peers and the excluded nodes.
+    pub fn candidates<S: Store>(
+        &self,
+        store: &S,
+        stage: Stage,
+        count: u32,
+        excluded: &BTreeSet<Digest>,
+    ) -> Result<BTreeSet<Node>> {
+        let skipped = self.len() + excluded.len();
+        let sample_count = count.saturating_add(skipped as u32);
+
+        let candidates = Node::sample(store, stage, None, None, sample_count)?
+            .into_iter()
+            .filter(|node| !self.lookup_peer(&node.id) && !excluded.contains(&node.id))
+            .take(count as usize)
+            .collect();
+
+        Ok(candidates)
+    }
+
+    /// `sample` samples a maximum of count peers from the store.
+    pub fn sample<S: Store>(&self, store: &S, stage: Stage, count: u32) -> Result<BTreeSet<Node>> {
+        let mut nodes = BTreeSet::new();
+
+        for node_id in self.random_peers(count as usize)? {
+            nodes.insert(Node::get(store, stage, &node_id)?);
+        }
+
+        Ok(nodes)
+    }
+}
+
+impl Default for ConnectionManager {
+    fn default() -> ConnectionManager {
+        ConnectionManager {
+            target_peers: NetworkConfig::DEFAULT_TARGET_PEERS,
+            peers: BTreeSet::new(),
+        }
+    }
+}
+
+#[test]
+fn test_connection_manager() {
+    use store::memory::MemoryStoreFactory;
+
+    let res = ConnectionManager::new(0);
+    assert!(res.is_err());
+
+    let target_peers = 4;
+    let mut manager = ConnectionManager::new(target_peers).unwrap();
+    assert_eq!(manager.missing_peers(), target_peers);
+
+    let stage = Stage::random().unwrap();
+    let mut store = MemoryStoreFactory::new_btree(1 << 10, 1 << 30).unwrap();
+
+    for i in 0..10u8 {
+        let node = Node::new(stage, &[i]);
+        Node::create(&mut store, stage, &node).unwrap();
+    }
+
+    let excluded = BTreeSet::new();
+
+    let res = manager.candidates(&store, stage, target_peers, &excluded);
+    assert!(res.is_ok());
+
+    let candidates = res.unwrap();
+    assert_eq!(candidates.len() as u32, target_peers);
+
+    for node in candidates.iter() {
+        let res = manager.add_peer(node.id);
+        assert!(res.is_ok());
+    }
+
+    assert_eq!(manager.missing_peers(), 0);
+
+    let res = manager.add_peer(Digest::random().unwrap());
+    assert!(res.is_err());
+
+    let res = manager.sample(&store, stage, 2);
+    assert!(res.is_ok());
+
+    for node in res.unwrap() {
+        assert!(manager.lookup_peer(&node.id));
+    }
+
+    let res = manager.rotate();
+    assert!(res.is_ok());
+
+    let rotated = res.unwrap();
+    assert_eq!(rotated.len(), 1);
+    assert_eq!(manager.missing_peers(), 1);
+
+    let res = manager.candidates(&store, stage, 1, &rotated);
+    assert!(res.is_ok());
+
+    for node in res.unwrap() {
+        assert!(!manager.lookup_peer(&node.id));
+        assert!(!rotated.contains(&node.id));
+    }
+
+    let node_id = *manager.peers.iter().next().unwrap();
+
+    let res = manager.remove_peer(&node_id);
+    assert!(res.is_ok());
+
+    let res = manager.remove_peer(&node_id);
+    assert!(res.is_err());
+}
@@ -2,6 +2,7 @@
 //!
 //! `consensus_server` is the module containing the protocol consensus server type and functions.
 
+use crate::events::ProtocolEvent;
 use crate::network::serve_consensus;
 use crate::result::{handle_result, Result};
 use crate::state::ProtocolState;
@@ -13,8 +14,8 @@ use store::traits::Store;
 /// `ProtocolConsensusServer` is the protocol consensus server type.
 pub struct ProtocolConsensusServer<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 {
     pub state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -24,8 +25,8 @@ where
 
 impl<S, P, N> ProtocolConsensusServer<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 {
     /// `new` creates a new `ProtocolConsensusServer`.
@@ -86,6 +87,14 @@ where
             self.logger.clone(),
         );
 
+        if let Err(ref err) = res {
+            let msg = err.to_string();
+            self.state
+                .lock()
+                .unwrap()
+                .emit(ProtocolEvent::ConsensusError { msg });
+        }
+
         handle_result(
             self.logger.clone(),
             res,
@@ -7,6 +7,7 @@ use crypto::error::Error as CryptoError;
 use log::error::Error as LogError;
 use mining::error::Error as MiningError;
 use models::error::Error as ModelError;
+use models::error_code::ErrorCode;
 use network::error::Error as NetworkError;
 use serde_cbor;
 use serde_json;
@@ -25,9 +26,9 @@ pub enum Error {
     #[fail(display = "Store: {}", msg)]
     Store { msg: String },
     #[fail(display = "Model: {}", msg)]
-    Model { msg: String },
+    Model { code: ErrorCode, msg: String },
     #[fail(display = "Network: {}", msg)]
-    Network { msg: String },
+    Network { code: ErrorCode, msg: String },
     #[fail(display = "Config: {}", msg)]
     Config { msg: String },
     #[fail(display = "Log: {}", msg)]
@@ -40,6 +41,8 @@ pub enum Error {
     InvalidId,
     #[fail(display = "Invalid length")]
     InvalidLength,
+    #[fail(display = "Out of bound")]
+    OutOfBound,
     #[fail(display = "Not implemented")]
     NotImplemented,
     #[fail(display = "Not allowed")]
@@ -52,10 +55,16 @@ pub enum Error {
     InvalidStage,
     #[fail(display = "Invalid account")]
     InvalidAccount,
+    #[fail(display = "Locked account")]
+    LockedAccount,
+    #[fail(display = "Locked balance")]
+    LockedBalance,
     #[fail(display = "Invalid node")]
     InvalidNode,
     #[fail(display = "Invalid transaction")]
     InvalidTransaction,
+    #[fail(display = "Expired transaction")]
+    ExpiredTransaction,
     #[fail(display = "Invalid address")]
     InvalidAddress,
     #[fail(display = "Already mined")]
@@ -64,6 +73,65 @@ pub enum Error {
     NotMined,
     #[fail(display = "Invalid message")]
     InvalidMessage,
+    #[fail(display = "Replayed message")]
+    ReplayedMessage,
+    #[fail(display = "Read only")]
+    ReadOnly,
+    #[fail(display = "Invalid kind")]
+    InvalidKind,
+    #[fail(display = "Incompatible version")]
+    IncompatibleVersion,
+    #[fail(display = "Banned node")]
+    BannedNode,
+    #[fail(display = "Double spend")]
+    DoubleSpend,
+    #[fail(display = "Policy rejected: {}", msg)]
+    PolicyRejected { msg: String },
+    #[fail(display = "Rejected {}: {}", code, msg)]
+    Rejected { code: ErrorCode, msg: String },
+}
+
+impl Error {
+    /// `code` returns the `ErrorCode` of the `Error`.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::IO { .. }
+            | Error::Crypto { .. }
+            | Error::Config { .. }
+            | Error::Log { .. }
+            | Error::Thread { .. } => ErrorCode::Internal,
+            Error::Mining { .. } => ErrorCode::Mining,
+            Error::Store { .. } => ErrorCode::Store,
+            Error::Model { code, .. } | Error::Network { code, .. } => *code,
+            Error::Rejected { code, .. } => *code,
+            Error::Parse { .. } => ErrorCode::InvalidEncoding,
+            Error::InvalidId => ErrorCode::InvalidId,
+            Error::InvalidLength => ErrorCode::InvalidLength,
+            Error::OutOfBound => ErrorCode::OutOfBound,
+            Error::NotImplemented => ErrorCode::NotImplemented,
+            Error::NotAllowed => ErrorCode::NotAllowed,
+            Error::AlreadyFound => ErrorCode::AlreadyFound,
+            Error::NotFound => ErrorCode::NotFound,
+            Error::InvalidStage => ErrorCode::InvalidStage,
+            Error::InvalidAccount => ErrorCode::InvalidAccount,
+            Error::LockedAccount => ErrorCode::LockedAccount,
+            Error::LockedBalance => ErrorCode::LockedBalance,
+            Error::InvalidNode => ErrorCode::InvalidNode,
+            Error::InvalidTransaction => ErrorCode::InvalidTransaction,
+            Error::ExpiredTransaction => ErrorCode::ExpiredTransaction,
+            Error::InvalidAddress => ErrorCode::InvalidAddress,
+            Error::AlreadyMined => ErrorCode::AlreadyMined,
+            Error::NotMined => ErrorCode::NotMined,
+            Error::InvalidMessage => ErrorCode::InvalidMessage,
+            Error::ReplayedMessage => ErrorCode::ReplayedMessage,
+            Error::ReadOnly => ErrorCode::ReadOnly,
+            Error::InvalidKind => ErrorCode::InvalidArgument,
+            Error::IncompatibleVersion => ErrorCode::IncompatibleVersion,
+            Error::BannedNode => ErrorCode::BannedNode,
+            Error::PolicyRejected { .. } => ErrorCode::InvalidTransaction,
+            Error::DoubleSpend => ErrorCode::DoubleSpend,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -110,15 +178,17 @@ impl From<StoreError> for Error {
 
 impl From<ModelError> for Error {
     fn from(error: ModelError) -> Error {
+        let code = error.code();
         let msg = format!("{}", error);
-        Error::Model { msg }
+        Error::Model { code, msg }
     }
 }
 
 impl From<NetworkError> for Error {
     fn from(error: NetworkError) -> Error {
+        let code = error.code();
         let msg = format!("{}", error);
-        Error::Network { msg }
+        Error::Network { code, msg }
     }
 }
 
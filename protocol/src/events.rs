@@ -0,0 +1,343 @@
+//! # Events
+//!
+//! `events` contains the protocol events and the channels used to subscribe to them.
+
+use crate::error::Error;
+use crate::result::Result;
+use crypto::hash::Digest;
+use log::logger::Logger;
+use models::address::Address;
+use models::conflict_set::ConflictSet;
+use models::watch::WatchAction;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use serde_json;
+use std::collections::BTreeSet;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// `ProtocolEvent` is an event of the life of the `Transaction`s handled by the protocol.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub enum ProtocolEvent {
+    TransactionAdded {
+        tx_id: Digest,
+        addresses: BTreeSet<Address>,
+    },
+    TransactionAccepted {
+        tx_id: Digest,
+        addresses: BTreeSet<Address>,
+    },
+    TransactionRejected {
+        tx_id: Digest,
+        reason: String,
+        addresses: BTreeSet<Address>,
+    },
+    ConflictDetected {
+        conflict_set: ConflictSet,
+    },
+    WatchTriggered {
+        tx_id: Digest,
+        address: Address,
+        action: WatchAction,
+    },
+    ConsensusError {
+        msg: String,
+    },
+    TransactionStalled {
+        tx_id: Digest,
+        requeries: u32,
+        addresses: BTreeSet<Address>,
+    },
+}
+
+impl ProtocolEvent {
+    /// `kind` returns the `EventKind` of the `ProtocolEvent`.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            ProtocolEvent::TransactionAdded { .. } => EventKind::NewTransaction,
+            ProtocolEvent::TransactionAccepted { .. } => EventKind::Accepted,
+            ProtocolEvent::TransactionRejected { .. } => EventKind::Rejected,
+            ProtocolEvent::ConflictDetected { .. } => EventKind::Conflict,
+            ProtocolEvent::WatchTriggered { .. } => EventKind::Watch,
+            ProtocolEvent::ConsensusError { .. } => EventKind::Error,
+            ProtocolEvent::TransactionStalled { .. } => EventKind::Stalled,
+        }
+    }
+
+    /// `addresses` returns the addresses involved in the `ProtocolEvent`.
+    pub fn addresses(&self) -> BTreeSet<Address> {
+        match self {
+            ProtocolEvent::TransactionAdded { addresses, .. }
+            | ProtocolEvent::TransactionAccepted { addresses, .. }
+            | ProtocolEvent::TransactionRejected { addresses, .. }
+            | ProtocolEvent::TransactionStalled { addresses, .. } => addresses.clone(),
+            ProtocolEvent::ConflictDetected { conflict_set } => {
+                let mut addresses = BTreeSet::new();
+                addresses.insert(conflict_set.address);
+                addresses
+            }
+            ProtocolEvent::WatchTriggered { address, .. } => {
+                let mut addresses = BTreeSet::new();
+                addresses.insert(*address);
+                addresses
+            }
+            ProtocolEvent::ConsensusError { .. } => BTreeSet::new(),
+        }
+    }
+
+    /// `is_streamed` returns if the `ProtocolEvent` is streamed to the gRPC event
+    /// subscribers. The triggered watches are streamed only when their action
+    /// is an event.
+    pub fn is_streamed(&self) -> bool {
+        match self {
+            ProtocolEvent::WatchTriggered { action, .. } => action == &WatchAction::Event,
+            _ => true,
+        }
+    }
+
+    /// `to_bytes` converts the `ProtocolEvent` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into an `ProtocolEvent`.
+    pub fn from_bytes(b: &[u8]) -> Result<ProtocolEvent> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `to_json` converts the `ProtocolEvent` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `ProtocolEvent`.
+    pub fn from_json(s: &str) -> Result<ProtocolEvent> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+/// `EventKind` is the kind of a `ProtocolEvent`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize)]
+pub enum EventKind {
+    NewTransaction,
+    Accepted,
+    Rejected,
+    Conflict,
+    Watch,
+    Error,
+    Stalled,
+}
+
+impl EventKind {
+    /// `parse` parses a `EventKind` from its name.
+    pub fn parse(s: &str) -> Result<EventKind> {
+        match s {
+            "new_transaction" => Ok(EventKind::NewTransaction),
+            "accepted" => Ok(EventKind::Accepted),
+            "rejected" => Ok(EventKind::Rejected),
+            "conflict" => Ok(EventKind::Conflict),
+            "watch" => Ok(EventKind::Watch),
+            "error" => Ok(EventKind::Error),
+            "stalled" => Ok(EventKind::Stalled),
+            _ => {
+                let msg = format!("invalid event kind: {}", s);
+                let err = Error::Parse { msg };
+                Err(err)
+            }
+        }
+    }
+}
+
+/// `EventFilter` selects the `ProtocolEvent`s of some kinds involving
+/// some addresses. An empty set of kinds or of addresses selects them all.
+#[derive(Clone, Eq, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub struct EventFilter {
+    pub kinds: BTreeSet<EventKind>,
+    pub addresses: BTreeSet<Address>,
+}
+
+impl EventFilter {
+    /// `new` creates a new `EventFilter`.
+    pub fn new(kinds: &BTreeSet<EventKind>, addresses: &BTreeSet<Address>) -> EventFilter {
+        EventFilter {
+            kinds: kinds.clone(),
+            addresses: addresses.clone(),
+        }
+    }
+
+    /// `matches` returns if a `ProtocolEvent` is selected by the `EventFilter`.
+    pub fn matches(&self, event: &ProtocolEvent) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind()) {
+            return false;
+        }
+
+        self.addresses.is_empty() || !self.addresses.is_disjoint(&event.addresses())
+    }
+}
+
+/// `EventBus` dispatches the `ProtocolEvent`s to its subscribers. A subscriber
+/// not keeping up with the events misses the events emitted while its buffer is full.
+#[derive(Debug, Default)]
+pub struct EventBus {
+    subscribers: Vec<SyncSender<ProtocolEvent>>,
+}
+
+impl EventBus {
+    /// `BUFFER_SIZE` is the number of `ProtocolEvent`s buffered per subscriber.
+    pub const BUFFER_SIZE: usize = 1024;
+
+    /// `subscribe` returns a new receiver of the `ProtocolEvent`s.
+    pub fn subscribe(&mut self) -> Receiver<ProtocolEvent> {
+        let (sender, receiver) = sync_channel(Self::BUFFER_SIZE);
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// `subscribers_count` returns the number of subscribers of the `EventBus`.
+    pub fn subscribers_count(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// `emit` sends a `ProtocolEvent` to the subscribers, dropping the ones
+    /// that hung up.
+    pub fn emit(&mut self, event: ProtocolEvent) {
+        self.subscribers
+            .retain(|sender| match sender.try_send(event.clone()) {
+                Err(TrySendError::Disconnected(_)) => false,
+                _ => true,
+            });
+    }
+}
+
+/// `log_events` spawns a thread logging the `ProtocolEvent`s of a receiver.
+pub fn log_events(logger: Arc<Logger>, receiver: Receiver<ProtocolEvent>) -> JoinHandle<()> {
+    let logger = logger.with_module("protocol::events");
+
+    thread::spawn(move || {
+        for event in receiver.iter() {
+            let msg = match event {
+                ProtocolEvent::TransactionAdded { tx_id, .. } => {
+                    format!("Transaction {} added", tx_id)
+                }
+                ProtocolEvent::TransactionAccepted { tx_id, .. } => {
+                    format!("Transaction {} accepted", tx_id)
+                }
+                ProtocolEvent::TransactionRejected {
+                    tx_id, ref reason, ..
+                } => {
+                    format!("Transaction {} rejected: {}", tx_id, reason)
+                }
+                ProtocolEvent::ConflictDetected { ref conflict_set } => format!(
+                    "Conflict detected among {} transactions",
+                    conflict_set.transactions.len()
+                ),
+                ProtocolEvent::WatchTriggered {
+                    tx_id,
+                    address,
+                    ref action,
+                } => format!(
+                    "Transaction {} touched watched address {} ({})",
+                    tx_id, address, action
+                ),
+                ProtocolEvent::ConsensusError { ref msg } => format!("Consensus error: {}", msg),
+                ProtocolEvent::TransactionStalled {
+                    tx_id, requeries, ..
+                } => format!(
+                    "Transaction {} stalled after {} re-queries",
+                    tx_id, requeries
+                ),
+            };
+
+            // NB: a failed log write cannot be reported anywhere else.
+            let _ = logger.log_info(&msg);
+            let _ = logger.log_debug(&format!("Protocol event: {:?}", event));
+        }
+    })
+}
+
+#[test]
+fn test_event_filter() {
+    use models::stage::Stage;
+
+    let address_a = Digest::random().unwrap();
+    let address_b = Digest::random().unwrap();
+    let tx_id = Digest::random().unwrap();
+
+    let mut addresses = BTreeSet::new();
+    addresses.insert(address_a);
+
+    let added = ProtocolEvent::TransactionAdded {
+        tx_id,
+        addresses: addresses.clone(),
+    };
+    let accepted = ProtocolEvent::TransactionAccepted {
+        tx_id,
+        addresses: addresses.clone(),
+    };
+    let conflict = ProtocolEvent::ConflictDetected {
+        conflict_set: ConflictSet::new(address_b, Stage::default()),
+    };
+
+    let filter = EventFilter::default();
+    assert!(filter.matches(&added));
+    assert!(filter.matches(&accepted));
+    assert!(filter.matches(&conflict));
+
+    let mut kinds = BTreeSet::new();
+    kinds.insert(EventKind::parse("accepted").unwrap());
+
+    let filter = EventFilter::new(&kinds, &BTreeSet::new());
+    assert!(!filter.matches(&added));
+    assert!(filter.matches(&accepted));
+    assert!(!filter.matches(&conflict));
+
+    let filter = EventFilter::new(&BTreeSet::new(), &addresses);
+    assert!(filter.matches(&added));
+    assert!(filter.matches(&accepted));
+    assert!(!filter.matches(&conflict));
+
+    let watch = ProtocolEvent::WatchTriggered {
+        tx_id,
+        address: address_a,
+        action: WatchAction::Log,
+    };
+
+    let mut kinds = BTreeSet::new();
+    kinds.insert(EventKind::parse("watch").unwrap());
+
+    let filter = EventFilter::new(&kinds, &addresses);
+    assert!(filter.matches(&watch));
+    assert!(!filter.matches(&accepted));
+    assert!(!watch.is_streamed());
+    assert!(accepted.is_streamed());
+
+    let error = ProtocolEvent::ConsensusError {
+        msg: "error".into(),
+    };
+
+    let mut kinds = BTreeSet::new();
+    kinds.insert(EventKind::parse("error").unwrap());
+
+    let filter = EventFilter::new(&kinds, &BTreeSet::new());
+    assert!(filter.matches(&error));
+    assert!(!filter.matches(&accepted));
+    assert!(error.addresses().is_empty());
+
+    let stalled = ProtocolEvent::TransactionStalled {
+        tx_id,
+        requeries: 3,
+        addresses: addresses.clone(),
+    };
+
+    let mut kinds = BTreeSet::new();
+    kinds.insert(EventKind::parse("stalled").unwrap());
+
+    let filter = EventFilter::new(&kinds, &addresses);
+    assert!(filter.matches(&stalled));
+    assert!(!filter.matches(&accepted));
+    assert_eq!(stalled.addresses(), addresses);
+
+    let res = EventKind::parse("unknown");
+    assert!(res.is_err());
+}
@@ -0,0 +1,133 @@
+//! # Greylist
+//!
+//! `greylist` contains the list of the nodes that sent invalid messages.
+//! Greylisted nodes are not dialed by the connection manager and, once they
+//! reach the strikes threshold, are banned. The strikes are persisted in the
+//! store as `NodeReputation`s and forgotten after `STRIKES_TTL` seconds
+//! without new strikes.
+
+use crypto::hash::Digest;
+use std::collections::BTreeMap;
+
+/// `Greylist` keeps the number of invalid `ConsensusMessage`s sent by each node.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Greylist {
+    pub threshold: u32,
+    strikes: BTreeMap<Digest, u32>,
+}
+
+impl Greylist {
+    /// `DEFAULT_THRESHOLD` is the default number of strikes after which a node is banned.
+    pub const DEFAULT_THRESHOLD: u32 = 16;
+
+    /// `BAN_DURATION` is the duration in seconds of the automatic bans.
+    pub const BAN_DURATION: u64 = 24 * 3600;
+
+    /// `STRIKES_TTL` is the time in seconds after the last strike of a node
+    /// when its strikes are forgotten.
+    pub const STRIKES_TTL: u64 = 7 * 24 * 3600;
+
+    /// `new` creates a new empty `Greylist`.
+    pub fn new(threshold: u32) -> Greylist {
+        Greylist {
+            threshold,
+            strikes: BTreeMap::new(),
+        }
+    }
+
+    /// `len` returns the number of nodes in the `Greylist`.
+    pub fn len(&self) -> usize {
+        self.strikes.len()
+    }
+
+    /// `is_empty` returns if the `Greylist` is empty.
+    pub fn is_empty(&self) -> bool {
+        self.strikes.is_empty()
+    }
+
+    /// `lookup` returns if a node is in the `Greylist`.
+    pub fn lookup(&self, node_id: &Digest) -> bool {
+        self.strikes.contains_key(node_id)
+    }
+
+    /// `strikes` returns the number of strikes of a node.
+    pub fn strikes(&self, node_id: &Digest) -> u32 {
+        self.strikes.get(node_id).copied().unwrap_or(0)
+    }
+
+    /// `nodes` returns the ids of the greylisted nodes.
+    pub fn nodes(&self) -> impl Iterator<Item = &Digest> {
+        self.strikes.keys()
+    }
+
+    /// `strike` records an invalid message of a node, returning true if the
+    /// node reached the threshold. In that case the node is removed from
+    /// the `Greylist`, as it is expected to be banned.
+    pub fn strike(&mut self, node_id: &Digest) -> bool {
+        let strikes = self.strikes.entry(*node_id).or_insert(0);
+        *strikes += 1;
+
+        if *strikes >= self.threshold {
+            self.strikes.remove(node_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `restore` sets the strikes of a node, as recovered from the store.
+    /// Nodes without strikes are removed from the `Greylist`.
+    pub fn restore(&mut self, node_id: &Digest, strikes: u32) {
+        if strikes == 0 {
+            self.strikes.remove(node_id);
+        } else {
+            self.strikes.insert(*node_id, strikes);
+        }
+    }
+
+    /// `remove` removes a node from the `Greylist`, returning if it was found.
+    pub fn remove(&mut self, node_id: &Digest) -> bool {
+        self.strikes.remove(node_id).is_some()
+    }
+
+    /// `clear` clears the `Greylist`.
+    pub fn clear(&mut self) {
+        self.strikes.clear();
+    }
+}
+
+impl Default for Greylist {
+    fn default() -> Greylist {
+        Greylist::new(Greylist::DEFAULT_THRESHOLD)
+    }
+}
+
+#[test]
+fn test_greylist_strike() {
+    let node_id = Digest::random().unwrap();
+    let mut greylist = Greylist::new(3);
+
+    assert!(!greylist.lookup(&node_id));
+    assert_eq!(greylist.strikes(&node_id), 0);
+
+    assert!(!greylist.strike(&node_id));
+    assert!(!greylist.strike(&node_id));
+    assert!(greylist.lookup(&node_id));
+    assert_eq!(greylist.strikes(&node_id), 2);
+
+    assert!(greylist.strike(&node_id));
+    assert!(!greylist.lookup(&node_id));
+    assert!(greylist.is_empty());
+
+    greylist.strike(&node_id);
+    assert!(greylist.remove(&node_id));
+    assert!(!greylist.remove(&node_id));
+
+    greylist.restore(&node_id, 2);
+    assert_eq!(greylist.strikes(&node_id), 2);
+    assert!(greylist.strike(&node_id));
+
+    greylist.restore(&node_id, 1);
+    greylist.restore(&node_id, 0);
+    assert!(!greylist.lookup(&node_id));
+}
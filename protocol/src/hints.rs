@@ -0,0 +1,133 @@
+//! # Hints
+//!
+//! `hints` contains the tallies of the `ReplyHints` received when querying the
+//! `Transaction`s. The hints do not change the chits: they are only used to detect
+//! that the sampled nodes prefer another `Transaction` of a conflict set, so that
+//! the divergence is found before the confidence builds up, and the preferred
+//! `Transaction` of the sampled nodes is queried first.
+
+use crypto::hash::Digest;
+use models::consensus_message::ReplyHints;
+use std::collections::BTreeMap;
+
+/// `ReplyTally` is the tally of the `ReplyHints` of the queries of a `Transaction`.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ReplyTally {
+    pub replies: u32,
+    pub confidence: u64,
+    pub preferred: BTreeMap<Digest, u32>,
+}
+
+impl ReplyTally {
+    /// `record` records the `ReplyHints` of a `Reply`. Replies without hints,
+    /// as sent by older nodes, are not counted.
+    pub fn record(&mut self, hints: &ReplyHints) {
+        if hints.confidence.is_none() && hints.preferred.is_none() {
+            return;
+        }
+
+        self.replies += 1;
+
+        if let Some(confidence) = hints.confidence {
+            self.confidence = self.confidence.max(confidence);
+        }
+
+        if let Some(preferred) = hints.preferred {
+            *self.preferred.entry(preferred).or_insert(0) += 1;
+        }
+    }
+
+    /// `majority_preferred` returns the `Transaction` preferred by more than half
+    /// of the hinted replies, if any.
+    pub fn majority_preferred(&self) -> Option<Digest> {
+        self.preferred
+            .iter()
+            .find(|(_, count)| **count * 2 > self.replies)
+            .map(|(tx_id, _)| *tx_id)
+    }
+}
+
+/// `ReplyTallies` keeps the `ReplyTally` of the queried `Transaction`s until they
+/// are checked by the consensus step.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ReplyTallies {
+    tallies: BTreeMap<Digest, ReplyTally>,
+}
+
+impl ReplyTallies {
+    /// `new` creates a new empty `ReplyTallies`.
+    pub fn new() -> ReplyTallies {
+        ReplyTallies::default()
+    }
+
+    /// `len` returns the number of tallied `Transaction`s.
+    pub fn len(&self) -> usize {
+        self.tallies.len()
+    }
+
+    /// `is_empty` returns if there are no tallied `Transaction`s.
+    pub fn is_empty(&self) -> bool {
+        self.tallies.is_empty()
+    }
+
+    /// `get` returns the `ReplyTally` of a `Transaction`.
+    pub fn get(&self, tx_id: &Digest) -> Option<&ReplyTally> {
+        self.tallies.get(tx_id)
+    }
+
+    /// `record` records the `ReplyHints` of a `Reply` to a query of a `Transaction`.
+    pub fn record(&mut self, tx_id: &Digest, hints: &ReplyHints) {
+        self.tallies.entry(*tx_id).or_default().record(hints);
+    }
+
+    /// `drain` removes and returns all the `ReplyTally`s.
+    pub fn drain(&mut self) -> BTreeMap<Digest, ReplyTally> {
+        std::mem::replace(&mut self.tallies, BTreeMap::new())
+    }
+
+    /// `clear` clears the `ReplyTallies`.
+    pub fn clear(&mut self) {
+        self.tallies.clear();
+    }
+}
+
+#[test]
+fn test_reply_tallies() {
+    let tx_id = Digest::random().unwrap();
+    let preferred_a = Digest::random().unwrap();
+    let preferred_b = Digest::random().unwrap();
+
+    let mut tallies = ReplyTallies::new();
+    assert!(tallies.is_empty());
+
+    tallies.record(&tx_id, &ReplyHints::default());
+    assert_eq!(tallies.get(&tx_id).unwrap().replies, 0);
+
+    let hints_a = ReplyHints {
+        confidence: Some(3),
+        preferred: Some(preferred_a),
+    };
+
+    let hints_b = ReplyHints {
+        confidence: Some(1),
+        preferred: Some(preferred_b),
+    };
+
+    tallies.record(&tx_id, &hints_a);
+    tallies.record(&tx_id, &hints_b);
+
+    let tally = tallies.get(&tx_id).unwrap();
+    assert_eq!(tally.replies, 2);
+    assert_eq!(tally.confidence, 3);
+    assert_eq!(tally.majority_preferred(), None);
+
+    tallies.record(&tx_id, &hints_a);
+    assert_eq!(
+        tallies.get(&tx_id).unwrap().majority_preferred(),
+        Some(preferred_a)
+    );
+
+    let drained = tallies.drain();
+    assert_eq!(drained.len(), 1);
+    assert!(tallies.is_empty());
+}
@@ -0,0 +1,353 @@
+//! # Ledger
+//!
+//! `ledger` contains the per-address balance aggregates of the accepted `Transaction`s.
+
+use crate::result::Result;
+use crypto::hash::Digest;
+use models::address::Address;
+use models::stage::Stage;
+use models::timestamp::Timestamp;
+use models::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use std::collections::BTreeSet;
+use store::error::Error as StoreError;
+use store::traits::Store;
+
+/// `LEDGER_KEY_PREFIX` is the prefix of the ledger keys.
+pub const LEDGER_KEY_PREFIX: u8 = 10;
+
+/// `ENTRY_TAG` is the tag of the `LedgerEntry` keys.
+pub const ENTRY_TAG: u8 = 0;
+
+/// `APPLIED_TAG` is the tag of the keys marking the `Transaction`s applied to the ledger.
+pub const APPLIED_TAG: u8 = 1;
+
+/// `UnspentOutput` is an amount received by an `Address` in an accepted
+/// `Transaction` and not yet spent.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct UnspentOutput {
+    pub transaction_id: Digest,
+    pub amount: u64,
+}
+
+/// `LedgerEntry` is the balance aggregate of an `Address`. The unspent outputs
+/// are kept in acceptance order and are spent from the oldest.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub address: Address,
+    pub balance: u64,
+    pub unspent_outputs: Vec<UnspentOutput>,
+}
+
+impl LedgerEntry {
+    /// `new` creates a new empty `LedgerEntry`.
+    pub fn new(address: &Address) -> LedgerEntry {
+        LedgerEntry {
+            address: *address,
+            balance: 0,
+            unspent_outputs: Vec::new(),
+        }
+    }
+
+    /// `credit` credits an amount received in a `Transaction` to the `LedgerEntry`.
+    pub fn credit(&mut self, tx_id: &Digest, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+
+        self.balance = self.balance.saturating_add(amount);
+
+        let output = UnspentOutput {
+            transaction_id: *tx_id,
+            amount,
+        };
+
+        self.unspent_outputs.push(output);
+    }
+
+    /// `debit` debits an amount from the `LedgerEntry`, spending the oldest
+    /// unspent outputs first.
+    pub fn debit(&mut self, amount: u64) {
+        self.balance = self.balance.saturating_sub(amount);
+
+        let mut amount = amount;
+
+        while amount > 0 && !self.unspent_outputs.is_empty() {
+            if self.unspent_outputs[0].amount > amount {
+                self.unspent_outputs[0].amount -= amount;
+                amount = 0;
+            } else {
+                amount -= self.unspent_outputs[0].amount;
+                self.unspent_outputs.remove(0);
+            }
+        }
+    }
+
+    /// `to_bytes` converts the `LedgerEntry` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `LedgerEntry`.
+    pub fn from_bytes(b: &[u8]) -> Result<LedgerEntry> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+}
+
+/// `HistoryEntry` is the effect of a `Transaction` on the balance of an `Address`:
+/// the amount spent by its input, received by its output and rewarded by its
+/// mined coinbase. Only applied entries are reflected in the ledger balance.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub time: Timestamp,
+    pub transaction_id: Digest,
+    pub spent: u64,
+    pub received: u64,
+    pub reward: u64,
+    pub applied: bool,
+}
+
+impl HistoryEntry {
+    /// `new` creates a new `HistoryEntry` of a `Transaction` for an `Address`.
+    pub fn new(transaction: &Transaction, address: &Address, applied: bool) -> HistoryEntry {
+        let spent = transaction
+            .inputs
+            .get(address)
+            .map_or(0, |input| input.amount);
+
+        let received = transaction
+            .outputs
+            .get(address)
+            .map_or(0, |output| output.amount);
+
+        let reward = match transaction.coinbase {
+            Some(ref coinbase) if coinbase.address == *address && coinbase.is_mined() => {
+                coinbase.amount
+            }
+            _ => 0,
+        };
+
+        HistoryEntry {
+            time: transaction.time,
+            transaction_id: transaction.id,
+            spent,
+            received,
+            reward,
+            applied,
+        }
+    }
+
+    /// `net` returns the net amount credited to the `Address` by the `HistoryEntry`.
+    pub fn net(&self) -> i64 {
+        self.received as i64 + self.reward as i64 - self.spent as i64
+    }
+}
+
+/// `AddressHistory` is a page of the history of an `Address`, in time order.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct AddressHistory {
+    pub address: Address,
+    pub total: u32,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// `entry_key` returns the `Store` key of the `LedgerEntry` of an `Address`.
+fn entry_key(stage: Stage, address: &Address) -> Vec<u8> {
+    let mut buf = vec![stage as u8, LEDGER_KEY_PREFIX, ENTRY_TAG];
+    buf.extend_from_slice(&address.to_bytes());
+    buf
+}
+
+/// `applied_key` returns the `Store` key marking a `Transaction` as applied.
+fn applied_key(stage: Stage, tx_id: &Digest) -> Vec<u8> {
+    let mut buf = vec![stage as u8, LEDGER_KEY_PREFIX, APPLIED_TAG];
+    buf.extend_from_slice(&tx_id.to_bytes());
+    buf
+}
+
+/// `get_entry` returns the `LedgerEntry` of an `Address`. Unknown addresses
+/// have an empty `LedgerEntry`.
+pub fn get_entry<S: Store>(store: &S, stage: Stage, address: &Address) -> Result<LedgerEntry> {
+    match store.get(&entry_key(stage, address)) {
+        Ok(buf) => LedgerEntry::from_bytes(&buf),
+        Err(StoreError::NotFound) => Ok(LedgerEntry::new(address)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `get_balance` returns the balance of an `Address`.
+pub fn get_balance<S: Store>(store: &S, stage: Stage, address: &Address) -> Result<u64> {
+    get_entry(store, stage, address).map(|entry| entry.balance)
+}
+
+/// `get_unspent_outputs` returns the unspent outputs of an `Address`.
+pub fn get_unspent_outputs<S: Store>(
+    store: &S,
+    stage: Stage,
+    address: &Address,
+) -> Result<Vec<UnspentOutput>> {
+    get_entry(store, stage, address).map(|entry| entry.unspent_outputs)
+}
+
+/// `unspent_transactions` returns the ids of the `Transaction`s with unspent outputs.
+pub fn unspent_transactions<S: Store>(store: &S, stage: Stage) -> Result<BTreeSet<Digest>> {
+    let from = vec![stage as u8, LEDGER_KEY_PREFIX, ENTRY_TAG];
+    let to = vec![stage as u8, LEDGER_KEY_PREFIX, ENTRY_TAG + 1];
+
+    let mut ids = BTreeSet::new();
+
+    for (_, value) in store.iter(Some(&from), Some(&to))? {
+        let entry = LedgerEntry::from_bytes(&value)?;
+        ids.extend(
+            entry
+                .unspent_outputs
+                .iter()
+                .map(|output| output.transaction_id),
+        );
+    }
+
+    Ok(ids)
+}
+
+/// `is_applied` returns if a `Transaction` has been applied to the ledger.
+pub fn is_applied<S: Store>(store: &S, stage: Stage, tx_id: &Digest) -> Result<bool> {
+    store
+        .lookup(&applied_key(stage, tx_id))
+        .map_err(|e| e.into())
+}
+
+/// `apply_transaction` applies an accepted `Transaction` to the ledger, debiting
+/// its inputs and crediting its outputs and mined coinbase. A `Transaction` is
+/// applied only once.
+pub fn apply_transaction<S: Store>(
+    store: &mut S,
+    stage: Stage,
+    transaction: &Transaction,
+) -> Result<()> {
+    if is_applied(store, stage, &transaction.id)? {
+        return Ok(());
+    }
+
+    for (address, input) in transaction.inputs.iter() {
+        let mut entry = get_entry(store, stage, address)?;
+        entry.debit(input.amount);
+        store.insert(&entry_key(stage, address), &entry.to_bytes()?, None)?;
+    }
+
+    for (address, output) in transaction.outputs.iter() {
+        let mut entry = get_entry(store, stage, address)?;
+        entry.credit(&transaction.id, output.amount);
+        store.insert(&entry_key(stage, address), &entry.to_bytes()?, None)?;
+    }
+
+    if let Some(ref coinbase) = transaction.coinbase {
+        if coinbase.is_mined() {
+            let mut entry = get_entry(store, stage, &coinbase.address)?;
+            entry.credit(&transaction.id, coinbase.amount);
+            store.insert(
+                &entry_key(stage, &coinbase.address),
+                &entry.to_bytes()?,
+                None,
+            )?;
+        }
+    }
+
+    store
+        .insert(&applied_key(stage, &transaction.id), &[], None)
+        .map_err(|e| e.into())
+}
+
+/// `get_history` returns the history of the `Transaction`s in the `Store` spending
+/// from, sending to or rewarding an `Address`, ordered by time and paginated.
+pub fn get_history<S: Store>(
+    store: &S,
+    stage: Stage,
+    address: &Address,
+    count: Option<u32>,
+    skip: Option<u32>,
+) -> Result<AddressHistory> {
+    let mut entries = Vec::new();
+
+    for transaction in Transaction::query_by_address(store, stage, address)? {
+        let applied = is_applied(store, stage, &transaction.id)?;
+        entries.push(HistoryEntry::new(&transaction, address, applied));
+    }
+
+    entries.sort();
+
+    let total = entries.len() as u32;
+    let skip = skip.unwrap_or(0) as usize;
+    let count = count.unwrap_or(total) as usize;
+
+    let entries = entries.into_iter().skip(skip).take(count).collect();
+
+    let history = AddressHistory {
+        address: *address,
+        total,
+        entries,
+    };
+
+    Ok(history)
+}
+
+#[test]
+fn test_ledger_history() {
+    use models::output::Output;
+    use models::traits::Storable;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+    let now = Timestamp::now().to_i64();
+
+    let transactions: Vec<Transaction> = (0..5)
+        .map(|i| {
+            let mut transaction = Transaction::new().unwrap();
+            transaction.stage = stage;
+            transaction.time = Timestamp::from_i64(now - 10 + i).unwrap();
+            transaction
+                .add_output(&Output::new(&address, 0, &[]))
+                .unwrap();
+            transaction.update_id().unwrap();
+
+            transaction
+        })
+        .collect();
+
+    for transaction in transactions.iter().rev() {
+        Transaction::insert(&mut store, stage, transaction).unwrap();
+    }
+
+    apply_transaction(&mut store, stage, &transactions[0]).unwrap();
+
+    let res = get_history(&store, stage, &address, None, None);
+    assert!(res.is_ok());
+    let history = res.unwrap();
+    assert_eq!(history.total, transactions.len() as u32);
+    assert_eq!(history.entries.len(), transactions.len());
+
+    for (entry, transaction) in history.entries.iter().zip(transactions.iter()) {
+        assert_eq!(entry.transaction_id, transaction.id);
+        assert_eq!(entry.net(), 0);
+    }
+
+    assert!(history.entries[0].applied);
+    assert!(!history.entries[1].applied);
+
+    let res = get_history(&store, stage, &address, Some(2), Some(2));
+    assert!(res.is_ok());
+    let page = res.unwrap();
+    assert_eq!(page.total, transactions.len() as u32);
+    assert_eq!(page.entries, history.entries[2..4].to_vec());
+
+    let other = Address::random().unwrap();
+    let res = get_history(&store, stage, &other, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().total, 0);
+}
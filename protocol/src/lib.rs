@@ -11,15 +11,71 @@ pub mod error;
 /// `result` contains the result type used in the crate.
 pub mod result;
 
+/// `events` contains the protocol event types and functions.
+pub mod events;
+
+/// `ledger` contains the protocol ledger types and functions.
+pub mod ledger;
+
+/// `utxo` contains the spent outputs bookkeeping types and functions.
+pub mod utxo;
+
 /// `network` contains the protocol network functions.
 pub mod network;
 
+/// `hints` contains the protocol query reply hints tally types and functions.
+pub mod hints;
+
+/// `priority` contains the protocol priority queue type and functions.
+pub mod priority;
+
+/// `policy` contains the protocol mempool acceptance policy types and functions.
+pub mod policy;
+
+/// `replay` contains the protocol replay window type and functions.
+pub mod replay;
+
+/// `orphan` contains the protocol orphan pool type and functions.
+pub mod orphan;
+
+/// `retry` contains the protocol network retry type and functions.
+pub mod retry;
+
+/// `prune` contains the protocol store pruning types and functions.
+pub mod prune;
+
+/// `adaptive` contains the protocol adaptive consensus parameters type and functions.
+pub mod adaptive;
+
+/// `connection` contains the protocol outbound connection manager type and functions.
+pub mod connection;
+
+/// `greylist` contains the protocol greylist of misbehaving nodes type and functions.
+pub mod greylist;
+
+/// `notifier` contains the protocol events HTTP notifier type and functions.
+pub mod notifier;
+
+/// `watch` contains the protocol watched addresses actions types and functions.
+pub mod watch;
+
 /// `state` contains the protocol state type and functions.
 pub mod state;
 
+/// `testkit` contains the protocol in-process test network types and functions.
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+
+/// `simulator` contains the protocol consensus simulator types and functions.
+#[cfg(any(test, feature = "testkit"))]
+pub mod simulator;
+
 /// `client` contains the protocol client type and functions.
 pub mod client;
 
+/// `light` contains the protocol light client type and functions.
+pub mod light;
+
 /// `client_server` contains the protocol client server type and functions.
 pub mod client_server;
 
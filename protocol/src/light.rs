@@ -0,0 +1,189 @@
+//! # Light Client
+//!
+//! `light` contains the protocol light client type and functions.
+//! A light client does not keep the DAG: it fetches the `TransactionProof`s of the
+//! `Transaction`s it is interested in from a sample of full nodes, and verifies
+//! them against the eve `Transaction` of its `Genesis`.
+
+use crate::error::Error;
+use crate::result::{handle_result, Result};
+use crate::retry::{Retry, RetryKind};
+use config::consensus::ConsensusConfig;
+use crypto::hash::Digest;
+use crypto::random::Random;
+use log::logger::Logger;
+use models::consensus_message::ConsensusMessage;
+use models::genesis::Genesis;
+use models::node::Node;
+use models::stage::Stage;
+use models::transaction_proof::TransactionProof;
+use network::message::Message;
+use network::traits::Network;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// `LightClient` is the protocol light client type.
+pub struct LightClient<N>
+where
+    N: Network + Send + 'static,
+{
+    pub address: Vec<u8>,
+    pub stage: Stage,
+    pub checkpoint: Digest,
+    pub config: ConsensusConfig,
+    pub nodes: BTreeSet<Vec<u8>>,
+    pub network: Arc<Mutex<N>>,
+    pub logger: Arc<Logger>,
+}
+
+impl<N> LightClient<N>
+where
+    N: Network + Send + 'static,
+{
+    /// `new` creates a new `LightClient` from a `Genesis` and the addresses of
+    /// the full nodes to query.
+    pub fn new(
+        genesis: &Genesis,
+        nodes: &BTreeSet<Vec<u8>>,
+        network: Arc<Mutex<N>>,
+        logger: Arc<Logger>,
+    ) -> Result<LightClient<N>> {
+        let res = genesis.validate().map_err(|e| e.into());
+        handle_result(logger.clone(), res, "Protocol light client creation error")?;
+
+        if nodes.is_empty() {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let address = network.lock().unwrap().local_address()?;
+
+        let mut config = genesis.consensus.clone();
+        config.populate();
+
+        let client = LightClient {
+            address,
+            stage: genesis.stage,
+            checkpoint: genesis.eve_transaction.id,
+            config,
+            nodes: nodes.to_owned(),
+            network,
+            logger,
+        };
+
+        Ok(client)
+    }
+
+    /// `send_message` sends a `ConsensusMessage` to a full node.
+    fn send_message(&self, cons_msg: &ConsensusMessage) -> Result<()> {
+        cons_msg.validate()?;
+
+        let address = cons_msg.node().address;
+        let msg = Message::from_consensus_message(cons_msg)?;
+
+        self.network
+            .lock()
+            .unwrap()
+            .send_message(&address, &msg, self.config.timeout)
+            .map_err(|e| e.into())
+    }
+
+    /// `recv_message` receives a `ConsensusMessage` from a full node.
+    fn recv_message(&self) -> Result<ConsensusMessage> {
+        let msg = self.network.lock().unwrap().recv(self.config.timeout)?;
+
+        let cons_msg = msg.to_consensus_message()?;
+        cons_msg.validate()?;
+
+        Ok(cons_msg)
+    }
+
+    /// `sample_nodes` samples a maximum of k full nodes addresses.
+    fn sample_nodes(&self) -> Result<Vec<Vec<u8>>> {
+        let mut nodes: Vec<Vec<u8>> = self.nodes.iter().cloned().collect();
+        let k = (self.config.k.unwrap() as usize).min(nodes.len());
+
+        let mut sample = Vec::new();
+
+        while sample.len() < k {
+            let idx = Random::u32_range(0, nodes.len() as u32)? as usize;
+            sample.push(nodes.swap_remove(idx));
+        }
+
+        Ok(sample)
+    }
+
+    /// `fetch_node_proof` fetches and verifies the `TransactionProof` of a
+    /// `Transaction` from a full node.
+    pub fn fetch_node_proof(&self, address: &[u8], tx_id: &Digest) -> Result<TransactionProof> {
+        let node = Node::new(self.stage, address);
+
+        let cons_msg = ConsensusMessage::new_fetch_proof(&self.address, &node, *tx_id)?;
+        self.send_message(&cons_msg)?;
+
+        let mut retry = Retry::new(&self.config, RetryKind::Fetch);
+
+        loop {
+            let recv_cons_msg = match self.recv_message() {
+                Ok(recv_cons_msg) => recv_cons_msg,
+                Err(err) => {
+                    if retry.next()? {
+                        continue;
+                    }
+
+                    return Err(err);
+                }
+            };
+
+            if recv_cons_msg.node().address == self.address
+                && recv_cons_msg.id() == cons_msg.id() + 1
+            {
+                if let ConsensusMessage::PushProof { proof, .. } = recv_cons_msg {
+                    if &proof.tx_id != tx_id {
+                        let err = Error::InvalidTransaction;
+                        return Err(err);
+                    }
+
+                    proof.verify(&self.checkpoint)?;
+
+                    return Ok(proof);
+                }
+            }
+
+            if !retry.next()? {
+                let err = Error::NotFound;
+                return Err(err);
+            }
+        }
+    }
+
+    /// `fetch_proofs` fetches and verifies the `TransactionProof`s of a `Transaction`
+    /// from a sample of full nodes, skipping the nodes failing to provide one.
+    pub fn fetch_proofs(&self, tx_id: &Digest) -> Result<Vec<TransactionProof>> {
+        let mut proofs = Vec::new();
+
+        for address in self.sample_nodes()? {
+            match self.fetch_node_proof(&address, tx_id) {
+                Ok(proof) => proofs.push(proof),
+                Err(err) => {
+                    self.logger.log_debug(&format!(
+                        "Protocol light client fetch_node_proof error: {}",
+                        err
+                    ))?;
+                }
+            }
+        }
+
+        Ok(proofs)
+    }
+
+    /// `is_accepted` returns if a `Transaction` is accepted by at least alpha
+    /// of the sampled full nodes, according to their verified `TransactionProof`s.
+    pub fn is_accepted(&self, tx_id: &Digest) -> Result<bool> {
+        let proofs = self.fetch_proofs(tx_id)?;
+
+        let accepted = proofs.iter().filter(|proof| proof.accepted).count() as u32;
+
+        Ok(accepted >= self.config.alpha.unwrap())
+    }
+}
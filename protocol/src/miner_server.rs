@@ -6,6 +6,7 @@ use crate::network::serve_mining;
 use crate::result::{handle_result, Result};
 use crate::state::ProtocolState;
 use log::logger::Logger;
+use mining::scheduler::SchedulerStatus;
 use network::traits::Network;
 use std::sync::{Arc, Mutex};
 use store::traits::Store;
@@ -13,9 +14,9 @@ use store::traits::Store;
 /// `ProtocolMinerServer` is the protocol miner server type.
 pub struct ProtocolMinerServer<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
-    N: Network + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Clone + Send + 'static,
 {
     pub state: Arc<Mutex<ProtocolState<S, P>>>,
     pub network: Arc<Mutex<N>>,
@@ -24,9 +25,9 @@ where
 
 impl<S, P, N> ProtocolMinerServer<S, P, N>
 where
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
-    N: Network + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Clone + Send + 'static,
 {
     /// `new` creates a new `ProtocolMinerServer`.
     pub fn new(
@@ -71,6 +72,30 @@ where
             .map_err(|e| e.into())
     }
 
+    /// `start` starts the mining of the `ProtocolMinerServer`.
+    pub fn start(&self) -> Result<()> {
+        self.state.lock().unwrap().scheduler.start();
+
+        self.logger
+            .log_info("Protocol miner server mining started")
+            .map_err(|e| e.into())
+    }
+
+    /// `stop` stops the mining of the `ProtocolMinerServer`, aborting
+    /// the running mining threads.
+    pub fn stop(&self) -> Result<()> {
+        self.state.lock().unwrap().scheduler.stop();
+
+        self.logger
+            .log_info("Protocol miner server mining stopped")
+            .map_err(|e| e.into())
+    }
+
+    /// `status` returns the mining `SchedulerStatus` of the `ProtocolMinerServer`.
+    pub fn status(&self) -> SchedulerStatus {
+        self.state.lock().unwrap().scheduler.status()
+    }
+
     /// `run` runs the `ProtocolMinerServer`.
     pub fn run(&mut self) -> Result<()> {
         self.logger.log_info("Starting the protocol miner server")?;
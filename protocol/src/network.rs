@@ -2,27 +2,91 @@
 //!
 //! `network` contains the network functionalities used in the module.
 
+use crate::connection::ConnectionManager;
 use crate::error::Error;
+use crate::events::ProtocolEvent;
 use crate::result::{handle_result, Result};
+use crate::retry::{Retry, RetryKind};
 use crate::state::ProtocolState;
+use config::consensus::ConsensusConfig;
 use crypto::hash::Digest;
 use log::logger::Logger;
+use log::trace::TraceContext;
+use models::address::Address;
 use models::conflict_set::ConflictSet;
-use models::consensus_message::ConsensusMessage;
+use models::consensus_message::{ConsensusMessage, ReplyHints};
+use models::consensus_params::ConsensusParams;
+use models::consensus_state::ConsensusMutation;
 use models::error::Error as ModelsError;
 use models::node::Node;
+use models::timestamp::Timestamp;
 use models::traits::Storable;
 use models::transaction::Transaction;
+use models::version::Version;
+use models::work::Work;
 use network::error::Error as NetworkError;
 use network::message::Message;
 use network::traits::Network;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 use store::traits::Store;
 
-/// `handle_message` handles a `ConsensusMessage`.
-pub fn handle_message<S: Store + Send + 'static, P: Store + Send + 'static>(
+/// `INVENTORY_PERIOD` is the number of avalanche steps between two
+/// inventory exchanges.
+pub const INVENTORY_PERIOD: u64 = 16;
+
+/// `PRUNE_PERIOD` is the number of avalanche steps between two prunings
+/// of the store.
+pub const PRUNE_PERIOD: u64 = 256;
+
+/// `OUTBOX_PERIOD` is the number of avalanche steps between two flushes
+/// of the outbox.
+pub const OUTBOX_PERIOD: u64 = 4;
+
+/// `INBOX_DRAIN_LEN` is the maximum number of spilled messages handled
+/// at each avalanche step.
+pub const INBOX_DRAIN_LEN: u32 = 64;
+
+/// `MAX_NODES_PER_SUBNET` is the maximum number of known nodes per subnet
+/// accepted from a `PushNodes`.
+pub const MAX_NODES_PER_SUBNET: usize = 2;
+
+/// `READ_ONLY_STEP_MILLIS` is the duration in milliseconds of a step of the
+/// consensus loop of a read-only replica, which does not run the avalanche steps.
+pub const READ_ONLY_STEP_MILLIS: u64 = 100;
+
+/// `handle_message` handles an incoming `ConsensusMessage`, dropping it if
+/// it was already seen or if it is too old to be checked.
+pub fn handle_message<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    cons_msg: &ConsensusMessage,
+) -> Result<()> {
+    cons_msg.validate()?;
+
+    if cons_msg.id() % 2 == 0 && cons_msg.content_id()? != cons_msg.id() {
+        let err = Error::InvalidMessage;
+        return Err(err);
+    }
+
+    let digest = cons_msg.digest()?;
+
+    {
+        let mut state = state.lock().unwrap();
+
+        if state.replay_window.is_expired(cons_msg.time()) || !state.replay_window.insert(&digest) {
+            let err = Error::ReplayedMessage;
+            return Err(err);
+        }
+    }
+
+    store_message(state, cons_msg)
+}
+
+/// `store_message` stores a `ConsensusMessage`, if the `ProtocolState`
+/// is configured to store messages.
+pub fn store_message<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     cons_msg: &ConsensusMessage,
 ) -> Result<()> {
@@ -32,25 +96,65 @@ pub fn handle_message<S: Store + Send + 'static, P: Store + Send + 'static>(
         return Ok(());
     }
 
-    if !ConsensusMessage::lookup(
-        &*state.lock().unwrap().store.lock().unwrap(),
-        state.lock().unwrap().stage,
-        &cons_msg.id(),
-    )? {
-        ConsensusMessage::create(
-            &mut *state.lock().unwrap().store.lock().unwrap(),
-            state.lock().unwrap().stage,
+    let (stage, store, _) = state.lock().unwrap().store_handles();
+
+    if !ConsensusMessage::lookup(&*store.read().unwrap(), stage, &cons_msg.id())? {
+        let ttl = state
+            .lock()
+            .unwrap()
+            .config
+            .message_ttl
+            .unwrap_or(ConsensusConfig::DEFAULT_MESSAGE_TTL);
+
+        ConsensusMessage::insert_with_ttl(
+            &mut *store.write().unwrap(),
+            stage,
             &cons_msg,
+            Some(ttl),
         )?;
     }
 
     Ok(())
 }
 
+/// `trace_logger` returns the `Logger` traced with the `TraceContext` of a
+/// `ConsensusMessage`. An already traced `Logger` keeps its trace, so that
+/// the messages sent while handling a request are correlated with it.
+pub fn trace_logger(logger: Arc<Logger>, cons_msg: &ConsensusMessage) -> Arc<Logger> {
+    if logger.trace().is_some() {
+        return logger;
+    }
+
+    let trace = TraceContext::from_message(cons_msg);
+    Arc::new(logger.with_trace(&trace))
+}
+
+/// `encode_message` encodes a `ConsensusMessage` in a `Message`. The `CompactQuery`s
+/// and the `Reply`s to the nodes negotiating the compact encoding are encoded in
+/// the compact binary encoding, the other `ConsensusMessage`s in CBOR.
+pub fn encode_message<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    cons_msg: &ConsensusMessage,
+) -> Result<Message> {
+    let compact = match cons_msg {
+        ConsensusMessage::CompactQuery { .. } => true,
+        ConsensusMessage::Reply { node, .. } => {
+            state.lock().unwrap().is_compact_peer(&node.address)?
+        }
+        _ => false,
+    };
+
+    if compact && cons_msg.is_compact_encodable() {
+        Message::from_compact_consensus_message(cons_msg).map_err(|e| e.into())
+    } else {
+        Message::from_consensus_message(cons_msg).map_err(|e| e.into())
+    }
+}
+
 /// `send_message` sends a `ConsensusMessage` to a `Node`.
 pub fn send_message<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -58,6 +162,8 @@ pub fn send_message<
     logger: Arc<Logger>,
     cons_msg: &ConsensusMessage,
 ) -> Result<()> {
+    let logger = trace_logger(logger, cons_msg);
+
     logger.log_info("Sending a consensus message")?;
     logger.log_debug(&format!(
         "Started network send_message of message: {:?}",
@@ -67,23 +173,28 @@ pub fn send_message<
     let res = cons_msg.validate().map_err(|e| e.into());
     handle_result(logger.clone(), res, "Protocol network send_message error")?;
 
-    let res = handle_message(state.clone(), cons_msg);
+    let res = store_message(state.clone(), cons_msg);
     handle_result(logger.clone(), res, "Protocol network send_message error")?;
 
     let address = cons_msg.node().address;
 
-    let res = Message::from_consensus_message(cons_msg).map_err(|e| e.into());
+    let res = encode_message(state.clone(), cons_msg);
     let msg = handle_result(logger.clone(), res, "Protocol network send_message error")?;
 
-    let res = msg.to_bytes().map_err(|e| e.into());
-    let data = handle_result(logger.clone(), res, "Protocol network send_message error")?;
-
     let res = network
         .lock()
         .unwrap()
-        .send(&address, &data, state.lock().unwrap().config.timeout)
+        .send_message(&address, &msg, state.lock().unwrap().config.timeout)
         .map_err(|e| e.into());
 
+    if let Err(ref err) = res {
+        // NB: the message is sent again by `flush_outbox`
+        state
+            .lock()
+            .unwrap()
+            .queue_outbox(cons_msg, &format!("{}", err))?;
+    }
+
     let res = handle_result(logger.clone(), res, "Protocol network send_message error");
     logger.log_info("Consensus message sent")?;
     logger.log_debug(&format!(
@@ -94,10 +205,74 @@ pub fn send_message<
     res
 }
 
+/// `flush_outbox` sends again the `ConsensusMessage`s of the `Outbox` whose
+/// next attempt is due, dropping the ones out of retries or too old to be
+/// accepted by their receivers. It returns the number of sent messages.
+pub fn flush_outbox<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+) -> Result<u32> {
+    let outboxes = state.lock().unwrap().due_outbox()?;
+    let max_attempts = state.lock().unwrap().config.max_retries.unwrap_or(1);
+    let timeout = state.lock().unwrap().config.timeout;
+
+    let mut sent = 0;
+
+    for outbox in outboxes {
+        let logger = trace_logger(logger.clone(), &outbox.message);
+
+        let expired = state
+            .lock()
+            .unwrap()
+            .replay_window
+            .is_expired(outbox.message.time());
+
+        if expired || outbox.attempts >= max_attempts {
+            logger.log_debug(&format!(
+                "Protocol outbox dropped message after {} attempts: {}",
+                outbox.attempts, outbox.last_error
+            ))?;
+
+            state.lock().unwrap().remove_outbox(&outbox.id)?;
+            continue;
+        }
+
+        let address = outbox.message.node().address;
+        let msg = encode_message(state.clone(), &outbox.message)?;
+
+        let res = network
+            .lock()
+            .unwrap()
+            .send_message(&address, &msg, timeout);
+
+        match res {
+            Ok(_) => {
+                state.lock().unwrap().remove_outbox(&outbox.id)?;
+                sent += 1;
+            }
+            Err(err) => {
+                logger.log_debug(&format!("Protocol outbox send error: {}", err))?;
+
+                state
+                    .lock()
+                    .unwrap()
+                    .queue_outbox(&outbox.message, &format!("{}", err))?;
+            }
+        }
+    }
+
+    Ok(sent)
+}
+
 /// `recv_message` receives a `ConsensusMessage` from a `Node`.
 pub fn recv_message<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -121,9 +296,18 @@ pub fn recv_message<
     let res = msg.to_consensus_message().map_err(|e| e.into());
     let cons_msg = handle_result(logger.clone(), res, "Protocol network recv_message error")?;
 
+    let logger = trace_logger(logger, &cons_msg);
+
     let res = handle_message(state, &cons_msg);
     handle_result(logger.clone(), res, "Protocol network recv_message error")?;
 
+    if let ConsensusMessage::Reject { ref reason, .. } = cons_msg {
+        let code = cons_msg.reject_code()?;
+        let msg = reason.to_owned();
+        let res = Err(Error::Rejected { code, msg });
+        return handle_result(logger, res, "Protocol network recv_message error");
+    }
+
     logger.log_info("Received a new consensus message")?;
     logger.log_debug(&format!(
         "Protocol network recv_message message: {:?}",
@@ -134,7 +318,7 @@ pub fn recv_message<
 }
 
 /// `handle_node` elaborates an incoming `Node`.
-pub fn handle_node<S: Store + Send + 'static, P: Store + Send + 'static>(
+pub fn handle_node<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     node: &Node,
 ) -> Result<()> {
@@ -145,43 +329,188 @@ pub fn handle_node<S: Store + Send + 'static, P: Store + Send + 'static>(
         return Err(err);
     }
 
-    if !Node::lookup(
-        &*state.lock().unwrap().store.lock().unwrap(),
-        state.lock().unwrap().stage,
-        &node.id,
-    )? {
-        Node::create(
-            &mut *state.lock().unwrap().store.lock().unwrap(),
-            state.lock().unwrap().stage,
-            &node,
-        )?;
-        state.lock().unwrap().state.add_known_node(node.id);
+    let (stage, store, _) = state.lock().unwrap().store_handles();
+
+    if !Node::lookup(&*store.read().unwrap(), stage, &node.id)? {
+        // NB: the latency and the work are measured locally, the ones of a
+        // gossiped node are dropped
+        let mut node = node.clone();
+        node.latency = None;
+        node.work = 0;
+
+        Node::create(&mut *store.write().unwrap(), stage, &node)?;
+        state
+            .lock()
+            .unwrap()
+            .mutate(ConsensusMutation::AddKnownNode { node_id: node.id })?;
     } else {
-        let known_node = Node::get(
-            &*state.lock().unwrap().store.lock().unwrap(),
-            state.lock().unwrap().stage,
-            &node.id,
-        )?;
+        let known_node = Node::get(&*store.read().unwrap(), stage, &node.id)?;
+
+        let mut node = node.clone();
+
+        let keep_known_announcement = match (&known_node.announcement, &node.announcement) {
+            (Some(known), Some(announcement)) => {
+                // NB: a node can move to a new key only with a rotation signed by the known one
+                if known.public_key != announcement.public_key && !node.is_rotated_from(known) {
+                    let err = Error::InvalidNode;
+                    return Err(err);
+                }
+
+                known.time > announcement.time
+            }
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        if keep_known_announcement {
+            node.addresses = known_node.addresses.clone();
+            node.announcement = known_node.announcement.clone();
+            node.rotation = known_node.rotation.clone();
+        }
+
+        // NB: services are only advertised in a handshake, so gossiped nodes do not carry them
+        if node.services.is_empty() {
+            node.services = known_node.services.clone();
+        }
+
+        node.latency = known_node.latency;
+        node.work = known_node.work;
+
         if known_node.last_seen < node.last_seen {
-            Node::update(
-                &mut *state.lock().unwrap().store.lock().unwrap(),
-                state.lock().unwrap().stage,
-                &node,
-            )?;
+            Node::update(&mut *store.write().unwrap(), stage, &node)?;
         }
 
         if !state.lock().unwrap().state.lookup_known_node(&node.id) {
-            state.lock().unwrap().state.add_known_node(node.id);
+            state
+                .lock()
+                .unwrap()
+                .mutate(ConsensusMutation::AddKnownNode { node_id: node.id })?;
         }
     }
 
     Ok(())
 }
 
+/// `verify_hello` verifies a `Hello` message, rejecting the peers with an
+/// incompatible `Version` or a different `Stage`, and records the advertised
+/// `Service`s on the peer `Node`, which is returned.
+pub fn verify_hello<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    msg: &ConsensusMessage,
+) -> Result<Node> {
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::Hello {
+            address,
+            node,
+            version,
+            stage,
+            services,
+            ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            if stage != state.lock().unwrap().stage {
+                let err = Error::InvalidStage;
+                return Err(err);
+            }
+
+            if !version.is_compatible(&Version::default())? {
+                let err = Error::IncompatibleVersion;
+                return Err(err);
+            }
+
+            let mut peer = Node::new(stage, &address);
+            peer.set_services(&services);
+
+            handle_node(state, &peer)?;
+
+            Ok(peer)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
+}
+
+/// `hello` performs the handshake with a remote node contacted for the first
+/// time, returning the peer `Node` with its advertised `Service`s.
+pub fn hello<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+) -> Result<Node> {
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let services = state.lock().unwrap().services.clone();
+    let cons_msg = ConsensusMessage::new_hello(
+        &*state.lock().unwrap().address,
+        &node,
+        &Version::default(),
+        &services,
+    )?;
+    send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+
+    let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+
+    while max_retries > 0 {
+        let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
+        if recv_cons_msg.is_hello()?
+            && recv_cons_msg.node().address == state.lock().unwrap().address
+            && recv_cons_msg.id() == cons_msg.id() + 1
+        {
+            return verify_hello(state, &recv_cons_msg);
+        } else {
+            max_retries -= 1;
+        }
+    }
+
+    let err = Error::NotFound;
+    Err(err)
+}
+
+/// `handle_hello` handles a `Hello` message, replying with the local `Hello`
+/// when the message is not itself a reply.
+pub fn handle_hello<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    let peer = verify_hello(state.clone(), msg)?;
+
+    if msg.id() % 2 != 0 {
+        return Ok(());
+    }
+
+    let services = state.lock().unwrap().services.clone();
+    let cons_msg = ConsensusMessage::new_hello_reply(
+        &*state.lock().unwrap().address,
+        msg.id(),
+        &peer,
+        &Version::default(),
+        &services,
+    )?;
+    send_message(state, network, logger, &cons_msg)
+}
+
 /// `push_transactions` sends `Transaction`s to a remote node.
 pub fn push_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -206,8 +535,8 @@ pub fn push_transactions<
 
 /// `handle_fetch_transactions` handles a `FetchTransactions` request.
 pub fn handle_fetch_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -233,18 +562,15 @@ pub fn handle_fetch_transactions<
             let node = Node::new(state.lock().unwrap().stage, &address);
             handle_node(state.clone(), &node)?;
 
+            let (stage, store, _) = state.lock().unwrap().store_handles();
             let txs_arc = Arc::new(Mutex::new(BTreeSet::new()));
 
             for id in ids {
-                let state = state.clone();
+                let store = store.clone();
                 let txs_arc = txs_arc.clone();
 
                 thread::spawn(move || {
-                    let res = Transaction::lookup(
-                        &*state.lock().unwrap().store.lock().unwrap(),
-                        state.lock().unwrap().stage,
-                        &id,
-                    );
+                    let res = Transaction::lookup(&*store.read().unwrap(), stage, &id);
 
                     if res.is_err() {
                         let res: Result<()> = res.map(|_| ()).map_err(|e| e.into());
@@ -252,11 +578,7 @@ pub fn handle_fetch_transactions<
                     }
 
                     if res.unwrap() {
-                        let res = Transaction::get(
-                            &*state.lock().unwrap().store.lock().unwrap(),
-                            state.lock().unwrap().stage,
-                            &id,
-                        );
+                        let res = Transaction::get(&*store.read().unwrap(), stage, &id);
 
                         if res.is_err() {
                             let res: Result<()> = res.map(|_| ()).map_err(|e| e.into());
@@ -294,8 +616,8 @@ pub fn handle_fetch_transactions<
 
 /// `handle_fetch_random_transactions` handles a `FetchRandomTransactions` request.
 pub fn handle_fetch_random_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -321,13 +643,9 @@ pub fn handle_fetch_random_transactions<
             let node = Node::new(state.lock().unwrap().stage, &address);
             handle_node(state.clone(), &node)?;
 
-            let transactions = Transaction::sample(
-                &*state.lock().unwrap().store.lock().unwrap(),
-                state.lock().unwrap().stage,
-                None,
-                None,
-                count,
-            )?;
+            let (stage, store, _) = state.lock().unwrap().store_handles();
+            let transactions =
+                Transaction::sample(&*store.read().unwrap(), stage, None, None, count)?;
 
             let cons_msg = ConsensusMessage::new_push_transactions(
                 &*state.lock().unwrap().address,
@@ -346,8 +664,8 @@ pub fn handle_fetch_random_transactions<
 
 /// `handle_push_transactions` handles a `PushTransactions`.
 pub fn handle_push_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -402,8 +720,8 @@ pub fn handle_push_transactions<
 /// `handle_push_random_transactions` handles a `PushTransactions` following a
 /// `FetchRandomTransactions`.
 pub fn handle_push_random_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -459,8 +777,8 @@ pub fn handle_push_random_transactions<
 
 /// `fetch_node_transactions` fetches transactions from a remote node.
 pub fn fetch_node_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -475,10 +793,20 @@ pub fn fetch_node_transactions<
     let cons_msg =
         ConsensusMessage::new_fetch_transactions(&*state.lock().unwrap().address, &node, ids)?;
     send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
-    let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+    let mut retry = Retry::new(&state.lock().unwrap().config, RetryKind::Fetch);
+
+    loop {
+        let recv_cons_msg = match recv_message(state.clone(), network.clone(), logger.clone()) {
+            Ok(recv_cons_msg) => recv_cons_msg,
+            Err(err) => {
+                if retry.next()? {
+                    continue;
+                }
+
+                return Err(err);
+            }
+        };
 
-    while max_retries > 0 {
-        let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
         if recv_cons_msg.is_push_transactions()?
             && recv_cons_msg.node().address == state.lock().unwrap().address
             && recv_cons_msg.id() == cons_msg.id() + 1
@@ -522,8 +850,8 @@ pub fn fetch_node_transactions<
             }
 
             break;
-        } else {
-            max_retries -= 1;
+        } else if !retry.next()? {
+            break;
         }
     }
 
@@ -533,8 +861,8 @@ pub fn fetch_node_transactions<
 
 /// `fetch_transactions` fetches transactions from remote.
 pub fn fetch_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -608,8 +936,8 @@ pub fn fetch_transactions<
 
 /// `fetch_node_random_transactions` fetches random transactions from a remote node.
 pub fn fetch_node_random_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -618,6 +946,13 @@ pub fn fetch_node_random_transactions<
     address: &[u8],
     count: u32,
 ) -> Result<BTreeSet<Transaction>> {
+    let count = state.lock().unwrap().random_fetch_count(count);
+
+    if count == 0 {
+        logger.log_debug("Random fetching paused: the pool is nearly full")?;
+        return Ok(BTreeSet::new());
+    }
+
     let node = Node::new(state.lock().unwrap().stage, address);
     let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
 
@@ -687,8 +1022,8 @@ pub fn fetch_node_random_transactions<
 
 /// `fetch_random_transactions` fetches random transactions from remote.
 pub fn fetch_random_transactions<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -696,6 +1031,13 @@ pub fn fetch_random_transactions<
     logger: Arc<Logger>,
     count: u32,
 ) -> Result<BTreeSet<Transaction>> {
+    let count = state.lock().unwrap().random_fetch_count(count);
+
+    if count == 0 {
+        logger.log_debug("Random fetching paused: the pool is nearly full")?;
+        return Ok(BTreeSet::new());
+    }
+
     let nodes = state.lock().unwrap().sample_nodes()?;
     let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
 
@@ -763,33 +1105,67 @@ pub fn fetch_random_transactions<
     Ok(res)
 }
 
-/// `push_nodes` sends `Node`s to a remote node.
-pub fn push_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `handle_inventory` handles an `Inventory` request, pushing back the known
+/// `Transaction`s missing from the remote `BloomFilter`.
+pub fn handle_inventory<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-    address: &[u8],
-    fetch_id: u64,
-    nodes: &BTreeSet<Node>,
+    msg: &ConsensusMessage,
 ) -> Result<()> {
-    let node = Node::new(state.lock().unwrap().stage, address);
-    let cons_msg = ConsensusMessage::new_push_nodes(
-        &*state.lock().unwrap().address,
-        fetch_id + 1,
-        &node,
-        nodes,
-    )?;
-    send_message(state, network, logger, &cons_msg)
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::Inventory {
+            address,
+            id,
+            node,
+            count,
+            filter,
+            ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            let node = Node::new(state.lock().unwrap().stage, &address);
+            handle_node(state.clone(), &node)?;
+
+            let known_ids = state.lock().unwrap().state.known_transactions.clone();
+            let missing_ids = filter.difference(&known_ids);
+
+            let mut transactions = BTreeSet::new();
+
+            for tx_id in missing_ids.iter().take(count as usize) {
+                let transaction = state.lock().unwrap().get_transaction(tx_id)?;
+                transactions.insert(transaction);
+            }
+
+            let cons_msg = ConsensusMessage::new_push_transactions(
+                &*state.lock().unwrap().address,
+                id + 1,
+                &node,
+                &transactions,
+            )?;
+            send_message(state, network, logger, &cons_msg)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
 }
 
-/// `handle_fetch_nodes` handles a `FetchNodes` request.
-pub fn handle_fetch_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `handle_fetch_proof` handles a `FetchProof` request, pushing back the
+/// `TransactionProof` of the requested `Transaction`.
+pub fn handle_fetch_proof<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -800,11 +1176,11 @@ pub fn handle_fetch_nodes<
     msg.validate()?;
 
     match msg.to_owned() {
-        ConsensusMessage::FetchNodes {
+        ConsensusMessage::FetchProof {
             address,
             id,
             node,
-            ids,
+            tx_id,
             ..
         } => {
             if node.address != state.lock().unwrap().address {
@@ -812,59 +1188,16 @@ pub fn handle_fetch_nodes<
                 return Err(err);
             }
 
+            // NB: light clients are not added to the known nodes
             let node = Node::new(state.lock().unwrap().stage, &address);
-            handle_node(state.clone(), &node)?;
-
-            let nodes_arc = Arc::new(Mutex::new(BTreeSet::new()));
 
-            for id in ids {
-                let state = state.clone();
-                let nodes_arc = nodes_arc.clone();
+            let proof = state.lock().unwrap().get_transaction_proof(&tx_id)?;
 
-                thread::spawn(move || {
-                    let res = Node::lookup(
-                        &*state.lock().unwrap().store.lock().unwrap(),
-                        state.lock().unwrap().stage,
-                        &id,
-                    );
-
-                    if res.is_err() {
-                        let res = res.map(|_| ());
-                        return res;
-                    }
-
-                    if res.unwrap() {
-                        let res = Node::get(
-                            &*state.lock().unwrap().store.lock().unwrap(),
-                            state.lock().unwrap().stage,
-                            &id,
-                        );
-
-                        if res.is_err() {
-                            let res = res.map(|_| ());
-                            return res;
-                        }
-
-                        let node = res.unwrap();
-
-                        nodes_arc.lock().unwrap().insert(node);
-                    }
-
-                    Ok(())
-                })
-                .join()
-                .map_err(|e| Error::Thread {
-                    msg: format!("{:?}", e),
-                })??;
-            }
-
-            let nodes = nodes_arc.lock().unwrap().clone();
-
-            let cons_msg = ConsensusMessage::new_push_nodes(
+            let cons_msg = ConsensusMessage::new_push_proof(
                 &*state.lock().unwrap().address,
-                id + 1,
+                id,
                 &node,
-                &nodes,
+                &proof,
             )?;
             send_message(state, network, logger, &cons_msg)
         }
@@ -875,10 +1208,56 @@ pub fn handle_fetch_nodes<
     }
 }
 
-/// `handle_fetch_random_nodes` handles a `FetchRandomNodes` request.
-pub fn handle_fetch_random_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `fetch_state` fetches from a trusted node the `TransactionState`s of the pending
+/// `Transaction`s in the pool, applying them to the `ConsensusState` to shorten the
+/// warm-up of a new replica. It returns the number of the applied `TransactionState`s.
+pub fn fetch_state<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+) -> Result<u32> {
+    let ids = state.lock().unwrap().state_delta()?;
+
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg = ConsensusMessage::new_fetch_state(&*state.lock().unwrap().address, &node, &ids)?;
+
+    send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+
+    let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+
+    while max_retries > 0 {
+        let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
+
+        if recv_cons_msg.node().address == state.lock().unwrap().address
+            && recv_cons_msg.address() == address
+            && recv_cons_msg.id() == cons_msg.id() + 1
+        {
+            if let ConsensusMessage::PushState { states, .. } = recv_cons_msg {
+                return state.lock().unwrap().apply_transaction_states(&states);
+            }
+        }
+
+        max_retries -= 1;
+    }
+
+    let err = Error::NotFound;
+    Err(err)
+}
+
+/// `handle_fetch_state` handles a `FetchState` request, pushing back the
+/// `TransactionState`s of the requested known `Transaction`s.
+pub fn handle_fetch_state<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -889,11 +1268,11 @@ pub fn handle_fetch_random_nodes<
     msg.validate()?;
 
     match msg.to_owned() {
-        ConsensusMessage::FetchRandomNodes {
+        ConsensusMessage::FetchState {
             address,
             id,
             node,
-            count,
+            ids,
             ..
         } => {
             if node.address != state.lock().unwrap().address {
@@ -904,19 +1283,14 @@ pub fn handle_fetch_random_nodes<
             let node = Node::new(state.lock().unwrap().stage, &address);
             handle_node(state.clone(), &node)?;
 
-            let nodes = Node::sample(
-                &*state.lock().unwrap().store.lock().unwrap(),
-                state.lock().unwrap().stage,
-                None,
-                None,
-                count,
-            )?;
+            let states = state.lock().unwrap().transaction_states(&ids);
 
-            let cons_msg = ConsensusMessage::new_push_nodes(
+            let cons_msg = ConsensusMessage::new_push_state(
                 &*state.lock().unwrap().address,
-                id + 1,
+                id,
                 &node,
-                &nodes,
+                &ids,
+                &states,
             )?;
             send_message(state, network, logger, &cons_msg)
         }
@@ -927,145 +1301,108 @@ pub fn handle_fetch_random_nodes<
     }
 }
 
-/// `handle_push_nodes` handles a `PushNodes`.
-pub fn handle_push_nodes<S: Store + Send + 'static, P: Store + Send + 'static>(
+/// `push_params_update` relays a signed `ConsensusParams` to the sampled nodes.
+pub fn push_params_update<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
-    msg: &ConsensusMessage,
-    fetch_id: u64,
-    ids: &BTreeSet<Digest>,
-) -> Result<BTreeSet<Node>> {
-    msg.validate()?;
-    let expected_ids = ids;
-
-    if msg.is_push_nodes()?
-        && msg.node().address == state.lock().unwrap().address
-        && msg.id() == fetch_id + 1
-    {
-        match msg.to_owned() {
-            ConsensusMessage::PushNodes { ids, nodes, .. } => {
-                if !ids.is_subset(&expected_ids) {
-                    let err = Error::InvalidMessage;
-                    return Err(err);
-                }
-
-                for node in &nodes {
-                    let state = state.clone();
-                    let node = node.clone();
-
-                    thread::spawn(move || handle_node(state, &node))
-                        .join()
-                        .map_err(|e| Error::Thread {
-                            msg: format!("{:?}", e),
-                        })??;
-                }
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    params: &ConsensusParams,
+) -> Result<()> {
+    let nodes = state.lock().unwrap().sample_nodes()?;
 
-                Ok(nodes)
-            }
-            _ => {
-                let err = Error::InvalidMessage;
-                Err(err)
-            }
-        }
-    } else {
-        let err = Error::InvalidMessage;
-        Err(err)
+    for node in nodes {
+        let cons_msg =
+            ConsensusMessage::new_params_update(&*state.lock().unwrap().address, &node, params)?;
+        send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
     }
+
+    Ok(())
 }
 
-/// `handle_push_random_nodes` handles a `PushNodes` following a
-/// `FetchRandomNodes`.
-pub fn handle_push_random_nodes<S: Store + Send + 'static, P: Store + Send + 'static>(
+/// `handle_params_update` handles a `ParamsUpdate` message, applying the
+/// signed `ConsensusParams` and relaying it when it is newer than the
+/// applied one.
+pub fn handle_params_update<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
     msg: &ConsensusMessage,
-    fetch_id: u64,
-    count: u32,
-) -> Result<BTreeSet<Node>> {
+) -> Result<()> {
     msg.validate()?;
-    let expected_count = count;
 
-    if msg.is_push_nodes()?
-        && msg.node().address == state.lock().unwrap().address
-        && msg.id() == fetch_id + 1
-    {
-        match msg.to_owned() {
-            ConsensusMessage::PushNodes { count, nodes, .. } => {
-                if count > expected_count {
-                    let err = Error::InvalidMessage;
-                    return Err(err);
-                }
+    match msg.to_owned() {
+        ConsensusMessage::ParamsUpdate { node, params, .. } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
 
-                for node in &nodes {
-                    let state = state.clone();
-                    let node = node.clone();
+            let applied = state.lock().unwrap().handle_params_update(&params)?;
 
-                    thread::spawn(move || handle_node(state, &node))
-                        .join()
-                        .map_err(|e| Error::Thread {
-                            msg: format!("{:?}", e),
-                        })??;
-                }
+            if applied {
+                logger.log_info(&format!(
+                    "Applied consensus params update {}",
+                    params.sequence
+                ))?;
 
-                Ok(nodes)
-            }
-            _ => {
-                let err = Error::InvalidMessage;
-                Err(err)
+                push_params_update(state, network, logger, &params)?;
             }
+
+            Ok(())
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
         }
-    } else {
-        let err = Error::InvalidMessage;
-        Err(err)
     }
 }
 
-/// `fetch_node_nodes` fetches nodes from a remote node.
-pub fn fetch_node_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `fetch_node_inventory` sends the inventory of the known transactions to a
+/// remote node, fetching the transactions missing from it.
+pub fn fetch_node_inventory<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     address: &[u8],
-    ids: &BTreeSet<Digest>,
-) -> Result<BTreeSet<Node>> {
+    count: u32,
+) -> Result<BTreeSet<Transaction>> {
     let node = Node::new(state.lock().unwrap().stage, address);
-    let cons_msg = ConsensusMessage::new_fetch_nodes(&*state.lock().unwrap().address, &node, ids)?;
+    let known_ids = state.lock().unwrap().state.known_transactions.clone();
+
+    let cons_msg =
+        ConsensusMessage::new_inventory(&*state.lock().unwrap().address, &node, count, &known_ids)?;
+
     send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
 
-    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    let mut res = BTreeSet::new();
     let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
 
     while max_retries > 0 {
         let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
-        if recv_cons_msg.is_push_nodes()?
+        if recv_cons_msg.is_push_transactions()?
             && recv_cons_msg.node().address == state.lock().unwrap().address
             && recv_cons_msg.id() == cons_msg.id() + 1
         {
-            let nodes = handle_push_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), ids)?;
-
-            for node in nodes {
-                let state = state.clone();
-                let node = node.clone();
-                let res_arc = res_arc.clone();
-
-                thread::spawn(move || {
-                    let res: Result<()> = handle_node(state.clone(), &node);
-
-                    if res.is_err() {
-                        return res;
-                    }
-
-                    res_arc.lock().unwrap().insert(node);
-
-                    Ok(())
-                })
-                .join()
-                .map_err(|e| Error::Thread {
-                    msg: format!("{:?}", e),
-                })??;
-            }
+            res = handle_push_random_transactions(
+                state.clone(),
+                network.clone(),
+                logger.clone(),
+                &recv_cons_msg,
+                cons_msg.id(),
+                count,
+            )?;
 
             break;
         } else {
@@ -1073,87 +1410,366 @@ pub fn fetch_node_nodes<
         }
     }
 
-    let res = res_arc.lock().unwrap().clone();
     Ok(res)
 }
 
-/// `fetch_nodes` fetches nodes from remote.
-pub fn fetch_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `fetch_inventory` exchanges the inventory of the known transactions with
+/// remote nodes, fetching the transactions missing from it. The failures of
+/// the single nodes are logged and do not stop the exchange with the others.
+pub fn fetch_inventory<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-    ids: &BTreeSet<Digest>,
-) -> Result<BTreeSet<Node>> {
+    count: u32,
+) -> Result<BTreeSet<Transaction>> {
     let nodes = state.lock().unwrap().sample_nodes()?;
-    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    let mut res = BTreeSet::new();
 
     for node in nodes {
-        let cons_msg =
-            ConsensusMessage::new_fetch_nodes(&*state.lock().unwrap().address, &node, ids)?;
-        send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
-
-        let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
-
-        while max_retries > 0 {
-            let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
-            if recv_cons_msg.is_push_nodes()?
-                && recv_cons_msg.node().address == state.lock().unwrap().address
-                && recv_cons_msg.id() == cons_msg.id() + 1
-            {
-                let nodes = handle_push_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), ids)?;
-
-                for node in nodes {
-                    let state = state.clone();
-                    let node = node.clone();
-                    let res_arc = res_arc.clone();
-
-                    thread::spawn(move || {
-                        let res: Result<()> = handle_node(state.clone(), &node);
-
-                        if res.is_err() {
-                            return res;
-                        }
+        let node_res = fetch_node_inventory(
+            state.clone(),
+            network.clone(),
+            logger.clone(),
+            &node.address,
+            count,
+        );
 
-                        res_arc.lock().unwrap().insert(node);
+        if let Ok(transactions) = handle_result(
+            logger.clone(),
+            node_res,
+            "Protocol network fetch_node_inventory error",
+        ) {
+            res.extend(transactions);
+        }
+    }
 
-                        Ok(())
-                    })
-                    .join()
-                    .map_err(|e| Error::Thread {
-                        msg: format!("{:?}", e),
-                    })??;
-                }
+    Ok(res)
+}
 
-                break;
-            } else {
-                max_retries -= 1;
+/// `push_nodes` sends `Node`s to a remote node.
+pub fn push_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+    fetch_id: u64,
+    nodes: &BTreeSet<Node>,
+) -> Result<()> {
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg = ConsensusMessage::new_push_nodes(
+        &*state.lock().unwrap().address,
+        fetch_id + 1,
+        &node,
+        nodes,
+    )?;
+    send_message(state, network, logger, &cons_msg)
+}
+
+/// `handle_fetch_nodes` handles a `FetchNodes` request.
+pub fn handle_fetch_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::FetchNodes {
+            address,
+            id,
+            node,
+            ids,
+            ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            let node = Node::new(state.lock().unwrap().stage, &address);
+            handle_node(state.clone(), &node)?;
+
+            let nodes_arc = Arc::new(Mutex::new(BTreeSet::new()));
+
+            if let Some(own_node) = state.lock().unwrap().announced_node()? {
+                if ids.contains(&own_node.id) {
+                    nodes_arc.lock().unwrap().insert(own_node);
+                }
+            }
+
+            let (stage, store, _) = state.lock().unwrap().store_handles();
+
+            for id in ids {
+                let store = store.clone();
+                let nodes_arc = nodes_arc.clone();
+
+                thread::spawn(move || {
+                    let res = Node::lookup(&*store.read().unwrap(), stage, &id);
+
+                    if res.is_err() {
+                        let res = res.map(|_| ());
+                        return res;
+                    }
+
+                    if res.unwrap() {
+                        let res = Node::get(&*store.read().unwrap(), stage, &id);
+
+                        if res.is_err() {
+                            let res = res.map(|_| ());
+                            return res;
+                        }
+
+                        let node = res.unwrap();
+
+                        nodes_arc.lock().unwrap().insert(node);
+                    }
+
+                    Ok(())
+                })
+                .join()
+                .map_err(|e| Error::Thread {
+                    msg: format!("{:?}", e),
+                })??;
             }
+
+            let nodes = nodes_arc
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|node| node.is_announced())
+                .cloned()
+                .collect();
+
+            let cons_msg = ConsensusMessage::new_push_nodes(
+                &*state.lock().unwrap().address,
+                id + 1,
+                &node,
+                &nodes,
+            )?;
+            send_message(state, network, logger, &cons_msg)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
         }
     }
+}
 
-    let res = res_arc.lock().unwrap().clone();
-    Ok(res)
+/// `handle_fetch_random_nodes` handles a `FetchRandomNodes` request.
+pub fn handle_fetch_random_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::FetchRandomNodes {
+            address,
+            id,
+            node,
+            count,
+            ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            let node = Node::new(state.lock().unwrap().stage, &address);
+            handle_node(state.clone(), &node)?;
+
+            let (stage, store, _) = state.lock().unwrap().store_handles();
+            let mut nodes: BTreeSet<Node> =
+                Node::sample(&*store.read().unwrap(), stage, None, None, count)?
+                    .into_iter()
+                    .filter(|node| node.is_announced())
+                    .collect();
+
+            if let Some(own_node) = state.lock().unwrap().announced_node()? {
+                if nodes.len() < count as usize {
+                    nodes.insert(own_node);
+                }
+            }
+
+            let cons_msg = ConsensusMessage::new_push_nodes(
+                &*state.lock().unwrap().address,
+                id + 1,
+                &node,
+                &nodes,
+            )?;
+            send_message(state, network, logger, &cons_msg)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
 }
 
-/// `fetch_node_random_nodes` fetches random nodes from a remote node.
-pub fn fetch_node_random_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `handle_announced_nodes` elaborates the `Node`s of a `PushNodes`. Every `Node`
+/// must carry a valid `NodeAnnouncement`, and unknown `Node`s are accepted only
+/// up to `MAX_NODES_PER_SUBNET` known nodes per subnet.
+pub fn handle_announced_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    nodes: &BTreeSet<Node>,
+) -> Result<BTreeSet<Node>> {
+    for node in nodes {
+        node.verify_announcement()
+            .map_err(|_| Error::InvalidMessage)?;
+    }
+
+    let known_nodes = state.lock().unwrap().state.known_nodes.clone();
+    let (stage, store, _) = state.lock().unwrap().store_handles();
+    let mut subnets: BTreeMap<Vec<u8>, usize> = BTreeMap::new();
+
+    for node_id in known_nodes.iter() {
+        let res = Node::get(&*store.read().unwrap(), stage, node_id);
+
+        match res {
+            Ok(node) => *subnets.entry(node.subnet()).or_insert(0) += 1,
+            Err(ModelsError::NotFound) => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    let address = state.lock().unwrap().address.clone();
+    let mut accepted = BTreeSet::new();
+
+    for node in nodes {
+        if node.address == address || state.lock().unwrap().is_blacklisted(node) {
+            continue;
+        }
+
+        if !state.lock().unwrap().state.lookup_known_node(&node.id) {
+            let count = subnets.entry(node.subnet()).or_insert(0);
+
+            if *count >= MAX_NODES_PER_SUBNET {
+                continue;
+            }
+
+            *count += 1;
+        }
+
+        match handle_node(state.clone(), node) {
+            Ok(()) => {
+                accepted.insert(node.clone());
+            }
+            Err(Error::InvalidNode) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(accepted)
+}
+
+/// `handle_push_nodes` handles a `PushNodes`.
+pub fn handle_push_nodes<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    msg: &ConsensusMessage,
+    fetch_id: u64,
+    ids: &BTreeSet<Digest>,
+) -> Result<BTreeSet<Node>> {
+    msg.validate()?;
+    let expected_ids = ids;
+
+    if msg.is_push_nodes()?
+        && msg.node().address == state.lock().unwrap().address
+        && msg.id() == fetch_id + 1
+    {
+        match msg.to_owned() {
+            ConsensusMessage::PushNodes { ids, nodes, .. } => {
+                if !ids.is_subset(&expected_ids) {
+                    let err = Error::InvalidMessage;
+                    return Err(err);
+                }
+
+                handle_announced_nodes(state, &nodes)
+            }
+            _ => {
+                let err = Error::InvalidMessage;
+                Err(err)
+            }
+        }
+    } else {
+        let err = Error::InvalidMessage;
+        Err(err)
+    }
+}
+
+/// `handle_push_random_nodes` handles a `PushNodes` following a
+/// `FetchRandomNodes`.
+pub fn handle_push_random_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    msg: &ConsensusMessage,
+    fetch_id: u64,
+    count: u32,
+) -> Result<BTreeSet<Node>> {
+    msg.validate()?;
+    let expected_count = count;
+
+    if msg.is_push_nodes()?
+        && msg.node().address == state.lock().unwrap().address
+        && msg.id() == fetch_id + 1
+    {
+        match msg.to_owned() {
+            ConsensusMessage::PushNodes { count, nodes, .. } => {
+                if count > expected_count {
+                    let err = Error::InvalidMessage;
+                    return Err(err);
+                }
+
+                handle_announced_nodes(state, &nodes)
+            }
+            _ => {
+                let err = Error::InvalidMessage;
+                Err(err)
+            }
+        }
+    } else {
+        let err = Error::InvalidMessage;
+        Err(err)
+    }
+}
+
+/// `fetch_node_nodes` fetches nodes from a remote node.
+pub fn fetch_node_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     address: &[u8],
-    count: u32,
+    ids: &BTreeSet<Digest>,
 ) -> Result<BTreeSet<Node>> {
-    let node = Node::new(state.lock().unwrap().stage, &address);
-    let cons_msg =
-        ConsensusMessage::new_fetch_random_nodes(&*state.lock().unwrap().address, &node, count)?;
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg = ConsensusMessage::new_fetch_nodes(&*state.lock().unwrap().address, &node, ids)?;
     send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
 
     let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
@@ -1165,8 +1781,145 @@ pub fn fetch_node_random_nodes<
             && recv_cons_msg.node().address == state.lock().unwrap().address
             && recv_cons_msg.id() == cons_msg.id() + 1
         {
-            let nodes =
-                handle_push_random_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), count)?;
+            let nodes = handle_push_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), ids)?;
+
+            for node in nodes {
+                let state = state.clone();
+                let node = node.clone();
+                let res_arc = res_arc.clone();
+
+                thread::spawn(move || {
+                    let res: Result<()> = handle_node(state.clone(), &node);
+
+                    if res.is_err() {
+                        return res;
+                    }
+
+                    res_arc.lock().unwrap().insert(node);
+
+                    Ok(())
+                })
+                .join()
+                .map_err(|e| Error::Thread {
+                    msg: format!("{:?}", e),
+                })??;
+            }
+
+            break;
+        } else {
+            max_retries -= 1;
+        }
+    }
+
+    let res = res_arc.lock().unwrap().clone();
+    Ok(res)
+}
+
+/// `fetch_nodes` fetches nodes from remote.
+pub fn fetch_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    ids: &BTreeSet<Digest>,
+) -> Result<BTreeSet<Node>> {
+    let nodes = state.lock().unwrap().sample_nodes()?;
+    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    let mut retry = Retry::new(&state.lock().unwrap().config, RetryKind::Fetch);
+
+    for node in nodes {
+        if retry.is_expired() {
+            break;
+        }
+
+        let cons_msg =
+            ConsensusMessage::new_fetch_nodes(&*state.lock().unwrap().address, &node, ids)?;
+        send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+
+        retry.reset();
+
+        loop {
+            let recv_cons_msg = match recv_message(state.clone(), network.clone(), logger.clone()) {
+                Ok(recv_cons_msg) => recv_cons_msg,
+                Err(err) => {
+                    if retry.next()? {
+                        continue;
+                    }
+
+                    return Err(err);
+                }
+            };
+
+            if recv_cons_msg.is_push_nodes()?
+                && recv_cons_msg.node().address == state.lock().unwrap().address
+                && recv_cons_msg.id() == cons_msg.id() + 1
+            {
+                let nodes = handle_push_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), ids)?;
+
+                for node in nodes {
+                    let state = state.clone();
+                    let node = node.clone();
+                    let res_arc = res_arc.clone();
+
+                    thread::spawn(move || {
+                        let res: Result<()> = handle_node(state.clone(), &node);
+
+                        if res.is_err() {
+                            return res;
+                        }
+
+                        res_arc.lock().unwrap().insert(node);
+
+                        Ok(())
+                    })
+                    .join()
+                    .map_err(|e| Error::Thread {
+                        msg: format!("{:?}", e),
+                    })??;
+                }
+
+                break;
+            } else if !retry.next()? {
+                break;
+            }
+        }
+    }
+
+    let res = res_arc.lock().unwrap().clone();
+    Ok(res)
+}
+
+/// `fetch_node_random_nodes` fetches random nodes from a remote node.
+pub fn fetch_node_random_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+    count: u32,
+) -> Result<BTreeSet<Node>> {
+    let node = Node::new(state.lock().unwrap().stage, &address);
+    let cons_msg =
+        ConsensusMessage::new_fetch_random_nodes(&*state.lock().unwrap().address, &node, count)?;
+    send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+
+    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+
+    while max_retries > 0 {
+        let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
+        if recv_cons_msg.is_push_nodes()?
+            && recv_cons_msg.node().address == state.lock().unwrap().address
+            && recv_cons_msg.id() == cons_msg.id() + 1
+        {
+            let nodes =
+                handle_push_random_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), count)?;
 
             for node in nodes {
                 let state = state.clone();
@@ -1180,291 +1933,926 @@ pub fn fetch_node_random_nodes<
                         return res;
                     }
 
-                    res_arc.lock().unwrap().insert(node);
+                    res_arc.lock().unwrap().insert(node);
+
+                    Ok(())
+                })
+                .join()
+                .map_err(|e| Error::Thread {
+                    msg: format!("{:?}", e),
+                })??;
+            }
+
+            break;
+        } else {
+            max_retries -= 1;
+        }
+    }
+
+    let res = res_arc.lock().unwrap().clone();
+    Ok(res)
+}
+
+/// `fetch_random_nodes` fetches random nodes from remote.
+pub fn fetch_random_nodes<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    count: u32,
+) -> Result<BTreeSet<Node>> {
+    let nodes = state.lock().unwrap().sample_nodes()?;
+    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+
+    for node in nodes {
+        let cons_msg = ConsensusMessage::new_fetch_random_nodes(
+            &*state.lock().unwrap().address,
+            &node,
+            count,
+        )?;
+
+        send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+
+        let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+
+        while max_retries > 0 {
+            let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
+            if recv_cons_msg.is_push_nodes()?
+                && recv_cons_msg.node().address == state.lock().unwrap().address
+                && recv_cons_msg.id() == cons_msg.id() + 1
+            {
+                let nodes =
+                    handle_push_random_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), count)?;
+
+                for node in nodes {
+                    let state = state.clone();
+                    let node = node.clone();
+                    let res_arc = res_arc.clone();
+
+                    thread::spawn(move || {
+                        let res: Result<()> = handle_node(state.clone(), &node);
+
+                        if res.is_err() {
+                            return res;
+                        }
+
+                        res_arc.lock().unwrap().insert(node);
+
+                        Ok(())
+                    })
+                    .join()
+                    .map_err(|e| Error::Thread {
+                        msg: format!("{:?}", e),
+                    })??;
+                }
+
+                break;
+            } else {
+                max_retries -= 1;
+            }
+        }
+    }
+
+    let res = res_arc.lock().unwrap().clone();
+    Ok(res)
+}
+
+/// `fetch_missing_ancestors` fetches a `Transaction` ancestors from remote if missing.
+pub fn fetch_missing_ancestors<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    transaction: &Transaction,
+) -> Result<BTreeSet<Transaction>> {
+    state.lock().unwrap().validate_transaction(transaction)?;
+
+    let to_fetch: BTreeSet<Digest> = transaction
+        .ancestors()?
+        .iter()
+        .filter(|id| !state.lock().unwrap().state.lookup_known_transaction(&id))
+        .copied()
+        .collect();
+
+    if to_fetch.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+
+    let nodes = state.lock().unwrap().sample_nodes()?;
+    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+
+    for node in &nodes {
+        let state = state.clone();
+        let network = network.clone();
+        let logger = logger.clone();
+        let node = node.clone();
+        let nodes = nodes.clone();
+        let to_fetch = to_fetch.clone();
+        let res_arc = res_arc.clone();
+
+        thread::spawn(move || {
+            let result = fetch_node_transactions(
+                state.clone(),
+                network.clone(),
+                logger.clone(),
+                &node.address,
+                &to_fetch,
+            );
+
+            if let Ok(txs) = result {
+                for tx in txs {
+                    res_arc.lock().unwrap().insert(tx);
+                }
+            } else {
+                let res = state.lock().unwrap().random_node();
+
+                if res.is_err() {
+                    let res = res.map(|_| ());
+                    return res;
+                }
+
+                let mut node = res.unwrap();
+
+                while node.address == state.lock().unwrap().address || nodes.contains(&node) {
+                    node = state.lock().unwrap().random_node()?;
+                }
+
+                let res = fetch_node_transactions(state, network, logger, &node.address, &to_fetch);
+
+                if res.is_err() {
+                    let res = res.map(|_| ());
+                    return res;
+                }
+
+                let txs = res.unwrap();
+
+                for tx in txs {
+                    res_arc.lock().unwrap().insert(tx);
+                }
+            };
+
+            Ok(())
+        })
+        .join()
+        .map_err(|e| Error::Thread {
+            msg: format!("{:?}", e),
+        })??;
+    }
+
+    let res = res_arc.lock().unwrap().clone();
+    Ok(res)
+}
+
+/// `mine` mines a set of `Transaction`s.
+pub fn mine<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+    transactions: &BTreeSet<Transaction>,
+) -> Result<()> {
+    state.lock().unwrap().validate_writable()?;
+
+    for transaction in transactions {
+        state.lock().unwrap().validate_transaction(transaction)?;
+
+        if transaction.is_mined() {
+            let err = Error::AlreadyMined;
+            return Err(err);
+        }
+    }
+
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg =
+        ConsensusMessage::new_mine(&*state.lock().unwrap().address, &node, transactions)?;
+    send_message(state, network, logger, &cons_msg)
+}
+
+/// `handle_mine` handles a `Mine` `ConsensusMessage` request.
+pub fn handle_mine<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    state.lock().unwrap().validate_writable()?;
+
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::Mine {
+            id,
+            address,
+            node,
+            transactions,
+            ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            let node = Node::new(state.lock().unwrap().stage, &address);
+            handle_node(state.clone(), &node)?;
+
+            for transaction in &transactions {
+                state.lock().unwrap().validate_transaction(transaction)?;
+
+                if transaction.is_mined() {
+                    let err = Error::AlreadyMined;
+                    return Err(err);
+                }
+            }
+
+            // NB: with the local mining stopped, the transactions are left to the remote miners.
+            if !state.lock().unwrap().scheduler.status().running {
+                for transaction in &transactions {
+                    state.lock().unwrap().push_work(transaction)?;
+                }
+
+                return Ok(());
+            }
+
+            if state.lock().unwrap().schedule_mining() {
+                logger.log_debug("Protocol mining paused: node behind on consensus")?;
+            }
+
+            let scheduler = state.lock().unwrap().scheduler.clone();
+            let mined_arc = Arc::new(Mutex::new(BTreeSet::new()));
+            let mut handles = Vec::new();
+
+            // NB: the scheduler caps the number of concurrent mining threads.
+            for transaction in &transactions {
+                let mut transaction = transaction.clone();
+                state
+                    .lock()
+                    .unwrap()
+                    .retarget_transaction(&mut transaction)?;
+
+                let mined_arc = mined_arc.clone();
+                let scheduler = scheduler.clone();
+
+                let handle = thread::spawn(move || {
+                    let res = transaction.mine_scheduled(&scheduler);
+
+                    if res.is_err() {
+                        return res;
+                    }
+
+                    mined_arc.lock().unwrap().insert(transaction);
+
+                    Ok(())
+                });
+
+                handles.push(handle);
+            }
+
+            for handle in handles {
+                handle.join().map_err(|e| Error::Thread {
+                    msg: format!("{:?}", e),
+                })??;
+            }
+
+            for transaction in &*mined_arc.lock().unwrap() {
+                let state = state.clone();
+                let network = network.clone();
+                let logger = logger.clone();
+                let transaction = transaction.clone();
+
+                thread::spawn(move || {
+                    handle_transaction(state.clone(), network.clone(), logger.clone(), &transaction)
+                })
+                .join()
+                .map_err(|e| Error::Thread {
+                    msg: format!("{:?}", e),
+                })??;
+            }
+
+            let mined = mined_arc.lock().unwrap().clone();
+
+            let cons_msg = ConsensusMessage::new_push_transactions(
+                &*state.lock().unwrap().address,
+                id + 1,
+                &node,
+                &mined,
+            )?;
+
+            send_message(state, network, logger, &cons_msg)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
+}
+
+/// `get_work` requests a mining `Work` to a miner node.
+pub fn get_work<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+) -> Result<Work> {
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg = ConsensusMessage::new_get_work(&*state.lock().unwrap().address, &node)?;
+
+    send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+
+    let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+
+    while max_retries > 0 {
+        let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
+
+        if recv_cons_msg.node().address == state.lock().unwrap().address
+            && recv_cons_msg.id() == cons_msg.id() + 1
+        {
+            if let ConsensusMessage::PushWork { work, .. } = recv_cons_msg {
+                return Ok(work);
+            }
+        }
+
+        max_retries -= 1;
+    }
+
+    let err = Error::NotFound;
+    Err(err)
+}
+
+/// `handle_get_work` handles a `GetWork` `ConsensusMessage` request.
+pub fn handle_get_work<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    state.lock().unwrap().validate_writable()?;
+
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::GetWork {
+            id, address, node, ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            let node = Node::new(state.lock().unwrap().stage, &address);
+            handle_node(state.clone(), &node)?;
+
+            let work = state.lock().unwrap().next_work()?;
+
+            let cons_msg =
+                ConsensusMessage::new_push_work(&*state.lock().unwrap().address, id, &node, &work)?;
+
+            send_message(state, network, logger, &cons_msg)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
+}
+
+/// `submit_work` submits the solution of a mining `Work` to a miner node.
+pub fn submit_work<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+    work: &Work,
+    nonce: u64,
+    digest: Digest,
+) -> Result<()> {
+    work.proof(nonce, digest)?;
+
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg = ConsensusMessage::new_submit_work(
+        &*state.lock().unwrap().address,
+        &node,
+        work.tx_id,
+        nonce,
+        digest,
+    )?;
+
+    send_message(state, network, logger, &cons_msg)
+}
+
+/// `handle_submit_work` handles a `SubmitWork` `ConsensusMessage` request.
+pub fn handle_submit_work<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    state.lock().unwrap().validate_writable()?;
+
+    msg.validate()?;
+
+    match msg.to_owned() {
+        ConsensusMessage::SubmitWork {
+            id,
+            address,
+            node,
+            tx_id,
+            nonce,
+            digest,
+            ..
+        } => {
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
+
+            let node = Node::new(state.lock().unwrap().stage, &address);
+            handle_node(state.clone(), &node)?;
+
+            let transaction = state.lock().unwrap().submit_work(&tx_id, nonce, digest)?;
+
+            if let Some(ref coinbase) = transaction.coinbase {
+                state
+                    .lock()
+                    .unwrap()
+                    .record_work(&address, coinbase.difficulty)?;
+            }
+
+            handle_transaction(state.clone(), network.clone(), logger.clone(), &transaction)?;
+
+            let mut mined = BTreeSet::new();
+            mined.insert(transaction);
+
+            let cons_msg = ConsensusMessage::new_push_transactions(
+                &*state.lock().unwrap().address,
+                id + 1,
+                &node,
+                &mined,
+            )?;
+
+            send_message(state, network, logger, &cons_msg)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
+}
+
+/// `handle_mining` handles the mining `ConsensusMessage` requests.
+pub fn handle_mining<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    match msg {
+        ConsensusMessage::Mine { .. } => handle_mine(state, network, logger, msg),
+        ConsensusMessage::GetWork { .. } => handle_get_work(state, network, logger, msg),
+        ConsensusMessage::SubmitWork { .. } => handle_submit_work(state, network, logger, msg),
+        _ => {
+            let err = Error::InvalidMessage;
+            Err(err)
+        }
+    }
+}
+
+/// `serve_mining` serves the mining operations.
+pub fn serve_mining<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Clone + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+) -> Result<()> {
+    state.lock().unwrap().validate_writable()?;
+
+    let timeout = state.lock().unwrap().config.timeout;
+
+    // NB: the listener serves on its own copy of the transport, so that the
+    // handlers can send their replies through the shared one.
+    let mut server = network.lock().unwrap().clone();
+
+    server
+        .serve(
+            timeout,
+            Box::new(move |msg| {
+                let cons_msg = msg.to_consensus_message()?;
+
+                serve_message(
+                    state.clone(),
+                    network.clone(),
+                    logger.clone(),
+                    &cons_msg,
+                    |logger| handle_mining(state.clone(), network.clone(), logger, &cons_msg),
+                )
+                .map_err(|e| NetworkError::Consensus {
+                    code: e.code(),
+                    msg: format!("{}", e),
+                })
+            }),
+        )
+        .map_err(|e| e.into())
+}
+
+/// `handle_orphan` parks a `Transaction` with missing ancestors in the orphan pool,
+/// queueing the ancestors not already awaited to be fetched by the consensus loop.
+pub fn handle_orphan<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    logger: Arc<Logger>,
+    transaction: &Transaction,
+    missing: &BTreeSet<Digest>,
+) -> Result<()> {
+    let to_fetch = state.lock().unwrap().orphans.insert(transaction, missing);
+
+    if !to_fetch.is_empty() {
+        logger.log_debug(&format!(
+            "Queued {} missing ancestors of orphan transaction {}",
+            to_fetch.len(),
+            transaction.id.to_string()
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// `fetch_orphan_ancestors` fetches a batch of the queued missing ancestors of the
+/// orphans. The fetched ancestors are handled as they arrive, promoting the orphans
+/// waiting for them, while the orphans waiting for the ancestors that could not be
+/// fetched are abandoned. It returns the number of fetched ancestors.
+pub fn fetch_orphan_ancestors<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+) -> Result<u32> {
+    let ids = state
+        .lock()
+        .unwrap()
+        .orphans
+        .next_fetches(ConsensusMessage::MAX_BATCH_LEN as usize);
+
+    if ids.is_empty() {
+        return Ok(0);
+    }
 
-                    Ok(())
-                })
-                .join()
-                .map_err(|e| Error::Thread {
-                    msg: format!("{:?}", e),
-                })??;
+    let fetched: BTreeSet<Digest> =
+        match fetch_transactions(state.clone(), network, logger.clone(), &ids) {
+            Ok(transactions) => transactions.iter().map(|tx| tx.id).collect(),
+            Err(err) => {
+                logger.log_debug(&format!("Fetch of missing ancestors failed: {}", err))?;
+                BTreeSet::new()
             }
+        };
 
-            break;
-        } else {
-            max_retries -= 1;
+    for ancestor_id in ids.difference(&fetched) {
+        let abandoned = state.lock().unwrap().orphans.abandon(ancestor_id);
+
+        if abandoned > 0 {
+            logger.log_debug(&format!(
+                "Abandoned {} orphan transactions missing ancestor {}",
+                abandoned,
+                ancestor_id.to_string()
+            ))?;
         }
     }
 
-    let res = res_arc.lock().unwrap().clone();
-    Ok(res)
+    Ok(fetched.len() as u32)
 }
 
-/// `fetch_random_nodes` fetches random nodes from remote.
-pub fn fetch_random_nodes<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `promote_orphans` handles the orphans that were waiting only for a
+/// `Transaction` that has been added to the pool.
+pub fn promote_orphans<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-    count: u32,
-) -> Result<BTreeSet<Node>> {
-    let nodes = state.lock().unwrap().sample_nodes()?;
-    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    tx_id: &Digest,
+) -> Result<()> {
+    let promoted = state.lock().unwrap().orphans.resolve(tx_id);
 
-    for node in nodes {
-        let cons_msg = ConsensusMessage::new_fetch_random_nodes(
-            &*state.lock().unwrap().address,
-            &node,
-            count,
-        )?;
+    for orphan in promoted {
+        let res = handle_transaction(state.clone(), network.clone(), logger.clone(), &orphan);
 
-        send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
+        if let Err(err) = res {
+            logger.log_debug(&format!(
+                "Promotion of orphan transaction {} failed: {}",
+                orphan.id.to_string(),
+                err
+            ))?;
+        }
+    }
 
-        let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
+    Ok(())
+}
 
-        while max_retries > 0 {
-            let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
-            if recv_cons_msg.is_push_nodes()?
-                && recv_cons_msg.node().address == state.lock().unwrap().address
-                && recv_cons_msg.id() == cons_msg.id() + 1
-            {
-                let nodes =
-                    handle_push_random_nodes(state.clone(), &recv_cons_msg, cons_msg.id(), count)?;
+/// `check_transaction_policies` checks the admission to the mempool of a `Transaction`
+/// received from remote: its expiry and the mempool acceptance policies. The
+/// `Transaction`s already in the pool or in the store, and the ancestors awaited by
+/// the known ones, are not checked, so that the admission never affects the consensus
+/// on the known `Transaction`s nor the fetching of their history.
+pub fn check_transaction_policies<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    transaction: &Transaction,
+) -> Result<()> {
+    let (stage, store, pool) = state.lock().unwrap().store_handles();
 
-                for node in nodes {
-                    let state = state.clone();
-                    let node = node.clone();
-                    let res_arc = res_arc.clone();
+    if Transaction::lookup(&*pool.read().unwrap(), stage, &transaction.id)?
+        || Transaction::lookup(&*store.read().unwrap(), stage, &transaction.id)?
+    {
+        return Ok(());
+    }
 
-                    thread::spawn(move || {
-                        let res: Result<()> = handle_node(state.clone(), &node);
+    if state.lock().unwrap().is_awaited_ancestor(&transaction.id)? {
+        return Ok(());
+    }
 
-                        if res.is_err() {
-                            return res;
-                        }
+    if transaction.is_expired(Timestamp::now()) {
+        let err = Error::ExpiredTransaction;
+        return Err(err);
+    }
 
-                        res_arc.lock().unwrap().insert(node);
+    state.lock().unwrap().check_policies(transaction)
+}
 
-                        Ok(())
-                    })
-                    .join()
-                    .map_err(|e| Error::Thread {
-                        msg: format!("{:?}", e),
-                    })??;
-                }
+/// `validate_incoming_transaction` validates a `Transaction` received from remote.
+pub fn validate_incoming_transaction<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    transaction: &Transaction,
+) -> Result<()> {
+    state
+        .lock()
+        .unwrap()
+        .validate_limited_transaction(transaction)?;
+    state
+        .lock()
+        .unwrap()
+        .validate_unlocked_transaction(transaction)?;
+    state
+        .lock()
+        .unwrap()
+        .validate_fully_signed_transaction(transaction)?;
+    state
+        .lock()
+        .unwrap()
+        .validate_staked_transaction(transaction)?;
 
-                break;
-            } else {
-                max_retries -= 1;
-            }
+    // NB: the expected difficulty and the maturity are derived from the ancestors,
+    // so the ones of an orphan are enforced when it is promoted and validated again.
+    if state
+        .lock()
+        .unwrap()
+        .missing_ancestors(transaction)?
+        .is_empty()
+    {
+        state
+            .lock()
+            .unwrap()
+            .validate_mature_transaction(transaction)?;
+        state
+            .lock()
+            .unwrap()
+            .validate_mined_transaction(transaction)?;
+    } else {
+        state
+            .lock()
+            .unwrap()
+            .validate_mined_orphan_transaction(transaction)?;
+    }
+
+    if transaction.is_eve()? && transaction.id != state.lock().unwrap().state.eve_transaction_id {
+        let err = Error::InvalidTransaction;
+        return Err(err);
+    }
+
+    for input in transaction.inputs.values() {
+        if input.account.is_eve()?
+            && input.account.address() != state.lock().unwrap().state.eve_account_address
+        {
+            let err = Error::InvalidAccount;
+            return Err(err);
         }
     }
 
-    let res = res_arc.lock().unwrap().clone();
-    Ok(res)
+    state.lock().unwrap().verify_spends(transaction)
 }
 
-/// `fetch_missing_ancestors` fetches a `Transaction` ancestors from remote if missing.
-pub fn fetch_missing_ancestors<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `handle_transaction` elaborates an incoming `Node`.
+/// It is equivalent to the `OnReceiveTx` function in the Avalanche paper.
+pub fn handle_transaction<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     transaction: &Transaction,
-) -> Result<BTreeSet<Transaction>> {
-    transaction.validate()?;
+) -> Result<()> {
+    let tx_id = transaction.id;
 
-    let to_fetch: BTreeSet<Digest> = transaction
-        .ancestors()?
-        .iter()
-        .filter(|id| !state.lock().unwrap().state.lookup_known_transaction(&id))
-        .copied()
-        .collect();
+    let res = validate_incoming_transaction(state.clone(), transaction)
+        .and_then(|_| check_transaction_policies(state.clone(), transaction));
 
-    if to_fetch.is_empty() {
-        return Ok(BTreeSet::new());
+    if let Err(ref err) = res {
+        state
+            .lock()
+            .unwrap()
+            .emit(ProtocolEvent::TransactionRejected {
+                tx_id,
+                reason: format!("{}", err),
+                addresses: transaction.addresses(),
+            });
     }
 
-    let nodes = state.lock().unwrap().sample_nodes()?;
-    let res_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    res?;
 
-    for node in &nodes {
-        let state = state.clone();
-        let network = network.clone();
-        let logger = logger.clone();
-        let node = node.clone();
-        let nodes = nodes.clone();
-        let to_fetch = to_fetch.clone();
-        let res_arc = res_arc.clone();
+    // NB: state may have been cleared, so the first places to check are the stores
 
-        thread::spawn(move || {
-            let result = fetch_node_transactions(
-                state.clone(),
-                network.clone(),
-                logger.clone(),
-                &node.address,
-                &to_fetch,
-            );
+    let (stage, store, pool) = state.lock().unwrap().store_handles();
 
-            if let Ok(txs) = result {
-                for tx in txs {
-                    res_arc.lock().unwrap().insert(tx);
-                }
-            } else {
-                let res = state.lock().unwrap().random_node();
+    if !Transaction::lookup(&*pool.read().unwrap(), stage, &tx_id)?
+        && !Transaction::lookup(&*store.read().unwrap(), stage, &tx_id)?
+    {
+        let missing = state.lock().unwrap().missing_ancestors(transaction)?;
 
-                if res.is_err() {
-                    let res = res.map(|_| ());
-                    return res;
-                }
+        if !missing.is_empty() {
+            return handle_orphan(state, logger, transaction, &missing);
+        }
 
-                let mut node = res.unwrap();
+        Transaction::create(&mut *pool.write().unwrap(), stage, &transaction)?;
 
-                while node.address == state.lock().unwrap().address || nodes.contains(&node) {
-                    node = state.lock().unwrap().random_node()?;
-                }
+        state
+            .lock()
+            .unwrap()
+            .mutate(ConsensusMutation::AddKnownTransaction { tx_id })?;
 
-                let res = fetch_node_transactions(state, network, logger, &node.address, &to_fetch);
+        state.lock().unwrap().queue.push(transaction)?;
 
-                if res.is_err() {
-                    let res = res.map(|_| ());
-                    return res;
-                }
+        state.lock().unwrap().add_successor(transaction)?;
 
-                let txs = res.unwrap();
+        state.lock().unwrap().record_seen(&tx_id)?;
 
-                for tx in txs {
-                    res_arc.lock().unwrap().insert(tx);
-                }
-            };
+        state.lock().unwrap().emit(ProtocolEvent::TransactionAdded {
+            tx_id,
+            addresses: transaction.addresses(),
+        });
 
-            Ok(())
-        })
-        .join()
-        .map_err(|e| Error::Thread {
-            msg: format!("{:?}", e),
-        })??;
-    }
+        state.lock().unwrap().upsert_conflict_sets(&transaction)?;
 
-    let res = res_arc.lock().unwrap().clone();
-    Ok(res)
-}
+        state
+            .lock()
+            .unwrap()
+            .mutate(ConsensusMutation::SetTransactionChit { tx_id, chit: false })?;
 
-/// `mine` mines a set of `Transaction`s.
-pub fn mine<S: Store + Send + 'static, P: Store + Send + 'static, N: Network + Send + 'static>(
-    state: Arc<Mutex<ProtocolState<S, P>>>,
-    network: Arc<Mutex<N>>,
-    logger: Arc<Logger>,
-    address: &[u8],
-    transactions: &BTreeSet<Transaction>,
-) -> Result<()> {
-    for transaction in transactions {
-        transaction.validate()?;
+        state
+            .lock()
+            .unwrap()
+            .mutate(ConsensusMutation::SetTransactionConfidence {
+                tx_id,
+                confidence: 0,
+            })?;
 
-        if transaction.is_mined() {
-            let err = Error::AlreadyMined;
-            return Err(err);
-        }
+        promote_orphans(state.clone(), network.clone(), logger.clone(), &tx_id)?;
     }
 
-    let node = Node::new(state.lock().unwrap().stage, address);
-    let cons_msg =
-        ConsensusMessage::new_mine(&*state.lock().unwrap().address, &node, transactions)?;
-    send_message(state, network, logger, &cons_msg)
+    Ok(())
 }
 
-/// `handle_mine` handles a `Mine` `ConsensusMessage` request.
-pub fn handle_mine<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `broadcast_transaction` handles a local `Transaction` and sends it to the
+/// sampled nodes, querying them, returning the number of nodes reached. A
+/// read-only replica keeps the `Transaction` without sending it.
+pub fn broadcast_transaction<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-    msg: &ConsensusMessage,
-) -> Result<()> {
-    msg.validate()?;
-
-    match msg.to_owned() {
-        ConsensusMessage::Mine {
-            id,
-            address,
-            node,
-            transactions,
-            ..
-        } => {
-            if node.address != state.lock().unwrap().address {
-                let err = Error::InvalidAddress;
-                return Err(err);
-            }
-
-            let node = Node::new(state.lock().unwrap().stage, &address);
-            handle_node(state.clone(), &node)?;
-
-            for transaction in &transactions {
-                transaction.validate()?;
-
-                if transaction.is_mined() {
-                    let err = Error::AlreadyMined;
-                    return Err(err);
-                }
-            }
+    transaction: &Transaction,
+) -> Result<u32> {
+    handle_transaction(state.clone(), network.clone(), logger.clone(), transaction)?;
 
-            let mined_arc = Arc::new(Mutex::new(BTreeSet::new()));
+    if state.lock().unwrap().read_only {
+        return Ok(0);
+    }
 
-            for transaction in &transactions {
-                let mut transaction = transaction.clone();
-                let mined_arc = mined_arc.clone();
+    let nodes = state.lock().unwrap().sample_nodes()?;
+    let mut reached = 0;
 
-                thread::spawn(move || {
-                    let res = transaction.mine();
+    for node in nodes {
+        let res = query_node(
+            state.clone(),
+            network.clone(),
+            logger.clone(),
+            &node.address,
+            transaction,
+        );
+
+        match res {
+            Ok(_) => reached += 1,
+            Err(err) => logger.log_debug(&format!(
+                "Broadcast of transaction {} to node {} failed: {}",
+                transaction.id.to_string(),
+                node.id.to_string(),
+                err
+            ))?,
+        }
+    }
 
-                    if res.is_err() {
-                        return res;
-                    }
+    Ok(reached)
+}
 
-                    mined_arc.lock().unwrap().insert(transaction);
+/// `handle_reply` handles a `Reply` request, returning its chit and tallying its hints.
+pub fn handle_reply<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    msg: &ConsensusMessage,
+    query_id: u64,
+    transaction_id: &Digest,
+) -> Result<bool> {
+    msg.validate()?;
 
-                    Ok(())
-                })
-                .join()
-                .map_err(|e| Error::Thread {
-                    msg: format!("{:?}", e),
-                })??;
+    match msg.to_owned() {
+        ConsensusMessage::Reply {
+            id,
+            node,
+            tx_id,
+            chit,
+            confidence,
+            preferred,
+            ..
+        } => {
+            if id != query_id + 1 {
+                let err = Error::InvalidId;
+                return Err(err);
             }
 
-            for transaction in &*mined_arc.lock().unwrap() {
-                let state = state.clone();
-                let network = network.clone();
-                let logger = logger.clone();
-                let transaction = transaction.clone();
+            if node.address != state.lock().unwrap().address {
+                let err = Error::InvalidAddress;
+                return Err(err);
+            }
 
-                thread::spawn(move || {
-                    handle_transaction(state.clone(), network.clone(), logger.clone(), &transaction)
-                })
-                .join()
-                .map_err(|e| Error::Thread {
-                    msg: format!("{:?}", e),
-                })??;
+            if transaction_id != &tx_id {
+                let err = Error::InvalidId;
+                return Err(err);
             }
 
-            let mined = mined_arc.lock().unwrap().clone();
+            let hints = ReplyHints {
+                confidence,
+                preferred,
+            };
 
-            let cons_msg = ConsensusMessage::new_push_transactions(
-                &*state.lock().unwrap().address,
-                id + 1,
-                &node,
-                &mined,
-            )?;
+            state.lock().unwrap().reply_tallies.record(&tx_id, &hints);
 
-            send_message(state, network, logger, &cons_msg)
+            Ok(chit)
         }
         _ => {
             let err = Error::InvalidMessage;
@@ -1473,159 +2861,256 @@ pub fn handle_mine<
     }
 }
 
-/// `serve_mining` serves the mining operations.
-pub fn serve_mining<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `query_node` queries a single remote node.
+pub fn query_node<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-) -> Result<()> {
-    let timeout = state.lock().unwrap().config.timeout;
+    address: &[u8],
+    transaction: &Transaction,
+) -> Result<bool> {
+    state.lock().unwrap().validate_writable()?;
 
-    network
-        .clone()
-        .lock()
-        .unwrap()
-        .serve(
-            timeout,
-            Box::new(move |msg| {
-                let cons_msg = msg.to_consensus_message()?;
+    let node = Node::new(state.lock().unwrap().stage, address);
+    let cons_msg = if state.lock().unwrap().is_compact_peer(address)? {
+        ConsensusMessage::new_compact_query(&*state.lock().unwrap().address, &node, transaction)?
+    } else {
+        ConsensusMessage::new_query(&*state.lock().unwrap().address, &node, transaction)?
+    };
+    let start = Instant::now();
+    send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
 
-                handle_mine(state.clone(), network.clone(), logger.clone(), &cons_msg).map_err(
-                    |e| NetworkError::Consensus {
-                        msg: format!("{}", e),
-                    },
-                )
-            }),
-        )
-        .map_err(|e| e.into())
+    let mut res = false;
+    let mut retry = Retry::new(&state.lock().unwrap().config, RetryKind::Query);
+
+    loop {
+        let recv_cons_msg = match recv_message(state.clone(), network.clone(), logger.clone()) {
+            Ok(recv_cons_msg) => recv_cons_msg,
+            Err(err) => {
+                if retry.next()? {
+                    continue;
+                }
+
+                return Err(err);
+            }
+        };
+
+        if recv_cons_msg.is_reply()?
+            && recv_cons_msg.node().address == state.lock().unwrap().address
+            && recv_cons_msg.id() == cons_msg.id() + 1
+        {
+            res = handle_reply(
+                state.clone(),
+                &recv_cons_msg,
+                cons_msg.id(),
+                &transaction.id,
+            )?;
+
+            let rtt = start.elapsed().as_millis() as u64;
+            state.lock().unwrap().record_latency(address, rtt)?;
+
+            break;
+        } else if !retry.next()? {
+            break;
+        }
+    }
+
+    Ok(res)
 }
 
-/// `update_ancestors` updates the ancestors set of a `Transaction`.
-pub fn update_ancestors<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `query` queries remote nodes.
+pub fn query<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     transaction: &Transaction,
-) -> Result<()> {
-    let mut res = Ok(());
+) -> Result<u32> {
+    state.lock().unwrap().validate_writable()?;
 
-    for ancestor in
-        fetch_missing_ancestors(state.clone(), network.clone(), logger.clone(), transaction)?
-    {
+    let nodes = state.lock().unwrap().sample_nodes()?;
+    let res_arc = Arc::new(Mutex::new(0));
+
+    for node in nodes {
         let state = state.clone();
         let network = network.clone();
         let logger = logger.clone();
+        let node = node.clone();
+        let transaction = transaction.clone();
+        let res_arc = res_arc.clone();
 
-        res = thread::spawn(move || handle_transaction(state, network, logger, &ancestor))
-            .join()
-            .map_err(|e| Error::Thread {
-                msg: format!("{:?}", e),
-            })?;
+        thread::spawn(move || {
+            let res = query_node(
+                state.clone(),
+                network.clone(),
+                logger.clone(),
+                &node.address,
+                &transaction,
+            );
 
-        if res.is_err() {
-            return res;
-        }
+            state.lock().unwrap().adaptive.record_query(res.is_ok());
+
+            if res.is_err() {
+                let res: Result<()> = res.map(|_| ());
+                return res;
+            }
+
+            let chit = res.unwrap() as u32;
+            *res_arc.lock().unwrap() += chit;
+
+            Ok(())
+        })
+        .join()
+        .map_err(|e| Error::Thread {
+            msg: format!("{:?}", e),
+        })??;
     }
 
-    res
+    let res = *res_arc.lock().unwrap();
+    Ok(res)
 }
 
-/// `handle_transaction` elaborates an incoming `Node`.
-/// It is equivalent to the `OnReceiveTx` function in the Avalanche paper.
-pub fn handle_transaction<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `reply` replies to a `Query` or a `CompactQuery` request.
+/// In the Avalanche paper the function is called "OnQuery".
+pub fn reply<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-    transaction: &Transaction,
+    msg: &ConsensusMessage,
 ) -> Result<()> {
-    transaction.validate_fully_signed()?;
-    transaction.validate_mined()?;
+    state.lock().unwrap().validate_writable()?;
 
-    let tx_id = transaction.id;
+    msg.validate()?;
 
-    if transaction.is_eve()? && tx_id != state.lock().unwrap().state.eve_transaction_id {
-        let err = Error::InvalidTransaction;
+    if msg.node().address != state.lock().unwrap().address {
+        let err = Error::InvalidAddress;
         return Err(err);
     }
 
-    for input in transaction.inputs.values() {
-        if input.account.is_eve()?
-            && input.account.address() != state.lock().unwrap().state.eve_account_address
-        {
-            let err = Error::InvalidAccount;
+    let (address, id, tx_id) = match msg.to_owned() {
+        ConsensusMessage::Query {
+            address,
+            id,
+            transaction,
+            ..
+        } => (address, id, transaction.id),
+        ConsensusMessage::CompactQuery {
+            address,
+            id,
+            tx_id,
+            tx_time,
+            ..
+        } => {
+            fetch_queried_transaction(
+                state.clone(),
+                network.clone(),
+                logger.clone(),
+                &address,
+                &tx_id,
+                tx_time,
+            )?;
+
+            (address, id, tx_id)
+        }
+        _ => {
+            let err = Error::InvalidMessage;
             return Err(err);
         }
-    }
-
-    // NB: state may have been cleared, so the first places to check are the stores
+    };
 
-    if !Transaction::lookup(
-        &*state.lock().unwrap().pool.lock().unwrap(),
-        state.lock().unwrap().stage,
-        &tx_id,
-    )? && !Transaction::lookup(
-        &*state.lock().unwrap().store.lock().unwrap(),
-        state.lock().unwrap().stage,
-        &tx_id,
-    )? {
-        Transaction::create(
-            &mut *state.lock().unwrap().pool.lock().unwrap(),
-            state.lock().unwrap().stage,
-            &transaction,
-        )?;
+    let chit = is_strongly_preferred(&state, &tx_id)?;
+    let hints = state.lock().unwrap().reply_hints(&tx_id);
+    let node = Node::new(state.lock().unwrap().stage, &address);
+    handle_node(state.clone(), &node)?;
 
-        state.lock().unwrap().state.add_known_transaction(tx_id);
+    let cons_msg =
+        ConsensusMessage::new_reply(&*state.lock().unwrap().address, id, &node, tx_id, chit)?
+            .with_reply_hints(hints)?;
 
-        state.lock().unwrap().upsert_conflict_sets(&transaction)?;
+    send_message(state, network, logger, &cons_msg)
+}
 
-        state
-            .lock()
-            .unwrap()
-            .state
-            .set_transaction_chit(tx_id, false)?;
+/// `is_strongly_preferred` returns if a `Transaction` is strongly preferred, reading
+/// the pool and the store without holding the `ProtocolState` lock.
+fn is_strongly_preferred<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: &Arc<Mutex<ProtocolState<S, P>>>,
+    tx_id: &Digest,
+) -> Result<bool> {
+    let (stage, store, pool) = state.lock().unwrap().store_handles();
+    let res = Transaction::get(&*pool.read().unwrap(), stage, tx_id);
 
-        state
+    match res {
+        Ok(transaction) => state
             .lock()
             .unwrap()
-            .state
-            .set_transaction_confidence(tx_id, 0)?;
+            .is_strongly_preferred_transaction(&transaction),
+        Err(ModelsError::NotFound) => {
+            // if it was stored in the store it was
+            // accepted (chit = 1)
+            Transaction::lookup(&*store.read().unwrap(), stage, tx_id).map_err(|e| e.into())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
 
-        update_ancestors(state.clone(), network.clone(), logger.clone(), transaction)?;
-        state.lock().unwrap().update_successors(transaction)?;
+/// `fetch_queried_transaction` fetches from the querying node the `Transaction` of
+/// a `CompactQuery`, when it is not known, handling it as a new `Transaction`.
+pub fn fetch_queried_transaction<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    address: &[u8],
+    tx_id: &Digest,
+    tx_time: Timestamp,
+) -> Result<()> {
+    if state.lock().unwrap().is_known_transaction(tx_id)? {
+        return Ok(());
     }
 
-    Ok(())
+    let mut ids = BTreeSet::new();
+    ids.insert(*tx_id);
+
+    let transactions = fetch_node_transactions(state, network, logger, address, &ids)?;
+
+    match transactions.iter().next() {
+        Some(transaction) if transaction.time == tx_time => Ok(()),
+        Some(_) => {
+            let err = Error::InvalidTransaction;
+            Err(err)
+        }
+        None => {
+            let err = Error::NotFound;
+            Err(err)
+        }
+    }
 }
 
-/// `handle_reply` handles a `Reply` request.
-pub fn handle_reply<S: Store + Send + 'static, P: Store + Send + 'static>(
+/// `handle_batch_reply` handles a `BatchReply` request.
+pub fn handle_batch_reply<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     msg: &ConsensusMessage,
     query_id: u64,
-    transaction_id: &Digest,
-) -> Result<bool> {
+    transaction_ids: &BTreeSet<Digest>,
+) -> Result<BTreeMap<Digest, bool>> {
     msg.validate()?;
 
     match msg.to_owned() {
-        ConsensusMessage::Reply {
-            id,
-            node,
-            tx_id,
-            chit,
-            ..
-        } => {
+        ConsensusMessage::BatchReply { id, node, ids, .. } => {
             if id != query_id + 1 {
                 let err = Error::InvalidId;
                 return Err(err);
@@ -1636,12 +3121,12 @@ pub fn handle_reply<S: Store + Send + 'static, P: Store + Send + 'static>(
                 return Err(err);
             }
 
-            if transaction_id != &tx_id {
+            if transaction_ids != &ids {
                 let err = Error::InvalidId;
                 return Err(err);
             }
 
-            Ok(chit)
+            msg.batch_chits().map_err(|e| e.into())
         }
         _ => {
             let err = Error::InvalidMessage;
@@ -1650,38 +3135,41 @@ pub fn handle_reply<S: Store + Send + 'static, P: Store + Send + 'static>(
     }
 }
 
-/// `query_node` queries a single remote node.
-pub fn query_node<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+/// `batch_query_node` queries a single remote node about many `Transaction`s.
+pub fn batch_query_node<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     address: &[u8],
-    transaction: &Transaction,
-) -> Result<bool> {
+    transactions: &BTreeSet<Transaction>,
+) -> Result<BTreeMap<Digest, bool>> {
+    state.lock().unwrap().validate_writable()?;
+
     let node = Node::new(state.lock().unwrap().stage, address);
     let cons_msg =
-        ConsensusMessage::new_query(&*state.lock().unwrap().address, &node, transaction)?;
+        ConsensusMessage::new_batch_query(&*state.lock().unwrap().address, &node, transactions)?;
+    let start = Instant::now();
     send_message(state.clone(), network.clone(), logger.clone(), &cons_msg)?;
 
-    let mut res = false;
+    let ids: BTreeSet<Digest> = transactions.iter().map(|tx| tx.id).collect();
+
+    let mut res = BTreeMap::new();
     let mut max_retries = state.lock().unwrap().config.max_retries.unwrap_or(1);
 
     while max_retries > 0 {
         let recv_cons_msg = recv_message(state.clone(), network.clone(), logger.clone())?;
-        if recv_cons_msg.is_reply()?
+        if recv_cons_msg.is_batch_reply()?
             && recv_cons_msg.node().address == state.lock().unwrap().address
             && recv_cons_msg.id() == cons_msg.id() + 1
         {
-            res = handle_reply(
-                state.clone(),
-                &recv_cons_msg,
-                cons_msg.id(),
-                &transaction.id,
-            )?;
+            res = handle_batch_reply(state.clone(), &recv_cons_msg, cons_msg.id(), &ids)?;
+
+            let rtt = start.elapsed().as_millis() as u64;
+            state.lock().unwrap().record_latency(address, rtt)?;
 
             break;
         } else {
@@ -1692,40 +3180,50 @@ pub fn query_node<
     Ok(res)
 }
 
-/// `query` queries remote nodes.
-pub fn query<S: Store + Send + 'static, P: Store + Send + 'static, N: Network + Send + 'static>(
+/// `batch_query` queries remote nodes about many `Transaction`s, returning
+/// the sum of the chits of each `Transaction`.
+pub fn batch_query<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
-    transaction: &Transaction,
-) -> Result<u32> {
+    transactions: &BTreeSet<Transaction>,
+) -> Result<BTreeMap<Digest, u32>> {
+    state.lock().unwrap().validate_writable()?;
+
     let nodes = state.lock().unwrap().sample_nodes()?;
-    let res_arc = Arc::new(Mutex::new(0));
+    let res_arc = Arc::new(Mutex::new(BTreeMap::new()));
 
     for node in nodes {
         let state = state.clone();
         let network = network.clone();
         let logger = logger.clone();
         let node = node.clone();
-        let transaction = transaction.clone();
+        let transactions = transactions.clone();
         let res_arc = res_arc.clone();
 
         thread::spawn(move || {
-            let res = query_node(
+            let res = batch_query_node(
                 state.clone(),
                 network.clone(),
                 logger.clone(),
                 &node.address,
-                &transaction,
+                &transactions,
             );
 
+            state.lock().unwrap().adaptive.record_query(res.is_ok());
+
             if res.is_err() {
                 let res: Result<()> = res.map(|_| ());
                 return res;
             }
 
-            let chit = res.unwrap() as u32;
-            *res_arc.lock().unwrap() += chit;
+            for (tx_id, chit) in res.unwrap() {
+                *res_arc.lock().unwrap().entry(tx_id).or_insert(0) += chit as u32;
+            }
 
             Ok(())
         })
@@ -1735,26 +3233,31 @@ pub fn query<S: Store + Send + 'static, P: Store + Send + 'static, N: Network +
         })??;
     }
 
-    let res = *res_arc.lock().unwrap();
+    let res = res_arc.lock().unwrap().clone();
     Ok(res)
 }
 
-/// `reply` replies to a `Query` request.
-/// In the Avalanche paper the function is called "OnQuery".
-pub fn reply<S: Store + Send + 'static, P: Store + Send + 'static, N: Network + Send + 'static>(
+/// `batch_reply` replies to a `BatchQuery` request.
+pub fn batch_reply<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     msg: &ConsensusMessage,
 ) -> Result<()> {
+    state.lock().unwrap().validate_writable()?;
+
     msg.validate()?;
 
     match msg.to_owned() {
-        ConsensusMessage::Query {
+        ConsensusMessage::BatchQuery {
             address,
             id,
             node,
-            transaction,
+            transactions,
             ..
         } => {
             if node.address != state.lock().unwrap().address {
@@ -1762,19 +3265,21 @@ pub fn reply<S: Store + Send + 'static, P: Store + Send + 'static, N: Network +
                 return Err(err);
             }
 
-            let chit = state
-                .lock()
-                .unwrap()
-                .is_strongly_preferred(&transaction.id)?;
+            let mut chits = BTreeMap::new();
+
+            for transaction in transactions.iter() {
+                let chit = is_strongly_preferred(&state, &transaction.id)?;
+                chits.insert(transaction.id, chit);
+            }
+
             let node = Node::new(state.lock().unwrap().stage, &address);
             handle_node(state.clone(), &node)?;
 
-            let cons_msg = ConsensusMessage::new_reply(
+            let cons_msg = ConsensusMessage::new_batch_reply(
                 &*state.lock().unwrap().address,
                 id,
                 &node,
-                transaction.id,
-                chit,
+                &chits,
             )?;
 
             send_message(state, network, logger, &cons_msg)
@@ -1786,14 +3291,167 @@ pub fn reply<S: Store + Send + 'static, P: Store + Send + 'static, N: Network +
     }
 }
 
-/// `handle` handles incoming `ConsensusMessage`s.
-pub fn handle<S: Store + Send + 'static, P: Store + Send + 'static, N: Network + Send + 'static>(
+/// `is_invalid_message` returns if an error is caused by an invalid
+/// `ConsensusMessage`, and so is recorded against its sender.
+fn is_invalid_message(err: &Error) -> bool {
+    match err {
+        Error::InvalidMessage
+        | Error::InvalidId
+        | Error::InvalidLength
+        | Error::InvalidStage
+        | Error::InvalidAccount
+        | Error::InvalidNode
+        | Error::InvalidTransaction
+        | Error::InvalidAddress
+        | Error::AlreadyMined => true,
+        _ => false,
+    }
+}
+
+/// `serve_message` handles an incoming `ConsensusMessage` with a handler,
+/// refusing it if its sender is banned and recording it in the `Greylist`
+/// if invalid, so that its sender is banned when it misbehaves repeatedly.
+/// The handler logs with a `Logger` traced with the message `TraceContext`.
+pub fn serve_message<S, P, N, F>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+    handler: F,
+) -> Result<()>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+    F: FnOnce(Arc<Logger>) -> Result<()>,
+{
+    let logger = trace_logger(logger, msg);
+    let address = msg.address();
+
+    if state.lock().unwrap().is_banned(&address)? {
+        let err = Error::BannedNode;
+        return Err(err);
+    }
+
+    let res = match msg.validate() {
+        Ok(_) => handler(logger.clone()),
+        Err(_) => Err(Error::InvalidMessage),
+    };
+
+    if let Err(ref err) = res {
+        if is_invalid_message(err) {
+            let reason = format!("{}", err);
+
+            if state.lock().unwrap().record_invalid(&address, &reason)? {
+                logger.log_info(&format!(
+                    "Protocol banned node {} after repeated invalid messages",
+                    String::from_utf8_lossy(&address)
+                ))?;
+            }
+        }
+
+        reject(state, network, logger.clone(), msg, err)?;
+    }
+
+    res
+}
+
+/// `reject` replies to a failed request `ConsensusMessage` with a `Reject`
+/// carrying the `ErrorCode` of the failure. Replies, replayed messages and
+/// messages from banned nodes are not rejected.
+pub fn reject<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
     msg: &ConsensusMessage,
+    err: &Error,
 ) -> Result<()> {
-    msg.validate()?;
+    match err {
+        Error::ReplayedMessage | Error::BannedNode => return Ok(()),
+        _ => {}
+    }
+
+    if msg.id() % 2 != 0 {
+        return Ok(());
+    }
+
+    let node = Node::new(state.lock().unwrap().stage, &msg.address());
+    let cons_msg = ConsensusMessage::new_reject(
+        &*state.lock().unwrap().address,
+        msg.id(),
+        &node,
+        err.code(),
+        &format!("{}", err),
+    )?;
+
+    let res = send_message(state, network, logger.clone(), &cons_msg);
+
+    if let Err(err) = res {
+        logger.log_debug(&format!("Protocol network reject error: {}", err))?;
+    }
+
+    Ok(())
+}
+
+/// `handle` handles incoming `ConsensusMessage`s. While the consensus loop is
+/// behind, the queries are spilled to the `Inbox` and handled later by `drain_inbox`,
+/// unless the `Inbox` is full.
+pub fn handle<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    handle_message(state.clone(), msg)?;
+
+    if is_spillable(msg) && state.lock().unwrap().is_behind() {
+        if state.lock().unwrap().spill_inbox(msg)? {
+            logger.log_debug(&format!(
+                "Protocol network spilled message {} to the inbox",
+                msg.id()
+            ))?;
+
+            return Ok(());
+        }
+
+        logger.log_debug("Protocol network inbox full, handling the message right away")?;
+    }
+
+    dispatch_message(state, network, logger, msg)
+}
+
+/// `is_spillable` returns if an incoming `ConsensusMessage` can be spilled to the
+/// `Inbox`, being handled through the consensus state.
+fn is_spillable(msg: &ConsensusMessage) -> bool {
+    match msg {
+        ConsensusMessage::Query { .. }
+        | ConsensusMessage::CompactQuery { .. }
+        | ConsensusMessage::BatchQuery { .. } => true,
+        _ => false,
+    }
+}
+
+/// `dispatch_message` dispatches an incoming `ConsensusMessage`, already checked
+/// by `handle_message`, to its handler.
+pub fn dispatch_message<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    msg: &ConsensusMessage,
+) -> Result<()> {
+    let read_only = state.lock().unwrap().read_only;
 
     match msg.to_owned() {
         ConsensusMessage::FetchNodes { .. } => {
@@ -1808,8 +3466,77 @@ pub fn handle<S: Store + Send + 'static, P: Store + Send + 'static, N: Network +
         ConsensusMessage::FetchRandomTransactions { .. } => {
             handle_fetch_random_transactions(state.clone(), network.clone(), logger.clone(), msg)
         }
-        ConsensusMessage::Query { .. } => {
-            reply(state.clone(), network.clone(), logger.clone(), msg)
+        ConsensusMessage::Inventory { .. } => {
+            handle_inventory(state.clone(), network.clone(), logger.clone(), msg)
+        }
+        ConsensusMessage::FetchProof { .. } => {
+            handle_fetch_proof(state.clone(), network.clone(), logger.clone(), msg)
+        }
+        ConsensusMessage::FetchState { .. } => {
+            handle_fetch_state(state.clone(), network.clone(), logger.clone(), msg)
+        }
+        ConsensusMessage::ParamsUpdate { .. } => {
+            handle_params_update(state.clone(), network.clone(), logger.clone(), msg)
+        }
+        ConsensusMessage::Hello { .. } => {
+            handle_hello(state.clone(), network.clone(), logger.clone(), msg)
+        }
+        ConsensusMessage::Reject { code, reason, .. } => {
+            logger.log_debug(&format!(
+                "Protocol network rejected message {}: E{} {}",
+                msg.id() - 1,
+                code,
+                reason
+            ))?;
+
+            Ok(())
+        }
+        // NB: a replica keeps the queried transactions but does not vote on them,
+        // rejecting the queries so that the querying node does not wait for a reply.
+        ConsensusMessage::Query { transaction, .. } => {
+            if read_only {
+                handle_transaction(state.clone(), network.clone(), logger.clone(), &transaction)?;
+                Err(Error::ReadOnly)
+            } else {
+                reply(state.clone(), network.clone(), logger.clone(), msg)
+            }
+        }
+        ConsensusMessage::CompactQuery {
+            address,
+            tx_id,
+            tx_time,
+            ..
+        } => {
+            if read_only {
+                fetch_queried_transaction(
+                    state.clone(),
+                    network.clone(),
+                    logger.clone(),
+                    &address,
+                    &tx_id,
+                    tx_time,
+                )?;
+
+                Err(Error::ReadOnly)
+            } else {
+                reply(state.clone(), network.clone(), logger.clone(), msg)
+            }
+        }
+        ConsensusMessage::BatchQuery { transactions, .. } => {
+            if read_only {
+                for transaction in transactions.iter() {
+                    handle_transaction(
+                        state.clone(),
+                        network.clone(),
+                        logger.clone(),
+                        transaction,
+                    )?;
+                }
+
+                Err(Error::ReadOnly)
+            } else {
+                batch_reply(state.clone(), network.clone(), logger.clone(), msg)
+            }
         }
         _ => {
             let err = Error::InvalidMessage;
@@ -1818,11 +3545,70 @@ pub fn handle<S: Store + Send + 'static, P: Store + Send + 'static, N: Network +
     }
 }
 
+/// `drain_inbox` handles in order the oldest `ConsensusMessage`s spilled to the
+/// `Inbox`, up to `INBOX_DRAIN_LEN`, dropping the ones too old to be answered.
+/// It returns the number of handled messages.
+pub fn drain_inbox<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+) -> Result<u32> {
+    let inboxes = state.lock().unwrap().drain_inbox(INBOX_DRAIN_LEN)?;
+    let mut handled = 0;
+
+    for inbox in inboxes {
+        let logger = trace_logger(logger.clone(), &inbox.message);
+
+        let expired = state
+            .lock()
+            .unwrap()
+            .replay_window
+            .is_expired(inbox.message.time());
+
+        if expired {
+            logger.log_debug(&format!(
+                "Protocol inbox dropped expired message {}",
+                inbox.message.id()
+            ))?;
+
+            continue;
+        }
+
+        let res = dispatch_message(
+            state.clone(),
+            network.clone(),
+            logger.clone(),
+            &inbox.message,
+        );
+
+        match res {
+            Ok(_) => handled += 1,
+            Err(err) => {
+                logger.log_debug(&format!("Protocol inbox handling error: {}", err))?;
+
+                reject(
+                    state.clone(),
+                    network.clone(),
+                    logger.clone(),
+                    &inbox.message,
+                    &err,
+                )?;
+            }
+        }
+    }
+
+    Ok(handled)
+}
+
 /// `serve_client` serves the client `ConsensusMessage`s.
 pub fn serve_client<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
-    N: Network + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Clone + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
@@ -1830,66 +3616,249 @@ pub fn serve_client<
 ) -> Result<()> {
     let timeout = state.lock().unwrap().config.timeout;
 
-    network
-        .clone()
-        .lock()
-        .unwrap()
+    // NB: the listener serves on its own copy of the transport, so that the
+    // handlers can send their replies through the shared one.
+    let mut server = network.lock().unwrap().clone();
+
+    server
         .serve(
             timeout,
             Box::new(move |msg| {
                 let cons_msg = msg.to_consensus_message()?;
 
-                handle(state.clone(), network.clone(), logger.clone(), &cons_msg).map_err(|e| {
-                    NetworkError::Consensus {
-                        msg: format!("{}", e),
-                    }
+                serve_message(
+                    state.clone(),
+                    network.clone(),
+                    logger.clone(),
+                    &cons_msg,
+                    |logger| handle(state.clone(), network.clone(), logger, &cons_msg),
+                )
+                .map_err(|e| NetworkError::Consensus {
+                    code: e.code(),
+                    msg: format!("{}", e),
                 })
             }),
         )
         .map_err(|e| e.into())
 }
 
+/// `update_chit_sum` updates the consensus state of a queried `Transaction` given
+/// the sum of the chits received from the sampled nodes.
+pub fn update_chit_sum<S: Store + Send + Sync + 'static, P: Store + Send + Sync + 'static>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    tx: &Transaction,
+    chit_sum: u32,
+) -> Result<()> {
+    let tx_id = tx.id;
+    let (stage, store, pool) = state.lock().unwrap().store_handles();
+
+    let chit = chit_sum >= state.lock().unwrap().threshold();
+
+    if chit {
+        state
+            .lock()
+            .unwrap()
+            .mutate(ConsensusMutation::SetTransactionChit { tx_id, chit: true })?;
+
+        let mut cs = if let Some(cs_id) = state
+            .lock()
+            .unwrap()
+            .state
+            .get_transaction_conflict_set(&tx_id)
+        {
+            ConflictSet::get(&*pool.read().unwrap(), stage, &cs_id)
+        } else {
+            let err = ModelsError::NotFound;
+            Err(err)
+        }?;
+
+        cs.validate()?;
+
+        state.lock().unwrap().update_confidence(&tx_id)?;
+
+        if cs.preferred.is_none() || cs.last.is_none() {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        let pref_id = cs.preferred.unwrap();
+        let last_id = cs.last.unwrap();
+
+        let pref_confidence = state
+            .lock()
+            .unwrap()
+            .state
+            .get_transaction_confidence(&pref_id)
+            .unwrap_or(0);
+
+        let confidence = state
+            .lock()
+            .unwrap()
+            .state
+            .get_transaction_confidence(&tx_id)
+            .unwrap_or(0);
+
+        if confidence > pref_confidence {
+            cs.preferred = Some(tx_id);
+        }
+
+        if tx_id != last_id {
+            cs.last = Some(tx_id);
+            cs.count = 1;
+        } else {
+            cs.count += 1;
+        }
+
+        ConflictSet::update(&mut *pool.write().unwrap(), stage, &cs)?;
+
+        let accepted = !Transaction::lookup(&*store.read().unwrap(), stage, &tx_id)?;
+
+        Transaction::insert(&mut *store.write().unwrap(), stage, tx)?;
+
+        state.lock().unwrap().apply_to_ledger(tx)?;
+
+        if accepted {
+            let mut state = state.lock().unwrap();
+
+            state.record_accepted(&tx_id)?;
+
+            state.emit(ProtocolEvent::TransactionAccepted {
+                tx_id,
+                addresses: tx.addresses(),
+            });
+
+            state.trigger_watches(tx)?;
+
+            for conflict_id in cs.transactions.iter().filter(|id| **id != tx_id) {
+                let mut addresses = BTreeSet::new();
+                addresses.insert(cs.address);
+
+                state.emit(ProtocolEvent::TransactionRejected {
+                    tx_id: *conflict_id,
+                    reason: format!("Conflicting transaction {} accepted", tx_id),
+                    addresses,
+                });
+            }
+        }
+    } else {
+        let ancestors: BTreeSet<Digest> = tx
+            .ancestors()?
+            .iter()
+            .filter(|id| state.lock().unwrap().state.lookup_known_transaction(&id))
+            .copied()
+            .collect();
+
+        for tx_id in ancestors {
+            let state = state.clone();
+            let pool = pool.clone();
+
+            thread::spawn(move || {
+                if let Some(cs_id) = state
+                    .lock()
+                    .unwrap()
+                    .state
+                    .get_transaction_conflict_set(&tx_id)
+                {
+                    let res = ConflictSet::get(&*pool.read().unwrap(), stage, &cs_id);
+
+                    let mut cs = res.unwrap();
+
+                    let res = cs.validate();
+
+                    if res.is_err() {
+                        let res = res.map_err(|e| e.into());
+                        return res;
+                    }
+
+                    cs.count = 0;
+
+                    let res = ConflictSet::update(&mut *pool.write().unwrap(), stage, &cs);
+
+                    if res.is_err() {
+                        let res = res.map_err(|e| e.into());
+                        return res;
+                    }
+
+                    Ok(())
+                } else {
+                    let err = Error::NotFound;
+                    Err(err)
+                }
+            })
+            .join()
+            .map_err(|e| Error::Thread {
+                msg: format!("{:?}", e),
+            })??;
+        }
+    }
+
+    let confidence = state
+        .lock()
+        .unwrap()
+        .state
+        .get_transaction_confidence(&tx_id)
+        .unwrap_or(0);
+
+    state
+        .lock()
+        .unwrap()
+        .record_query(&tx_id, chit_sum, chit, confidence)?;
+
+    state
+        .lock()
+        .unwrap()
+        .mutate(ConsensusMutation::AddQueriedTransaction { tx_id: tx.id })?;
+
+    state.lock().unwrap().queue.remove(&tx.id);
+
+    Ok(())
+}
+
 /// `avalanche_step` is a single execution of the main Avalanche Consensus procedure.
 pub fn avalanche_step<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
     network: Arc<Mutex<N>>,
     logger: Arc<Logger>,
 ) -> Result<()> {
-    let tx_ids: BTreeSet<Digest> = state
-        .lock()
-        .unwrap()
-        .state
-        .known_transactions
-        .iter()
-        .filter(|id| !state.lock().unwrap().state.lookup_queried_transaction(&id))
-        .copied()
-        .collect();
+    state.lock().unwrap().validate_writable()?;
+
+    let requeried = state.lock().unwrap().requery_stalled()?;
+
+    if requeried > 0 {
+        logger.log_debug(&format!("Re-queried {} stalled transactions", requeried))?;
+    }
+
+    let tx_ids: BTreeSet<Digest> = {
+        let state = state.lock().unwrap();
+
+        state
+            .state
+            .known_transactions
+            .iter()
+            .filter(|id| !state.state.lookup_queried_transaction(&id))
+            .copied()
+            .collect()
+    };
 
     for tx_id in tx_ids {
-        let tx = match Transaction::get(
-            &*state.lock().unwrap().pool.lock().unwrap(),
-            state.lock().unwrap().stage,
-            &tx_id,
-        ) {
-            Ok(tx) => {
-                tx.validate()?;
-                Ok(tx)
-            }
-            Err(ModelsError::NotFound) => {
-                let tx = Transaction::get(
-                    &*state.lock().unwrap().store.lock().unwrap(),
-                    state.lock().unwrap().stage,
-                    &tx_id,
-                )?;
-                tx.validate()?;
-                Ok(tx)
-            }
-            Err(err) => Err(err),
-        }?;
+        if !state.lock().unwrap().queue.lookup(&tx_id) {
+            let tx = state.lock().unwrap().get_transaction(&tx_id)?;
+            state.lock().unwrap().queue.push(&tx)?;
+        }
+    }
+
+    let tx_ids = state.lock().unwrap().queue.ids();
+
+    let mut txs = Vec::new();
+
+    for tx_id in tx_ids {
+        let tx = state.lock().unwrap().get_transaction(&tx_id)?;
+
+        state.lock().unwrap().validate_transaction(&tx)?;
 
         let missing_txs =
             fetch_missing_ancestors(state.clone(), network.clone(), logger.clone(), &tx)?;
@@ -1909,152 +3878,303 @@ pub fn avalanche_step<
             })??;
         }
 
-        let chit_sum = query(state.clone(), network.clone(), logger.clone(), &tx)?;
+        txs.push(tx);
+    }
 
-        let mut config = state.lock().unwrap().config.clone();
-        config.populate();
+    let partitions = {
+        let tx_ids: Vec<Digest> = txs.iter().map(|tx| tx.id).collect();
+        let mut txs: BTreeMap<Digest, Transaction> =
+            txs.into_iter().map(|tx| (tx.id, tx)).collect();
 
-        if chit_sum >= config.alpha.unwrap() {
-            state
-                .lock()
-                .unwrap()
-                .state
-                .set_transaction_chit(tx_id, true)?;
+        state
+            .lock()
+            .unwrap()
+            .state
+            .partition_by_conflict_set(&tx_ids)
+            .into_iter()
+            .map(|ids| ids.iter().filter_map(|id| txs.remove(id)).collect())
+            .collect::<Vec<Vec<Transaction>>>()
+    };
+
+    let mut config = state.lock().unwrap().config.clone();
+    config.populate();
+
+    let max_threads = config.max_threads.unwrap().max(1) as usize;
+
+    // NB: the queries share the network and its replies, so they are never concurrent.
+    // The transactions of a conflict set are queried in order, one per round, while
+    // the ones of independent conflict sets are batched together in the same round,
+    // and so are processed concurrently once their chit sums are known.
+    for round in conflict_rounds(partitions) {
+        for batch in round.chunks(ConsensusMessage::MAX_BATCH_LEN as usize) {
+            let chit_sums = query_batch(state.clone(), network.clone(), logger.clone(), batch)?;
+
+            for txs in batch.chunks(max_threads) {
+                let handles: Vec<_> = txs
+                    .iter()
+                    .cloned()
+                    .map(|tx| {
+                        let state = state.clone();
+                        let chit_sum = chit_sums.get(&tx.id).copied().unwrap_or(0);
+
+                        thread::spawn(move || update_chit_sum(state, &tx, chit_sum))
+                    })
+                    .collect();
 
-            let mut cs = if let Some(cs_id) = state
-                .lock()
-                .unwrap()
-                .state
-                .get_transaction_conflict_set(&tx_id)
-            {
-                ConflictSet::get(
-                    &*state.lock().unwrap().pool.lock().unwrap(),
-                    state.lock().unwrap().stage,
-                    &cs_id,
-                )
-            } else {
-                let err = ModelsError::NotFound;
-                Err(err)
-            }?;
+                let mut res = Ok(());
 
-            cs.validate()?;
+                for handle in handles {
+                    let handle_res = handle
+                        .join()
+                        .map_err(|e| Error::Thread {
+                            msg: format!("{:?}", e),
+                        })
+                        .and_then(|res| res);
 
-            state.lock().unwrap().update_confidence(&tx_id)?;
+                    if res.is_ok() {
+                        res = handle_res;
+                    }
+                }
 
-            if cs.preferred.is_none() || cs.last.is_none() {
-                let err = Error::NotFound;
-                return Err(err);
+                res?;
             }
+        }
+    }
 
-            let pref_id = cs.preferred.unwrap();
-            let last_id = cs.last.unwrap();
+    check_reply_tallies(state, network, logger)?;
 
-            let pref_confidence = state
-                .lock()
-                .unwrap()
-                .state
-                .get_transaction_confidence(&pref_id)
-                .unwrap_or(0);
+    Ok(())
+}
 
-            let confidence = state
-                .lock()
-                .unwrap()
-                .state
-                .get_transaction_confidence(&tx_id)
-                .unwrap_or(0);
+/// `check_reply_tallies` checks the tallies of the hints of the last replies against
+/// the conflict sets of the queried `Transaction`s. When most of the hinted replies
+/// prefer another `Transaction` of the conflict set, the divergence is logged and
+/// that `Transaction` is fetched if missing and queried first at the next step.
+/// The chits and the confidences are left untouched.
+pub fn check_reply_tallies<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+) -> Result<u32> {
+    let tallies = state.lock().unwrap().reply_tallies.drain();
+    let mut divergent = 0;
+
+    let conflict_sets = {
+        let state = state.lock().unwrap();
+
+        let addresses: BTreeSet<Address> = tallies
+            .iter()
+            .filter(|(_, tally)| tally.majority_preferred().is_some())
+            .filter_map(|(tx_id, _)| state.state.get_transaction_conflict_set(tx_id))
+            .collect();
+
+        state.get_conflict_sets(&addresses).unwrap_or_default()
+    };
+
+    for (tx_id, tally) in tallies {
+        let remote_preferred = match tally.majority_preferred() {
+            Some(remote_preferred) => remote_preferred,
+            None => continue,
+        };
+
+        let local_preferred = state
+            .lock()
+            .unwrap()
+            .state
+            .get_transaction_conflict_set(&tx_id)
+            .and_then(|cs_id| conflict_sets.get(&cs_id))
+            .and_then(|cs| cs.preferred);
+
+        if local_preferred == Some(remote_preferred) {
+            continue;
+        }
+
+        divergent += 1;
+
+        logger.log_debug(&format!(
+            "Protocol divergence on transaction {}: sampled nodes prefer transaction {}",
+            tx_id.to_string(),
+            remote_preferred.to_string()
+        ))?;
+
+        if state
+            .lock()
+            .unwrap()
+            .state
+            .lookup_queried_transaction(&remote_preferred)
+        {
+            continue;
+        }
+
+        if !state
+            .lock()
+            .unwrap()
+            .state
+            .lookup_known_transaction(&remote_preferred)
+        {
+            let mut ids = BTreeSet::new();
+            ids.insert(remote_preferred);
+
+            let res = fetch_transactions(state.clone(), network.clone(), logger.clone(), &ids);
 
-            if confidence > pref_confidence {
-                cs.preferred = Some(tx_id);
+            if let Err(err) = res {
+                logger.log_debug(&format!(
+                    "Protocol fetch of preferred transaction {} failed: {}",
+                    remote_preferred.to_string(),
+                    err
+                ))?;
+
+                continue;
             }
+        }
 
-            if tx_id != last_id {
-                cs.last = Some(tx_id);
-                cs.count = 1;
-            } else {
-                cs.count += 1;
+        if state
+            .lock()
+            .unwrap()
+            .state
+            .lookup_known_transaction(&remote_preferred)
+        {
+            state.lock().unwrap().queue.promote(&remote_preferred);
+        }
+    }
+
+    Ok(divergent)
+}
+
+/// `conflict_rounds` splits in rounds the partitions of the `Transaction`s by
+/// conflict set, the i-th round holding the i-th `Transaction` of each partition.
+fn conflict_rounds(partitions: Vec<Vec<Transaction>>) -> Vec<Vec<Transaction>> {
+    let mut rounds: Vec<Vec<Transaction>> = Vec::new();
+
+    for partition in partitions {
+        for (i, tx) in partition.into_iter().enumerate() {
+            if rounds.len() <= i {
+                rounds.push(Vec::new());
             }
 
-            ConflictSet::update(
-                &mut *state.lock().unwrap().pool.lock().unwrap(),
-                state.lock().unwrap().stage,
-                &cs,
-            )?;
+            rounds[i].push(tx);
+        }
+    }
 
-            Transaction::insert(
-                &mut *state.lock().unwrap().store.lock().unwrap(),
-                state.lock().unwrap().stage,
-                &tx,
-            )?;
-        } else {
-            let ancestors: BTreeSet<Digest> = tx
-                .ancestors()?
-                .iter()
-                .filter(|id| state.lock().unwrap().state.lookup_known_transaction(&id))
-                .copied()
-                .collect();
+    rounds
+}
 
-            for tx_id in ancestors {
-                let state = state.clone();
+/// `query_batch` queries the sampled nodes about a batch of `Transaction`s,
+/// returning the sum of the chits of each `Transaction`.
+pub fn query_batch<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    batch: &[Transaction],
+) -> Result<BTreeMap<Digest, u32>> {
+    if batch.len() == 1 {
+        let tx = &batch[0];
+        let chit_sum = query(state, network, logger, tx)?;
 
-                thread::spawn(move || {
-                    if let Some(cs_id) = state
-                        .lock()
-                        .unwrap()
-                        .state
-                        .get_transaction_conflict_set(&tx_id)
-                    {
-                        let res = ConflictSet::get(
-                            &*state.lock().unwrap().pool.lock().unwrap(),
-                            state.lock().unwrap().stage,
-                            &cs_id,
-                        );
+        let mut chit_sums = BTreeMap::new();
+        chit_sums.insert(tx.id, chit_sum);
 
-                        let mut cs = res.unwrap();
+        Ok(chit_sums)
+    } else {
+        let transactions: BTreeSet<Transaction> = batch.iter().cloned().collect();
+        batch_query(state, network, logger, &transactions)
+    }
+}
 
-                        let res = cs.validate();
+/// `maintain_connections` keeps the outbound peers at their target number,
+/// dialing new nodes from the store when below target and, if required,
+/// first rotating a fraction of the peers. It returns the number of dialed nodes.
+pub fn maintain_connections<
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+>(
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    rotate: bool,
+) -> Result<u32> {
+    let mut excluded = if rotate {
+        state.lock().unwrap().connections.rotate()?
+    } else {
+        BTreeSet::new()
+    };
 
-                        if res.is_err() {
-                            let res = res.map_err(|e| e.into());
-                            return res;
-                        }
+    excluded.extend(state.lock().unwrap().excluded_nodes()?);
 
-                        cs.count = 0;
+    let missing = state.lock().unwrap().connections.missing_peers();
 
-                        let res = ConflictSet::update(
-                            &mut *state.lock().unwrap().pool.lock().unwrap(),
-                            state.lock().unwrap().stage,
-                            &cs,
-                        );
+    if missing == 0 {
+        return Ok(0);
+    }
 
-                        if res.is_err() {
-                            let res = res.map_err(|e| e.into());
-                            return res;
-                        }
+    let candidates = {
+        let state = state.lock().unwrap();
+        let store = state.store.read().unwrap();
 
-                        Ok(())
-                    } else {
-                        let err = Error::NotFound;
-                        Err(err)
-                    }
-                })
-                .join()
-                .map_err(|e| Error::Thread {
-                    msg: format!("{:?}", e),
-                })??;
+        state
+            .connections
+            .candidates(&*store, state.stage, missing, &excluded)?
+    };
+
+    let count = state.lock().unwrap().config.k.unwrap_or(1);
+    let mut dialed = 0;
+
+    for node in candidates {
+        // NB: the node is dialed by a handshake, then by fetching its known
+        // nodes, which also feeds the store with new candidates.
+        let res = hello(
+            state.clone(),
+            network.clone(),
+            logger.clone(),
+            &node.address,
+        )
+        .and_then(|_| {
+            fetch_node_random_nodes(
+                state.clone(),
+                network.clone(),
+                logger.clone(),
+                &node.address,
+                count,
+            )
+        });
+
+        match res {
+            Ok(_) => {
+                state.lock().unwrap().connections.add_peer(node.id)?;
+                dialed += 1;
             }
-        }
+            Err(Error::InvalidStage) | Err(Error::IncompatibleVersion) => {
+                logger.log_debug(&format!(
+                    "Protocol connection manager rejected node {}",
+                    node.id.to_string()
+                ))?;
 
-        state.lock().unwrap().state.add_queried_transaction(tx.id)?;
+                state.lock().unwrap().remove_node(&node.id)?;
+            }
+            Err(err) => {
+                logger.log_debug(&format!("Protocol connection manager dial error: {}", err))?;
+            }
+        }
     }
 
-    Ok(())
+    Ok(dialed)
 }
 
 /// `serve_consensus` serves the `Protocol` consensus.
 /// The name of the function in the Avalanche paper is "AvalancheLoop".
+/// A read-only replica runs only the maintenance and the inbox steps.
 pub fn serve_consensus<
-    S: Store + Send + 'static,
-    P: Store + Send + 'static,
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
     N: Network + Send + 'static,
 >(
     state: Arc<Mutex<ProtocolState<S, P>>>,
@@ -2062,8 +4182,92 @@ pub fn serve_consensus<
     logger: Arc<Logger>,
 ) -> Result<()> {
     let mut res = Ok(());
+    let mut step = 0u64;
+
+    while res.is_ok() && !state.lock().unwrap().is_stopped() {
+        // NB: the failures of the network maintenance are logged and retried
+        // at the next period, without stopping the consensus.
+        if step % INVENTORY_PERIOD == 0 {
+            let inventory_res = fetch_inventory(
+                state.clone(),
+                network.clone(),
+                logger.clone(),
+                ConsensusMessage::MAX_BATCH_LEN,
+            );
+
+            let _ = handle_result(
+                logger.clone(),
+                inventory_res,
+                "Protocol network fetch_inventory error",
+            );
+
+            let removed = state.lock().unwrap().cleanup_expired()?;
+            logger.log_debug(&format!(
+                "Removed {} expired store items and transactions",
+                removed
+            ))?;
+
+            let rotate = step % ConnectionManager::ROTATION_PERIOD == 0;
+            let dial_res =
+                maintain_connections(state.clone(), network.clone(), logger.clone(), rotate);
+
+            if let Ok(dialed) = handle_result(
+                logger.clone(),
+                dial_res,
+                "Protocol network maintain_connections error",
+            ) {
+                logger.log_debug(&format!("Dialed {} outbound peers", dialed))?;
+            }
+        }
+
+        if step % OUTBOX_PERIOD == 0 {
+            let outbox_res = flush_outbox(state.clone(), network.clone(), logger.clone());
+
+            if let Ok(sent) = handle_result(
+                logger.clone(),
+                outbox_res,
+                "Protocol network flush_outbox error",
+            ) {
+                logger.log_debug(&format!("Sent {} outbox messages", sent))?;
+            }
+        }
+
+        if step % PRUNE_PERIOD == 0 {
+            let prune_res = state.lock().unwrap().prune();
+
+            if let Ok(report) =
+                handle_result(logger.clone(), prune_res, "Protocol state prune error")
+            {
+                logger.log_debug(&format!(
+                    "Pruned {} transactions below distance {}",
+                    report.pruned, report.min_distance
+                ))?;
+            }
+        }
+
+        let drained = drain_inbox(state.clone(), network.clone(), logger.clone())?;
+
+        if drained > 0 {
+            logger.log_debug(&format!("Handled {} inbox messages", drained))?;
+        }
+
+        let fetched = fetch_orphan_ancestors(state.clone(), network.clone(), logger.clone())?;
+
+        if fetched > 0 {
+            logger.log_debug(&format!("Fetched {} missing ancestors of orphans", fetched))?;
+        }
+
+        step += 1;
+
+        // NB: a replica only serves the read paths and keeps its store in sync.
+        if state.lock().unwrap().read_only {
+            thread::sleep(Duration::from_millis(READ_ONLY_STEP_MILLIS));
+            continue;
+        }
+
+        // NB: the mining is paused while the queued transactions exceed the backlog.
+        state.lock().unwrap().schedule_mining();
 
-    while res.is_ok() {
         let state = state.clone();
         let network = network.clone();
         let logger = logger.clone();
@@ -2081,3 +4285,255 @@ pub fn serve_consensus<
 
     res
 }
+
+#[test]
+fn test_key_rotation() {
+    use crate::testkit::TestNetwork;
+    use crypto::ecc::ed25519::SecretKey;
+
+    let network = TestNetwork::new(2).unwrap();
+    let node = network.node(0).unwrap();
+    let peer = network.node(1).unwrap();
+
+    let old_secret_key = SecretKey::random().unwrap();
+    let new_secret_key = SecretKey::random().unwrap();
+
+    peer.state.lock().unwrap().set_secret_key(old_secret_key);
+
+    let mut announced = peer
+        .state
+        .lock()
+        .unwrap()
+        .announced_node()
+        .unwrap()
+        .unwrap();
+    announced.last_seen = announced.last_seen.after(1);
+
+    let res = handle_node(node.state.clone(), &announced);
+    assert!(res.is_ok());
+
+    let node_id = announced.id;
+    let stage = node.state.lock().unwrap().stage;
+    node.state
+        .lock()
+        .unwrap()
+        .record_work(&peer.address(), 10)
+        .unwrap();
+
+    let mut forged = announced.clone();
+    forged.announce(&new_secret_key).unwrap();
+    forged.last_seen = announced.last_seen.after(1);
+
+    let res = handle_node(node.state.clone(), &forged);
+    assert!(res.is_err());
+
+    let res = peer
+        .state
+        .lock()
+        .unwrap()
+        .rotate_secret_key(new_secret_key.clone());
+    assert!(res.is_ok());
+
+    let mut rotated = peer
+        .state
+        .lock()
+        .unwrap()
+        .announced_node()
+        .unwrap()
+        .unwrap();
+    rotated.last_seen = forged.last_seen;
+
+    let res = handle_node(node.state.clone(), &rotated);
+    assert!(res.is_ok());
+
+    let state = node.state.lock().unwrap();
+    let known = Node::get(&*state.store.read().unwrap(), stage, &node_id).unwrap();
+    assert_eq!(
+        known.announcement.unwrap().public_key,
+        new_secret_key.to_public()
+    );
+    assert_eq!(known.work, 1 << 10);
+    assert!(state.state.lookup_known_node(&node_id));
+}
+
+#[test]
+fn test_reply_tallies() {
+    use crate::testkit::TestNetwork;
+
+    let network = TestNetwork::new(1).unwrap();
+    let node = network.node(0).unwrap();
+    let stage = node.state.lock().unwrap().stage;
+
+    let mut cs = ConflictSet::new(Address::random().unwrap(), stage);
+    let mut txs = Vec::new();
+
+    for _ in 0..3 {
+        let tx = network.transaction(&[]).unwrap();
+
+        node.add_transaction(&tx).unwrap();
+        cs.add_transaction(tx.id);
+        txs.push(tx);
+    }
+
+    {
+        let mut state = node.state.lock().unwrap();
+
+        ConflictSet::create(&mut *state.pool.write().unwrap(), stage, &cs).unwrap();
+
+        for tx in txs.iter() {
+            state
+                .state
+                .set_transaction_conflict_set(tx.id, cs.address)
+                .unwrap();
+        }
+
+        let hints = state.reply_hints(&txs[0].id);
+        assert_eq!(hints.confidence, Some(0));
+        assert_eq!(hints.preferred, cs.preferred);
+
+        for preferred in &[txs[2].id, txs[2].id, txs[0].id] {
+            let hints = ReplyHints {
+                confidence: Some(1),
+                preferred: Some(*preferred),
+            };
+
+            state.reply_tallies.record(&txs[0].id, &hints);
+        }
+
+        state.reply_tallies.record(&txs[1].id, &hints);
+    }
+
+    let res = check_reply_tallies(
+        node.state.clone(),
+        node.network.clone(),
+        node.logger.clone(),
+    );
+    assert_eq!(res.unwrap(), 1);
+
+    let state = node.state.lock().unwrap();
+    assert!(state.reply_tallies.is_empty());
+    assert_eq!(state.queue.ids()[0], txs[2].id);
+    assert_eq!(state.state.get_transaction_chit(&txs[2].id), Some(false));
+}
+
+#[test]
+fn test_inbox() {
+    use crate::testkit::TestNetwork;
+    use models::inbox::Inbox;
+
+    let network = TestNetwork::new(2).unwrap();
+    let node = network.node(0).unwrap();
+    let peer = network.node(1).unwrap();
+    let stage = node.state.lock().unwrap().stage;
+
+    {
+        let mut state = node.state.lock().unwrap();
+        let mut config = state.config.clone();
+        config.mining_max_backlog = Some(1);
+        config.max_spooled = Some(1);
+        state.set_config(&config).unwrap();
+    }
+
+    let mut txs = Vec::new();
+
+    for _ in 0..3 {
+        let tx = network.transaction(&[]).unwrap();
+        txs.push(tx);
+    }
+
+    for tx in txs[..2].iter() {
+        node.add_transaction(tx).unwrap();
+    }
+
+    assert!(node.state.lock().unwrap().is_behind());
+
+    let target = Node::new(stage, &node.address());
+    let query = ConsensusMessage::new_query(&peer.address(), &target, &txs[2]).unwrap();
+
+    let res = handle(
+        node.state.clone(),
+        node.network.clone(),
+        node.logger.clone(),
+        &query,
+    );
+    assert!(res.is_ok());
+
+    let state = node.state.lock().unwrap();
+    let count = Inbox::count(&*state.store.read().unwrap(), stage, None, None, None);
+    assert_eq!(count.unwrap(), 1);
+
+    let res = state.spill_inbox(&query);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = state.drain_inbox(10);
+    assert!(res.is_ok());
+
+    let inboxes = res.unwrap();
+    assert_eq!(inboxes.len(), 1);
+    assert_eq!(inboxes[0].message, query);
+
+    let count = Inbox::count(&*state.store.read().unwrap(), stage, None, None, None);
+    assert_eq!(count.unwrap(), 0);
+}
+
+#[test]
+fn test_compact_queries() {
+    use crate::testkit::TestNetwork;
+    use models::node::Service;
+
+    let network = TestNetwork::new(2).unwrap();
+    let node = network.node(0).unwrap();
+    let peer_address = network.node(1).unwrap().address();
+    let stage = node.state.lock().unwrap().stage;
+
+    let tx = network.transaction(&[]).unwrap();
+
+    let mut services = BTreeSet::new();
+    services.insert(Service::CompactQuery);
+
+    {
+        let mut state = node.state.lock().unwrap();
+        assert!(!state.is_compact_peer(&peer_address).unwrap());
+
+        state.set_services(&services);
+        assert!(!state.is_compact_peer(&peer_address).unwrap());
+
+        let node_id = Node::new(stage, &peer_address).id;
+        let mut peer = Node::get(&*state.store.read().unwrap(), stage, &node_id).unwrap();
+        peer.set_services(&services);
+        Node::update(&mut *state.store.write().unwrap(), stage, &peer).unwrap();
+
+        assert!(state.is_compact_peer(&peer_address).unwrap());
+        assert!(!state.is_known_transaction(&tx.id).unwrap());
+    }
+
+    node.add_transaction(&tx).unwrap();
+    assert!(node
+        .state
+        .lock()
+        .unwrap()
+        .is_known_transaction(&tx.id)
+        .unwrap());
+
+    let target = Node::new(stage, &peer_address);
+
+    let query = ConsensusMessage::new_compact_query(&node.address(), &target, &tx).unwrap();
+    let msg = encode_message(node.state.clone(), &query).unwrap();
+    assert!(msg.is_compact());
+    assert_eq!(msg.to_consensus_message().unwrap(), query);
+
+    let reply =
+        ConsensusMessage::new_reply(&node.address(), query.id(), &target, tx.id, true).unwrap();
+    let msg = encode_message(node.state.clone(), &reply).unwrap();
+    assert!(msg.is_compact());
+
+    let full_query = ConsensusMessage::new_query(&node.address(), &target, &tx).unwrap();
+    let msg = encode_message(node.state.clone(), &full_query).unwrap();
+    assert!(!msg.is_compact());
+
+    node.state.lock().unwrap().set_services(&BTreeSet::new());
+
+    let msg = encode_message(node.state.clone(), &reply).unwrap();
+    assert!(!msg.is_compact());
+}
@@ -0,0 +1,318 @@
+//! # Notifier
+//!
+//! `notifier` contains the notifier posting the `ProtocolEvent`s to the HTTP
+//! callback URLs of the `NotifierConfig`. The payloads are signed with the node
+//! key when required, so that the receivers can authenticate them.
+
+use crate::events::{EventKind, ProtocolEvent};
+use crate::result::Result;
+use crate::watch::post_webhook;
+use config::notifier::NotifierConfig;
+use config::retry::RetryPolicy;
+use crypto::ecc::ed25519::SecretKey;
+use log::logger::Logger;
+use models::stage::Stage;
+use models::timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::BTreeSet;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// `SIGNATURE_HEADER` is the header of the signature of a notification payload.
+pub const SIGNATURE_HEADER: &str = "X-Alsacoin-Signature";
+
+/// `PUBLIC_KEY_HEADER` is the header of the public key verifying the signature
+/// of a notification payload.
+pub const PUBLIC_KEY_HEADER: &str = "X-Alsacoin-Public-Key";
+
+/// `NotifierPayload` is the JSON payload posted by the `Notifier`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct NotifierPayload {
+    pub stage: Stage,
+    pub time: Timestamp,
+    pub event: ProtocolEvent,
+}
+
+impl NotifierPayload {
+    /// `new` creates a new `NotifierPayload` of a `ProtocolEvent`.
+    pub fn new(stage: Stage, event: &ProtocolEvent) -> NotifierPayload {
+        NotifierPayload {
+            stage,
+            time: Timestamp::now(),
+            event: event.to_owned(),
+        }
+    }
+
+    /// `to_json` converts the `NotifierPayload` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+
+    /// `from_json` converts a JSON string into an `NotifierPayload`.
+    pub fn from_json(s: &str) -> Result<NotifierPayload> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+/// `Notifier` posts the `ProtocolEvent`s of some kinds to a set of URLs,
+/// retrying the failed posts.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    pub stage: Stage,
+    pub urls: Vec<String>,
+    pub kinds: BTreeSet<EventKind>,
+    pub max_attempts: u32,
+    pub retry_policy: RetryPolicy,
+    pub secret_key: Option<SecretKey>,
+}
+
+impl Notifier {
+    /// `new` creates a new `Notifier` from a `NotifierConfig`. The `SecretKey`
+    /// is used only if the configuration requires signed payloads.
+    pub fn new(
+        stage: Stage,
+        config: &NotifierConfig,
+        secret_key: Option<SecretKey>,
+    ) -> Result<Notifier> {
+        config.validate()?;
+
+        let urls = config.urls.clone().unwrap_or_default();
+
+        let mut kinds = BTreeSet::new();
+
+        for event in config.events.clone().unwrap_or_default() {
+            kinds.insert(EventKind::parse(&event)?);
+        }
+
+        let max_attempts = config
+            .max_attempts
+            .unwrap_or(NotifierConfig::DEFAULT_MAX_ATTEMPTS);
+
+        let retry_policy = config.retry_policy.clone().unwrap_or_default();
+
+        let secret_key = if config.sign.unwrap_or(NotifierConfig::DEFAULT_SIGN) {
+            secret_key
+        } else {
+            None
+        };
+
+        let notifier = Notifier {
+            stage,
+            urls,
+            kinds,
+            max_attempts,
+            retry_policy,
+            secret_key,
+        };
+
+        Ok(notifier)
+    }
+
+    /// `notifies` returns if the `Notifier` posts a `ProtocolEvent`.
+    pub fn notifies(&self, event: &ProtocolEvent) -> bool {
+        self.kinds.contains(&event.kind())
+    }
+
+    /// `headers` returns the signature headers of a payload, if the `Notifier`
+    /// signs them.
+    pub fn headers(&self, body: &str) -> Vec<(&'static str, String)> {
+        if let Some(ref secret_key) = self.secret_key {
+            let signature = secret_key.sign(body.as_bytes());
+
+            vec![
+                (SIGNATURE_HEADER, signature.to_string()),
+                (PUBLIC_KEY_HEADER, secret_key.to_public().to_string()),
+            ]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// `post` posts a payload to a URL, retrying up to the maximum number of
+    /// attempts with the delays of the `RetryPolicy`.
+    pub fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<()> {
+        let mut attempt = 0;
+
+        loop {
+            let res = post_webhook(url, headers, body);
+
+            attempt += 1;
+
+            if res.is_ok() || attempt >= self.max_attempts {
+                return res;
+            }
+
+            let delay = self.retry_policy.delay(attempt - 1);
+            thread::sleep(Duration::from_millis(delay));
+        }
+    }
+
+    /// `notify` posts a `ProtocolEvent` to all the URLs, returning the number
+    /// of successful posts, or the last error if none succeeded.
+    pub fn notify(&self, event: &ProtocolEvent) -> Result<u32> {
+        let payload = NotifierPayload::new(self.stage, event);
+        let body = payload.to_json()?;
+        let headers = self.headers(&body);
+
+        let mut count = 0;
+        let mut res = Ok(());
+
+        for url in self.urls.iter() {
+            match self.post(url, &headers, &body) {
+                Ok(()) => count += 1,
+                Err(err) => res = Err(err),
+            }
+        }
+
+        if count == 0 {
+            res?;
+        }
+
+        Ok(count)
+    }
+}
+
+/// `notify_events` spawns a thread posting the notified `ProtocolEvent`s of a
+/// receiver with a `Notifier`.
+pub fn notify_events(
+    notifier: Notifier,
+    logger: Arc<Logger>,
+    receiver: Receiver<ProtocolEvent>,
+) -> JoinHandle<()> {
+    let logger = logger.with_module("protocol::notifier");
+
+    thread::spawn(move || {
+        for event in receiver.iter() {
+            if !notifier.notifies(&event) {
+                continue;
+            }
+
+            // NB: a failed log write cannot be reported anywhere else.
+            match notifier.notify(&event) {
+                Ok(count) => {
+                    let _ = logger.log_debug(&format!(
+                        "Notified {:?} event to {} urls",
+                        event.kind(),
+                        count
+                    ));
+                }
+                Err(err) => {
+                    let _ = logger.log_critical(&format!("Notification failed: {}", err));
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn test_notifier() {
+    use crypto::ecc::ed25519::{PublicKey, Signature};
+    use crypto::hash::Digest;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // NB: the first post fails, so that the second attempt is the delivered one.
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+
+        for status in ["500 Internal Server Error", "200 OK"].iter() {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut headers = Vec::new();
+            let mut len = 0;
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+
+                if line.is_empty() {
+                    break;
+                }
+
+                if line.starts_with("Content-Length: ") {
+                    len = line["Content-Length: ".len()..].parse().unwrap();
+                }
+
+                headers.push(line);
+            }
+
+            let mut body = vec![0u8; len];
+            reader.read_exact(&mut body).unwrap();
+
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+            stream.write_all(response.as_bytes()).unwrap();
+
+            requests.push((headers, String::from_utf8(body).unwrap()));
+        }
+
+        requests
+    });
+
+    let stage = Stage::random().unwrap();
+    let secret_key = SecretKey::random().unwrap();
+
+    let mut config = NotifierConfig::default();
+    config.urls = Some(vec![format!("http://127.0.0.1:{}/events", port)]);
+    config.events = Some(vec!["accepted".into()]);
+    config.retry_policy =
+        Some(RetryPolicy::new(Some(1), Some(10), None, None, None, None).unwrap());
+
+    let notifier = Notifier::new(stage, &config, Some(secret_key.clone())).unwrap();
+
+    let accepted = ProtocolEvent::TransactionAccepted {
+        tx_id: Digest::random().unwrap(),
+        addresses: BTreeSet::new(),
+    };
+
+    let error = ProtocolEvent::ConsensusError {
+        msg: "error".into(),
+    };
+
+    assert!(notifier.notifies(&accepted));
+    assert!(!notifier.notifies(&error));
+
+    let res = notifier.notify(&accepted);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+
+    let (ref headers, ref body) = requests[1];
+
+    let payload = NotifierPayload::from_json(body).unwrap();
+    assert_eq!(payload.stage, stage);
+    assert_eq!(payload.event, accepted);
+
+    let header_value = |name: &str| {
+        let prefix = format!("{}: ", name);
+
+        headers
+            .iter()
+            .find(|header| header.starts_with(&prefix))
+            .map(|header| header[prefix.len()..].to_string())
+            .unwrap()
+    };
+
+    let signature = Signature::from_str(&header_value(SIGNATURE_HEADER)).unwrap();
+    let public_key = PublicKey::from_str(&header_value(PUBLIC_KEY_HEADER)).unwrap();
+    assert_eq!(public_key, secret_key.to_public());
+
+    let res = public_key.verify(&signature, body.as_bytes());
+    assert!(res.is_ok());
+
+    config.sign = Some(false);
+    let notifier = Notifier::new(stage, &config, Some(secret_key)).unwrap();
+    assert!(notifier.headers(body).is_empty());
+
+    config.events = Some(vec!["watch".into()]);
+    let res = Notifier::new(stage, &config, None);
+    assert!(res.is_err());
+}
@@ -0,0 +1,348 @@
+//! # Orphan
+//!
+//! `orphan` contains the pool of the transactions received before their ancestors.
+//! The orphans are kept out of the consensus until all their missing ancestors
+//! arrive, then they are promoted and handled as new transactions. The missing
+//! ancestors are queued to be fetched in batches by the consensus loop.
+
+use config::consensus::ConsensusConfig;
+use crypto::hash::Digest;
+use models::timestamp::Timestamp;
+use models::transaction::Transaction;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// `Orphan` is a `Transaction` waiting for its missing ancestors.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Orphan {
+    transaction: Transaction,
+    missing: BTreeSet<Digest>,
+}
+
+/// `OrphanPool` keeps the `Transaction`s with missing ancestors, evicting the
+/// oldest ones when full. The queue of the ancestors to fetch only holds awaited
+/// ancestors, so it is bounded by the size of the pool.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct OrphanPool {
+    max_size: usize,
+    orphans: BTreeMap<Digest, Orphan>,
+    waiting: BTreeMap<Digest, BTreeSet<Digest>>,
+    order: VecDeque<Digest>,
+    fetches: VecDeque<Digest>,
+}
+
+impl OrphanPool {
+    /// `new` creates a new empty `OrphanPool` from a `ConsensusConfig`.
+    pub fn new(config: &ConsensusConfig) -> OrphanPool {
+        let mut pool = OrphanPool::default();
+        pool.set_config(config);
+        pool
+    }
+
+    /// `set_config` sets the `OrphanPool` maximum size from a `ConsensusConfig`,
+    /// evicting the exceeding orphans.
+    pub fn set_config(&mut self, config: &ConsensusConfig) {
+        self.max_size = config
+            .max_orphans
+            .unwrap_or(ConsensusConfig::DEFAULT_MAX_ORPHANS) as usize;
+
+        while self.orphans.len() > self.max_size {
+            self.evict();
+        }
+    }
+
+    /// `len` returns the number of orphans in the `OrphanPool`.
+    pub fn len(&self) -> usize {
+        self.orphans.len()
+    }
+
+    /// `is_empty` returns if the `OrphanPool` is empty.
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty()
+    }
+
+    /// `lookup` returns if a `Transaction` is in the `OrphanPool`.
+    pub fn lookup(&self, tx_id: &Digest) -> bool {
+        self.orphans.contains_key(tx_id)
+    }
+
+    /// `is_awaited` returns if a `Transaction` is an ancestor awaited by the orphans.
+    pub fn is_awaited(&self, tx_id: &Digest) -> bool {
+        self.waiting.contains_key(tx_id)
+    }
+
+    /// `missing` returns the ids of the ancestors awaited by the orphans.
+    pub fn missing(&self) -> BTreeSet<Digest> {
+        self.waiting.keys().copied().collect()
+    }
+
+    /// `insert` inserts a `Transaction` with its missing ancestors in the
+    /// `OrphanPool`, evicting the oldest orphan if full. It returns the ids of
+    /// the missing ancestors that were not already awaited, which are queued
+    /// to be fetched.
+    pub fn insert(
+        &mut self,
+        transaction: &Transaction,
+        missing: &BTreeSet<Digest>,
+    ) -> BTreeSet<Digest> {
+        if missing.is_empty() || self.lookup(&transaction.id) {
+            return BTreeSet::new();
+        }
+
+        if self.orphans.len() >= self.max_size {
+            self.evict();
+        }
+
+        let mut to_fetch = BTreeSet::new();
+
+        for ancestor_id in missing {
+            let orphans = self.waiting.entry(*ancestor_id).or_insert_with(|| {
+                to_fetch.insert(*ancestor_id);
+                BTreeSet::new()
+            });
+
+            orphans.insert(transaction.id);
+        }
+
+        let orphan = Orphan {
+            transaction: transaction.to_owned(),
+            missing: missing.to_owned(),
+        };
+
+        self.orphans.insert(transaction.id, orphan);
+        self.order.push_back(transaction.id);
+        self.fetches.extend(to_fetch.iter().copied());
+
+        to_fetch
+    }
+
+    /// `next_fetches` pops from the queue of the ancestors to fetch at most
+    /// a number of ids of ancestors that are still awaited.
+    pub fn next_fetches(&mut self, count: usize) -> BTreeSet<Digest> {
+        let mut ids = BTreeSet::new();
+
+        while ids.len() < count {
+            match self.fetches.pop_front() {
+                Some(ancestor_id) => {
+                    if self.waiting.contains_key(&ancestor_id) {
+                        ids.insert(ancestor_id);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        ids
+    }
+
+    /// `abandon` gives up on an ancestor that could not be fetched, removing
+    /// the orphans waiting for it. It returns the number of removed orphans.
+    pub fn abandon(&mut self, ancestor_id: &Digest) -> u32 {
+        let orphan_ids = match self.waiting.remove(ancestor_id) {
+            Some(orphan_ids) => orphan_ids,
+            None => return 0,
+        };
+
+        self.fetches.retain(|id| id != ancestor_id);
+
+        let mut removed = 0;
+
+        for tx_id in orphan_ids {
+            if self.remove(&tx_id).is_some() {
+                removed += 1;
+            }
+        }
+
+        removed
+    }
+
+    /// `remove` removes a `Transaction` from the `OrphanPool`, returning it if found.
+    pub fn remove(&mut self, tx_id: &Digest) -> Option<Transaction> {
+        let orphan = self.orphans.remove(tx_id)?;
+
+        for ancestor_id in orphan.missing.iter() {
+            if let Some(orphans) = self.waiting.get_mut(ancestor_id) {
+                orphans.remove(tx_id);
+
+                if orphans.is_empty() {
+                    self.waiting.remove(ancestor_id);
+                    self.fetches.retain(|id| id != ancestor_id);
+                }
+            }
+        }
+
+        self.order.retain(|id| id != tx_id);
+
+        Some(orphan.transaction)
+    }
+
+    /// `evict` removes the oldest orphan from the `OrphanPool`.
+    fn evict(&mut self) {
+        if let Some(tx_id) = self.order.front().copied() {
+            self.remove(&tx_id);
+        }
+    }
+
+    /// `resolve` marks an ancestor as arrived, removing and returning the
+    /// orphans that have no more missing ancestors.
+    pub fn resolve(&mut self, ancestor_id: &Digest) -> Vec<Transaction> {
+        let orphan_ids = match self.waiting.remove(ancestor_id) {
+            Some(orphan_ids) => orphan_ids,
+            None => return Vec::new(),
+        };
+
+        self.fetches.retain(|id| id != ancestor_id);
+
+        let mut promoted = Vec::new();
+
+        for tx_id in orphan_ids {
+            let complete = match self.orphans.get_mut(&tx_id) {
+                Some(orphan) => {
+                    orphan.missing.remove(ancestor_id);
+                    orphan.missing.is_empty()
+                }
+                None => false,
+            };
+
+            if complete {
+                if let Some(transaction) = self.remove(&tx_id) {
+                    promoted.push(transaction);
+                }
+            }
+        }
+
+        promoted
+    }
+
+    /// `cleanup_expired` removes the expired orphans, returning their number.
+    pub fn cleanup_expired(&mut self, now: Timestamp) -> u32 {
+        let expired: Vec<Digest> = self
+            .orphans
+            .values()
+            .filter(|orphan| orphan.transaction.is_expired(now))
+            .map(|orphan| orphan.transaction.id)
+            .collect();
+
+        for tx_id in expired.iter() {
+            self.remove(tx_id);
+        }
+
+        expired.len() as u32
+    }
+
+    /// `clear` clears the `OrphanPool`.
+    pub fn clear(&mut self) {
+        self.orphans.clear();
+        self.waiting.clear();
+        self.order.clear();
+        self.fetches.clear();
+    }
+}
+
+#[test]
+fn test_orphan_pool() {
+    let mut config = ConsensusConfig::default();
+    config.max_orphans = Some(2);
+
+    let mut pool = OrphanPool::new(&config);
+    assert!(pool.is_empty());
+
+    let ancestor_a = Digest::random().unwrap();
+    let ancestor_b = Digest::random().unwrap();
+
+    let mut orphan_a = Transaction::new().unwrap();
+    orphan_a.id = Digest::random().unwrap();
+
+    let mut orphan_b = Transaction::new().unwrap();
+    orphan_b.id = Digest::random().unwrap();
+
+    let mut missing = BTreeSet::new();
+    missing.insert(ancestor_a);
+    missing.insert(ancestor_b);
+
+    let to_fetch = pool.insert(&orphan_a, &missing);
+    assert_eq!(to_fetch, missing);
+    assert!(pool.lookup(&orphan_a.id));
+
+    let to_fetch = pool.insert(&orphan_a, &missing);
+    assert!(to_fetch.is_empty());
+
+    let mut missing_b = BTreeSet::new();
+    missing_b.insert(ancestor_a);
+
+    let to_fetch = pool.insert(&orphan_b, &missing_b);
+    assert!(to_fetch.is_empty());
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.missing(), missing);
+
+    let promoted = pool.resolve(&ancestor_a);
+    assert_eq!(promoted, vec![orphan_b.clone()]);
+    assert!(pool.lookup(&orphan_a.id));
+
+    let promoted = pool.resolve(&ancestor_b);
+    assert_eq!(promoted, vec![orphan_a.clone()]);
+    assert!(pool.is_empty());
+    assert!(pool.missing().is_empty());
+
+    let mut orphan_c = Transaction::new().unwrap();
+    orphan_c.id = Digest::random().unwrap();
+
+    pool.insert(&orphan_a, &missing);
+    pool.insert(&orphan_b, &missing_b);
+    pool.insert(&orphan_c, &missing_b);
+
+    assert_eq!(pool.len(), 2);
+    assert!(!pool.lookup(&orphan_a.id));
+    assert_eq!(pool.missing(), missing_b);
+
+    config.max_orphans = Some(1);
+    pool.set_config(&config);
+    assert_eq!(pool.len(), 1);
+    assert!(pool.lookup(&orphan_c.id));
+
+    assert_eq!(pool.remove(&orphan_c.id), Some(orphan_c));
+    assert!(pool.is_empty());
+    assert!(pool.missing().is_empty());
+    assert!(pool.next_fetches(2).is_empty());
+}
+
+#[test]
+fn test_orphan_pool_fetches() {
+    let config = ConsensusConfig::default();
+    let mut pool = OrphanPool::new(&config);
+
+    let ancestor_a = Digest::random().unwrap();
+    let ancestor_b = Digest::random().unwrap();
+
+    let mut orphan_a = Transaction::new().unwrap();
+    orphan_a.id = Digest::random().unwrap();
+
+    let mut orphan_b = Transaction::new().unwrap();
+    orphan_b.id = Digest::random().unwrap();
+
+    let mut missing_a = BTreeSet::new();
+    missing_a.insert(ancestor_a);
+
+    let mut missing_b = BTreeSet::new();
+    missing_b.insert(ancestor_a);
+    missing_b.insert(ancestor_b);
+
+    pool.insert(&orphan_a, &missing_a);
+    pool.insert(&orphan_b, &missing_b);
+
+    let fetches = pool.next_fetches(1);
+    assert_eq!(fetches, missing_a);
+
+    let fetches = pool.next_fetches(2);
+    assert_eq!(fetches.len(), 1);
+    assert!(fetches.contains(&ancestor_b));
+    assert!(pool.next_fetches(2).is_empty());
+
+    assert_eq!(pool.abandon(&ancestor_a), 2);
+    assert!(pool.is_empty());
+    assert!(pool.missing().is_empty());
+    assert_eq!(pool.abandon(&ancestor_a), 0);
+
+    pool.insert(&orphan_a, &missing_a);
+    pool.resolve(&ancestor_a);
+    assert!(pool.next_fetches(2).is_empty());
+}
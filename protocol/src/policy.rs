@@ -0,0 +1,296 @@
+//! # Policy
+//!
+//! `policy` contains the mempool acceptance policy types and functions.
+//!
+//! A `MempoolPolicy` decides which valid `Transaction`s a node keeps in its pool
+//! and relays. The policies are local to the node and do not change the
+//! consensus rules: a `Transaction` already accepted in the store is never
+//! checked against them.
+
+use crate::error::Error;
+use crate::result::Result;
+use config::pool::PoolConfig;
+use models::transaction::Transaction;
+use std::fmt;
+use std::sync::Arc;
+
+/// `MempoolPolicy` is the trait implemented by the mempool acceptance policies.
+pub trait MempoolPolicy: Send + Sync {
+    /// `name` returns the name of the `MempoolPolicy`, reported in the rejections.
+    fn name(&self) -> String;
+
+    /// `check` returns an error if a `Transaction` should not enter the pool.
+    fn check(&self, transaction: &Transaction) -> Result<()>;
+}
+
+/// `PoolLimits` is the `MempoolPolicy` enforcing the limits of a `PoolConfig`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct PoolLimits {
+    pub min_fee: u64,
+    pub max_tx_size: u32,
+    pub max_inputs: u32,
+    pub max_outputs: u32,
+}
+
+impl PoolLimits {
+    /// `from_config` creates the `PoolLimits` of a `PoolConfig`.
+    pub fn from_config(config: &PoolConfig) -> Result<PoolLimits> {
+        config.validate()?;
+
+        let mut config = config.clone();
+        config.populate();
+
+        let limits = PoolLimits {
+            min_fee: config.min_fee.unwrap(),
+            max_tx_size: config.max_tx_size.unwrap(),
+            max_inputs: config.max_inputs.unwrap(),
+            max_outputs: config.max_outputs.unwrap(),
+        };
+
+        Ok(limits)
+    }
+
+    /// `fee` returns the fee of a `Transaction`, as ordered by the fee
+    /// priority criterion.
+    pub fn fee(transaction: &Transaction) -> Result<u64> {
+        let balance = transaction.balance()?;
+        Ok(balance.max(0) as u64)
+    }
+}
+
+impl Default for PoolLimits {
+    fn default() -> PoolLimits {
+        PoolLimits {
+            min_fee: PoolConfig::DEFAULT_MIN_FEE,
+            max_tx_size: PoolConfig::DEFAULT_MAX_TX_SIZE,
+            max_inputs: PoolConfig::DEFAULT_MAX_INPUTS,
+            max_outputs: PoolConfig::DEFAULT_MAX_OUTPUTS,
+        }
+    }
+}
+
+impl MempoolPolicy for PoolLimits {
+    fn name(&self) -> String {
+        "pool-limits".into()
+    }
+
+    fn check(&self, transaction: &Transaction) -> Result<()> {
+        if transaction.inputs.len() > self.max_inputs as usize {
+            let msg = format!("too many inputs: {}", transaction.inputs.len());
+            let err = Error::PolicyRejected { msg };
+            return Err(err);
+        }
+
+        if transaction.outputs.len() > self.max_outputs as usize {
+            let msg = format!("too many outputs: {}", transaction.outputs.len());
+            let err = Error::PolicyRejected { msg };
+            return Err(err);
+        }
+
+        let size = transaction.to_bytes()?.len();
+
+        if size > self.max_tx_size as usize {
+            let msg = format!("too large: {} bytes", size);
+            let err = Error::PolicyRejected { msg };
+            return Err(err);
+        }
+
+        let fee = PoolLimits::fee(transaction)?;
+
+        if fee < self.min_fee {
+            let msg = format!("fee too low: {}", fee);
+            let err = Error::PolicyRejected { msg };
+            return Err(err);
+        }
+
+        Ok(())
+    }
+}
+
+/// `FilterPolicy` is a `MempoolPolicy` rejecting the `Transaction`s that do
+/// not pass a custom filter.
+pub struct FilterPolicy<F>
+where
+    F: Fn(&Transaction) -> bool + Send + Sync,
+{
+    name: String,
+    filter: F,
+}
+
+impl<F> FilterPolicy<F>
+where
+    F: Fn(&Transaction) -> bool + Send + Sync,
+{
+    /// `new` creates a new `FilterPolicy` accepting the `Transaction`s for
+    /// which the filter returns true.
+    pub fn new(name: &str, filter: F) -> FilterPolicy<F> {
+        FilterPolicy {
+            name: name.into(),
+            filter,
+        }
+    }
+}
+
+impl<F> MempoolPolicy for FilterPolicy<F>
+where
+    F: Fn(&Transaction) -> bool + Send + Sync,
+{
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn check(&self, transaction: &Transaction) -> Result<()> {
+        if (self.filter)(transaction) {
+            Ok(())
+        } else {
+            let msg = format!("filtered by {}", self.name);
+            let err = Error::PolicyRejected { msg };
+            Err(err)
+        }
+    }
+}
+
+/// `MempoolPolicies` is the list of the `MempoolPolicy`s a `Transaction` has
+/// to pass to enter the pool, checked in order.
+#[derive(Clone)]
+pub struct MempoolPolicies {
+    policies: Vec<Arc<dyn MempoolPolicy>>,
+}
+
+impl MempoolPolicies {
+    /// `new` creates a new `MempoolPolicies` enforcing the limits of a `PoolConfig`.
+    pub fn new(config: &PoolConfig) -> Result<MempoolPolicies> {
+        let limits = PoolLimits::from_config(config)?;
+
+        let policies = MempoolPolicies {
+            policies: vec![Arc::new(limits)],
+        };
+
+        Ok(policies)
+    }
+
+    /// `len` returns the number of policies in the `MempoolPolicies`.
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    /// `is_empty` returns if the `MempoolPolicies` is empty.
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// `names` returns the names of the policies in the `MempoolPolicies`.
+    pub fn names(&self) -> Vec<String> {
+        self.policies.iter().map(|policy| policy.name()).collect()
+    }
+
+    /// `add` appends a `MempoolPolicy` to the `MempoolPolicies`.
+    pub fn add<T: MempoolPolicy + 'static>(&mut self, policy: T) {
+        self.policies.push(Arc::new(policy));
+    }
+
+    /// `remove` removes the policies with a given name from the `MempoolPolicies`.
+    pub fn remove(&mut self, name: &str) {
+        self.policies.retain(|policy| policy.name() != name);
+    }
+
+    /// `clear` removes all the policies from the `MempoolPolicies`.
+    pub fn clear(&mut self) {
+        self.policies.clear();
+    }
+
+    /// `check` checks a `Transaction` against all the policies.
+    pub fn check(&self, transaction: &Transaction) -> Result<()> {
+        for policy in self.policies.iter() {
+            policy.check(transaction)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MempoolPolicies {
+    fn default() -> MempoolPolicies {
+        MempoolPolicies {
+            policies: vec![Arc::new(PoolLimits::default())],
+        }
+    }
+}
+
+impl fmt::Debug for MempoolPolicies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[test]
+fn test_pool_limits() {
+    use models::address::Address;
+    use models::output::Output;
+
+    let mut transaction = Transaction::new().unwrap();
+
+    for _ in 0..4 {
+        let output = Output::new(&Address::random().unwrap(), 10, &[0; 10]);
+        transaction.add_output(&output).unwrap();
+    }
+
+    let limits = PoolLimits::default();
+    let res = limits.check(&transaction);
+    assert!(res.is_ok());
+
+    let mut limits = PoolLimits::default();
+    limits.max_outputs = 3;
+    let res = limits.check(&transaction);
+    assert!(res.is_err());
+
+    let mut limits = PoolLimits::default();
+    limits.max_tx_size = 64;
+    let res = limits.check(&transaction);
+    assert!(res.is_err());
+
+    let mut limits = PoolLimits::default();
+    limits.min_fee = 1;
+    let res = limits.check(&Transaction::new().unwrap());
+    assert!(res.is_err());
+
+    let config = PoolConfig::new(None, None, None, None, Some(10), None, Some(8), None).unwrap();
+    let limits = PoolLimits::from_config(&config).unwrap();
+    assert_eq!(limits.min_fee, 10);
+    assert_eq!(limits.max_inputs, 8);
+    assert_eq!(limits.max_outputs, PoolConfig::DEFAULT_MAX_OUTPUTS);
+}
+
+#[test]
+fn test_mempool_policies() {
+    let mut policies = MempoolPolicies::default();
+    assert_eq!(policies.len(), 1);
+
+    let transaction = Transaction::new().unwrap();
+    let res = policies.check(&transaction);
+    assert!(res.is_ok());
+
+    let filter = FilterPolicy::new("no-coinbase", |tx: &Transaction| tx.coinbase.is_none());
+    policies.add(filter);
+    assert_eq!(policies.names(), vec!["pool-limits", "no-coinbase"]);
+
+    let res = policies.check(&transaction);
+    assert!(res.is_ok());
+
+    let mut coinbase_tx = Transaction::new().unwrap();
+    coinbase_tx
+        .set_coinbase(&models::address::Address::random().unwrap(), 1)
+        .unwrap();
+
+    let res = policies.check(&coinbase_tx);
+    assert!(res.is_err());
+
+    policies.remove("no-coinbase");
+    assert_eq!(policies.len(), 1);
+
+    let res = policies.check(&coinbase_tx);
+    assert!(res.is_ok());
+
+    policies.clear();
+    assert!(policies.is_empty());
+}
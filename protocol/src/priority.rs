@@ -0,0 +1,145 @@
+//! # Priority
+//!
+//! `priority` contains the priority queue used to order the queried transactions.
+
+use crate::error::Error;
+use crate::result::Result;
+use config::consensus::ConsensusConfig;
+use crypto::hash::Digest;
+use models::transaction::Transaction;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `PriorityCriterion` is a criterion used to order the queried transactions.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum PriorityCriterion {
+    Depth,
+    Fee,
+    Age,
+}
+
+impl PriorityCriterion {
+    /// `parse` parses a `PriorityCriterion` from a string.
+    pub fn parse(s: &str) -> Result<PriorityCriterion> {
+        match s {
+            "depth" => Ok(PriorityCriterion::Depth),
+            "fee" => Ok(PriorityCriterion::Fee),
+            "age" => Ok(PriorityCriterion::Age),
+            _ => {
+                let err = Error::InvalidKind;
+                Err(err)
+            }
+        }
+    }
+
+    /// `key` returns the key of a `Transaction` under the `PriorityCriterion`.
+    /// Lower keys have higher priority.
+    pub fn key(self, transaction: &Transaction) -> Result<u64> {
+        let key = match self {
+            PriorityCriterion::Depth => transaction.distance,
+            PriorityCriterion::Fee => u64::max_value() - transaction.balance()?.max(0) as u64,
+            PriorityCriterion::Age => (transaction.time.to_i64() as u64) ^ (1 << 63),
+        };
+
+        Ok(key)
+    }
+}
+
+/// `PriorityQueue` orders the known `Transaction`s to query by a policy of
+/// `PriorityCriterion`s, applied in order.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct PriorityQueue {
+    policy: Vec<PriorityCriterion>,
+    queue: BTreeSet<(Vec<u64>, Digest)>,
+    keys: BTreeMap<Digest, Vec<u64>>,
+}
+
+impl PriorityQueue {
+    /// `new` creates a new empty `PriorityQueue` from the policy of a `ConsensusConfig`.
+    pub fn new(config: &ConsensusConfig) -> Result<PriorityQueue> {
+        let mut queue = PriorityQueue::default();
+        queue.set_policy(config)?;
+
+        Ok(queue)
+    }
+
+    /// `set_policy` sets the `PriorityQueue` policy from a `ConsensusConfig`,
+    /// clearing the queue.
+    pub fn set_policy(&mut self, config: &ConsensusConfig) -> Result<()> {
+        let policy = config
+            .priority_policy
+            .clone()
+            .unwrap_or_else(ConsensusConfig::default_priority_policy);
+
+        let mut criteria = Vec::new();
+
+        for criterion in policy.iter() {
+            criteria.push(PriorityCriterion::parse(criterion)?);
+        }
+
+        self.policy = criteria;
+        self.clear();
+
+        Ok(())
+    }
+
+    /// `len` returns the number of queued `Transaction`s.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// `is_empty` returns if the `PriorityQueue` is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// `lookup` returns if a `Transaction` is queued.
+    pub fn lookup(&self, tx_id: &Digest) -> bool {
+        self.keys.contains_key(tx_id)
+    }
+
+    /// `push` queues a `Transaction`.
+    pub fn push(&mut self, transaction: &Transaction) -> Result<()> {
+        if self.lookup(&transaction.id) {
+            return Ok(());
+        }
+
+        let mut key = Vec::new();
+
+        for criterion in self.policy.iter() {
+            key.push(criterion.key(transaction)?);
+        }
+
+        self.queue.insert((key.clone(), transaction.id));
+        self.keys.insert(transaction.id, key);
+
+        Ok(())
+    }
+
+    /// `promote` moves a `Transaction` ahead of the ones not promoted,
+    /// queuing it if missing.
+    pub fn promote(&mut self, tx_id: &Digest) {
+        self.remove(tx_id);
+
+        // NB: an empty key orders before all the keys of the policy
+        self.queue.insert((Vec::new(), *tx_id));
+        self.keys.insert(*tx_id, Vec::new());
+    }
+
+    /// `remove` removes a `Transaction` from the queue.
+    pub fn remove(&mut self, tx_id: &Digest) {
+        if let Some(key) = self.keys.remove(tx_id) {
+            self.queue.remove(&(key, *tx_id));
+        }
+    }
+
+    /// `ids` returns the queued `Transaction` ids, from the highest priority.
+    pub fn ids(&self) -> Vec<Digest> {
+        self.queue.iter().map(|(_, tx_id)| *tx_id).collect()
+    }
+
+    /// `clear` clears the `PriorityQueue`.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.keys.clear();
+    }
+}
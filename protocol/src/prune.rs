@@ -0,0 +1,178 @@
+//! # Prune
+//!
+//! `prune` contains the types and functions used by the nodes that are not
+//! archives to remove the deep `Transaction`s from their persistent store.
+
+use crate::ledger;
+use crate::result::Result;
+use crypto::hash::Digest;
+use models::account::Account;
+use models::stage::Stage;
+use models::traits::Storable;
+use models::transaction::Transaction;
+use models::tx_telemetry::TxTelemetry;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use store::traits::Store;
+
+/// `PruneReport` is the report of a pruning of the `Store`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct PruneReport {
+    pub checked: u32,
+    pub retained: u32,
+    pub pruned: u32,
+    pub min_distance: u64,
+}
+
+/// `retained_transactions` returns the ids of the `Transaction`s that are never
+/// pruned, beside the eve checkpoints: the last `Transaction`s of the `Account`s
+/// and the `Transaction`s with unspent outputs.
+pub fn retained_transactions<S: Store>(store: &S, stage: Stage) -> Result<BTreeSet<Digest>> {
+    let mut ids = ledger::unspent_transactions(store, stage)?;
+
+    let (from, to) = <Account as Storable<S>>::stage_range(stage);
+
+    for (_, value) in store.iter(Some(&from), Some(&to))? {
+        let account = Account::from_bytes(&value)?;
+
+        if let Some(tx_id) = account.transaction_id {
+            ids.insert(tx_id);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// `prune` removes from the `Store` the `Transaction`s farther than a depth
+/// from the deepest `Transaction`, together with their telemetry, keeping the
+/// eve checkpoints and the retained `Transaction`s.
+pub fn prune<S: Store>(store: &mut S, stage: Stage, depth: u64) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    let mut candidates = Vec::new();
+    let mut max_distance = 0;
+
+    let (from, to) = <Transaction as Storable<S>>::stage_range(stage);
+
+    for (_, value) in store.iter(Some(&from), Some(&to))? {
+        let transaction = Transaction::from_bytes(&value)?;
+        report.checked += 1;
+
+        if transaction.distance > max_distance {
+            max_distance = transaction.distance;
+        }
+
+        if !transaction.is_eve()? {
+            candidates.push((transaction.id, transaction.distance));
+        }
+    }
+
+    if max_distance <= depth {
+        report.retained = report.checked;
+        return Ok(report);
+    }
+
+    report.min_distance = max_distance - depth;
+
+    let retained = retained_transactions(store, stage)?;
+
+    let ids: BTreeSet<Digest> = candidates
+        .into_iter()
+        .filter(|(id, distance)| *distance < report.min_distance && !retained.contains(id))
+        .map(|(id, _)| id)
+        .collect();
+
+    // NB: the store backends do not implement the batch removals
+    for id in ids.iter() {
+        Transaction::remove(store, stage, id)?;
+
+        if TxTelemetry::lookup(store, stage, id)? {
+            TxTelemetry::remove(store, stage, id)?;
+        }
+    }
+
+    report.pruned = ids.len() as u32;
+    report.retained = report.checked - report.pruned;
+
+    Ok(report)
+}
+
+#[test]
+fn test_prune() {
+    use crypto::ecc::ed25519::SecretKey;
+    use models::address::Address;
+    use models::input::Input;
+    use models::output::Output;
+    use models::signer::Signer;
+    use models::signers::Signers;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 16;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+    let address = Address::random().unwrap();
+
+    let secret_key = SecretKey::random().unwrap();
+    let signer = Signer {
+        public_key: secret_key.to_public(),
+        weight: 1,
+    };
+    let mut signers = Signers::new().unwrap();
+    signers.add(&signer).unwrap();
+    signers.set_threshold(1).unwrap();
+
+    let mut eve_transaction = Transaction::new_eve(stage, &signers.address).unwrap();
+    eve_transaction.mine().unwrap();
+    eve_transaction.update_id().unwrap();
+    Transaction::create(&mut store, stage, &eve_transaction).unwrap();
+
+    let account = Account::new(stage, &signers, 100, Some(eve_transaction.id)).unwrap();
+
+    let transactions: Vec<Transaction> = (1..=10)
+        .map(|distance| {
+            let mut transaction = Transaction::new().unwrap();
+            transaction.stage = stage;
+            transaction.distance = distance;
+            transaction
+                .add_input(&Input::new(&account, 1, distance).unwrap())
+                .unwrap();
+            transaction
+                .add_output(&Output::new(&address, distance, &[]))
+                .unwrap();
+            transaction.update_id().unwrap();
+
+            transaction
+        })
+        .collect();
+
+    for transaction in transactions.iter() {
+        Transaction::insert(&mut store, stage, transaction).unwrap();
+    }
+
+    ledger::apply_transaction(&mut store, stage, &transactions[1]).unwrap();
+
+    let res = prune(&mut store, stage, 20);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert_eq!(report.checked, 11);
+    assert_eq!(report.pruned, 0);
+
+    let res = prune(&mut store, stage, 5);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert_eq!(report.min_distance, 5);
+    assert_eq!(report.pruned, 3);
+    assert_eq!(report.retained, 8);
+
+    for transaction in transactions.iter() {
+        let found = Transaction::lookup(&store, stage, &transaction.id).unwrap();
+        let expected = transaction.distance >= 5 || transaction.id == transactions[1].id;
+        assert_eq!(found, expected);
+    }
+
+    let res = Transaction::lookup(&store, stage, &eve_transaction.id);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+}
@@ -0,0 +1,98 @@
+//! # Replay
+//!
+//! `replay` contains the window used to drop duplicated or replayed consensus messages.
+
+use config::consensus::ConsensusConfig;
+use crypto::hash::Digest;
+use models::timestamp::Timestamp;
+use std::collections::{BTreeMap, VecDeque};
+
+/// `ReplayWindow` keeps the digests of the recently seen `ConsensusMessage`s,
+/// evicting the oldest ones when full or expired.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ReplayWindow {
+    size: usize,
+    ttl: u64,
+    seen: BTreeMap<Digest, Timestamp>,
+    order: VecDeque<(Timestamp, Digest)>,
+}
+
+impl ReplayWindow {
+    /// `new` creates a new empty `ReplayWindow` from a `ConsensusConfig`.
+    pub fn new(config: &ConsensusConfig) -> ReplayWindow {
+        let mut window = ReplayWindow::default();
+        window.set_config(config);
+        window
+    }
+
+    /// `set_config` sets the `ReplayWindow` size and time-to-live from a
+    /// `ConsensusConfig`, evicting the exceeding digests.
+    pub fn set_config(&mut self, config: &ConsensusConfig) {
+        self.size = config
+            .replay_window_size
+            .unwrap_or(ConsensusConfig::DEFAULT_REPLAY_WINDOW_SIZE) as usize;
+        self.ttl = config
+            .replay_window_ttl
+            .unwrap_or(ConsensusConfig::DEFAULT_REPLAY_WINDOW_TTL);
+
+        self.evict(Timestamp::now());
+    }
+
+    /// `len` returns the number of digests in the `ReplayWindow`.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// `is_empty` returns if the `ReplayWindow` is empty.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// `lookup` returns if a digest is in the `ReplayWindow`.
+    pub fn lookup(&self, digest: &Digest) -> bool {
+        self.seen.contains_key(digest)
+    }
+
+    /// `is_expired` returns if a time is older than the `ReplayWindow` time-to-live.
+    pub fn is_expired(&self, time: Timestamp) -> bool {
+        Timestamp::now().diff(time) > self.ttl as i64
+    }
+
+    /// `evict` removes the expired digests and the oldest digests exceeding
+    /// the `ReplayWindow` size.
+    fn evict(&mut self, now: Timestamp) {
+        while let Some((time, digest)) = self.order.front().copied() {
+            if self.order.len() <= self.size && now.diff(time) <= self.ttl as i64 {
+                break;
+            }
+
+            self.order.pop_front();
+            self.seen.remove(&digest);
+        }
+    }
+
+    /// `insert` inserts a digest in the `ReplayWindow`, returning false if
+    /// it was already seen.
+    pub fn insert(&mut self, digest: &Digest) -> bool {
+        let now = Timestamp::now();
+
+        self.evict(now);
+
+        if self.lookup(digest) {
+            return false;
+        }
+
+        self.seen.insert(*digest, now);
+        self.order.push_back((now, *digest));
+
+        self.evict(now);
+
+        true
+    }
+
+    /// `clear` clears the `ReplayWindow`.
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+}
@@ -0,0 +1,76 @@
+//! # Retry
+//!
+//! `retry` contains the type used to retry the protocol network operations.
+
+use crate::result::Result;
+use config::consensus::ConsensusConfig;
+use config::retry::RetryPolicy;
+use crypto::random::Random;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// `RetryKind` is the kind of a network operation retried under a `RetryPolicy`.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum RetryKind {
+    Fetch,
+    Query,
+}
+
+/// `Retry` tracks the retries of a network operation under the `RetryPolicy`
+/// of a `ConsensusConfig`.
+#[derive(Clone, Debug)]
+pub struct Retry {
+    policy: RetryPolicy,
+    max_retries: u32,
+    retries: u32,
+    deadline: Instant,
+}
+
+impl Retry {
+    /// `new` creates a new `Retry` of a kind of operation, starting its timeout.
+    pub fn new(config: &ConsensusConfig, kind: RetryKind) -> Retry {
+        let mut policy = config.retry_policy.clone().unwrap_or_default();
+        policy.populate();
+
+        let timeout = match kind {
+            RetryKind::Fetch => policy.fetch_timeout.unwrap(),
+            RetryKind::Query => policy.query_timeout.unwrap(),
+        };
+
+        Retry {
+            policy,
+            max_retries: config.max_retries.unwrap_or(1),
+            retries: 0,
+            deadline: Instant::now() + Duration::from_secs(timeout),
+        }
+    }
+
+    /// `reset` resets the retries count of the `Retry`, keeping its timeout.
+    pub fn reset(&mut self) {
+        self.retries = 0;
+    }
+
+    /// `is_expired` returns if the `Retry` operation timed out.
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// `next` waits the backoff delay of the next retry. It returns false,
+    /// without waiting, if the retries are exhausted or the operation timed out.
+    pub fn next(&mut self) -> Result<bool> {
+        self.retries += 1;
+
+        if self.retries >= self.max_retries || self.is_expired() {
+            return Ok(false);
+        }
+
+        let jitter = Random::u64_range(0, self.policy.jitter.unwrap())?;
+        let delay = self.policy.delay(self.retries - 1).saturating_add(jitter);
+        let delay = Duration::from_millis(delay);
+
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        thread::sleep(delay.min(remaining));
+
+        Ok(!self.is_expired())
+    }
+}
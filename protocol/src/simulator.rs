@@ -0,0 +1,491 @@
+//! # Simulator
+//!
+//! `simulator` contains the consensus simulator. A `Simulation` submits a tree of
+//! `Transaction`s to a `TestNetwork` whose queries and replies can be lost, and
+//! measures how many steps the `TestNode`s take to accept them, so that the
+//! consensus parameters can be tuned before a deployment.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::testkit::{TestNetwork, TestNode};
+use crypto::hash::Digest;
+use crypto::random::{Random, SeedGuard};
+use models::account::Account;
+use models::transaction::Transaction;
+use models::transaction_builder::TransactionBuilder;
+use models::wallet::Wallet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `SimulationParams` are the parameters of a `Simulation`.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SimulationParams {
+    pub nodes: usize,
+    pub transactions: usize,
+    pub loss: f64,
+    pub max_steps: usize,
+    pub k: Option<u32>,
+    pub alpha: Option<u32>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl SimulationParams {
+    /// `DEFAULT_MAX_STEPS` is the default maximum number of steps of a `Simulation`.
+    pub const DEFAULT_MAX_STEPS: usize = 1000;
+
+    /// `AMOUNT` is the amount spent by the root `Transaction` of a `Simulation`.
+    pub const AMOUNT: u64 = 1 << 32;
+
+    /// `new` creates a new `SimulationParams`.
+    pub fn new(nodes: usize, transactions: usize, loss: f64) -> SimulationParams {
+        SimulationParams {
+            nodes,
+            transactions,
+            loss,
+            max_steps: Self::DEFAULT_MAX_STEPS,
+            k: None,
+            alpha: None,
+            seed: None,
+        }
+    }
+
+    /// `validate` validates the `SimulationParams`.
+    pub fn validate(&self) -> Result<()> {
+        if self.nodes < 2 || self.transactions == 0 {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if !(0.0..1.0).contains(&self.loss) || self.max_steps == 0 {
+            let err = Error::OutOfBound;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+}
+
+/// `MessageCounts` are the counts of the messages exchanged in a `Simulation`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct MessageCounts {
+    pub queries: u64,
+    pub replies: u64,
+    pub lost: u64,
+}
+
+/// `LatencyStats` is the distribution of the acceptance latencies of a
+/// `Simulation`, in steps.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+impl LatencyStats {
+    /// `from_samples` computes the `LatencyStats` of a set of latencies.
+    pub fn from_samples(samples: &[u64]) -> LatencyStats {
+        if samples.is_empty() {
+            return LatencyStats::default();
+        }
+
+        let mut samples = samples.to_vec();
+        samples.sort_unstable();
+
+        let count = samples.len();
+        let percentile = |p: usize| samples[((count - 1) * p) / 100];
+
+        LatencyStats {
+            count: count as u64,
+            min: samples[0],
+            max: samples[count - 1],
+            mean: samples.iter().sum::<u64>() as f64 / count as f64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+        }
+    }
+}
+
+/// `DagEntry` is a `Transaction` of the DAG resulting from a `Simulation`.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct DagEntry {
+    pub id: Digest,
+    pub parents: BTreeSet<Digest>,
+    pub submitted_at: Option<u64>,
+    pub accepted_by: u64,
+}
+
+/// `SimulationReport` is the outcome of a `Simulation`.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub params: SimulationParams,
+    pub steps: u64,
+    pub accepted: u64,
+    pub expected: u64,
+    pub latency: LatencyStats,
+    pub messages: MessageCounts,
+}
+
+/// `Simulation` is a simulation of the consensus on a `TestNetwork`.
+pub struct Simulation {
+    pub params: SimulationParams,
+    pub network: TestNetwork,
+    pub transactions: Vec<Transaction>,
+    pub messages: MessageCounts,
+    submitted: BTreeMap<Digest, u64>,
+    accepted: BTreeMap<Digest, BTreeMap<usize, u64>>,
+    _seed: Option<SeedGuard>,
+}
+
+impl Simulation {
+    /// `new` creates a new `Simulation`, with its `TestNetwork` and the tree
+    /// of `Transaction`s to submit. If the `SimulationParams` have a seed, the
+    /// RNG of the current thread is seeded with it until the `Simulation` is
+    /// dropped, so that the draws of the `Simulation` can be reproduced.
+    pub fn new(params: &SimulationParams) -> Result<Simulation> {
+        params.validate()?;
+
+        let _seed = params.seed.map(Random::seed);
+
+        let network = TestNetwork::new(params.nodes)?;
+
+        for node in network.nodes.iter() {
+            let mut state = node.state.lock().unwrap();
+            let mut config = state.config.clone();
+
+            if params.k.is_some() {
+                config.k = params.k;
+            }
+
+            if params.alpha.is_some() {
+                config.alpha = params.alpha;
+            }
+
+            state.set_config(&config)?;
+        }
+
+        let transactions = Simulation::transactions(&network, params.transactions)?;
+
+        let simulation = Simulation {
+            params: params.to_owned(),
+            network,
+            transactions,
+            messages: MessageCounts::default(),
+            submitted: BTreeMap::new(),
+            accepted: BTreeMap::new(),
+            _seed,
+        };
+
+        Ok(simulation)
+    }
+
+    /// `transactions` creates a tree of unsigned `Transaction`s, where every
+    /// `Transaction` spends an output of a previous one and splits it in two.
+    /// The first `Transaction` spends from an `Account` funded by the eve `Transaction`.
+    fn transactions(network: &TestNetwork, count: usize) -> Result<Vec<Transaction>> {
+        let stage = network.genesis.stage;
+
+        let new_account = || -> Result<Account> {
+            let signers = Wallet::new(stage)?.to_signers()?;
+            Account::new(stage, &signers, 0, None).map_err(|e| e.into())
+        };
+
+        let mut source = new_account()?;
+        source.amount = SimulationParams::AMOUNT;
+        source.transaction_id = Some(network.genesis.eve_transaction.id);
+
+        let mut transactions: Vec<Transaction> = Vec::with_capacity(count);
+        let mut unspent: Vec<(Account, usize)> = Vec::new();
+        let mut source = Some(source);
+
+        while transactions.len() < count {
+            let (account, parent_idx) = if let Some(account) = source.take() {
+                (account, None)
+            } else if !unspent.is_empty() {
+                let idx = Random::u32_range(0, unspent.len() as u32)? as usize;
+                let (account, tx_idx) = unspent.swap_remove(idx);
+
+                (account, Some(tx_idx))
+            } else {
+                let err = Error::InvalidLength;
+                return Err(err);
+            };
+
+            let amount = if let Some(tx_idx) = parent_idx {
+                transactions[tx_idx].get_output(&account.address())?.amount
+            } else {
+                account.amount
+            };
+
+            let amounts = if amount > 1 {
+                vec![amount / 2, amount - amount / 2]
+            } else {
+                vec![amount]
+            };
+
+            // NB: the accounts have to be older than the transactions paying them
+            let accounts = amounts
+                .iter()
+                .map(|_| new_account())
+                .collect::<Result<Vec<Account>>>()?;
+
+            let builder = TransactionBuilder::new(stage)?;
+
+            let mut builder = if let Some(tx_idx) = parent_idx {
+                builder.spend_from_transaction(&account, &transactions[tx_idx])?
+            } else {
+                builder.spend_from(&account, amount)?
+            };
+
+            let tx_idx = transactions.len();
+
+            for (account, amount) in accounts.into_iter().zip(amounts) {
+                builder = builder.pay_to(&account.address(), amount)?;
+                unspent.push((account, tx_idx));
+            }
+
+            transactions.push(builder.build()?);
+        }
+
+        Ok(transactions)
+    }
+
+    /// `is_lost` returns if a message is lost, given the loss probability.
+    fn is_lost(loss: f64) -> Result<bool> {
+        if loss == 0.0 {
+            return Ok(false);
+        }
+
+        let draw = Random::u32_range(0, 1_000_000)? as f64 / 1_000_000.0;
+        Ok(draw < loss)
+    }
+
+    /// `deliver` adds a `Transaction` of the `Simulation` to a `TestNode`, after
+    /// the ancestors it does not know yet, as the `TestNode` would fetch them.
+    fn deliver(
+        transactions: &[Transaction],
+        node: &TestNode,
+        transaction: &Transaction,
+    ) -> Result<()> {
+        let known = node
+            .state
+            .lock()
+            .unwrap()
+            .state
+            .lookup_known_transaction(&transaction.id);
+
+        if known || node.is_stored(&transaction.id)? {
+            return Ok(());
+        }
+
+        let ancestors = transaction.ancestors()?;
+
+        for ancestor in transactions.iter().filter(|tx| ancestors.contains(&tx.id)) {
+            Simulation::deliver(transactions, node, ancestor)?;
+        }
+
+        node.add_transaction(transaction)
+    }
+
+    /// `step` advances the consensus of a `TestNode` of one step with
+    /// `TestNetwork::step_with`, delivering the queried `Transaction`s to the
+    /// peers and losing the queries and replies with the loss probability.
+    fn step(&mut self, idx: usize) -> Result<()> {
+        let loss = self.params.loss;
+        let transactions = &self.transactions;
+        let messages = &mut self.messages;
+
+        self.network.step_with(idx, |peer, transaction| {
+            messages.queries += 1;
+
+            if Simulation::is_lost(loss)? {
+                messages.lost += 1;
+                return Ok(None);
+            }
+
+            Simulation::deliver(transactions, peer, transaction)?;
+
+            let chit = peer
+                .state
+                .lock()
+                .unwrap()
+                .is_strongly_preferred(&transaction.id)?;
+
+            messages.replies += 1;
+
+            if Simulation::is_lost(loss)? {
+                messages.lost += 1;
+                return Ok(None);
+            }
+
+            Ok(Some(chit))
+        })
+    }
+
+    /// `record_accepted` records the `Transaction`s accepted by the `TestNode`s
+    /// at a step.
+    fn record_accepted(&mut self, step: u64) -> Result<()> {
+        for (tx_id, submitted_at) in self.submitted.iter() {
+            let accepted = self.accepted.entry(*tx_id).or_insert_with(BTreeMap::new);
+
+            for (idx, node) in self.network.nodes.iter().enumerate() {
+                if !accepted.contains_key(&idx) && node.is_stored(tx_id)? {
+                    accepted.insert(idx, step - submitted_at);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `is_done` returns if all the `Transaction`s are accepted by all the `TestNode`s.
+    fn is_done(&self) -> bool {
+        self.submitted.len() == self.transactions.len()
+            && self
+                .accepted
+                .values()
+                .all(|accepted| accepted.len() == self.network.len())
+    }
+
+    /// `run` runs the `Simulation`, submitting a `Transaction` per step to a
+    /// random `TestNode` until all of them are accepted or the maximum number
+    /// of steps is reached.
+    pub fn run(&mut self) -> Result<SimulationReport> {
+        let mut steps = 0;
+
+        for step in 0..self.params.max_steps as u64 {
+            if let Some(tx) = self.transactions.get(step as usize) {
+                let idx = Random::u32_range(0, self.network.len() as u32)? as usize;
+                Simulation::deliver(&self.transactions, self.network.node(idx)?, tx)?;
+                self.submitted.insert(tx.id, step);
+            }
+
+            for idx in 0..self.network.len() {
+                self.step(idx)?;
+            }
+
+            steps = step + 1;
+            self.record_accepted(steps)?;
+
+            if self.is_done() {
+                break;
+            }
+        }
+
+        Ok(self.report(steps))
+    }
+
+    /// `report` returns the `SimulationReport` of the `Simulation` after a
+    /// number of steps.
+    fn report(&self, steps: u64) -> SimulationReport {
+        let latencies: Vec<u64> = self
+            .accepted
+            .values()
+            .flat_map(|accepted| accepted.values().copied())
+            .collect();
+
+        SimulationReport {
+            params: self.params,
+            steps,
+            accepted: latencies.len() as u64,
+            expected: (self.transactions.len() * self.network.len()) as u64,
+            latency: LatencyStats::from_samples(&latencies),
+            messages: self.messages,
+        }
+    }
+
+    /// `dag` returns the DAG of the `Transaction`s of the `Simulation`.
+    pub fn dag(&self) -> Result<Vec<DagEntry>> {
+        let mut dag = Vec::with_capacity(self.transactions.len());
+
+        for tx in self.transactions.iter() {
+            let entry = DagEntry {
+                id: tx.id,
+                parents: tx.ancestors()?,
+                submitted_at: self.submitted.get(&tx.id).copied(),
+                accepted_by: self
+                    .accepted
+                    .get(&tx.id)
+                    .map_or(0, |accepted| accepted.len() as u64),
+            };
+
+            dag.push(entry);
+        }
+
+        Ok(dag)
+    }
+}
+
+#[test]
+fn test_latency_stats() {
+    let stats = LatencyStats::from_samples(&[]);
+    assert_eq!(stats, LatencyStats::default());
+
+    let samples: Vec<u64> = (1..=100).rev().collect();
+    let stats = LatencyStats::from_samples(&samples);
+
+    assert_eq!(stats.count, 100);
+    assert_eq!(stats.min, 1);
+    assert_eq!(stats.max, 100);
+    assert_eq!(stats.p50, 50);
+    assert_eq!(stats.p90, 90);
+    assert_eq!(stats.p99, 99);
+    assert!((stats.mean - 50.5).abs() < std::f64::EPSILON);
+}
+
+#[test]
+fn test_simulation() {
+    let invalid_params = vec![
+        SimulationParams::new(1, 1, 0.0),
+        SimulationParams::new(3, 0, 0.0),
+        SimulationParams::new(3, 1, 1.0),
+        SimulationParams::new(3, 1, -0.1),
+    ];
+
+    for params in invalid_params {
+        let res = Simulation::new(&params);
+        assert!(res.is_err());
+    }
+
+    let params = SimulationParams::new(3, 4, 0.0);
+
+    let res = Simulation::new(&params);
+    assert!(res.is_ok());
+
+    let mut simulation = res.unwrap();
+    assert_eq!(simulation.transactions.len(), 4);
+
+    let res = simulation.run();
+    assert!(res.is_ok());
+
+    let report = res.unwrap();
+    assert_eq!(report.expected, 12);
+    assert_eq!(report.accepted, report.expected);
+    assert_eq!(report.latency.count, report.accepted);
+    assert_eq!(report.messages.lost, 0);
+    assert_eq!(report.messages.queries, report.messages.replies);
+
+    let dag = simulation.dag().unwrap();
+    assert_eq!(dag.len(), 4);
+    assert!(dag.iter().all(|entry| entry.parents.len() == 1));
+
+    let eve_tx_id = simulation.network.genesis.eve_transaction.id;
+    assert!(dag[0].parents.contains(&eve_tx_id));
+    assert!(dag.iter().all(|entry| entry.accepted_by == 3));
+
+    let mut params = SimulationParams::new(3, 4, 0.1);
+    params.seed = Some(Random::u64().unwrap());
+
+    let res = Simulation::new(&params);
+    assert!(res.is_ok());
+    assert!(Random::is_seeded());
+
+    drop(res);
+    assert!(!Random::is_seeded());
+}
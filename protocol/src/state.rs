@@ -2,24 +2,58 @@
 //!
 //! `state` is the module containing the protocol state type and functions.
 
+use crate::adaptive::AdaptiveParams;
+use crate::connection::ConnectionManager;
 use crate::error::Error;
+use crate::events::{EventBus, ProtocolEvent};
+use crate::greylist::Greylist;
+use crate::hints::ReplyTallies;
+use crate::ledger::{self, AddressHistory, UnspentOutput};
+use crate::orphan::OrphanPool;
+use crate::policy::{MempoolPolicies, MempoolPolicy};
+use crate::priority::PriorityQueue;
+use crate::prune::{self, PruneReport};
+use crate::replay::ReplayWindow;
 use crate::result::Result;
+use crate::utxo;
 use config::consensus::ConsensusConfig;
+use config::pool::PoolConfig;
+use crypto::ecc::ed25519::SecretKey;
 use crypto::hash::Digest;
+use crypto::random::Random;
+use mining::scheduler::Scheduler;
 use models::account::Account;
 use models::address::Address;
 use models::conflict_set::ConflictSet;
-use models::consensus_state::ConsensusState;
+use models::consensus_message::{ConsensusMessage, ReplyHints};
+use models::consensus_params::ConsensusParams;
+use models::consensus_state::{ConsensusMutation, ConsensusState, TransactionState};
+use models::emission::{self, Issuance};
 use models::error::Error as ModelsError;
-use models::node::Node;
+use models::genesis::Genesis;
+use models::inbox::Inbox;
+use models::node::{KeyRotation, Node, Service};
+use models::node_ban::NodeBan;
+use models::node_reputation::NodeReputation;
+use models::outbox::Outbox;
 use models::stage::Stage;
+use models::timestamp::Timestamp;
 use models::traits::Storable;
 use models::transaction::Transaction;
-use std::collections::BTreeSet;
-use std::sync::{Arc, Mutex};
+use models::transaction_proof::TransactionProof;
+use models::tx_telemetry::TxTelemetry;
+use models::validation_cache::ValidationCache;
+use models::watch::Watch;
+use models::work::Work;
+use network::blacklist::Blacklist;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, RwLock};
+use store::stats::StoreOccupancy;
 use store::traits::Store;
 
-/// `ProtocolState` is the protocol state type.
+/// `ProtocolState` is the protocol state type. The stores are shared behind
+/// `RwLock`s, so that the read-only operations on them run concurrently.
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct ProtocolState<S: Store, P: Store> {
@@ -27,11 +61,46 @@ pub struct ProtocolState<S: Store, P: Store> {
     pub address: Vec<u8>,
     pub config: ConsensusConfig,
     pub state: ConsensusState,
-    pub store: Arc<Mutex<S>>,
-    pub pool: Arc<Mutex<P>>,
+    pub journal_seq: u64,
+    pub store: Arc<RwLock<S>>,
+    pub pool: Arc<RwLock<P>>,
+    pub cache: Arc<Mutex<ValidationCache>>,
+    pub read_only: bool,
+    pub stopped: bool,
+    pub queue: PriorityQueue,
+    pub reply_tallies: ReplyTallies,
+    pub replay_window: ReplayWindow,
+    pub adaptive: AdaptiveParams,
+    pub orphans: OrphanPool,
+    pub events: EventBus,
+    pub connections: ConnectionManager,
+    pub greylist: Greylist,
+    pub blacklist: Arc<Mutex<Blacklist>>,
+    pub secret_key: Option<SecretKey>,
+    pub key_rotation: Option<KeyRotation>,
+    pub services: BTreeSet<Service>,
+    pub scheduler: Scheduler,
+    pub work: BTreeMap<Digest, Transaction>,
+    pub policies: MempoolPolicies,
 }
 
 impl<S: Store, P: Store> ProtocolState<S, P> {
+    /// `MAX_WEIGHTED_CANDIDATES` is the maximum number of nodes among which the
+    /// candidates are drawn in work sampling mode.
+    pub const MAX_WEIGHTED_CANDIDATES: u32 = 1024;
+
+    /// `POOL_SOFT_LIMIT` is the percentage of the pool occupancy above which
+    /// the random fetching of `Transaction`s slows down.
+    pub const POOL_SOFT_LIMIT: u32 = 75;
+
+    /// `POOL_HARD_LIMIT` is the percentage of the pool occupancy above which
+    /// the random fetching of `Transaction`s stops.
+    pub const POOL_HARD_LIMIT: u32 = 95;
+
+    /// `SNAPSHOT_PERIOD` is the number of journaled mutations after which the
+    /// `ConsensusState` is saved and its journal truncated.
+    pub const SNAPSHOT_PERIOD: u64 = 1024;
+
     /// `create` creates a new `ProtocolState` instance, erasing
     /// the previous content of the stores.
     /// The method is equivalent to the "Init" procedure in
@@ -42,8 +111,8 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
         config: &mut ConsensusConfig,
         eve_account: &Account,
         seed: &BTreeSet<Vec<u8>>,
-        store: Arc<Mutex<S>>,
-        pool: Arc<Mutex<P>>,
+        store: Arc<RwLock<S>>,
+        pool: Arc<RwLock<P>>,
     ) -> Result<ProtocolState<S, P>> {
         config.validate()?;
         eve_account.validate()?;
@@ -62,20 +131,21 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
 
         let mut eve_transaction = Transaction::new_eve(stage, &eve_account.address())?;
         eve_transaction.mine()?;
+        eve_transaction.update_id()?;
 
-        store.lock().unwrap().clear()?;
-        pool.lock().unwrap().clear()?;
+        store.write().unwrap().clear()?;
+        pool.write().unwrap().clear()?;
 
-        Account::create(&mut *store.lock().unwrap(), stage, &eve_account)?;
+        Account::create(&mut *store.write().unwrap(), stage, &eve_account)?;
 
-        Transaction::create(&mut *store.lock().unwrap(), stage, &eve_transaction)?;
+        Transaction::create(&mut *store.write().unwrap(), stage, &eve_transaction)?;
 
         let mut seed_ids = BTreeSet::new();
         let mut seed_nodes = BTreeSet::new();
 
         for address in seed {
             let node = Node::new(stage, address);
-            Node::create(&mut *store.lock().unwrap(), stage, &node)?;
+            Node::create(&mut *store.write().unwrap(), stage, &node)?;
             seed_ids.insert(node.id);
             seed_nodes.insert(node);
         }
@@ -87,104 +157,667 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
             &eve_transaction.id,
             &seed_ids,
         );
-        ConsensusState::create(&mut *store.lock().unwrap(), stage, &state)?;
+        ConsensusState::create(&mut *store.write().unwrap(), stage, &state)?;
 
         let state = ProtocolState {
             stage,
             address: address.to_owned(),
             config: config.to_owned(),
             state,
+            journal_seq: 0,
+            store,
+            pool,
+            cache: Arc::new(Mutex::new(ValidationCache::default())),
+            read_only: false,
+            stopped: false,
+            secret_key: None,
+            key_rotation: None,
+            services: BTreeSet::new(),
+            queue: PriorityQueue::new(config)?,
+            reply_tallies: ReplyTallies::new(),
+            replay_window: ReplayWindow::new(config),
+            adaptive: AdaptiveParams::new(config),
+            orphans: OrphanPool::new(config),
+            events: EventBus::default(),
+            connections: ConnectionManager::default(),
+            greylist: Greylist::default(),
+            blacklist: Arc::new(Mutex::new(Blacklist::default())),
+            scheduler: ProtocolState::<S, P>::new_scheduler(config)?,
+            work: BTreeMap::new(),
+            policies: MempoolPolicies::default(),
+        };
+
+        Ok(state)
+    }
+
+    /// `create_from_genesis` creates a new `ProtocolState` instance from a
+    /// `Genesis`, erasing the previous content of the stores.
+    pub fn create_from_genesis(
+        genesis: &Genesis,
+        address: &[u8],
+        seed: &BTreeSet<Vec<u8>>,
+        store: Arc<RwLock<S>>,
+        pool: Arc<RwLock<P>>,
+    ) -> Result<ProtocolState<S, P>> {
+        genesis.validate()?;
+        genesis.verify_signature()?;
+
+        let stage = genesis.stage;
+        let eve_account = genesis.eve_account.clone();
+        let eve_transaction = genesis.eve_transaction.clone();
+        let config = genesis.consensus.clone();
+
+        store.write().unwrap().clear()?;
+        pool.write().unwrap().clear()?;
+
+        Account::create(&mut *store.write().unwrap(), stage, &eve_account)?;
+
+        Transaction::create(&mut *store.write().unwrap(), stage, &eve_transaction)?;
+
+        let mut seed_ids = BTreeSet::new();
+
+        for address in seed {
+            let node = Node::new(stage, address);
+            Node::create(&mut *store.write().unwrap(), stage, &node)?;
+            seed_ids.insert(node.id);
+        }
+
+        let state = ConsensusState::new(
+            0,
+            stage,
+            &eve_account.address(),
+            &eve_transaction.id,
+            &seed_ids,
+        );
+        ConsensusState::create(&mut *store.write().unwrap(), stage, &state)?;
+
+        let state = ProtocolState {
+            stage,
+            address: address.to_owned(),
+            queue: PriorityQueue::new(&config)?,
+            reply_tallies: ReplyTallies::new(),
+            replay_window: ReplayWindow::new(&config),
+            adaptive: AdaptiveParams::new(&config),
+            orphans: OrphanPool::new(&config),
+            events: EventBus::default(),
+            connections: ConnectionManager::default(),
+            greylist: Greylist::default(),
+            blacklist: Arc::new(Mutex::new(Blacklist::default())),
+            scheduler: ProtocolState::<S, P>::new_scheduler(&config)?,
+            work: BTreeMap::new(),
+            policies: MempoolPolicies::default(),
+            config,
+            state,
+            journal_seq: 0,
             store,
             pool,
+            cache: Arc::new(Mutex::new(ValidationCache::default())),
+            read_only: false,
+            stopped: false,
+            secret_key: None,
+            key_rotation: None,
+            services: BTreeSet::new(),
         };
 
         Ok(state)
     }
 
     /// `open` creates a new `ProtocolState` instance from a
-    /// populated store.
+    /// populated store, recovering the latest `ConsensusState` from
+    /// its last snapshot and journal.
     pub fn open(
         stage: Stage,
         address: &[u8],
         config: &mut ConsensusConfig,
-        store: Arc<Mutex<S>>,
-        pool: Arc<Mutex<P>>,
+        store: Arc<RwLock<S>>,
+        pool: Arc<RwLock<P>>,
     ) -> Result<ProtocolState<S, P>> {
         config.validate()?;
         config.populate();
 
-        ConsensusState::cleanup(&mut *store.lock().unwrap(), stage, None)?;
-
-        let states = ConsensusState::query(&*store.lock().unwrap(), stage, None, None, None, None)?;
-
-        let max_id: u64 = states.iter().map(|state| state.id).max().unwrap_or(0);
+        ConsensusState::cleanup(&mut *store.write().unwrap(), stage, None)?;
 
-        let mut last_state = ConsensusState::default();
-
-        if states.len() > 1 {
-            for state in states {
-                if state.id != max_id {
-                    ConsensusState::remove(&mut *store.lock().unwrap(), stage, &state.id)?;
-                } else {
-                    last_state = state;
-                }
-            }
-        } else if states.len() == 1 {
-            last_state = states.iter().next().cloned().unwrap();
-        } else {
-            let err = Error::NotFound;
-            return Err(err);
-        }
+        let last_state = ConsensusState::recover(&*store.read().unwrap(), stage)?;
+        let journal_seq =
+            ConsensusState::journal_len(&*store.read().unwrap(), stage, last_state.id)?;
 
-        let state = ProtocolState {
+        let mut state = ProtocolState {
             stage,
             address: address.to_owned(),
             config: config.to_owned(),
             state: last_state,
+            journal_seq,
             store,
             pool,
+            cache: Arc::new(Mutex::new(ValidationCache::default())),
+            read_only: false,
+            stopped: false,
+            secret_key: None,
+            key_rotation: None,
+            services: BTreeSet::new(),
+            queue: PriorityQueue::new(config)?,
+            reply_tallies: ReplyTallies::new(),
+            replay_window: ReplayWindow::new(config),
+            adaptive: AdaptiveParams::new(config),
+            orphans: OrphanPool::new(config),
+            events: EventBus::default(),
+            connections: ConnectionManager::default(),
+            greylist: Greylist::default(),
+            blacklist: Arc::new(Mutex::new(Blacklist::default())),
+            scheduler: ProtocolState::<S, P>::new_scheduler(config)?,
+            work: BTreeMap::new(),
+            policies: MempoolPolicies::default(),
         };
 
+        state.load_params()?;
+        state.load_reputations()?;
+
         Ok(state)
     }
 
-    /// `save` saves the `ProtocolState` state in the store.
+    /// `save` saves a snapshot of the `ProtocolState` state in the store,
+    /// dropping the previous snapshots and their journals.
     pub fn save(&mut self) -> Result<()> {
-        ConsensusState::cleanup(&mut *self.store.lock().unwrap(), self.stage, None)?;
+        let mut state = self.state.clone();
+        state.id += 1;
+
+        ConsensusState::create(&mut *self.store.write().unwrap(), self.stage, &state)?;
+
+        self.state = state;
+        self.journal_seq = 0;
 
-        ConsensusState::create(&mut *self.store.lock().unwrap(), self.stage, &self.state)
+        ConsensusState::cleanup(&mut *self.store.write().unwrap(), self.stage, None)
             .map_err(|e| e.into())
     }
 
+    /// `mutate` applies a `ConsensusMutation` to the `ProtocolState` state,
+    /// journaling it in the store so that it can be recovered after a crash.
+    /// Every `SNAPSHOT_PERIOD` mutations the state is saved and the journal
+    /// truncated, so that the recovery replays a bounded journal.
+    pub fn mutate(&mut self, mutation: ConsensusMutation) -> Result<()> {
+        self.state.apply(&mutation)?;
+
+        ConsensusState::journal(
+            &mut *self.store.write().unwrap(),
+            self.stage,
+            self.state.id,
+            self.journal_seq,
+            &mutation,
+        )?;
+
+        self.journal_seq += 1;
+
+        if self.journal_seq >= Self::SNAPSHOT_PERIOD {
+            let id = self.state.id;
+            self.save()?;
+
+            ConsensusState::clear_journal(&mut *self.store.write().unwrap(), self.stage, id)?;
+        }
+
+        Ok(())
+    }
+
+    /// `stop` stops the consensus loop running on the `ProtocolState`
+    /// at the end of its current step.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// `is_stopped` returns if the `ProtocolState` consensus loop has been stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// `store_handles` returns the `Stage` and the store and pool handles of the
+    /// `ProtocolState`, so that they can be read without holding its lock.
+    pub fn store_handles(&self) -> (Stage, Arc<RwLock<S>>, Arc<RwLock<P>>) {
+        (self.stage, self.store.clone(), self.pool.clone())
+    }
+
+    /// `set_read_only` sets the `ProtocolState` read-only mode. A read-only
+    /// `ProtocolState` is a replica: it receives and validates transactions
+    /// and answers fetch requests, but never queries other nodes or mines.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// `validate_writable` returns an error if the `ProtocolState` is read-only.
+    pub fn validate_writable(&self) -> Result<()> {
+        if self.read_only {
+            let err = Error::ReadOnly;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `set_secret_key` sets the `SecretKey` used to sign the `ProtocolState`
+    /// node announcements.
+    pub fn set_secret_key(&mut self, secret_key: SecretKey) {
+        self.secret_key = Some(secret_key);
+    }
+
+    /// `rotate_secret_key` replaces the `SecretKey` used to sign the `ProtocolState`
+    /// node announcements, keeping the `KeyRotation` signed by the old and the new
+    /// keys, so that the other nodes accept the new one.
+    pub fn rotate_secret_key(&mut self, secret_key: SecretKey) -> Result<()> {
+        let old_secret_key = match self.secret_key {
+            Some(ref old_secret_key) => old_secret_key.to_owned(),
+            None => {
+                self.secret_key = Some(secret_key);
+                return Ok(());
+            }
+        };
+
+        let mut node = Node::new(self.stage, &self.address);
+        node.rotate_key(&old_secret_key, &secret_key)?;
+
+        self.key_rotation = node.rotation;
+        self.secret_key = Some(secret_key);
+
+        Ok(())
+    }
+
+    /// `set_services` sets the `Service`s advertised by the `ProtocolState` node
+    /// in its handshakes.
+    pub fn set_services(&mut self, services: &BTreeSet<Service>) {
+        self.services = services.to_owned();
+    }
+
+    /// `sample_services` returns the `Service`s required to the sampled nodes.
+    pub fn sample_services(&self) -> Result<BTreeSet<Service>> {
+        let mut services = BTreeSet::new();
+
+        if let Some(ref sample_services) = self.config.sample_services {
+            for service in sample_services {
+                services.insert(Service::parse(service)?);
+            }
+        }
+
+        Ok(services)
+    }
+
+    /// `announced_node` returns the `ProtocolState` node signed with its
+    /// `SecretKey`, if any.
+    pub fn announced_node(&self) -> Result<Option<Node>> {
+        if let Some(ref secret_key) = self.secret_key {
+            let mut node = Node::new(self.stage, &self.address);
+            node.rotation = self.key_rotation.clone();
+            node.announce(secret_key)?;
+            Ok(Some(node))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// `subscribe` returns a new receiver of the `ProtocolState` events.
+    pub fn subscribe(&mut self) -> Receiver<ProtocolEvent> {
+        self.events.subscribe()
+    }
+
+    /// `emit` sends an event to the `ProtocolState` events subscribers.
+    pub fn emit(&mut self, event: ProtocolEvent) {
+        self.events.emit(event);
+    }
+
     /// `set_config` sets a new `ConsensusConfig` in the `ProtocolState`.
     pub fn set_config(&mut self, config: &ConsensusConfig) -> Result<()> {
         config.validate()?;
 
+        let (duty_cycle, max_threads) = ProtocolState::<S, P>::mining_limits(config);
+
+        self.queue.set_policy(config)?;
+        self.replay_window.set_config(config);
+        self.adaptive.set_config(config);
+        self.orphans.set_config(config);
+        self.scheduler.set_limits(duty_cycle, max_threads)?;
         self.config = config.to_owned();
 
         Ok(())
     }
 
+    /// `set_pool_policy` sets the mempool acceptance limits of a `PoolConfig`
+    /// in the `ProtocolState`, dropping the custom policies.
+    pub fn set_pool_policy(&mut self, config: &PoolConfig) -> Result<()> {
+        self.policies = MempoolPolicies::new(config)?;
+
+        Ok(())
+    }
+
+    /// `add_policy` adds a custom `MempoolPolicy` to the `ProtocolState`.
+    pub fn add_policy<T: MempoolPolicy + 'static>(&mut self, policy: T) {
+        self.policies.add(policy);
+    }
+
+    /// `check_policies` checks a `Transaction` against the `ProtocolState`
+    /// mempool acceptance policies.
+    pub fn check_policies(&self, transaction: &Transaction) -> Result<()> {
+        self.policies.check(transaction)
+    }
+
+    /// `mining_limits` returns the mining duty cycle and maximum number of
+    /// mining threads of a `ConsensusConfig`.
+    fn mining_limits(config: &ConsensusConfig) -> (u32, u32) {
+        let duty_cycle = config
+            .mining_duty_cycle
+            .unwrap_or(ConsensusConfig::DEFAULT_MINING_DUTY_CYCLE);
+        let max_threads = config
+            .mining_threads
+            .unwrap_or(ConsensusConfig::DEFAULT_MINING_THREADS);
+
+        (duty_cycle, max_threads)
+    }
+
+    /// `new_scheduler` creates the mining `Scheduler` of a `ConsensusConfig`.
+    fn new_scheduler(config: &ConsensusConfig) -> Result<Scheduler> {
+        let (duty_cycle, max_threads) = ProtocolState::<S, P>::mining_limits(config);
+
+        Scheduler::new(duty_cycle, max_threads).map_err(|e| e.into())
+    }
+
+    /// `is_behind` returns if the node is behind on consensus processing,
+    /// having more queued transactions than the mining maximum backlog.
+    pub fn is_behind(&self) -> bool {
+        let max_backlog = self
+            .config
+            .mining_max_backlog
+            .unwrap_or(ConsensusConfig::DEFAULT_MINING_MAX_BACKLOG);
+
+        self.queue.len() > max_backlog as usize
+    }
+
+    /// `schedule_mining` pauses the mining while the node is behind on
+    /// consensus processing, resuming it otherwise. It returns if the
+    /// mining is paused.
+    pub fn schedule_mining(&self) -> bool {
+        let behind = self.is_behind();
+
+        if behind {
+            self.scheduler.pause();
+        } else {
+            self.scheduler.resume();
+        }
+
+        behind
+    }
+
+    /// `push_work` queues an unmined `Transaction` to be mined by the remote miners,
+    /// at the difficulty expected from its ancestors.
+    pub fn push_work(&mut self, transaction: &Transaction) -> Result<()> {
+        self.validate_transaction(transaction)?;
+
+        if transaction.is_mined() {
+            let err = Error::AlreadyMined;
+            return Err(err);
+        }
+
+        let mut transaction = transaction.to_owned();
+        self.retarget_transaction(&mut transaction)?;
+
+        self.work.insert(transaction.id, transaction);
+
+        Ok(())
+    }
+
+    /// `next_work` returns the mining `Work` of a random queued `Transaction`,
+    /// so that concurrent remote miners are unlikely to mine the same one.
+    pub fn next_work(&self) -> Result<Work> {
+        if self.work.is_empty() {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        let idx = Random::u32_range(0, self.work.len() as u32)? as usize;
+        let transaction = self.work.values().nth(idx).unwrap();
+
+        Work::from_transaction(transaction).map_err(|e| e.into())
+    }
+
+    /// `submit_work` applies the solution of the mining `Work` of a queued
+    /// `Transaction`, returning the mined `Transaction`.
+    pub fn submit_work(
+        &mut self,
+        tx_id: &Digest,
+        nonce: u64,
+        digest: Digest,
+    ) -> Result<Transaction> {
+        let mut transaction = match self.work.get(tx_id) {
+            Some(transaction) => transaction.clone(),
+            None => {
+                let err = Error::NotFound;
+                return Err(err);
+            }
+        };
+
+        let work = Work::from_transaction(&transaction)?;
+        work.apply(&mut transaction, nonce, digest)?;
+
+        self.work.remove(tx_id);
+
+        Ok(transaction)
+    }
+
+    /// `params_sequence` returns the sequence of the latest `ConsensusParams`
+    /// applied to the `ProtocolState`, or zero if there are none.
+    pub fn params_sequence(&self) -> Result<u64> {
+        let sequence = ConsensusParams::latest(&*self.store.read().unwrap(), self.stage)?
+            .map(|params| params.sequence)
+            .unwrap_or(0);
+
+        Ok(sequence)
+    }
+
+    /// `verify_params` verifies a `ConsensusParams` against the authority key
+    /// of the `ProtocolState` config.
+    pub fn verify_params(&self, params: &ConsensusParams) -> Result<()> {
+        params.validate()?;
+
+        if params.stage != self.stage {
+            let err = Error::InvalidStage;
+            return Err(err);
+        }
+
+        if let Some(authority) = self.config.authority_public_key()? {
+            params.verify_signature(&authority).map_err(|e| e.into())
+        } else {
+            let err = Error::NotAllowed;
+            Err(err)
+        }
+    }
+
+    /// `handle_params_update` applies a signed `ConsensusParams` to the
+    /// `ProtocolState` config and persists it in the store. It returns false
+    /// if the `ConsensusParams` is not newer than the one already applied.
+    pub fn handle_params_update(&mut self, params: &ConsensusParams) -> Result<bool> {
+        self.verify_params(params)?;
+
+        if params.sequence <= self.params_sequence()? {
+            return Ok(false);
+        }
+
+        let config = params.apply(&self.config)?;
+
+        ConsensusParams::insert(&mut *self.store.write().unwrap(), self.stage, params)?;
+
+        self.set_config(&config)?;
+
+        Ok(true)
+    }
+
+    /// `load_params` applies to the `ProtocolState` config the latest
+    /// `ConsensusParams` persisted in the store, if any.
+    pub fn load_params(&mut self) -> Result<()> {
+        let latest = ConsensusParams::latest(&*self.store.read().unwrap(), self.stage)?;
+
+        if let Some(params) = latest {
+            self.verify_params(&params)?;
+
+            let config = params.apply(&self.config)?;
+            self.set_config(&config)?;
+        }
+
+        Ok(())
+    }
+
     /// `set_state` sets a new `ConsensusState` in the `ProtocolState`.
     pub fn set_state(&mut self, state: &ConsensusState) -> Result<()> {
         state.validate()?;
 
+        self.journal_seq =
+            ConsensusState::journal_len(&*self.store.read().unwrap(), self.stage, state.id)?;
         self.state = state.to_owned();
 
         Ok(())
     }
 
+    /// `validate_transaction` validates a `Transaction`, using the `ProtocolState`
+    /// validation cache.
+    pub fn validate_transaction(&self, transaction: &Transaction) -> Result<()> {
+        self.cache
+            .lock()
+            .unwrap()
+            .validate(transaction)
+            .map_err(|e| e.into())
+    }
+
+    /// `validate_fully_signed_transaction` validates a fully signed `Transaction`,
+    /// using the `ProtocolState` validation cache.
+    pub fn validate_fully_signed_transaction(&self, transaction: &Transaction) -> Result<()> {
+        self.cache
+            .lock()
+            .unwrap()
+            .validate_fully_signed(transaction)
+            .map_err(|e| e.into())
+    }
+
+    /// `validate_mined_transaction` validates a mined `Transaction`, using the
+    /// `ProtocolState` validation cache and enforcing the retargeted difficulty
+    /// and the emission schedule. The distance, the difficulty and the issuance
+    /// are derived from the ancestors of the `Transaction`, which so have to be known.
+    pub fn validate_mined_transaction(&self, transaction: &Transaction) -> Result<()> {
+        if transaction.is_eve()? {
+            return self
+                .cache
+                .lock()
+                .unwrap()
+                .validate_mined(transaction, None)
+                .map_err(|e| e.into());
+        }
+
+        let ancestors = self.get_ancestors(transaction)?;
+
+        transaction.validate_ancestors_distance(&ancestors)?;
+
+        let difficulty = transaction.expected_difficulty(&ancestors, &self.config)?;
+
+        self.cache
+            .lock()
+            .unwrap()
+            .validate_mined(transaction, Some(difficulty))?;
+
+        let issued = self.ancestry_issuance(transaction, &ancestors)?;
+
+        emission::validate_issuance(transaction, issued).map_err(|e| e.into())
+    }
+
+    /// `validate_mined_orphan_transaction` validates the mining proof of a mined
+    /// `Transaction` with missing ancestors. Its difficulty is enforced when it is
+    /// promoted from the orphan pool and validated again.
+    pub fn validate_mined_orphan_transaction(&self, transaction: &Transaction) -> Result<()> {
+        self.cache
+            .lock()
+            .unwrap()
+            .validate_mined(transaction, None)
+            .map_err(|e| e.into())
+    }
+
+    /// `get_ancestors` returns the ancestors of a `Transaction`, looked up in the
+    /// pool and in the store.
+    pub fn get_ancestors(&self, transaction: &Transaction) -> Result<Vec<Transaction>> {
+        let mut ancestors = Vec::new();
+
+        for ancestor_id in transaction.ancestors()? {
+            let ancestor =
+                match Transaction::get(&*self.pool.read().unwrap(), self.stage, &ancestor_id) {
+                    Ok(ancestor) => Ok(ancestor),
+                    Err(ModelsError::NotFound) => {
+                        Transaction::get(&*self.store.read().unwrap(), self.stage, &ancestor_id)
+                    }
+                    Err(e) => Err(e),
+                }?;
+
+            ancestors.push(ancestor);
+        }
+
+        Ok(ancestors)
+    }
+
+    /// `expected_difficulty` returns the minimum mining difficulty of a `Transaction`,
+    /// retargeted from its ancestors following the consensus schedule.
+    pub fn expected_difficulty(&self, transaction: &Transaction) -> Result<u64> {
+        let ancestors = self.get_ancestors(transaction)?;
+
+        transaction
+            .expected_difficulty(&ancestors, &self.config)
+            .map_err(|e| e.into())
+    }
+
+    /// `ancestry_issuance` returns the ancestry issuance of a `Transaction`, given its
+    /// ancestors. The ancestry issuances of the ancestors are read from the store
+    /// when recorded, and otherwise computed from their own ancestors.
+    pub fn ancestry_issuance(
+        &self,
+        transaction: &Transaction,
+        ancestors: &[Transaction],
+    ) -> Result<u64> {
+        let mut ancestors_issued = Vec::new();
+
+        for ancestor in ancestors {
+            let recorded = emission::get_ancestry_issuance(
+                &*self.store.read().unwrap(),
+                self.stage,
+                &ancestor.id,
+            )?;
+
+            let issued = match recorded {
+                Some(issued) => issued,
+                None => {
+                    let ancestor_ancestors = self.get_ancestors(ancestor)?;
+                    self.ancestry_issuance(ancestor, &ancestor_ancestors)?
+                }
+            };
+
+            ancestors_issued.push(issued);
+        }
+
+        Ok(emission::ancestry_issuance(transaction, &ancestors_issued))
+    }
+
+    /// `retarget_transaction` raises the difficulty of the unmined `Coinbase` of a
+    /// `Transaction` to the expected one, so that the `Transaction` is not rejected
+    /// once mined. The signed `Transaction`s mined below it are rejected upfront.
+    pub fn retarget_transaction(&self, transaction: &mut Transaction) -> Result<()> {
+        let difficulty = self.expected_difficulty(transaction)?;
+
+        match transaction.coinbase {
+            Some(coinbase) if coinbase.difficulty < difficulty => {
+                transaction.set_difficulty(difficulty).map_err(|e| e.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// `get_known_ancestors` returns a `Transaction` known ancestors.
     pub fn get_known_ancestors(&self, tx_id: &Digest) -> Result<BTreeSet<Digest>> {
-        let tx = match Transaction::get(&*self.pool.lock().unwrap(), self.stage, tx_id) {
+        let tx = match Transaction::get(&*self.pool.read().unwrap(), self.stage, tx_id) {
             Ok(tx) => Ok(tx),
             Err(ModelsError::NotFound) => {
-                Transaction::get(&*self.store.lock().unwrap(), self.stage, tx_id)
+                Transaction::get(&*self.store.read().unwrap(), self.stage, tx_id)
             }
             Err(e) => Err(e),
         }?;
 
-        tx.validate()?;
+        self.validate_transaction(&tx)?;
 
         let ancestors: BTreeSet<Digest> = tx
             .ancestors()?
@@ -198,15 +831,15 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
 
     /// `get_unknown_ancestors` returns the unknown ancestors of a `Transactions`.
     pub fn get_unknown_ancestors(&self, tx_id: &Digest) -> Result<BTreeSet<Digest>> {
-        let tx = match Transaction::get(&*self.pool.lock().unwrap(), self.stage, tx_id) {
+        let tx = match Transaction::get(&*self.pool.read().unwrap(), self.stage, tx_id) {
             Ok(tx) => Ok(tx),
             Err(ModelsError::NotFound) => {
-                Transaction::get(&*self.store.lock().unwrap(), self.stage, tx_id)
+                Transaction::get(&*self.store.read().unwrap(), self.stage, tx_id)
             }
             Err(e) => Err(e),
         }?;
 
-        tx.validate()?;
+        self.validate_transaction(&tx)?;
 
         let ancestors: BTreeSet<Digest> = tx
             .ancestors()?
@@ -218,24 +851,25 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
         Ok(ancestors)
     }
 
-    /// `update_successors` updates the set of successors of the ancestor `Transaction`s of the
-    /// `Transaction`.
-    pub fn update_successors(&mut self, transaction: &Transaction) -> Result<()> {
-        transaction.validate()?;
+    /// `get_transaction_successors` returns the ids of the successors of a `Transaction`,
+    /// read from the successors index of the store and of the pool.
+    pub fn get_transaction_successors(&self, tx_id: &Digest) -> Result<BTreeSet<Digest>> {
+        let mut successors =
+            Transaction::query_successors(&*self.store.read().unwrap(), self.stage, tx_id)?;
 
-        let id = transaction.id;
-        let ancestors = transaction.ancestors()?;
-        for anc_id in ancestors {
-            self.state.add_transaction_successor(&anc_id, id)?;
+        for succ_id in
+            Transaction::query_successors(&*self.pool.read().unwrap(), self.stage, tx_id)?
+        {
+            successors.insert(succ_id);
         }
 
-        Ok(())
+        Ok(successors)
     }
 
     /// `get_transaction_conflict_set` returns a `Transaction` `ConflictSet`.
     pub fn get_transaction_conflict_set(&self, tx_id: &Digest) -> Result<ConflictSet> {
         if let Some(cs_id) = self.state.get_transaction_conflict_set(tx_id) {
-            let cs = ConflictSet::get(&*self.pool.lock().unwrap(), self.stage, &cs_id)?;
+            let cs = ConflictSet::get(&*self.pool.read().unwrap(), self.stage, &cs_id)?;
             cs.validate()?;
 
             Ok(cs)
@@ -245,22 +879,35 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
         }
     }
 
+    /// `get_conflict_sets` returns the `ConflictSet`s of a set of `Address`es found
+    /// in the pool, fetching them by shard with a single read of the pool.
+    pub fn get_conflict_sets(
+        &self,
+        addresses: &BTreeSet<Address>,
+    ) -> Result<BTreeMap<Address, ConflictSet>> {
+        let sets = ConflictSet::get_batch(&*self.pool.read().unwrap(), self.stage, addresses)?;
+
+        for cs in sets.values() {
+            cs.validate()?;
+        }
+
+        Ok(sets)
+    }
+
     /// `calc_confidence` calculates the confidence of a `Transaction`.
     pub fn calc_confidence(&self, tx_id: &Digest) -> Result<u64> {
-        let tx_in_pool = Transaction::lookup(&*self.pool.lock().unwrap(), self.stage, tx_id)?;
-        let tx_in_store = Transaction::lookup(&*self.store.lock().unwrap(), self.stage, tx_id)?;
+        let tx_in_pool = Transaction::lookup(&*self.pool.read().unwrap(), self.stage, tx_id)?;
+        let tx_in_store = Transaction::lookup(&*self.store.read().unwrap(), self.stage, tx_id)?;
 
         if tx_in_pool || tx_in_store {
-            let confidence = if let Some(successors) = self.state.get_transaction_successors(tx_id)
-            {
+            let successors = self.get_transaction_successors(tx_id)?;
+
+            let confidence = if successors.is_empty() {
+                0
+            } else {
                 let mut confidence = 0;
 
                 for succ_id in successors {
-                    if !self.state.lookup_known_transaction(&succ_id) {
-                        let err = Error::NotFound;
-                        return Err(err);
-                    }
-
                     let chit = self.state.get_transaction_chit(&succ_id).unwrap_or(false) as u64;
 
                     confidence += chit;
@@ -269,8 +916,6 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
                 confidence += self.state.get_transaction_chit(tx_id).unwrap_or(false) as u64;
 
                 confidence
-            } else {
-                0
             };
 
             Ok(confidence)
@@ -280,45 +925,136 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
         }
     }
 
-    /// `update_confidence` updates the confidence of a `Transaction`.
-    pub fn update_confidence(&mut self, tx_id: &Digest) -> Result<()> {
-        let confidence = self.calc_confidence(tx_id)?;
+    /// `counted_confidence` returns the confidence of a `Transaction` from its
+    /// `ConsensusState` confidence counters, seeding them from its successors
+    /// the first time or after they have been invalidated.
+    pub fn counted_confidence(&mut self, tx_id: &Digest) -> Result<u64> {
+        if let Some(confidence) = self.state.counted_transaction_confidence(tx_id) {
+            return Ok(confidence);
+        }
 
-        self.state
-            .set_transaction_confidence(*tx_id, confidence)
-            .map_err(|e| e.into())
-    }
+        let tx_in_pool = Transaction::lookup(&*self.pool.read().unwrap(), self.stage, tx_id)?;
+        let tx_in_store = Transaction::lookup(&*self.store.read().unwrap(), self.stage, tx_id)?;
 
-    /// `upsert_conflict_sets` upserts the `ConsensusState` conflict sets.
-    pub fn upsert_conflict_sets(&mut self, transaction: &Transaction) -> Result<()> {
-        transaction.validate()?;
+        if !tx_in_pool && !tx_in_store {
+            let err = Error::NotFound;
+            return Err(err);
+        }
 
-        let tx_id = transaction.id;
-        let addresses: BTreeSet<Address> = transaction
-            .outputs
-            .values()
-            .map(|out| out.address)
-            .collect();
+        let successors = self.get_transaction_successors(tx_id)?;
+        self.state.seed_transaction_counters(*tx_id, &successors)?;
 
-        for address in addresses {
-            if ConflictSet::lookup(&*self.pool.lock().unwrap(), self.stage, &address)? {
-                let mut cs = ConflictSet::get(&*self.pool.lock().unwrap(), self.stage, &address)?;
-                cs.validate()?;
-                cs.transactions.insert(tx_id);
-                ConflictSet::update(&mut *self.pool.lock().unwrap(), self.stage, &cs)?;
-            } else {
-                let mut cs = ConflictSet::new(address, self.stage);
-                cs.add_transaction(tx_id);
-                cs.count = 0;
-                ConflictSet::create(&mut *self.pool.lock().unwrap(), self.stage, &cs)?;
-            }
+        let confidence = self
+            .state
+            .counted_transaction_confidence(tx_id)
+            .unwrap_or(0);
+
+        Ok(confidence)
+    }
+
+    /// `add_successor` adds a known `Transaction` to the confidence counters of
+    /// its ancestors.
+    pub fn add_successor(&mut self, transaction: &Transaction) -> Result<()> {
+        for ancestor_id in transaction.ancestors()? {
+            self.state
+                .add_transaction_successor(ancestor_id, transaction.id)?;
         }
 
         Ok(())
     }
 
-    /// `is_preferred` returns if a `Transaction` is preferred.
-    /// The name of the function in the Avalanche paper is "IsPreferred".
+    /// `missing_ancestors` returns the ids of the ancestors of a `Transaction`
+    /// that are neither known nor in the pool or in the store.
+    pub fn missing_ancestors(&self, transaction: &Transaction) -> Result<BTreeSet<Digest>> {
+        let mut missing = BTreeSet::new();
+
+        for ancestor_id in transaction.ancestors()? {
+            if self.state.lookup_known_transaction(&ancestor_id)
+                || Transaction::lookup(&*self.pool.read().unwrap(), self.stage, &ancestor_id)?
+                || Transaction::lookup(&*self.store.read().unwrap(), self.stage, &ancestor_id)?
+            {
+                continue;
+            }
+
+            missing.insert(ancestor_id);
+        }
+
+        Ok(missing)
+    }
+
+    /// `update_confidence` updates the confidence of a `Transaction`. Unlike
+    /// `calc_confidence`, it uses the incremental confidence counters.
+    pub fn update_confidence(&mut self, tx_id: &Digest) -> Result<()> {
+        let confidence = self.counted_confidence(tx_id)?;
+
+        self.mutate(ConsensusMutation::SetTransactionConfidence {
+            tx_id: *tx_id,
+            confidence,
+        })
+    }
+
+    /// `upsert_conflict_sets` upserts the `ConsensusState` conflict sets, journaling
+    /// the conflict set of the `Transaction`. As when the state is rebuilt, that is
+    /// the one of its highest output address.
+    pub fn upsert_conflict_sets(&mut self, transaction: &Transaction) -> Result<()> {
+        self.validate_transaction(transaction)?;
+
+        let tx_id = transaction.id;
+        let mut addresses: BTreeSet<Address> = transaction
+            .outputs
+            .values()
+            .map(|out| out.address)
+            .collect();
+
+        // NB: a coinbase only transaction has to be in a conflict set to be voted
+        if let Some(ref coinbase) = transaction.coinbase {
+            addresses.insert(coinbase.address);
+        }
+
+        let cs_id = addresses.iter().next_back().copied();
+
+        let mut found = self.get_conflict_sets(&addresses)?;
+        let mut updated = Vec::new();
+        let mut created = Vec::new();
+
+        for address in addresses {
+            if let Some(mut cs) = found.remove(&address) {
+                if cs.transactions.insert(tx_id) && cs.transactions.len() > 1 {
+                    self.emit(ProtocolEvent::ConflictDetected {
+                        conflict_set: cs.clone(),
+                    });
+                }
+
+                updated.push(cs);
+            } else {
+                let mut cs = ConflictSet::new(address, self.stage);
+                cs.add_transaction(tx_id);
+                cs.count = 0;
+                created.push(cs);
+            }
+        }
+
+        {
+            let mut pool = self.pool.write().unwrap();
+
+            for cs in updated {
+                ConflictSet::update(&mut *pool, self.stage, &cs)?;
+            }
+
+            for cs in created {
+                ConflictSet::create(&mut *pool, self.stage, &cs)?;
+            }
+        }
+
+        if let Some(address) = cs_id {
+            self.mutate(ConsensusMutation::SetTransactionConflictSet { tx_id, address })?;
+        }
+
+        Ok(())
+    }
+
+    /// `is_preferred` returns if a `Transaction` is preferred.
+    /// The name of the function in the Avalanche paper is "IsPreferred".
     pub fn is_preferred(&self, tx_id: &Digest) -> Result<bool> {
         let cs = self.get_transaction_conflict_set(tx_id)?;
         cs.validate()?;
@@ -334,35 +1070,54 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
     /// `is_strongly_preferred` returns if a `Transaction` is strongly preferred.
     /// The name of the function in the Avalanche paper is "IsStronglyPreferred".
     pub fn is_strongly_preferred(&self, tx_id: &Digest) -> Result<bool> {
-        match Transaction::get(&*self.pool.lock().unwrap(), self.stage, tx_id) {
-            Ok(tx) => {
-                tx.validate()?;
-
-                let ancestors: BTreeSet<Digest> = tx
-                    .ancestors()?
-                    .iter()
-                    .filter(|id| self.state.lookup_known_transaction(&id))
-                    .copied()
-                    .collect();
-
-                for tx_id in ancestors {
-                    if !self.is_preferred(&tx_id)? {
-                        return Ok(false);
-                    }
-                }
-
-                Ok(true)
-            }
+        match Transaction::get(&*self.pool.read().unwrap(), self.stage, tx_id) {
+            Ok(tx) => self.is_strongly_preferred_transaction(&tx),
             Err(ModelsError::NotFound) => {
                 // if it was stored in the store it was
                 // accepted (chit = 1)
-                let found = Transaction::lookup(&*self.store.lock().unwrap(), self.stage, tx_id)?;
+                let found = Transaction::lookup(&*self.store.read().unwrap(), self.stage, tx_id)?;
                 Ok(found)
             }
             Err(err) => Err(err.into()),
         }
     }
 
+    /// `is_strongly_preferred_transaction` returns if a pooled `Transaction` is
+    /// strongly preferred, that is if all its known ancestors are preferred.
+    pub fn is_strongly_preferred_transaction(&self, transaction: &Transaction) -> Result<bool> {
+        self.validate_transaction(transaction)?;
+
+        let ancestors: BTreeSet<Digest> = transaction
+            .ancestors()?
+            .iter()
+            .filter(|id| self.state.lookup_known_transaction(&id))
+            .copied()
+            .collect();
+
+        for tx_id in ancestors {
+            if !self.is_preferred(&tx_id)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// `reply_hints` returns the `ReplyHints` of a `Reply` to a query of a `Transaction`:
+    /// its confidence and the preferred `Transaction` of its conflict set, when known.
+    pub fn reply_hints(&self, tx_id: &Digest) -> ReplyHints {
+        let confidence = self.state.get_transaction_confidence(tx_id);
+        let preferred = self
+            .get_transaction_conflict_set(tx_id)
+            .ok()
+            .and_then(|cs| cs.preferred);
+
+        ReplyHints {
+            confidence,
+            preferred,
+        }
+    }
+
     /// `is_accepted` returns if a `Transaction` is accepted.
     /// The name of the function in the Avalanche paper is "IsAccepted".
     pub fn is_accepted(&self, tx_id: &Digest) -> Result<bool> {
@@ -372,9 +1127,9 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
             return Ok(true);
         }
 
-        match Transaction::get(&*self.pool.lock().unwrap(), self.stage, tx_id) {
+        match Transaction::get(&*self.pool.read().unwrap(), self.stage, tx_id) {
             Ok(tx) => {
-                tx.validate()?;
+                self.validate_transaction(&tx)?;
 
                 let ancestors: BTreeSet<Digest> = tx
                     .ancestors()?
@@ -398,7 +1153,7 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
             Err(ModelsError::NotFound) => {
                 // if it was stored in the store it was
                 // accepted (chit = 1)
-                let found = Transaction::lookup(&*self.store.lock().unwrap(), self.stage, tx_id)?;
+                let found = Transaction::lookup(&*self.store.read().unwrap(), self.stage, tx_id)?;
                 if found {
                     return Ok(true);
                 }
@@ -429,50 +1184,2240 @@ impl<S: Store, P: Store> ProtocolState<S, P> {
         Ok(false)
     }
 
-    /// `sample_nodes` samples a maximum of k nodes from the store.
+    /// `sample_size` returns the number of nodes sampled in a query: the
+    /// configured k or, in adaptive mode, the one adjusted to the known nodes
+    /// and to the observed failures and latency.
+    pub fn sample_size(&self) -> u32 {
+        self.adaptive.k(self.state.known_nodes.len())
+    }
+
+    /// `threshold` returns the number of positive chits needed by a query
+    /// of the current sample size: the configured alpha or, in adaptive mode,
+    /// the one adjusted to the sample size and to the observed failures.
+    pub fn threshold(&self) -> u32 {
+        self.adaptive.alpha(self.sample_size())
+    }
+
+    /// `pool_occupancy` returns the `StoreOccupancy` of the pool, read from its
+    /// counters.
+    pub fn pool_occupancy(&self) -> StoreOccupancy {
+        StoreOccupancy::new(&*self.pool.read().unwrap())
+    }
+
+    /// `random_fetch_count` returns the number of `Transaction`s to fetch randomly
+    /// in place of a requested count. The count decreases linearly between the
+    /// soft and the hard limits of the pool occupancy, and is zero above.
+    pub fn random_fetch_count(&self, count: u32) -> u32 {
+        let percent = self.pool_occupancy().percent();
+
+        if percent < Self::POOL_SOFT_LIMIT {
+            return count;
+        }
+
+        if percent >= Self::POOL_HARD_LIMIT {
+            return 0;
+        }
+
+        let headroom = u64::from(Self::POOL_HARD_LIMIT - percent);
+        let range = u64::from(Self::POOL_HARD_LIMIT - Self::POOL_SOFT_LIMIT);
+
+        ((u64::from(count) * headroom / range) as u32).max(count.min(1))
+    }
+
+    /// `sample_nodes` samples a maximum of k nodes from the outbound peers or,
+    /// when they are less than k, from the store, skipping the banned nodes.
+    /// With a latency bias, latency_bias * k candidates are sampled and the k
+    /// with the lowest latency are kept. In adaptive mode k is the `sample_size`.
+    /// In work sampling mode the candidates are drawn with a probability
+    /// proportional to the mining work submitted by the nodes. The nodes not
+    /// advertising all the configured sample services are skipped.
     pub fn sample_nodes(&mut self) -> Result<BTreeSet<Node>> {
         self.config.populate();
-        let count = self.config.k.unwrap();
-        Node::sample(&*self.store.lock().unwrap(), self.stage, None, None, count)
-            .map_err(|e| e.into())
+        let count = self.sample_size();
+        let services = self.sample_services()?;
+        let candidates = count.saturating_mul(self.config.latency_bias.unwrap());
+
+        let weighted = self.config.sampling.as_ref().unwrap() == "work";
+
+        let drawn = if weighted {
+            Self::MAX_WEIGHTED_CANDIDATES.max(candidates)
+        } else {
+            candidates
+        };
+
+        let nodes = if self.connections.len() >= count as usize {
+            self.connections
+                .sample(&*self.store.read().unwrap(), self.stage, drawn)?
+        } else {
+            Node::sample(&*self.store.read().unwrap(), self.stage, None, None, drawn)?
+        };
+
+        let mut sampled = Vec::new();
+
+        for node in nodes {
+            if node.has_services(&services) && !self.is_banned(&node.address)? {
+                sampled.push(node);
+            }
+        }
+
+        if weighted {
+            sampled = Self::weighted_sample(sampled, candidates as usize)?;
+        }
+
+        // NB: the nodes never measured come first, so that their latency gets measured.
+        sampled.sort_by_key(|node| node.latency.map_or(0, |latency| latency + 1));
+
+        Ok(sampled.into_iter().take(count as usize).collect())
     }
 
-    /// `random_node` returns a random node.
-    pub fn random_node(&self) -> Result<Node> {
-        let nodes = Node::sample(&*self.store.lock().unwrap(), self.stage, None, None, 1)?;
+    /// `weighted_sample` draws without replacement a number of nodes with a
+    /// probability proportional to their work. The nodes without work keep a
+    /// unit weight, so that they can still be sampled.
+    fn weighted_sample(mut nodes: Vec<Node>, count: usize) -> Result<Vec<Node>> {
+        // NB: the weights are capped so that their sum never overflows
+        let max_weight = u64::max_value() / (nodes.len() as u64 + 1);
+        let mut weights: Vec<u64> = nodes
+            .iter()
+            .map(|node| node.work.min(max_weight - 1) + 1)
+            .collect();
 
-        if nodes.len() != 1 {
-            let err = Error::InvalidLength;
-            return Err(err);
+        let mut sampled = Vec::new();
+
+        while sampled.len() < count && !nodes.is_empty() {
+            let total: u64 = weights.iter().sum();
+            let mut target = Random::u64_range(0, total)?;
+            let mut idx = 0;
+
+            while target >= weights[idx] {
+                target -= weights[idx];
+                idx += 1;
+            }
+
+            weights.swap_remove(idx);
+            sampled.push(nodes.swap_remove(idx));
         }
 
-        if let Some(node) = nodes.iter().next().cloned() {
-            Ok(node)
-        } else {
-            let err = Error::InvalidLength;
-            Err(err)
+        Ok(sampled)
+    }
+
+    /// `get_transaction` returns a `Transaction` from the pool or, if missing,
+    /// from the store.
+    pub fn get_transaction(&self, tx_id: &Digest) -> Result<Transaction> {
+        match Transaction::get(&*self.pool.read().unwrap(), self.stage, tx_id) {
+            Ok(tx) => Ok(tx),
+            Err(ModelsError::NotFound) => {
+                Transaction::get(&*self.store.read().unwrap(), self.stage, tx_id)
+                    .map_err(|e| e.into())
+            }
+            Err(err) => Err(err.into()),
         }
     }
 
-    /// `clear_state` clears the state of the `ProtocolState`.
-    pub fn clear_state(&mut self) {
-        self.state.clear()
+    /// `get_transaction_proof` returns the `TransactionProof` of a `Transaction`,
+    /// following its closest ancestors down to the eve `Transaction`.
+    pub fn get_transaction_proof(&self, tx_id: &Digest) -> Result<TransactionProof> {
+        let mut transaction = self.get_transaction(tx_id)?;
+        let mut path = Vec::new();
+
+        while !transaction.is_eve()? {
+            if path.len() + 1 >= TransactionProof::MAX_PATH_LEN {
+                let err = Error::InvalidLength;
+                return Err(err);
+            }
+
+            let mut closest: Option<Transaction> = None;
+
+            for ancestor_id in transaction.ancestors()? {
+                let ancestor = self.get_transaction(&ancestor_id)?;
+
+                if closest
+                    .as_ref()
+                    .map(|closest| ancestor.distance < closest.distance)
+                    .unwrap_or(true)
+                {
+                    closest = Some(ancestor);
+                }
+            }
+
+            let ancestor = closest.ok_or(Error::NotFound)?;
+            path.push(transaction);
+            transaction = ancestor;
+        }
+
+        if transaction.id != self.state.eve_transaction_id {
+            let err = Error::InvalidTransaction;
+            return Err(err);
+        }
+
+        path.push(transaction);
+
+        let confidence = self.calc_confidence(tx_id).unwrap_or(0);
+        let accepted = self.is_accepted(tx_id)?;
+
+        TransactionProof::new(&path, confidence, accepted).map_err(|e| e.into())
     }
 
-    /// `clear` clears the state and stores of the `ProtocolState`.
-    pub fn clear(&mut self) -> Result<()> {
-        self.clear_state();
-        self.pool.lock().unwrap().clear()?;
-        self.store.lock().unwrap().clear()?;
+    /// `apply_to_ledger` applies an accepted `Transaction` to the ledger,
+    /// marking the outputs it spends as spent and recording its issuance.
+    pub fn apply_to_ledger(&self, transaction: &Transaction) -> Result<()> {
+        let ancestors = self.get_ancestors(transaction)?;
+        let issued = self.ancestry_issuance(transaction, &ancestors)?;
+
+        let mut store = self.store.write().unwrap();
+
+        utxo::spend_outputs(&mut *store, self.stage, transaction)?;
+        emission::record_issuance(&mut *store, self.stage, transaction, issued)?;
+        ledger::apply_transaction(&mut *store, self.stage, transaction)
+    }
+
+    /// `get_issuance` returns the `Issuance` of the accepted `Transaction`s.
+    pub fn get_issuance(&self) -> Result<Issuance> {
+        emission::get_issuance(&*self.store.read().unwrap(), self.stage).map_err(|e| e.into())
+    }
+
+    /// `verify_spends` verifies that the `Input`s of a `Transaction` spend existing
+    /// outputs not already spent by other accepted `Transaction`s.
+    pub fn verify_spends(&self, transaction: &Transaction) -> Result<()> {
+        utxo::verify_spends(
+            &*self.store.read().unwrap(),
+            &*self.pool.read().unwrap(),
+            self.stage,
+            transaction,
+        )
+    }
+
+    /// `validate_unlocked_transaction` validates that the `Output`s spent by a
+    /// `Transaction` are not locked at the time of the `Transaction` by the locktimes
+    /// of their `Transaction`s. The spent `Transaction`s are looked up in the store
+    /// or in the pool, and are not checked if not known yet.
+    pub fn validate_unlocked_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let store = self.store.read().unwrap();
+        let pool = self.pool.read().unwrap();
+
+        for input in transaction.inputs.values() {
+            let prev_id = match input.account.transaction_id {
+                Some(prev_id) => prev_id,
+                None => continue,
+            };
+
+            let prev = if Transaction::lookup(&*store, self.stage, &prev_id)? {
+                Transaction::get(&*store, self.stage, &prev_id)?
+            } else if Transaction::lookup(&*pool, self.stage, &prev_id)? {
+                Transaction::get(&*pool, self.stage, &prev_id)?
+            } else {
+                continue;
+            };
+
+            if prev.is_locked(transaction.time) {
+                let err = Error::LockedAccount;
+                return Err(err);
+            }
+        }
 
         Ok(())
     }
 
-    /// `validate` validates the `ProtocolState`.
-    pub fn validate(&self) -> Result<()> {
-        self.config.validate()?;
-        self.state.validate()?;
+    /// `validate_staked_transaction` validates the spends of a `Transaction` against
+    /// the `Stake`s carried by the `Transaction`s in the store. The pool only knows
+    /// the `Stake`s of the pending `Transaction`s, so they are checked on receipt.
+    pub fn validate_staked_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let store = self.store.read().unwrap();
+
+        if let Err(err) = transaction.validate_staked_spends(&*store, self.stage) {
+            let err = match err {
+                ModelsError::LockedBalance => Error::LockedBalance,
+                err => err.into(),
+            };
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// `validate_limited_transaction` validates a `Transaction` against the protocol
+    /// maximum size and maximum `Input`s and `Output`s counts. The limits are the
+    /// same on every node, while the configurable ones of the pool are checked only
+    /// in the mempool admission.
+    pub fn validate_limited_transaction(&self, transaction: &Transaction) -> Result<()> {
+        transaction
+            .validate_limits(
+                ConsensusConfig::MAX_TRANSACTION_SIZE,
+                ConsensusConfig::MAX_TRANSACTION_INPUTS,
+                ConsensusConfig::MAX_TRANSACTION_OUTPUTS,
+            )
+            .map_err(|e| e.into())
+    }
+
+    /// `validate_mature_transaction` validates that the `Coinbase` rewards spent
+    /// by a `Transaction` are mature: the `Transaction`s mining them have to be
+    /// accepted and to have reached the configured coinbase maturity confidence.
+    /// The ancestors of the `Transaction` have to be known.
+    pub fn validate_mature_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let maturity = u64::from(
+            self.config
+                .coinbase_maturity
+                .unwrap_or(ConsensusConfig::DEFAULT_COINBASE_MATURITY),
+        );
+
+        let ancestors = self.get_ancestors(transaction)?;
+
+        for tx_id in transaction.spent_rewards(&ancestors)? {
+            if !self.is_accepted(&tx_id)? || self.calc_confidence(&tx_id)? < maturity {
+                let err: Error = ModelsError::ImmatureCoinbase.into();
+                return Err(err);
+            }
+        }
 
         Ok(())
     }
+
+    /// `get_balance` returns the balance of an `Address`.
+    pub fn get_balance(&self, address: &Address) -> Result<u64> {
+        ledger::get_balance(&*self.store.read().unwrap(), self.stage, address)
+    }
+
+    /// `get_unspent_outputs` returns the unspent outputs of an `Address`.
+    pub fn get_unspent_outputs(&self, address: &Address) -> Result<Vec<UnspentOutput>> {
+        ledger::get_unspent_outputs(&*self.store.read().unwrap(), self.stage, address)
+    }
+
+    /// `get_history` returns a page of the history of an `Address`.
+    pub fn get_history(
+        &self,
+        address: &Address,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<AddressHistory> {
+        ledger::get_history(
+            &*self.store.read().unwrap(),
+            self.stage,
+            address,
+            count,
+            skip,
+        )
+    }
+
+    /// `get_telemetry` returns the `TxTelemetry` of a `Transaction`.
+    pub fn get_telemetry(&self, tx_id: &Digest) -> Result<TxTelemetry> {
+        TxTelemetry::get(&*self.store.read().unwrap(), self.stage, tx_id).map_err(|e| e.into())
+    }
+
+    /// `update_telemetry` updates the `TxTelemetry` of a `Transaction`, creating it
+    /// if the `Transaction` is seen for the first time.
+    fn update_telemetry<F: FnOnce(&mut TxTelemetry)>(&self, tx_id: &Digest, f: F) -> Result<()> {
+        let mut store = self.store.write().unwrap();
+
+        let mut telemetry = if TxTelemetry::lookup(&*store, self.stage, tx_id)? {
+            TxTelemetry::get(&*store, self.stage, tx_id)?
+        } else {
+            TxTelemetry::new(self.stage, *tx_id)
+        };
+
+        f(&mut telemetry);
+
+        TxTelemetry::insert(&mut *store, self.stage, &telemetry).map_err(|e| e.into())
+    }
+
+    /// `record_seen` records the first sight of a `Transaction` in its `TxTelemetry`.
+    pub fn record_seen(&self, tx_id: &Digest) -> Result<()> {
+        self.update_telemetry(tx_id, |_| {})
+    }
+
+    /// `record_query` records the outcome of a query of a `Transaction` in its `TxTelemetry`.
+    pub fn record_query(
+        &self,
+        tx_id: &Digest,
+        chit_sum: u32,
+        chit: bool,
+        confidence: u64,
+    ) -> Result<()> {
+        self.update_telemetry(tx_id, |telemetry| {
+            telemetry.record_query(chit_sum, chit, confidence)
+        })
+    }
+
+    /// `record_accepted` records the acceptance of a `Transaction` in its `TxTelemetry`.
+    pub fn record_accepted(&self, tx_id: &Digest) -> Result<()> {
+        self.update_telemetry(tx_id, |telemetry| telemetry.set_accepted())
+    }
+
+    /// `is_settled` returns if a `Transaction` has been accepted, or rejected by the
+    /// acceptance of a conflicting `Transaction`.
+    fn is_settled(&self, tx_id: &Digest) -> Result<bool> {
+        if Transaction::lookup(&*self.store.read().unwrap(), self.stage, tx_id)? {
+            return Ok(true);
+        }
+
+        if let Ok(cs) = self.get_transaction_conflict_set(tx_id) {
+            for conflict_id in cs.transactions.iter().filter(|id| *id != tx_id) {
+                if Transaction::lookup(&*self.store.read().unwrap(), self.stage, conflict_id)? {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// `requery_stalled` schedules a new query of the queried `Transaction`s neither
+    /// accepted nor rejected after the configured duration without progress, so that
+    /// they are queried again with fresh node samples. A `Transaction` still stalled
+    /// after the maximum number of re-queries is reported with a `TransactionStalled`
+    /// event. Returns the number of rescheduled `Transaction`s.
+    pub fn requery_stalled(&mut self) -> Result<u32> {
+        let mut config = self.config.clone();
+        config.populate();
+
+        let requery_after = config.requery_after.unwrap();
+        let max_requeries = config.max_requeries.unwrap();
+        let now = Timestamp::now();
+
+        let tx_ids: Vec<Digest> = self.state.queried_transactions.iter().copied().collect();
+
+        let mut requeried = 0;
+
+        for tx_id in tx_ids {
+            if !Transaction::lookup(&*self.pool.read().unwrap(), self.stage, &tx_id)?
+                || !TxTelemetry::lookup(&*self.store.read().unwrap(), self.stage, &tx_id)?
+                || self.is_settled(&tx_id)?
+            {
+                continue;
+            }
+
+            let telemetry = self.get_telemetry(&tx_id)?;
+
+            if !telemetry.is_stalled(now, requery_after) {
+                continue;
+            }
+
+            if telemetry.requeries >= max_requeries {
+                self.update_telemetry(&tx_id, |telemetry| telemetry.set_stalled())?;
+
+                let transaction = self.get_transaction(&tx_id)?;
+
+                self.emit(ProtocolEvent::TransactionStalled {
+                    tx_id,
+                    requeries: telemetry.requeries,
+                    addresses: transaction.addresses(),
+                });
+
+                continue;
+            }
+
+            self.mutate(ConsensusMutation::RemoveQueriedTransaction { tx_id })?;
+            self.update_telemetry(&tx_id, |telemetry| telemetry.record_requery())?;
+
+            requeried += 1;
+        }
+
+        Ok(requeried)
+    }
+
+    /// `trigger_watches` emits a `WatchTriggered` event for every watched `Address`
+    /// touched by an accepted `Transaction`, returning the number of triggered `Watch`es.
+    pub fn trigger_watches(&mut self, transaction: &Transaction) -> Result<u32> {
+        let watches = Watch::watched(
+            &*self.store.read().unwrap(),
+            self.stage,
+            &transaction.addresses(),
+        )?;
+
+        let count = watches.len() as u32;
+
+        for watch in watches {
+            self.emit(ProtocolEvent::WatchTriggered {
+                tx_id: transaction.id,
+                address: watch.address,
+                action: watch.action,
+            });
+        }
+
+        Ok(count)
+    }
+
+    /// `outbox_delay` returns the delay in seconds before the next send attempt
+    /// of an `Outbox` after a number of failed attempts, following the `RetryPolicy`.
+    fn outbox_delay(&self, attempts: u32) -> u64 {
+        let policy = self.config.retry_policy.clone().unwrap_or_default();
+        let delay = policy.delay(attempts);
+
+        // NB: the delay is in milliseconds, the `Outbox` times in seconds
+        (delay + 999) / 1000
+    }
+
+    /// `queue_outbox` queues a `ConsensusMessage` that failed to be sent in the
+    /// `Outbox`, or records the failure of a new attempt if already queued.
+    pub fn queue_outbox(&self, cons_msg: &ConsensusMessage, error: &str) -> Result<()> {
+        let id = cons_msg.digest()?;
+        let mut store = self.store.write().unwrap();
+
+        let outbox = if Outbox::lookup(&*store, self.stage, &id)? {
+            let mut outbox = Outbox::get(&*store, self.stage, &id)?;
+            let delay = self.outbox_delay(outbox.attempts);
+            outbox.record_failure(error, delay)?;
+            outbox
+        } else {
+            Outbox::new(cons_msg, error, self.outbox_delay(0))?
+        };
+
+        Outbox::insert(&mut *store, self.stage, &outbox).map_err(|e| e.into())
+    }
+
+    /// `due_outbox` returns the `Outbox` messages whose next send attempt is due.
+    pub fn due_outbox(&self) -> Result<Vec<Outbox>> {
+        let now = Timestamp::now();
+
+        let store = self.store.read().unwrap();
+        let outboxes = Outbox::query(&*store, self.stage, None, None, None, None)?;
+
+        let due = outboxes
+            .into_iter()
+            .filter(|outbox| outbox.is_due(now))
+            .collect();
+
+        Ok(due)
+    }
+
+    /// `remove_outbox` removes a message from the `Outbox`.
+    pub fn remove_outbox(&self, id: &Digest) -> Result<()> {
+        Outbox::remove(&mut *self.store.write().unwrap(), self.stage, id).map_err(|e| e.into())
+    }
+
+    /// `spill_inbox` spills an incoming `ConsensusMessage` to the `Inbox`, returning
+    /// false if the `Inbox` is full and the message has to be handled right away.
+    pub fn spill_inbox(&self, cons_msg: &ConsensusMessage) -> Result<bool> {
+        let max_spooled = self
+            .config
+            .max_spooled
+            .unwrap_or(ConsensusConfig::DEFAULT_MAX_SPOOLED);
+
+        let mut store = self.store.write().unwrap();
+
+        if Inbox::count(&*store, self.stage, None, None, None)? >= max_spooled {
+            return Ok(false);
+        }
+
+        let seq = Inbox::next_seq(&*store, self.stage)?;
+        let inbox = Inbox::new(seq, cons_msg)?;
+        Inbox::create(&mut *store, self.stage, &inbox)?;
+
+        Ok(true)
+    }
+
+    /// `drain_inbox` removes and returns up to a number of the oldest `Inbox` messages.
+    pub fn drain_inbox(&self, count: u32) -> Result<Vec<Inbox>> {
+        let mut store = self.store.write().unwrap();
+        let inboxes = Inbox::oldest(&*store, self.stage, count)?;
+
+        for inbox in inboxes.iter() {
+            Inbox::remove(&mut *store, self.stage, &inbox.seq)?;
+        }
+
+        Ok(inboxes)
+    }
+
+    /// `random_node` returns a random node.
+    pub fn random_node(&self) -> Result<Node> {
+        let nodes = Node::sample(&*self.store.read().unwrap(), self.stage, None, None, 1)?;
+
+        if nodes.len() != 1 {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if let Some(node) = nodes.iter().next().cloned() {
+            Ok(node)
+        } else {
+            let err = Error::InvalidLength;
+            Err(err)
+        }
+    }
+
+    /// `is_compact_peer` returns if the `Query`s and `Reply`s exchanged with a node
+    /// use the compact encoding, negotiated when both the nodes advertise it in
+    /// their handshakes. Unknown nodes use the full encoding.
+    pub fn is_compact_peer(&self, address: &[u8]) -> Result<bool> {
+        if !self.services.contains(&Service::CompactQuery) {
+            return Ok(false);
+        }
+
+        let node_id = Node::new(self.stage, address).id;
+
+        match Node::get(&*self.store.read().unwrap(), self.stage, &node_id) {
+            Ok(node) => Ok(node.has_service(Service::CompactQuery)),
+            Err(ModelsError::NotFound) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// `is_known_transaction` returns if a `Transaction` is in the pool or in the store.
+    pub fn is_known_transaction(&self, tx_id: &Digest) -> Result<bool> {
+        if Transaction::lookup(&*self.pool.read().unwrap(), self.stage, tx_id)? {
+            return Ok(true);
+        }
+
+        Transaction::lookup(&*self.store.read().unwrap(), self.stage, tx_id).map_err(|e| e.into())
+    }
+
+    /// `record_latency` records a round-trip time in milliseconds in the latency
+    /// of a stored node and in the adaptive parameters. Unknown nodes are skipped.
+    pub fn record_latency(&mut self, address: &[u8], rtt: u64) -> Result<()> {
+        self.adaptive.record_latency(rtt);
+
+        let node_id = Node::new(self.stage, address).id;
+        let mut store = self.store.write().unwrap();
+
+        let mut node = match Node::get(&*store, self.stage, &node_id) {
+            Ok(node) => node,
+            Err(ModelsError::NotFound) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        node.record_latency(rtt);
+
+        Node::update(&mut *store, self.stage, &node).map_err(|e| e.into())
+    }
+
+    /// `record_work` records a valid proof of a mining difficulty in the work of
+    /// a stored node. Unknown nodes are skipped.
+    pub fn record_work(&self, address: &[u8], difficulty: u64) -> Result<()> {
+        let node_id = Node::new(self.stage, address).id;
+        let mut store = self.store.write().unwrap();
+
+        let mut node = match Node::get(&*store, self.stage, &node_id) {
+            Ok(node) => node,
+            Err(ModelsError::NotFound) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        node.record_work(difficulty);
+
+        Node::update(&mut *store, self.stage, &node).map_err(|e| e.into())
+    }
+
+    /// `remove_node` removes a node from the store, the known nodes and the
+    /// outbound peers.
+    pub fn remove_node(&mut self, node_id: &Digest) -> Result<()> {
+        Node::remove(&mut *self.store.write().unwrap(), self.stage, node_id)?;
+
+        if self.connections.lookup_peer(node_id) {
+            self.connections.remove_peer(node_id)?;
+        }
+
+        if self.state.lookup_known_node(node_id) {
+            self.mutate(ConsensusMutation::RemoveKnownNode { node_id: *node_id })?;
+        }
+
+        Ok(())
+    }
+
+    /// `ban` bans a node address for a duration in seconds, dropping it from the
+    /// outbound peers and the known nodes. The node is kept in the store, but
+    /// it is not sampled and its messages are refused until the ban expires.
+    pub fn ban(&mut self, address: &[u8], duration: u64, reason: &str) -> Result<NodeBan> {
+        let ban = NodeBan::new(self.stage, address, duration, reason)?;
+
+        NodeBan::insert(&mut *self.store.write().unwrap(), self.stage, &ban)?;
+
+        self.forgive(&ban.id)?;
+
+        if self.connections.lookup_peer(&ban.id) {
+            self.connections.remove_peer(&ban.id)?;
+        }
+
+        if self.state.lookup_known_node(&ban.id) {
+            self.mutate(ConsensusMutation::RemoveKnownNode { node_id: ban.id })?;
+        }
+
+        Ok(ban)
+    }
+
+    /// `unban` removes the ban of a node address.
+    pub fn unban(&mut self, address: &[u8]) -> Result<()> {
+        let node_id = Node::new(self.stage, address).id;
+
+        if !NodeBan::lookup(&*self.store.read().unwrap(), self.stage, &node_id)? {
+            let err = Error::NotFound;
+            return Err(err);
+        }
+
+        self.forgive(&node_id)?;
+
+        NodeBan::remove(&mut *self.store.write().unwrap(), self.stage, &node_id)
+            .map_err(|e| e.into())
+    }
+
+    /// `is_banned` returns if a node address is currently banned.
+    pub fn is_banned(&self, address: &[u8]) -> Result<bool> {
+        let node_id = Node::new(self.stage, address).id;
+        let store = self.store.read().unwrap();
+
+        if !NodeBan::lookup(&*store, self.stage, &node_id)? {
+            return Ok(false);
+        }
+
+        let ban = NodeBan::get(&*store, self.stage, &node_id)?;
+
+        Ok(ban.is_active(Timestamp::now()))
+    }
+
+    /// `is_blacklisted` returns if a `Node` address or one of its advertised
+    /// addresses is blacklisted, reloading the `Blacklist` file if modified.
+    pub fn is_blacklisted(&self, node: &Node) -> bool {
+        let mut blacklist = self.blacklist.lock().unwrap();
+
+        // NB: an unreadable or invalid file keeps the previous ranges
+        let _ = blacklist.reload();
+
+        blacklist.contains_address(&node.address)
+            || node
+                .addresses
+                .iter()
+                .any(|address| blacklist.contains_net_address(address))
+    }
+
+    /// `bans` returns the active `NodeBan`s.
+    pub fn bans(&self) -> Result<BTreeSet<NodeBan>> {
+        let now = Timestamp::now();
+
+        let bans = NodeBan::query(
+            &*self.store.read().unwrap(),
+            self.stage,
+            None,
+            None,
+            None,
+            None,
+        )?
+        .into_iter()
+        .filter(|ban| ban.is_active(now))
+        .collect();
+
+        Ok(bans)
+    }
+
+    /// `excluded_nodes` returns the ids of the banned and greylisted nodes,
+    /// which are not dialed by the connection manager.
+    pub fn excluded_nodes(&self) -> Result<BTreeSet<Digest>> {
+        let mut excluded: BTreeSet<Digest> = self.greylist.nodes().copied().collect();
+
+        for ban in self.bans()? {
+            excluded.insert(ban.id);
+        }
+
+        Ok(excluded)
+    }
+
+    /// `record_invalid` records an invalid message sent by a node address in
+    /// the `Greylist`, banning the node when it reaches the threshold.
+    /// It returns if the node has been banned.
+    pub fn record_invalid(&mut self, address: &[u8], reason: &str) -> Result<bool> {
+        let node_id = Node::new(self.stage, address).id;
+
+        if !self.greylist.strike(&node_id) {
+            self.save_reputation(address)?;
+            return Ok(false);
+        }
+
+        let mut reason = reason.to_owned();
+        while reason.len() > NodeBan::MAX_REASON_LEN {
+            reason.pop();
+        }
+
+        self.ban(address, Greylist::BAN_DURATION, &reason)?;
+
+        Ok(true)
+    }
+
+    /// `save_reputation` saves in the store the `Greylist` strikes of a node address.
+    fn save_reputation(&self, address: &[u8]) -> Result<()> {
+        let node_id = Node::new(self.stage, address).id;
+        let strikes = self.greylist.strikes(&node_id);
+        let mut store = self.store.write().unwrap();
+
+        let reputation = match NodeReputation::get(&*store, self.stage, &node_id) {
+            Ok(mut reputation) => {
+                reputation.strike(strikes);
+                reputation
+            }
+            Err(ModelsError::NotFound) => NodeReputation::new(self.stage, address, strikes),
+            Err(err) => return Err(err.into()),
+        };
+
+        NodeReputation::insert(&mut *store, self.stage, &reputation).map_err(|e| e.into())
+    }
+
+    /// `forgive` removes the strikes of a node from the `Greylist` and the store.
+    fn forgive(&mut self, node_id: &Digest) -> Result<()> {
+        self.greylist.remove(node_id);
+
+        let mut store = self.store.write().unwrap();
+
+        if NodeReputation::lookup(&*store, self.stage, node_id)? {
+            NodeReputation::remove(&mut *store, self.stage, node_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// `load_reputations` restores the `Greylist` from the `NodeReputation`s in the
+    /// store, skipping the stale ones.
+    pub fn load_reputations(&mut self) -> Result<()> {
+        let now = Timestamp::now();
+
+        let reputations = NodeReputation::query(
+            &*self.store.read().unwrap(),
+            self.stage,
+            None,
+            None,
+            None,
+            None,
+        )?;
+
+        for reputation in reputations {
+            if !reputation.is_stale(now, Greylist::STRIKES_TTL) {
+                self.greylist.restore(&reputation.id, reputation.strikes);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `cleanup_reputations` removes from the store the expired `NodeBan`s and
+    /// the stale `NodeReputation`s, dropping the latter from the `Greylist`,
+    /// and returns the number of removed items.
+    pub fn cleanup_reputations(&mut self) -> Result<u32> {
+        let now = Timestamp::now();
+        let mut store = self.store.write().unwrap();
+
+        let bans = NodeBan::count(&*store, self.stage, None, None, None)?;
+        NodeBan::cleanup(&mut *store, self.stage, Some(now))?;
+        let mut removed = bans - NodeBan::count(&*store, self.stage, None, None, None)?;
+
+        let stale: BTreeSet<Digest> =
+            NodeReputation::query(&*store, self.stage, None, None, None, None)?
+                .into_iter()
+                .filter(|reputation| reputation.is_stale(now, Greylist::STRIKES_TTL))
+                .map(|reputation| reputation.id)
+                .collect();
+
+        // NB: the store backends do not implement the batch removals
+        for node_id in stale.iter() {
+            NodeReputation::remove(&mut *store, self.stage, node_id)?;
+            self.greylist.remove(node_id);
+        }
+
+        removed += stale.len() as u32;
+
+        Ok(removed)
+    }
+
+    /// `cleanup_expired_transactions` removes from the consensus the `Transaction`s
+    /// that expired before being accepted, reporting them as rejected and
+    /// returning their number.
+    pub fn cleanup_expired_transactions(&mut self) -> Result<u32> {
+        let now = Timestamp::now();
+
+        let (from, to) = <Transaction as Storable<P>>::stage_range(self.stage);
+        let mut expired = Vec::new();
+
+        for (_, value) in self.pool.read().unwrap().iter(Some(&from), Some(&to))? {
+            let tx = Transaction::from_bytes(&value)?;
+            if tx.is_expired(now) {
+                expired.push(tx);
+            }
+        }
+
+        let mut removed = 0;
+
+        for tx in expired {
+            // NB: an expired transaction still needed by the history of a pooled one is kept
+            if Transaction::lookup(&*self.store.read().unwrap(), self.stage, &tx.id)?
+                || self.is_awaited_ancestor(&tx.id)?
+            {
+                continue;
+            }
+
+            self.forget_transaction(&tx)?;
+
+            self.emit(ProtocolEvent::TransactionRejected {
+                tx_id: tx.id,
+                reason: format!("{}", Error::ExpiredTransaction),
+                addresses: tx.addresses(),
+            });
+
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// `forget_transaction` removes a pooled `Transaction` from the consensus: from
+    /// the pool, from the `ConsensusState`, journaling the removal, from the queue
+    /// and from the conflict sets of its output addresses.
+    pub fn forget_transaction(&mut self, transaction: &Transaction) -> Result<()> {
+        let tx_id = transaction.id;
+
+        if self.state.lookup_known_transaction(&tx_id) {
+            self.mutate(ConsensusMutation::RemoveKnownTransaction { tx_id })?;
+        }
+
+        self.queue.remove(&tx_id);
+        self.work.remove(&tx_id);
+
+        let addresses: BTreeSet<Address> = transaction
+            .outputs
+            .values()
+            .map(|out| out.address)
+            .collect();
+
+        let sets = self.get_conflict_sets(&addresses)?;
+        let mut pool = self.pool.write().unwrap();
+
+        for (address, mut cs) in sets {
+            if !cs.lookup_transaction(&tx_id) {
+                continue;
+            }
+
+            cs.remove_transaction(&tx_id)?;
+
+            // NB: the preference and the last of the set restart from a remaining transaction
+            match cs.transactions.iter().next().copied() {
+                Some(remaining_id) => {
+                    if cs.preferred.is_none() {
+                        cs.preferred = Some(remaining_id);
+                    }
+
+                    if cs.last.is_none() {
+                        cs.last = cs.preferred;
+                        cs.count = 0;
+                    }
+
+                    ConflictSet::update(&mut *pool, self.stage, &cs)?;
+                }
+                None => ConflictSet::remove(&mut *pool, self.stage, &address)?,
+            }
+        }
+
+        Transaction::remove(&mut *pool, self.stage, &tx_id).map_err(|e| e.into())
+    }
+
+    /// `is_awaited_ancestor` returns if a `Transaction` is an ancestor awaited by an
+    /// orphan or by a `Transaction` in the pool, and so is needed to complete their
+    /// history.
+    pub fn is_awaited_ancestor(&self, tx_id: &Digest) -> Result<bool> {
+        if self.orphans.is_awaited(tx_id) {
+            return Ok(true);
+        }
+
+        let count = Transaction::count_successors(&*self.pool.read().unwrap(), self.stage, tx_id)?;
+
+        Ok(count > 0)
+    }
+
+    /// `cleanup_expired` removes the expired items, `Transaction`s, orphans, `NodeBan`s and
+    /// `NodeReputation`s from the store and pool, returning the number of removed items.
+    pub fn cleanup_expired(&mut self) -> Result<u32> {
+        let mut removed = self.cleanup_expired_transactions()?;
+        removed += self.orphans.cleanup_expired(Timestamp::now());
+        removed += self.cleanup_reputations()?;
+        removed += self.store.write().unwrap().cleanup_expired()?;
+        removed += self.pool.write().unwrap().cleanup_expired()?;
+
+        Ok(removed)
+    }
+
+    /// `is_archive` returns if the `ProtocolState` node is an archive, never
+    /// pruning its store.
+    pub fn is_archive(&self) -> bool {
+        self.config
+            .archive
+            .unwrap_or(ConsensusConfig::DEFAULT_ARCHIVE)
+    }
+
+    /// `prune` prunes the deep `Transaction`s from the store, unless the
+    /// `ProtocolState` node is an archive, invalidating the confidence counters.
+    pub fn prune(&mut self) -> Result<PruneReport> {
+        if self.is_archive() {
+            return Ok(PruneReport::default());
+        }
+
+        let depth = self
+            .config
+            .prune_depth
+            .unwrap_or(ConsensusConfig::DEFAULT_PRUNE_DEPTH);
+
+        let report = prune::prune(&mut *self.store.write().unwrap(), self.stage, depth)?;
+
+        // NB: the pruned transactions are no more in the successors index
+        if report.pruned > 0 {
+            self.state.invalidate_counters();
+        }
+
+        Ok(report)
+    }
+
+    /// `clear_state` clears the state of the `ProtocolState`. The clearing of the
+    /// `ConsensusState` is journaled, so that it is not undone by a recovery.
+    pub fn clear_state(&mut self) -> Result<()> {
+        self.mutate(ConsensusMutation::Clear)?;
+        self.cache.lock().unwrap().clear();
+        self.queue.clear();
+        self.reply_tallies.clear();
+        self.replay_window.clear();
+        self.adaptive.clear();
+        self.orphans.clear();
+        self.connections.peers.clear();
+        self.greylist.clear();
+        self.work.clear();
+
+        Ok(())
+    }
+
+    /// `rebuild_state` rebuilds the `ConsensusState` of the `ProtocolState` from the
+    /// contents of the store and of the pool, as after the state has been cleared.
+    /// The known transactions are the ones in the pool and the accepted ones in the
+    /// store, the chits are set for the accepted transactions and for the ones with
+    /// a positive chit in their telemetry, the conflict sets are read from the pool
+    /// and the confidences are recalculated from the chits of the successors.
+    /// The transactions still to be accepted are queued again.
+    pub fn rebuild_state(&mut self) -> Result<()> {
+        let mut state = ConsensusState::new(
+            self.state.id,
+            self.stage,
+            &self.state.eve_account_address,
+            &self.state.eve_transaction_id,
+            &self.state.seed_nodes,
+        );
+
+        let (from, to) = <Transaction as Storable<S>>::stage_range(self.stage);
+        let mut accepted = BTreeSet::new();
+
+        for (_, value) in self.store.read().unwrap().iter(Some(&from), Some(&to))? {
+            let tx = Transaction::from_bytes(&value)?;
+
+            if tx.id != state.eve_transaction_id {
+                accepted.insert(tx.id);
+            }
+        }
+
+        let mut pending = BTreeMap::new();
+
+        for (_, value) in self.pool.read().unwrap().iter(Some(&from), Some(&to))? {
+            let tx = Transaction::from_bytes(&value)?;
+
+            if !accepted.contains(&tx.id) && tx.id != state.eve_transaction_id {
+                pending.insert(tx.id, tx);
+            }
+        }
+
+        for tx_id in accepted.iter().chain(pending.keys()) {
+            state.add_known_transaction(*tx_id);
+
+            let telemetry = {
+                let store = self.store.read().unwrap();
+
+                if TxTelemetry::lookup(&*store, self.stage, tx_id)? {
+                    Some(TxTelemetry::get(&*store, self.stage, tx_id)?)
+                } else {
+                    None
+                }
+            };
+
+            let chit = accepted.contains(tx_id)
+                || telemetry
+                    .as_ref()
+                    .map(|telemetry| telemetry.samples.iter().any(|sample| sample.chit))
+                    .unwrap_or(false);
+
+            state.set_transaction_chit(*tx_id, chit)?;
+
+            if telemetry
+                .map(|telemetry| telemetry.queries > 0)
+                .unwrap_or(false)
+            {
+                state.add_queried_transaction(*tx_id)?;
+            }
+        }
+
+        for cs in ConflictSet::query(
+            &*self.pool.read().unwrap(),
+            self.stage,
+            None,
+            None,
+            None,
+            None,
+        )? {
+            for tx_id in cs.transactions.iter() {
+                // NB: as in `upsert_conflict_sets`, the highest address is kept.
+                let highest = state
+                    .get_transaction_conflict_set(tx_id)
+                    .map_or(true, |address| address < cs.address);
+
+                if state.lookup_known_transaction(tx_id) && highest {
+                    state.set_transaction_conflict_set(*tx_id, cs.address)?;
+                }
+            }
+        }
+
+        for node in Node::query(
+            &*self.store.read().unwrap(),
+            self.stage,
+            None,
+            None,
+            None,
+            None,
+        )? {
+            state.add_known_node(node.id);
+        }
+
+        state.validate()?;
+        self.state = state;
+
+        let tx_ids: Vec<Digest> = self.state.known_transactions.iter().copied().collect();
+
+        for tx_id in tx_ids {
+            let confidence = self.calc_confidence(&tx_id)?;
+            self.state.set_transaction_confidence(tx_id, confidence)?;
+        }
+
+        self.queue.clear();
+
+        for tx in pending.values() {
+            self.queue.push(tx)?;
+        }
+
+        self.save()
+    }
+
+    /// `state_delta` returns the ids of the pending `Transaction`s in the pool,
+    /// whose `TransactionState`s can be fetched from a trusted node.
+    pub fn state_delta(&self) -> Result<BTreeSet<Digest>> {
+        let (from, to) = <Transaction as Storable<P>>::stage_range(self.stage);
+        let max_len = ConsensusMessage::MAX_STATE_LEN as usize;
+        let mut ids = BTreeSet::new();
+
+        for (_, value) in self.pool.read().unwrap().iter(Some(&from), Some(&to))? {
+            let tx = Transaction::from_bytes(&value)?;
+
+            if tx.id == self.state.eve_transaction_id
+                || Transaction::lookup(&*self.store.read().unwrap(), self.stage, &tx.id)?
+            {
+                continue;
+            }
+
+            ids.insert(tx.id);
+
+            if ids.len() == max_len {
+                break;
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// `transaction_states` returns the `TransactionState`s of the known `Transaction`s
+    /// among a set of ids.
+    pub fn transaction_states(&self, ids: &BTreeSet<Digest>) -> BTreeSet<TransactionState> {
+        ids.iter()
+            .filter_map(|tx_id| self.state.get_transaction_state(tx_id))
+            .collect()
+    }
+
+    /// `apply_transaction_states` applies the `TransactionState`s fetched from a trusted
+    /// node, returning the number of the applied ones. They are verified against the
+    /// contents of the stores: only the known `Transaction`s in the pool are updated,
+    /// while the accepted ones in the store keep their state.
+    pub fn apply_transaction_states(&mut self, states: &BTreeSet<TransactionState>) -> Result<u32> {
+        let mut applied = 0;
+
+        for tx_state in states.iter() {
+            let tx_id = tx_state.tx_id;
+
+            if !self.state.lookup_known_transaction(&tx_id)
+                || !Transaction::lookup(&*self.pool.read().unwrap(), self.stage, &tx_id)?
+                || Transaction::lookup(&*self.store.read().unwrap(), self.stage, &tx_id)?
+            {
+                continue;
+            }
+
+            for mutation in ConsensusState::transaction_state_mutations(tx_state) {
+                self.mutate(mutation)?;
+            }
+
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+
+    /// `clear` clears the state and stores of the `ProtocolState`.
+    pub fn clear(&mut self) -> Result<()> {
+        self.clear_state()?;
+        self.pool.write().unwrap().clear()?;
+        self.store.write().unwrap().clear()?;
+
+        Ok(())
+    }
+
+    /// `validate` validates the `ProtocolState`.
+    pub fn validate(&self) -> Result<()> {
+        self.config.validate()?;
+        self.state.validate()?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_cleanup_expired_transactions() {
+    use crate::network::check_transaction_policies;
+    use crate::testkit::TestNetwork;
+    use models::output::Output;
+
+    let network = TestNetwork::new(1).unwrap();
+    let node = network.node(0).unwrap();
+    let stage = node.state.lock().unwrap().stage;
+
+    let address = Address::random().unwrap();
+    let now = Timestamp::now().to_i64();
+
+    let live = network.transaction(&[address]).unwrap();
+
+    let mut expired = Transaction::new().unwrap();
+    expired.stage = stage;
+    expired.time = Timestamp::from_i64(now - 10).unwrap();
+    expired.add_output(&Output::new(&address, 0, &[])).unwrap();
+    expired
+        .set_expiry(Timestamp::from_i64(now - 5).unwrap())
+        .unwrap();
+
+    node.add_transaction(&expired).unwrap();
+    node.add_transaction(&live).unwrap();
+
+    {
+        let mut state = node.state.lock().unwrap();
+        assert!(state.queue.lookup(&expired.id));
+
+        let res = state.cleanup_expired_transactions();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 1);
+
+        assert!(!state.state.lookup_known_transaction(&expired.id));
+        assert!(state.state.lookup_known_transaction(&live.id));
+        assert!(!state.queue.lookup(&expired.id));
+
+        let pool = state.pool.read().unwrap();
+        assert!(!Transaction::lookup(&*pool, stage, &expired.id).unwrap());
+
+        let cs = ConflictSet::get(&*pool, stage, &address).unwrap();
+        assert!(!cs.lookup_transaction(&expired.id));
+        assert!(cs.validate().is_ok());
+        assert_eq!(cs.preferred, Some(live.id));
+
+        let recovered = ConsensusState::recover(&*state.store.read().unwrap(), stage).unwrap();
+        assert!(!recovered.lookup_known_transaction(&expired.id));
+        assert!(recovered.lookup_known_transaction(&live.id));
+    }
+
+    let res = check_transaction_policies(node.state.clone(), &expired);
+    match res {
+        Err(Error::ExpiredTransaction) => {}
+        _ => panic!("expected ExpiredTransaction"),
+    }
+}
+
+#[test]
+fn test_validate_unlocked_transaction() {
+    use crate::testkit::TestNetwork;
+    use models::transaction_builder::TransactionBuilder;
+
+    let (network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+    let stage = state.stage;
+
+    let mut account = network.genesis.eve_account.clone();
+    account.amount = 100;
+    account.transaction_id = Some(network.genesis.eve_transaction.id);
+
+    let signers = account.signers.clone();
+    let address = account.address();
+    let to = Address::random().unwrap();
+
+    let fund = |locktime: Option<Timestamp>| {
+        let mut builder = TransactionBuilder::new(stage)
+            .and_then(|builder| builder.spend_from(&account, 100))
+            .unwrap();
+
+        if let Some(locktime) = locktime {
+            builder = builder.with_locktime(locktime).unwrap();
+        }
+
+        builder.with_change(&address).build().unwrap()
+    };
+
+    // NB: the spender does not carry the locktime in the account
+    let spend = |tx_id: Digest| {
+        let funded = Account::new(stage, &signers, 100, Some(tx_id)).unwrap();
+
+        TransactionBuilder::new(stage)
+            .and_then(|builder| builder.spend_from(&funded, 100))
+            .and_then(|builder| builder.pay_to(&to, 100))
+            .and_then(|builder| builder.build())
+            .unwrap()
+    };
+
+    let locking = fund(Some(Timestamp::now().after(1_000)));
+    let unlocked = fund(None);
+
+    let transaction = spend(locking.id);
+
+    // NB: the spent transactions not known yet are checked once known
+    let res = state.validate_unlocked_transaction(&transaction);
+    assert!(res.is_ok());
+
+    Transaction::insert(&mut *state.pool.write().unwrap(), stage, &locking).unwrap();
+
+    let res = state.validate_unlocked_transaction(&transaction);
+    match res {
+        Err(Error::LockedAccount) => {}
+        _ => panic!("expected LockedAccount"),
+    }
+
+    Transaction::insert(&mut *state.store.write().unwrap(), stage, &unlocked).unwrap();
+
+    let res = state.validate_unlocked_transaction(&spend(unlocked.id));
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_validate_limited_transaction() {
+    use crate::network::check_transaction_policies;
+    use crate::testkit::TestNetwork;
+    use models::output::Output;
+
+    let (network, state) = TestNetwork::fixture(1).unwrap();
+
+    let addresses = [Address::random().unwrap(), Address::random().unwrap()];
+    let mut tx = network.transaction(&addresses).unwrap();
+
+    let res = state.lock().unwrap().validate_limited_transaction(&tx);
+    assert!(res.is_ok());
+
+    let res = check_transaction_policies(state.clone(), &tx);
+    assert!(res.is_ok());
+
+    let config = PoolConfig::new(None, None, None, None, None, None, None, Some(1)).unwrap();
+    state.lock().unwrap().set_pool_policy(&config).unwrap();
+
+    // NB: the configured limits are a mempool policy, and not a consensus rule
+    let res = check_transaction_policies(state.clone(), &tx);
+    assert!(res.is_err());
+
+    let res = state.lock().unwrap().validate_limited_transaction(&tx);
+    assert!(res.is_ok());
+
+    while tx.outputs.len() <= ConsensusConfig::MAX_TRANSACTION_OUTPUTS as usize {
+        let output = Output::new(&Address::random().unwrap(), 0, &[]);
+        tx.outputs.insert(output.address, output);
+    }
+
+    tx.update_id().unwrap();
+
+    let res = state.lock().unwrap().validate_limited_transaction(&tx);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_rebuild_state() {
+    use crate::testkit::TestNetwork;
+    use store::backend::BTreeStore;
+
+    let (network, state) = TestNetwork::fixture(2).unwrap();
+    let mut state = state.lock().unwrap();
+    let stage = state.stage;
+
+    let pending = network.transaction(&[]).unwrap();
+    let accepted = network.transaction(&[]).unwrap();
+
+    for tx in [&pending, &accepted].iter() {
+        let key = <Transaction as Storable<BTreeStore>>::key_to_bytes(stage, &tx.id).unwrap();
+        let value = tx.to_bytes().unwrap();
+
+        state
+            .pool
+            .write()
+            .unwrap()
+            .insert(&key, &value, None)
+            .unwrap();
+
+        if tx.id == accepted.id {
+            state
+                .store
+                .write()
+                .unwrap()
+                .insert(&key, &value, None)
+                .unwrap();
+        }
+    }
+
+    let res = state.mutate(ConsensusMutation::AddKnownTransaction { tx_id: pending.id });
+    assert!(res.is_ok());
+
+    let res = state.clear_state();
+    assert!(res.is_ok());
+    assert!(state.state.known_transactions.is_empty());
+
+    let recovered = ConsensusState::recover(&*state.store.read().unwrap(), stage).unwrap();
+    assert!(recovered.known_transactions.is_empty());
+
+    let res = state.rebuild_state();
+    assert!(res.is_ok());
+
+    assert!(state.state.lookup_known_transaction(&pending.id));
+    assert!(state.state.lookup_known_transaction(&accepted.id));
+    assert!(!state
+        .state
+        .lookup_known_transaction(&state.state.eve_transaction_id));
+    assert_eq!(state.state.get_transaction_chit(&pending.id), Some(false));
+    assert_eq!(state.state.get_transaction_chit(&accepted.id), Some(true));
+    assert_eq!(state.state.get_transaction_confidence(&pending.id), Some(0));
+    assert!(state.queue.lookup(&pending.id));
+    assert!(!state.queue.lookup(&accepted.id));
+    assert_eq!(state.state.known_nodes.len(), 1);
+
+    let rebuilt = state.state.clone();
+
+    let res = state.rebuild_state();
+    assert!(res.is_ok());
+    assert_eq!(state.state.known_transactions, rebuilt.known_transactions);
+    assert_eq!(state.state.transaction_chit, rebuilt.transaction_chit);
+    assert_eq!(
+        state.state.transaction_confidence,
+        rebuilt.transaction_confidence
+    );
+}
+
+#[test]
+fn test_journal_snapshots() {
+    use crate::testkit::{TestNetwork, TestState};
+
+    let (_network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+    let stage = state.stage;
+    let id = state.state.id;
+
+    let tx_id = Digest::random().unwrap();
+    let count = TestState::SNAPSHOT_PERIOD - state.journal_seq - 1;
+
+    for _ in 0..count {
+        let res = state.mutate(ConsensusMutation::AddKnownTransaction { tx_id });
+        assert!(res.is_ok());
+    }
+
+    assert_eq!(state.state.id, id);
+    assert_eq!(state.journal_seq, TestState::SNAPSHOT_PERIOD - 1);
+
+    let len = ConsensusState::journal_len(&*state.store.read().unwrap(), stage, id).unwrap();
+    assert_eq!(len, state.journal_seq);
+
+    let res = state.mutate(ConsensusMutation::AddKnownTransaction { tx_id });
+    assert!(res.is_ok());
+
+    assert_eq!(state.state.id, id + 1);
+    assert_eq!(state.journal_seq, 0);
+    assert!(state.state.lookup_known_transaction(&tx_id));
+
+    let len = ConsensusState::journal_len(&*state.store.read().unwrap(), stage, id).unwrap();
+    assert_eq!(len, 0);
+
+    let recovered = ConsensusState::recover(&*state.store.read().unwrap(), stage).unwrap();
+    assert_eq!(recovered.id, id + 1);
+    assert!(recovered.lookup_known_transaction(&tx_id));
+}
+
+#[test]
+fn test_conflict_set_partitions() {
+    use crate::testkit::TestNetwork;
+
+    let network = TestNetwork::new(1).unwrap();
+    let node = network.node(0).unwrap();
+    let stage = node.state.lock().unwrap().stage;
+
+    let conflicting = Address::random().unwrap();
+    let mut txs = Vec::new();
+
+    for i in 0..3 {
+        let address = if i < 2 {
+            conflicting
+        } else {
+            Address::random().unwrap()
+        };
+
+        let tx = network.transaction(&[address]).unwrap();
+
+        node.add_transaction(&tx).unwrap();
+        txs.push(tx);
+    }
+
+    let tx_ids: Vec<Digest> = txs.iter().map(|tx| tx.id).collect();
+
+    let state = node.state.lock().unwrap();
+
+    assert_eq!(
+        state.state.get_transaction_conflict_set(&tx_ids[0]),
+        Some(conflicting)
+    );
+
+    let partitions = state.state.partition_by_conflict_set(&tx_ids);
+    assert_eq!(
+        partitions,
+        vec![vec![tx_ids[0], tx_ids[1]], vec![tx_ids[2]]]
+    );
+
+    let recovered = ConsensusState::recover(&*state.store.read().unwrap(), stage).unwrap();
+    assert_eq!(recovered.partition_by_conflict_set(&tx_ids), partitions);
+}
+
+#[test]
+fn test_schedule_mining() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(2).unwrap();
+    let mut state = state.lock().unwrap();
+
+    assert!(!state.schedule_mining());
+    assert!(!state.scheduler.status().paused);
+
+    let mut config = state.config.clone();
+    config.mining_max_backlog = Some(1);
+    state.set_config(&config).unwrap();
+
+    for _ in 0..2 {
+        let transaction = network.transaction(&[]).unwrap();
+
+        state.queue.push(&transaction).unwrap();
+    }
+
+    assert!(state.is_behind());
+    assert!(state.schedule_mining());
+    assert!(state.scheduler.status().paused);
+
+    state.queue.clear();
+
+    assert!(!state.schedule_mining());
+    assert!(!state.scheduler.status().paused);
+}
+
+#[test]
+fn test_remote_work() {
+    use crate::testkit::TestNetwork;
+
+    let (_network, state) = TestNetwork::fixture(2).unwrap();
+    let mut state = state.lock().unwrap();
+
+    let res = state.next_work();
+    assert!(res.is_err());
+
+    let address = Address::random().unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = state.stage;
+    transaction.set_coinbase(&address, 1).unwrap();
+    transaction.update_id().unwrap();
+
+    let res = state.push_work(&transaction);
+    assert!(res.is_ok());
+
+    let work = state.next_work().unwrap();
+    assert_eq!(work.tx_id, transaction.id);
+
+    let proof = work.mine().unwrap();
+
+    let res = state.submit_work(&work.tx_id, proof.nonce.wrapping_add(1), proof.digest);
+    assert!(res.is_err());
+
+    let res = state.submit_work(&work.tx_id, proof.nonce, proof.digest);
+    assert!(res.is_ok());
+
+    let mined = res.unwrap();
+    assert!(mined.validate_mined(Some(work.difficulty)).is_ok());
+    assert!(state.work.is_empty());
+
+    let res = state.push_work(&mined);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_retarget_transaction() {
+    use crate::testkit::TestNetwork;
+    use models::input::Input;
+
+    let (network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+
+    let address = Address::random().unwrap();
+    let now = Timestamp::now();
+    let difficulty = 3;
+
+    let mut ancestor = Transaction::new().unwrap();
+    ancestor.stage = state.stage;
+    ancestor.time = Timestamp::from_i64(now.to_i64() - 15).unwrap();
+    ancestor.set_coinbase(&address, difficulty).unwrap();
+    ancestor.mine().unwrap();
+    ancestor.update_id().unwrap();
+
+    let stage = state.stage;
+    Transaction::create(&mut *state.pool.write().unwrap(), stage, &ancestor).unwrap();
+
+    let mut account = network.genesis.eve_account.clone();
+    account.transaction_id = Some(ancestor.id);
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = state.stage;
+    transaction.time = now;
+    transaction
+        .add_input(&Input::new(&account, 1, 0).unwrap())
+        .unwrap();
+    transaction.set_coinbase(&address, 1).unwrap();
+    transaction.update_id().unwrap();
+
+    let expected = state.expected_difficulty(&transaction).unwrap();
+    assert!(expected > difficulty);
+
+    let res = state.push_work(&transaction);
+    assert!(res.is_ok());
+
+    let work = state.next_work().unwrap();
+    assert_ne!(work.tx_id, transaction.id);
+    assert_eq!(work.difficulty, expected);
+
+    let res = state.retarget_transaction(&mut transaction);
+    assert!(res.is_ok());
+    assert_eq!(transaction.id, work.tx_id);
+    assert_eq!(transaction.coinbase.unwrap().difficulty, expected);
+
+    transaction.mine().unwrap();
+
+    let res = state.validate_mined_transaction(&transaction);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_outbox() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(2).unwrap();
+    let state = state.lock().unwrap();
+
+    let peer = Node::new(state.stage, &network.node(1).unwrap().address());
+    let cons_msg = ConsensusMessage::new_fetch_random_nodes(&state.address, &peer, 1).unwrap();
+    let id = cons_msg.digest().unwrap();
+
+    let res = state.queue_outbox(&cons_msg, "Connection refused");
+    assert!(res.is_ok());
+
+    let outbox = Outbox::get(&*state.store.read().unwrap(), state.stage, &id).unwrap();
+    assert_eq!(outbox.attempts, 1);
+    assert!(outbox.next_attempt > outbox.queued_at);
+
+    let res = state.due_outbox();
+    assert!(res.is_ok());
+
+    let res = state.queue_outbox(&cons_msg, "Connection refused");
+    assert!(res.is_ok());
+
+    let outbox = Outbox::get(&*state.store.read().unwrap(), state.stage, &id).unwrap();
+    assert_eq!(outbox.attempts, 2);
+    assert_eq!(outbox.message, cons_msg);
+
+    let res = state.remove_outbox(&id);
+    assert!(res.is_ok());
+
+    let res = Outbox::lookup(&*state.store.read().unwrap(), state.stage, &id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+}
+
+#[test]
+fn test_latency_sampling() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(4).unwrap();
+    let mut state = state.lock().unwrap();
+
+    let latencies = [(1, 50), (2, 10), (3, 30)];
+
+    for (idx, rtt) in latencies.iter() {
+        let address = network.node(*idx).unwrap().address();
+        let res = state.record_latency(&address, *rtt);
+        assert!(res.is_ok());
+
+        let node_id = Node::new(state.stage, &address).id;
+        let peer = Node::get(&*state.store.read().unwrap(), state.stage, &node_id).unwrap();
+        assert_eq!(peer.latency, Some(*rtt));
+
+        state.connections.add_peer(node_id).unwrap();
+    }
+
+    let mut config = state.config.clone();
+    config.k = Some(1);
+    config.alpha = Some(1);
+    config.latency_bias = Some(3);
+    state.set_config(&config).unwrap();
+
+    let sampled = state.sample_nodes().unwrap();
+    assert_eq!(sampled.len(), 1);
+
+    let fastest = network.node(2).unwrap().address();
+    assert_eq!(sampled.iter().next().unwrap().address, fastest);
+
+    let res = state.record_latency(b"unknown", 10);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_work_sampling() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(4).unwrap();
+    let mut state = state.lock().unwrap();
+
+    for idx in 1..4 {
+        let address = network.node(idx).unwrap().address();
+        let node_id = Node::new(state.stage, &address).id;
+        state.connections.add_peer(node_id).unwrap();
+    }
+
+    let miner = network.node(3).unwrap().address();
+    let res = state.record_work(&miner, 40);
+    assert!(res.is_ok());
+
+    let node_id = Node::new(state.stage, &miner).id;
+    let peer = Node::get(&*state.store.read().unwrap(), state.stage, &node_id).unwrap();
+    assert_eq!(peer.work, 1 << 40);
+
+    let mut config = state.config.clone();
+    config.k = Some(1);
+    config.alpha = Some(1);
+    config.sampling = Some("work".into());
+    state.set_config(&config).unwrap();
+
+    for _ in 0..10 {
+        let sampled = state.sample_nodes().unwrap();
+        assert_eq!(sampled.len(), 1);
+        assert_eq!(sampled.iter().next().unwrap().address, miner);
+    }
+
+    let res = state.record_work(b"unknown", 10);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_service_sampling() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(4).unwrap();
+    let mut state = state.lock().unwrap();
+
+    let mut services = BTreeSet::new();
+    services.insert(Service::Relay);
+
+    for idx in 1..4 {
+        let address = network.node(idx).unwrap().address();
+        let node_id = Node::new(state.stage, &address).id;
+        state.connections.add_peer(node_id).unwrap();
+
+        if idx == 2 {
+            let mut peer = Node::get(&*state.store.read().unwrap(), state.stage, &node_id).unwrap();
+            peer.set_services(&services);
+            let stage = state.stage;
+            Node::update(&mut *state.store.write().unwrap(), stage, &peer).unwrap();
+        }
+    }
+
+    let mut config = state.config.clone();
+    config.k = Some(3);
+    config.alpha = Some(1);
+    config.sample_services = Some(vec!["relay".into()]);
+    state.set_config(&config).unwrap();
+
+    let res = state.sample_services();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), services);
+
+    let sampled = state.sample_nodes().unwrap();
+    assert_eq!(sampled.len(), 1);
+
+    let relay = network.node(2).unwrap().address();
+    assert_eq!(sampled.iter().next().unwrap().address, relay);
+
+    config.sample_services = None;
+    state.set_config(&config).unwrap();
+
+    let sampled = state.sample_nodes().unwrap();
+    assert_eq!(sampled.len(), 3);
+}
+
+#[test]
+fn test_persistent_reputations() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(2).unwrap();
+    let mut state = state.lock().unwrap();
+
+    let address = network.node(1).unwrap().address();
+    let node_id = Node::new(state.stage, &address).id;
+
+    for _ in 0..2 {
+        let res = state.record_invalid(&address, "invalid message");
+        assert!(res.is_ok());
+        assert!(!res.unwrap());
+    }
+
+    let reputation =
+        NodeReputation::get(&*state.store.read().unwrap(), state.stage, &node_id).unwrap();
+    assert_eq!(reputation.strikes, 2);
+
+    state.greylist.clear();
+
+    let res = state.load_reputations();
+    assert!(res.is_ok());
+    assert_eq!(state.greylist.strikes(&node_id), 2);
+
+    let res = state.cleanup_reputations();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+
+    let res = state.ban(&address, Greylist::BAN_DURATION, "banned");
+    assert!(res.is_ok());
+    assert!(!state.greylist.lookup(&node_id));
+
+    let res = NodeReputation::lookup(&*state.store.read().unwrap(), state.stage, &node_id);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = state.is_banned(&address);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+}
+
+#[test]
+fn test_staked_transaction() {
+    use crate::testkit::TestNetwork;
+    use models::stake::Stake;
+    use models::transaction_builder::TransactionBuilder;
+
+    let (network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+    let stage = state.stage;
+
+    let mut account = network.genesis.eve_account.clone();
+    account.amount = 100;
+    account.transaction_id = Some(network.genesis.eve_transaction.id);
+
+    let address = account.address();
+    let to = Address::random().unwrap();
+
+    let spend = |amount: u64, stake: Option<&Stake>| {
+        let mut builder = TransactionBuilder::new(stage)
+            .and_then(|builder| builder.spend_from(&account, 100))
+            .and_then(|builder| builder.pay_to(&to, amount))
+            .map(|builder| builder.with_change(&address))
+            .unwrap();
+
+        if let Some(stake) = stake {
+            builder = builder.with_stake(stake).unwrap();
+        }
+
+        builder.build()
+    };
+
+    let stake = Stake::new(stage, &address, 50, 1_000).unwrap();
+
+    let res = spend(60, Some(&stake));
+    assert!(res.is_err());
+
+    let staking = spend(50, Some(&stake)).unwrap();
+
+    let res = state.validate_staked_transaction(&staking);
+    assert!(res.is_ok());
+
+    Transaction::insert(&mut *state.store.write().unwrap(), stage, &staking).unwrap();
+
+    let transaction = spend(60, None).unwrap();
+    let res = state.validate_staked_transaction(&transaction);
+    match res {
+        Err(Error::LockedBalance) => {}
+        _ => panic!("expected LockedBalance"),
+    }
+
+    let transaction = spend(50, None).unwrap();
+    let res = state.validate_staked_transaction(&transaction);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_issuance() {
+    use crate::testkit::TestNetwork;
+
+    let (network, state) = TestNetwork::fixture(1).unwrap();
+    let state = state.lock().unwrap();
+
+    let res = state.get_issuance();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().amount, 0);
+
+    let res = state.apply_to_ledger(&network.genesis.eve_transaction);
+    assert!(res.is_ok());
+    assert_eq!(state.get_issuance().unwrap().amount, 0);
+
+    let address = Address::random().unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = state.stage;
+    transaction.set_coinbase(&address, 1).unwrap();
+    transaction.mine().unwrap();
+
+    for _ in 0..2 {
+        let res = state.apply_to_ledger(&transaction);
+        assert!(res.is_ok());
+
+        let issuance = state.get_issuance().unwrap();
+        assert_eq!(issuance.amount, transaction.coinbase_amount());
+        assert_eq!(issuance.coinbases, 1);
+    }
+
+    let res = state.ancestry_issuance(&transaction, &[]);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), transaction.coinbase_amount());
+
+    let res = state.validate_mined_transaction(&transaction);
+    assert!(res.is_ok());
+
+    let mut far = transaction.clone();
+    far.distance += 1;
+
+    let res = state.validate_mined_transaction(&far);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_coinbase_maturity() {
+    use crate::testkit::TestNetwork;
+    use crypto::ecc::ed25519::PublicKey;
+    use models::input::Input;
+    use models::signer::Signer;
+    use models::signers::Signers;
+
+    let (_network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+    let stage = state.stage;
+
+    let mut config = state.config.clone();
+    config.coinbase_maturity = Some(1);
+    state.set_config(&config).unwrap();
+
+    let mut signers = Signers::new().unwrap();
+    signers.threshold = 1;
+    signers
+        .add(&Signer {
+            public_key: PublicKey::random().unwrap(),
+            weight: 1,
+        })
+        .unwrap();
+
+    let mut coinbase_tx = Transaction::new().unwrap();
+    coinbase_tx.stage = stage;
+    coinbase_tx.set_coinbase(&signers.address, 1).unwrap();
+    coinbase_tx.mine().unwrap();
+    coinbase_tx.update_id().unwrap();
+
+    let account = Account::new(stage, &signers, 0, Some(coinbase_tx.id)).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = stage;
+    transaction
+        .add_input(&Input::new(&account, 1, 0).unwrap())
+        .unwrap();
+    transaction.update_id().unwrap();
+
+    // the funding transaction is unknown
+    let res = state.validate_mature_transaction(&transaction);
+    assert!(res.is_err());
+
+    Transaction::create(&mut *state.store.write().unwrap(), stage, &coinbase_tx).unwrap();
+
+    // the funding transaction is accepted, but it has no confidence
+    let res = state.validate_mature_transaction(&transaction);
+    assert!(res.is_err());
+
+    Transaction::create(&mut *state.store.write().unwrap(), stage, &transaction).unwrap();
+    state.state.add_known_transaction(transaction.id);
+    state
+        .state
+        .set_transaction_chit(transaction.id, true)
+        .unwrap();
+
+    let res = state.validate_mature_transaction(&transaction);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn test_missing_ancestors() {
+    use crate::testkit::TestNetwork;
+    use models::input::Input;
+
+    let (network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+
+    let mut account = network.genesis.eve_account.clone();
+    account.transaction_id = Some(network.genesis.eve_transaction.id);
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = state.stage;
+
+    let input = Input::new(&account, 1, 0).unwrap();
+    transaction.inputs.insert(account.address(), input);
+
+    let res = state.missing_ancestors(&transaction);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_empty());
+
+    account.transaction_id = Some(Digest::random().unwrap());
+
+    let mut orphan = Transaction::new().unwrap();
+    orphan.stage = state.stage;
+
+    let input = Input::new(&account, 1, 0).unwrap();
+    orphan.inputs.insert(account.address(), input);
+
+    let missing = state.missing_ancestors(&orphan).unwrap();
+    assert_eq!(missing.len(), 1);
+    assert!(missing.contains(&account.transaction_id.unwrap()));
+
+    let to_fetch = state.orphans.insert(&orphan, &missing);
+    assert_eq!(to_fetch, missing);
+    assert!(state.orphans.lookup(&orphan.id));
+
+    let res = state.clear_state();
+    assert!(res.is_ok());
+    assert!(state.orphans.is_empty());
+}
+
+#[test]
+fn test_state_delta_sync() {
+    use crate::testkit::TestNetwork;
+    use store::backend::BTreeStore;
+
+    let network = TestNetwork::new(2).unwrap();
+    let stage = network.genesis.stage;
+
+    let pending = network.transaction(&[]).unwrap();
+    let accepted = network.transaction(&[]).unwrap();
+
+    for node in network.nodes.iter() {
+        let mut state = node.state.lock().unwrap();
+
+        for tx in [&pending, &accepted].iter() {
+            let key = <Transaction as Storable<BTreeStore>>::key_to_bytes(stage, &tx.id).unwrap();
+            let value = tx.to_bytes().unwrap();
+
+            state
+                .pool
+                .write()
+                .unwrap()
+                .insert(&key, &value, None)
+                .unwrap();
+
+            if tx.id == accepted.id {
+                state
+                    .store
+                    .write()
+                    .unwrap()
+                    .insert(&key, &value, None)
+                    .unwrap();
+            }
+        }
+
+        state.rebuild_state().unwrap();
+    }
+
+    let trusted = network.node(0).unwrap().state.lock().unwrap();
+    let mut replica = network.node(1).unwrap().state.lock().unwrap();
+
+    let mut trusted_states = BTreeSet::new();
+
+    for tx_id in [pending.id, accepted.id].iter() {
+        let mut tx_state = trusted.state.get_transaction_state(tx_id).unwrap();
+        tx_state.chit = !tx_state.chit;
+        tx_state.confidence = 2;
+        tx_state.queried = true;
+        trusted_states.insert(tx_state);
+    }
+
+    let delta = replica.state_delta().unwrap();
+    assert_eq!(delta.len(), 1);
+    assert!(delta.contains(&pending.id));
+
+    let states = trusted.transaction_states(&delta);
+    assert_eq!(states.len(), 1);
+
+    let res = replica.apply_transaction_states(&trusted_states);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let pending_state = replica.state.get_transaction_state(&pending.id).unwrap();
+    assert!(pending_state.chit);
+    assert_eq!(pending_state.confidence, 2);
+    assert!(pending_state.queried);
+
+    let accepted_state = replica.state.get_transaction_state(&accepted.id).unwrap();
+    assert!(accepted_state.chit);
+    assert!(!accepted_state.queried);
+}
+
+#[test]
+fn test_trigger_watches() {
+    use crate::testkit::TestNetwork;
+    use models::output::Output;
+    use models::watch::WatchAction;
+
+    let (_network, state) = TestNetwork::fixture(1).unwrap();
+    let mut state = state.lock().unwrap();
+    let stage = state.stage;
+    let receiver = state.subscribe();
+
+    let mut tx = Transaction::new().unwrap();
+    tx.stage = stage;
+
+    let output = Output::random(10).unwrap();
+    tx.add_output(&output).unwrap();
+    tx.update_id().unwrap();
+
+    assert_eq!(state.trigger_watches(&tx).unwrap(), 0);
+
+    let watch = Watch::new(stage, &output.address, &WatchAction::Event).unwrap();
+    Watch::create(&mut *state.store.write().unwrap(), stage, &watch).unwrap();
+
+    assert_eq!(state.trigger_watches(&tx).unwrap(), 1);
+
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(
+        event,
+        ProtocolEvent::WatchTriggered {
+            tx_id: tx.id,
+            address: output.address,
+            action: WatchAction::Event,
+        }
+    );
+}
+
+#[test]
+fn test_pool_pressure() {
+    use crate::testkit::TestNetwork;
+
+    let (_network, state) = TestNetwork::fixture(1).unwrap();
+    let state = state.lock().unwrap();
+
+    assert_eq!(state.random_fetch_count(10), 10);
+
+    let size = {
+        let mut pool = state.pool.write().unwrap();
+        pool.set_max_value_size(1 << 10);
+        pool.insert(&[0xff; 24], &[0; 1000], None).unwrap();
+        pool.size()
+    };
+
+    assert_eq!(
+        state.pool_occupancy().keys_count,
+        state.pool.read().unwrap().count(None, None, None).unwrap()
+    );
+
+    // NB: 80% of the pool is used, between the soft and the hard limits.
+    state
+        .pool
+        .write()
+        .unwrap()
+        .set_max_size(size * 5 / 4)
+        .unwrap();
+    assert_eq!(state.pool_occupancy().percent(), 80);
+    assert_eq!(state.random_fetch_count(10), 7);
+
+    state.pool.write().unwrap().set_max_size(size).unwrap();
+    assert_eq!(state.random_fetch_count(10), 0);
+}
+
+#[test]
+fn test_requery_stalled() {
+    use crate::testkit::{TestNetwork, TestState};
+
+    let network = TestNetwork::new(1).unwrap();
+    let node = network.node(0).unwrap();
+    let stage = node.state.lock().unwrap().stage;
+
+    let tx = network.transaction(&[]).unwrap();
+
+    node.add_transaction(&tx).unwrap();
+
+    let mut state = node.state.lock().unwrap();
+    let receiver = state.subscribe();
+
+    let mut config = state.config.clone();
+    config.requery_after = Some(10);
+    config.max_requeries = Some(2);
+    state.set_config(&config).unwrap();
+
+    state
+        .mutate(ConsensusMutation::AddQueriedTransaction { tx_id: tx.id })
+        .unwrap();
+    state.queue.remove(&tx.id);
+
+    // NB: the transaction was just seen, so it is not stalled yet.
+    assert_eq!(state.requery_stalled().unwrap(), 0);
+    assert!(state.state.lookup_queried_transaction(&tx.id));
+
+    // NB: moves the last progress of the transaction a minute in the past.
+    let stall = |state: &TestState| {
+        let past = Timestamp::from_i64(Timestamp::now().to_i64() - 60).unwrap();
+
+        let mut telemetry = state.get_telemetry(&tx.id).unwrap();
+        telemetry.first_seen = past;
+        telemetry.last_requery = telemetry.last_requery.map(|_| past);
+        TxTelemetry::update(&mut *state.store.write().unwrap(), stage, &telemetry).unwrap();
+    };
+
+    for requeries in 1..=2 {
+        stall(&state);
+
+        assert_eq!(state.requery_stalled().unwrap(), 1);
+        assert!(!state.state.lookup_queried_transaction(&tx.id));
+        assert_eq!(state.get_telemetry(&tx.id).unwrap().requeries, requeries);
+
+        state
+            .mutate(ConsensusMutation::AddQueriedTransaction { tx_id: tx.id })
+            .unwrap();
+    }
+
+    assert!(receiver.try_recv().is_err());
+
+    stall(&state);
+
+    assert_eq!(state.requery_stalled().unwrap(), 0);
+    assert!(state.state.lookup_queried_transaction(&tx.id));
+    assert!(state.get_telemetry(&tx.id).unwrap().is_given_up());
+
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(
+        event,
+        ProtocolEvent::TransactionStalled {
+            tx_id: tx.id,
+            requeries: 2,
+            addresses: tx.addresses(),
+        }
+    );
+
+    // NB: a given up transaction is reported only once.
+    assert_eq!(state.requery_stalled().unwrap(), 0);
+    assert!(receiver.try_recv().is_err());
 }
@@ -0,0 +1,469 @@
+//! # Testkit
+//!
+//! `testkit` contains an in-process network of protocol nodes used to test the consensus.
+//! The nodes keep their state in memory stores and talk through channel transports,
+//! while the consensus steps are advanced explicitly, one node at a time.
+
+use crate::error::Error;
+use crate::network::{handle_transaction, update_chit_sum};
+use crate::result::Result;
+use crate::state::ProtocolState;
+use config::pool::PoolConfig;
+use crypto::ecc::ed25519::SecretKey;
+use crypto::hash::Digest;
+use log::file::LogFile;
+use log::format::LogFormat;
+use log::level::LogLevel;
+use log::logger::Logger;
+use models::address::Address;
+use models::consensus_state::ConsensusMutation;
+use models::genesis::Genesis;
+use models::output::Output;
+use models::traits::Storable;
+use models::transaction::Transaction;
+use network::backend::ChannelNetwork;
+use network::traits::Network;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, RwLock};
+use store::backend::BTreeStore;
+
+/// `TestState` is the `ProtocolState` of a `TestNode`.
+pub type TestState = ProtocolState<BTreeStore, BTreeStore>;
+
+/// `TestNode` is a protocol node of a `TestNetwork`.
+pub struct TestNode {
+    pub state: Arc<Mutex<TestState>>,
+    pub network: Arc<Mutex<ChannelNetwork>>,
+    pub logger: Arc<Logger>,
+}
+
+impl TestNode {
+    /// `address` returns the `TestNode` address.
+    pub fn address(&self) -> Vec<u8> {
+        self.state.lock().unwrap().address.clone()
+    }
+
+    /// `submit` submits a `Transaction` to the `TestNode`.
+    pub fn submit(&self, transaction: &Transaction) -> Result<()> {
+        handle_transaction(
+            self.state.clone(),
+            self.network.clone(),
+            self.logger.clone(),
+            transaction,
+        )
+    }
+
+    /// `add_transaction` adds a `Transaction` to the `TestNode` like `submit`,
+    /// but without verifying its signatures, its mining and its spends, so that
+    /// unsigned `Transaction`s can be used to exercise the consensus.
+    pub fn add_transaction(&self, transaction: &Transaction) -> Result<()> {
+        let tx_id = transaction.id;
+        let mut state = self.state.lock().unwrap();
+
+        state.validate_transaction(transaction)?;
+
+        if state.state.lookup_known_transaction(&tx_id) {
+            return Ok(());
+        }
+
+        let stage = state.stage;
+        Transaction::create(&mut *state.pool.write().unwrap(), stage, transaction)?;
+
+        state.mutate(ConsensusMutation::AddKnownTransaction { tx_id })?;
+        state.queue.push(transaction)?;
+        state.add_successor(transaction)?;
+        state.record_seen(&tx_id)?;
+        state.upsert_conflict_sets(transaction)?;
+        state.mutate(ConsensusMutation::SetTransactionChit { tx_id, chit: false })?;
+        state.mutate(ConsensusMutation::SetTransactionConfidence {
+            tx_id,
+            confidence: 0,
+        })
+    }
+
+    /// `is_accepted` returns if a `Transaction` is accepted by the `TestNode`.
+    pub fn is_accepted(&self, tx_id: &Digest) -> Result<bool> {
+        self.state.lock().unwrap().is_accepted(tx_id)
+    }
+
+    /// `is_stored` returns if a `Transaction` has been accepted and moved to
+    /// the store of the `TestNode`.
+    pub fn is_stored(&self, tx_id: &Digest) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        let found = Transaction::lookup(&*state.store.read().unwrap(), state.stage, tx_id)?;
+        Ok(found)
+    }
+}
+
+/// `TestNetwork` is an in-process network of `TestNode`s sharing the same `Genesis`.
+pub struct TestNetwork {
+    pub genesis: Genesis,
+    pub secret_key: SecretKey,
+    pub nodes: Vec<TestNode>,
+}
+
+impl TestNetwork {
+    /// `new` creates a new `TestNetwork` of a number of `TestNode`s, all connected
+    /// to each other, from a random `Genesis` whose eve `Account` is owned by the
+    /// `TestNetwork` `SecretKey`.
+    pub fn new(count: usize) -> Result<TestNetwork> {
+        let secret_key = SecretKey::random()?;
+
+        let mut genesis = Genesis::random(&secret_key)?;
+        genesis.sign(&secret_key)?;
+
+        TestNetwork::from_genesis(&genesis, &secret_key, count)
+    }
+
+    /// `from_genesis` creates a new `TestNetwork` of a number of `TestNode`s
+    /// from a signed `Genesis`.
+    pub fn from_genesis(
+        genesis: &Genesis,
+        secret_key: &SecretKey,
+        count: usize,
+    ) -> Result<TestNetwork> {
+        if count == 0 {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        let mut networks = Vec::new();
+
+        for _ in 0..count {
+            networks.push(ChannelNetwork::new()?);
+        }
+
+        for i in 0..count {
+            for j in 0..count {
+                if i != j {
+                    let id = networks[j].id();
+                    let sender = networks[j].sender();
+                    networks[i].add_channel(id, &sender)?;
+                }
+            }
+        }
+
+        let addresses: Vec<Vec<u8>> = networks
+            .iter()
+            .map(|network| network.local_address())
+            .collect::<std::result::Result<_, _>>()?;
+
+        let mut nodes = Vec::new();
+
+        for (network, address) in networks.into_iter().zip(addresses.iter()) {
+            let seed: BTreeSet<Vec<u8>> = addresses
+                .iter()
+                .filter(|other| *other != address)
+                .cloned()
+                .collect();
+
+            let store = Arc::new(RwLock::new(TestNetwork::memory_store()?));
+            let pool = Arc::new(RwLock::new(TestNetwork::memory_store()?));
+
+            let state =
+                ProtocolState::create_from_genesis(genesis, address, &seed, store, pool.clone())?;
+
+            // NB: the pool has to know the eve transaction to pool the spends of the eve account
+            Transaction::create(
+                &mut *pool.write().unwrap(),
+                genesis.stage,
+                &genesis.eve_transaction,
+            )?;
+
+            let logger = Logger::new(
+                LogLevel::None,
+                LogFormat::default(),
+                &LogFile::default(),
+                false,
+            )?;
+
+            let node = TestNode {
+                state: Arc::new(Mutex::new(state)),
+                network: Arc::new(Mutex::new(network)),
+                logger: Arc::new(logger),
+            };
+
+            nodes.push(node);
+        }
+
+        let network = TestNetwork {
+            genesis: genesis.to_owned(),
+            secret_key: secret_key.to_owned(),
+            nodes,
+        };
+
+        Ok(network)
+    }
+
+    /// `memory_store` creates a new memory store for a `TestNode`.
+    fn memory_store() -> Result<BTreeStore> {
+        BTreeStore::new(
+            PoolConfig::DEFAULT_MAX_VALUE_SIZE,
+            PoolConfig::DEFAULT_MAX_SIZE,
+        )
+        .map_err(|e| e.into())
+    }
+
+    /// `len` returns the number of `TestNode`s.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// `is_empty` returns if the `TestNetwork` has no `TestNode`s.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// `node` returns a `TestNode`.
+    pub fn node(&self, idx: usize) -> Result<&TestNode> {
+        self.nodes.get(idx).ok_or(Error::NotFound)
+    }
+
+    /// `fixture` creates a new `TestNetwork` of a number of `TestNode`s, returning it
+    /// together with the state of its first `TestNode`, the one exercised by the tests.
+    pub fn fixture(count: usize) -> Result<(TestNetwork, Arc<Mutex<TestState>>)> {
+        let network = TestNetwork::new(count)?;
+        let state = network.node(0)?.state.clone();
+
+        Ok((network, state))
+    }
+
+    /// `transaction` creates an unsigned `Transaction` of the `TestNetwork` stage,
+    /// with an empty `Output` for each of a list of `Address`es.
+    pub fn transaction(&self, addresses: &[Address]) -> Result<Transaction> {
+        let mut transaction = Transaction::new()?;
+        transaction.stage = self.genesis.stage;
+
+        for address in addresses {
+            transaction.add_output(&Output::new(address, 0, &[]))?;
+        }
+
+        transaction.update_id()?;
+
+        Ok(transaction)
+    }
+
+    /// `submit` submits a `Transaction` to a `TestNode`.
+    pub fn submit(&self, idx: usize, transaction: &Transaction) -> Result<()> {
+        self.node(idx)?.submit(transaction)
+    }
+
+    /// `broadcast` submits a `Transaction` to all the `TestNode`s.
+    pub fn broadcast(&self, transaction: &Transaction) -> Result<()> {
+        for node in self.nodes.iter() {
+            node.submit(transaction)?;
+        }
+
+        Ok(())
+    }
+
+    /// `peers` returns the indexes of the `TestNode`s queried by a `TestNode`:
+    /// the k `TestNode`s following it, in order.
+    pub(crate) fn peers(&self, idx: usize) -> Result<Vec<usize>> {
+        let mut config = self.node(idx)?.state.lock().unwrap().config.clone();
+        config.populate();
+
+        let k = (config.k.unwrap() as usize).min(self.len() - 1);
+
+        let peers = (1..=k).map(|i| (idx + i) % self.len()).collect();
+
+        Ok(peers)
+    }
+
+    /// `step` advances the consensus of a `TestNode` of one step, querying its
+    /// queued `Transaction`s. Unlike `avalanche_step`, the queries are answered
+    /// in-process by a fixed set of peers, so that the step is deterministic.
+    pub fn step(&self, idx: usize) -> Result<()> {
+        self.step_with(idx, |peer, transaction| {
+            let chit = peer
+                .state
+                .lock()
+                .unwrap()
+                .is_strongly_preferred(&transaction.id)?;
+
+            Ok(Some(chit))
+        })
+    }
+
+    /// `step_with` advances the consensus of a `TestNode` of one step like `step`,
+    /// but querying the peers with a function returning the chit of a peer for a
+    /// `Transaction`, or `None` if the query or its reply is lost.
+    pub fn step_with<F>(&self, idx: usize, mut query: F) -> Result<()>
+    where
+        F: FnMut(&TestNode, &Transaction) -> Result<Option<bool>>,
+    {
+        let node = self.node(idx)?;
+        let state = node.state.clone();
+
+        state.lock().unwrap().validate_writable()?;
+
+        let tx_ids: BTreeSet<Digest> = {
+            let state = state.lock().unwrap();
+
+            state
+                .state
+                .known_transactions
+                .iter()
+                .filter(|id| !state.state.lookup_queried_transaction(&id))
+                .copied()
+                .collect()
+        };
+
+        for tx_id in tx_ids {
+            if !state.lock().unwrap().queue.lookup(&tx_id) {
+                let tx = state.lock().unwrap().get_transaction(&tx_id)?;
+                state.lock().unwrap().queue.push(&tx)?;
+            }
+        }
+
+        let tx_ids = state.lock().unwrap().queue.ids();
+        let mut pending = Vec::with_capacity(tx_ids.len());
+
+        for tx_id in tx_ids {
+            let tx = state.lock().unwrap().get_transaction(&tx_id)?;
+            let ancestors = tx.ancestors()?;
+            pending.push((tx, ancestors));
+        }
+
+        // NB: the queued ancestors are queried first, as a transaction cannot be
+        // accepted before them
+        let mut txs = Vec::with_capacity(pending.len());
+
+        while !pending.is_empty() {
+            let tx_ids: BTreeSet<Digest> = pending.iter().map(|(tx, _)| tx.id).collect();
+
+            let (ready, blocked): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(_, ancestors)| ancestors.is_disjoint(&tx_ids));
+
+            if ready.is_empty() {
+                txs.extend(blocked.into_iter().map(|(tx, _)| tx));
+                break;
+            }
+
+            txs.extend(ready.into_iter().map(|(tx, _)| tx));
+            pending = blocked;
+        }
+
+        let peers = self.peers(idx)?;
+
+        for tx in txs {
+            let mut chit_sum = 0;
+
+            for peer in peers.iter() {
+                if query(&self.nodes[*peer], &tx)? == Some(true) {
+                    chit_sum += 1;
+                }
+            }
+
+            update_chit_sum(state.clone(), &tx, chit_sum)?;
+        }
+
+        Ok(())
+    }
+
+    /// `step_all` advances the consensus of all the `TestNode`s of one step, in order.
+    pub fn step_all(&self) -> Result<()> {
+        for idx in 0..self.len() {
+            self.step(idx)?;
+        }
+
+        Ok(())
+    }
+
+    /// `run` advances the consensus of all the `TestNode`s of a number of steps.
+    pub fn run(&self, steps: usize) -> Result<()> {
+        for _ in 0..steps {
+            self.step_all()?;
+        }
+
+        Ok(())
+    }
+
+    /// `is_accepted` returns if a `Transaction` is accepted by all the `TestNode`s.
+    pub fn is_accepted(&self, tx_id: &Digest) -> Result<bool> {
+        for node in self.nodes.iter() {
+            if !node.is_accepted(tx_id)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// `assert_accepted` panics if a `Transaction` is not accepted by all the `TestNode`s.
+    pub fn assert_accepted(&self, tx_id: &Digest) {
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let accepted = node.is_accepted(tx_id).unwrap_or(false);
+            assert!(
+                accepted,
+                "transaction {} not accepted by node {}",
+                tx_id, idx
+            );
+        }
+    }
+}
+
+#[test]
+fn test_testkit_network() {
+    let res = TestNetwork::new(0);
+    assert!(res.is_err());
+
+    let count = 3;
+
+    let res = TestNetwork::new(count);
+    assert!(res.is_ok());
+
+    let network = res.unwrap();
+    assert_eq!(network.len(), count);
+
+    let eve_tx_id = network.genesis.eve_transaction.id;
+
+    for idx in 0..count {
+        let node = network.node(idx).unwrap();
+        let state = node.state.lock().unwrap();
+
+        assert_eq!(state.state.eve_transaction_id, eve_tx_id);
+        assert_eq!(state.state.seed_nodes.len(), count - 1);
+    }
+
+    let res = network.node(count);
+    assert!(res.is_err());
+
+    let res = network.peers(0);
+    assert!(res.is_ok());
+
+    let peers = res.unwrap();
+    assert!(!peers.contains(&0));
+}
+
+#[test]
+fn test_testkit_run() {
+    let network = TestNetwork::new(3).unwrap();
+
+    let mut transaction = Transaction::new().unwrap();
+    transaction.stage = network.genesis.stage;
+    transaction
+        .set_coinbase(&Address::random().unwrap(), 1)
+        .unwrap();
+    transaction.mine().unwrap();
+    transaction.update_id().unwrap();
+
+    let res = network.broadcast(&transaction);
+    assert!(res.is_ok());
+
+    let res = network.run(10);
+    assert!(res.is_ok());
+
+    let res = network.is_accepted(&transaction.id);
+    assert!(res.is_ok());
+    assert!(res.unwrap());
+
+    network.assert_accepted(&transaction.id);
+
+    for node in network.nodes.iter() {
+        let res = node.is_stored(&transaction.id);
+        assert!(res.is_ok());
+        assert!(res.unwrap());
+    }
+}
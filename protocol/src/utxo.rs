@@ -0,0 +1,260 @@
+//! # UTXO
+//!
+//! `utxo` contains the spent outputs bookkeeping of the accepted `Transaction`s.
+//! An `Output` is identified by the id of its `Transaction` and its `Address`,
+//! and is spent by the `Input` of the `Account` of the same `Address` referencing
+//! the `Transaction`.
+
+use crate::error::Error;
+use crate::result::Result;
+use crypto::hash::Digest;
+use models::address::Address;
+use models::input::Input;
+use models::stage::Stage;
+use models::traits::Storable;
+use models::transaction::Transaction;
+use store::error::Error as StoreError;
+use store::traits::Store;
+
+/// `SPENT_KEY_PREFIX` is the prefix of the spent outputs keys.
+pub const SPENT_KEY_PREFIX: u8 = 14;
+
+/// `spent_key` returns the `Store` key marking the `Output` of a `Transaction`
+/// to an `Address` as spent.
+fn spent_key(stage: Stage, tx_id: &Digest, address: &Address) -> Vec<u8> {
+    let mut buf = vec![stage as u8, SPENT_KEY_PREFIX];
+    buf.extend_from_slice(&tx_id.to_bytes());
+    buf.extend_from_slice(&address.to_bytes());
+    buf
+}
+
+/// `get_spender` returns the id of the accepted `Transaction` spending the
+/// `Output` of a `Transaction` to an `Address`, if any.
+pub fn get_spender<S: Store>(
+    store: &S,
+    stage: Stage,
+    tx_id: &Digest,
+    address: &Address,
+) -> Result<Option<Digest>> {
+    match store.get(&spent_key(stage, tx_id, address)) {
+        Ok(buf) => Digest::from_slice(&buf).map(Some).map_err(|e| e.into()),
+        Err(StoreError::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `is_spent` returns if the `Output` of a `Transaction` to an `Address` is spent.
+pub fn is_spent<S: Store>(
+    store: &S,
+    stage: Stage,
+    tx_id: &Digest,
+    address: &Address,
+) -> Result<bool> {
+    get_spender(store, stage, tx_id, address).map(|spender| spender.is_some())
+}
+
+/// `verify_input` verifies that an `Input` of a `Transaction` spends an existing
+/// `Output` not spent by another accepted `Transaction`. The `Output` is looked
+/// up in the known ancestor `Transaction`, in the `Store` or in the pool, and is
+/// not checked if the ancestor is not known yet.
+pub fn verify_input<S: Store, P: Store>(
+    store: &S,
+    pool: &P,
+    stage: Stage,
+    tx_id: &Digest,
+    input: &Input,
+) -> Result<()> {
+    let prev_id = match input.account.transaction_id {
+        Some(prev_id) => prev_id,
+        None => return Ok(()),
+    };
+
+    let address = input.address();
+
+    if let Some(spender) = get_spender(store, stage, &prev_id, &address)? {
+        if &spender != tx_id {
+            let err = Error::DoubleSpend;
+            return Err(err);
+        }
+    }
+
+    let prev_transaction = if Transaction::lookup(store, stage, &prev_id)? {
+        Transaction::get(store, stage, &prev_id)?
+    } else if Transaction::lookup(pool, stage, &prev_id)? {
+        Transaction::get(pool, stage, &prev_id)?
+    } else {
+        return Ok(());
+    };
+
+    if prev_transaction.is_eve()? {
+        return Ok(());
+    }
+
+    if !prev_transaction.lookup_output(&address) {
+        let err = Error::InvalidTransaction;
+        return Err(err);
+    }
+
+    if prev_transaction.get_output(&address)?.amount < input.amount {
+        let err = Error::InvalidTransaction;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// `verify_spends` verifies the `Input`s of a `Transaction`, rejecting the
+/// `Transaction` if any of them spends a missing or already spent `Output`.
+pub fn verify_spends<S: Store, P: Store>(
+    store: &S,
+    pool: &P,
+    stage: Stage,
+    transaction: &Transaction,
+) -> Result<()> {
+    for input in transaction.inputs.values() {
+        verify_input(store, pool, stage, &transaction.id, input)?;
+    }
+
+    Ok(())
+}
+
+/// `spend_outputs` marks the `Output`s spent by an accepted `Transaction` as spent.
+/// An `Output` already spent by an other accepted `Transaction` is left to the
+/// first one, so that the bookkeeping is the same on all the nodes.
+pub fn spend_outputs<S: Store>(
+    store: &mut S,
+    stage: Stage,
+    transaction: &Transaction,
+) -> Result<()> {
+    for input in transaction.inputs.values() {
+        let prev_id = match input.account.transaction_id {
+            Some(prev_id) => prev_id,
+            None => continue,
+        };
+
+        let address = input.address();
+
+        if is_spent(store, stage, &prev_id, &address)? {
+            continue;
+        }
+
+        store.insert(
+            &spent_key(stage, &prev_id, &address),
+            &transaction.id.to_bytes(),
+            None,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_utxo_spends() {
+    use crypto::ecc::ed25519::SecretKey;
+    use models::account::Account;
+    use models::output::Output;
+    use models::signer::Signer;
+    use models::signers::Signers;
+    use store::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 16;
+    let max_size = 1 << 30;
+
+    let mut store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+    let pool = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+
+    let stage = Stage::random().unwrap();
+
+    let secret_key = SecretKey::random().unwrap();
+    let signer = Signer {
+        public_key: secret_key.to_public(),
+        weight: 1,
+    };
+    let mut signers = Signers::new().unwrap();
+    signers.add(&signer).unwrap();
+    signers.set_threshold(1).unwrap();
+
+    let address = signers.address;
+
+    let funder_secret_key = SecretKey::random().unwrap();
+    let funder = Signer {
+        public_key: funder_secret_key.to_public(),
+        weight: 1,
+    };
+    let mut funder_signers = Signers::new().unwrap();
+    funder_signers.add(&funder).unwrap();
+    funder_signers.set_threshold(1).unwrap();
+
+    let mut eve_transaction = Transaction::new_eve(stage, &funder_signers.address).unwrap();
+    eve_transaction.mine().unwrap();
+    eve_transaction.update_id().unwrap();
+    Transaction::create(&mut store, stage, &eve_transaction).unwrap();
+
+    let funder_account =
+        Account::new(stage, &funder_signers, 100, Some(eve_transaction.id)).unwrap();
+
+    let mut prev_transaction = Transaction::new().unwrap();
+    prev_transaction.stage = stage;
+    prev_transaction
+        .add_input(&Input::new(&funder_account, 1, 10).unwrap())
+        .unwrap();
+    prev_transaction
+        .add_output(&Output::new(&address, 10, &[]))
+        .unwrap();
+    prev_transaction.update_id().unwrap();
+
+    Transaction::insert(&mut store, stage, &prev_transaction).unwrap();
+
+    let account = Account::new(stage, &signers, 10, Some(prev_transaction.id)).unwrap();
+
+    let new_spend = |amount: u64| {
+        let mut transaction = Transaction::new().unwrap();
+        transaction.stage = stage;
+        transaction
+            .add_input(&Input::new(&account, 1, amount).unwrap())
+            .unwrap();
+        transaction
+    };
+
+    let transaction_a = new_spend(10);
+    let transaction_b = new_spend(5);
+
+    let res = verify_spends(&store, &pool, stage, &transaction_a);
+    assert!(res.is_ok());
+
+    let res = verify_spends(&store, &pool, stage, &transaction_b);
+    assert!(res.is_ok());
+
+    let res = spend_outputs(&mut store, stage, &transaction_a);
+    assert!(res.is_ok());
+
+    let res = get_spender(&store, stage, &prev_transaction.id, &address);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Some(transaction_a.id));
+
+    let res = verify_spends(&store, &pool, stage, &transaction_a);
+    assert!(res.is_ok());
+
+    let res = verify_spends(&store, &pool, stage, &transaction_b);
+    assert!(res.is_err());
+
+    let res = spend_outputs(&mut store, stage, &transaction_b);
+    assert!(res.is_ok());
+
+    let res = get_spender(&store, stage, &prev_transaction.id, &address);
+    assert_eq!(res.unwrap(), Some(transaction_a.id));
+
+    let other_account = Account::new(stage, &signers, 20, Some(prev_transaction.id)).unwrap();
+    let mut other_store = MemoryStoreFactory::new_unqlite(max_value_size, max_size).unwrap();
+    Transaction::create(&mut other_store, stage, &eve_transaction).unwrap();
+    Transaction::insert(&mut other_store, stage, &prev_transaction).unwrap();
+
+    let mut transaction_c = Transaction::new().unwrap();
+    transaction_c.stage = stage;
+    transaction_c
+        .add_input(&Input::new(&other_account, 1, 20).unwrap())
+        .unwrap();
+
+    let res = verify_spends(&other_store, &pool, stage, &transaction_c);
+    assert!(res.is_err());
+}
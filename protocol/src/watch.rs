@@ -0,0 +1,201 @@
+//! # Watch
+//!
+//! `watch` contains the functions running the actions of the watched addresses
+//! touched by the accepted `Transaction`s. The event actions are left to the
+//! gRPC event subscribers.
+
+use crate::error::Error;
+use crate::events::ProtocolEvent;
+use crate::result::Result;
+use crypto::hash::Digest;
+use log::logger::Logger;
+use models::address::{self, Address};
+use models::stage::Stage;
+use models::watch::WatchAction;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// `WEBHOOK_TIMEOUT` is the timeout in seconds of the webhook requests.
+pub const WEBHOOK_TIMEOUT: u64 = 10;
+
+/// `WEBHOOK_DEFAULT_PORT` is the port of the webhook URLs without one.
+pub const WEBHOOK_DEFAULT_PORT: u16 = 80;
+
+/// `WatchNotification` is the JSON notification posted to the webhooks.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct WatchNotification {
+    pub stage: Stage,
+    pub tx_id: String,
+    pub address: String,
+}
+
+impl WatchNotification {
+    /// `new` creates a new `WatchNotification` of a `Transaction` touching a
+    /// watched `Address`.
+    pub fn new(stage: Stage, tx_id: &Digest, address: &Address) -> WatchNotification {
+        WatchNotification {
+            stage,
+            tx_id: tx_id.to_string(),
+            address: address::encode(stage, address),
+        }
+    }
+
+    /// `to_json` converts the `WatchNotification` into a JSON string.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| e.into())
+    }
+}
+
+/// `post_webhook` posts a JSON body with a set of extra headers to a webhook
+/// URL, expecting a successful HTTP status.
+pub fn post_webhook(url: &str, headers: &[(&str, String)], body: &str) -> Result<()> {
+    let (host, path) = WatchAction::split_url(url)?;
+
+    let target = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, WEBHOOK_DEFAULT_PORT)
+    };
+
+    let socket_address = target.to_socket_addrs()?.next().ok_or_else(|| {
+        let msg = format!("unresolved webhook host: {}", host);
+        Error::IO { msg }
+    })?;
+
+    let timeout = Duration::from_secs(WEBHOOK_TIMEOUT);
+    let mut stream = TcpStream::connect_timeout(&socket_address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n",
+        path,
+        host,
+        body.len()
+    );
+
+    for (name, value) in headers.iter() {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    request.push_str("\r\n");
+    request.push_str(body);
+
+    stream.write_all(request.as_bytes())?;
+
+    // NB: the status line starts with "HTTP/1.x NNN".
+    let mut status_line = [0u8; 12];
+    stream.read_exact(&mut status_line)?;
+
+    if status_line[9] != b'2' {
+        let msg = format!(
+            "webhook status: {}",
+            String::from_utf8_lossy(&status_line[9..])
+        );
+        let err = Error::IO { msg };
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// `run_watch` runs the action of a triggered watch.
+pub fn run_watch(
+    stage: Stage,
+    logger: &Logger,
+    tx_id: &Digest,
+    address: &Address,
+    action: &WatchAction,
+) -> Result<()> {
+    match action {
+        WatchAction::Log => {
+            logger.log_info(&format!(
+                "Transaction {} touched watched address {}",
+                tx_id,
+                address::encode(stage, address)
+            ))?;
+
+            Ok(())
+        }
+        WatchAction::Event => Ok(()),
+        WatchAction::Webhook { url } => {
+            let notification = WatchNotification::new(stage, tx_id, address);
+            post_webhook(url, &[], &notification.to_json()?)
+        }
+    }
+}
+
+/// `watch_events` spawns a thread running the actions of the watches triggered
+/// in the `ProtocolEvent`s of a receiver.
+pub fn watch_events(
+    stage: Stage,
+    logger: Arc<Logger>,
+    receiver: Receiver<ProtocolEvent>,
+) -> JoinHandle<()> {
+    let logger = logger.with_module("protocol::watch");
+
+    thread::spawn(move || {
+        for event in receiver.iter() {
+            if let ProtocolEvent::WatchTriggered {
+                tx_id,
+                address,
+                ref action,
+            } = event
+            {
+                if let Err(err) = run_watch(stage, &logger, &tx_id, &address, action) {
+                    // NB: a failed log write cannot be reported anywhere else.
+                    let _ = logger.log_critical(&format!(
+                        "Watch of address {} failed: {}",
+                        address::encode(stage, &address),
+                        err
+                    ));
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn test_post_webhook() {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request_line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut request_line)
+            .unwrap();
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        request_line
+    });
+
+    let stage = Stage::random().unwrap();
+    let notification = WatchNotification::new(
+        stage,
+        &Digest::random().unwrap(),
+        &Address::random().unwrap(),
+    );
+
+    let url = format!("http://127.0.0.1:{}/deposits", port);
+    let res = post_webhook(&url, &[], &notification.to_json().unwrap());
+    assert!(res.is_ok());
+
+    let request_line = server.join().unwrap();
+    assert!(request_line.starts_with("POST /deposits HTTP/1.1"));
+
+    let res = post_webhook("ftp://127.0.0.1/", &[], "{}");
+    assert!(res.is_err());
+}
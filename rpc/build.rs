@@ -0,0 +1,4 @@
+fn main() {
+    tonic_build::compile_protos("proto/alsacoin.proto")
+        .unwrap_or_else(|e| panic!("cannot compile protos: {}", e));
+}
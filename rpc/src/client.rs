@@ -0,0 +1,464 @@
+//! # Client
+//!
+//! `client` contains the gRPC client type and functions.
+
+use crate::convert::*;
+use crate::error::Error;
+use crate::proto;
+use crate::proto::admin_service_client::AdminServiceClient;
+use crate::proto::consensus_service_client::ConsensusServiceClient;
+use crate::proto::dag_service_client::DagServiceClient;
+use crate::proto::event_service_client::EventServiceClient;
+use crate::proto::ledger_service_client::LedgerServiceClient;
+use crate::proto::peer_service_client::PeerServiceClient;
+use crate::proto::transaction_service_client::TransactionServiceClient;
+use crate::proto::wallet_service_client::WalletServiceClient;
+use crate::result::Result;
+use crypto::hash::Digest;
+use models::address::Address;
+use models::transaction::Transaction;
+use network::codec::Codec;
+use protocol::client::BroadcastStatus;
+use protocol::events::EventFilter;
+use protocol::ledger::{AddressHistory, UnspentOutput};
+use std::collections::BTreeSet;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Request;
+
+/// `RpcClient` is the gRPC client type. It wraps the gRPC services clients
+/// behind a blocking interface, so that it can be used outside of an async context.
+pub struct RpcClient {
+    runtime: Runtime,
+    transaction: TransactionServiceClient<Channel>,
+    dag: DagServiceClient<Channel>,
+    ledger: LedgerServiceClient<Channel>,
+    peer: PeerServiceClient<Channel>,
+    consensus: ConsensusServiceClient<Channel>,
+    wallet: WalletServiceClient<Channel>,
+    events: EventServiceClient<Channel>,
+    admin: AdminServiceClient<Channel>,
+    codec: Codec,
+    admin_token: Option<String>,
+}
+
+impl RpcClient {
+    /// `DEFAULT_ADDRESS` is the default address of the daemon gRPC server.
+    pub const DEFAULT_ADDRESS: &'static str = "127.0.0.1:2022";
+
+    /// `POLL_TIME` is the time in milliseconds between two polls of a `Transaction` status.
+    pub const POLL_TIME: u64 = 1000;
+
+    /// `connect` connects a new `RpcClient` to the gRPC server at an address.
+    pub fn connect(address: &str) -> Result<RpcClient> {
+        let mut runtime = Runtime::new()?;
+
+        let endpoint = Endpoint::from_shared(format!("http://{}", address)).map_err(|e| {
+            let msg = format!("{}", e);
+            Error::InvalidArgument { msg }
+        })?;
+
+        let channel = runtime.block_on(endpoint.connect())?;
+
+        let client = RpcClient {
+            runtime,
+            transaction: TransactionServiceClient::new(channel.clone()),
+            dag: DagServiceClient::new(channel.clone()),
+            ledger: LedgerServiceClient::new(channel.clone()),
+            peer: PeerServiceClient::new(channel.clone()),
+            consensus: ConsensusServiceClient::new(channel.clone()),
+            wallet: WalletServiceClient::new(channel.clone()),
+            events: EventServiceClient::new(channel.clone()),
+            admin: AdminServiceClient::new(channel),
+            codec: Codec::Cbor,
+            admin_token: None,
+        };
+
+        Ok(client)
+    }
+
+    /// `set_codec` sets the `Codec` of the binary fields of the requests and
+    /// of the responses. The `RpcClient` decodes the returned `Transaction`s
+    /// from their binary field, so it can not use Protobuf.
+    pub fn set_codec(&mut self, codec: Codec) -> Result<()> {
+        if !codec.is_schemaless() {
+            let msg = format!("unsupported codec {}", codec);
+            let err = Error::InvalidArgument { msg };
+            return Err(err);
+        }
+
+        self.codec = codec;
+
+        Ok(())
+    }
+
+    /// `codec` returns the `Codec` of the binary fields of the requests and
+    /// of the responses.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// `set_admin_token` sets the token authorizing the admin requests.
+    pub fn set_admin_token(&mut self, token: &str) {
+        self.admin_token = Some(token.into());
+    }
+
+    /// `admin_request` creates a `Request` carrying the `Codec` and the admin token
+    /// of the `RpcClient`.
+    fn admin_request<T>(&self, message: T) -> Result<Request<T>> {
+        let mut request = self.request(message)?;
+
+        if let Some(ref token) = self.admin_token {
+            admin_token_to_metadata(token, request.metadata_mut())?;
+        }
+
+        Ok(request)
+    }
+
+    /// `request` creates a `Request` carrying the `Codec` of the `RpcClient`.
+    fn request<T>(&self, message: T) -> Result<Request<T>> {
+        let mut request = Request::new(message);
+        codec_to_metadata(self.codec, request.metadata_mut())?;
+
+        Ok(request)
+    }
+
+    /// `submit_transaction` submits a `Transaction`, returning its id.
+    pub fn submit_transaction(&mut self, transaction: &Transaction) -> Result<Digest> {
+        let request = self.request(proto::SubmitTransactionRequest {
+            transaction: self.codec.encode(transaction)?,
+        })?;
+
+        let response = self
+            .runtime
+            .block_on(self.transaction.submit_transaction(request))?;
+
+        digest_from_proto(&response.into_inner().id)
+    }
+
+    /// `get_transaction` returns a `Transaction`.
+    pub fn get_transaction(&mut self, tx_id: &Digest) -> Result<Transaction> {
+        let request = self.request(proto::TransactionId { id: tx_id.to_vec() })?;
+
+        let response = self
+            .runtime
+            .block_on(self.transaction.get_transaction(request))?;
+
+        transaction_from_proto(&response.into_inner().raw, self.codec)
+    }
+
+    /// `get_ancestors` returns the known ancestors of a `Transaction`.
+    pub fn get_ancestors(&mut self, tx_id: &Digest) -> Result<BTreeSet<Digest>> {
+        let request = proto::TransactionId { id: tx_id.to_vec() };
+
+        let response = self.runtime.block_on(self.dag.get_ancestors(request))?;
+
+        digests_from_proto(&response.into_inner())
+    }
+
+    /// `get_successors` returns the successors of a `Transaction`.
+    pub fn get_successors(&mut self, tx_id: &Digest) -> Result<BTreeSet<Digest>> {
+        let request = proto::TransactionId { id: tx_id.to_vec() };
+
+        let response = self.runtime.block_on(self.dag.get_successors(request))?;
+
+        digests_from_proto(&response.into_inner())
+    }
+
+    /// `get_status` returns the consensus status of a `Transaction`.
+    pub fn get_status(&mut self, tx_id: &Digest) -> Result<proto::TransactionStatus> {
+        let request = proto::TransactionId { id: tx_id.to_vec() };
+
+        let response = self.runtime.block_on(self.dag.get_status(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `track_transaction` polls the status of a submitted `Transaction` until it
+    /// is accepted or the timeout in seconds expires, reporting the progress to a
+    /// callback.
+    pub fn track_transaction(
+        &mut self,
+        tx_id: &Digest,
+        timeout: u64,
+        progress: &mut dyn FnMut(&BroadcastStatus),
+    ) -> Result<BroadcastStatus> {
+        let deadline = Instant::now() + Duration::from_secs(timeout);
+        let poll_time = Duration::from_millis(Self::POLL_TIME);
+        let mut last_confidence = None;
+
+        let status = loop {
+            let status = self.get_status(tx_id)?;
+
+            if status.accepted {
+                break BroadcastStatus::Accepted;
+            }
+
+            if last_confidence != Some(status.confidence) {
+                last_confidence = Some(status.confidence);
+                progress(&BroadcastStatus::Pending {
+                    confidence: status.confidence,
+                });
+            }
+
+            if Instant::now() >= deadline {
+                break BroadcastStatus::TimedOut;
+            }
+
+            thread::sleep(poll_time);
+        };
+
+        progress(&status);
+
+        Ok(status)
+    }
+
+    /// `get_balance` returns the balance of an `Address`.
+    pub fn get_balance(&mut self, address: &Address) -> Result<u64> {
+        let request = proto::AddressRequest {
+            address: address.to_vec(),
+        };
+
+        let response = self.runtime.block_on(self.ledger.get_balance(request))?;
+
+        Ok(response.into_inner().balance)
+    }
+
+    /// `get_unspent_outputs` returns the unspent outputs of an `Address`.
+    pub fn get_unspent_outputs(&mut self, address: &Address) -> Result<Vec<UnspentOutput>> {
+        let request = proto::AddressRequest {
+            address: address.to_vec(),
+        };
+
+        let response = self
+            .runtime
+            .block_on(self.ledger.get_unspent_outputs(request))?;
+
+        response
+            .into_inner()
+            .outputs
+            .iter()
+            .map(unspent_output_from_proto)
+            .collect()
+    }
+
+    /// `get_history` returns a page of the history of an `Address`. A zero count
+    /// returns the maximum page allowed by the daemon.
+    pub fn get_history(
+        &mut self,
+        address: &Address,
+        count: u32,
+        skip: u32,
+    ) -> Result<AddressHistory> {
+        let request = proto::HistoryRequest {
+            address: address.to_vec(),
+            count,
+            skip,
+        };
+
+        let response = self.runtime.block_on(self.ledger.get_history(request))?;
+
+        history_from_proto(&response.into_inner())
+    }
+
+    /// `list_peers` lists the peers known by the daemon.
+    pub fn list_peers(&mut self, count: u32, skip: u32) -> Result<Vec<proto::Node>> {
+        let request = proto::ListPeersRequest { count, skip };
+
+        let response = self.runtime.block_on(self.peer.list_peers(request))?;
+
+        Ok(response.into_inner().nodes)
+    }
+
+    /// `add_peer` adds a peer to the daemon.
+    pub fn add_peer(&mut self, address: &[u8]) -> Result<proto::Node> {
+        let request = self.admin_request(proto::NodeAddress {
+            address: address.to_vec(),
+        })?;
+
+        let response = self.runtime.block_on(self.peer.add_peer(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `remove_peer` removes a peer from the daemon.
+    pub fn remove_peer(&mut self, node_id: &Digest) -> Result<()> {
+        let request = self.admin_request(proto::NodeId {
+            id: node_id.to_vec(),
+        })?;
+
+        self.runtime.block_on(self.peer.remove_peer(request))?;
+
+        Ok(())
+    }
+
+    /// `ban_peer` bans a node address on the daemon for a duration in seconds.
+    pub fn ban_peer(
+        &mut self,
+        address: &[u8],
+        duration: u64,
+        reason: &str,
+    ) -> Result<proto::NodeBan> {
+        let request = self.admin_request(proto::BanRequest {
+            address: address.to_vec(),
+            duration,
+            reason: reason.into(),
+        })?;
+
+        let response = self.runtime.block_on(self.peer.ban_peer(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `unban_peer` removes the ban of a node address on the daemon.
+    pub fn unban_peer(&mut self, address: &[u8]) -> Result<()> {
+        let request = self.admin_request(proto::NodeAddress {
+            address: address.to_vec(),
+        })?;
+
+        self.runtime.block_on(self.peer.unban_peer(request))?;
+
+        Ok(())
+    }
+
+    /// `list_bans` lists the active node bans of the daemon.
+    pub fn list_bans(&mut self) -> Result<Vec<proto::NodeBan>> {
+        let request = proto::Empty {};
+
+        let response = self.runtime.block_on(self.peer.list_bans(request))?;
+
+        Ok(response.into_inner().bans)
+    }
+
+    /// `get_metrics` returns the consensus metrics of the daemon.
+    pub fn get_metrics(&mut self) -> Result<proto::ConsensusMetrics> {
+        let request = proto::Empty {};
+
+        let response = self.runtime.block_on(self.consensus.get_metrics(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `get_runtime_config` returns the runtime parameters of the daemon.
+    pub fn get_runtime_config(&mut self) -> Result<proto::RuntimeConfig> {
+        let request = self.admin_request(proto::Empty {})?;
+
+        let response = self
+            .runtime
+            .block_on(self.admin.get_runtime_config(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `set_runtime_config` sets the runtime parameters of the daemon, leaving
+    /// unchanged the empty and zero ones, and returns the updated parameters.
+    pub fn set_runtime_config(
+        &mut self,
+        config: &proto::RuntimeConfig,
+    ) -> Result<proto::RuntimeConfig> {
+        let request = self.admin_request(config.to_owned())?;
+
+        let response = self
+            .runtime
+            .block_on(self.admin.set_runtime_config(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `get_health` returns the health and telemetry of the daemon.
+    pub fn get_health(&mut self) -> Result<proto::Health> {
+        let request = self.admin_request(proto::Empty {})?;
+
+        let response = self.runtime.block_on(self.admin.get_health(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `subscribe` subscribes to the daemon events selected by an `EventFilter`,
+    /// passing them to a handler until the handler returns false or the
+    /// daemon closes the stream.
+    pub fn subscribe(
+        &mut self,
+        filter: &EventFilter,
+        handler: &mut dyn FnMut(&proto::Event) -> bool,
+    ) -> Result<()> {
+        let request = event_filter_to_proto(filter);
+        let events = &mut self.events;
+
+        self.runtime.block_on(async move {
+            let mut stream = events.subscribe(request).await?.into_inner();
+
+            while let Some(event) = stream.message().await? {
+                if !handler(&event) {
+                    break;
+                }
+            }
+
+            Ok::<(), Error>(())
+        })
+    }
+
+    /// `unlock_wallet` unlocks the daemon wallet of an `Address` with a passphrase
+    /// for a timeout in seconds.
+    pub fn unlock_wallet(
+        &mut self,
+        address: &Address,
+        passphrase: &str,
+        timeout: u64,
+    ) -> Result<proto::WalletStatus> {
+        let request = self.admin_request(proto::UnlockWalletRequest {
+            address: address.to_vec(),
+            passphrase: passphrase.into(),
+            timeout,
+        })?;
+
+        let response = self.runtime.block_on(self.wallet.unlock_wallet(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `lock_wallet` locks the daemon wallet of an `Address`.
+    pub fn lock_wallet(&mut self, address: &Address) -> Result<proto::WalletStatus> {
+        let request = self.admin_request(proto::AddressRequest {
+            address: address.to_vec(),
+        })?;
+
+        let response = self.runtime.block_on(self.wallet.lock_wallet(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `get_wallet_status` returns the status of the daemon wallet of an `Address`.
+    pub fn get_wallet_status(&mut self, address: &Address) -> Result<proto::WalletStatus> {
+        let request = proto::AddressRequest {
+            address: address.to_vec(),
+        };
+
+        let response = self
+            .runtime
+            .block_on(self.wallet.get_wallet_status(request))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// `sign_transaction` signs the input of an `Address` of a `Transaction` with
+    /// the unlocked daemon wallet of the `Address`.
+    pub fn sign_transaction(
+        &mut self,
+        address: &Address,
+        transaction: &Transaction,
+    ) -> Result<Transaction> {
+        let request = self.admin_request(proto::SignTransactionRequest {
+            address: address.to_vec(),
+            transaction: self.codec.encode(transaction)?,
+        })?;
+
+        let response = self
+            .runtime
+            .block_on(self.wallet.sign_transaction(request))?;
+
+        transaction_from_proto(&response.into_inner().raw, self.codec)
+    }
+}
@@ -0,0 +1,635 @@
+//! # Convert
+//!
+//! `convert` contains the conversions between the `models` and `proto` types.
+
+use crate::error::Error;
+use crate::proto;
+use crate::result::Result;
+use crypto::hash::Digest;
+use models::account::Account;
+use models::coinbase::Coinbase;
+use models::input::Input;
+use models::node::Node;
+use models::node_ban::NodeBan;
+use models::output::Output;
+use models::stake::Stake;
+use models::timestamp::Timestamp;
+use models::transaction::Transaction;
+use network::codec::Codec;
+use protocol::events::{EventFilter, EventKind, ProtocolEvent};
+use protocol::ledger::{AddressHistory, HistoryEntry, UnspentOutput};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use tonic::metadata::{MetadataMap, MetadataValue};
+
+/// `CODEC_KEY` is the metadata key of the `Codec` of the binary fields of the
+/// requests and of the responses. The `Codec` defaults to CBOR.
+pub const CODEC_KEY: &str = "alsacoin-codec";
+
+/// `codec_from_metadata` returns the `Codec` requested in the metadata of a request.
+pub fn codec_from_metadata(metadata: &MetadataMap) -> Result<Codec> {
+    if let Some(value) = metadata.get(CODEC_KEY) {
+        let name = value.to_str().map_err(|_| {
+            let msg = "invalid codec".into();
+            Error::InvalidArgument { msg }
+        })?;
+
+        Codec::parse(name).map_err(|e| e.into())
+    } else {
+        Ok(Codec::Cbor)
+    }
+}
+
+/// `codec_to_metadata` sets the `Codec` in the metadata of a request.
+pub fn codec_to_metadata(codec: Codec, metadata: &mut MetadataMap) -> Result<()> {
+    let value = MetadataValue::from_str(&codec.to_string()).map_err(|_| {
+        let msg = "invalid codec".into();
+        Error::InvalidArgument { msg }
+    })?;
+
+    metadata.insert(CODEC_KEY, value);
+
+    Ok(())
+}
+
+/// `ADMIN_TOKEN_KEY` is the metadata key of the token authorizing the admin requests.
+pub const ADMIN_TOKEN_KEY: &str = "alsacoin-admin-token";
+
+/// `admin_token_from_metadata` returns the admin token in the metadata of a request, if any.
+pub fn admin_token_from_metadata(metadata: &MetadataMap) -> Result<Option<String>> {
+    if let Some(value) = metadata.get(ADMIN_TOKEN_KEY) {
+        let token = value.to_str().map_err(|_| {
+            let msg = "invalid admin token".into();
+            Error::InvalidArgument { msg }
+        })?;
+
+        Ok(Some(token.into()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// `admin_token_to_metadata` sets the admin token in the metadata of a request.
+pub fn admin_token_to_metadata(token: &str, metadata: &mut MetadataMap) -> Result<()> {
+    let value = MetadataValue::from_str(token).map_err(|_| {
+        let msg = "invalid admin token".into();
+        Error::InvalidArgument { msg }
+    })?;
+
+    metadata.insert(ADMIN_TOKEN_KEY, value);
+
+    Ok(())
+}
+
+/// `encode_with` encodes a value for a binary field with a `Codec`. Protobuf
+/// clients read the typed fields, so the binary field is left empty.
+pub fn encode_with<T: Serialize>(codec: Codec, value: &T) -> Result<Vec<u8>> {
+    if codec.is_schemaless() {
+        codec.encode(value).map_err(|e| e.into())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// `decode_with` decodes a binary field with a `Codec`. Protobuf clients send
+/// the `models` values in one of the schemaless codecs.
+pub fn decode_with<T: DeserializeOwned>(codec: Codec, buf: &[u8]) -> Result<T> {
+    if !codec.is_schemaless() {
+        let msg = format!("binary fields can not be encoded with {}", codec);
+        let err = Error::InvalidArgument { msg };
+        return Err(err);
+    }
+
+    codec.decode(buf).map_err(|e| e.into())
+}
+
+/// `digest_from_proto` converts a `proto` bytes field into a `Digest`.
+pub fn digest_from_proto(buf: &[u8]) -> Result<Digest> {
+    Digest::from_slice(buf).map_err(|e| e.into())
+}
+
+/// `digests_to_proto` converts a set of `Digest`s into `proto::TransactionIds`.
+pub fn digests_to_proto(ids: &BTreeSet<Digest>) -> proto::TransactionIds {
+    let ids = ids.iter().map(|id| id.to_vec()).collect();
+
+    proto::TransactionIds { ids }
+}
+
+/// `digests_from_proto` converts `proto::TransactionIds` into a set of `Digest`s.
+pub fn digests_from_proto(ids: &proto::TransactionIds) -> Result<BTreeSet<Digest>> {
+    ids.ids.iter().map(|id| digest_from_proto(id)).collect()
+}
+
+/// `locktime_to_proto` converts an optional `Timestamp` into a `proto` timestamp,
+/// where zero means no locktime (or expiry).
+fn locktime_to_proto(locktime: Option<Timestamp>) -> i64 {
+    locktime.map(|t| t.to_i64()).unwrap_or(0)
+}
+
+/// `account_to_proto` converts an `Account` into a `proto::Account`.
+pub fn account_to_proto(account: &Account) -> proto::Account {
+    let signers = account
+        .signers
+        .signers
+        .values()
+        .map(|signer| proto::Signer {
+            public_key: signer.public_key.to_vec(),
+            weight: signer.weight,
+        })
+        .collect();
+
+    proto::Account {
+        address: account.address().to_vec(),
+        stage: account.stage as u32,
+        time: account.time.to_i64(),
+        locktime: locktime_to_proto(account.locktime),
+        signers,
+        threshold: account.signers.threshold,
+        amount: account.amount,
+        counter: account.counter,
+        transaction_id: account
+            .transaction_id
+            .map(|id| id.to_vec())
+            .unwrap_or_default(),
+    }
+}
+
+/// `input_to_proto` converts an `Input` into a `proto::Input`.
+pub fn input_to_proto(input: &Input) -> proto::Input {
+    let signatures = input
+        .signatures
+        .iter()
+        .map(|(public_key, signature)| proto::Signature {
+            public_key: public_key.to_vec(),
+            signature: signature.to_vec(),
+        })
+        .collect();
+
+    proto::Input {
+        account: Some(account_to_proto(&input.account)),
+        signatures,
+        amount: input.amount,
+        distance: input.distance,
+    }
+}
+
+/// `output_to_proto` converts an `Output` into a `proto::Output`.
+pub fn output_to_proto(output: &Output) -> proto::Output {
+    proto::Output {
+        address: output.address.to_vec(),
+        amount: output.amount,
+        custom_len: output.custom_len,
+        custom: output.custom.clone(),
+    }
+}
+
+/// `coinbase_to_proto` converts a `Coinbase` into a `proto::Coinbase`.
+pub fn coinbase_to_proto(coinbase: &Coinbase) -> proto::Coinbase {
+    proto::Coinbase {
+        address: coinbase.address.to_vec(),
+        distance: coinbase.distance,
+        difficulty: coinbase.difficulty,
+        custom_digest: coinbase.custom_digest.to_vec(),
+        amount: coinbase.amount,
+        nonce: coinbase.proof.map(|proof| proof.nonce).unwrap_or(0),
+        digest: coinbase
+            .proof
+            .map(|proof| proof.digest.to_vec())
+            .unwrap_or_default(),
+        mined: coinbase.is_mined(),
+    }
+}
+
+/// `transaction_to_proto` converts a `Transaction` into a `proto::Transaction`,
+/// encoding its raw field with a `Codec`.
+pub fn transaction_to_proto(transaction: &Transaction, codec: Codec) -> Result<proto::Transaction> {
+    let proto_tx = proto::Transaction {
+        id: transaction.id.to_vec(),
+        version: transaction.version.to_string(),
+        stage: transaction.stage as u32,
+        time: transaction.time.to_i64(),
+        locktime: locktime_to_proto(transaction.locktime),
+        distance: transaction.distance,
+        inputs: transaction.inputs.values().map(input_to_proto).collect(),
+        outputs: transaction.outputs.values().map(output_to_proto).collect(),
+        coinbase: transaction.coinbase.as_ref().map(coinbase_to_proto),
+        nonce: transaction.nonce,
+        raw: encode_with(codec, transaction)?,
+        expiry: locktime_to_proto(transaction.expiry),
+        stakes: transaction.stakes.values().map(stake_to_proto).collect(),
+    };
+
+    Ok(proto_tx)
+}
+
+/// `transaction_from_proto` converts the encoding of a `Transaction` in a
+/// `Codec` into a `Transaction`, checking its id.
+pub fn transaction_from_proto(buf: &[u8], codec: Codec) -> Result<Transaction> {
+    let transaction: Transaction = decode_with(codec, buf)?;
+
+    if transaction.id != transaction.calc_id()? {
+        let msg = "invalid transaction id".into();
+        let err = Error::InvalidArgument { msg };
+        return Err(err);
+    }
+
+    Ok(transaction)
+}
+
+/// `unspent_output_to_proto` converts an `UnspentOutput` into a `proto::UnspentOutput`.
+pub fn unspent_output_to_proto(output: &UnspentOutput) -> proto::UnspentOutput {
+    proto::UnspentOutput {
+        transaction_id: output.transaction_id.to_vec(),
+        amount: output.amount,
+    }
+}
+
+/// `unspent_output_from_proto` converts a `proto::UnspentOutput` into an `UnspentOutput`.
+pub fn unspent_output_from_proto(output: &proto::UnspentOutput) -> Result<UnspentOutput> {
+    let output = UnspentOutput {
+        transaction_id: digest_from_proto(&output.transaction_id)?,
+        amount: output.amount,
+    };
+
+    Ok(output)
+}
+
+/// `history_entry_to_proto` converts a `HistoryEntry` into a `proto::HistoryEntry`.
+pub fn history_entry_to_proto(entry: &HistoryEntry) -> proto::HistoryEntry {
+    proto::HistoryEntry {
+        transaction_id: entry.transaction_id.to_vec(),
+        time: entry.time.to_i64(),
+        spent: entry.spent,
+        received: entry.received,
+        reward: entry.reward,
+        applied: entry.applied,
+    }
+}
+
+/// `history_entry_from_proto` converts a `proto::HistoryEntry` into a `HistoryEntry`.
+pub fn history_entry_from_proto(entry: &proto::HistoryEntry) -> Result<HistoryEntry> {
+    let entry = HistoryEntry {
+        time: Timestamp::from_i64(entry.time)?,
+        transaction_id: digest_from_proto(&entry.transaction_id)?,
+        spent: entry.spent,
+        received: entry.received,
+        reward: entry.reward,
+        applied: entry.applied,
+    };
+
+    Ok(entry)
+}
+
+/// `history_to_proto` converts an `AddressHistory` into a `proto::AddressHistory`.
+pub fn history_to_proto(history: &AddressHistory) -> proto::AddressHistory {
+    proto::AddressHistory {
+        address: history.address.to_vec(),
+        total: history.total,
+        entries: history.entries.iter().map(history_entry_to_proto).collect(),
+    }
+}
+
+/// `history_from_proto` converts a `proto::AddressHistory` into an `AddressHistory`.
+pub fn history_from_proto(history: &proto::AddressHistory) -> Result<AddressHistory> {
+    let entries = history
+        .entries
+        .iter()
+        .map(history_entry_from_proto)
+        .collect::<Result<Vec<HistoryEntry>>>()?;
+
+    let history = AddressHistory {
+        address: digest_from_proto(&history.address)?,
+        total: history.total,
+        entries,
+    };
+
+    Ok(history)
+}
+
+/// `event_to_proto` converts a `ProtocolEvent` into its gRPC message.
+pub fn event_to_proto(event: &ProtocolEvent) -> proto::Event {
+    let kind = match event {
+        ProtocolEvent::TransactionAdded { tx_id, addresses } => {
+            proto::event::Kind::TransactionAdded(proto::TransactionAdded {
+                tx_id: tx_id.to_vec(),
+                addresses: addresses.iter().map(|a| a.to_vec()).collect(),
+            })
+        }
+        ProtocolEvent::TransactionAccepted { tx_id, addresses } => {
+            proto::event::Kind::TransactionAccepted(proto::TransactionAccepted {
+                tx_id: tx_id.to_vec(),
+                addresses: addresses.iter().map(|a| a.to_vec()).collect(),
+            })
+        }
+        ProtocolEvent::TransactionRejected {
+            tx_id,
+            reason,
+            addresses,
+        } => proto::event::Kind::TransactionRejected(proto::TransactionRejected {
+            tx_id: tx_id.to_vec(),
+            reason: reason.to_owned(),
+            addresses: addresses.iter().map(|a| a.to_vec()).collect(),
+        }),
+        ProtocolEvent::ConflictDetected { conflict_set } => {
+            proto::event::Kind::ConflictDetected(proto::ConflictDetected {
+                address: conflict_set.address.to_vec(),
+                transactions: conflict_set
+                    .transactions
+                    .iter()
+                    .map(|id| id.to_vec())
+                    .collect(),
+                preferred: conflict_set
+                    .preferred
+                    .map(|id| id.to_vec())
+                    .unwrap_or_default(),
+            })
+        }
+        ProtocolEvent::WatchTriggered { tx_id, address, .. } => {
+            proto::event::Kind::WatchTriggered(proto::WatchTriggered {
+                tx_id: tx_id.to_vec(),
+                address: address.to_vec(),
+            })
+        }
+        ProtocolEvent::ConsensusError { msg } => {
+            proto::event::Kind::ConsensusError(proto::ConsensusError {
+                msg: msg.to_owned(),
+            })
+        }
+        ProtocolEvent::TransactionStalled {
+            tx_id,
+            requeries,
+            addresses,
+        } => proto::event::Kind::TransactionStalled(proto::TransactionStalled {
+            tx_id: tx_id.to_vec(),
+            requeries: *requeries,
+            addresses: addresses.iter().map(|a| a.to_vec()).collect(),
+        }),
+    };
+
+    proto::Event { kind: Some(kind) }
+}
+
+/// `event_kind_to_proto` converts an `EventKind` into a `proto::EventKind`.
+pub fn event_kind_to_proto(kind: EventKind) -> proto::EventKind {
+    match kind {
+        EventKind::NewTransaction => proto::EventKind::NewTransaction,
+        EventKind::Accepted => proto::EventKind::Accepted,
+        EventKind::Rejected => proto::EventKind::Rejected,
+        EventKind::Conflict => proto::EventKind::Conflict,
+        EventKind::Watch => proto::EventKind::Watch,
+        EventKind::Error => proto::EventKind::Error,
+        EventKind::Stalled => proto::EventKind::Stalled,
+    }
+}
+
+/// `event_kind_from_proto` converts the value of a `proto::EventKind` into an `EventKind`.
+pub fn event_kind_from_proto(kind: i32) -> Result<EventKind> {
+    match proto::EventKind::from_i32(kind) {
+        Some(proto::EventKind::NewTransaction) => Ok(EventKind::NewTransaction),
+        Some(proto::EventKind::Accepted) => Ok(EventKind::Accepted),
+        Some(proto::EventKind::Rejected) => Ok(EventKind::Rejected),
+        Some(proto::EventKind::Conflict) => Ok(EventKind::Conflict),
+        Some(proto::EventKind::Watch) => Ok(EventKind::Watch),
+        Some(proto::EventKind::Error) => Ok(EventKind::Error),
+        Some(proto::EventKind::Stalled) => Ok(EventKind::Stalled),
+        None => {
+            let msg = format!("invalid event kind: {}", kind);
+            let err = Error::InvalidArgument { msg };
+            Err(err)
+        }
+    }
+}
+
+/// `event_filter_to_proto` converts an `EventFilter` into a `proto::SubscribeRequest`.
+pub fn event_filter_to_proto(filter: &EventFilter) -> proto::SubscribeRequest {
+    proto::SubscribeRequest {
+        kinds: filter
+            .kinds
+            .iter()
+            .map(|kind| event_kind_to_proto(*kind) as i32)
+            .collect(),
+        addresses: filter.addresses.iter().map(|a| a.to_vec()).collect(),
+    }
+}
+
+/// `event_filter_from_proto` converts a `proto::SubscribeRequest` into an `EventFilter`.
+pub fn event_filter_from_proto(request: &proto::SubscribeRequest) -> Result<EventFilter> {
+    let mut filter = EventFilter::default();
+
+    for kind in request.kinds.iter() {
+        filter.kinds.insert(event_kind_from_proto(*kind)?);
+    }
+
+    for address in request.addresses.iter() {
+        filter.addresses.insert(digest_from_proto(address)?);
+    }
+
+    Ok(filter)
+}
+
+/// `node_to_proto` converts a `Node` into a `proto::Node`.
+pub fn node_to_proto(node: &Node) -> proto::Node {
+    proto::Node {
+        id: node.id.to_vec(),
+        address: node.address.clone(),
+        stage: node.stage as u32,
+        last_seen: node.last_seen.to_i64(),
+        addresses: node.addresses.iter().map(|a| a.to_string()).collect(),
+        services: node.services.iter().map(|s| s.to_string()).collect(),
+        latency: node.latency.unwrap_or_default(),
+    }
+}
+
+/// `node_ban_to_proto` converts a `NodeBan` into its protobuf message.
+pub fn node_ban_to_proto(ban: &NodeBan) -> proto::NodeBan {
+    proto::NodeBan {
+        id: ban.id.to_vec(),
+        address: ban.address.clone(),
+        reason: ban.reason.clone(),
+        banned_at: ban.banned_at.to_i64(),
+        until: ban.until.to_i64(),
+    }
+}
+
+/// `stake_to_proto` converts a `Stake` into its protobuf message.
+pub fn stake_to_proto(stake: &Stake) -> proto::Stake {
+    proto::Stake {
+        address: stake.address.to_vec(),
+        stage: stake.stage as u32,
+        amount: stake.amount,
+        time: stake.time.to_i64(),
+        locktime: stake.locktime.to_i64(),
+    }
+}
+
+#[test]
+fn test_convert_digest() {
+    let digest = Digest::random().unwrap();
+
+    let res = digest_from_proto(&digest.to_vec());
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), digest);
+
+    let res = digest_from_proto(&[0; 3]);
+    assert!(res.is_err());
+
+    let ids: BTreeSet<Digest> = (0..10).map(|_| Digest::random().unwrap()).collect();
+    let proto_ids = digests_to_proto(&ids);
+
+    for (id, proto_id) in ids.iter().zip(proto_ids.ids.iter()) {
+        assert_eq!(&id.to_vec(), proto_id);
+    }
+
+    let res = digests_from_proto(&proto_ids);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), ids);
+}
+
+#[test]
+fn test_convert_unspent_output() {
+    let output = UnspentOutput {
+        transaction_id: Digest::random().unwrap(),
+        amount: 10,
+    };
+
+    let proto_output = unspent_output_to_proto(&output);
+
+    let res = unspent_output_from_proto(&proto_output);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), output);
+}
+
+#[test]
+fn test_convert_event_filter() {
+    let mut filter = EventFilter::default();
+    filter.kinds.insert(EventKind::NewTransaction);
+    filter.kinds.insert(EventKind::Conflict);
+    filter.kinds.insert(EventKind::Watch);
+    filter.kinds.insert(EventKind::Error);
+    filter.kinds.insert(EventKind::Stalled);
+    filter.addresses.insert(Digest::random().unwrap());
+
+    let request = event_filter_to_proto(&filter);
+
+    let res = event_filter_from_proto(&request);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), filter);
+
+    let mut request = request;
+    request.kinds.push(100);
+
+    let res = event_filter_from_proto(&request);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_convert_history() {
+    let entry = HistoryEntry {
+        time: Timestamp::now(),
+        transaction_id: Digest::random().unwrap(),
+        spent: 10,
+        received: 5,
+        reward: 0,
+        applied: true,
+    };
+
+    let history = AddressHistory {
+        address: Digest::random().unwrap(),
+        total: 3,
+        entries: vec![entry],
+    };
+
+    let proto_history = history_to_proto(&history);
+
+    let res = history_from_proto(&proto_history);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), history);
+}
+
+#[test]
+fn test_convert_transaction() {
+    let transaction = Transaction::new().unwrap();
+
+    let res = transaction_to_proto(&transaction, Codec::Cbor);
+    assert!(res.is_ok());
+    let proto_tx = res.unwrap();
+
+    assert_eq!(proto_tx.id, transaction.id.to_vec());
+    assert_eq!(proto_tx.distance, transaction.distance);
+    assert_eq!(proto_tx.locktime, 0);
+
+    let res = transaction_from_proto(&proto_tx.raw, Codec::Cbor);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), transaction);
+
+    let mut invalid_tx = transaction.clone();
+    invalid_tx.nonce = invalid_tx.nonce.wrapping_add(1);
+
+    let res = transaction_from_proto(&invalid_tx.to_bytes().unwrap(), Codec::Cbor);
+    assert!(res.is_err());
+
+    let res = transaction_to_proto(&transaction, Codec::MessagePack);
+    assert!(res.is_ok());
+    let proto_tx = res.unwrap();
+
+    let res = transaction_from_proto(&proto_tx.raw, Codec::Cbor);
+    assert!(res.is_err());
+
+    let res = transaction_from_proto(&proto_tx.raw, Codec::MessagePack);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), transaction);
+
+    let res = transaction_to_proto(&transaction, Codec::Protobuf);
+    assert!(res.is_ok());
+    let proto_tx = res.unwrap();
+    assert!(proto_tx.raw.is_empty());
+    assert_eq!(proto_tx.id, transaction.id.to_vec());
+
+    let res = transaction_from_proto(&transaction.to_bytes().unwrap(), Codec::Protobuf);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_convert_codec() {
+    let mut metadata = MetadataMap::new();
+
+    let res = codec_from_metadata(&metadata);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Codec::Cbor);
+
+    for name in Codec::VALID_CODECS.iter() {
+        let codec = Codec::parse(name).unwrap();
+
+        let res = codec_to_metadata(codec, &mut metadata);
+        assert!(res.is_ok());
+
+        let res = codec_from_metadata(&metadata);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), codec);
+    }
+
+    metadata.insert(CODEC_KEY, MetadataValue::from_static("xml"));
+
+    let res = codec_from_metadata(&metadata);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_convert_admin_token() {
+    let mut metadata = MetadataMap::new();
+
+    let res = admin_token_from_metadata(&metadata);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = admin_token_to_metadata("token", &mut metadata);
+    assert!(res.is_ok());
+
+    let res = admin_token_from_metadata(&metadata);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Some("token".into()));
+
+    let res = admin_token_to_metadata("invalid\ntoken", &mut metadata);
+    assert!(res.is_err());
+}
@@ -0,0 +1,239 @@
+//! # Error
+//!
+//! `error` contains the `rpc` crate `Error` type.
+
+use crate::proto::ErrorDetails;
+use bytes::Bytes;
+use crypto::error::Error as CryptoError;
+use log::error::Error as LogError;
+use models::error::Error as ModelsError;
+use models::error_code::{ErrorCategory, ErrorCode};
+use network::error::Error as NetworkError;
+use prost::Message;
+use protocol::error::Error as ProtocolError;
+use serde_cbor;
+use std::convert::From;
+use std::io;
+use tonic::transport::Error as TransportError;
+use tonic::{Code, Status};
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "IO: {}", msg)]
+    IO { msg: String },
+    #[fail(display = "Crypto: {}", msg)]
+    Crypto { msg: String },
+    #[fail(display = "Model: {}", msg)]
+    Model { code: ErrorCode, msg: String },
+    #[fail(display = "Protocol: {}", msg)]
+    Protocol { code: ErrorCode, msg: String },
+    #[fail(display = "Log: {}", msg)]
+    Log { msg: String },
+    #[fail(display = "Parse: {}", msg)]
+    Parse { msg: String },
+    #[fail(display = "Transport: {}", msg)]
+    Transport { msg: String },
+    #[fail(display = "Thread: {}", msg)]
+    Thread { msg: String },
+    #[fail(display = "Invalid argument: {}", msg)]
+    InvalidArgument { msg: String },
+    #[fail(display = "Not found")]
+    NotFound,
+    #[fail(display = "Read only")]
+    ReadOnly,
+    #[fail(display = "Wallet locked")]
+    WalletLocked,
+    #[fail(display = "Unauthorized")]
+    Unauthorized,
+    #[fail(display = "Remote {}: {}", code, msg)]
+    Remote { code: ErrorCode, msg: String },
+}
+
+impl Error {
+    /// `code` returns the `ErrorCode` of the `Error`.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::IO { .. } | Error::Crypto { .. } | Error::Log { .. } | Error::Thread { .. } => {
+                ErrorCode::Internal
+            }
+            Error::Model { code, .. } | Error::Protocol { code, .. } => *code,
+            Error::Remote { code, .. } => *code,
+            Error::Parse { .. } => ErrorCode::InvalidEncoding,
+            Error::Transport { .. } => ErrorCode::Network,
+            Error::InvalidArgument { .. } => ErrorCode::InvalidArgument,
+            Error::NotFound => ErrorCode::NotFound,
+            Error::ReadOnly => ErrorCode::ReadOnly,
+            Error::WalletLocked => ErrorCode::WalletLocked,
+            Error::Unauthorized => ErrorCode::NotAllowed,
+        }
+    }
+
+    /// `details` returns the `ErrorDetails` of the `Error`, sent with its `Status`.
+    pub fn details(&self) -> ErrorDetails {
+        let code = self.code();
+
+        ErrorDetails {
+            code: code.to_u16() as u32,
+            category: code.category().to_string(),
+            retryable: code.is_retryable(),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        let msg = format!("{}", error);
+        Error::IO { msg }
+    }
+}
+
+impl From<serde_cbor::error::Error> for Error {
+    fn from(err: serde_cbor::error::Error) -> Error {
+        let msg = format!("{}", err);
+        Error::Parse { msg }
+    }
+}
+
+impl From<CryptoError> for Error {
+    fn from(error: CryptoError) -> Error {
+        let msg = format!("{}", error);
+        Error::InvalidArgument { msg }
+    }
+}
+
+impl From<ModelsError> for Error {
+    fn from(error: ModelsError) -> Error {
+        match error {
+            ModelsError::NotFound => Error::NotFound,
+            ModelsError::WalletLocked => Error::WalletLocked,
+            _ => {
+                let code = error.code();
+                let msg = format!("{}", error);
+                Error::Model { code, msg }
+            }
+        }
+    }
+}
+
+impl From<NetworkError> for Error {
+    fn from(error: NetworkError) -> Error {
+        let msg = format!("{}", error);
+
+        match error {
+            NetworkError::Parse { .. } => Error::Parse { msg },
+            _ => Error::InvalidArgument { msg },
+        }
+    }
+}
+
+impl From<ProtocolError> for Error {
+    fn from(error: ProtocolError) -> Error {
+        match error {
+            ProtocolError::NotFound => Error::NotFound,
+            ProtocolError::ReadOnly => Error::ReadOnly,
+            _ => {
+                let code = error.code();
+                let msg = format!("{}", error);
+                Error::Protocol { code, msg }
+            }
+        }
+    }
+}
+
+impl From<LogError> for Error {
+    fn from(error: LogError) -> Error {
+        let msg = format!("{}", error);
+        Error::Log { msg }
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(error: TransportError) -> Error {
+        let msg = format!("{}", error);
+        Error::Transport { msg }
+    }
+}
+
+impl From<Status> for Error {
+    fn from(status: Status) -> Error {
+        let msg = status.message().to_string();
+
+        let details = ErrorDetails::decode(status.details()).ok();
+        let code = details.and_then(|details| ErrorCode::from_u16(details.code as u16).ok());
+
+        if let Some(code) = code {
+            return match code {
+                ErrorCode::NotFound => Error::NotFound,
+                ErrorCode::ReadOnly => Error::ReadOnly,
+                ErrorCode::WalletLocked => Error::WalletLocked,
+                ErrorCode::NotAllowed => Error::Unauthorized,
+                _ => Error::Remote { code, msg },
+            };
+        }
+
+        match status.code() {
+            Code::NotFound => Error::NotFound,
+            Code::FailedPrecondition => Error::ReadOnly,
+            Code::PermissionDenied => Error::WalletLocked,
+            Code::Unauthenticated => Error::Unauthorized,
+            Code::InvalidArgument => Error::InvalidArgument { msg },
+            _ => Error::Transport { msg },
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(error: Error) -> Status {
+        let msg = format!("{}", error);
+
+        let code = error.code();
+
+        let status_code = match code {
+            ErrorCode::NotFound => Code::NotFound,
+            ErrorCode::ReadOnly => Code::FailedPrecondition,
+            ErrorCode::WalletLocked => Code::PermissionDenied,
+            ErrorCode::NotAllowed => Code::Unauthenticated,
+            _ => match code.category() {
+                ErrorCategory::Validation => Code::InvalidArgument,
+                ErrorCategory::Network => Code::Unavailable,
+                _ => Code::Internal,
+            },
+        };
+
+        let mut details = Vec::new();
+
+        if error.details().encode(&mut details).is_err() {
+            return Status::new(status_code, msg);
+        }
+
+        Status::with_details(status_code, msg, Bytes::from(details))
+    }
+}
+
+#[test]
+fn test_error_status() {
+    let error = Error::from(ProtocolError::InvalidTransaction);
+    assert_eq!(error.code(), ErrorCode::InvalidTransaction);
+
+    let status = Status::from(error);
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let error = Error::from(status);
+    assert_eq!(error.code(), ErrorCode::InvalidTransaction);
+
+    let status = Status::from(Error::NotFound);
+    assert_eq!(status.code(), Code::NotFound);
+
+    let error = Error::from(status);
+    assert_eq!(error.code(), ErrorCode::NotFound);
+
+    let status = Status::from(Error::Unauthorized);
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let error = Error::from(status);
+    assert_eq!(error.code(), ErrorCode::NotAllowed);
+
+    let status = Status::new(Code::Unavailable, "unavailable");
+    let error = Error::from(status);
+    assert_eq!(error.code(), ErrorCode::Network);
+}
@@ -0,0 +1,202 @@
+//! # Keyring
+//!
+//! `keyring` contains the `Keyring` type, which keeps the wallets unlocked
+//! on the daemon until their timeout expires.
+
+use crate::error::Error;
+use crate::result::Result;
+use models::address::Address;
+use models::stage::Stage;
+use models::timestamp::Timestamp;
+use models::traits::Storable;
+use models::wallet::Wallet;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use store::traits::Store;
+
+/// `Keyring` keeps the unlocked `Wallet`s by address, together with
+/// the time their unlock expires. The failed unlocks delay the next ones
+/// with an exponential backoff.
+#[derive(Clone, Default, Debug)]
+pub struct Keyring {
+    wallets: BTreeMap<Address, (Wallet, Timestamp)>,
+    failed_unlocks: u32,
+    unlock_after: Option<Instant>,
+}
+
+impl Keyring {
+    /// `MAX_TIMEOUT` is the maximum time in seconds a `Wallet` can stay unlocked.
+    pub const MAX_TIMEOUT: u64 = 86_400;
+
+    /// `UNLOCK_BACKOFF` is the time in milliseconds a first failed unlock delays
+    /// the next one. The delay doubles at each consecutive failure.
+    pub const UNLOCK_BACKOFF: u64 = 500;
+
+    /// `MAX_UNLOCK_BACKOFF` is the maximum time in milliseconds a failed unlock
+    /// delays the next one.
+    pub const MAX_UNLOCK_BACKOFF: u64 = 300_000;
+
+    /// `new` creates a new `Keyring`.
+    pub fn new() -> Keyring {
+        Keyring::default()
+    }
+
+    /// `find_wallet` returns the `Wallet` in the store whose single signer
+    /// `Signers` have a given address.
+    pub fn find_wallet<S: Store>(store: &S, stage: Stage, address: &Address) -> Result<Wallet> {
+        for wallet in Wallet::query(store, stage, None, None, None, None)? {
+            if &wallet.to_signers()?.address == address {
+                return Ok(wallet);
+            }
+        }
+
+        let err = Error::NotFound;
+        Err(err)
+    }
+
+    /// `unlock` unlocks an encrypted `Wallet` with a passphrase for a timeout
+    /// in seconds, returning the time the unlock expires.
+    pub fn unlock(
+        &mut self,
+        wallet: &Wallet,
+        passphrase: &[u8],
+        timeout: u64,
+    ) -> Result<Timestamp> {
+        if timeout == 0 || timeout > Keyring::MAX_TIMEOUT {
+            let msg = format!("invalid timeout: {}", timeout);
+            let err = Error::InvalidArgument { msg };
+            return Err(err);
+        }
+
+        if let Some(unlock_after) = self.unlock_after {
+            let now = Instant::now();
+
+            if now < unlock_after {
+                let msg = format!(
+                    "too many failed unlocks, retry in {} ms",
+                    (unlock_after - now).as_millis()
+                );
+                let err = Error::InvalidArgument { msg };
+                return Err(err);
+            }
+        }
+
+        let address = wallet.to_signers()?.address;
+
+        let unlocked = match wallet.unlock(passphrase) {
+            Ok(unlocked) => unlocked,
+            Err(_) => {
+                self.fail_unlock();
+
+                let msg = "invalid passphrase".into();
+                let err = Error::InvalidArgument { msg };
+                return Err(err);
+            }
+        };
+
+        self.failed_unlocks = 0;
+        self.unlock_after = None;
+
+        let expires = Timestamp::now().after(timeout);
+
+        self.wallets.insert(address, (unlocked, expires));
+
+        Ok(expires)
+    }
+
+    /// `lock` locks the `Wallet` with a given address, returning if it was unlocked.
+    pub fn lock(&mut self, address: &Address) -> bool {
+        self.purge();
+        self.wallets.remove(address).is_some()
+    }
+
+    /// `expires` returns the time the unlock of the `Wallet` with a given address
+    /// expires, if the `Wallet` is unlocked.
+    pub fn expires(&mut self, address: &Address) -> Option<Timestamp> {
+        self.purge();
+        self.wallets.get(address).map(|(_, expires)| *expires)
+    }
+
+    /// `get` returns the unlocked `Wallet` with a given address.
+    pub fn get(&mut self, address: &Address) -> Result<Wallet> {
+        self.purge();
+
+        if let Some((wallet, _)) = self.wallets.get(address) {
+            Ok(wallet.clone())
+        } else {
+            let err = Error::WalletLocked;
+            Err(err)
+        }
+    }
+
+    /// `fail_unlock` records a failed unlock, delaying the next one.
+    fn fail_unlock(&mut self) {
+        let shift = cmp::min(self.failed_unlocks, 16);
+        let backoff = cmp::min(
+            Keyring::UNLOCK_BACKOFF << shift,
+            Keyring::MAX_UNLOCK_BACKOFF,
+        );
+
+        self.failed_unlocks = self.failed_unlocks.saturating_add(1);
+        self.unlock_after = Some(Instant::now() + Duration::from_millis(backoff));
+    }
+
+    /// `purge` removes the `Wallet`s whose unlock expired.
+    fn purge(&mut self) {
+        let now = Timestamp::now();
+        let expired: Vec<Address> = self
+            .wallets
+            .iter()
+            .filter(|(_, (_, expires))| *expires <= now)
+            .map(|(address, _)| *address)
+            .collect();
+
+        for address in expired {
+            self.wallets.remove(&address);
+        }
+    }
+}
+
+#[test]
+fn test_keyring_unlock() {
+    let stage = Stage::default();
+    let passphrase = b"passphrase";
+
+    let mut wallet = Wallet::new(stage).unwrap();
+    wallet.encrypt(passphrase).unwrap();
+    let address = wallet.to_signers().unwrap().address;
+
+    let mut keyring = Keyring::new();
+
+    let res = keyring.get(&address);
+    assert!(res.is_err());
+
+    let res = keyring.unlock(&wallet, passphrase, 0);
+    assert!(res.is_err());
+
+    let res = keyring.unlock(&wallet, passphrase, Keyring::MAX_TIMEOUT + 1);
+    assert!(res.is_err());
+
+    let res = keyring.unlock(&wallet, b"wrong passphrase", 60);
+    assert!(res.is_err());
+
+    let res = keyring.unlock(&wallet, passphrase, 60);
+    assert!(res.is_err());
+
+    std::thread::sleep(Duration::from_millis(Keyring::UNLOCK_BACKOFF));
+
+    let res = keyring.unlock(&wallet, passphrase, 60);
+    assert!(res.is_ok());
+    assert_eq!(keyring.expires(&address), Some(res.unwrap()));
+
+    let res = keyring.get(&address);
+    assert!(res.is_ok());
+    assert!(!res.unwrap().is_encrypted());
+
+    assert!(keyring.lock(&address));
+    assert!(!keyring.lock(&address));
+
+    let res = keyring.get(&address);
+    assert!(res.is_err());
+}
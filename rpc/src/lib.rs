@@ -0,0 +1,32 @@
+//! # RPC
+//!
+//! `rpc` contains Alsacoin`s gRPC interface types and functions.
+
+#[macro_use]
+extern crate failure;
+
+/// `error` contains the error type used in the crate.
+pub mod error;
+
+/// `result` contains the result type used in the crate.
+pub mod result;
+
+/// `proto` contains the types and services generated from the `.proto` definitions.
+pub mod proto {
+    tonic::include_proto!("alsacoin");
+}
+
+/// `convert` contains the conversions between the `models` and `proto` types.
+pub mod convert;
+
+/// `keyring` contains the type keeping the wallets unlocked on the daemon.
+pub mod keyring;
+
+/// `server` contains the gRPC server type and functions.
+pub mod server;
+
+/// `client` contains the gRPC client type and functions.
+pub mod client;
+
+pub use self::client::RpcClient;
+pub use self::server::RpcServer;
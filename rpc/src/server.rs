@@ -0,0 +1,754 @@
+//! # Server
+//!
+//! `server` contains the gRPC server type and functions.
+
+use crate::convert::*;
+use crate::error::Error;
+use crate::keyring::Keyring;
+use crate::proto;
+use crate::proto::admin_service_server::{AdminService, AdminServiceServer};
+use crate::proto::consensus_service_server::{ConsensusService, ConsensusServiceServer};
+use crate::proto::dag_service_server::{DagService, DagServiceServer};
+use crate::proto::event_service_server::{EventService, EventServiceServer};
+use crate::proto::ledger_service_server::{LedgerService, LedgerServiceServer};
+use crate::proto::peer_service_server::{PeerService, PeerServiceServer};
+use crate::proto::transaction_service_server::{TransactionService, TransactionServiceServer};
+use crate::proto::wallet_service_server::{WalletService, WalletServiceServer};
+use crate::result::Result;
+use crypto::ecc::ed25519::SecretKey;
+use crypto::hash::{Blake512Hasher, Digest};
+use log::level::LogLevel;
+use log::logger::Logger;
+use models::address::Address;
+use models::node::Node;
+use models::node_ban::NodeBan;
+use models::traits::Storable;
+use network::traits::Network;
+use protocol::network::{broadcast_transaction, handle_node};
+use protocol::state::ProtocolState;
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::result::Result as StdResult;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use store::traits::Store;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio::task;
+use tonic::metadata::MetadataMap;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+/// `RpcResult` is the result type of the gRPC services.
+type RpcResult<T> = StdResult<Response<T>, Status>;
+
+/// `RpcServer` is the gRPC server type. It exposes the protocol state as an
+/// alternative integration point alongside the internal CBOR protocol.
+pub struct RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    state: Arc<Mutex<ProtocolState<S, P>>>,
+    network: Arc<Mutex<N>>,
+    logger: Arc<Logger>,
+    keyring: Arc<Mutex<Keyring>>,
+    admin_token: Option<Digest>,
+}
+
+impl<S, P, N> Clone for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        RpcServer {
+            state: self.state.clone(),
+            network: self.network.clone(),
+            logger: self.logger.clone(),
+            keyring: self.keyring.clone(),
+            admin_token: self.admin_token,
+        }
+    }
+}
+
+impl<S, P, N> RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    /// `MAX_PEERS` is the maximum number of peers returned by `list_peers`.
+    pub const MAX_PEERS: u32 = 1000;
+
+    /// `MAX_HISTORY` is the maximum number of history entries returned by `get_history`.
+    pub const MAX_HISTORY: u32 = 1000;
+
+    /// `new` creates a new `RpcServer`.
+    pub fn new(
+        state: Arc<Mutex<ProtocolState<S, P>>>,
+        network: Arc<Mutex<N>>,
+        logger: Arc<Logger>,
+    ) -> RpcServer<S, P, N> {
+        RpcServer {
+            state,
+            network,
+            logger,
+            keyring: Arc::new(Mutex::new(Keyring::new())),
+            admin_token: None,
+        }
+    }
+
+    /// `set_admin_token` sets the token authorizing the admin requests. The
+    /// admin service rejects all the requests until a token is set.
+    pub fn set_admin_token(&mut self, token: &str) -> Result<()> {
+        if token.is_empty() {
+            let msg = "invalid admin token".into();
+            let err = Error::InvalidArgument { msg };
+            return Err(err);
+        }
+
+        self.admin_token = Some(Blake512Hasher::hash(token.as_bytes()));
+
+        Ok(())
+    }
+
+    /// `serve` serves the gRPC services on an address.
+    pub async fn serve(self, address: SocketAddr) -> Result<()> {
+        let msg = format!("serving gRPC on {}", address);
+        self.logger.log_info(&msg)?;
+
+        Server::builder()
+            .add_service(TransactionServiceServer::new(self.clone()))
+            .add_service(DagServiceServer::new(self.clone()))
+            .add_service(LedgerServiceServer::new(self.clone()))
+            .add_service(PeerServiceServer::new(self.clone()))
+            .add_service(ConsensusServiceServer::new(self.clone()))
+            .add_service(WalletServiceServer::new(self.clone()))
+            .add_service(AdminServiceServer::new(self.clone()))
+            .add_service(EventServiceServer::new(self))
+            .serve(address)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// `run` serves the gRPC services on an address, blocking the current thread.
+    pub fn run(self, address: SocketAddr) -> Result<()> {
+        let mut runtime = Runtime::new()?;
+        runtime.block_on(self.serve(address))
+    }
+
+    /// `log_request` logs a gRPC request.
+    fn log_request(&self, method: &str) -> Result<()> {
+        let msg = format!("gRPC request: {}", method);
+        self.logger.log_debug(&msg).map_err(|e| e.into())
+    }
+
+    /// `authorize` checks the admin token in the metadata of a request. The
+    /// tokens are compared by their digests, in constant time.
+    fn authorize(&self, metadata: &MetadataMap) -> Result<()> {
+        let token = admin_token_from_metadata(metadata)?;
+
+        // NB: the Digest equality is constant-time.
+        match (self.admin_token, token) {
+            (Some(admin_token), Some(token))
+                if admin_token == Blake512Hasher::hash(token.as_bytes()) =>
+            {
+                Ok(())
+            }
+            _ => {
+                let err = Error::Unauthorized;
+                Err(err)
+            }
+        }
+    }
+
+    /// `runtime_config` returns the current runtime parameters of the node.
+    fn runtime_config(&self) -> proto::RuntimeConfig {
+        let state = self.state.lock().unwrap();
+
+        let mut config = state.config.clone();
+        config.populate();
+
+        proto::RuntimeConfig {
+            log_level: self.logger.level().to_string(),
+            target_peers: state.connections.target_peers,
+            mining_duty_cycle: config.mining_duty_cycle.unwrap(),
+            mining_threads: config.mining_threads.unwrap(),
+            mining_max_backlog: config.mining_max_backlog.unwrap(),
+        }
+    }
+
+    /// `wallet_status` returns the status of the wallet with a given address
+    /// in the store.
+    fn wallet_status(&self, address: &Address) -> Result<proto::WalletStatus> {
+        let wallet = {
+            let state = self.state.lock().unwrap();
+            let store = state.store.read().unwrap();
+            Keyring::find_wallet(&*store, state.stage, address)?
+        };
+
+        let expires = self.keyring.lock().unwrap().expires(address);
+
+        let status = proto::WalletStatus {
+            address: address.to_vec(),
+            encrypted: wallet.is_encrypted(),
+            unlocked: !wallet.is_encrypted() || expires.is_some(),
+            expires: expires.map(|t| t.to_i64()).unwrap_or_default(),
+        };
+
+        Ok(status)
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> TransactionService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn submit_transaction(
+        &self,
+        request: Request<proto::SubmitTransactionRequest>,
+    ) -> RpcResult<proto::TransactionId> {
+        self.log_request("submit_transaction")?;
+
+        let codec = codec_from_metadata(request.metadata())?;
+        let transaction = transaction_from_proto(&request.into_inner().transaction, codec)?;
+        let id = transaction.id.to_vec();
+
+        let state = self.state.clone();
+        let network = self.network.clone();
+        let logger = self.logger.clone();
+
+        // NB: the protocol functions block on the state and the network.
+        task::spawn_blocking(move || broadcast_transaction(state, network, logger, &transaction))
+            .await
+            .map_err(|e| {
+                let msg = format!("{}", e);
+                Error::Thread { msg }
+            })?
+            .map_err(Error::from)?;
+
+        Ok(Response::new(proto::TransactionId { id }))
+    }
+
+    async fn get_transaction(
+        &self,
+        request: Request<proto::TransactionId>,
+    ) -> RpcResult<proto::Transaction> {
+        self.log_request("get_transaction")?;
+
+        let codec = codec_from_metadata(request.metadata())?;
+        let tx_id = digest_from_proto(&request.into_inner().id)?;
+        let transaction = self
+            .state
+            .lock()
+            .unwrap()
+            .get_transaction(&tx_id)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(transaction_to_proto(&transaction, codec)?))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> DagService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn get_ancestors(
+        &self,
+        request: Request<proto::TransactionId>,
+    ) -> RpcResult<proto::TransactionIds> {
+        self.log_request("get_ancestors")?;
+
+        let tx_id = digest_from_proto(&request.into_inner().id)?;
+        let ancestors = self
+            .state
+            .lock()
+            .unwrap()
+            .get_known_ancestors(&tx_id)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(digests_to_proto(&ancestors)))
+    }
+
+    async fn get_successors(
+        &self,
+        request: Request<proto::TransactionId>,
+    ) -> RpcResult<proto::TransactionIds> {
+        self.log_request("get_successors")?;
+
+        let tx_id = digest_from_proto(&request.into_inner().id)?;
+        let successors = self
+            .state
+            .lock()
+            .unwrap()
+            .get_transaction_successors(&tx_id)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(digests_to_proto(&successors)))
+    }
+
+    async fn get_status(
+        &self,
+        request: Request<proto::TransactionId>,
+    ) -> RpcResult<proto::TransactionStatus> {
+        self.log_request("get_status")?;
+
+        let tx_id = digest_from_proto(&request.into_inner().id)?;
+        let state = self.state.lock().unwrap();
+
+        let mut status = proto::TransactionStatus {
+            id: tx_id.to_vec(),
+            known: state.state.lookup_known_transaction(&tx_id),
+            queried: state.state.lookup_queried_transaction(&tx_id),
+            chit: state.state.get_transaction_chit(&tx_id).unwrap_or(false),
+            confidence: state.state.get_transaction_confidence(&tx_id).unwrap_or(0),
+            ..Default::default()
+        };
+
+        if status.known {
+            status.preferred = state.is_preferred(&tx_id).map_err(Error::from)?;
+            status.strongly_preferred = state.is_strongly_preferred(&tx_id).map_err(Error::from)?;
+            status.accepted = state.is_accepted(&tx_id).map_err(Error::from)?;
+        }
+
+        Ok(Response::new(status))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> LedgerService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn get_balance(
+        &self,
+        request: Request<proto::AddressRequest>,
+    ) -> RpcResult<proto::Balance> {
+        self.log_request("get_balance")?;
+
+        let address = digest_from_proto(&request.into_inner().address)?;
+        let balance = self
+            .state
+            .lock()
+            .unwrap()
+            .get_balance(&address)
+            .map_err(Error::from)?;
+
+        let balance = proto::Balance {
+            address: address.to_vec(),
+            balance,
+        };
+
+        Ok(Response::new(balance))
+    }
+
+    async fn get_unspent_outputs(
+        &self,
+        request: Request<proto::AddressRequest>,
+    ) -> RpcResult<proto::UnspentOutputs> {
+        self.log_request("get_unspent_outputs")?;
+
+        let address = digest_from_proto(&request.into_inner().address)?;
+        let outputs = self
+            .state
+            .lock()
+            .unwrap()
+            .get_unspent_outputs(&address)
+            .map_err(Error::from)?;
+
+        let outputs = proto::UnspentOutputs {
+            address: address.to_vec(),
+            outputs: outputs.iter().map(unspent_output_to_proto).collect(),
+        };
+
+        Ok(Response::new(outputs))
+    }
+
+    async fn get_history(
+        &self,
+        request: Request<proto::HistoryRequest>,
+    ) -> RpcResult<proto::AddressHistory> {
+        self.log_request("get_history")?;
+
+        let request = request.into_inner();
+        let address = digest_from_proto(&request.address)?;
+
+        let count = if request.count == 0 || request.count > Self::MAX_HISTORY {
+            Self::MAX_HISTORY
+        } else {
+            request.count
+        };
+
+        let history = self
+            .state
+            .lock()
+            .unwrap()
+            .get_history(&address, Some(count), Some(request.skip))
+            .map_err(Error::from)?;
+
+        Ok(Response::new(history_to_proto(&history)))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> PeerService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn list_peers(
+        &self,
+        request: Request<proto::ListPeersRequest>,
+    ) -> RpcResult<proto::Peers> {
+        self.log_request("list_peers")?;
+
+        let request = request.into_inner();
+
+        let count = if request.count == 0 || request.count > Self::MAX_PEERS {
+            Self::MAX_PEERS
+        } else {
+            request.count
+        };
+
+        let state = self.state.lock().unwrap();
+        let nodes = Node::query(
+            &*state.store.read().unwrap(),
+            state.stage,
+            None,
+            None,
+            Some(count),
+            Some(request.skip),
+        )
+        .map_err(Error::from)?;
+
+        let nodes = nodes.iter().map(node_to_proto).collect();
+
+        Ok(Response::new(proto::Peers { nodes }))
+    }
+
+    async fn add_peer(&self, request: Request<proto::NodeAddress>) -> RpcResult<proto::Node> {
+        self.log_request("add_peer")?;
+        self.authorize(request.metadata())?;
+
+        let stage = self.state.lock().unwrap().stage;
+        let node = Node::new(stage, &request.into_inner().address);
+
+        handle_node(self.state.clone(), &node).map_err(Error::from)?;
+
+        Ok(Response::new(node_to_proto(&node)))
+    }
+
+    async fn remove_peer(&self, request: Request<proto::NodeId>) -> RpcResult<proto::Empty> {
+        self.log_request("remove_peer")?;
+        self.authorize(request.metadata())?;
+
+        let node_id = digest_from_proto(&request.into_inner().id)?;
+        self.state
+            .lock()
+            .unwrap()
+            .remove_node(&node_id)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn ban_peer(&self, request: Request<proto::BanRequest>) -> RpcResult<proto::NodeBan> {
+        self.log_request("ban_peer")?;
+        self.authorize(request.metadata())?;
+
+        let request = request.into_inner();
+
+        if request.duration == 0 || request.duration > NodeBan::MAX_DURATION {
+            let msg = format!("invalid duration: {}", request.duration);
+            let err = Error::InvalidArgument { msg };
+            return Err(err.into());
+        }
+
+        let ban = self
+            .state
+            .lock()
+            .unwrap()
+            .ban(&request.address, request.duration, &request.reason)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(node_ban_to_proto(&ban)))
+    }
+
+    async fn unban_peer(&self, request: Request<proto::NodeAddress>) -> RpcResult<proto::Empty> {
+        self.log_request("unban_peer")?;
+        self.authorize(request.metadata())?;
+
+        self.state
+            .lock()
+            .unwrap()
+            .unban(&request.into_inner().address)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn list_bans(&self, _request: Request<proto::Empty>) -> RpcResult<proto::NodeBans> {
+        self.log_request("list_bans")?;
+
+        let bans = self.state.lock().unwrap().bans().map_err(Error::from)?;
+        let bans = bans.iter().map(node_ban_to_proto).collect();
+
+        Ok(Response::new(proto::NodeBans { bans }))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> ConsensusService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn get_metrics(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> RpcResult<proto::ConsensusMetrics> {
+        self.log_request("get_metrics")?;
+
+        let state = self.state.lock().unwrap();
+
+        let conflict_sets: BTreeSet<_> = state.state.transaction_conflict_set.values().collect();
+
+        let metrics = proto::ConsensusMetrics {
+            stage: state.stage as u32,
+            state_id: state.state.id,
+            known_transactions: state.state.known_transactions.len() as u64,
+            queried_transactions: state.state.queried_transactions.len() as u64,
+            queued_transactions: state.queue.len() as u64,
+            conflict_sets: conflict_sets.len() as u64,
+            known_nodes: state.state.known_nodes.len() as u64,
+            seed_nodes: state.state.seed_nodes.len() as u64,
+            read_only: state.read_only,
+        };
+
+        Ok(Response::new(metrics))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> WalletService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn unlock_wallet(
+        &self,
+        request: Request<proto::UnlockWalletRequest>,
+    ) -> RpcResult<proto::WalletStatus> {
+        self.log_request("unlock_wallet")?;
+        self.authorize(request.metadata())?;
+
+        let request = request.into_inner();
+        let address = digest_from_proto(&request.address)?;
+
+        let wallet = {
+            let state = self.state.lock().unwrap();
+            let store = state.store.read().unwrap();
+            Keyring::find_wallet(&*store, state.stage, &address)?
+        };
+
+        self.keyring.lock().unwrap().unlock(
+            &wallet,
+            request.passphrase.as_bytes(),
+            request.timeout,
+        )?;
+
+        Ok(Response::new(self.wallet_status(&address)?))
+    }
+
+    async fn lock_wallet(
+        &self,
+        request: Request<proto::AddressRequest>,
+    ) -> RpcResult<proto::WalletStatus> {
+        self.log_request("lock_wallet")?;
+        self.authorize(request.metadata())?;
+
+        let address = digest_from_proto(&request.into_inner().address)?;
+        self.keyring.lock().unwrap().lock(&address);
+
+        Ok(Response::new(self.wallet_status(&address)?))
+    }
+
+    async fn get_wallet_status(
+        &self,
+        request: Request<proto::AddressRequest>,
+    ) -> RpcResult<proto::WalletStatus> {
+        self.log_request("get_wallet_status")?;
+
+        let address = digest_from_proto(&request.into_inner().address)?;
+
+        Ok(Response::new(self.wallet_status(&address)?))
+    }
+
+    async fn sign_transaction(
+        &self,
+        request: Request<proto::SignTransactionRequest>,
+    ) -> RpcResult<proto::Transaction> {
+        self.log_request("sign_transaction")?;
+        self.authorize(request.metadata())?;
+
+        let codec = codec_from_metadata(request.metadata())?;
+        let request = request.into_inner();
+        let address = digest_from_proto(&request.address)?;
+        let mut transaction = transaction_from_proto(&request.transaction, codec)?;
+
+        let wallet = self.keyring.lock().unwrap().get(&address)?;
+        let secret_key = SecretKey::from_slice(&wallet.secret_key).map_err(Error::from)?;
+
+        transaction
+            .sign_input(&secret_key, &address)
+            .map_err(Error::from)?;
+
+        Ok(Response::new(transaction_to_proto(&transaction, codec)?))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> EventService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    type SubscribeStream = mpsc::UnboundedReceiver<StdResult<proto::Event, Status>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> RpcResult<Self::SubscribeStream> {
+        self.log_request("subscribe")?;
+
+        let filter = event_filter_from_proto(&request.into_inner())?;
+
+        let receiver = self.state.lock().unwrap().subscribe();
+        let (sender, stream) = mpsc::unbounded_channel();
+
+        // NB: the thread ends, unsubscribing, when the client hangs up.
+        thread::spawn(move || {
+            for event in receiver
+                .iter()
+                .filter(|event| event.is_streamed() && filter.matches(event))
+            {
+                if sender.send(Ok(event_to_proto(&event))).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(stream))
+    }
+}
+
+#[tonic::async_trait]
+impl<S, P, N> AdminService for RpcServer<S, P, N>
+where
+    S: Store + Send + Sync + 'static,
+    P: Store + Send + Sync + 'static,
+    N: Network + Send + 'static,
+{
+    async fn get_runtime_config(
+        &self,
+        request: Request<proto::Empty>,
+    ) -> RpcResult<proto::RuntimeConfig> {
+        self.log_request("get_runtime_config")?;
+        self.authorize(request.metadata())?;
+
+        Ok(Response::new(self.runtime_config()))
+    }
+
+    async fn set_runtime_config(
+        &self,
+        request: Request<proto::RuntimeConfig>,
+    ) -> RpcResult<proto::RuntimeConfig> {
+        self.log_request("set_runtime_config")?;
+        self.authorize(request.metadata())?;
+
+        let request = request.into_inner();
+
+        let log_level = if request.log_level.is_empty() {
+            None
+        } else {
+            let level = LogLevel::parse(&request.log_level).map_err(|_| {
+                let msg = format!("invalid log level: {}", request.log_level);
+                Error::InvalidArgument { msg }
+            })?;
+
+            Some(level)
+        };
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let mut config = state.config.clone();
+
+            if request.mining_duty_cycle != 0 {
+                config.mining_duty_cycle = Some(request.mining_duty_cycle);
+            }
+
+            if request.mining_threads != 0 {
+                config.mining_threads = Some(request.mining_threads);
+            }
+
+            if request.mining_max_backlog != 0 {
+                config.mining_max_backlog = Some(request.mining_max_backlog);
+            }
+
+            state.set_config(&config).map_err(Error::from)?;
+
+            if request.target_peers != 0 {
+                state.connections.target_peers = request.target_peers;
+            }
+        }
+
+        if let Some(level) = log_level {
+            self.logger.set_level(level);
+        }
+
+        let config = self.runtime_config();
+
+        let msg = format!("runtime config updated: {:?}", config);
+        self.logger.log_info(&msg).map_err(Error::from)?;
+
+        Ok(Response::new(config))
+    }
+
+    async fn get_health(&self, request: Request<proto::Empty>) -> RpcResult<proto::Health> {
+        self.log_request("get_health")?;
+        self.authorize(request.metadata())?;
+
+        let state = self.state.lock().unwrap();
+
+        let health = proto::Health {
+            stage: state.stage as u32,
+            read_only: state.read_only,
+            behind: state.is_behind(),
+            peers: state.connections.len() as u64,
+            target_peers: state.connections.target_peers,
+            known_transactions: state.state.known_transactions.len() as u64,
+            queued_transactions: state.queue.len() as u64,
+            known_nodes: state.state.known_nodes.len() as u64,
+            store_size: state.store.read().unwrap().size(),
+            pool_size: state.pool.read().unwrap().size(),
+            dropped_logs: self.logger.dropped(),
+        };
+
+        Ok(Response::new(health))
+    }
+}
@@ -2,13 +2,37 @@
 //!
 //! `alsacoin` is a decentralized dagchain with memory-hard mining based on the Avalanche
 //! consensus.
+//!
+//! The stable public API is exposed through the `prelude` module and the explicit
+//! re-exports below. The internal crates are re-exported for convenience, but they
+//! are not covered by the semver guarantees of `alsacoin`. The stable API is
+//! checked by the `tests/public_api.rs` compile tests, built as a downstream crate.
+
+/// `prelude` contains the curated Alsacoin public API.
+pub mod prelude;
+
+pub use crate::prelude::{
+    Account, Address, CliClient, CliDaemon, Config, ConsensusMessage, Node, PoolFactory,
+    ProtocolClient, ProtocolState, Stage, StoreFactory, Transaction, Wallet,
+};
 
+#[doc(hidden)]
 pub use cli;
+#[doc(hidden)]
 pub use config;
+#[doc(hidden)]
 pub use crypto;
+#[doc(hidden)]
 pub use log;
+#[doc(hidden)]
 pub use mining;
+#[doc(hidden)]
 pub use models;
+#[doc(hidden)]
 pub use network;
+#[doc(hidden)]
 pub use protocol;
+#[doc(hidden)]
+pub use rpc;
+#[doc(hidden)]
 pub use store;
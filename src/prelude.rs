@@ -0,0 +1,34 @@
+//! # Prelude
+//!
+//! `prelude` contains the curated set of types that make up the stable
+//! Alsacoin public API.
+//!
+//! ```
+//! use alsacoin::prelude::*;
+//! ```
+
+pub use cli::{CliClient, CliDaemon};
+pub use config::Config;
+pub use crypto::ecc::ed25519::{KeyPair, PublicKey, SecretKey, Signature};
+pub use crypto::hash::{BalloonParams, Digest};
+pub use log::logger::Logger;
+pub use models::account::Account;
+pub use models::address::Address;
+pub use models::consensus_message::ConsensusMessage;
+pub use models::input::Input;
+pub use models::node::Node;
+pub use models::output::Output;
+pub use models::stage::Stage;
+pub use models::timestamp::Timestamp;
+pub use models::traits::Storable;
+pub use models::transaction::Transaction;
+pub use models::wallet::Wallet;
+pub use network::message::Message;
+pub use network::traits::Network;
+pub use network::NetworkFactory;
+pub use protocol::{
+    ProtocolClient, ProtocolClientServer, ProtocolConsensusServer, ProtocolMinerServer,
+    ProtocolState,
+};
+pub use store::traits::Store;
+pub use store::{PoolFactory, StoreFactory};
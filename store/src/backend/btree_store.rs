@@ -4,10 +4,13 @@
 
 use crate::error::Error;
 use crate::result::Result;
-use crate::traits::{MemoryStore, Store};
+use crate::stats::StoreStats;
+use crate::traits::{MemoryStore, Store, StoreIter};
+use crate::ttl;
 use crypto::random::Random;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::ops::Bound;
 
 /// `BTreeStore` is an implementor of `Store` built on a `BTreeMap`.
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
@@ -17,6 +20,7 @@ pub struct BTreeStore {
     max_size: u32,
     keys_size: u32,
     values_size: u32,
+    default_ttl: Option<u64>,
 }
 
 impl BTreeStore {
@@ -33,6 +37,7 @@ impl BTreeStore {
             max_size,
             keys_size: 0,
             values_size: 0,
+            default_ttl: None,
         };
 
         Ok(store)
@@ -59,6 +64,26 @@ impl BTreeStore {
         }
     }
 
+    /// `_iter` returns an iterator over a range of items of the `BTreeStore`.
+    fn _iter(&self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                let err = Error::InvalidRange;
+                return Err(err);
+            }
+        }
+
+        let from = from.map_or(Bound::Unbounded, |from| Bound::Included(from.to_vec()));
+        let to = to.map_or(Bound::Unbounded, |to| Bound::Excluded(to.to_vec()));
+
+        let iter = self
+            .db
+            .range((from, to))
+            .map(|(k, v)| (k.to_owned(), v.to_owned()));
+
+        Ok(Box::new(iter))
+    }
+
     /// `_query` returns a list of values from the `BTreeStore`.
     fn _query(
         &self,
@@ -155,12 +180,11 @@ impl BTreeStore {
             }
         }
 
-        let len = self.count(from, to, Some(count))?;
+        let values = self.query(from, to, None, None)?;
 
+        let len = values.len() as u32;
         let count = u32::min(count, len);
 
-        let values = self.query(from, to, Some(count), None)?;
-
         let idxs: Vec<u32> = Random::u32_sample_unique_range(0, len, count)?;
 
         let mut res = Vec::new();
@@ -228,8 +252,12 @@ impl BTreeStore {
             return Err(err);
         }
 
-        self.db.insert(key.to_owned(), value.to_owned());
-        self.keys_size += key_size;
+        if let Some(prev_value) = self.db.insert(key.to_owned(), value.to_owned()) {
+            self.values_size -= prev_value.len() as u32;
+        } else {
+            self.keys_size += key_size;
+        }
+
         self.values_size += value_size;
         Ok(())
     }
@@ -427,6 +455,10 @@ impl Store for BTreeStore {
         self.keys_size
     }
 
+    fn keys_count(&self) -> u32 {
+        self.db.len() as u32
+    }
+
     fn values_size(&self) -> u32 {
         self.values_size
     }
@@ -458,6 +490,14 @@ impl Store for BTreeStore {
         self.max_size
     }
 
+    fn set_default_ttl(&mut self, ttl: Option<u64>) {
+        self.default_ttl = ttl;
+    }
+
+    fn get_default_ttl(&self) -> Option<u64> {
+        self.default_ttl
+    }
+
     fn lookup(&self, key: &[u8]) -> Result<bool> {
         Ok(self._lookup(key))
     }
@@ -466,6 +506,10 @@ impl Store for BTreeStore {
         self._get(key)
     }
 
+    fn iter<'a>(&'a self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter<'a>> {
+        self._iter(from, to)
+    }
+
     fn query(
         &self,
         from: Option<&[u8]>,
@@ -484,12 +528,19 @@ impl Store for BTreeStore {
         self._count(from, to, skip)
     }
 
-    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self._insert(key, value)
+    fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<u64>) -> Result<()> {
+        self._insert(key, value)?;
+        ttl::set_expiry(self, key, ttl.or(self.default_ttl))
     }
 
     fn create(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self._create(key, value)
+        self._create(key, value)?;
+
+        if self.default_ttl.is_some() {
+            ttl::set_expiry(self, key, self.default_ttl)?;
+        }
+
+        Ok(())
     }
 
     fn update(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
@@ -501,7 +552,8 @@ impl Store for BTreeStore {
     }
 
     fn remove(&mut self, key: &[u8]) -> Result<()> {
-        self._remove(key)
+        self._remove(key)?;
+        ttl::clear_expiry(self, key)
     }
 
     fn remove_batch(&mut self, _keys: &[&[u8]]) -> Result<()> {
@@ -521,6 +573,24 @@ impl Store for BTreeStore {
         self._clear();
         Ok(())
     }
+
+    fn cleanup_expired(&mut self) -> Result<u32> {
+        ttl::cleanup_expired(self, ttl::now())
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::new();
+
+        for (key, value) in self.db.iter() {
+            stats.add_item(key, value);
+        }
+
+        Ok(stats)
+    }
 }
 
 impl MemoryStore for BTreeStore {}
@@ -569,10 +639,16 @@ fn test_btree_store_ops() {
         let res = store.get(&key);
         assert!(res.is_err());
 
-        let res = store.insert(&key, &value);
+        let res = store.insert(&key, &value, None);
         assert!(res.is_ok());
 
         expected_size += (key.len() + value.len()) as u32;
+        assert_eq!(store.keys_count(), 1);
+
+        let res = store.insert(&key, &value, None);
+        assert!(res.is_ok());
+        assert_eq!(store.keys_count(), 1);
+        assert_eq!(store.size(), expected_size);
 
         let res = store.count(Some(&key), None, None);
         assert!(res.is_ok());
@@ -612,7 +688,7 @@ fn test_btree_store_ops() {
         let res = store.get(&key);
         assert!(res.is_err());
 
-        let res = store.insert(&key, &value);
+        let res = store.insert(&key, &value, None);
         assert!(res.is_ok());
 
         let res = store.clear();
@@ -620,6 +696,7 @@ fn test_btree_store_ops() {
 
         assert_eq!(store.keys_size(), 0);
         assert_eq!(store.values_size(), 0);
+        assert_eq!(store.keys_count(), 0);
     }
 
     let invalid_value_len = max_value_size + 1;
@@ -629,12 +706,12 @@ fn test_btree_store_ops() {
         Random::bytes(invalid_value_len as usize).unwrap(),
     );
 
-    let res = store.insert(&invalid_item.0, &invalid_item.1);
+    let res = store.insert(&invalid_item.0, &invalid_item.1, None);
     assert!(res.is_err());
 
     store.set_max_value_size(invalid_value_len as u32);
 
-    let res = store.insert(&invalid_item.0, &invalid_item.1);
+    let res = store.insert(&invalid_item.0, &invalid_item.1, None);
     assert!(res.is_ok());
 
     let res = store.lookup(&invalid_item.0);
@@ -642,3 +719,105 @@ fn test_btree_store_ops() {
     let found = res.unwrap();
     assert!(found);
 }
+
+#[test]
+fn test_btree_store_ttl() {
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = BTreeStore::new(max_value_size, max_size).unwrap();
+
+    let key_a = vec![0, 1];
+    let key_b = vec![0, 2];
+    let key_c = vec![0, 3];
+    let value = vec![0; 10];
+
+    let res = store.insert(&key_a, &value, Some(0));
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_b, &value, Some(3600));
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_c, &value, Some(0));
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_c, &value, None);
+    assert!(res.is_ok());
+
+    let res = ttl::get_expiry(&store, &key_c);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    assert!(!store.lookup(&key_a).unwrap());
+    assert!(store.lookup(&key_b).unwrap());
+    assert!(store.lookup(&key_c).unwrap());
+
+    let res = ttl::cleanup_expired(&mut store, ttl::now() + 3600);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    assert!(!store.lookup(&key_b).unwrap());
+    assert!(store.lookup(&key_c).unwrap());
+
+    let res = store.count(None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    store.set_default_ttl(Some(0));
+    assert_eq!(store.get_default_ttl(), Some(0));
+
+    let res = store.create(&key_a, &value);
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_b, &value, Some(3600));
+    assert!(res.is_ok());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    assert!(!store.lookup(&key_a).unwrap());
+    assert!(store.lookup(&key_b).unwrap());
+}
+
+#[test]
+fn test_btree_store_iter() {
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = BTreeStore::new(max_value_size, max_size).unwrap();
+
+    let items: Vec<(Vec<u8>, Vec<u8>)> = (0..10u8).map(|i| (vec![1, i], vec![i; 10])).collect();
+
+    for (key, value) in &items {
+        store.insert(key, value, None).unwrap();
+    }
+
+    store.insert(&[2, 0], &[0; 10], None).unwrap();
+
+    let from = vec![1, 2];
+    let to = vec![1, 5];
+
+    let res = store.iter(Some(&to), Some(&from));
+    assert!(res.is_err());
+
+    let res = store.iter(Some(&from), Some(&to));
+    assert!(res.is_ok());
+
+    let mut found: Vec<(Vec<u8>, Vec<u8>)> = res.unwrap().collect();
+    found.sort();
+    assert_eq!(found, items[2..5].to_vec());
+
+    let found: Vec<(Vec<u8>, Vec<u8>)> = store.iter(Some(&[1]), Some(&[2])).unwrap().collect();
+    assert_eq!(found.len(), items.len());
+
+    let found = store.iter(None, None).unwrap().count();
+    assert_eq!(found, items.len() + 1);
+
+    let found = store.iter(Some(&[1]), Some(&[2])).unwrap().take(3).count();
+    assert_eq!(found, 3);
+}
@@ -0,0 +1,425 @@
+//! # CachedStore
+//
+// `cached_store` contains the LRU read-through cache store backend type and functions.
+
+use crate::result::Result;
+use crate::stats::StoreStats;
+use crate::traits::{MemoryStore, PersistentStore, Store, StoreIter, TemporaryStore};
+use crate::ttl;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// `Cache` is the LRU cache of a `CachedStore`.
+#[derive(Clone, Debug, Default)]
+struct Cache {
+    capacity: usize,
+    tick: u64,
+    items: BTreeMap<Vec<u8>, (u64, Vec<u8>)>,
+    ticks: BTreeMap<u64, Vec<u8>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl Cache {
+    /// `new` creates a new `Cache`.
+    fn new(capacity: usize) -> Cache {
+        Cache {
+            capacity,
+            ..Cache::default()
+        }
+    }
+
+    /// `next_tick` returns the next access tick of the `Cache`.
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// `get` returns a cached value, marking it as the most recently used.
+    fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let tick = self.next_tick();
+
+        match self.items.get_mut(key) {
+            Some((prev_tick, value)) => {
+                self.ticks.remove(prev_tick);
+                self.ticks.insert(tick, key.to_owned());
+                *prev_tick = tick;
+                self.hits += 1;
+
+                Some(value.to_owned())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// `put` caches a value, evicting the least recently used one if the
+    /// `Cache` is full.
+    fn put(&mut self, key: &[u8], value: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.remove(key);
+
+        if self.items.len() >= self.capacity {
+            let lru = self.ticks.keys().next().copied();
+
+            if let Some(lru) = lru {
+                if let Some(lru_key) = self.ticks.remove(&lru) {
+                    self.items.remove(&lru_key);
+                }
+            }
+        }
+
+        let tick = self.next_tick();
+        self.ticks.insert(tick, key.to_owned());
+        self.items.insert(key.to_owned(), (tick, value.to_owned()));
+    }
+
+    /// `remove` removes a value from the `Cache`.
+    fn remove(&mut self, key: &[u8]) {
+        if let Some((tick, _)) = self.items.remove(key) {
+            self.ticks.remove(&tick);
+        }
+    }
+
+    /// `clear` removes all the values from the `Cache`.
+    fn clear(&mut self) {
+        self.items.clear();
+        self.ticks.clear();
+    }
+}
+
+/// `CachedStore` is an implementor of `Store` wrapping another `Store` with an
+/// LRU read-through cache of its values, so that the hot items are read and
+/// copied out of the inner `Store` only once.
+///
+/// The writes go to the inner `Store` and invalidate the cached values of the
+/// written keys. The expiration entries are never cached.
+#[derive(Debug)]
+pub struct CachedStore<S: Store> {
+    store: S,
+    cache: Mutex<Cache>,
+}
+
+impl<S: Store> CachedStore<S> {
+    /// `DEFAULT_CAPACITY` is the default number of values cached by a `CachedStore`.
+    pub const DEFAULT_CAPACITY: u32 = 1 << 12;
+
+    /// `new` creates a new `CachedStore` caching up to a number of values
+    /// of a `Store`. A zero capacity disables the cache.
+    pub fn new(store: S, capacity: u32) -> CachedStore<S> {
+        CachedStore {
+            store,
+            cache: Mutex::new(Cache::new(capacity as usize)),
+        }
+    }
+
+    /// `capacity` returns the max number of values cached by the `CachedStore`.
+    pub fn capacity(&self) -> u32 {
+        self.cache.lock().unwrap().capacity as u32
+    }
+
+    /// `cached` returns the number of values cached by the `CachedStore`.
+    pub fn cached(&self) -> u32 {
+        self.cache.lock().unwrap().items.len() as u32
+    }
+
+    /// `hits` returns the number of reads served by the cache of the `CachedStore`.
+    pub fn hits(&self) -> u64 {
+        self.cache.lock().unwrap().hits
+    }
+
+    /// `misses` returns the number of reads served by the inner `Store`.
+    pub fn misses(&self) -> u64 {
+        self.cache.lock().unwrap().misses
+    }
+
+    /// `inner` returns a reference to the inner `Store`.
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// `into_inner` returns the inner `Store`, dropping the cache.
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    /// `invalidate` removes a key from the cache.
+    fn invalidate(&self, key: &[u8]) {
+        self.cache.lock().unwrap().remove(key);
+    }
+
+    /// `invalidate_all` removes all the keys from the cache.
+    fn invalidate_all(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+impl<S: Store> Store for CachedStore<S> {
+    fn keys_size(&self) -> u32 {
+        self.store.keys_size()
+    }
+
+    fn keys_count(&self) -> u32 {
+        self.store.keys_count()
+    }
+
+    fn values_size(&self) -> u32 {
+        self.store.values_size()
+    }
+
+    fn size(&self) -> u32 {
+        self.store.size()
+    }
+
+    fn set_max_value_size(&mut self, size: u32) {
+        self.store.set_max_value_size(size)
+    }
+
+    fn get_max_value_size(&self) -> u32 {
+        self.store.get_max_value_size()
+    }
+
+    fn set_max_size(&mut self, size: u32) -> Result<()> {
+        self.store.set_max_size(size)
+    }
+
+    fn get_max_size(&self) -> u32 {
+        self.store.get_max_size()
+    }
+
+    fn set_default_ttl(&mut self, ttl: Option<u64>) {
+        self.store.set_default_ttl(ttl)
+    }
+
+    fn get_default_ttl(&self) -> Option<u64> {
+        self.store.get_default_ttl()
+    }
+
+    fn lookup(&self, key: &[u8]) -> Result<bool> {
+        if self.cache.lock().unwrap().items.contains_key(key) {
+            return Ok(true);
+        }
+
+        self.store.lookup(key)
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        if ttl::is_ttl_key(key) {
+            return self.store.get(key);
+        }
+
+        if let Some(value) = self.cache.lock().unwrap().get(key) {
+            return Ok(value);
+        }
+
+        let value = self.store.get(key)?;
+        self.cache.lock().unwrap().put(key, &value);
+
+        Ok(value)
+    }
+
+    fn iter<'a>(&'a self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter<'a>> {
+        self.store.iter(from, to)
+    }
+
+    fn query(
+        &self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.store.query(from, to, count, skip)
+    }
+
+    fn sample(&self, from: Option<&[u8]>, to: Option<&[u8]>, count: u32) -> Result<Vec<Vec<u8>>> {
+        self.store.sample(from, to, count)
+    }
+
+    fn count(&self, from: Option<&[u8]>, to: Option<&[u8]>, skip: Option<u32>) -> Result<u32> {
+        self.store.count(from, to, skip)
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<u64>) -> Result<()> {
+        self.invalidate(key);
+        self.store.insert(key, value, ttl)
+    }
+
+    fn create(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.invalidate(key);
+        self.store.create(key, value)
+    }
+
+    fn update(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.invalidate(key);
+        self.store.update(key, value)
+    }
+
+    fn insert_batch(&mut self, items: &[(&[u8], &[u8])]) -> Result<()> {
+        for (key, _) in items {
+            self.invalidate(key);
+        }
+
+        self.store.insert_batch(items)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<()> {
+        self.invalidate(key);
+        self.store.remove(key)
+    }
+
+    fn remove_range(
+        &mut self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        skip: Option<u32>,
+    ) -> Result<()> {
+        self.invalidate_all();
+        self.store.remove_range(from, to, skip)
+    }
+
+    fn remove_batch(&mut self, keys: &[&[u8]]) -> Result<()> {
+        for key in keys {
+            self.invalidate(key);
+        }
+
+        self.store.remove_batch(keys)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.invalidate_all();
+        self.store.clear()
+    }
+
+    fn cleanup_expired(&mut self) -> Result<u32> {
+        self.invalidate_all();
+        self.store.cleanup_expired()
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        self.store.compact()
+    }
+
+    fn stats(&self) -> Result<StoreStats> {
+        self.store.stats()
+    }
+
+    fn begin_batch(&mut self) -> Result<()> {
+        self.store.begin_batch()
+    }
+
+    fn commit_batch(&mut self) -> Result<()> {
+        self.store.commit_batch()
+    }
+
+    fn rollback_batch(&mut self) -> Result<()> {
+        self.invalidate_all();
+        self.store.rollback_batch()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.store.flush()
+    }
+}
+
+impl<S: MemoryStore> MemoryStore for CachedStore<S> {}
+
+impl<S: TemporaryStore> TemporaryStore for CachedStore<S> {}
+
+impl<S: PersistentStore> PersistentStore for CachedStore<S> {}
+
+#[test]
+fn test_cached_store_ops() {
+    use crate::backend::BTreeStore;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+    let capacity = 10;
+
+    let inner = BTreeStore::new(max_value_size, max_size).unwrap();
+    let mut store = CachedStore::new(inner, capacity);
+    assert_eq!(store.capacity(), capacity);
+
+    let items: Vec<(Vec<u8>, Vec<u8>)> = (0..20u8).map(|i| (vec![1, i], vec![i; 10])).collect();
+
+    for (key, value) in &items {
+        let res = store.create(key, value);
+        assert!(res.is_ok());
+    }
+
+    assert_eq!(store.cached(), 0);
+
+    for (key, value) in &items {
+        let res = store.get(key);
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap(), value);
+    }
+
+    assert_eq!(store.cached(), capacity);
+    assert_eq!(store.misses(), 20);
+
+    let res = store.get(&items[19].0);
+    assert!(res.is_ok());
+    assert_eq!(store.hits(), 1);
+
+    let res = store.get(&items[0].0);
+    assert!(res.is_ok());
+    assert_eq!(store.hits(), 1);
+    assert_eq!(store.misses(), 21);
+
+    let res = store.update(&items[19].0, &[0; 5]);
+    assert!(res.is_ok());
+
+    let res = store.get(&items[19].0);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), vec![0; 5]);
+
+    let res = store.remove(&items[19].0);
+    assert!(res.is_ok());
+
+    let res = store.get(&items[19].0);
+    assert!(res.is_err());
+    assert!(!store.lookup(&items[19].0).unwrap());
+
+    let res = store.insert(&[2, 0], &[0; 10], Some(0));
+    assert!(res.is_ok());
+
+    let res = store.get(&[2, 0]);
+    assert!(res.is_ok());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+    assert_eq!(store.cached(), 0);
+
+    let res = store.get(&[2, 0]);
+    assert!(res.is_err());
+
+    let res = store.clear();
+    assert!(res.is_ok());
+    assert_eq!(store.size(), 0);
+
+    let res = store.get(&items[0].0);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_cached_store_disabled() {
+    use crate::backend::BTreeStore;
+
+    let inner = BTreeStore::new(1 << 10, 1 << 20).unwrap();
+    let mut store = CachedStore::new(inner, 0);
+
+    let res = store.insert(&[1], &[1], None);
+    assert!(res.is_ok());
+
+    let res = store.get(&[1]);
+    assert!(res.is_ok());
+    assert_eq!(store.cached(), 0);
+    assert_eq!(store.hits(), 0);
+}
@@ -1,5 +1,14 @@
 pub mod btree_store;
 pub use btree_store::*;
 
+pub mod cached_store;
+pub use cached_store::*;
+
+pub mod sharded_store;
+pub use sharded_store::*;
+
 pub mod unqlite_store;
 pub use unqlite_store::*;
+
+pub mod wal_store;
+pub use wal_store::*;
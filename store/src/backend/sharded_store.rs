@@ -0,0 +1,509 @@
+//! # ShardedStore
+//
+// `sharded_store` contains the sharded in-memory store backend type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::stats::StoreStats;
+use crate::traits::{MemoryStore, Store, StoreIter};
+use crate::ttl;
+use crypto::random::Random;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// `Shard` is a shard of a `ShardedStore`.
+type Shard = RwLock<BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// `ShardedStore` is an in-memory implementor of `Store` built on a list of
+/// `BTreeMap`s, each one guarded by its own lock. The keys are distributed
+/// among the shards by their hash.
+///
+/// The clones of a `ShardedStore` share its shards, so that concurrent writers
+/// holding a clone each only contend on the shard of the written key. The size
+/// limits are checked per write, so concurrent writers can exceed them slightly.
+#[derive(Clone, Debug)]
+pub struct ShardedStore {
+    shards: Arc<Vec<Shard>>,
+    max_value_size: u32,
+    max_size: u32,
+    keys_size: Arc<AtomicU32>,
+    values_size: Arc<AtomicU32>,
+    keys_count: Arc<AtomicU32>,
+    default_ttl: Option<u64>,
+}
+
+impl ShardedStore {
+    /// `DEFAULT_SHARDS` is the default number of shards of a `ShardedStore`.
+    pub const DEFAULT_SHARDS: u32 = 16;
+
+    /// `new` creates a new `ShardedStore` with a number of shards.
+    pub fn new(shards: u32, max_value_size: u32, max_size: u32) -> Result<ShardedStore> {
+        if shards == 0 {
+            let err = Error::InvalidLength;
+            return Err(err);
+        }
+
+        if max_size < max_value_size {
+            let err = Error::InvalidSize;
+            return Err(err);
+        }
+
+        let shards = (0..shards).map(|_| RwLock::new(BTreeMap::new())).collect();
+
+        let store = ShardedStore {
+            shards: Arc::new(shards),
+            max_value_size,
+            max_size,
+            keys_size: Arc::new(AtomicU32::new(0)),
+            values_size: Arc::new(AtomicU32::new(0)),
+            keys_count: Arc::new(AtomicU32::new(0)),
+            default_ttl: None,
+        };
+
+        Ok(store)
+    }
+
+    /// `shards` returns the number of shards of the `ShardedStore`.
+    pub fn shards(&self) -> u32 {
+        self.shards.len() as u32
+    }
+
+    /// `shard` returns the shard of a key, chosen by its FNV-1a hash.
+    fn shard(&self, key: &[u8]) -> &Shard {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+        for byte in key {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+
+        let idx = (hash % self.shards.len() as u64) as usize;
+        &self.shards[idx]
+    }
+
+    /// `_items` returns the sorted items of a range of the `ShardedStore`.
+    fn _items(&self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                let err = Error::InvalidRange;
+                return Err(err);
+            }
+        }
+
+        let mut items = Vec::new();
+
+        for shard in self.shards.iter() {
+            let from = from.map_or(Bound::Unbounded, |from| Bound::Included(from.to_vec()));
+            let to = to.map_or(Bound::Unbounded, |to| Bound::Excluded(to.to_vec()));
+
+            let shard = shard.read().unwrap();
+
+            items.extend(
+                shard
+                    .range((from, to))
+                    .map(|(k, v)| (k.to_owned(), v.to_owned())),
+            );
+        }
+
+        items.sort();
+
+        Ok(items)
+    }
+
+    /// `_write` writes a binary key-value pair in the `ShardedStore`. If required,
+    /// the key is checked to exist or not before writing.
+    fn _write(&self, key: &[u8], value: &[u8], exists: Option<bool>) -> Result<()> {
+        let key_size = key.len() as u32;
+        let value_size = value.len() as u32;
+
+        if value_size > self.get_max_value_size() {
+            let err = Error::InvalidSize;
+            return Err(err);
+        }
+
+        let mut shard = self.shard(key).write().unwrap();
+        let prev_value_size = shard.get(key).map(|value| value.len() as u32);
+
+        match (exists, prev_value_size) {
+            (Some(true), None) => {
+                let err = Error::NotFound;
+                return Err(err);
+            }
+            (Some(false), Some(_)) => {
+                let err = Error::AlreadyFound;
+                return Err(err);
+            }
+            _ => {}
+        }
+
+        let size = if let Some(prev_value_size) = prev_value_size {
+            self.size()
+                .saturating_sub(key_size)
+                .saturating_sub(prev_value_size)
+        } else {
+            self.size()
+        };
+
+        if key_size + value_size + size > self.get_max_size() {
+            let err = Error::InvalidSize;
+            return Err(err);
+        }
+
+        shard.insert(key.to_owned(), value.to_owned());
+
+        if let Some(prev_value_size) = prev_value_size {
+            self.values_size
+                .fetch_sub(prev_value_size, Ordering::SeqCst);
+        } else {
+            self.keys_size.fetch_add(key_size, Ordering::SeqCst);
+            self.keys_count.fetch_add(1, Ordering::SeqCst);
+        }
+
+        self.values_size.fetch_add(value_size, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// `_remove` removes a key-value pair from the `ShardedStore`.
+    fn _remove(&self, key: &[u8]) -> Result<()> {
+        let mut shard = self.shard(key).write().unwrap();
+
+        match shard.remove(key) {
+            Some(value) => {
+                self.keys_size.fetch_sub(key.len() as u32, Ordering::SeqCst);
+                self.values_size
+                    .fetch_sub(value.len() as u32, Ordering::SeqCst);
+                self.keys_count.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            }
+            None => {
+                let err = Error::NotFound;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Store for ShardedStore {
+    fn keys_size(&self) -> u32 {
+        self.keys_size.load(Ordering::SeqCst)
+    }
+
+    fn keys_count(&self) -> u32 {
+        self.keys_count.load(Ordering::SeqCst)
+    }
+
+    fn values_size(&self) -> u32 {
+        self.values_size.load(Ordering::SeqCst)
+    }
+
+    fn size(&self) -> u32 {
+        self.keys_size() + self.values_size()
+    }
+
+    fn set_max_value_size(&mut self, size: u32) {
+        self.max_value_size = size
+    }
+
+    fn get_max_value_size(&self) -> u32 {
+        self.max_value_size
+    }
+
+    fn set_max_size(&mut self, size: u32) -> Result<()> {
+        if size < self.get_max_value_size() {
+            let err = Error::InvalidSize;
+            return Err(err);
+        }
+
+        self.max_size = size;
+
+        Ok(())
+    }
+
+    fn get_max_size(&self) -> u32 {
+        self.max_size
+    }
+
+    fn set_default_ttl(&mut self, ttl: Option<u64>) {
+        self.default_ttl = ttl;
+    }
+
+    fn get_default_ttl(&self) -> Option<u64> {
+        self.default_ttl
+    }
+
+    fn lookup(&self, key: &[u8]) -> Result<bool> {
+        let shard = self.shard(key).read().unwrap();
+        Ok(shard.contains_key(key))
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let shard = self.shard(key).read().unwrap();
+
+        match shard.get(key) {
+            Some(value) => Ok(value.to_owned()),
+            None => {
+                let err = Error::NotFound;
+                Err(err)
+            }
+        }
+    }
+
+    fn iter<'a>(&'a self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter<'a>> {
+        let items = self._items(from, to)?;
+        Ok(Box::new(items.into_iter()))
+    }
+
+    fn query(
+        &self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let items = self._items(from, to)?;
+        let skip = skip.unwrap_or(0) as usize;
+        let count = count.map_or(items.len(), |count| count as usize);
+
+        let values = items
+            .into_iter()
+            .skip(skip)
+            .take(count)
+            .map(|(_, v)| v)
+            .collect();
+
+        Ok(values)
+    }
+
+    fn sample(&self, from: Option<&[u8]>, to: Option<&[u8]>, count: u32) -> Result<Vec<Vec<u8>>> {
+        let values = self.query(from, to, None, None)?;
+
+        let len = values.len() as u32;
+        let count = u32::min(count, len);
+
+        let idxs: Vec<u32> = Random::u32_sample_unique_range(0, len, count)?;
+
+        let res = values
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| idxs.contains(&(*idx as u32)))
+            .map(|(_, value)| value)
+            .collect();
+
+        Ok(res)
+    }
+
+    fn count(&self, from: Option<&[u8]>, to: Option<&[u8]>, skip: Option<u32>) -> Result<u32> {
+        let len = self._items(from, to)?.len() as u32;
+        Ok(len.saturating_sub(skip.unwrap_or(0)))
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<u64>) -> Result<()> {
+        self._write(key, value, None)?;
+        ttl::set_expiry(self, key, ttl.or(self.default_ttl))
+    }
+
+    fn create(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self._write(key, value, Some(false))?;
+
+        if self.default_ttl.is_some() {
+            ttl::set_expiry(self, key, self.default_ttl)?;
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self._write(key, value, Some(true))
+    }
+
+    fn insert_batch(&mut self, items: &[(&[u8], &[u8])]) -> Result<()> {
+        for (key, value) in items {
+            self.insert(key, value, None)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<()> {
+        self._remove(key)?;
+        ttl::clear_expiry(self, key)
+    }
+
+    fn remove_range(
+        &mut self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        skip: Option<u32>,
+    ) -> Result<()> {
+        let keys: Vec<Vec<u8>> = self
+            ._items(from, to)?
+            .into_iter()
+            .skip(skip.unwrap_or(0) as usize)
+            .map(|(k, _)| k)
+            .collect();
+
+        for key in keys {
+            self._remove(&key)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_batch(&mut self, keys: &[&[u8]]) -> Result<()> {
+        for key in keys {
+            self.remove(key)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        for shard in self.shards.iter() {
+            shard.write().unwrap().clear();
+        }
+
+        self.keys_size.store(0, Ordering::SeqCst);
+        self.values_size.store(0, Ordering::SeqCst);
+        self.keys_count.store(0, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn cleanup_expired(&mut self) -> Result<u32> {
+        ttl::cleanup_expired(self, ttl::now())
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::new();
+
+        for shard in self.shards.iter() {
+            for (key, value) in shard.read().unwrap().iter() {
+                stats.add_item(key, value);
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl MemoryStore for ShardedStore {}
+
+#[test]
+fn test_sharded_store_ops() {
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+
+    let res = ShardedStore::new(0, max_value_size, max_size);
+    assert!(res.is_err());
+
+    let res = ShardedStore::new(4, max_size, max_value_size);
+    assert!(res.is_err());
+
+    let res = ShardedStore::new(4, max_value_size, max_size);
+    assert!(res.is_ok());
+
+    let mut store = res.unwrap();
+    assert_eq!(store.shards(), 4);
+
+    let items: Vec<(Vec<u8>, Vec<u8>)> = (0..100u8).map(|i| (vec![1, i], vec![i; 10])).collect();
+
+    for (key, value) in &items {
+        let res = store.create(key, value);
+        assert!(res.is_ok());
+    }
+
+    assert_eq!(store.keys_size(), 200);
+    assert_eq!(store.values_size(), 1000);
+    assert_eq!(store.keys_count(), 100);
+
+    let res = store.create(&items[0].0, &items[0].1);
+    assert!(res.is_err());
+
+    let res = store.update(&[2, 0], &[0]);
+    assert!(res.is_err());
+
+    let res = store.update(&items[0].0, &[0; 5]);
+    assert!(res.is_ok());
+    assert_eq!(store.values_size(), 995);
+
+    let res = store.get(&items[1].0);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), items[1].1);
+
+    let found: Vec<(Vec<u8>, Vec<u8>)> = store
+        .iter(Some(&[1, 10]), Some(&[1, 20]))
+        .unwrap()
+        .collect();
+    assert_eq!(found, items[10..20].to_vec());
+
+    let res = store.query(Some(&[1, 10]), Some(&[1, 20]), Some(5), Some(2));
+    assert!(res.is_ok());
+    let values: Vec<Vec<u8>> = items[12..17].iter().map(|(_, v)| v.clone()).collect();
+    assert_eq!(res.unwrap(), values);
+
+    let res = store.count(Some(&[1, 10]), None, Some(10));
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 80);
+
+    let res = store.sample(None, None, 10);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().len(), 10);
+
+    let res = store.remove_range(Some(&[1, 50]), None, None);
+    assert!(res.is_ok());
+    assert_eq!(store.count(None, None, None).unwrap(), 50);
+
+    let res = store.remove(&items[1].0);
+    assert!(res.is_ok());
+    assert!(!store.lookup(&items[1].0).unwrap());
+    assert_eq!(store.keys_count(), 49);
+
+    let res = store.remove(&items[1].0);
+    assert!(res.is_err());
+
+    let res = store.insert(&[2, 0], &[0; 10], Some(0));
+    assert!(res.is_ok());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    let res = store.clear();
+    assert!(res.is_ok());
+    assert_eq!(store.size(), 0);
+    assert_eq!(store.keys_count(), 0);
+}
+
+#[test]
+fn test_sharded_store_concurrency() {
+    use std::thread;
+
+    let store = ShardedStore::new(ShardedStore::DEFAULT_SHARDS, 1 << 10, 1 << 20).unwrap();
+
+    let handles: Vec<_> = (0..4u8)
+        .map(|i| {
+            let mut store = store.clone();
+
+            thread::spawn(move || {
+                for j in 0..100u8 {
+                    store.insert(&[i, j], &[j], None).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(store.count(None, None, None).unwrap(), 400);
+    assert_eq!(store.keys_size(), 800);
+    assert_eq!(store.values_size(), 400);
+}
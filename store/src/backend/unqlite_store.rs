@@ -4,18 +4,26 @@
 
 use crate::error::Error;
 use crate::result::Result;
-use crate::traits::{MemoryStore, PersistentStore, Store, TemporaryStore};
+use crate::stats::StoreStats;
+use crate::traits::{MemoryStore, PersistentStore, Store, StoreIter, TemporaryStore};
+use crate::ttl;
 use crypto::random::Random;
+use std::fs;
+use std::iter;
+use std::mem;
 use unqlite::Cursor as StoreCursor;
-use unqlite::{Config, UnQLite, KV};
+use unqlite::{Config, Transaction, UnQLite, KV};
 
 /// `UnQLiteStore` is an implementor of `Store` built on a `UnQLite`.
 pub struct UnQLiteStore {
     db: UnQLite,
+    path: Option<String>,
     max_value_size: u32,
     max_size: u32,
     keys_size: u32,
     values_size: u32,
+    keys_count: u32,
+    default_ttl: Option<u64>,
 }
 
 impl UnQLiteStore {
@@ -28,10 +36,13 @@ impl UnQLiteStore {
 
         let mut store = UnQLiteStore {
             db,
+            path: None,
             max_value_size,
             max_size,
             keys_size: 0,
             values_size: 0,
+            keys_count: 0,
+            default_ttl: None,
         };
 
         store.fetch_sizes()?;
@@ -54,7 +65,10 @@ impl UnQLiteStore {
     /// `new_persistent` creates a new persistent `UnQLiteStore`.
     pub fn new_persistent(path: &str, max_value_size: u32, max_size: u32) -> Result<UnQLiteStore> {
         let db = UnQLite::create(path);
-        Self::new_from_db(db, max_value_size, max_size)
+        let mut store = Self::new_from_db(db, max_value_size, max_size)?;
+        store.path = Some(path.into());
+
+        Ok(store)
     }
 
     /// `fetch_sizes` fetches the `UnQLiteStore` cached sizes.
@@ -62,6 +76,7 @@ impl UnQLiteStore {
         let mut entry = self.db.first();
         let mut keys_size = 0;
         let mut values_size = 0;
+        let mut keys_count = 0;
 
         loop {
             if entry.is_none() {
@@ -71,16 +86,26 @@ impl UnQLiteStore {
             let item = entry.unwrap();
             keys_size += item.key().len() as u32;
             values_size += item.value().len() as u32;
+            keys_count += 1;
 
             entry = item.next();
         }
 
         self.keys_size = keys_size;
         self.values_size = values_size;
+        self.keys_count = keys_count;
 
         Ok(())
     }
 
+    /// `COMPACT_SUFFIX` is the suffix of the file used to compact a persistent `UnQLiteStore`.
+    pub const COMPACT_SUFFIX: &'static str = ".compact";
+
+    /// `path` returns the path of a persistent `UnQLiteStore`.
+    pub fn path(&self) -> Option<String> {
+        self.path.clone()
+    }
+
     /// `log_errors` logs the `UnQLiteStore` errors.
     pub fn log_errors(&self) -> Option<String> {
         self.db.err_log()
@@ -785,6 +810,35 @@ impl UnQLiteStore {
         Ok(values)
     }
 
+    /// `_iter` returns an iterator over a range of items of the `UnQLiteStore`.
+    /// The items are read from the database cursor as the iterator advances.
+    fn _iter(&self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                let err = Error::InvalidRange;
+                return Err(err);
+            }
+        }
+
+        let from = from.map(|from| from.to_vec());
+        let to = to.map(|to| to.to_vec());
+
+        let mut entry = self.db.first();
+
+        let iter = iter::from_fn(move || {
+            let item = entry.take()?;
+            let pair = (item.key(), item.value());
+            entry = item.next();
+            Some(pair)
+        })
+        .filter(move |(key, _)| {
+            from.as_ref().map_or(true, |from| from <= key)
+                && to.as_ref().map_or(true, |to| to > key)
+        });
+
+        Ok(Box::new(iter))
+    }
+
     /// `_query` returns a list of values from the `UnQLiteStore`.
     fn _query(
         &self,
@@ -858,12 +912,11 @@ impl UnQLiteStore {
             }
         }
 
-        let len = self.count(from, to, Some(count))?;
+        let values = self.query(from, to, None, None)?;
 
+        let len = values.len() as u32;
         let count = u32::min(count, len);
 
-        let values = self.query(from, to, Some(count), None)?;
-
         let idxs: Vec<u32> = Random::u32_sample_unique_range(0, len, count)?;
 
         let mut res = Vec::new();
@@ -892,9 +945,22 @@ impl UnQLiteStore {
             return Err(err);
         }
 
+        let prev_value_size = if self._lookup(key) {
+            Some(self.db.kv_fetch_length(key)? as u32)
+        } else {
+            None
+        };
+
         self.db.kv_store(key, value)?;
-        self.keys_size += key.len() as u32;
-        self.values_size += value.len() as u32;
+
+        if let Some(prev_value_size) = prev_value_size {
+            self.values_size -= prev_value_size;
+        } else {
+            self.keys_size += key_size;
+            self.keys_count += 1;
+        }
+
+        self.values_size += value_size;
 
         Ok(())
     }
@@ -931,6 +997,7 @@ impl UnQLiteStore {
         self.db.kv_delete(key)?;
         self.keys_size -= key.len() as u32;
         self.values_size -= value_len as u32;
+        self.keys_count -= 1;
 
         Ok(())
     }
@@ -1198,11 +1265,80 @@ impl UnQLiteStore {
                 self.values_size -= value_size;
             }
 
+            if self.keys_count > 0 {
+                self.keys_count -= 1;
+            }
+
             entry = item.next();
         }
 
         Ok(())
     }
+
+    /// `_compact` compacts a persistent `UnQLiteStore` by copying its items
+    /// in a new database file replacing the old one.
+    fn _compact(&mut self) -> Result<()> {
+        let path = if let Some(ref path) = self.path {
+            path.to_owned()
+        } else {
+            return Ok(());
+        };
+
+        let compact_path = format!("{}{}", &path, Self::COMPACT_SUFFIX);
+
+        if fs::metadata(&compact_path).is_ok() {
+            fs::remove_file(&compact_path)?;
+        }
+
+        let compact_db = UnQLite::create(&compact_path);
+        let mut entry = self.db.first();
+
+        loop {
+            if entry.is_none() {
+                break;
+            }
+
+            let item = entry.unwrap();
+            compact_db.kv_store(item.key(), item.value())?;
+
+            entry = item.next();
+        }
+
+        compact_db.commit()?;
+        mem::drop(compact_db);
+
+        let db = mem::replace(&mut self.db, UnQLite::create_in_memory());
+        db.commit()?;
+        mem::drop(db);
+
+        fs::rename(&compact_path, &path)?;
+
+        self.db = UnQLite::create(&path);
+        self.fetch_sizes()
+    }
+
+    /// `_stats` returns the `UnQLiteStore` statistics.
+    fn _stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::new();
+        let mut entry = self.db.first();
+
+        loop {
+            if entry.is_none() {
+                break;
+            }
+
+            let item = entry.unwrap();
+            stats.add_item(&item.key(), &item.value());
+
+            entry = item.next();
+        }
+
+        if let Some(ref path) = self.path {
+            stats.disk_size = fs::metadata(path)?.len();
+        }
+
+        Ok(stats)
+    }
 }
 
 impl Store for UnQLiteStore {
@@ -1210,6 +1346,10 @@ impl Store for UnQLiteStore {
         self.keys_size
     }
 
+    fn keys_count(&self) -> u32 {
+        self.keys_count
+    }
+
     fn values_size(&self) -> u32 {
         self.values_size
     }
@@ -1241,6 +1381,14 @@ impl Store for UnQLiteStore {
         self.max_size
     }
 
+    fn set_default_ttl(&mut self, ttl: Option<u64>) {
+        self.default_ttl = ttl;
+    }
+
+    fn get_default_ttl(&self) -> Option<u64> {
+        self.default_ttl
+    }
+
     fn lookup(&self, key: &[u8]) -> Result<bool> {
         Ok(self._lookup(key))
     }
@@ -1249,6 +1397,10 @@ impl Store for UnQLiteStore {
         self._get(key)
     }
 
+    fn iter<'a>(&'a self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter<'a>> {
+        self._iter(from, to)
+    }
+
     fn query(
         &self,
         from: Option<&[u8]>,
@@ -1267,12 +1419,19 @@ impl Store for UnQLiteStore {
         self._count(from, to, skip)
     }
 
-    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self._insert(key, value)
+    fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<u64>) -> Result<()> {
+        self._insert(key, value)?;
+        ttl::set_expiry(self, key, ttl.or(self.default_ttl))
     }
 
     fn create(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self._create(key, value)
+        self._create(key, value)?;
+
+        if self.default_ttl.is_some() {
+            ttl::set_expiry(self, key, self.default_ttl)?;
+        }
+
+        Ok(())
     }
 
     fn update(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
@@ -1284,7 +1443,8 @@ impl Store for UnQLiteStore {
     }
 
     fn remove(&mut self, key: &[u8]) -> Result<()> {
-        self._remove(key)
+        self._remove(key)?;
+        ttl::clear_expiry(self, key)
     }
 
     fn remove_batch(&mut self, _keys: &[&[u8]]) -> Result<()> {
@@ -1303,6 +1463,22 @@ impl Store for UnQLiteStore {
     fn clear(&mut self) -> Result<()> {
         self._clear()
     }
+
+    fn cleanup_expired(&mut self) -> Result<u32> {
+        ttl::cleanup_expired(self, ttl::now())
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        self._compact()
+    }
+
+    fn stats(&self) -> Result<StoreStats> {
+        self._stats()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.db.commit().map_err(|e| e.into())
+    }
 }
 
 impl MemoryStore for UnQLiteStore {}
@@ -1355,10 +1531,16 @@ fn test_unqlite_store_ops() {
         let res = store.get(&key);
         assert!(res.is_err());
 
-        let res = store.insert(&key, &value);
+        let res = store.insert(&key, &value, None);
         assert!(res.is_ok());
 
         expected_size += (key.len() + value.len()) as u32;
+        assert_eq!(store.keys_count(), 1);
+
+        let res = store.insert(&key, &value, None);
+        assert!(res.is_ok());
+        assert_eq!(store.keys_count(), 1);
+        assert_eq!(store.size(), expected_size);
 
         let res = store.count(Some(&key), None, None);
         assert!(res.is_ok());
@@ -1398,7 +1580,7 @@ fn test_unqlite_store_ops() {
         let res = store.get(&key);
         assert!(res.is_err());
 
-        let res = store.insert(&key, &value);
+        let res = store.insert(&key, &value, None);
         assert!(res.is_ok());
 
         let res = store.clear();
@@ -1406,6 +1588,7 @@ fn test_unqlite_store_ops() {
 
         assert_eq!(store.keys_size(), 0);
         assert_eq!(store.values_size(), 0);
+        assert_eq!(store.keys_count(), 0);
     }
 
     let invalid_value_len = max_value_size + 1;
@@ -1415,12 +1598,12 @@ fn test_unqlite_store_ops() {
         Random::bytes(invalid_value_len as usize).unwrap(),
     );
 
-    let res = store.insert(&invalid_item.0, &invalid_item.1);
+    let res = store.insert(&invalid_item.0, &invalid_item.1, None);
     assert!(res.is_err());
 
     store.set_max_value_size(invalid_value_len as u32);
 
-    let res = store.insert(&invalid_item.0, &invalid_item.1);
+    let res = store.insert(&invalid_item.0, &invalid_item.1, None);
     assert!(res.is_ok());
 
     let res = store.lookup(&invalid_item.0);
@@ -1428,3 +1611,147 @@ fn test_unqlite_store_ops() {
     let found = res.unwrap();
     assert!(found);
 }
+
+#[test]
+fn test_unqlite_store_compact() {
+    use crypto::random::Random;
+    use tempfile::tempdir;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("store");
+    let path = path.to_str().unwrap();
+
+    let mut store = UnQLiteStore::new_persistent(path, max_value_size, max_size).unwrap();
+    assert_eq!(store.path(), Some(path.to_string()));
+
+    let items: Vec<(Vec<u8>, Vec<u8>)> = (0..10u8)
+        .map(|i| {
+            let mut key = vec![0, i % 2];
+            key.extend_from_slice(&Random::bytes(10).unwrap());
+            (key, Random::bytes(100).unwrap())
+        })
+        .collect();
+
+    for (key, value) in &items {
+        let res = store.insert(&key, &value, None);
+        assert!(res.is_ok());
+    }
+
+    for (key, _) in items.iter().take(5) {
+        let res = store.remove(&key);
+        assert!(res.is_ok());
+    }
+
+    let res = store.stats();
+    assert!(res.is_ok());
+    let stats = res.unwrap();
+
+    assert_eq!(stats.keys_count, 5);
+    assert_eq!(stats.size(), store.size() as u64);
+    assert_eq!(stats.get_stage(0).keys_count, 5);
+
+    let res = store.compact();
+    assert!(res.is_ok());
+
+    for (key, value) in items.iter().skip(5) {
+        let res = store.get(&key);
+        assert!(res.is_ok());
+        assert_eq!(&res.unwrap(), value);
+    }
+
+    let res = store.stats();
+    assert!(res.is_ok());
+    let compact_stats = res.unwrap();
+
+    assert_eq!(compact_stats.keys_count, stats.keys_count);
+    assert_eq!(compact_stats.stages, stats.stages);
+}
+
+#[test]
+fn test_unqlite_store_ttl() {
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = UnQLiteStore::new_temporary(max_value_size, max_size).unwrap();
+
+    let key_a = vec![0, 1];
+    let key_b = vec![0, 2];
+    let key_c = vec![0, 3];
+    let value = vec![0; 10];
+
+    let res = store.insert(&key_a, &value, Some(0));
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_b, &value, Some(3600));
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_c, &value, Some(0));
+    assert!(res.is_ok());
+
+    let res = store.insert(&key_c, &value, None);
+    assert!(res.is_ok());
+
+    let res = ttl::get_expiry(&store, &key_c);
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    assert!(!store.lookup(&key_a).unwrap());
+    assert!(store.lookup(&key_b).unwrap());
+    assert!(store.lookup(&key_c).unwrap());
+
+    let res = ttl::cleanup_expired(&mut store, ttl::now() + 3600);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+
+    assert!(!store.lookup(&key_b).unwrap());
+    assert!(store.lookup(&key_c).unwrap());
+
+    let res = store.count(None, None, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+}
+
+#[test]
+fn test_unqlite_store_iter() {
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = UnQLiteStore::new_temporary(max_value_size, max_size).unwrap();
+
+    let items: Vec<(Vec<u8>, Vec<u8>)> = (0..10u8).map(|i| (vec![1, i], vec![i; 10])).collect();
+
+    for (key, value) in &items {
+        store.insert(key, value, None).unwrap();
+    }
+
+    store.insert(&[2, 0], &[0; 10], None).unwrap();
+
+    let from = vec![1, 2];
+    let to = vec![1, 5];
+
+    let res = store.iter(Some(&to), Some(&from));
+    assert!(res.is_err());
+
+    let res = store.iter(Some(&from), Some(&to));
+    assert!(res.is_ok());
+
+    let mut found: Vec<(Vec<u8>, Vec<u8>)> = res.unwrap().collect();
+    found.sort();
+    assert_eq!(found, items[2..5].to_vec());
+
+    let found: Vec<(Vec<u8>, Vec<u8>)> = store.iter(Some(&[1]), Some(&[2])).unwrap().collect();
+    assert_eq!(found.len(), items.len());
+
+    let found = store.iter(None, None).unwrap().count();
+    assert_eq!(found, items.len() + 1);
+
+    let found = store.iter(Some(&[1]), Some(&[2])).unwrap().take(3).count();
+    assert_eq!(found, 3);
+}
@@ -0,0 +1,423 @@
+//! # WalStore
+//
+// `wal_store` contains the write-ahead logged store backend type and functions.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::stats::StoreStats;
+use crate::traits::{MemoryStore, PersistentStore, Store, StoreIter, TemporaryStore};
+use crate::ttl;
+use crate::wal::{self, WalImage, WalLog, WalRecord, WalReport, WalSync};
+
+/// `WalStore` is an implementor of `Store` wrapping another `Store` with a
+/// write-ahead log, so that the batches of writes are crash-consistent whatever
+/// the guarantees of the inner `Store`.
+///
+/// Every write is logged with the images of its keys before and after the write.
+/// The writes outside a batch are committed one by one. On opening, the log is
+/// replayed and, once the inner `Store` is flushed, truncated.
+#[derive(Debug)]
+pub struct WalStore<S: Store> {
+    store: S,
+    log: Option<WalLog>,
+    depth: u32,
+    undo: Vec<Vec<WalImage>>,
+    report: WalReport,
+}
+
+impl<S: Store> WalStore<S> {
+    /// `MAX_LOG_SIZE` is the size in bytes of the log after which a `WalStore`
+    /// flushes the inner `Store` and truncates the log.
+    pub const MAX_LOG_SIZE: u64 = 1 << 26;
+
+    /// `new` creates a new `WalStore` without a log, forwarding the writes to the
+    /// inner `Store`.
+    pub fn new(store: S) -> WalStore<S> {
+        WalStore {
+            store,
+            log: None,
+            depth: 0,
+            undo: Vec::new(),
+            report: WalReport::default(),
+        }
+    }
+
+    /// `open` creates a new `WalStore` logging the writes to the log at a path,
+    /// replaying the records left in the log.
+    pub fn open(store: S, path: &str, sync: WalSync) -> Result<WalStore<S>> {
+        let mut log = WalLog::open(path, sync)?;
+        let mut store = store;
+
+        let records = log.records()?;
+        let report = wal::replay(&mut store, &records)?;
+
+        let mut wal_store = WalStore {
+            store,
+            log: Some(log),
+            depth: 0,
+            undo: Vec::new(),
+            report,
+        };
+
+        wal_store.checkpoint()?;
+
+        Ok(wal_store)
+    }
+
+    /// `is_logged` returns if the `WalStore` logs its writes.
+    pub fn is_logged(&self) -> bool {
+        self.log.is_some()
+    }
+
+    /// `in_batch` returns if the `WalStore` is in a batch of writes.
+    pub fn in_batch(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// `report` returns the report of the replay of the log on opening.
+    pub fn report(&self) -> WalReport {
+        self.report
+    }
+
+    /// `inner` returns a reference to the inner `Store`.
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// `into_inner` returns the inner `Store`, dropping the log.
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    /// `checkpoint` flushes the inner `Store` and truncates the log.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        if self.in_batch() {
+            let err = Error::NotAllowed;
+            return Err(err);
+        }
+
+        if let Some(ref mut log) = self.log {
+            self.store.flush()?;
+            log.truncate()?;
+        }
+
+        Ok(())
+    }
+
+    /// `append` appends a record to the log.
+    fn append(&mut self, record: &WalRecord) -> Result<()> {
+        if let Some(ref mut log) = self.log {
+            log.append(record)?;
+        }
+
+        Ok(())
+    }
+
+    /// `write` logs and applies a write of a list of keys to the inner `Store`.
+    fn write<F>(&mut self, keys: &[Vec<u8>], f: F) -> Result<()>
+    where
+        F: FnOnce(&mut S) -> Result<()>,
+    {
+        if !self.is_logged() {
+            return f(&mut self.store);
+        }
+
+        let images = wal::key_images(&self.store, keys)?;
+        self.append(&WalRecord::Undo {
+            images: images.clone(),
+        })?;
+        self.undo.push(images);
+
+        let res = f(&mut self.store);
+
+        let images = wal::key_images(&self.store, keys)?;
+        self.append(&WalRecord::Redo { images })?;
+
+        if !self.in_batch() {
+            self.commit()?;
+        }
+
+        res
+    }
+
+    /// `range_keys` returns the keys of the inner `Store` in a range.
+    fn range_keys(&self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<Vec<Vec<u8>>> {
+        if !self.is_logged() {
+            return Ok(Vec::new());
+        }
+
+        let keys = self.store.iter(from, to)?.map(|(key, _)| key).collect();
+        Ok(keys)
+    }
+
+    /// `commit` commits the logged writes, truncating the log if too big.
+    fn commit(&mut self) -> Result<()> {
+        self.undo.clear();
+        self.append(&WalRecord::Commit)?;
+
+        let size = self.log.as_ref().map(|log| log.size()).unwrap_or(0);
+
+        if size > Self::MAX_LOG_SIZE {
+            self.checkpoint()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Store> Store for WalStore<S> {
+    fn keys_size(&self) -> u32 {
+        self.store.keys_size()
+    }
+
+    fn keys_count(&self) -> u32 {
+        self.store.keys_count()
+    }
+
+    fn values_size(&self) -> u32 {
+        self.store.values_size()
+    }
+
+    fn size(&self) -> u32 {
+        self.store.size()
+    }
+
+    fn set_max_value_size(&mut self, size: u32) {
+        self.store.set_max_value_size(size)
+    }
+
+    fn get_max_value_size(&self) -> u32 {
+        self.store.get_max_value_size()
+    }
+
+    fn set_max_size(&mut self, size: u32) -> Result<()> {
+        self.store.set_max_size(size)
+    }
+
+    fn get_max_size(&self) -> u32 {
+        self.store.get_max_size()
+    }
+
+    fn set_default_ttl(&mut self, ttl: Option<u64>) {
+        self.store.set_default_ttl(ttl)
+    }
+
+    fn get_default_ttl(&self) -> Option<u64> {
+        self.store.get_default_ttl()
+    }
+
+    fn lookup(&self, key: &[u8]) -> Result<bool> {
+        self.store.lookup(key)
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.store.get(key)
+    }
+
+    fn iter<'a>(&'a self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter<'a>> {
+        self.store.iter(from, to)
+    }
+
+    fn query(
+        &self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.store.query(from, to, count, skip)
+    }
+
+    fn sample(&self, from: Option<&[u8]>, to: Option<&[u8]>, count: u32) -> Result<Vec<Vec<u8>>> {
+        self.store.sample(from, to, count)
+    }
+
+    fn count(&self, from: Option<&[u8]>, to: Option<&[u8]>, skip: Option<u32>) -> Result<u32> {
+        self.store.count(from, to, skip)
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<u64>) -> Result<()> {
+        self.write(&[key.to_owned()], |store| store.insert(key, value, ttl))
+    }
+
+    fn create(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.write(&[key.to_owned()], |store| store.create(key, value))
+    }
+
+    fn update(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.write(&[key.to_owned()], |store| store.update(key, value))
+    }
+
+    fn insert_batch(&mut self, items: &[(&[u8], &[u8])]) -> Result<()> {
+        let keys: Vec<Vec<u8>> = items.iter().map(|(key, _)| key.to_vec()).collect();
+        self.write(&keys, |store| store.insert_batch(items))
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<()> {
+        self.write(&[key.to_owned()], |store| store.remove(key))
+    }
+
+    fn remove_range(
+        &mut self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        skip: Option<u32>,
+    ) -> Result<()> {
+        let keys = self.range_keys(from, to)?;
+        self.write(&keys, |store| store.remove_range(from, to, skip))
+    }
+
+    fn remove_batch(&mut self, keys: &[&[u8]]) -> Result<()> {
+        let _keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_vec()).collect();
+        self.write(&_keys, |store| store.remove_batch(keys))
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        let keys = self.range_keys(None, None)?;
+        self.write(&keys, |store| store.clear())
+    }
+
+    fn cleanup_expired(&mut self) -> Result<u32> {
+        if !self.is_logged() {
+            return self.store.cleanup_expired();
+        }
+
+        wal::atomic(self, |store| ttl::cleanup_expired(store, ttl::now()))
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        self.store.compact()
+    }
+
+    fn stats(&self) -> Result<StoreStats> {
+        self.store.stats()
+    }
+
+    fn begin_batch(&mut self) -> Result<()> {
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn commit_batch(&mut self) -> Result<()> {
+        if !self.in_batch() {
+            let err = Error::NotAllowed;
+            return Err(err);
+        }
+
+        self.depth -= 1;
+
+        if !self.in_batch() && self.is_logged() {
+            self.commit()?;
+        }
+
+        Ok(())
+    }
+
+    fn rollback_batch(&mut self) -> Result<()> {
+        if !self.in_batch() {
+            let err = Error::NotAllowed;
+            return Err(err);
+        }
+
+        self.depth = 0;
+
+        if !self.is_logged() {
+            return Ok(());
+        }
+
+        let undo: Vec<Vec<WalImage>> = self.undo.drain(..).collect();
+
+        for images in undo.iter().rev() {
+            wal::apply_images(&mut self.store, images)?;
+        }
+
+        self.append(&WalRecord::Rollback)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.store.flush()
+    }
+}
+
+impl<S: MemoryStore> MemoryStore for WalStore<S> {}
+
+impl<S: TemporaryStore> TemporaryStore for WalStore<S> {}
+
+impl<S: PersistentStore> PersistentStore for WalStore<S> {}
+
+#[test]
+fn test_wal_store_ops() {
+    use crate::backend::BTreeStore;
+    use tempfile::NamedTempFile;
+
+    let path = NamedTempFile::new().unwrap().into_temp_path();
+    let path = path.to_str().unwrap();
+
+    let inner = BTreeStore::new(1 << 10, 1 << 20).unwrap();
+    let mut store = WalStore::open(inner, path, WalSync::Always).unwrap();
+    assert!(store.is_logged());
+    assert_eq!(store.report(), WalReport::default());
+
+    let res = store.insert(&[1], &[1], None);
+    assert!(res.is_ok());
+
+    let res = store.begin_batch();
+    assert!(res.is_ok());
+    assert!(store.in_batch());
+
+    store.insert(&[2], &[2], Some(1000)).unwrap();
+    store.update(&[1], &[10]).unwrap();
+    store.remove(&[1]).unwrap();
+
+    let res = store.rollback_batch();
+    assert!(res.is_ok());
+    assert!(!store.in_batch());
+
+    assert_eq!(store.get(&[1]).unwrap(), vec![1]);
+    assert!(!store.lookup(&[2]).unwrap());
+    assert!(!store.lookup(&ttl::expiry_key(&[2])).unwrap());
+
+    let res = store.rollback_batch();
+    assert!(res.is_err());
+
+    let res: Result<()> = wal::atomic(&mut store, |store| {
+        store.insert(&[3], &[3], None)?;
+        store.create(&[1], &[1])
+    });
+    assert!(res.is_err());
+    assert!(!store.lookup(&[3]).unwrap());
+
+    let res: Result<()> = wal::atomic(&mut store, |store| {
+        store.insert(&[3], &[3], Some(1000))?;
+        store.update(&[1], &[2])
+    });
+    assert!(res.is_ok());
+
+    // NB: the inner store of a crashed process is lost, the log is replayed on a copy
+    let mut crashed = BTreeStore::new(1 << 10, 1 << 20).unwrap();
+    crashed.insert(&[1], &[1], None).unwrap();
+
+    let res = store.begin_batch();
+    assert!(res.is_ok());
+
+    store.remove(&[3]).unwrap();
+
+    let store = WalStore::open(crashed, path, WalSync::Commit).unwrap();
+    assert!(store.report().redone > 0);
+    assert!(store.report().undone > 0);
+
+    assert_eq!(store.get(&[1]).unwrap(), vec![2]);
+    assert_eq!(store.get(&[3]).unwrap(), vec![3]);
+    assert!(ttl::get_expiry(&store, &[3]).unwrap().is_some());
+    assert!(!store.lookup(&[2]).unwrap());
+
+    let mut store = WalStore::new(BTreeStore::new(1 << 10, 1 << 20).unwrap());
+    assert!(!store.is_logged());
+
+    let res = store.insert(&[1], &[1], Some(0));
+    assert!(res.is_ok());
+
+    let res = store.cleanup_expired();
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1);
+}
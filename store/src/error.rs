@@ -4,6 +4,7 @@
 
 use config::error::Error as ConfigError;
 use crypto::error::Error as CryptoError;
+use serde_cbor;
 use std::convert::From;
 use std::io;
 use unqlite::Error as UnQLiteError;
@@ -18,6 +19,8 @@ pub enum Error {
     Config { msg: String },
     #[fail(display = "Store: {}", msg)]
     Store { msg: String },
+    #[fail(display = "Parse: {}", msg)]
+    Parse { msg: String },
     #[fail(display = "Not implemented")]
     NotImplemented,
     #[fail(display = "Not allowed")]
@@ -40,6 +43,12 @@ pub enum Error {
     InvalidPath,
     #[fail(display = "Invalid kind")]
     InvalidKind,
+    #[fail(display = "Invalid snapshot")]
+    InvalidSnapshot,
+    #[fail(display = "Invalid migration")]
+    InvalidMigration,
+    #[fail(display = "Invalid schema version")]
+    InvalidSchemaVersion,
 }
 
 impl From<io::Error> for Error {
@@ -63,6 +72,13 @@ impl From<ConfigError> for Error {
     }
 }
 
+impl From<serde_cbor::error::Error> for Error {
+    fn from(error: serde_cbor::error::Error) -> Error {
+        let msg = format!("{}", error);
+        Error::Parse { msg }
+    }
+}
+
 impl From<UnQLiteError> for Error {
     fn from(error: UnQLiteError) -> Error {
         let msg = format!("{}", error);
@@ -0,0 +1,233 @@
+//! # Index
+//!
+//! `index` contains the secondary index trait and types.
+
+use crate::result::Result;
+use crate::traits::Store;
+
+/// `Index` is the trait implemented by `Store` secondary indexes.
+///
+/// An index entry maps an index key to a primary key. Entries are stored in the
+/// indexed `Store` itself, under the key `prefix | index_key | primary_key`, with
+/// the primary key as value, so that all the primary keys of an index key can be
+/// retrieved with a single range query. Index keys of the same index must have
+/// the same length.
+pub trait Index<S: Store> {
+    /// `prefix` returns the prefix of the index entries keys.
+    fn prefix(&self) -> Vec<u8>;
+
+    /// `entry_key` returns the `Store` key of an index entry.
+    fn entry_key(&self, index_key: &[u8], key: &[u8]) -> Vec<u8> {
+        let mut buf = self.prefix();
+        buf.extend_from_slice(index_key);
+        buf.extend_from_slice(key);
+        buf
+    }
+
+    /// `range` returns the `Store` range of the entries of an index key.
+    fn range(&self, index_key: &[u8]) -> (Vec<u8>, Option<Vec<u8>>) {
+        let mut from = self.prefix();
+        from.extend_from_slice(index_key);
+
+        let to = next_key(&from);
+
+        (from, to)
+    }
+
+    /// `lookup` looks up an index entry in the `Store`.
+    fn lookup(&self, store: &S, index_key: &[u8], key: &[u8]) -> Result<bool> {
+        let entry_key = self.entry_key(index_key, key);
+        store.lookup(&entry_key)
+    }
+
+    /// `query` returns the primary keys indexed by an index key.
+    fn query(
+        &self,
+        store: &S,
+        index_key: &[u8],
+        count: Option<u32>,
+        skip: Option<u32>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let (from, to) = self.range(index_key);
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.query(Some(&from), to, count, skip)
+    }
+
+    /// `count` counts the primary keys indexed by an index key.
+    fn count(&self, store: &S, index_key: &[u8], skip: Option<u32>) -> Result<u32> {
+        let (from, to) = self.range(index_key);
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.count(Some(&from), to, skip)
+    }
+
+    /// `insert` inserts an index entry in the `Store`.
+    fn insert(&self, store: &mut S, index_key: &[u8], key: &[u8]) -> Result<()> {
+        let entry_key = self.entry_key(index_key, key);
+        store.insert(&entry_key, key, None)
+    }
+
+    /// `remove` removes an index entry from the `Store`.
+    fn remove(&self, store: &mut S, index_key: &[u8], key: &[u8]) -> Result<()> {
+        let entry_key = self.entry_key(index_key, key);
+
+        if store.lookup(&entry_key)? {
+            store.remove(&entry_key)?;
+        }
+
+        Ok(())
+    }
+
+    /// `remove_all` removes all the entries of an index key from the `Store`.
+    fn remove_all(&self, store: &mut S, index_key: &[u8]) -> Result<()> {
+        let (from, to) = self.range(index_key);
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.remove_range(Some(&from), to, None)
+    }
+
+    /// `clear` removes all the index entries from the `Store`.
+    fn clear(&self, store: &mut S) -> Result<()> {
+        let from = self.prefix();
+        let to = next_key(&from);
+        let to = to.as_ref().map(|to| to.as_slice());
+        store.remove_range(Some(&from), to, None)
+    }
+}
+
+/// `StoreIndex` is a secondary index identified by its entries prefix.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Debug)]
+pub struct StoreIndex {
+    prefix: Vec<u8>,
+}
+
+impl StoreIndex {
+    /// `new` creates a new `StoreIndex`.
+    pub fn new(prefix: &[u8]) -> StoreIndex {
+        StoreIndex {
+            prefix: prefix.to_owned(),
+        }
+    }
+}
+
+impl<S: Store> Index<S> for StoreIndex {
+    fn prefix(&self) -> Vec<u8> {
+        self.prefix.clone()
+    }
+}
+
+/// `next_key` returns the smallest key greater than all the keys starting
+/// with `key`, if any.
+pub fn next_key(key: &[u8]) -> Option<Vec<u8>> {
+    let mut next = key.to_owned();
+
+    while let Some(last) = next.pop() {
+        if last < std::u8::MAX {
+            next.push(last + 1);
+            return Some(next);
+        }
+    }
+
+    None
+}
+
+#[test]
+fn test_next_key() {
+    let key = [1, 2, 3];
+    assert_eq!(next_key(&key), Some(vec![1, 2, 4]));
+
+    let key = [1, 2, 255];
+    assert_eq!(next_key(&key), Some(vec![1, 3]));
+
+    let key = [255, 255];
+    assert_eq!(next_key(&key), None);
+
+    let key: [u8; 0] = [];
+    assert_eq!(next_key(&key), None);
+}
+
+#[test]
+fn test_store_index_ops() {
+    use crate::backend::BTreeStore;
+    use crypto::random::Random;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 30;
+
+    let mut store = BTreeStore::new(max_value_size, max_size).unwrap();
+
+    let index = StoreIndex::new(&[0, 4]);
+    let other_index = StoreIndex::new(&[0, 5]);
+
+    let index_key = Random::bytes(32).unwrap();
+    let mut other_index_key = Random::bytes(32).unwrap();
+    while other_index_key == index_key {
+        other_index_key = Random::bytes(32).unwrap();
+    }
+
+    let keys: Vec<Vec<u8>> = (0..10).map(|_| Random::bytes(64).unwrap()).collect();
+
+    for key in &keys {
+        let res = index.lookup(&store, &index_key, key);
+        assert!(res.is_ok());
+        assert!(!res.unwrap());
+
+        let res = index.insert(&mut store, &index_key, key);
+        assert!(res.is_ok());
+
+        let res = index.lookup(&store, &index_key, key);
+        assert!(res.is_ok());
+        assert!(res.unwrap());
+
+        let res = other_index.insert(&mut store, &index_key, key);
+        assert!(res.is_ok());
+
+        let res = index.insert(&mut store, &other_index_key, key);
+        assert!(res.is_ok());
+    }
+
+    let res = Index::<BTreeStore>::count(&index, &store, &index_key, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), keys.len() as u32);
+
+    let res = Index::<BTreeStore>::query(&index, &store, &index_key, None, None);
+    assert!(res.is_ok());
+    let mut found = res.unwrap();
+    found.sort();
+    let mut expected = keys.clone();
+    expected.sort();
+    assert_eq!(found, expected);
+
+    let key = &keys[0];
+
+    let res = index.remove(&mut store, &index_key, key);
+    assert!(res.is_ok());
+
+    let res = index.lookup(&store, &index_key, key);
+    assert!(res.is_ok());
+    assert!(!res.unwrap());
+
+    let res = Index::<BTreeStore>::count(&index, &store, &index_key, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), keys.len() as u32 - 1);
+
+    let res = index.remove_all(&mut store, &index_key);
+    assert!(res.is_ok());
+
+    let res = Index::<BTreeStore>::count(&index, &store, &index_key, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+
+    let res = Index::<BTreeStore>::count(&index, &store, &other_index_key, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), keys.len() as u32);
+
+    let res = Index::<BTreeStore>::clear(&index, &mut store);
+    assert!(res.is_ok());
+
+    let res = Index::<BTreeStore>::count(&index, &store, &other_index_key, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 0);
+
+    let res = Index::<BTreeStore>::count(&other_index, &store, &index_key, None);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), keys.len() as u32);
+}
@@ -14,6 +14,27 @@ pub mod result;
 /// `traits` contains the storage traits.
 pub mod traits;
 
+/// `stats` contains the store statistics type and functions.
+pub mod stats;
+
+/// `index` contains the secondary index trait and types.
+pub mod index;
+
+/// `ttl` contains the functions used to expire the store items.
+pub mod ttl;
+
+/// `verify` contains the store integrity check types and functions.
+pub mod verify;
+
+/// `snapshot` contains the store backup and restore types and functions.
+pub mod snapshot;
+
+/// `migrations` contains the store schema versioning and migration types and functions.
+pub mod migrations;
+
+/// `wal` contains the write-ahead log types and functions.
+pub mod wal;
+
 /// `backend` contains the store backends.
 pub mod backend;
 
@@ -2,7 +2,7 @@
 //
 // `memory` contains the memory store type and functions.
 
-use crate::backend::{BTreeStore, UnQLiteStore};
+use crate::backend::{BTreeStore, ShardedStore, UnQLiteStore};
 use crate::result::Result;
 
 /// `MemoryStoreFactory` is a factory for in-memory stores.
@@ -14,6 +14,11 @@ impl MemoryStoreFactory {
         BTreeStore::new(max_value_size, max_size)
     }
 
+    /// `new_sharded` creates a new `ShardedStore` with a number of shards.
+    pub fn new_sharded(shards: u32, max_value_size: u32, max_size: u32) -> Result<ShardedStore> {
+        ShardedStore::new(shards, max_value_size, max_size)
+    }
+
     /// `new_unqlite` creates a new in-memory `UnQLiteStore`.
     pub fn new_unqlite(max_value_size: u32, max_size: u32) -> Result<UnQLiteStore> {
         UnQLiteStore::new_memory(max_value_size, max_size)
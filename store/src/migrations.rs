@@ -0,0 +1,294 @@
+//! # Migrations
+//!
+//! `migrations` contains the types and functions used to version the schema of
+//! the `Store` records and to migrate them when the serialization of the models
+//! changes.
+//!
+//! The schema version is kept in the `Store` itself, under the reserved key
+//! `SCHEMA_VERSION_KEY`. At startup, the `Migration`s with a version greater than
+//! the `Store` one are run in order, and the schema version is updated after each
+//! one, so that an interrupted upgrade resumes from the first pending `Migration`.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::traits::Store;
+use crate::ttl;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+
+/// `SCHEMA_TAG` is the first byte of the schema version key.
+pub const SCHEMA_TAG: u8 = 0xfd;
+
+/// `SCHEMA_VERSION_KEY` is the key of the `Store` schema version.
+pub const SCHEMA_VERSION_KEY: [u8; 1] = [SCHEMA_TAG];
+
+/// `BASE_SCHEMA_VERSION` is the schema version of the `Store`s created before
+/// the schema was versioned.
+pub const BASE_SCHEMA_VERSION: u32 = 1;
+
+/// `Migration` is the trait implemented by the migrations of the `Store` records.
+pub trait Migration<S: Store> {
+    /// `version` returns the schema version the `Migration` upgrades to.
+    fn version(&self) -> u32;
+
+    /// `name` returns the name of the `Migration`.
+    fn name(&self) -> String;
+
+    /// `migrate` migrates the `Store` records from the previous schema version.
+    fn migrate(&self, store: &mut S) -> Result<()>;
+}
+
+/// `ValueMigration` is a `Migration` rewriting the values of the records in a
+/// keys range, as when the serialization of a model changes.
+pub struct ValueMigration {
+    version: u32,
+    name: String,
+    from: Vec<u8>,
+    to: Option<Vec<u8>>,
+    convert: Box<dyn Fn(&[u8]) -> Result<Vec<u8>>>,
+}
+
+impl ValueMigration {
+    /// `new` creates a new `ValueMigration` converting the values of the records
+    /// in a keys range.
+    pub fn new<F>(
+        version: u32,
+        name: &str,
+        from: &[u8],
+        to: Option<&[u8]>,
+        convert: F,
+    ) -> ValueMigration
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>> + 'static,
+    {
+        ValueMigration {
+            version,
+            name: name.into(),
+            from: from.to_vec(),
+            to: to.map(|to| to.to_vec()),
+            convert: Box::new(convert),
+        }
+    }
+}
+
+impl<S: Store> Migration<S> for ValueMigration {
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn migrate(&self, store: &mut S) -> Result<()> {
+        let to = self.to.as_ref().map(|to| to.as_slice());
+
+        let mut items = Vec::new();
+
+        for (key, value) in store.iter(Some(&self.from), to)? {
+            if ttl::is_ttl_key(&key) || is_schema_key(&key) {
+                continue;
+            }
+
+            let value = (self.convert)(&value)?;
+            items.push((key, value));
+        }
+
+        for (key, value) in items {
+            store.update(&key, &value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `MigrationReport` is the report of the `Migration`s run on a `Store`.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub applied: Vec<String>,
+}
+
+impl MigrationReport {
+    /// `is_migrated` returns if any `Migration` was run.
+    pub fn is_migrated(&self) -> bool {
+        !self.applied.is_empty()
+    }
+}
+
+/// `is_schema_key` returns if a key is reserved to the schema version.
+pub fn is_schema_key(key: &[u8]) -> bool {
+    key == SCHEMA_VERSION_KEY
+}
+
+/// `get_schema_version` returns the schema version of a `Store`, if any.
+pub fn get_schema_version<S: Store + ?Sized>(store: &S) -> Result<Option<u32>> {
+    if !store.lookup(&SCHEMA_VERSION_KEY)? {
+        return Ok(None);
+    }
+
+    let value = store.get(&SCHEMA_VERSION_KEY)?;
+
+    if let Ok(buf) = value.as_slice().try_into() {
+        Ok(Some(u32::from_be_bytes(buf)))
+    } else {
+        let err = Error::InvalidSchemaVersion;
+        Err(err)
+    }
+}
+
+/// `set_schema_version` sets the schema version of a `Store`.
+pub fn set_schema_version<S: Store + ?Sized>(store: &mut S, version: u32) -> Result<()> {
+    store.insert(&SCHEMA_VERSION_KEY, &version.to_be_bytes(), None)
+}
+
+/// `latest_version` returns the schema version reached by a list of `Migration`s.
+pub fn latest_version<S: Store>(migrations: &[Box<dyn Migration<S>>]) -> u32 {
+    migrations
+        .iter()
+        .map(|migration| migration.version())
+        .fold(BASE_SCHEMA_VERSION, u32::max)
+}
+
+/// `validate_migrations` validates a list of `Migration`s, which must have
+/// strictly increasing versions greater than the base schema version.
+pub fn validate_migrations<S: Store>(migrations: &[Box<dyn Migration<S>>]) -> Result<()> {
+    let mut version = BASE_SCHEMA_VERSION;
+
+    for migration in migrations {
+        if migration.version() <= version {
+            let err = Error::InvalidMigration;
+            return Err(err);
+        }
+
+        version = migration.version();
+    }
+
+    Ok(())
+}
+
+/// `migrate` runs on a `Store` the `Migration`s with a version greater than the
+/// `Store` schema version. A new empty `Store` is set to the latest schema version
+/// without running any `Migration`, while a `Store` with records and no schema version
+/// is considered at the base schema version. A `Store` with a schema version greater
+/// than the latest one cannot be opened, as its records cannot be decoded.
+pub fn migrate<S: Store>(
+    store: &mut S,
+    migrations: &[Box<dyn Migration<S>>],
+) -> Result<MigrationReport> {
+    validate_migrations(migrations)?;
+
+    let latest = latest_version(migrations);
+
+    let current = match get_schema_version(store)? {
+        Some(version) => version,
+        None if store.count(None, None, None)? == 0 => latest,
+        None => BASE_SCHEMA_VERSION,
+    };
+
+    if current > latest {
+        let err = Error::InvalidSchemaVersion;
+        return Err(err);
+    }
+
+    let mut report = MigrationReport {
+        from_version: current,
+        to_version: current,
+        applied: Vec::new(),
+    };
+
+    for migration in migrations {
+        if migration.version() <= current {
+            continue;
+        }
+
+        migration.migrate(store)?;
+        set_schema_version(store, migration.version())?;
+
+        report.to_version = migration.version();
+        report.applied.push(migration.name());
+    }
+
+    set_schema_version(store, report.to_version)?;
+
+    Ok(report)
+}
+
+#[test]
+fn test_migrate() {
+    use crate::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+
+    let double = |value: &[u8]| -> Result<Vec<u8>> { Ok(value.iter().map(|v| v * 2).collect()) };
+    let increment = |value: &[u8]| -> Result<Vec<u8>> { Ok(value.iter().map(|v| v + 1).collect()) };
+
+    let migrations: Vec<Box<dyn Migration<_>>> = vec![
+        Box::new(ValueMigration::new(2, "double", &[0], Some(&[1]), double)),
+        Box::new(ValueMigration::new(
+            3,
+            "increment",
+            &[0],
+            Some(&[1]),
+            increment,
+        )),
+    ];
+
+    let mut empty = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    let res = migrate(&mut empty, &migrations);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert!(!report.is_migrated());
+    assert_eq!(report.to_version, 3);
+    assert_eq!(get_schema_version(&empty).unwrap(), Some(3));
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    for i in 0..10u8 {
+        store.insert(&[0, i], &[i], None).unwrap();
+        store.insert(&[1, i], &[i], None).unwrap();
+    }
+
+    assert_eq!(get_schema_version(&store).unwrap(), None);
+
+    let res = migrate(&mut store, &migrations[..1]);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert_eq!(report.from_version, BASE_SCHEMA_VERSION);
+    assert_eq!(report.to_version, 2);
+    assert_eq!(report.applied, vec!["double".to_string()]);
+    assert_eq!(store.get(&[0, 3]).unwrap(), vec![6]);
+    assert_eq!(store.get(&[1, 3]).unwrap(), vec![3]);
+
+    let res = migrate(&mut store, &migrations);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert_eq!(report.from_version, 2);
+    assert_eq!(report.applied, vec!["increment".to_string()]);
+    assert_eq!(store.get(&[0, 3]).unwrap(), vec![7]);
+
+    let res = migrate(&mut store, &migrations);
+    assert!(res.is_ok());
+    assert!(!res.unwrap().is_migrated());
+    assert_eq!(store.get(&[0, 3]).unwrap(), vec![7]);
+
+    let res = migrate(&mut store, &migrations[..1]);
+    assert!(res.is_err());
+
+    let unordered: Vec<Box<dyn Migration<_>>> = vec![
+        Box::new(ValueMigration::new(
+            3,
+            "increment",
+            &[0],
+            Some(&[1]),
+            increment,
+        )),
+        Box::new(ValueMigration::new(2, "double", &[0], Some(&[1]), double)),
+    ];
+
+    let res = migrate(&mut store, &unordered);
+    assert!(res.is_err());
+}
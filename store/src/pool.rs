@@ -5,17 +5,116 @@
 use crate::backend::UnQLiteStore;
 use crate::memory::MemoryStoreFactory;
 use crate::result::Result;
+use crate::traits::Store;
 use config::pool::PoolConfig;
+use std::collections::BTreeMap;
 
 /// `PoolFactory` is the factory for store types.
 pub struct PoolFactory {}
 
 impl PoolFactory {
-    /// `create` creates a new pool from the configs.
+    /// `create` creates a new pool from the configs. The pool items expire
+    /// after the configured max age.
     pub fn create(config: &PoolConfig) -> Result<UnQLiteStore> {
         let mut config = config.clone();
         config.populate();
 
-        MemoryStoreFactory::new_unqlite(config.max_value_size.unwrap(), config.max_size.unwrap())
+        let mut pool = MemoryStoreFactory::new_unqlite(
+            config.max_value_size.unwrap(),
+            config.max_size.unwrap(),
+        )?;
+
+        pool.set_default_ttl(config.max_age.map(u64::from));
+
+        Ok(pool)
+    }
+
+    /// `create_stage` creates a new pool for a single stage from the configs,
+    /// using the limits of the stage.
+    pub fn create_stage(config: &PoolConfig, stage: &str) -> Result<UnQLiteStore> {
+        config.validate()?;
+
+        let config = config.stage_config(stage)?;
+
+        PoolFactory::create(&config)
+    }
+
+    /// `create_stages` creates a new independent pool for each stage from the configs.
+    pub fn create_stages(config: &PoolConfig) -> Result<BTreeMap<String, UnQLiteStore>> {
+        let mut pools = BTreeMap::new();
+
+        for stage in PoolConfig::VALID_STAGES.iter() {
+            let pool = PoolFactory::create_stage(config, stage)?;
+            pools.insert(stage.to_string(), pool);
+        }
+
+        Ok(pools)
     }
 }
+
+#[test]
+fn test_pool_factory_stages() {
+    let mut stages = BTreeMap::new();
+
+    let testing_config = PoolConfig {
+        max_value_size: None,
+        max_size: Some(1 << 12),
+        max_age: Some(60),
+        stages: None,
+        min_fee: None,
+        max_tx_size: None,
+        max_inputs: None,
+        max_outputs: None,
+    };
+
+    stages.insert("testing".to_string(), testing_config);
+
+    let config = PoolConfig::new(
+        Some(1 << 10),
+        None,
+        None,
+        Some(stages),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let res = PoolFactory::create_stage(&config, "staging");
+    assert!(res.is_err());
+
+    let res = PoolFactory::create_stages(&config);
+    assert!(res.is_ok());
+
+    let mut pools = res.unwrap();
+    assert_eq!(pools.len(), PoolConfig::VALID_STAGES.len());
+
+    let testing_pool = pools.get("testing").unwrap();
+    assert_eq!(testing_pool.get_max_size(), 1 << 12);
+    assert_eq!(testing_pool.get_default_ttl(), Some(60));
+
+    let production_pool = pools.get("production").unwrap();
+    assert_eq!(production_pool.get_max_size(), PoolConfig::DEFAULT_MAX_SIZE);
+    assert_eq!(
+        production_pool.get_default_ttl(),
+        Some(u64::from(PoolConfig::DEFAULT_MAX_AGE))
+    );
+
+    let value = vec![0; 1 << 10];
+
+    let testing_pool = pools.get_mut("testing").unwrap();
+
+    for i in 0..3 {
+        let res = testing_pool.insert(&[1, i], &value, None);
+        assert!(res.is_ok());
+    }
+
+    let res = testing_pool.insert(&[1, 3], &value, None);
+    assert!(res.is_err());
+
+    let production_pool = pools.get_mut("production").unwrap();
+
+    let res = production_pool.insert(&[2, 0], &value, None);
+    assert!(res.is_ok());
+}
@@ -0,0 +1,248 @@
+//! # Snapshot
+//!
+//! `snapshot` contains the types and functions used to back up the items of a
+//! `Store` in a file and to restore them. A backup is a point-in-time copy of
+//! the items, read before the file is written, so that the writes following the
+//! backup do not end up in it.
+
+use crate::error::Error;
+use crate::index::next_key;
+use crate::result::Result;
+use crate::traits::Store;
+use crate::ttl;
+use crypto::hash::Blake512Hasher;
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use std::fs;
+
+/// `SnapshotItem` is an item of a `Snapshot`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize, Deserialize)]
+pub struct SnapshotItem {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub expiry: Option<u64>,
+}
+
+/// `Snapshot` is a point-in-time copy of the items of a `Store`, optionally
+/// restricted to the keys starting with a prefix.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub time: u64,
+    pub prefix: Option<Vec<u8>>,
+    pub items: Vec<SnapshotItem>,
+    pub checksum: Vec<u8>,
+}
+
+impl Snapshot {
+    /// `VERSION` is the current version of the `Snapshot` format.
+    pub const VERSION: u32 = 1;
+
+    /// `TMP_SUFFIX` is the suffix of the file a `Snapshot` is written to before
+    /// replacing the backup file.
+    pub const TMP_SUFFIX: &'static str = ".tmp";
+
+    /// `new` creates a new `Snapshot` of the items of a `Store` with keys
+    /// starting with a prefix, or of all the items if no prefix is given.
+    pub fn new<S: Store + ?Sized>(store: &S, prefix: Option<&[u8]>) -> Result<Snapshot> {
+        let to = prefix.and_then(next_key);
+        let to = to.as_ref().map(|to| to.as_slice());
+
+        let mut items = Vec::new();
+
+        for (key, value) in store.iter(prefix, to)? {
+            if ttl::is_ttl_key(&key) {
+                continue;
+            }
+
+            let expiry = ttl::get_expiry(store, &key)?;
+
+            let item = SnapshotItem { key, value, expiry };
+            items.push(item);
+        }
+
+        let mut snapshot = Snapshot {
+            version: Snapshot::VERSION,
+            time: ttl::now(),
+            prefix: prefix.map(|prefix| prefix.to_vec()),
+            items,
+            checksum: Vec::new(),
+        };
+
+        snapshot.checksum = snapshot.calc_checksum()?;
+
+        Ok(snapshot)
+    }
+
+    /// `calc_checksum` calculates the `Snapshot` checksum.
+    pub fn calc_checksum(&self) -> Result<Vec<u8>> {
+        let mut clone = self.clone();
+        clone.checksum = Vec::new();
+
+        let buf = clone.to_bytes()?;
+        let checksum = Blake512Hasher::hash(&buf).to_bytes().to_vec();
+
+        Ok(checksum)
+    }
+
+    /// `validate` validates the `Snapshot`.
+    pub fn validate(&self) -> Result<()> {
+        if self.version != Snapshot::VERSION {
+            let err = Error::InvalidSnapshot;
+            return Err(err);
+        }
+
+        if self.checksum != self.calc_checksum()? {
+            let err = Error::InvalidSnapshot;
+            return Err(err);
+        }
+
+        if let Some(ref prefix) = self.prefix {
+            for item in self.items.iter() {
+                if !item.key.starts_with(prefix) {
+                    let err = Error::InvalidSnapshot;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `to_bytes` converts the `Snapshot` into a CBOR binary.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(self).map_err(|e| e.into())
+    }
+
+    /// `from_bytes` converts a CBOR binary into a `Snapshot`.
+    pub fn from_bytes(b: &[u8]) -> Result<Snapshot> {
+        serde_cbor::from_slice(b).map_err(|e| e.into())
+    }
+
+    /// `write` writes the `Snapshot` to a file, replacing it only once
+    /// the `Snapshot` is fully written.
+    pub fn write(&self, path: &str) -> Result<()> {
+        let tmp_path = format!("{}{}", path, Snapshot::TMP_SUFFIX);
+
+        fs::write(&tmp_path, &self.to_bytes()?)?;
+        fs::rename(&tmp_path, path).map_err(|e| e.into())
+    }
+
+    /// `read` reads and validates a `Snapshot` from a file.
+    pub fn read(path: &str) -> Result<Snapshot> {
+        let buf = fs::read(path)?;
+        let snapshot = Snapshot::from_bytes(&buf)?;
+        snapshot.validate()?;
+
+        Ok(snapshot)
+    }
+
+    /// `restore` restores the `Snapshot` items in a `Store`, replacing the
+    /// items in the `Snapshot` prefix, and returns the number of restored items.
+    /// The items expired since the backup are not restored.
+    pub fn restore<S: Store + ?Sized>(&self, store: &mut S) -> Result<u64> {
+        self.validate()?;
+
+        if let Some(ref prefix) = self.prefix {
+            let to = next_key(prefix);
+            let to = to.as_ref().map(|to| to.as_slice());
+
+            let keys: Vec<Vec<u8>> = store
+                .iter(Some(prefix), to)?
+                .map(|(key, _)| key)
+                .filter(|key| !ttl::is_ttl_key(key))
+                .collect();
+
+            for key in keys {
+                store.remove(&key)?;
+            }
+        } else {
+            store.clear()?;
+        }
+
+        let now = ttl::now();
+        let mut restored = 0;
+
+        for item in self.items.iter() {
+            let ttl = match item.expiry {
+                Some(expiry) if expiry <= now => continue,
+                Some(expiry) => Some(expiry - now),
+                None => None,
+            };
+
+            store.insert(&item.key, &item.value, ttl)?;
+            restored += 1;
+        }
+
+        Ok(restored)
+    }
+}
+
+/// `backup` backs up the items of a `Store` with keys starting with a prefix,
+/// or all the items if no prefix is given, to a file, returning the number of
+/// backed up items.
+pub fn backup<S: Store + ?Sized>(store: &S, path: &str, prefix: Option<&[u8]>) -> Result<u64> {
+    let snapshot = Snapshot::new(store, prefix)?;
+    snapshot.write(path)?;
+
+    Ok(snapshot.items.len() as u64)
+}
+
+/// `restore` restores the items of a `Store` from a backup file, returning
+/// the number of restored items.
+pub fn restore<S: Store + ?Sized>(store: &mut S, path: &str) -> Result<u64> {
+    let snapshot = Snapshot::read(path)?;
+    snapshot.restore(store)
+}
+
+#[test]
+fn test_snapshot() {
+    use crate::memory::MemoryStoreFactory;
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    for i in 0..10u8 {
+        store.insert(&[0, i], &[i], None).unwrap();
+        store.insert(&[1, i], &[i], None).unwrap();
+    }
+
+    store.insert(&[0, 10], &[10], Some(3600)).unwrap();
+
+    let res = Snapshot::new(&store, Some(&[0]));
+    assert!(res.is_ok());
+    let snapshot = res.unwrap();
+    assert!(snapshot.validate().is_ok());
+    assert_eq!(snapshot.items.len(), 11);
+    assert!(snapshot.items.iter().all(|item| item.key[0] == 0));
+
+    let mut invalid = snapshot.clone();
+    invalid.items[0].value = vec![42];
+    assert!(invalid.validate().is_err());
+
+    store.remove(&[0, 1]).unwrap();
+    store.insert(&[0, 11], &[11], None).unwrap();
+
+    let res = snapshot.restore(&mut store);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 11);
+
+    assert!(store.lookup(&[0, 1]).unwrap());
+    assert!(!store.lookup(&[0, 11]).unwrap());
+    assert!(store.lookup(&[1, 1]).unwrap());
+    assert!(ttl::get_expiry(&store, &[0, 10]).unwrap().is_some());
+
+    let mut empty = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+    let res = Snapshot::new(&empty, None);
+    assert!(res.is_ok());
+    assert!(res.unwrap().items.is_empty());
+
+    let snapshot = Snapshot::new(&store, None).unwrap();
+    assert_eq!(snapshot.items.len(), 21);
+
+    let res = snapshot.restore(&mut empty);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 21);
+}
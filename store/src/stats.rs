@@ -0,0 +1,172 @@
+//! # Stats
+//!
+//! `stats` contains the `StoreStats` type and functions.
+
+use crate::traits::Store;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `PrefixStats` are the statistics of the `Store` items sharing a key prefix.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct PrefixStats {
+    pub keys_count: u64,
+    pub keys_size: u64,
+    pub values_size: u64,
+}
+
+impl PrefixStats {
+    /// `size` returns the size of the items with the prefix.
+    pub fn size(&self) -> u64 {
+        self.keys_size + self.values_size
+    }
+}
+
+/// `StoreStats` are the statistics of a `Store`. The items are grouped by stage
+/// and prefix, following the `[stage, prefix, ..]` layout of the storable keys.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct StoreStats {
+    pub keys_count: u64,
+    pub keys_size: u64,
+    pub values_size: u64,
+    pub disk_size: u64,
+    pub stages: BTreeMap<u8, BTreeMap<u8, PrefixStats>>,
+}
+
+impl StoreStats {
+    /// `new` creates a new empty `StoreStats`.
+    pub fn new() -> StoreStats {
+        StoreStats::default()
+    }
+
+    /// `size` returns the size of the `Store` items.
+    pub fn size(&self) -> u64 {
+        self.keys_size + self.values_size
+    }
+
+    /// `add_item` adds a `Store` item to the `StoreStats`. Keys shorter than
+    /// a stage and a prefix are only added to the totals.
+    pub fn add_item(&mut self, key: &[u8], value: &[u8]) {
+        self.keys_count += 1;
+        self.keys_size += key.len() as u64;
+        self.values_size += value.len() as u64;
+
+        if key.len() < 2 {
+            return;
+        }
+
+        let prefix_stats = self
+            .stages
+            .entry(key[0])
+            .or_insert_with(BTreeMap::new)
+            .entry(key[1])
+            .or_insert_with(PrefixStats::default);
+
+        prefix_stats.keys_count += 1;
+        prefix_stats.keys_size += key.len() as u64;
+        prefix_stats.values_size += value.len() as u64;
+    }
+
+    /// `get_prefix` returns the `PrefixStats` of a stage and a prefix.
+    pub fn get_prefix(&self, stage: u8, prefix: u8) -> PrefixStats {
+        self.stages
+            .get(&stage)
+            .and_then(|prefixes| prefixes.get(&prefix))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// `get_stage` returns the `PrefixStats` of all the items of a stage.
+    pub fn get_stage(&self, stage: u8) -> PrefixStats {
+        let mut stage_stats = PrefixStats::default();
+
+        if let Some(prefixes) = self.stages.get(&stage) {
+            for prefix_stats in prefixes.values() {
+                stage_stats.keys_count += prefix_stats.keys_count;
+                stage_stats.keys_size += prefix_stats.keys_size;
+                stage_stats.values_size += prefix_stats.values_size;
+            }
+        }
+
+        stage_stats
+    }
+}
+
+/// `StoreOccupancy` is the occupancy of a `Store`, read from the counters the
+/// `Store` maintains on insert and remove instead of scanning its items.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct StoreOccupancy {
+    pub keys_count: u32,
+    pub size: u32,
+    pub max_size: u32,
+}
+
+impl StoreOccupancy {
+    /// `new` creates a new `StoreOccupancy` from the counters of a `Store`.
+    pub fn new<S: Store + ?Sized>(store: &S) -> StoreOccupancy {
+        StoreOccupancy {
+            keys_count: store.keys_count(),
+            size: store.size(),
+            max_size: store.get_max_size(),
+        }
+    }
+
+    /// `percent` returns the percentage of the maximum size of the `Store`
+    /// used by its items.
+    pub fn percent(&self) -> u32 {
+        if self.max_size == 0 {
+            return 100;
+        }
+
+        let percent = u64::from(self.size) * 100 / u64::from(self.max_size);
+
+        percent.min(100) as u32
+    }
+}
+
+#[test]
+fn test_store_stats() {
+    let mut stats = StoreStats::new();
+
+    stats.add_item(&[0, 1, 2], &[0; 10]);
+    stats.add_item(&[0, 1, 3], &[0; 20]);
+    stats.add_item(&[0, 3, 2], &[0; 30]);
+    stats.add_item(&[2, 1, 2], &[0; 40]);
+    stats.add_item(&[0], &[0; 50]);
+
+    assert_eq!(stats.keys_count, 5);
+    assert_eq!(stats.keys_size, 13);
+    assert_eq!(stats.values_size, 150);
+    assert_eq!(stats.size(), 163);
+
+    let prefix_stats = stats.get_prefix(0, 1);
+    assert_eq!(prefix_stats.keys_count, 2);
+    assert_eq!(prefix_stats.size(), 36);
+
+    let stage_stats = stats.get_stage(0);
+    assert_eq!(stage_stats.keys_count, 3);
+    assert_eq!(stage_stats.values_size, 60);
+
+    let stage_stats = stats.get_stage(1);
+    assert_eq!(stage_stats, PrefixStats::default());
+}
+
+#[test]
+fn test_store_occupancy() {
+    use crate::backend::BTreeStore;
+
+    let mut store = BTreeStore::new(1 << 8, 1 << 10).unwrap();
+
+    let occupancy = StoreOccupancy::new(&store);
+    assert_eq!(occupancy.keys_count, 0);
+    assert_eq!(occupancy.percent(), 0);
+
+    store.insert(&[0, 1], &[0; 254], None).unwrap();
+    store.insert(&[0, 2], &[0; 254], None).unwrap();
+
+    let occupancy = StoreOccupancy::new(&store);
+    assert_eq!(occupancy.keys_count, 2);
+    assert_eq!(occupancy.size, 512);
+    assert_eq!(occupancy.percent(), 50);
+
+    assert_eq!(StoreOccupancy::default().percent(), 100);
+}
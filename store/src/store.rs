@@ -2,29 +2,42 @@
 //!
 //! `store` is the module containing the store type and functions.
 
-use crate::backend::UnQLiteStore;
+use crate::backend::{CachedStore, UnQLiteStore, WalStore};
 use crate::error::Error;
+use crate::migrations::{self, Migration, MigrationReport};
 use crate::persistent::PersistentStoreFactory;
 use crate::result::Result;
 use crate::temporary::TemporaryStoreFactory;
+use crate::traits::Store;
+use crate::verify::{self, Verifier, VerifyReport};
+use crate::wal::{WalLog, WalSync};
 use config::store::StoreConfig;
 
 /// `StoreFactory` is the factory for store types.
 pub struct StoreFactory {}
 
 impl StoreFactory {
-    /// `create` creates a new store from the configs.
-    pub fn create(path: Option<String>, config: &StoreConfig) -> Result<UnQLiteStore> {
+    /// `create` creates a new store from the configs, caching the most recently
+    /// read values. The writes of a persistent store are logged in a write-ahead
+    /// log next to the store, which is replayed on opening.
+    pub fn create(
+        path: Option<String>,
+        config: &StoreConfig,
+    ) -> Result<CachedStore<WalStore<UnQLiteStore>>> {
         config.validate()?;
 
         let mut config = config.clone();
         config.populate();
 
-        match config.kind.unwrap().as_str() {
-            "temporary" => TemporaryStoreFactory::new_unqlite(
-                config.max_value_size.unwrap(),
-                config.max_size.unwrap(),
-            ),
+        let store = match config.kind.unwrap().as_str() {
+            "temporary" => {
+                let store = TemporaryStoreFactory::new_unqlite(
+                    config.max_value_size.unwrap(),
+                    config.max_size.unwrap(),
+                )?;
+
+                Ok(WalStore::new(store))
+            }
             "persistent" => {
                 if path.is_none() {
                     let err = Error::InvalidPath;
@@ -33,16 +46,46 @@ impl StoreFactory {
 
                 let path = path.unwrap();
 
-                PersistentStoreFactory::new_unqlite(
+                let store = PersistentStoreFactory::new_unqlite(
                     &path,
                     config.max_value_size.unwrap(),
                     config.max_size.unwrap(),
-                )
+                )?;
+
+                if config.wal.unwrap() {
+                    let log_path = format!("{}{}", &path, WalLog::SUFFIX);
+                    let sync = WalSync::parse(&config.wal_sync.unwrap())?;
+
+                    WalStore::open(store, &log_path, sync)
+                } else {
+                    Ok(WalStore::new(store))
+                }
             }
             _ => {
                 let err = Error::InvalidKind;
                 Err(err)
             }
-        }
+        }?;
+
+        Ok(CachedStore::new(store, config.cache_capacity.unwrap()))
+    }
+
+    /// `verify_and_repair` checks the integrity of a store with a list of `Verifier`s,
+    /// removing the faulty records if required.
+    pub fn verify_and_repair<S: Store>(
+        store: &mut S,
+        verifiers: &[Box<dyn Verifier<S>>],
+        repair: bool,
+    ) -> Result<VerifyReport> {
+        verify::verify_and_repair(store, verifiers, repair)
+    }
+
+    /// `migrate` runs the pending `Migration`s of a store, upgrading its schema
+    /// to the latest version.
+    pub fn migrate<S: Store>(
+        store: &mut S,
+        migrations: &[Box<dyn Migration<S>>],
+    ) -> Result<MigrationReport> {
+        migrations::migrate(store, migrations)
     }
 }
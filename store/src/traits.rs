@@ -3,12 +3,20 @@
 //! `traits` contains Alsacoin's storage traits.
 
 use crate::result::Result;
+use crate::snapshot;
+use crate::stats::StoreStats;
+
+/// `StoreIter` is the lazy iterator over the key-value pairs of a `Store`.
+pub type StoreIter<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
 
 /// `Store` is the trait implemented by `Alsacoin` stores.
 pub trait Store {
     /// `keys_size` returns the size of the store keys.
     fn keys_size(&self) -> u32;
 
+    /// `keys_count` returns the number of store keys.
+    fn keys_count(&self) -> u32;
+
     /// `values_size` returns the size of the store values.
     fn values_size(&self) -> u32;
 
@@ -27,13 +35,23 @@ pub trait Store {
     /// `max_size` gets the maximum size of a store size.
     fn get_max_size(&self) -> u32;
 
+    /// `set_default_ttl` sets the time-to-live, in seconds, of the items
+    /// inserted or created without one.
+    fn set_default_ttl(&mut self, ttl: Option<u64>);
+
+    /// `get_default_ttl` gets the default time-to-live of the store items.
+    fn get_default_ttl(&self) -> Option<u64>;
+
     /// `lookup` looks up a `Store` value by key.
     fn lookup(&self, key: &[u8]) -> Result<bool>;
 
     /// `get` returns a `Store` value by key.
     fn get(&self, key: &[u8]) -> Result<Vec<u8>>;
 
-    // TODO: de-lame query: use streams
+    /// `iter` returns an iterator over the `Store` items in a range, yielding
+    /// the key-value pairs one at a time instead of materializing them.
+    fn iter<'a>(&'a self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<StoreIter<'a>>;
+
     /// `query` queries the `Store` for values.
     fn query(
         &self,
@@ -49,8 +67,9 @@ pub trait Store {
     /// `count` counts `Store` items matching a specific query.
     fn count(&self, from: Option<&[u8]>, to: Option<&[u8]>, skip: Option<u32>) -> Result<u32>;
 
-    /// `insert` inserts an item in the `Store`.
-    fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<()>;
+    /// `insert` inserts an item in the `Store`, optionally expiring after a
+    /// time-to-live in seconds.
+    fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<u64>) -> Result<()>;
 
     /// `create` creates a previously not existing item in the `Store`.
     fn create(&mut self, key: &[u8], value: &[u8]) -> Result<()>;
@@ -77,6 +96,38 @@ pub trait Store {
 
     /// `clear` clears the `Store`.
     fn clear(&mut self) -> Result<()>;
+
+    /// `cleanup_expired` removes the expired items from the `Store`, returning
+    /// the number of removed items.
+    fn cleanup_expired(&mut self) -> Result<u32>;
+
+    /// `compact` compacts the `Store`, reclaiming the space of the removed items.
+    fn compact(&mut self) -> Result<()>;
+
+    /// `stats` returns the `Store` statistics.
+    fn stats(&self) -> Result<StoreStats>;
+
+    /// `begin_batch` starts a batch of writes, applied atomically on `commit_batch`
+    /// and reverted on `rollback_batch`. The `Store`s without a write-ahead log
+    /// apply the writes of a batch as they come and cannot revert them.
+    fn begin_batch(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// `commit_batch` commits the batch of writes started by `begin_batch`.
+    fn commit_batch(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// `rollback_batch` reverts the batch of writes started by `begin_batch`.
+    fn rollback_batch(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// `flush` writes the pending writes of the `Store` to its storage.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// `MemoryStore` is the trait implemented by in-memory `Store`s.
@@ -86,4 +137,17 @@ pub trait MemoryStore: Store {}
 pub trait TemporaryStore: Store {}
 
 /// `PersistentStore` is the trait implemented by persistent `Store`s.
-pub trait PersistentStore: Store {}
+pub trait PersistentStore: Store {
+    /// `backup` backs up the items of the `Store` with keys starting with a prefix,
+    /// or all the items if no prefix is given, to a file, returning the number
+    /// of backed up items.
+    fn backup(&self, path: &str, prefix: Option<&[u8]>) -> Result<u64> {
+        snapshot::backup(self, path, prefix)
+    }
+
+    /// `restore` restores the items of the `Store` from a backup file, returning
+    /// the number of restored items.
+    fn restore(&mut self, path: &str) -> Result<u64> {
+        snapshot::restore(self, path)
+    }
+}
@@ -0,0 +1,141 @@
+//! # TTL
+//!
+//! `ttl` contains the functions used to expire `Store` items.
+//!
+//! The expiration time of an item is kept in the `Store` itself, in two reserved
+//! key families: the expiry entry `EXPIRY_TAG | key`, holding the expiration time,
+//! and the schedule entry `SCHEDULE_TAG | expiration time | key`, holding the key,
+//! so that the expired items can be found with a single range query.
+
+use crate::index::next_key;
+use crate::result::Result;
+use crate::traits::Store;
+use std::convert::TryInto;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `EXPIRY_TAG` is the first byte of the expiry entries keys.
+pub const EXPIRY_TAG: u8 = 0xfe;
+
+/// `SCHEDULE_TAG` is the first byte of the schedule entries keys.
+pub const SCHEDULE_TAG: u8 = 0xff;
+
+/// `now` returns the current time in seconds from the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// `is_ttl_key` returns if a key is reserved to the expiration entries.
+pub fn is_ttl_key(key: &[u8]) -> bool {
+    !key.is_empty() && (key[0] == EXPIRY_TAG || key[0] == SCHEDULE_TAG)
+}
+
+/// `expiry_key` returns the key of the expiry entry of a key.
+pub fn expiry_key(key: &[u8]) -> Vec<u8> {
+    let mut buf = vec![EXPIRY_TAG];
+    buf.extend_from_slice(key);
+    buf
+}
+
+/// `schedule_key` returns the key of the schedule entry of a key.
+pub fn schedule_key(key: &[u8], expiry: u64) -> Vec<u8> {
+    let mut buf = vec![SCHEDULE_TAG];
+    buf.extend_from_slice(&expiry.to_be_bytes());
+    buf.extend_from_slice(key);
+    buf
+}
+
+/// `get_expiry` returns the expiration time of a key, if any.
+pub fn get_expiry<S: Store + ?Sized>(store: &S, key: &[u8]) -> Result<Option<u64>> {
+    let expiry_key = expiry_key(key);
+
+    if !store.lookup(&expiry_key)? {
+        return Ok(None);
+    }
+
+    let value = store.get(&expiry_key)?;
+
+    Ok(value.as_slice().try_into().ok().map(u64::from_be_bytes))
+}
+
+/// `clear_expiry` removes the expiration time of a key, if any.
+pub fn clear_expiry<S: Store + ?Sized>(store: &mut S, key: &[u8]) -> Result<()> {
+    if is_ttl_key(key) {
+        return Ok(());
+    }
+
+    if let Some(expiry) = get_expiry(store, key)? {
+        store.remove(&expiry_key(key))?;
+
+        let schedule_key = schedule_key(key, expiry);
+
+        if store.lookup(&schedule_key)? {
+            store.remove(&schedule_key)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `set_expiry` sets the expiration time of a key to a number of seconds from now,
+/// replacing the previous one. A `None` time-to-live removes the expiration time.
+pub fn set_expiry<S: Store + ?Sized>(store: &mut S, key: &[u8], ttl: Option<u64>) -> Result<()> {
+    if is_ttl_key(key) {
+        return Ok(());
+    }
+
+    clear_expiry(store, key)?;
+
+    if let Some(ttl) = ttl {
+        let expiry = now().saturating_add(ttl);
+
+        store.insert(&expiry_key(key), &expiry.to_be_bytes(), None)?;
+        store.insert(&schedule_key(key, expiry), key, None)?;
+    }
+
+    Ok(())
+}
+
+/// `cleanup_expired` removes the items expired up to a time, returning the
+/// number of removed items.
+pub fn cleanup_expired<S: Store + ?Sized>(store: &mut S, time: u64) -> Result<u32> {
+    let from = vec![SCHEDULE_TAG];
+    let to = next_key(&schedule_key(&[], time));
+    let to = to.as_ref().map(|to| to.as_slice());
+
+    let keys = store.query(Some(&from), to, None, None)?;
+
+    let mut removed = 0;
+
+    for key in keys {
+        if store.lookup(&key)? {
+            store.remove(&key)?;
+            removed += 1;
+        } else {
+            clear_expiry(store, &key)?;
+        }
+    }
+
+    store.remove_range(Some(&from), to, None)?;
+
+    Ok(removed)
+}
+
+#[test]
+fn test_ttl_keys() {
+    let key = b"key";
+
+    let expiry_key = expiry_key(key);
+    assert!(is_ttl_key(&expiry_key));
+    assert_eq!(&expiry_key[1..], key);
+
+    let schedule_a = schedule_key(key, 1);
+    let schedule_b = schedule_key(key, 2);
+    assert!(is_ttl_key(&schedule_a));
+    assert!(schedule_a < schedule_b);
+
+    assert!(!is_ttl_key(key));
+    assert!(!is_ttl_key(&[]));
+}
@@ -0,0 +1,180 @@
+//! # Verify
+//!
+//! `verify` contains the types and functions used to check the integrity of a
+//! `Store` and to repair it, removing the records left broken by partial writes.
+
+use crate::result::Result;
+use crate::traits::Store;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// `Fault` is the fault of a broken `Store` record.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum Fault {
+    /// The record cannot be decoded, or it is stored under a wrong key.
+    Corrupted,
+    /// The record is decoded, but it is not valid.
+    Invalid,
+    /// The record references a record missing from the `Store`.
+    Orphaned,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Corrupted => write!(f, "corrupted"),
+            Fault::Invalid => write!(f, "invalid"),
+            Fault::Orphaned => write!(f, "orphaned"),
+        }
+    }
+}
+
+/// `Verifier` is the trait implemented by the checkers of a family of `Store` records.
+pub trait Verifier<S: Store> {
+    /// `name` returns the name of the checked records.
+    fn name(&self) -> String;
+
+    /// `range` returns the `Store` keys range of the checked records.
+    fn range(&self) -> (Vec<u8>, Option<Vec<u8>>);
+
+    /// `verify` verifies a single record, returning its `Fault`, if any.
+    fn verify(&self, store: &S, key: &[u8], value: &[u8]) -> Result<Option<Fault>>;
+
+    /// `verify_all` verifies the records as a whole, after the single records
+    /// have been verified, returning the description of the failure, if any.
+    fn verify_all(&self, _store: &S) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// `FaultyRecord` is a broken `Store` record found by a `Verifier`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct FaultyRecord {
+    pub name: String,
+    pub key: Vec<u8>,
+    pub fault: Fault,
+}
+
+/// `VerifyReport` is the report of a `Store` integrity check.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub checked: u64,
+    pub faulty: Vec<FaultyRecord>,
+    pub failures: Vec<String>,
+    pub repaired: bool,
+}
+
+impl VerifyReport {
+    /// `is_ok` returns if no broken record or failure was found.
+    pub fn is_ok(&self) -> bool {
+        self.faulty.is_empty() && self.failures.is_empty()
+    }
+}
+
+/// `verify_and_repair` checks the records of a `Store` with a list of `Verifier`s,
+/// removing the faulty ones if required. The `Verifier`s run in order, each one
+/// after the repairs of the previous ones, so that the records referencing the
+/// removed ones are found as orphaned by the following `Verifier`s.
+pub fn verify_and_repair<S: Store>(
+    store: &mut S,
+    verifiers: &[Box<dyn Verifier<S>>],
+    repair: bool,
+) -> Result<VerifyReport> {
+    let mut report = VerifyReport {
+        repaired: repair,
+        ..Default::default()
+    };
+
+    for verifier in verifiers {
+        let (from, to) = verifier.range();
+        let to = to.as_ref().map(|to| to.as_slice());
+        let mut faulty = Vec::new();
+
+        for (key, value) in store.iter(Some(&from), to)? {
+            report.checked += 1;
+
+            if let Some(fault) = verifier.verify(store, &key, &value)? {
+                let record = FaultyRecord {
+                    name: verifier.name(),
+                    key,
+                    fault,
+                };
+
+                faulty.push(record);
+            }
+        }
+
+        if repair {
+            for record in faulty.iter() {
+                store.remove(&record.key)?;
+            }
+        }
+
+        report.faulty.extend(faulty);
+
+        if let Some(failure) = verifier.verify_all(store)? {
+            let failure = format!("{}: {}", verifier.name(), failure);
+            report.failures.push(failure);
+        }
+    }
+
+    Ok(report)
+}
+
+#[test]
+fn test_verify_and_repair() {
+    use crate::memory::MemoryStoreFactory;
+
+    struct EvenVerifier;
+
+    impl<S: Store> Verifier<S> for EvenVerifier {
+        fn name(&self) -> String {
+            "even".into()
+        }
+
+        fn range(&self) -> (Vec<u8>, Option<Vec<u8>>) {
+            (vec![0], Some(vec![1]))
+        }
+
+        fn verify(&self, _store: &S, _key: &[u8], value: &[u8]) -> Result<Option<Fault>> {
+            if value.len() == 1 && value[0] % 2 == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(Fault::Corrupted))
+            }
+        }
+    }
+
+    let max_value_size = 1 << 10;
+    let max_size = 1 << 20;
+
+    let mut store = MemoryStoreFactory::new_btree(max_value_size, max_size).unwrap();
+
+    for i in 0..10u8 {
+        store.insert(&[0, i], &[i], None).unwrap();
+    }
+
+    store.insert(&[1, 1], &[1], None).unwrap();
+
+    let verifiers: Vec<Box<dyn Verifier<_>>> = vec![Box::new(EvenVerifier)];
+
+    let res = verify_and_repair(&mut store, &verifiers, false);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.checked, 10);
+    assert_eq!(report.faulty.len(), 5);
+    assert!(store.lookup(&[0, 1]).unwrap());
+
+    let res = verify_and_repair(&mut store, &verifiers, true);
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap().faulty.len(), 5);
+    assert!(!store.lookup(&[0, 1]).unwrap());
+    assert!(store.lookup(&[1, 1]).unwrap());
+
+    let res = verify_and_repair(&mut store, &verifiers, false);
+    assert!(res.is_ok());
+    let report = res.unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.checked, 5);
+}
@@ -0,0 +1,424 @@
+//! # WAL
+//!
+//! `wal` contains the write-ahead log types and functions. For every write of a
+//! `Store`, the log keeps the images of the written keys before and after the
+//! write, so that on startup the committed writes are redone and the uncommitted
+//! ones are undone, whatever the guarantees of the `Store` backend.
+//!
+//! The images of a key include its expiration entries, so that the items with a
+//! time-to-live are restored with their expiration time.
+
+use crate::error::Error;
+use crate::result::Result;
+use crate::traits::Store;
+use crate::ttl;
+use crypto::hash::digest::DIGEST_LEN;
+use crypto::hash::{Blake512Hasher, Digest};
+use serde::{Deserialize, Serialize};
+use serde_cbor;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::result;
+
+/// `WalImage` is the image of a `Store` key: its value, or `None` if missing.
+pub type WalImage = (Vec<u8>, Option<Vec<u8>>);
+
+/// `WalRecord` is a record of a `WalLog`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WalRecord {
+    /// The images of the keys before a write.
+    Undo { images: Vec<WalImage> },
+    /// The images of the keys after a write.
+    Redo { images: Vec<WalImage> },
+    /// The commit of the writes following the previous commit or rollback.
+    Commit,
+    /// The rollback of the writes following the previous commit or rollback.
+    Rollback,
+}
+
+/// `WalSync` is the policy used to flush the `WalLog` records to disk.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum WalSync {
+    /// Every record is flushed to disk.
+    Always,
+    /// The records are flushed to disk on commit and rollback.
+    Commit,
+    /// The records are flushed to disk by the operating system.
+    Never,
+}
+
+impl WalSync {
+    /// `parse` parses a `WalSync` from a string.
+    pub fn parse(s: &str) -> Result<WalSync> {
+        match s {
+            "always" => Ok(WalSync::Always),
+            "commit" => Ok(WalSync::Commit),
+            "never" => Ok(WalSync::Never),
+            _ => {
+                let err = Error::InvalidKind;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Default for WalSync {
+    fn default() -> WalSync {
+        WalSync::Commit
+    }
+}
+
+impl fmt::Display for WalSync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WalSync::Always => write!(f, "always"),
+            WalSync::Commit => write!(f, "commit"),
+            WalSync::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// `WalReport` is the report of the replay of a `WalLog`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub struct WalReport {
+    pub redone: u64,
+    pub undone: u64,
+}
+
+/// `WalLog` is an append-only file of `WalRecord`s. Every record is framed by its
+/// length and checksum, so that a record torn by a crash ends the log.
+#[derive(Debug)]
+pub struct WalLog {
+    path: String,
+    file: File,
+    sync: WalSync,
+    size: u64,
+}
+
+impl WalLog {
+    /// `SUFFIX` is the suffix of the file of the `WalLog` of a persistent `Store`.
+    pub const SUFFIX: &'static str = ".wal";
+
+    /// `open` opens or creates the `WalLog` at a path.
+    pub fn open(path: &str, sync: WalSync) -> Result<WalLog> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let size = file.metadata()?.len();
+
+        let log = WalLog {
+            path: path.into(),
+            file,
+            sync,
+            size,
+        };
+
+        Ok(log)
+    }
+
+    /// `path` returns the path of the `WalLog`.
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    /// `size` returns the size in bytes of the `WalLog`.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// `sync_policy` returns the `WalSync` of the `WalLog`.
+    pub fn sync_policy(&self) -> WalSync {
+        self.sync
+    }
+
+    /// `append` appends a `WalRecord` to the `WalLog`, flushing it to disk as
+    /// required by the `WalSync`.
+    pub fn append(&mut self, record: &WalRecord) -> Result<()> {
+        let payload = serde_cbor::to_vec(record)?;
+        let checksum = Blake512Hasher::hash(&payload);
+
+        let mut buf = Vec::with_capacity(4 + DIGEST_LEN + payload.len());
+        buf.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&checksum.to_bytes());
+        buf.extend_from_slice(&payload);
+
+        self.file.write_all(&buf)?;
+        self.size += buf.len() as u64;
+
+        let sync = match self.sync {
+            WalSync::Always => true,
+            WalSync::Commit => match record {
+                WalRecord::Commit | WalRecord::Rollback => true,
+                _ => false,
+            },
+            WalSync::Never => false,
+        };
+
+        if sync {
+            self.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// `sync` flushes the `WalLog` to disk.
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.sync_data().map_err(|e| e.into())
+    }
+
+    /// `records` reads the `WalRecord`s of the `WalLog`, up to the first torn
+    /// or corrupted record.
+    pub fn records(&mut self) -> Result<Vec<WalRecord>> {
+        let mut buf = Vec::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut buf)?;
+
+        let mut records = Vec::new();
+        let mut offset = 0;
+
+        while offset + 4 + DIGEST_LEN <= buf.len() {
+            let len = u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap()) as usize;
+            let start = offset + 4 + DIGEST_LEN;
+
+            if start + len > buf.len() {
+                break;
+            }
+
+            let checksum = Digest::from_slice(&buf[offset + 4..start])?;
+            let payload = &buf[start..start + len];
+
+            if Blake512Hasher::hash(payload) != checksum {
+                break;
+            }
+
+            match serde_cbor::from_slice(payload) {
+                Ok(record) => records.push(record),
+                Err(_) => break,
+            }
+
+            offset = start + len;
+        }
+
+        Ok(records)
+    }
+
+    /// `truncate` removes all the records of the `WalLog`.
+    pub fn truncate(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.size = 0;
+        self.sync()
+    }
+}
+
+/// `apply_images` sets the keys of a `Store` to their images. The images of the
+/// expiration entries of a key have to follow the image of the key.
+pub fn apply_images<S: Store + ?Sized>(store: &mut S, images: &[WalImage]) -> Result<()> {
+    let default_ttl = store.get_default_ttl();
+    store.set_default_ttl(None);
+
+    let res = images
+        .iter()
+        .try_for_each(|(key, value)| apply_image(store, key, value.as_ref()));
+
+    store.set_default_ttl(default_ttl);
+
+    res
+}
+
+/// `apply_image` sets a key of a `Store` to its image.
+fn apply_image<S: Store + ?Sized>(
+    store: &mut S,
+    key: &[u8],
+    value: Option<&Vec<u8>>,
+) -> Result<()> {
+    match value {
+        Some(value) => store.insert(key, value, None),
+        None => {
+            if store.lookup(key)? {
+                store.remove(key)
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `replay` applies the `WalRecord`s of a `WalLog` to a `Store`, redoing the
+/// committed writes and undoing the others.
+pub fn replay<S: Store + ?Sized>(store: &mut S, records: &[WalRecord]) -> Result<WalReport> {
+    let mut report = WalReport::default();
+    let mut undo: Vec<&[WalImage]> = Vec::new();
+    let mut redo: Vec<&[WalImage]> = Vec::new();
+
+    for record in records {
+        match record {
+            WalRecord::Undo { images } => undo.push(images),
+            WalRecord::Redo { images } => redo.push(images),
+            WalRecord::Commit => {
+                for images in redo.iter() {
+                    apply_images(store, images)?;
+                    report.redone += 1;
+                }
+
+                undo.clear();
+                redo.clear();
+            }
+            WalRecord::Rollback => {
+                for images in undo.iter().rev() {
+                    apply_images(store, images)?;
+                    report.undone += 1;
+                }
+
+                undo.clear();
+                redo.clear();
+            }
+        }
+    }
+
+    for images in undo.iter().rev() {
+        apply_images(store, images)?;
+        report.undone += 1;
+    }
+
+    Ok(report)
+}
+
+/// `key_images` returns the images of a list of `Store` keys, each followed by
+/// the images of its expiration entries.
+pub fn key_images<S: Store + ?Sized>(store: &S, keys: &[Vec<u8>]) -> Result<Vec<WalImage>> {
+    let mut images = Vec::new();
+
+    for key in keys {
+        images.push(key_image(store, key)?);
+
+        if ttl::is_ttl_key(key) {
+            continue;
+        }
+
+        images.push(key_image(store, &ttl::expiry_key(key))?);
+
+        if let Some(expiry) = ttl::get_expiry(store, key)? {
+            images.push(key_image(store, &ttl::schedule_key(key, expiry))?);
+        }
+    }
+
+    Ok(images)
+}
+
+/// `key_image` returns the image of a `Store` key.
+fn key_image<S: Store + ?Sized>(store: &S, key: &[u8]) -> Result<WalImage> {
+    let value = if store.lookup(key)? {
+        Some(store.get(key)?)
+    } else {
+        None
+    };
+
+    Ok((key.to_owned(), value))
+}
+
+/// `atomic` runs a group of writes on a `Store` as a batch, committing them if
+/// they succeed and rolling them back otherwise.
+pub fn atomic<S, T, E, F>(store: &mut S, f: F) -> result::Result<T, E>
+where
+    S: Store + ?Sized,
+    E: From<Error>,
+    F: FnOnce(&mut S) -> result::Result<T, E>,
+{
+    store.begin_batch()?;
+
+    match f(store) {
+        Ok(value) => {
+            store.commit_batch()?;
+            Ok(value)
+        }
+        Err(err) => {
+            store.rollback_batch()?;
+            Err(err)
+        }
+    }
+}
+
+#[test]
+fn test_wal_log() {
+    use tempfile::NamedTempFile;
+
+    let path = NamedTempFile::new().unwrap().into_temp_path();
+    let path = path.to_str().unwrap();
+
+    let mut log = WalLog::open(path, WalSync::Always).unwrap();
+    assert_eq!(log.size(), 0);
+
+    let records = vec![
+        WalRecord::Undo {
+            images: vec![(vec![1], None)],
+        },
+        WalRecord::Redo {
+            images: vec![(vec![1], Some(vec![1]))],
+        },
+        WalRecord::Commit,
+    ];
+
+    for record in records.iter() {
+        log.append(record).unwrap();
+    }
+
+    assert_eq!(log.records().unwrap(), records);
+
+    let size = log.size();
+    log.file.write_all(&[0, 0, 0, 10, 1, 2]).unwrap();
+
+    let mut log = WalLog::open(path, WalSync::Commit).unwrap();
+    assert_eq!(log.size(), size + 6);
+    assert_eq!(log.records().unwrap(), records);
+
+    log.truncate().unwrap();
+    assert!(log.records().unwrap().is_empty());
+
+    assert_eq!(WalSync::parse("commit").unwrap(), WalSync::Commit);
+    assert!(WalSync::parse("sometimes").is_err());
+}
+
+#[test]
+fn test_wal_replay() {
+    use crate::backend::BTreeStore;
+
+    let mut store = BTreeStore::new(1 << 10, 1 << 20).unwrap();
+    store.insert(&[1], &[1], None).unwrap();
+    store.insert(&[3], &[3], Some(1000)).unwrap();
+
+    let records = vec![
+        WalRecord::Undo {
+            images: key_images(&store, &[vec![1]]).unwrap(),
+        },
+        WalRecord::Redo {
+            images: vec![(vec![1], Some(vec![2]))],
+        },
+        WalRecord::Commit,
+        WalRecord::Undo {
+            images: key_images(&store, &[vec![3]]).unwrap(),
+        },
+        WalRecord::Redo {
+            images: vec![(vec![3], None)],
+        },
+        WalRecord::Undo {
+            images: vec![(vec![2], None)],
+        },
+    ];
+
+    store.remove(&[3]).unwrap();
+    store.insert(&[2], &[2], None).unwrap();
+
+    let report = replay(&mut store, &records).unwrap();
+    assert_eq!(report.redone, 1);
+    assert_eq!(report.undone, 2);
+
+    assert_eq!(store.get(&[1]).unwrap(), vec![2]);
+    assert!(!store.lookup(&[2]).unwrap());
+    assert_eq!(store.get(&[3]).unwrap(), vec![3]);
+    assert!(ttl::get_expiry(&store, &[3]).unwrap().is_some());
+}
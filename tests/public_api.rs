@@ -0,0 +1,12 @@
+//! # Public API
+//!
+//! `public_api` checks the stable public API of `alsacoin` by compiling the
+//! programs in `tests/ui` against it, as a downstream crate would.
+//! A change breaking one of the programs is a breaking change of the API.
+
+#[test]
+fn test_public_api() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/prelude.rs");
+    cases.pass("tests/ui/reexports.rs");
+}
@@ -0,0 +1,69 @@
+use alsacoin::prelude::*;
+
+fn is_store<S: Store>() {}
+fn is_network<N: Network>() {}
+fn is_storable<S: Store, T: Storable<S>>() {}
+
+#[allow(dead_code)]
+fn public_traits<S: Store, N: Network>() {
+    is_store::<S>();
+    is_network::<N>();
+    is_storable::<S, Transaction>();
+}
+
+#[allow(dead_code)]
+fn public_types(
+    _: Option<CliClient>,
+    _: Option<CliDaemon>,
+    _: Option<Config>,
+    _: Option<Logger>,
+    _: Option<Account>,
+    _: Option<Address>,
+    _: Option<ConsensusMessage>,
+    _: Option<Input>,
+    _: Option<Node>,
+    _: Option<Output>,
+    _: Option<Message>,
+    _: Option<NetworkFactory>,
+    _: Option<ProtocolState>,
+    _: Option<ProtocolClient>,
+    _: Option<ProtocolClientServer>,
+    _: Option<ProtocolConsensusServer>,
+    _: Option<ProtocolMinerServer>,
+    _: Option<PoolFactory>,
+    _: Option<StoreFactory>,
+) {
+}
+
+fn main() {
+    let digest: Digest = Digest::random().unwrap();
+    let _: [u8; 64] = digest.to_bytes();
+
+    let stage: Stage = Stage::random().unwrap();
+    let _: Timestamp = Timestamp::now();
+    let _: BalloonParams = BalloonParams::new(16, 1, 3).unwrap();
+
+    let keypair: KeyPair = KeyPair::new().unwrap();
+    let secret_key: SecretKey = SecretKey::random().unwrap();
+    let public_key: PublicKey = secret_key.to_public();
+
+    let msg = b"message";
+    let signature: Signature = keypair.sign(msg).unwrap();
+    keypair.verify(&signature, msg).unwrap();
+
+    let signature: Signature = secret_key.sign(msg);
+    public_key.verify(&signature, msg).unwrap();
+
+    let wallet: Wallet = Wallet::new(stage).unwrap();
+    let _: Signature = wallet.sign(msg).unwrap();
+    let buf: Vec<u8> = wallet.to_bytes().unwrap();
+    let _: Wallet = Wallet::from_bytes(&buf).unwrap();
+
+    let transaction: Transaction = Transaction::new().unwrap();
+    let buf: Vec<u8> = transaction.to_bytes().unwrap();
+    let _: Transaction = Transaction::from_bytes(&buf).unwrap();
+
+    let node: Node = Node::random(16).unwrap();
+    let buf: Vec<u8> = node.to_bytes().unwrap();
+    let _: Node = Node::from_bytes(&buf).unwrap();
+}
@@ -0,0 +1,53 @@
+use alsacoin::prelude;
+
+#[allow(dead_code)]
+fn reexports(
+    account: alsacoin::Account,
+    address: alsacoin::Address,
+    cli_client: alsacoin::CliClient,
+    cli_daemon: alsacoin::CliDaemon,
+    config: alsacoin::Config,
+    cons_msg: alsacoin::ConsensusMessage,
+    node: alsacoin::Node,
+    pool_factory: alsacoin::PoolFactory,
+    protocol_client: alsacoin::ProtocolClient,
+    protocol_state: alsacoin::ProtocolState,
+    stage: alsacoin::Stage,
+    store_factory: alsacoin::StoreFactory,
+    transaction: alsacoin::Transaction,
+    wallet: alsacoin::Wallet,
+) -> (
+    prelude::Account,
+    prelude::Address,
+    prelude::CliClient,
+    prelude::CliDaemon,
+    prelude::Config,
+    prelude::ConsensusMessage,
+    prelude::Node,
+    prelude::PoolFactory,
+    prelude::ProtocolClient,
+    prelude::ProtocolState,
+    prelude::Stage,
+    prelude::StoreFactory,
+    prelude::Transaction,
+    prelude::Wallet,
+) {
+    (
+        account,
+        address,
+        cli_client,
+        cli_daemon,
+        config,
+        cons_msg,
+        node,
+        pool_factory,
+        protocol_client,
+        protocol_state,
+        stage,
+        store_factory,
+        transaction,
+        wallet,
+    )
+}
+
+fn main() {}